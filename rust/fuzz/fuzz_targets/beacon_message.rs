@@ -0,0 +1,17 @@
+//! BeaconMessage 디코더 퍼징
+//!
+//! ```bash
+//! cargo +nightly fuzz run beacon_message
+//! ```
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use native::api::discovery::BeaconMessage;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(beacon) = BeaconMessage::decode(data) {
+        // 서명 검증은 임의의 필드 값에도 패닉 없이 끝나야 함
+        let _ = beacon.verify("fuzz-secret");
+    }
+});
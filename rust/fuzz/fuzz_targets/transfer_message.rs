@@ -0,0 +1,23 @@
+//! TransferMessage 프레임 디코더 퍼징
+//!
+//! ```bash
+//! cargo +nightly fuzz run transfer_message
+//! ```
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use native::api::transfer::TransferMessage;
+
+fuzz_target!(|data: &[u8]| {
+    // 연속된 프레임을 끝까지 디코딩 (패닉이나 과도한 할당이 없어야 함)
+    let mut rest = data;
+    while let Ok((msg, consumed)) = TransferMessage::decode_frame(rest) {
+        // 디코딩된 메시지는 다시 인코딩할 수 있어야 함
+        let encoded = msg.to_bytes().expect("decoded message must re-encode");
+        assert!(TransferMessage::decode_frame(&encoded).is_ok());
+        rest = &rest[consumed..];
+    }
+
+    let _ = TransferMessage::decode_payload(data);
+});
@@ -0,0 +1,297 @@
+//! 수신 전송 자동 수락 정책 (Accept Policy)
+//!
+//! 전송 요청을 받으면 `TransferAccept`를 보내기 전에 설정된 정책을 평가하여
+//! 자동 수락, 자동 거부, 사용자 승인 요청 중 하나를 결정합니다.
+//!
+//! # Process Flow
+//! 1. 실행 파일 형식이면 거부 (`reject_executables`)
+//! 2. 신뢰하는 기기면 수락 (`trusted_devices`)
+//! 3. 크기가 기준 이하면 수락 (`auto_accept_max_bytes`)
+//! 4. 그 외에는 `require_approval`이면 호스트(UI)에 승인을 요청하고, 아니면 수락
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+use super::error::PebbleError;
+use super::filename::{self, FALLBACK_FILE_NAME};
+use super::paths;
+use super::util::Subscribers;
+
+/// 사용자가 승인 요청에 응답할 때까지 기다리는 최대 시간
+pub const APPROVAL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 기본 실행 파일 확장자 목록
+const DEFAULT_BLOCKED_EXTENSIONS: &[&str] = &[
+    "exe", "msi", "bat", "cmd", "com", "scr", "ps1", "vbs", "jar", "apk", "app", "dmg", "pkg", "deb", "rpm", "sh",
+];
+
+/// 수신 전송 수락 정책
+///
+/// 설정 파일(`PebbleConfig.accept_policy`)에 저장됩니다.
+/// 기본값은 모든 요청을 수락하는 기존 동작과 같습니다.
+///
+/// # Security
+/// - `trusted_devices`는 송신측이 알리는 기기 ID와 비교합니다. 인증서가 고정된 기기의 ID는 전송 서버가
+///   TLS 클라이언트 인증서로 확인하지만(`revocation::check_peer`), 고정되지 않은 기기는 ID를 사칭할 수
+///   있습니다. 실행 파일 거부는 신뢰 기기에도 적용됩니다.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AcceptPolicy {
+    /// 항상 자동 수락할 기기 ID 목록
+    pub trusted_devices: Vec<String>,
+
+    /// 이 크기 이하의 파일은 자동 수락 (bytes, 0이면 사용하지 않음)
+    pub auto_accept_max_bytes: u64,
+
+    /// 실행 파일 형식 거부 여부
+    pub reject_executables: bool,
+
+    /// 실행 파일로 간주할 확장자 (소문자, 점 제외)
+    pub blocked_extensions: Vec<String>,
+
+    /// 자동 수락되지 않은 요청을 사용자 승인에 맡김 (false면 수락)
+    pub require_approval: bool,
+}
+
+impl Default for AcceptPolicy {
+    fn default() -> Self {
+        Self {
+            trusted_devices: Vec::new(),
+            auto_accept_max_bytes: 0,
+            reject_executables: false,
+            blocked_extensions: DEFAULT_BLOCKED_EXTENSIONS.iter().map(|ext| ext.to_string()).collect(),
+            require_approval: false,
+        }
+    }
+}
+
+/// 정책 평가 결과
+#[derive(Debug, Clone, PartialEq)]
+pub enum AcceptDecision {
+    /// 바로 수락
+    Accept,
+    /// 거부 (사유)
+    Reject(String),
+    /// 사용자 승인 필요
+    AskUser,
+}
+
+impl AcceptPolicy {
+    /// 전송 요청에 정책을 적용합니다.
+    ///
+    /// # Arguments
+    /// * `sender_device_id` - 송신 기기 ID (구버전은 None)
+    /// * `file_path` - 송신측이 보낸 파일 경로
+    /// * `file_size` - 파일 크기 (bytes)
+    pub fn evaluate(&self, sender_device_id: Option<&str>, file_path: &str, file_size: u64) -> AcceptDecision {
+        if self.reject_executables && self.is_blocked(file_path) {
+            return AcceptDecision::Reject(format!("Executable file type is not accepted: {}", file_path));
+        }
+
+        if sender_device_id.is_some_and(|id| self.trusted_devices.iter().any(|trusted| trusted == id)) {
+            return AcceptDecision::Accept;
+        }
+
+        if self.auto_accept_max_bytes > 0 && file_size <= self.auto_accept_max_bytes {
+            return AcceptDecision::Accept;
+        }
+
+        if self.require_approval {
+            AcceptDecision::AskUser
+        } else {
+            AcceptDecision::Accept
+        }
+    }
+
+    /// 파일 이름의 확장자가 차단 목록에 있는지 확인합니다.
+    ///
+    /// 끝의 `.`이나 제어 문자로 검사를 피하지 못하도록 수신측이 실제로 저장할 이름(`filename::sanitize`)으로 확인합니다.
+    fn is_blocked(&self, file_path: &str) -> bool {
+        let name = filename::sanitize(paths::remote_file_name(file_path).unwrap_or(FALLBACK_FILE_NAME));
+        let Some((_, extension)) = name.rsplit_once('.') else {
+            return false;
+        };
+
+        self.blocked_extensions
+            .iter()
+            .any(|blocked| blocked.trim_start_matches('.').eq_ignore_ascii_case(extension))
+    }
+}
+
+/// 호스트(UI)에게 보내는 수신 승인 요청
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalRequest {
+    /// 응답 시 사용할 요청 ID
+    pub request_id: String,
+
+    /// 수신 중인 전송 ID
+    pub transfer_id: String,
+
+    /// 송신 기기 ID (구버전은 None)
+    pub sender_device_id: Option<String>,
+
+    /// 송신 기기 주소
+    pub peer_address: String,
+
+    /// 파일 이름
+    pub file_name: String,
+
+    /// 파일 크기 (bytes)
+    pub file_size: u64,
+}
+
+impl ApprovalRequest {
+    pub fn new(
+        transfer_id: &str,
+        sender_device_id: Option<String>,
+        peer_address: String,
+        file_path: &str,
+        file_size: u64,
+    ) -> Self {
+        Self {
+            request_id: Uuid::new_v4().to_string(),
+            transfer_id: transfer_id.to_string(),
+            sender_device_id,
+            peer_address,
+            file_name: paths::remote_file_name(file_path).unwrap_or("received_file").to_string(),
+            file_size,
+        }
+    }
+}
+
+/// 수신 승인 요청/응답 중계기
+#[derive(Clone, Default)]
+pub struct ApprovalBroker {
+    subscribers: Arc<Subscribers<ApprovalRequest>>,
+    pending: Arc<Mutex<HashMap<String, oneshot::Sender<bool>>>>,
+}
+
+impl ApprovalBroker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 승인 요청 구독자를 등록합니다.
+    pub fn subscribe<F>(&self, subscriber: F)
+    where
+        F: Fn(&ApprovalRequest) -> bool + Send + 'static,
+    {
+        self.subscribers.subscribe(subscriber);
+    }
+
+    /// 사용자에게 승인을 요청하고 응답을 기다립니다.
+    ///
+    /// # Returns
+    /// * `Result<bool>` - 승인 여부. 구독자가 없거나 시간 내에 응답하지 않으면 거부로 간주
+    pub async fn request_approval(&self, request: ApprovalRequest) -> Result<bool> {
+        let (tx, rx) = oneshot::channel();
+        self.pending
+            .lock()
+            .map_err(|e| anyhow::anyhow!("Failed to acquire approval lock: {}", e))?
+            .insert(request.request_id.clone(), tx);
+
+        if !self.subscribers.notify(&request) {
+            self.take_pending(&request.request_id);
+            tracing::warn!("No approver is listening, declining {}", request.file_name);
+            return Ok(false);
+        }
+
+        tracing::info!("Waiting for approval of {} ({} bytes) from {}",
+            request.file_name, request.file_size, request.peer_address);
+
+        match tokio::time::timeout(APPROVAL_TIMEOUT, rx).await {
+            Ok(Ok(approved)) => Ok(approved),
+            Ok(Err(_)) => Ok(false),
+            Err(_) => {
+                self.take_pending(&request.request_id);
+                tracing::warn!("Approval for {} timed out after {}s", request.file_name, APPROVAL_TIMEOUT.as_secs());
+                Ok(false)
+            }
+        }
+    }
+
+    /// 승인 요청에 응답합니다.
+    ///
+    /// # Arguments
+    /// * `request_id` - 응답할 요청 ID
+    /// * `approved` - 수락 여부
+    pub fn respond(&self, request_id: &str, approved: bool) -> Result<()> {
+        let response = self
+            .take_pending(request_id)
+            .ok_or_else(|| PebbleError::not_found(format!("Approval request {}", request_id)))?;
+
+        let _ = response.send(approved);
+
+        Ok(())
+    }
+
+    fn take_pending(&self, request_id: &str) -> Option<oneshot::Sender<bool>> {
+        self.pending.lock().ok()?.remove(request_id)
+    }
+}
+
+/// 전역 승인 중계기
+static APPROVALS: once_cell::sync::Lazy<ApprovalBroker> = once_cell::sync::Lazy::new(ApprovalBroker::new);
+
+/// 전역 승인 중계기를 반환합니다.
+pub fn global() -> &'static ApprovalBroker {
+    &APPROVALS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_order() {
+        let policy = AcceptPolicy {
+            trusted_devices: vec!["laptop".to_string()],
+            auto_accept_max_bytes: 1024,
+            reject_executables: true,
+            require_approval: true,
+            ..Default::default()
+        };
+
+        assert!(matches!(policy.evaluate(Some("laptop"), "C:\\tools\\setup.EXE", 10), AcceptDecision::Reject(_)));
+        assert_eq!(policy.evaluate(Some("laptop"), "movie.mkv", u64::MAX), AcceptDecision::Accept);
+        assert_eq!(policy.evaluate(None, "note.txt", 1024), AcceptDecision::Accept);
+        assert_eq!(policy.evaluate(Some("phone"), "movie.mkv", 1025), AcceptDecision::AskUser);
+        assert_eq!(policy.evaluate(None, "archive.tar.gz", 1025), AcceptDecision::AskUser);
+
+        assert_eq!(AcceptPolicy::default().evaluate(None, "setup.exe", u64::MAX), AcceptDecision::Accept);
+    }
+
+    #[test]
+    fn test_blocked_extension_checked_on_saved_name() {
+        let policy = AcceptPolicy { reject_executables: true, ..Default::default() };
+
+        // 모두 `setup.exe`로 저장되는 이름
+        for name in ["setup.exe.", "setup.exe ", "setup.exe. . ", "setup.ex\u{7}e", "dir/setup.e\u{0}xe"] {
+            assert!(matches!(policy.evaluate(None, name, 10), AcceptDecision::Reject(_)), "{:?} was accepted", name);
+        }
+        assert_eq!(policy.evaluate(None, "setup.exe.txt", 10), AcceptDecision::Accept);
+    }
+
+    #[tokio::test]
+    async fn test_approval_answered_or_declined() {
+        let broker = ApprovalBroker::new();
+        let request = || ApprovalRequest::new("t1", None, "10.0.0.2:1".to_string(), "/home/a/photo.jpg", 5);
+
+        // 구독자가 없으면 거부
+        assert!(!broker.request_approval(request()).await.unwrap());
+
+        let host = broker.clone();
+        broker.subscribe(move |request| {
+            assert_eq!(request.file_name, "photo.jpg");
+            host.respond(&request.request_id, true).unwrap();
+            true
+        });
+        assert!(broker.request_approval(request()).await.unwrap());
+        assert!(broker.respond("unknown", true).is_err());
+    }
+}
@@ -0,0 +1,401 @@
+//! 연결 감사 기록 (Connection Audit Log)
+//!
+//! 전송 서버(기본 서버와 추가 서버)가 받은 연결을 `connection_log` 테이블에 한 줄씩 남겨,
+//! 어떤 기기가 언제 이 기기에 연결했는지 나중에 확인할 수 있게 합니다.
+//! TLS 핸드셰이크에 실패한 연결도 기록하므로 알 수 없는 접속 시도를 찾는 데 사용할 수 있습니다.
+//!
+//! # Process Flow
+//! 1. 연결을 수락하면 `ConnectionAudit`을 만들고 TCP 스트림을 `wrap`으로 감싸 송수신 바이트 집계
+//! 2. 요청 메시지마다 `observe`로 요청 수와 상대 기기 ID(`sender_device_id`) 기록
+//! 3. 연결이 끝나면 `finish`로 결과(정상 종료, 실패, 핸드셰이크 실패)와 함께 DB에 저장
+//!    (기기 ID를 보내지 않은 연결은 그 IP에서 마지막으로 본 기기 ID 사용)
+//! 4. `query`로 기기, 주소, 결과, 기간별 조회 / 전송 기록과 같은 보존 정책으로 정리
+
+use anyhow::Result;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use super::db;
+use super::listeners::ListenerConfig;
+use super::peers;
+use super::transfer::TransferMessage;
+use super::util::unix_now;
+
+/// 연결 하나의 결과
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConnectionOutcome {
+    /// 상대가 연결을 닫았거나 유휴 시간이 지나 정상적으로 닫힘
+    Closed,
+
+    /// 요청 처리 중 에러로 연결을 닫음 (거절, 프로토콜 위반, 끊김 등)
+    Failed,
+
+    /// TLS 핸드셰이크 실패 (요청을 하나도 받지 못함)
+    HandshakeFailed,
+}
+
+impl ConnectionOutcome {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Closed => "Closed",
+            Self::Failed => "Failed",
+            Self::HandshakeFailed => "HandshakeFailed",
+        }
+    }
+
+    fn from_name(value: &str) -> Self {
+        match value {
+            "Closed" => Self::Closed,
+            "HandshakeFailed" => Self::HandshakeFailed,
+            _ => Self::Failed,
+        }
+    }
+}
+
+/// 기록된 연결 하나
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConnectionRecord {
+    pub id: i64,
+
+    /// 상대 주소 (IP:Port)
+    pub peer_address: String,
+
+    /// 상대 기기 ID (요청에 담겨 있었거나 그 IP에서 본 기기, 알 수 없으면 None)
+    pub device_id: Option<String>,
+
+    /// 연결을 받은 추가 전송 서버 이름 (기본 서버면 None)
+    pub listener: Option<String>,
+
+    pub outcome: ConnectionOutcome,
+
+    /// 실패 원인
+    pub error: Option<String>,
+
+    /// 처리한 요청 수 (연결 확인용 Ping 제외)
+    pub requests: u32,
+
+    /// 받은 바이트 수 (TLS 포함, 네트워크 기준)
+    pub bytes_received: u64,
+
+    /// 보낸 바이트 수 (TLS 포함, 네트워크 기준)
+    pub bytes_sent: u64,
+
+    /// 연결 시각 (Unix timestamp)
+    pub opened_at: i64,
+
+    /// 종료 시각 (Unix timestamp)
+    pub closed_at: i64,
+}
+
+/// 연결 기록 조회 조건 (None인 조건은 적용하지 않음)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditQuery {
+    pub device_id: Option<String>,
+
+    /// 상대 IP 주소 (포트 제외)
+    pub peer_ip: Option<String>,
+
+    pub outcome: Option<ConnectionOutcome>,
+
+    /// 이 시각 이후에 연결된 기록만 (Unix timestamp)
+    pub since: Option<i64>,
+
+    /// 이 시각 이전에 연결된 기록만 (Unix timestamp)
+    pub until: Option<i64>,
+
+    /// 건너뛸 행 수
+    pub offset: u32,
+
+    /// 최대 행 수
+    pub limit: u32,
+}
+
+/// 연결 기록 한 페이지
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditPage {
+    pub connections: Vec<ConnectionRecord>,
+
+    /// 조건에 맞는 전체 행 수
+    pub total_count: u64,
+}
+
+/// 처리 중인 연결 하나의 집계
+pub struct ConnectionAudit {
+    peer_addr: SocketAddr,
+    listener: Option<String>,
+    opened_at: i64,
+    device_id: Mutex<Option<String>>,
+    requests: AtomicU32,
+    received: Arc<AtomicU64>,
+    sent: Arc<AtomicU64>,
+}
+
+impl ConnectionAudit {
+    /// 수락한 연결의 집계를 시작합니다.
+    ///
+    /// # Arguments
+    /// * `listener` - 연결을 받은 서버의 설정 (기본 서버는 이름이 비어 있음)
+    pub fn new(peer_addr: SocketAddr, listener: &ListenerConfig) -> Self {
+        Self {
+            peer_addr,
+            listener: (!listener.name.is_empty()).then(|| listener.name.clone()),
+            opened_at: unix_now(),
+            device_id: Mutex::new(None),
+            requests: AtomicU32::new(0),
+            received: Arc::new(AtomicU64::new(0)),
+            sent: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// 스트림을 감싸 읽고 쓴 바이트를 이 연결에 집계합니다.
+    pub fn wrap<S>(&self, inner: S) -> CountingStream<S> {
+        CountingStream {
+            inner,
+            received: Arc::clone(&self.received),
+            sent: Arc::clone(&self.sent),
+        }
+    }
+
+    /// 요청 메시지 하나를 집계하고, 상대 기기 ID가 담겨 있으면 기억합니다.
+    pub fn observe(&self, msg: &TransferMessage) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+
+        if let Some(device_id) = msg.sender_device_id() {
+            *self.device_id.lock().unwrap() = Some(device_id.to_string());
+        }
+    }
+
+    /// 지금까지의 집계로 연결 기록을 만듭니다.
+    fn to_record(&self, outcome: ConnectionOutcome, error: Option<String>) -> ConnectionRecord {
+        let device_id = self.device_id.lock().unwrap().clone().or_else(|| {
+            let ip = self.peer_addr.ip().to_string();
+            peers::device_id_at(&ip).unwrap_or_else(|e| {
+                tracing::warn!("Failed to look up device at {}: {:#}", ip, e);
+                None
+            })
+        });
+
+        ConnectionRecord {
+            id: 0,
+            peer_address: self.peer_addr.to_string(),
+            device_id,
+            listener: self.listener.clone(),
+            outcome,
+            error,
+            requests: self.requests.load(Ordering::Relaxed),
+            bytes_received: self.received.load(Ordering::Relaxed),
+            bytes_sent: self.sent.load(Ordering::Relaxed),
+            opened_at: self.opened_at,
+            closed_at: unix_now(),
+        }
+    }
+
+    /// 연결이 끝난 결과를 기록합니다. 기록에 실패해도 연결 처리에는 영향을 주지 않습니다.
+    ///
+    /// # Arguments
+    /// * `outcome` - 연결 결과
+    /// * `error` - 실패 원인 (`Closed`면 None)
+    pub fn finish(&self, outcome: ConnectionOutcome, error: Option<&anyhow::Error>) {
+        let record = self.to_record(outcome, error.map(|e| format!("{:#}", e)));
+        if let Err(e) = insert(&record) {
+            tracing::warn!("Failed to record connection from {}: {:#}", record.peer_address, e);
+        }
+    }
+}
+
+/// 읽고 쓴 바이트 수를 세는 스트림
+pub struct CountingStream<S> {
+    inner: S,
+    received: Arc<AtomicU64>,
+    sent: Arc<AtomicU64>,
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for CountingStream<S> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let before = buf.filled().len();
+        let result = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = result {
+            self.received.fetch_add((buf.filled().len() - before) as u64, Ordering::Relaxed);
+        }
+        result
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for CountingStream<S> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let result = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(written)) = result {
+            self.sent.fetch_add(written as u64, Ordering::Relaxed);
+        }
+        result
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+/// 연결 기록 하나를 저장합니다.
+fn insert(record: &ConnectionRecord) -> Result<()> {
+    let conn = db::open_connection()?;
+    conn.execute(
+        "INSERT INTO connection_log
+         (peer_address, peer_ip, device_id, listener, outcome, error, requests, bytes_received, bytes_sent, opened_at, closed_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            record.peer_address,
+            record.peer_address.parse::<SocketAddr>().map(|addr| addr.ip().to_string()).unwrap_or_default(),
+            record.device_id,
+            record.listener,
+            record.outcome.name(),
+            record.error,
+            record.requests,
+            record.bytes_received as i64,
+            record.bytes_sent as i64,
+            record.opened_at,
+            record.closed_at,
+        ],
+    )?;
+    Ok(())
+}
+
+/// 조건에 맞는 연결 기록을 최신순으로 가져옵니다.
+///
+/// # Security Notes
+/// - 필터 값은 모두 파라미터로 바인딩하여 SQL Injection 방지
+pub fn query(query: &AuditQuery) -> Result<AuditPage> {
+    let conn = db::open_connection()?;
+
+    let mut conditions = Vec::new();
+    let mut values: Vec<rusqlite::types::Value> = Vec::new();
+
+    if let Some(ref device_id) = query.device_id {
+        values.push(device_id.clone().into());
+        conditions.push(format!("device_id = ?{}", values.len()));
+    }
+    if let Some(ref peer_ip) = query.peer_ip {
+        values.push(peer_ip.clone().into());
+        conditions.push(format!("peer_ip = ?{}", values.len()));
+    }
+    if let Some(outcome) = query.outcome {
+        values.push(outcome.name().to_string().into());
+        conditions.push(format!("outcome = ?{}", values.len()));
+    }
+    if let Some(since) = query.since {
+        values.push(since.into());
+        conditions.push(format!("opened_at >= ?{}", values.len()));
+    }
+    if let Some(until) = query.until {
+        values.push(until.into());
+        conditions.push(format!("opened_at <= ?{}", values.len()));
+    }
+
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+
+    let total_count: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM connection_log {}", where_clause),
+        rusqlite::params_from_iter(values.iter()),
+        |row| row.get(0),
+    )?;
+
+    let sql = format!(
+        "SELECT id, peer_address, device_id, listener, outcome, error, requests, bytes_received, bytes_sent, opened_at, closed_at
+         FROM connection_log {} ORDER BY opened_at DESC, id DESC LIMIT {} OFFSET {}",
+        where_clause, query.limit, query.offset,
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let connections = stmt
+        .query_map(rusqlite::params_from_iter(values.iter()), |row| {
+            Ok(ConnectionRecord {
+                id: row.get(0)?,
+                peer_address: row.get(1)?,
+                device_id: row.get(2)?,
+                listener: row.get(3)?,
+                outcome: ConnectionOutcome::from_name(&row.get::<_, String>(4)?),
+                error: row.get(5)?,
+                requests: row.get(6)?,
+                bytes_received: row.get::<_, i64>(7)? as u64,
+                bytes_sent: row.get::<_, i64>(8)? as u64,
+                opened_at: row.get(9)?,
+                closed_at: row.get(10)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(AuditPage {
+        connections,
+        total_count: total_count as u64,
+    })
+}
+
+/// 연결 기록을 모두 삭제합니다.
+pub fn clear() -> Result<()> {
+    let conn = db::open_connection()?;
+    conn.execute("DELETE FROM connection_log", [])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_audit_counts_and_query() {
+        crate::loopback::use_temp_environment();
+
+        // 감싼 스트림으로 오간 바이트 집계
+        let peer: SocketAddr = "198.51.100.7:40000".parse().unwrap();
+        let audit = ConnectionAudit::new(peer, &ListenerConfig { name: "vpn".to_string(), ..Default::default() });
+        let (client, server) = tokio::io::duplex(64);
+        let mut server = audit.wrap(server);
+        let mut client = client;
+        client.write_all(b"hello").await.unwrap();
+        let mut buf = [0u8; 5];
+        server.read_exact(&mut buf).await.unwrap();
+        server.write_all(b"hi").await.unwrap();
+
+        audit.observe(&TransferMessage::SendText {
+            message_id: "m".to_string(),
+            sender_device_id: Some("audit-device".to_string()),
+            text: "hello".to_string(),
+        });
+        audit.finish(ConnectionOutcome::Closed, None);
+        ConnectionAudit::new(peer, &ListenerConfig::default())
+            .finish(ConnectionOutcome::HandshakeFailed, Some(&anyhow::anyhow!("bad certificate")));
+
+        let page = query(&AuditQuery { peer_ip: Some("198.51.100.7".to_string()), limit: 10, ..Default::default() }).unwrap();
+        assert_eq!(page.total_count, 2);
+
+        let closed = page.connections.iter().find(|c| c.outcome == ConnectionOutcome::Closed).unwrap();
+        assert_eq!(closed.device_id.as_deref(), Some("audit-device"));
+        assert_eq!(closed.listener.as_deref(), Some("vpn"));
+        assert_eq!((closed.requests, closed.bytes_received, closed.bytes_sent), (1, 5, 2));
+
+        let failed = query(&AuditQuery {
+            peer_ip: Some("198.51.100.7".to_string()),
+            outcome: Some(ConnectionOutcome::HandshakeFailed),
+            limit: 10,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(failed.connections.len(), 1);
+        assert_eq!(failed.connections[0].error.as_deref(), Some("bad certificate"));
+        assert_eq!(failed.connections[0].listener, None);
+    }
+}
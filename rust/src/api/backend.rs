@@ -0,0 +1,432 @@
+//! 수신 파일 저장소 (Storage Backend)
+//!
+//! 전송 프로토콜(`transfer`)은 받은 데이터를 `ReceiveSink`에 쓰기만 하고, 어디에 어떻게
+//! 저장할지는 `StorageBackend`가 정합니다. 기본은 로컬 파일 시스템(`LocalBackend`)이며,
+//! 호스트 저장소(Android SAF)가 켜져 있으면 `HostBackend`를, 설정에 대기 폴더가 있으면
+//! 승인 전까지 암호화해 두는 `staging::StagingBackend`를 사용합니다.
+//! 암호화된 임시 영역이나 다른 프로세스로의 파이프처럼 다른 저장 위치가 필요하면
+//! `set_backend`로 구현체를 등록합니다 (프로토콜 코드는 수정하지 않음).
+//!
+//! # Process Flow
+//! 1. 전송 요청을 받으면 `open_destination`으로 저장 위치를 요청 (`DestinationRequest`)
+//! 2. 등록된 백엔드 → 호스트 저장소 → 암호화된 대기 영역 → 로컬 파일 시스템 순으로 백엔드 선택
+//! 3. 백엔드가 `Destination`(쓰기 대상, 표시용 경로)을 돌려줌 (None이면 건너뜀)
+//! 4. 프로토콜이 청크를 쓰고 파일 해시까지 검증되면 `ReceiveSink::finish` 호출
+
+use anyhow::{Context, Result};
+use futures::future::BoxFuture;
+use rusqlite::{params, OptionalExtension};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use super::config;
+use super::db;
+use super::error::PebbleError;
+use super::events::{self, PebbleEvent};
+use super::filename::{self, CaseCollisionPolicy, DestinationClaim, FALLBACK_FILE_NAME};
+use super::locked;
+use super::paths;
+use super::shares;
+use super::staging::StagingBackend;
+use super::storage;
+
+/// 받은 데이터를 쓰는 대상
+///
+/// 이어받기 때 이미 받은 앞부분을 해시하려면 읽을 수 있어야 하지만, 읽을 수 없는 대상
+/// (쓰기 전용 스트림 등)은 `read`에서 오류를 반환하면 됩니다 (전체 해시 검증만 생략됨).
+pub trait ReceiveSink: Read + Write + Seek + Send {
+    /// 크기를 바꿉니다 (늘리면 빈 부분은 0으로 채워짐).
+    fn set_len(&mut self, len: u64) -> io::Result<()>;
+
+    /// 현재 크기 (bytes)
+    fn size(&self) -> io::Result<u64>;
+
+    /// 받은 내용이 검증된 뒤 호출됩니다 (실패하거나 중단된 수신에는 호출되지 않음).
+    ///
+    /// # Arguments
+    /// * `file_hash` - 받은 파일 전체의 blake3 해시 (전송 수신은 항상 검증한 해시를 넘김)
+    fn finish(&mut self, _file_hash: Option<&str>) -> io::Result<()> {
+        self.flush()
+    }
+}
+
+impl ReceiveSink for File {
+    fn set_len(&mut self, len: u64) -> io::Result<()> {
+        File::set_len(self, len)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.metadata()?.len())
+    }
+}
+
+/// 수신 파일의 저장 위치
+pub struct Destination {
+    pub sink: Box<dyn ReceiveSink>,
+
+    /// 표시용 경로 (호스트 저장소면 문서 URI)
+    pub path: String,
+
+    /// 검사를 마칠 때까지 대신 받는 같은 폴더의 임시 파일 (호스트 저장소면 None)
+    pub staged: Option<PathBuf>,
+
+    /// 수신이 끝날 때까지 유지하는 경로 점유 (로컬 파일 시스템이 아니면 None)
+    pub claim: Option<DestinationClaim>,
+}
+
+impl Destination {
+    /// 임시 파일이나 경로 점유가 없는 저장 위치를 만듭니다.
+    pub fn new(sink: impl ReceiveSink + 'static, path: impl Into<String>) -> Self {
+        Self { sink: Box::new(sink), path: path.into(), staged: None, claim: None }
+    }
+}
+
+/// 저장 위치 요청
+#[derive(Debug, Clone)]
+pub struct DestinationRequest<'a> {
+    pub transfer_id: &'a str,
+
+    /// 송신측이 보낸 파일 경로 (송신측 OS의 구분자 사용)
+    pub file_path: &'a str,
+
+    pub file_size: u64,
+
+    /// 이어받기 여부 (기존 내용을 유지해야 함)
+    pub resuming: bool,
+
+    /// 받을 폴더 (추가 전송 서버의 폴더 또는 전역 설정)
+    pub download_dir: Option<String>,
+
+    pub sender_device_id: Option<&'a str>,
+}
+
+impl DestinationRequest<'_> {
+    /// 송신측 경로에서 정리한 파일 이름
+    pub fn file_name(&self) -> String {
+        filename::sanitize(paths::remote_file_name(self.file_path).unwrap_or(FALLBACK_FILE_NAME))
+    }
+
+    /// 받을 폴더 아래에 정리한 파일 이름을 붙인 저장 경로
+    ///
+    /// 받을 폴더가 없으면 설정의 수신 폴더(`PebbleConfig::receive_dir`)를 사용하므로,
+    /// 송신측이 보낸 경로의 폴더 부분은 저장 위치에 쓰이지 않습니다.
+    pub fn requested_path(&self) -> PathBuf {
+        let dir = self.download_dir.clone().unwrap_or_else(|| config::current().receive_dir());
+        Path::new(&dir).join(self.file_name())
+    }
+}
+
+/// 수신 파일 저장소
+pub trait StorageBackend: Send + Sync {
+    /// 로그에 남길 이름
+    fn name(&self) -> &str;
+
+    /// 수신 파일을 저장할 위치를 엽니다.
+    ///
+    /// # Returns
+    /// * 받지 않고 건너뛰면 None (송신측에는 대소문자 충돌로 거부됨)
+    ///
+    /// # Security
+    /// - 정책상 받을 수 없으면 `PebbleError::Rejected`를 반환 (송신측에 정책 거부로 알림)
+    fn open<'a>(&'a self, request: DestinationRequest<'a>) -> BoxFuture<'a, Result<Option<Destination>>>;
+}
+
+/// 로컬 파일 시스템 저장소 (기본)
+///
+/// 저장 위치가 공유 폴더 안이면 보낸 기기에 쓰기 권한이 있어야 합니다.
+/// 항상 같은 폴더의 임시 파일(`locked::staging_path`)에 받고, 검증과 검사를 마친 뒤에 저장 위치로
+/// 옮깁니다 (저장 위치가 다른 앱에 잠겨 있으면 풀린 뒤 옮김).
+#[derive(Debug, Default)]
+pub struct LocalBackend;
+
+impl LocalBackend {
+    /// 수신 파일의 저장 경로를 결정하고 수신이 끝날 때까지 점유합니다.
+    ///
+    /// 송신측 경로의 파일 이름만 정리하여 받을 폴더 아래에 저장하고(`requested_path`),
+    /// 같은 이름의 파일이 이미 있으면 번호를 붙여 덮어쓰지 않습니다.
+    /// 다른 수신이 같은 경로에 쓰는 중이면 번호를 붙인 경로로 받습니다.
+    /// 이어받기 중인 전송은 처음 정한 경로를 그대로 사용합니다.
+    fn resolve(request: &DestinationRequest<'_>) -> Result<Option<(PathBuf, DestinationClaim)>> {
+        if request.resuming {
+            let path = match get_resume_path(request.transfer_id)? {
+                Some(path) => PathBuf::from(path),
+                None => request.requested_path(),
+            };
+            let claim = filename::claim(&path)
+                .ok_or_else(|| PebbleError::io(format!("{} is already being received", path.display())))?;
+            return Ok(Some((path, claim)));
+        }
+
+        let requested = request.requested_path();
+
+        // 대소문자만 다른 파일은 같은 파일로 열리므로 덮어쓰지 않고 정책대로 처리
+        let case_variant = if filename::CASE_INSENSITIVE_FS { filename::find_case_variant(&requested) } else { None };
+        if let Some(existing) = &case_variant {
+            match config::current().case_collision_policy {
+                CaseCollisionPolicy::Rename => {
+                    tracing::info!("{} differs only in letter case from {}, renaming", requested.display(), existing.display());
+                }
+                CaseCollisionPolicy::Skip => {
+                    tracing::info!("{} differs only in letter case from {}, skipping", requested.display(), existing.display());
+                    return Ok(None);
+                }
+                CaseCollisionPolicy::Error => {
+                    return Err(PebbleError::io(format!(
+                        "{} differs only in letter case from existing {}", requested.display(), existing.display()
+                    )).into());
+                }
+            }
+        }
+
+        let dir = requested.parent().unwrap_or(Path::new(""));
+        let name = requested.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        let (path, claim) = filename::claim_unique(dir, &name);
+        if path != requested {
+            events::emit(PebbleEvent::ConflictDetected {
+                path: requested.to_string_lossy().to_string(),
+                saved_as: path.to_string_lossy().to_string(),
+            });
+        }
+        Ok(Some((path, claim)))
+    }
+
+    /// 이어받기를 위해 기존 내용은 유지한 채 저장 위치를 엽니다.
+    pub(crate) fn open_local(request: &DestinationRequest<'_>) -> Result<Option<Destination>> {
+        let Some((dest_path, claim)) = Self::resolve(request)? else {
+            return Ok(None);
+        };
+        shares::check_write(&dest_path.to_string_lossy(), request.sender_device_id)?;
+
+        if let Some(parent) = dest_path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(paths::long_path(parent))
+                    .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+            }
+        }
+
+        // 검사(`scan_hook`)를 통과하기 전에는 저장 위치에 나타나지 않도록 임시 파일에 받음
+        let staged = locked::staging_path(&dest_path);
+        if request.resuming && !paths::long_path(&staged).is_file() && paths::long_path(&dest_path).is_file() {
+            // 저장 위치에 바로 받던 이전 버전의 미완성 파일은 임시 파일로 옮겨 이어받음
+            std::fs::rename(paths::long_path(&dest_path), paths::long_path(&staged))
+                .with_context(|| format!("Failed to move partial file: {}", dest_path.display()))?;
+        }
+        let file = Self::open_for_write(&staged)?;
+        Ok(Some(Destination {
+            sink: Box::new(file),
+            path: dest_path.to_string_lossy().to_string(),
+            staged: Some(staged),
+            claim: Some(claim),
+        }))
+    }
+
+    /// 기존 내용을 유지한 채 쓰기용으로 파일을 엽니다 (없으면 만듦).
+    ///
+    /// 이어받을 때 이미 받은 앞부분을 해시하도록 읽기도 허용합니다.
+    fn open_for_write(path: &Path) -> Result<File> {
+        OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(paths::long_path(path))
+            .with_context(|| format!("Failed to open file: {}", path.display()))
+    }
+
+}
+
+impl StorageBackend for LocalBackend {
+    fn name(&self) -> &str {
+        "local"
+    }
+
+    fn open<'a>(&'a self, request: DestinationRequest<'a>) -> BoxFuture<'a, Result<Option<Destination>>> {
+        Box::pin(async move { Self::open_local(&request) })
+    }
+}
+
+/// 이어받기 중인 전송이 처음 저장한 경로를 가져옵니다 (호스트 저장소면 문서 URI).
+fn get_resume_path(transfer_id: &str) -> Result<Option<String>> {
+    let conn = db::open_connection()?;
+
+    let path: Option<String> = conn
+        .query_row(
+            "SELECT file_path FROM transfer_state WHERE transfer_id = ?1",
+            params![transfer_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    Ok(path.filter(|path| !path.is_empty()))
+}
+
+/// 호스트 저장소 (Android SAF)
+///
+/// 호스트에게 문서를 요청하고 받은 fd에 씁니다. 경로 점유와 잠금 처리는 호스트가 맡습니다.
+/// 이어받기이면 처음 받던 문서를 다시 열도록 요청합니다.
+#[derive(Debug, Default)]
+pub struct HostBackend;
+
+impl StorageBackend for HostBackend {
+    fn name(&self) -> &str {
+        "host"
+    }
+
+    fn open<'a>(&'a self, request: DestinationRequest<'a>) -> BoxFuture<'a, Result<Option<Destination>>> {
+        Box::pin(async move {
+            let resume_uri = if request.resuming { get_resume_path(request.transfer_id)? } else { None };
+            let host_file = storage::global()
+                .request_file(request.transfer_id, &request.file_name(), request.file_size, resume_uri.as_deref())
+                .await?;
+            Ok(Some(Destination::new(host_file.file, host_file.uri)))
+        })
+    }
+}
+
+/// 등록된 저장소 (None이면 기본 선택)
+static BACKEND: RwLock<Option<Arc<dyn StorageBackend>>> = RwLock::new(None);
+
+/// 수신 파일 저장소를 등록합니다 (호스트 저장소와 로컬 파일 시스템보다 우선).
+pub fn set_backend(backend: Arc<dyn StorageBackend>) {
+    tracing::info!("Receiving files into {} storage", backend.name());
+    if let Ok(mut current) = BACKEND.write() {
+        *current = Some(backend);
+    }
+}
+
+/// 등록된 저장소를 해제하고 기본 선택으로 돌아갑니다.
+pub fn clear_backend() {
+    if let Ok(mut current) = BACKEND.write() {
+        *current = None;
+    }
+}
+
+/// 지금 사용할 저장소 (등록된 저장소 → 호스트 저장소 → 암호화된 대기 영역 → 로컬 파일 시스템)
+pub fn current() -> Arc<dyn StorageBackend> {
+    if let Some(backend) = BACKEND.read().ok().and_then(|current| current.clone()) {
+        return backend;
+    }
+    if storage::global().is_enabled() {
+        return Arc::new(HostBackend);
+    }
+    match StagingBackend::from_config() {
+        Some(staging) => Arc::new(staging),
+        None => Arc::new(LocalBackend),
+    }
+}
+
+/// 지금 사용할 저장소에서 수신 파일을 저장할 위치를 엽니다.
+pub async fn open_destination(request: DestinationRequest<'_>) -> Result<Option<Destination>> {
+    current().open(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::sync::Mutex;
+
+    /// 받은 내용을 메모리에 모으는 쓰기 대상
+    struct MemorySink {
+        buffer: Cursor<Vec<u8>>,
+        finished: Arc<Mutex<Option<Vec<u8>>>>,
+    }
+
+    impl Read for MemorySink {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.buffer.read(buf)
+        }
+    }
+
+    impl Write for MemorySink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.buffer.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Seek for MemorySink {
+        fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+            self.buffer.seek(pos)
+        }
+    }
+
+    impl ReceiveSink for MemorySink {
+        fn set_len(&mut self, len: u64) -> io::Result<()> {
+            self.buffer.get_mut().resize(len as usize, 0);
+            Ok(())
+        }
+
+        fn size(&self) -> io::Result<u64> {
+            Ok(self.buffer.get_ref().len() as u64)
+        }
+
+        fn finish(&mut self, _file_hash: Option<&str>) -> io::Result<()> {
+            *self.finished.lock().unwrap() = Some(self.buffer.get_ref().clone());
+            Ok(())
+        }
+    }
+
+    struct MemoryBackend {
+        finished: Arc<Mutex<Option<Vec<u8>>>>,
+    }
+
+    impl StorageBackend for MemoryBackend {
+        fn name(&self) -> &str {
+            "memory"
+        }
+
+        fn open<'a>(&'a self, request: DestinationRequest<'a>) -> BoxFuture<'a, Result<Option<Destination>>> {
+            let sink = MemorySink { buffer: Cursor::new(Vec::new()), finished: self.finished.clone() };
+            let path = format!("memory://{}", request.file_name());
+            Box::pin(async move { Ok(Some(Destination::new(sink, path))) })
+        }
+    }
+
+    fn request<'a>(transfer_id: &'a str, file_path: &'a str, download_dir: Option<String>) -> DestinationRequest<'a> {
+        DestinationRequest { transfer_id, file_path, file_size: 5, resuming: false, download_dir, sender_device_id: None }
+    }
+
+    #[tokio::test]
+    async fn test_local_and_custom_backends() {
+        crate::loopback::use_temp_environment();
+        let dir = tempfile::TempDir::new().unwrap();
+        let download_dir = Some(dir.path().to_string_lossy().to_string());
+
+        // 로컬 파일 시스템은 송신측 경로의 파일 이름만 사용하고 수신 중에는 점유
+        let local = LocalBackend.open(request("t-local", "C:\\docs\\a.txt", download_dir.clone())).await.unwrap().unwrap();
+        assert_eq!(PathBuf::from(&local.path), dir.path().join("a.txt"));
+        assert!(local.claim.is_some());
+        // 검사를 마치고 옮기기 전에는 같은 폴더의 임시 파일에만 기록
+        assert_eq!(local.staged.as_deref(), Some(locked::staging_path(&dir.path().join("a.txt")).as_path()));
+        assert!(!dir.path().join("a.txt").exists());
+        drop(local);
+
+        // 이어받기를 기다리는 임시 파일이 있는 이름은 새 수신에 주지 않음
+        let next = LocalBackend.open(request("t-local-2", "/docs/a.txt", download_dir.clone())).await.unwrap().unwrap();
+        assert_eq!(PathBuf::from(&next.path), dir.path().join("a (2).txt"));
+        drop(next);
+
+        // 받을 폴더가 없어도 송신측 경로가 아니라 설정의 수신 폴더 아래에 저장
+        let fallback = LocalBackend.open(request("t-fallback", "/etc/../etc/evil.txt", None)).await.unwrap().unwrap();
+        assert_eq!(PathBuf::from(&fallback.path), Path::new(&config::current().receive_dir()).join("evil.txt"));
+        drop(fallback);
+
+        // 전역 등록은 동시에 도는 다른 전송 테스트에 영향을 주므로 직접 호출
+        let finished = Arc::new(Mutex::new(None));
+        let backend: Arc<dyn StorageBackend> = Arc::new(MemoryBackend { finished: finished.clone() });
+        let mut destination = backend.open(request("t-memory", "/docs/b.txt", download_dir)).await.unwrap().unwrap();
+        assert_eq!(destination.path, "memory://b.txt");
+        destination.sink.write_all(b"hello").unwrap();
+        destination.sink.set_len(3).unwrap();
+        assert_eq!(destination.sink.size().unwrap(), 3);
+        destination.sink.finish(None).unwrap();
+        assert_eq!(finished.lock().unwrap().as_deref(), Some(&b"hel"[..]));
+        assert!(!dir.path().join("b.txt").exists());
+    }
+}
@@ -0,0 +1,353 @@
+//! 대역폭 사용량 집계 (Bandwidth Accounting)
+//!
+//! 상대 기기별로 하루 동안 보내고 받은 바이트 수를 `bandwidth_usage` 테이블에 기록합니다.
+//! 데이터 요금이 있는 회선에서 Pebble이 얼마나 썼는지 보여주는 데 사용하며,
+//! 누적 사용량(`quota`)과 달리 날짜별로 남아 기간을 지정해 조회할 수 있습니다.
+//!
+//! 설정의 데이터 사용 한도(`PebbleConfig::data_caps`)도 같은 기록으로 판단합니다.
+//!
+//! # Process Flow
+//! 1. 송수신이 끝나면(중단 포함) 실제로 오간 바이트 수를 `record`로 오늘 날짜(로컬 시간)에 더함
+//! 2. `usage`가 기간(`UsageRange`)의 합계와 날짜별, 기기별 사용량을 집계
+//! 3. 송신 전에 `check_caps`로 상대 기기에 적용되는 한도를 확인
+//!    → 자동 동기화는 멈추고, 직접 보내는 전송은 사용자가 한도 무시를 선택해야 전송
+
+use anyhow::Result;
+use chrono::{Datelike, Duration as ChronoDuration, NaiveDate};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+
+use super::config;
+use super::db;
+use super::error::PebbleError;
+
+/// 날짜 형식 (DB 키와 `UsageRange::Between`)
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// 조회 기간
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UsageRange {
+    /// 오늘
+    Today,
+    /// 오늘을 포함한 최근 `days`일
+    LastDays { days: u32 },
+    /// 이번 달 1일부터 오늘까지
+    ThisMonth,
+    /// 지정한 기간 (YYYY-MM-DD, 양 끝 포함)
+    Between { start: String, end: String },
+}
+
+impl UsageRange {
+    /// 기간의 첫날과 마지막 날을 계산합니다.
+    ///
+    /// # Arguments
+    /// * `today` - 기준 날짜 (로컬 시간)
+    pub fn resolve(&self, today: NaiveDate) -> Result<(NaiveDate, NaiveDate)> {
+        let (start, end) = match self {
+            Self::Today => (today, today),
+            Self::LastDays { days: 0 } => {
+                return Err(PebbleError::invalid_argument("Usage range must cover at least one day").into());
+            }
+            Self::LastDays { days } => (today - ChronoDuration::days(i64::from(*days) - 1), today),
+            Self::ThisMonth => (today.with_day(1).unwrap_or(today), today),
+            Self::Between { start, end } => (parse_date(start)?, parse_date(end)?),
+        };
+        if start > end {
+            return Err(PebbleError::invalid_argument(format!("Usage range starts after it ends: {} > {}", start, end)).into());
+        }
+        Ok((start, end))
+    }
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(value, DATE_FORMAT)
+        .map_err(|_| PebbleError::invalid_argument(format!("Invalid date (expected YYYY-MM-DD): {}", value)).into())
+}
+
+/// 오늘 날짜 (로컬 시간)
+pub fn today() -> NaiveDate {
+    chrono::Local::now().date_naive()
+}
+
+/// 하루 사용량
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DailyUsage {
+    /// 날짜 (YYYY-MM-DD)
+    pub date: String,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+}
+
+/// 기간 동안의 상대 기기 하나의 사용량
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PeerBandwidth {
+    /// 상대 기기 ID (또는 IP 주소)
+    pub peer_id: String,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+}
+
+/// 기간 동안의 대역폭 사용량
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BandwidthUsage {
+    /// 기간 (YYYY-MM-DD, 양 끝 포함)
+    pub start: String,
+    pub end: String,
+
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+
+    /// 사용량이 있는 날짜별 합계 (날짜 순)
+    pub days: Vec<DailyUsage>,
+
+    /// 기기별 합계 (사용량이 많은 순)
+    pub peers: Vec<PeerBandwidth>,
+}
+
+/// 데이터 사용 한도의 기간
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CapPeriod {
+    /// 이번 달 (매월 1일에 초기화)
+    Monthly,
+    /// 기록이 남아 있는 전체 기간
+    Total,
+}
+
+/// 데이터 사용 한도 (보낸 양과 받은 양의 합)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DataCap {
+    /// 한도를 적용할 기기 ID (None이면 모든 기기의 합계)
+    pub peer_id: Option<String>,
+
+    pub period: CapPeriod,
+
+    /// 한도 (bytes)
+    pub limit_bytes: u64,
+}
+
+impl DataCap {
+    /// 한도 설정의 유효성을 검사합니다.
+    pub fn validate(&self) -> Result<()> {
+        if self.limit_bytes == 0 {
+            anyhow::bail!("Data cap must be at least 1 byte");
+        }
+        if self.peer_id.as_deref().is_some_and(|peer| peer.trim().is_empty()) {
+            anyhow::bail!("Data cap peer_id must not be empty (use null for all peers)");
+        }
+        Ok(())
+    }
+
+    fn applies_to(&self, peer_id: &str) -> bool {
+        self.peer_id.as_deref().is_none_or(|peer| peer == peer_id)
+    }
+}
+
+/// 한도 하나의 현재 사용량
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CapStatus {
+    pub cap: DataCap,
+
+    /// 한도 기간 동안 사용한 바이트 수
+    pub used_bytes: u64,
+
+    /// 한도에 도달했는지 여부
+    pub reached: bool,
+}
+
+/// 상대 기기와 오간 바이트 수를 날짜별 사용량에 더합니다.
+///
+/// # Arguments
+/// * `peer_id` - 상대 기기 ID (또는 IP 주소)
+/// * `date` - 사용 날짜 (로컬 시간)
+/// * `bytes_sent` / `bytes_received` - 이번 전송에서 보내거나 받은 바이트 수
+pub fn record_on(peer_id: &str, date: NaiveDate, bytes_sent: u64, bytes_received: u64) -> Result<()> {
+    if bytes_sent == 0 && bytes_received == 0 {
+        return Ok(());
+    }
+
+    let conn = db::open_connection()?;
+    conn.execute(
+        "INSERT INTO bandwidth_usage (day, peer_id, bytes_sent, bytes_received) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(day, peer_id) DO UPDATE SET
+            bytes_sent = bytes_sent + excluded.bytes_sent,
+            bytes_received = bytes_received + excluded.bytes_received",
+        params![date.format(DATE_FORMAT).to_string(), peer_id, bytes_sent as i64, bytes_received as i64],
+    )?;
+    Ok(())
+}
+
+/// 상대 기기와 오늘 오간 바이트 수를 사용량에 더합니다.
+pub fn record(peer_id: &str, bytes_sent: u64, bytes_received: u64) -> Result<()> {
+    record_on(peer_id, today(), bytes_sent, bytes_received)
+}
+
+/// 기간 동안의 사용량을 집계합니다.
+///
+/// # Arguments
+/// * `peer_id` - 특정 기기만 집계 (None이면 모든 기기)
+pub fn usage_between(start: NaiveDate, end: NaiveDate, peer_id: Option<&str>) -> Result<BandwidthUsage> {
+    let (start, end) = (start.format(DATE_FORMAT).to_string(), end.format(DATE_FORMAT).to_string());
+    let conn = db::open_connection()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT day, SUM(bytes_sent), SUM(bytes_received) FROM bandwidth_usage
+         WHERE day BETWEEN ?1 AND ?2 AND (?3 IS NULL OR peer_id = ?3)
+         GROUP BY day ORDER BY day",
+    )?;
+    let days = stmt
+        .query_map(params![start, end, peer_id], |row| {
+            Ok(DailyUsage {
+                date: row.get(0)?,
+                bytes_sent: row.get::<_, i64>(1)? as u64,
+                bytes_received: row.get::<_, i64>(2)? as u64,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT peer_id, SUM(bytes_sent), SUM(bytes_received) FROM bandwidth_usage
+         WHERE day BETWEEN ?1 AND ?2 AND (?3 IS NULL OR peer_id = ?3)
+         GROUP BY peer_id ORDER BY SUM(bytes_sent) + SUM(bytes_received) DESC, peer_id",
+    )?;
+    let peers = stmt
+        .query_map(params![start, end, peer_id], |row| {
+            Ok(PeerBandwidth {
+                peer_id: row.get(0)?,
+                bytes_sent: row.get::<_, i64>(1)? as u64,
+                bytes_received: row.get::<_, i64>(2)? as u64,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(BandwidthUsage {
+        bytes_sent: days.iter().map(|day| day.bytes_sent).sum(),
+        bytes_received: days.iter().map(|day| day.bytes_received).sum(),
+        start,
+        end,
+        days,
+        peers,
+    })
+}
+
+/// 기간 동안의 사용량을 집계합니다 (기간은 오늘 기준).
+pub fn usage(range: &UsageRange, peer_id: Option<&str>) -> Result<BandwidthUsage> {
+    let (start, end) = range.resolve(today())?;
+    usage_between(start, end, peer_id)
+}
+
+/// 한도 기간 동안의 사용량을 계산합니다.
+fn cap_status(cap: &DataCap, today: NaiveDate) -> Result<CapStatus> {
+    let start = match cap.period {
+        CapPeriod::Monthly => today.with_day(1).unwrap_or(today),
+        CapPeriod::Total => NaiveDate::MIN,
+    };
+    let usage = usage_between(start, today, cap.peer_id.as_deref())?;
+    let used_bytes = usage.bytes_sent + usage.bytes_received;
+    Ok(CapStatus {
+        cap: cap.clone(),
+        used_bytes,
+        reached: used_bytes >= cap.limit_bytes,
+    })
+}
+
+/// 설정된 모든 한도의 현재 사용량을 계산합니다.
+pub fn cap_statuses() -> Result<Vec<CapStatus>> {
+    let today = today();
+    config::current().data_caps.iter().map(|cap| cap_status(cap, today)).collect()
+}
+
+/// 상대 기기에 적용되는 한도 중 도달한 한도가 있는지 확인합니다.
+///
+/// # Arguments
+/// * `caps` - 확인할 한도 목록 (보통 설정의 `data_caps`)
+/// * `peer_id` - 상대 기기 ID (또는 IP 주소)
+pub fn reached_cap(caps: &[DataCap], peer_id: &str, today: NaiveDate) -> Result<Option<CapStatus>> {
+    for cap in caps.iter().filter(|cap| cap.applies_to(peer_id)) {
+        let status = cap_status(cap, today)?;
+        if status.reached {
+            return Ok(Some(status));
+        }
+    }
+    Ok(None)
+}
+
+/// 상대 기기로 보내기 전에 데이터 사용 한도를 확인합니다.
+///
+/// # Returns
+/// * 한도에 도달했으면 `PebbleError::QuotaExceeded`
+pub fn check_caps(peer_id: &str) -> Result<()> {
+    let caps = config::current().data_caps;
+    if caps.is_empty() {
+        return Ok(());
+    }
+
+    match reached_cap(&caps, peer_id, today())? {
+        Some(status) => Err(PebbleError::quota_exceeded(format!(
+            "{:?} data cap of {} bytes reached for {} ({} bytes used)",
+            status.cap.period,
+            status.cap.limit_bytes,
+            status.cap.peer_id.as_deref().unwrap_or("all peers"),
+            status.used_bytes
+        ))
+        .into()),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        parse_date(value).unwrap()
+    }
+
+    #[test]
+    fn test_range_resolution() {
+        let today = date("2024-03-15");
+        assert_eq!(UsageRange::Today.resolve(today).unwrap(), (today, today));
+        assert_eq!(UsageRange::LastDays { days: 7 }.resolve(today).unwrap(), (date("2024-03-09"), today));
+        assert_eq!(UsageRange::ThisMonth.resolve(today).unwrap(), (date("2024-03-01"), today));
+        assert!(UsageRange::LastDays { days: 0 }.resolve(today).is_err());
+        let reversed = UsageRange::Between { start: "2024-03-10".to_string(), end: "2024-03-01".to_string() };
+        assert!(reversed.resolve(today).is_err());
+    }
+
+    #[test]
+    fn test_usage_grouped_by_day_and_peer() {
+        crate::loopback::use_temp_environment();
+        // 다른 테스트와 겹치지 않는 과거 날짜
+        record_on("bandwidth-a", date("2001-01-01"), 100, 10).unwrap();
+        record_on("bandwidth-a", date("2001-01-01"), 50, 0).unwrap();
+        record_on("bandwidth-b", date("2001-01-02"), 0, 500).unwrap();
+        record_on("bandwidth-b", date("2001-02-01"), 999, 999).unwrap();
+
+        let usage = usage_between(date("2001-01-01"), date("2001-01-31"), None).unwrap();
+        assert_eq!((usage.bytes_sent, usage.bytes_received), (150, 510));
+        assert_eq!(usage.days.len(), 2);
+        assert_eq!(usage.days[0], DailyUsage { date: "2001-01-01".to_string(), bytes_sent: 150, bytes_received: 10 });
+        assert_eq!(usage.peers[0].peer_id, "bandwidth-b");
+
+        let only_a = usage_between(date("2001-01-01"), date("2001-12-31"), Some("bandwidth-a")).unwrap();
+        assert_eq!((only_a.bytes_sent, only_a.bytes_received), (150, 10));
+    }
+
+    #[test]
+    fn test_monthly_cap_resets_next_month() {
+        crate::loopback::use_temp_environment();
+        let peer = "cap-test-peer";
+        record_on(peer, date("2002-05-10"), 600, 400).unwrap();
+
+        let caps = vec![
+            DataCap { peer_id: Some("other-peer".to_string()), period: CapPeriod::Monthly, limit_bytes: 1 },
+            DataCap { peer_id: Some(peer.to_string()), period: CapPeriod::Monthly, limit_bytes: 1000 },
+        ];
+        let reached = reached_cap(&caps, peer, date("2002-05-20")).unwrap().unwrap();
+        assert_eq!(reached.used_bytes, 1000);
+        assert_eq!(reached.cap, caps[1]);
+
+        // 다음 달에는 다시 보낼 수 있음
+        assert_eq!(reached_cap(&caps, peer, date("2002-06-01")).unwrap(), None);
+        assert!(DataCap { peer_id: None, period: CapPeriod::Total, limit_bytes: 0 }.validate().is_err());
+    }
+}
@@ -5,6 +5,8 @@ use std::fs;
 use std::path::Path;
 use std::sync::Arc;
 
+use super::tuning;
+
 /// TLS 인증서 및 개인 키 쌍
 #[derive(Clone)]
 pub struct TlsCertificate {
@@ -31,7 +33,7 @@ impl TlsCertificate {
     /// - 1년 유효기간
     /// - P2P 통신을 위한 자기 서명 인증서
     pub fn generate_self_signed(device_id: &str, device_name: &str) -> Result<Self> {
-        log::info!("Generating self-signed certificate for device: {}", device_name);
+        tracing::info!("Generating self-signed certificate for device: {}", device_name);
 
         // Distinguished Name 설정
         let mut distinguished_name = DistinguishedName::new();
@@ -55,7 +57,7 @@ impl TlsCertificate {
         // 인증서 핑거프린트 계산 (SHA-256)
         let fingerprint = Self::calculate_fingerprint(&cert_der)?;
 
-        log::info!("Certificate generated. Fingerprint: {}", fingerprint);
+        tracing::info!("Certificate generated. Fingerprint: {}", fingerprint);
 
         Ok(Self {
             cert_der,
@@ -69,7 +71,7 @@ impl TlsCertificate {
     /// # Security
     /// - 인증서 핀닝(Certificate Pinning)에 사용
     /// - MITM 공격 방지를 위한 인증서 검증
-    fn calculate_fingerprint(cert_der: &[u8]) -> Result<String> {
+    pub(crate) fn calculate_fingerprint(cert_der: &[u8]) -> Result<String> {
         use sha2::{Digest, Sha256};
 
         let mut hasher = Sha256::new();
@@ -91,7 +93,7 @@ impl TlsCertificate {
         fs::write(key_path, &self.key_der)
             .with_context(|| format!("Failed to write private key to {}", key_path))?;
 
-        log::info!("Certificate saved to {} and {}", cert_path, key_path);
+        tracing::info!("Certificate saved to {} and {}", cert_path, key_path);
 
         Ok(())
     }
@@ -110,7 +112,7 @@ impl TlsCertificate {
 
         let fingerprint = Self::calculate_fingerprint(&cert_der)?;
 
-        log::info!("Certificate loaded from {}. Fingerprint: {}", cert_path, fingerprint);
+        tracing::info!("Certificate loaded from {}. Fingerprint: {}", cert_path, fingerprint);
 
         Ok(Self {
             cert_der,
@@ -119,16 +121,84 @@ impl TlsCertificate {
         })
     }
 
+    /// TLS 연결의 상대가 보낸 인증서의 핑거프린트를 계산합니다 (인증서가 없으면 None).
+    pub fn peer_fingerprint(peer_certificates: Option<&[CertificateDer<'_>]>) -> Option<String> {
+        let end_entity = peer_certificates?.first()?;
+        Self::calculate_fingerprint(end_entity.as_ref()).ok()
+    }
+
     /// Rustls용 ServerConfig를 생성합니다.
+    ///
+    /// # Security
+    /// - 클라이언트 인증서를 요청하지만 필수는 아님 (속도 측정, 상태 확인 등 기기 인증서 없는 연결 허용)
+    /// - 클라이언트 인증서는 자기 서명이므로 발급자는 확인하지 않고, 핸드셰이크 서명으로 키 소유만 확인
+    /// - 연결한 기기의 신원은 `peer_fingerprint`로 구해 고정된 핑거프린트와 비교해야 함
     pub fn build_server_config(&self) -> Result<Arc<rustls::ServerConfig>> {
+        use rustls::client::danger::HandshakeSignatureValid;
+        use rustls::crypto::WebPkiSupportedAlgorithms;
+        use rustls::pki_types::UnixTime;
+        use rustls::server::danger::{ClientCertVerified, ClientCertVerifier};
+        use rustls::{DigitallySignedStruct, DistinguishedName, SignatureScheme};
+
+        // 자기 서명 클라이언트 인증서 검증기
+        #[derive(Debug)]
+        struct PeerCertVerifier {
+            algorithms: WebPkiSupportedAlgorithms,
+        }
+
+        impl ClientCertVerifier for PeerCertVerifier {
+            fn client_auth_mandatory(&self) -> bool {
+                false
+            }
+
+            fn root_hint_subjects(&self) -> &[DistinguishedName] {
+                &[]
+            }
+
+            fn verify_client_cert(
+                &self,
+                _end_entity: &CertificateDer,
+                _intermediates: &[CertificateDer],
+                _now: UnixTime,
+            ) -> Result<ClientCertVerified, rustls::Error> {
+                Ok(ClientCertVerified::assertion())
+            }
+
+            fn verify_tls12_signature(
+                &self,
+                message: &[u8],
+                cert: &CertificateDer,
+                dss: &DigitallySignedStruct,
+            ) -> Result<HandshakeSignatureValid, rustls::Error> {
+                rustls::crypto::verify_tls12_signature(message, cert, dss, &self.algorithms)
+            }
+
+            fn verify_tls13_signature(
+                &self,
+                message: &[u8],
+                cert: &CertificateDer,
+                dss: &DigitallySignedStruct,
+            ) -> Result<HandshakeSignatureValid, rustls::Error> {
+                rustls::crypto::verify_tls13_signature(message, cert, dss, &self.algorithms)
+            }
+
+            fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+                self.algorithms.supported_schemes()
+            }
+        }
+
         let cert = CertificateDer::from(self.cert_der.clone());
         let key = PrivateKeyDer::try_from(self.key_der.clone())
             .map_err(|e| anyhow::anyhow!("Invalid private key: {:?}", e))?;
+        let verifier = Arc::new(PeerCertVerifier {
+            algorithms: rustls::crypto::aws_lc_rs::default_provider().signature_verification_algorithms,
+        });
 
-        let config = rustls::ServerConfig::builder()
-            .with_no_client_auth()
+        let mut config = rustls::ServerConfig::builder()
+            .with_client_cert_verifier(verifier)
             .with_single_cert(vec![cert], key)
             .context("Failed to build server config")?;
+        config.max_fragment_size = tuning::current().max_fragment_size();
 
         Ok(Arc::new(config))
     }
@@ -143,6 +213,21 @@ impl TlsCertificate {
     /// - 대신 Certificate Pinning으로 보안을 강화합니다
     /// - trusted_fingerprint가 제공되면 해당 핑거프린트만 허용
     pub fn build_client_config(trusted_fingerprint: Option<String>) -> Result<Arc<rustls::ClientConfig>> {
+        Self::build_client_config_as(trusted_fingerprint, None)
+    }
+
+    /// 이 기기의 인증서를 클라이언트 인증서로 보내는 ClientConfig를 생성합니다.
+    ///
+    /// # Arguments
+    /// * `trusted_fingerprint` - 신뢰할 서버 인증서의 핑거프린트 (Optional)
+    /// * `identity` - 서버에 보낼 이 기기의 인증서 (없으면 클라이언트 인증서 없이 연결)
+    ///
+    /// # Security
+    /// - 서버는 이 인증서의 핑거프린트로 요청한 기기를 확인 (철회 여부, 알린 기기 ID와의 일치)
+    pub fn build_client_config_as(
+        trusted_fingerprint: Option<String>,
+        identity: Option<&TlsCertificate>,
+    ) -> Result<Arc<rustls::ClientConfig>> {
         use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
         use rustls::pki_types::{ServerName, UnixTime};
         use rustls::{DigitallySignedStruct, SignatureScheme};
@@ -166,15 +251,15 @@ impl TlsCertificate {
                 let fingerprint = TlsCertificate::calculate_fingerprint(end_entity.as_ref())
                     .map_err(|_| rustls::Error::General("Failed to calculate fingerprint".into()))?;
 
-                log::debug!("Server certificate fingerprint: {}", fingerprint);
+                tracing::debug!("Server certificate fingerprint: {}", fingerprint);
 
                 // 핑거프린트 검증 (Certificate Pinning)
                 if let Some(ref trusted) = self.trusted_fingerprint {
                     if &fingerprint != trusted {
-                        log::error!("Certificate fingerprint mismatch! Expected: {}, Got: {}", trusted, fingerprint);
+                        tracing::error!("Certificate fingerprint mismatch! Expected: {}, Got: {}", trusted, fingerprint);
                         return Err(rustls::Error::General("Certificate fingerprint mismatch".into()));
                     }
-                    log::info!("Certificate pinning verified successfully");
+                    tracing::info!("Certificate pinning verified successfully");
                 }
 
                 Ok(ServerCertVerified::assertion())
@@ -209,10 +294,20 @@ impl TlsCertificate {
 
         let verifier = Arc::new(CustomCertVerifier { trusted_fingerprint });
 
-        let config = rustls::ClientConfig::builder()
+        let builder = rustls::ClientConfig::builder()
             .dangerous()
-            .with_custom_certificate_verifier(verifier)
-            .with_no_client_auth();
+            .with_custom_certificate_verifier(verifier);
+        let mut config = match identity {
+            Some(identity) => {
+                let key = PrivateKeyDer::try_from(identity.key_der.clone())
+                    .map_err(|e| anyhow::anyhow!("Invalid private key: {:?}", e))?;
+                builder
+                    .with_client_auth_cert(vec![CertificateDer::from(identity.cert_der.clone())], key)
+                    .context("Failed to use device certificate for client authentication")?
+            }
+            None => builder.with_no_client_auth(),
+        };
+        config.max_fragment_size = tuning::current().max_fragment_size();
 
         Ok(Arc::new(config))
     }
@@ -259,7 +354,7 @@ impl CertificateManager {
 
         // 기존 인증서 확인
         if Path::new(&cert_path).exists() && Path::new(&key_path).exists() {
-            log::info!("Loading existing certificate from {}", cert_path);
+            tracing::info!("Loading existing certificate from {}", cert_path);
             TlsCertificate::load_from_files(&cert_path, &key_path)
         } else {
             // 디렉토리 생성
@@ -276,6 +371,18 @@ impl CertificateManager {
         }
     }
 
+    /// 저장된 인증서가 있으면 로드합니다 (새로 생성하지 않음).
+    pub fn load_existing(&self) -> Result<Option<TlsCertificate>> {
+        let cert_path = self.cert_path();
+        let key_path = self.key_path();
+
+        if Path::new(&cert_path).exists() && Path::new(&key_path).exists() {
+            TlsCertificate::load_from_files(&cert_path, &key_path).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
     /// 인증서를 삭제합니다.
     pub fn delete_certificate(&self) -> Result<()> {
         let cert_path = self.cert_path();
@@ -291,7 +398,7 @@ impl CertificateManager {
                 .with_context(|| format!("Failed to delete private key: {}", key_path))?;
         }
 
-        log::info!("Certificate deleted");
+        tracing::info!("Certificate deleted");
 
         Ok(())
     }
@@ -0,0 +1,317 @@
+//! 기기 간 클립보드 동기화 (Clipboard Sync)
+//!
+//! 텍스트 메시지와 같은 TLS 연결로 클립보드 내용(텍스트, 이미지)을 주고받습니다.
+//! 동기화는 기기마다 켜야 하며(`set_enabled`), 켠 기기끼리만 보내고 받습니다.
+//! 운영체제 클립보드는 앱(호스트)이 감시하다가 바뀌면 `publish`를 호출하고,
+//! 받은 내용은 구독자(`subscribe`)를 통해 앱이 클립보드에 씁니다.
+//!
+//! # Process Flow
+//! 1. 앱이 클립보드 변경을 감지하면 `publish`로 내용을 넘김
+//! 2. 내용 해시가 마지막으로 보내거나 적용한 해시와 같으면 보내지 않음
+//!    (받은 내용을 앱이 클립보드에 쓰면서 다시 `publish`된 경우)
+//! 3. 동기화를 켠 기기마다 `ClipboardUpdate` 전송 → `ClipboardAck`
+//! 4. 받은 기기는 보낸 기기의 동기화가 켜져 있는지 확인
+//!    → 이 기기가 처음 복사한 내용이거나 마지막 해시와 같으면 적용하지 않음
+//!    → 새 내용이면 마지막 해시를 갱신하고 구독자에게 알림
+//!
+//! 받은 내용은 다른 기기로 다시 보내지 않으므로(처음 복사한 기기만 보냄) 기기 사이를 돌지 않습니다.
+//! 보내지 못한 기기에 다시 보내지도 않습니다 (다음 복사가 더 새로운 내용이므로).
+//!
+//! # Security
+//! - 동기화를 켜지 않은 기기가 보낸 내용은 거부 (`RejectCode::PolicyDenied`)
+//! - 비밀번호 등이 담길 수 있으므로 내용은 DB에 기록하지 않음
+//! - 크기 제한: 텍스트 `MAX_CLIPBOARD_TEXT_BYTES`, 이미지 `MAX_CLIPBOARD_IMAGE_BYTES`
+
+use anyhow::Result;
+use futures::future::join_all;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+use super::db;
+use super::error::PebbleError;
+use super::peers;
+use super::service;
+use super::transfer::TransferClient;
+use super::util::{unix_now, Subscribers};
+
+/// 클립보드 텍스트 최대 크기 (UTF-8 바이트)
+pub const MAX_CLIPBOARD_TEXT_BYTES: usize = 1024 * 1024;
+
+/// 클립보드 이미지 최대 크기
+pub const MAX_CLIPBOARD_IMAGE_BYTES: usize = 8 * 1024 * 1024;
+
+/// 클립보드 내용 종류
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClipboardKind {
+    /// UTF-8 텍스트 (`text/*`)
+    Text,
+
+    /// 인코딩된 이미지 (`image/*`, 예: PNG)
+    Image,
+}
+
+/// 기기 사이에 주고받는 클립보드 내용
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClipboardUpdate {
+    pub update_id: String,
+
+    /// 내용을 처음 복사한 기기 ID
+    pub origin_device_id: String,
+
+    pub kind: ClipboardKind,
+
+    /// MIME 형식 (예: "text/plain", "image/png")
+    pub mime_type: String,
+
+    pub data: Vec<u8>,
+
+    /// 내용 해시 (blake3, 같은 내용을 다시 보내거나 적용하지 않기 위해 사용)
+    pub content_hash: String,
+
+    /// 복사한 시각 (Unix timestamp)
+    pub created_at: i64,
+}
+
+impl ClipboardUpdate {
+    pub fn new(origin_device_id: String, kind: ClipboardKind, mime_type: String, data: Vec<u8>) -> Self {
+        let created_at = unix_now();
+
+        Self {
+            update_id: Uuid::new_v4().to_string(),
+            origin_device_id,
+            kind,
+            content_hash: content_hash(kind, &mime_type, &data),
+            mime_type,
+            data,
+            created_at,
+        }
+    }
+
+    /// 종류, 형식, 크기, 해시를 검증합니다.
+    pub fn validate(&self) -> Result<()> {
+        if self.data.is_empty() {
+            anyhow::bail!("Clipboard content must not be empty");
+        }
+
+        match self.kind {
+            ClipboardKind::Text => {
+                if !self.mime_type.starts_with("text/") {
+                    anyhow::bail!("Text clipboard must use a text/* type, got {}", self.mime_type);
+                }
+                if self.data.len() > MAX_CLIPBOARD_TEXT_BYTES {
+                    anyhow::bail!("Clipboard text is too large: {} bytes (max {})", self.data.len(), MAX_CLIPBOARD_TEXT_BYTES);
+                }
+                if std::str::from_utf8(&self.data).is_err() {
+                    anyhow::bail!("Clipboard text is not valid UTF-8");
+                }
+            }
+            ClipboardKind::Image => {
+                if !self.mime_type.starts_with("image/") {
+                    anyhow::bail!("Image clipboard must use an image/* type, got {}", self.mime_type);
+                }
+                if self.data.len() > MAX_CLIPBOARD_IMAGE_BYTES {
+                    anyhow::bail!("Clipboard image is too large: {} bytes (max {})", self.data.len(), MAX_CLIPBOARD_IMAGE_BYTES);
+                }
+            }
+        }
+
+        if self.content_hash != content_hash(self.kind, &self.mime_type, &self.data) {
+            anyhow::bail!("Clipboard content hash does not match");
+        }
+        Ok(())
+    }
+}
+
+/// 클립보드 내용을 보낸 결과
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClipboardPublishReport {
+    /// 보낸 내용의 ID (마지막으로 보내거나 받은 내용과 같아 보내지 않았으면 None)
+    pub update_id: Option<String>,
+
+    /// 받은 기기 ID
+    pub delivered: Vec<String>,
+
+    /// 보내지 못한 기기 ID
+    pub failed: Vec<String>,
+}
+
+fn content_hash(kind: ClipboardKind, mime_type: &str, data: &[u8]) -> String {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(format!("{:?}\0{}\0", kind, mime_type).as_bytes());
+    hasher.update(data);
+    hasher.finalize().to_hex().to_string()
+}
+
+/// 마지막으로 보내거나 적용한 내용 해시 (반복 방지)
+static LAST_HASH: once_cell::sync::Lazy<Mutex<Option<String>>> = once_cell::sync::Lazy::new(|| Mutex::new(None));
+
+/// 처음 보는 내용이면 마지막 해시로 기록하고 true를 반환합니다.
+fn remember(content_hash: &str) -> bool {
+    let Ok(mut last) = LAST_HASH.lock() else {
+        return true;
+    };
+    if last.as_deref() == Some(content_hash) {
+        return false;
+    }
+    *last = Some(content_hash.to_string());
+    true
+}
+
+/// 기기의 클립보드 동기화를 켜거나 끕니다.
+pub fn set_enabled(device_id: &str, enabled: bool) -> Result<()> {
+    let conn = db::open_connection()?;
+    if enabled {
+        conn.execute(
+            "INSERT OR IGNORE INTO clipboard_peers (device_id, enabled_at) VALUES (?1, strftime('%s', 'now'))",
+            params![device_id],
+        )?;
+    } else {
+        conn.execute("DELETE FROM clipboard_peers WHERE device_id = ?1", params![device_id])?;
+    }
+    Ok(())
+}
+
+/// 기기의 클립보드 동기화가 켜져 있는지 확인합니다.
+pub fn is_enabled(device_id: &str) -> Result<bool> {
+    let conn = db::open_connection()?;
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM clipboard_peers WHERE device_id = ?1",
+        params![device_id],
+        |row| row.get(0),
+    )?;
+    Ok(count > 0)
+}
+
+/// 클립보드 동기화를 켠 기기 ID 목록
+pub fn enabled_peers() -> Result<Vec<String>> {
+    let conn = db::open_connection()?;
+    let peers = conn
+        .prepare("SELECT device_id FROM clipboard_peers ORDER BY enabled_at, device_id")?
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    Ok(peers)
+}
+
+/// 클립보드 내용을 기기 하나에 보냅니다.
+async fn send(update: &ClipboardUpdate, peer_device_id: &str) -> Result<()> {
+    let peer = peers::resolve(peer_device_id)?;
+    TransferClient::new(peer.fingerprint).send_clipboard(&peer.addrs, update).await
+}
+
+/// 이 기기의 클립보드 내용을 동기화를 켠 기기들에 보냅니다.
+///
+/// # Arguments
+/// * `kind` - 내용 종류
+/// * `mime_type` - MIME 형식 (예: "text/plain", "image/png")
+/// * `data` - 내용 (텍스트는 UTF-8)
+///
+/// # Returns
+/// * 받은 기기와 보내지 못한 기기 (마지막 내용과 같으면 아무 기기에도 보내지 않음)
+pub async fn publish(kind: ClipboardKind, mime_type: String, data: Vec<u8>) -> Result<ClipboardPublishReport> {
+    let origin = service::device_id()
+        .ok_or_else(|| PebbleError::invalid_argument("Start Pebble before syncing the clipboard"))?;
+    let update = ClipboardUpdate::new(origin, kind, mime_type, data);
+    update.validate().map_err(|e| PebbleError::invalid_argument(format!("{:#}", e)))?;
+
+    let mut report = ClipboardPublishReport { update_id: None, delivered: Vec::new(), failed: Vec::new() };
+    if !remember(&update.content_hash) {
+        tracing::debug!("Clipboard content unchanged, not sending");
+        return Ok(report);
+    }
+    report.update_id = Some(update.update_id.clone());
+
+    let peers = enabled_peers()?;
+    let results = join_all(peers.iter().map(|peer| send(&update, peer))).await;
+    for (peer, result) in peers.into_iter().zip(results) {
+        match result {
+            Ok(()) => report.delivered.push(peer),
+            Err(e) => {
+                tracing::info!("Clipboard not delivered to {}: {:#}", peer, e);
+                report.failed.push(peer);
+            }
+        }
+    }
+
+    tracing::info!(
+        "Clipboard {:?} ({} bytes) sent to {} device(s), {} failed",
+        update.kind, update.data.len(), report.delivered.len(), report.failed.len()
+    );
+    Ok(report)
+}
+
+/// 상대 기기가 보낸 클립보드 내용을 확인하고, 새 내용이면 구독자에게 알립니다.
+///
+/// # Returns
+/// * 구독자에게 알렸으면 true (이 기기가 복사한 내용이거나 이미 적용한 내용이면 false)
+///
+/// # Security
+/// - 보낸 기기(`origin_device_id`)의 클립보드 동기화가 꺼져 있으면 `PebbleError::Rejected`
+pub fn receive(update: ClipboardUpdate) -> Result<bool> {
+    update.validate().map_err(|e| PebbleError::rejected(format!("{:#}", e)))?;
+    if !is_enabled(&update.origin_device_id)? {
+        return Err(PebbleError::rejected(format!(
+            "Clipboard sync is not enabled for device {}", update.origin_device_id
+        )).into());
+    }
+
+    if service::device_id().as_deref() == Some(update.origin_device_id.as_str()) || !remember(&update.content_hash) {
+        tracing::debug!("Ignoring clipboard update {} (already applied)", update.update_id);
+        return Ok(false);
+    }
+
+    SUBSCRIBERS.notify(&update);
+    Ok(true)
+}
+
+static SUBSCRIBERS: Subscribers<ClipboardUpdate> = Subscribers::new();
+
+/// 받은 클립보드 내용 구독자를 등록합니다.
+pub fn subscribe<F>(subscriber: F)
+where
+    F: Fn(&ClipboardUpdate) -> bool + Send + 'static,
+{
+    SUBSCRIBERS.subscribe(subscriber);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_limits_and_hash() {
+        let text = |data: &[u8]| ClipboardUpdate::new("a".to_string(), ClipboardKind::Text, "text/plain".to_string(), data.to_vec());
+        assert!(text(b"hello").validate().is_ok());
+        assert!(text(b"").validate().is_err());
+        assert!(text(&[0xff, 0xfe]).validate().is_err());
+        assert!(text(&vec![b'a'; MAX_CLIPBOARD_TEXT_BYTES + 1]).validate().is_err());
+
+        let image = ClipboardUpdate::new("a".to_string(), ClipboardKind::Image, "image/png".to_string(), vec![0u8; 1024]);
+        assert!(image.validate().is_ok());
+        let mismatched = ClipboardUpdate { mime_type: "text/plain".to_string(), ..image.clone() };
+        assert!(mismatched.validate().is_err());
+        let tampered = ClipboardUpdate { data: vec![1u8; 1024], ..image };
+        assert!(tampered.validate().is_err());
+    }
+
+    #[test]
+    fn test_receive_requires_enabled_peer_and_skips_repeats() {
+        crate::loopback::use_temp_environment();
+        let update = || {
+            ClipboardUpdate::new("clip-peer".to_string(), ClipboardKind::Text, "text/plain".to_string(), b"clip-test".to_vec())
+        };
+
+        assert!(receive(update()).is_err());
+
+        set_enabled("clip-peer", true).unwrap();
+        assert!(enabled_peers().unwrap().contains(&"clip-peer".to_string()));
+        assert!(receive(update()).unwrap());
+        // 같은 내용은 다시 적용하지 않음 (앱이 클립보드에 쓴 내용이 되돌아오는 경우 포함)
+        assert!(!receive(update()).unwrap());
+
+        set_enabled("clip-peer", false).unwrap();
+        assert!(!is_enabled("clip-peer").unwrap());
+    }
+}
@@ -0,0 +1,598 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tokio::sync::watch;
+
+use super::accept::AcceptPolicy;
+use super::bandwidth::DataCap;
+use super::filename::CaseCollisionPolicy;
+use super::listeners::ListenerConfig;
+use super::history::RetentionPolicy;
+use super::integrity::{ChangeDetection, RootChangeDetection};
+use super::presence::PresenceRule;
+use super::scanhook::ScanHook;
+use super::schedule::ScheduleWindow;
+use super::transfer::{CHUNK_SIZE, TRANSFER_PORT};
+use super::tuning::SocketTuning;
+
+/// 설정 파일 이름
+pub const CONFIG_FILE_NAME: &str = "pebble_config.json";
+
+/// `download_dir`이 없을 때 받은 파일을 저장하는 폴더 이름 (DB 파일과 같은 폴더 아래)
+pub const DEFAULT_DOWNLOAD_DIR_NAME: &str = "downloads";
+
+/// 허용되는 청크 크기 범위 (64KB ~ 16MB)
+pub const MIN_CHUNK_SIZE: u64 = 64 * 1024;
+pub const MAX_CHUNK_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Pebble 전역 설정
+///
+/// 앱 데이터 디렉토리의 JSON 파일로 저장되며, 누락된 필드는 기본값으로 채워집니다.
+/// 실행 중인 서비스는 `subscribe()`로 변경 사항을 전달받거나
+/// 사용 시점에 `current()`로 최신 값을 읽습니다.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PebbleConfig {
+    /// SQLite DB 파일 경로
+    pub db_path: String,
+
+    /// 파일 전송 서버 포트
+    pub transfer_port: u16,
+
+    /// 전송 서버를 바인딩할 IP 주소 또는 네트워크 인터페이스 이름 (예: "192.168.0.10", "en0")
+    ///
+    /// None이면 모든 인터페이스에서 연결을 받습니다. VPN이나 공용 네트워크에 전송 서버를
+    /// 노출하지 않으려면 LAN 인터페이스를 지정하세요. 추가 전송 서버(`listeners`)에는 적용되지 않습니다.
+    pub transfer_bind: Option<String>,
+
+    /// UDP 비콘 포트
+    pub discovery_port: u16,
+
+    /// 전송 청크 크기 (bytes)
+    pub chunk_size: u64,
+
+    /// 수신 파일 저장 디렉토리 (None이면 DB 파일 옆의 `DEFAULT_DOWNLOAD_DIR_NAME` 폴더, `receive_dir` 참고)
+    pub download_dir: Option<String>,
+
+    /// 받은 파일을 암호화해 두고 승인을 기다리는 대기 디렉토리 (None이면 바로 저장)
+    ///
+    /// 설정하면 받은 파일은 사용자가 승인(`approve_staged_file`)한 뒤에야 복호화되어 저장 위치에 놓입니다.
+    /// 호스트 저장소(Android SAF) 모드에서는 적용되지 않습니다.
+    pub staging_dir: Option<String>,
+
+    /// 최대 전송 속도 (bytes/sec, 0이면 무제한)
+    pub max_transfer_rate: u64,
+
+    /// 비콘 전송 주기 (초)
+    pub beacon_interval_secs: u64,
+
+    /// 기기 타임아웃 시간 (초)
+    pub device_timeout_secs: u64,
+
+    /// 비콘에 기기 이름과 ID 대신 비밀 키로 만든 불투명 ID만 싣기 (공용 네트워크용)
+    ///
+    /// 이름은 같은 비밀 키를 가진 기기가 TLS 연결로 요청할 때만 알려 줍니다.
+    pub beacon_privacy: bool,
+
+    /// 비콘 전체를 비밀 키에서 만든 키로 암호화 (ChaCha20-Poly1305)
+    ///
+    /// 켜면 같은 비밀 키가 없는 관찰자는 기기 ID, 이름, 포트를 볼 수 없습니다.
+    /// 받는 쪽은 설정과 무관하게 암호화된 비콘과 평문 비콘을 모두 받지만, 구버전 기기는 암호화된 비콘을 읽지 못합니다.
+    pub beacon_encryption: bool,
+
+    /// 받은 청크의 해시를 검증하는 작업자 수 (0이면 수신 루프에서 바로 검증)
+    ///
+    /// 작업자가 검증하는 동안 다음 청크를 읽고 쓰므로 CPU가 느린 기기에서 처리량이 늘어납니다.
+    pub chunk_verify_workers: u32,
+
+    /// 청크 검증에 비암호학적 고속 해시(xxh3) 사용 허용 (신뢰하는 LAN 전용)
+    ///
+    /// 양측이 모두 허용해야 사용되며, 파일 전체 해시는 항상 blake3를 사용합니다.
+    pub fast_chunk_hash: bool,
+
+    /// 받을 때 청크 검증에 CRC32C 사용 (저전력 기기용, 송신측이 지원하면 적용)
+    ///
+    /// 청크마다 CPU 명령으로 체크섬만 확인하고 파일 전체는 blake3로 검증하므로 CPU 사용량이 크게 줄어듭니다.
+    /// 송신측이 파일 해시를 보내지 않으면(구버전) 사용하지 않습니다.
+    pub crc_chunk_check: bool,
+
+    /// 수신 전송 자동 수락 정책
+    pub accept_policy: AcceptPolicy,
+
+    /// 시간대별 자동 동기화 중지/속도 제한 (동기화 쌍에 별도 일정이 없을 때 적용)
+    pub schedule: Vec<ScheduleWindow>,
+
+    /// 받은 청크를 해시로 색인해 두는 중복 제거 저장소 디렉토리 (None이면 사용 안 함)
+    ///
+    /// 이전에 받은 청크와 같은 청크는 네트워크로 다시 받지 않고 저장소에서 복사합니다.
+    pub dedup_store_dir: Option<String>,
+
+    /// 중복 제거 저장소의 최대 크기 (bytes, 0이면 제한 없음)
+    ///
+    /// 유지보수 때 넘으면 가장 오래 쓰지 않은 청크부터 지웁니다.
+    pub dedup_store_max_bytes: u64,
+
+    /// 전송 기록과 이어받기 상태의 보존 정책
+    pub history_retention: RetentionPolicy,
+
+    /// 받은 이미지의 미리보기를 저장할 캐시 디렉토리 (None이면 만들지 않음)
+    ///
+    /// 미리보기 경로는 전송 기록에 남으며, `thumbnails` 기능을 켜고 빌드한 경우에만 만들어집니다.
+    pub thumbnail_dir: Option<String>,
+
+    /// 미리보기 긴 변의 최대 크기 (px)
+    pub thumbnail_size: u32,
+
+    /// 중단된 수신의 미완성 파일과 이어받기 상태를 지우기까지의 시간 (시간 단위, 0이면 자동 정리 안 함)
+    ///
+    /// 마지막으로 청크를 받은 뒤 이 시간이 지나도록 이어받지 않은 수신이 대상입니다.
+    pub partial_max_age_hours: u64,
+
+    /// 데이터 사용 한도 (도달하면 자동 동기화를 멈추고, 직접 보내는 전송은 사용자 확인 필요)
+    pub data_caps: Vec<DataCap>,
+
+    /// 확장 속성(Finder 태그, 리소스 포크 등)을 보내고 받은 파일에 복원
+    ///
+    /// 보내는 쪽과 받는 쪽 모두 켜져 있어야 보존됩니다.
+    pub preserve_xattrs: bool,
+
+    /// 대소문자만 다른 이름의 파일이 이미 있을 때의 처리 (대소문자를 구분하지 않는 파일 시스템)
+    pub case_collision_policy: CaseCollisionPolicy,
+
+    /// 받은 파일을 저장 위치에 확정하기 전에 실행할 검사 명령 (백신 CLI 등, None이면 검사 안 함)
+    ///
+    /// 명령이 0이 아닌 코드로 끝나거나 시간을 넘기면 파일을 격리하고 송신측에 정책 거부로 알립니다.
+    pub scan_hook: Option<ScanHook>,
+
+    /// 기본 전송 서버와 함께 시작할 추가 전송 서버 (VPN 인터페이스 등)
+    pub listeners: Vec<ListenerConfig>,
+
+    /// 전송 연결의 TCP_NODELAY, 소켓 버퍼 크기, TLS 레코드 크기 (고속 LAN용)
+    ///
+    /// 바뀐 값은 새 연결부터 적용되며, 수신 버퍼 크기는 전송 서버를 다시 시작해야 적용됩니다
+    /// (`start_pebble`로 시작한 경우 자동으로 다시 시작).
+    pub socket_tuning: SocketTuning,
+
+    /// 기기가 나타나거나 사라질 때 실행할 동작 (동기화 쌍 비교, 로컬 웹훅, 명령 등)
+    pub presence_rules: Vec<PresenceRule>,
+
+    /// 루트별 변경 감지 방식 (목록에 없는 루트는 내용 해시)
+    ///
+    /// 바뀌지 않는 대용량 미디어 보관함에 `size_mtime`을 쓰면 해시하지 않아 스캔이 빨라지는 대신
+    /// 수정 시각을 유지한 채 바뀐 내용과 비트 손상은 찾지 못합니다. 매니페스트에도 표시됩니다.
+    pub change_detection: Vec<RootChangeDetection>,
+}
+
+impl Default for PebbleConfig {
+    fn default() -> Self {
+        Self {
+            db_path: "pebble.db".to_string(),
+            transfer_port: TRANSFER_PORT,
+            transfer_bind: None,
+            discovery_port: super::discovery::DISCOVERY_PORT,
+            chunk_size: CHUNK_SIZE as u64,
+            download_dir: None,
+            staging_dir: None,
+            max_transfer_rate: 0,
+            beacon_interval_secs: super::discovery::BEACON_INTERVAL_SECS,
+            device_timeout_secs: super::discovery::DEVICE_TIMEOUT_SECS,
+            beacon_privacy: false,
+            beacon_encryption: false,
+            chunk_verify_workers: super::verifier::DEFAULT_WORKERS,
+            fast_chunk_hash: false,
+            crc_chunk_check: false,
+            accept_policy: AcceptPolicy::default(),
+            schedule: Vec::new(),
+            dedup_store_dir: None,
+            dedup_store_max_bytes: super::dedup::DEFAULT_MAX_BYTES,
+            history_retention: RetentionPolicy::default(),
+            thumbnail_dir: None,
+            thumbnail_size: super::thumbnails::DEFAULT_SIZE,
+            partial_max_age_hours: super::partials::DEFAULT_MAX_AGE_HOURS,
+            data_caps: Vec::new(),
+            preserve_xattrs: false,
+            case_collision_policy: CaseCollisionPolicy::default(),
+            scan_hook: None,
+            listeners: Vec::new(),
+            socket_tuning: SocketTuning::default(),
+            presence_rules: Vec::new(),
+            change_detection: Vec::new(),
+        }
+    }
+}
+
+impl PebbleConfig {
+    /// 설정 값의 유효성을 검사합니다.
+    pub fn validate(&self) -> Result<()> {
+        if self.db_path.trim().is_empty() {
+            anyhow::bail!("db_path must not be empty");
+        }
+
+        if !(MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE).contains(&self.chunk_size) {
+            anyhow::bail!(
+                "chunk_size must be between {} and {} bytes",
+                MIN_CHUNK_SIZE,
+                MAX_CHUNK_SIZE
+            );
+        }
+
+        if self.transfer_bind.as_deref().is_some_and(|bind| bind.trim().is_empty()) {
+            anyhow::bail!("transfer_bind must not be empty (use null to bind all interfaces)");
+        }
+
+        if self.discovery_port == 0 {
+            anyhow::bail!("discovery_port must not be 0");
+        }
+
+        if self.beacon_interval_secs == 0 {
+            anyhow::bail!("beacon_interval_secs must be at least 1");
+        }
+
+        if self.device_timeout_secs <= self.beacon_interval_secs {
+            anyhow::bail!("device_timeout_secs must be greater than beacon_interval_secs");
+        }
+
+        if self.chunk_verify_workers > super::verifier::MAX_WORKERS {
+            anyhow::bail!("chunk_verify_workers must be at most {}", super::verifier::MAX_WORKERS);
+        }
+
+        for window in &self.schedule {
+            window.validate()?;
+        }
+
+        for cap in &self.data_caps {
+            cap.validate()?;
+        }
+
+        let mut listener_names = std::collections::HashSet::new();
+        for listener in &self.listeners {
+            listener.validate()?;
+            if !listener_names.insert(listener.name.as_str()) {
+                anyhow::bail!("Duplicate listener name: {}", listener.name);
+            }
+        }
+
+        self.socket_tuning.validate()?;
+
+        if let Some(hook) = &self.scan_hook {
+            hook.validate()?;
+        }
+
+        super::presence::validate_rules(&self.presence_rules)?;
+
+        let mut detection_roots = std::collections::HashSet::new();
+        for detection in &self.change_detection {
+            detection.validate()?;
+            if !detection_roots.insert(super::paths::comparison_key(&detection.root)) {
+                anyhow::bail!("Duplicate change_detection root: {}", detection.root);
+            }
+        }
+
+        if self.download_dir.as_deref().is_some_and(|dir| dir.trim().is_empty()) {
+            anyhow::bail!("download_dir must not be empty (use null for the default)");
+        }
+
+        if self.staging_dir.as_deref().is_some_and(|dir| dir.trim().is_empty()) {
+            anyhow::bail!("staging_dir must not be empty (use null to disable)");
+        }
+
+        if self.dedup_store_dir.as_deref().is_some_and(|dir| dir.trim().is_empty()) {
+            anyhow::bail!("dedup_store_dir must not be empty (use null to disable)");
+        }
+
+        if self.thumbnail_dir.as_deref().is_some_and(|dir| dir.trim().is_empty()) {
+            anyhow::bail!("thumbnail_dir must not be empty (use null to disable)");
+        }
+
+        if !(super::thumbnails::MIN_SIZE..=super::thumbnails::MAX_SIZE).contains(&self.thumbnail_size) {
+            anyhow::bail!(
+                "thumbnail_size must be between {} and {} pixels",
+                super::thumbnails::MIN_SIZE,
+                super::thumbnails::MAX_SIZE
+            );
+        }
+
+        Ok(())
+    }
+
+    /// 받은 파일을 저장할 폴더 (`download_dir`이 없으면 DB 파일 옆의 `DEFAULT_DOWNLOAD_DIR_NAME`)
+    pub fn receive_dir(&self) -> String {
+        match &self.download_dir {
+            Some(dir) => dir.clone(),
+            None => Path::new(&self.db_path)
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join(DEFAULT_DOWNLOAD_DIR_NAME)
+                .to_string_lossy()
+                .to_string(),
+        }
+    }
+
+    /// 경로가 속한 루트의 변경 감지 방식 (루트가 겹치면 가장 안쪽 루트 기준)
+    pub fn change_detection_for(&self, path: &str) -> ChangeDetection {
+        let path = super::paths::comparison_key(path);
+        self.change_detection
+            .iter()
+            .map(|detection| (super::paths::comparison_key(&detection.root), detection.mode))
+            .filter(|(root, _)| Path::new(&path).starts_with(root))
+            .max_by_key(|(root, _)| root.len())
+            .map(|(_, mode)| mode)
+            .unwrap_or_default()
+    }
+
+    /// 파일에서 설정을 읽습니다. 파일이 없으면 기본값을 반환합니다.
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+
+        if !path.exists() {
+            tracing::info!("Config file not found, using defaults: {}", path.display());
+            return Ok(Self::default());
+        }
+
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+
+        let config: Self = serde_json::from_str(&json)
+            .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
+
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// 상대 경로 DB를 현재 디렉토리가 아니라 설정 디렉토리 기준 절대 경로로 바꿉니다.
+    ///
+    /// 메모리에 있는 설정에만 적용하고 설정 파일에는 원래 값을 그대로 둡니다 (`stored_form` 참고).
+    pub fn resolve_db_path(&mut self, config_dir: &Path) {
+        if Path::new(&self.db_path).is_relative() {
+            self.db_path = config_dir.join(&self.db_path).to_string_lossy().to_string();
+        }
+    }
+
+    /// 설정 파일에 저장할 형태를 만듭니다.
+    ///
+    /// DB 경로가 파일에 적힌 상대 경로를 `resolve_db_path`로 바꾼 값 그대로라면 상대 경로로 되돌려,
+    /// 로드할 때 바꾼 절대 경로가 파일에 남지 않게 합니다.
+    fn stored_form(&self, path: &Path) -> PebbleConfig {
+        let mut stored = self.clone();
+        if let (Ok(file), Some(dir)) = (Self::load_from_file(path), path.parent()) {
+            let mut resolved = file.clone();
+            resolved.resolve_db_path(dir);
+            if resolved.db_path == self.db_path {
+                stored.db_path = file.db_path;
+            }
+        }
+        stored
+    }
+
+    /// 설정을 파일로 저장합니다.
+    ///
+    /// 임시 파일에 쓴 뒤 rename하여 저장 도중 종료되어도 기존 설정이 손상되지 않습니다.
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create config directory: {}", parent.display()))?;
+        }
+
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize config")?;
+
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, json)
+            .with_context(|| format!("Failed to write config file: {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, path)
+            .with_context(|| format!("Failed to replace config file: {}", path.display()))?;
+
+        Ok(())
+    }
+}
+
+/// 현재 설정 (watch 채널로 변경 사항을 구독자에게 전파)
+static CONFIG: once_cell::sync::Lazy<watch::Sender<PebbleConfig>> =
+    once_cell::sync::Lazy::new(|| watch::Sender::new(PebbleConfig::default()));
+
+/// 설정 파일 경로 (load 이후에만 설정됨)
+static CONFIG_PATH: once_cell::sync::Lazy<Mutex<Option<PathBuf>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(None));
+
+/// 현재 설정의 복사본을 반환합니다.
+pub fn current() -> PebbleConfig {
+    CONFIG.borrow().clone()
+}
+
+/// 설정 변경 알림을 구독합니다.
+pub fn subscribe() -> watch::Receiver<PebbleConfig> {
+    CONFIG.subscribe()
+}
+
+/// 앱 데이터 디렉토리에서 설정을 로드하고 전역 설정으로 적용합니다.
+///
+/// 상대 경로 DB는 설정 디렉토리 기준으로 바꿔 적용합니다 (설정 파일은 그대로).
+///
+/// # Arguments
+/// * `config_dir` - 설정 파일을 저장할 디렉토리 (앱 데이터 디렉토리)
+pub fn load(config_dir: &str) -> Result<PebbleConfig> {
+    let path = Path::new(config_dir).join(CONFIG_FILE_NAME);
+    let mut config = PebbleConfig::load_from_file(&path)?;
+    config.resolve_db_path(Path::new(config_dir));
+
+    *CONFIG_PATH
+        .lock()
+        .map_err(|e| anyhow::anyhow!("Failed to acquire config path lock: {}", e))? = Some(path.clone());
+
+    CONFIG.send_replace(config.clone());
+
+    tracing::info!("Configuration loaded from {}", path.display());
+
+    Ok(config)
+}
+
+/// 새 설정을 검증하고 적용한 뒤, 설정 파일이 로드된 상태라면 저장합니다.
+///
+/// 실행 중인 서비스에는 watch 채널을 통해 즉시 전파됩니다.
+pub fn update(config: PebbleConfig) -> Result<()> {
+    config.validate()?;
+
+    let path = CONFIG_PATH
+        .lock()
+        .map_err(|e| anyhow::anyhow!("Failed to acquire config path lock: {}", e))?
+        .clone();
+
+    if let Some(path) = path {
+        config.stored_form(&path).save_to_file(&path)?;
+    }
+
+    apply(config);
+
+    tracing::info!("Configuration updated");
+
+    Ok(())
+}
+
+/// 설정 파일에 저장하지 않고 전역 설정만 바꿉니다 (DB에 저장하는 런타임 설정용).
+///
+/// 호출자가 먼저 `validate`로 검증해야 합니다.
+///
+/// # Returns
+/// * 값이 실제로 바뀌어 구독자에게 알렸으면 true
+pub fn apply(config: PebbleConfig) -> bool {
+    CONFIG.send_if_modified(|current| {
+        if *current == config {
+            false
+        } else {
+            *current = config;
+            true
+        }
+    })
+}
+
+/// 설정 파일에 저장된 설정을 읽습니다 (로드 전이면 기본값).
+///
+/// DB 경로는 `load`와 같이 설정 디렉토리 기준으로 바꿉니다.
+pub fn file_config() -> Result<PebbleConfig> {
+    let path = CONFIG_PATH
+        .lock()
+        .map_err(|e| anyhow::anyhow!("Failed to acquire config path lock: {}", e))?
+        .clone();
+
+    match path {
+        Some(path) => {
+            let mut config = PebbleConfig::load_from_file(&path)?;
+            if let Some(dir) = path.parent() {
+                config.resolve_db_path(dir);
+            }
+            Ok(config)
+        }
+        None => Ok(PebbleConfig::default()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_default_config_is_valid() {
+        assert!(PebbleConfig::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_chunk_size() {
+        let config = PebbleConfig {
+            chunk_size: 10,
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_change_detection_uses_innermost_root() {
+        let root = std::env::temp_dir().join("pebble_media");
+        let detection = |path: &Path, mode| RootChangeDetection { root: path.to_string_lossy().to_string(), mode };
+        let config = PebbleConfig {
+            change_detection: vec![
+                detection(&root, ChangeDetection::SizeMtime),
+                detection(&root.join("projects"), ChangeDetection::ContentHash),
+            ],
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+
+        let mode = |path: PathBuf| config.change_detection_for(&path.to_string_lossy());
+        assert_eq!(mode(root.join("2019").join("a.mov")), ChangeDetection::SizeMtime);
+        assert_eq!(mode(root.join("projects").join("edit.prproj")), ChangeDetection::ContentHash);
+        assert_eq!(mode(std::env::temp_dir().join("pebble_media_other").join("a.mov")), ChangeDetection::ContentHash);
+
+        let duplicate = PebbleConfig {
+            change_detection: vec![detection(&root, ChangeDetection::SizeMtime), detection(&root, ChangeDetection::ContentHash)],
+            ..Default::default()
+        };
+        assert!(duplicate.validate().is_err());
+    }
+
+    #[test]
+    fn test_save_and_load_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(CONFIG_FILE_NAME);
+
+        let config = PebbleConfig {
+            transfer_port: 40123,
+            download_dir: Some("/tmp/pebble_downloads".to_string()),
+            max_transfer_rate: 1_000_000,
+            ..Default::default()
+        };
+        config.save_to_file(&path).unwrap();
+
+        let loaded = PebbleConfig::load_from_file(&path).unwrap();
+        assert_eq!(loaded, config);
+    }
+
+    #[test]
+    fn test_missing_fields_use_defaults() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(CONFIG_FILE_NAME);
+        fs::write(&path, r#"{ "transfer_port": 40000 }"#).unwrap();
+
+        let loaded = PebbleConfig::load_from_file(&path).unwrap();
+        assert_eq!(loaded.transfer_port, 40000);
+        assert_eq!(loaded.chunk_size, CHUNK_SIZE as u64);
+    }
+
+    #[test]
+    fn test_relative_db_path_resolves_under_config_dir() {
+        let dir = TempDir::new().unwrap();
+        let mut config = PebbleConfig::default();
+        config.resolve_db_path(dir.path());
+        assert_eq!(config.db_path, dir.path().join("pebble.db").to_string_lossy());
+
+        // 이미 절대 경로면 그대로
+        let absolute = config.db_path.clone();
+        config.resolve_db_path(Path::new("/elsewhere"));
+        assert_eq!(config.db_path, absolute);
+    }
+
+    #[test]
+    fn test_resolved_db_path_is_not_persisted() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(CONFIG_FILE_NAME);
+        PebbleConfig::default().save_to_file(&path).unwrap();
+
+        let mut config = PebbleConfig::load_from_file(&path).unwrap();
+        config.resolve_db_path(dir.path());
+        config.transfer_port = 40123;
+        config.stored_form(&path).save_to_file(&path).unwrap();
+
+        let saved = PebbleConfig::load_from_file(&path).unwrap();
+        assert_eq!(saved.db_path, "pebble.db");
+        assert_eq!(saved.transfer_port, 40123);
+
+        // 사용자가 바꾼 DB 경로는 그대로 저장
+        config.db_path = dir.path().join("other.db").to_string_lossy().to_string();
+        config.stored_form(&path).save_to_file(&path).unwrap();
+        assert_eq!(PebbleConfig::load_from_file(&path).unwrap().db_path, config.db_path);
+    }
+}
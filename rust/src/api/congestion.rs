@@ -0,0 +1,255 @@
+//! 혼잡 적응형 전송 창 (Adaptive Send Window)
+//!
+//! 묶음 ACK를 쓰는 송신에서 ACK 없이 보낼 수 있는 청크 수(창)를 ACK 왕복 시간(RTT)과
+//! 손실(재전송)에 맞춰 조정합니다. 붐비는 Wi-Fi에서는 공유기 대기열이 차면서 RTT가 늘어나므로,
+//! 대기열 지연이 목표를 넘으면 창을 줄이고 그렇지 않으면 늘립니다 (지연 기반 AIMD).
+//!
+//! # Process Flow
+//! 1. 시작 창은 `INITIAL_WINDOW_BATCHES`개 묶음, 첫 혼잡 신호 전까지는 확인된 만큼 늘림 (slow start)
+//! 2. 청크를 보낼 때 `on_send`로 보낸 시각 기록
+//! 3. ACK를 받으면 `on_ack`가 마지막으로 확인된 청크의 RTT를 측정
+//!    → 지금까지의 최소 RTT보다 평균 RTT가 `TARGET_QUEUE_DELAY` 이상 길면 창을 3/4로 줄임
+//!    → 아니면 창 하나만큼 확인될 때마다 묶음 하나씩 늘림
+//! 4. 수신측이 청크를 다시 요청하면 `on_loss`로 창을 절반으로 줄임
+//! 5. 줄이는 동작은 RTT마다 한 번만 적용 (같은 혼잡에 여러 번 반응하지 않도록)
+//!
+//! 창은 항상 묶음 크기(`ack_interval`)의 배수이며, 한 묶음보다 작아지지 않습니다.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// 시작 창 크기 (묶음 수)
+pub const INITIAL_WINDOW_BATCHES: u64 = 2;
+
+/// 창에 담을 수 있는 최대 바이트 (시작 창보다 작으면 시작 창을 최대로 사용)
+pub const MAX_IN_FLIGHT_BYTES: u64 = 64 * 1024 * 1024;
+
+/// 허용하는 대기열 지연 (평균 RTT - 최소 RTT)
+pub const TARGET_QUEUE_DELAY: Duration = Duration::from_millis(50);
+
+/// 평균 RTT 가중치 (새 측정값이 1/8 반영)
+const RTT_SMOOTHING: u32 = 8;
+
+/// 송신 창
+#[derive(Debug)]
+pub struct AdaptiveWindow {
+    /// 묶음 ACK 간격 (창 조정 단위)
+    batch: u64,
+    max: u64,
+    window: u64,
+    slow_start: bool,
+    min_rtt: Option<Duration>,
+    srtt: Option<Duration>,
+    /// 마지막으로 창을 늘린 뒤 확인된 청크 수
+    acked_since_growth: u64,
+    last_decrease: Option<Instant>,
+    /// 아직 확인되지 않은 청크와 보낸 시각
+    in_flight: VecDeque<(u64, Instant)>,
+}
+
+impl AdaptiveWindow {
+    /// 송신 창을 만듭니다.
+    ///
+    /// # Arguments
+    /// * `ack_interval` - 수신측의 묶음 ACK 간격 (1이면 청크마다 ACK를 기다리는 고정 창)
+    /// * `chunk_size` - 청크 크기 (최대 창 계산용)
+    pub fn new(ack_interval: u64, chunk_size: u64) -> Self {
+        let batch = ack_interval.max(1);
+        let (window, max) = if batch > 1 {
+            let initial = batch * INITIAL_WINDOW_BATCHES;
+            let max = MAX_IN_FLIGHT_BYTES / chunk_size.max(1) / batch * batch;
+            (initial, max.max(initial))
+        } else {
+            (1, 1)
+        };
+
+        Self {
+            batch,
+            max,
+            window,
+            slow_start: true,
+            min_rtt: None,
+            srtt: None,
+            acked_since_growth: 0,
+            last_decrease: None,
+            in_flight: VecDeque::new(),
+        }
+    }
+
+    /// 현재 창 크기 (청크 수)
+    pub fn size(&self) -> u64 {
+        self.window
+    }
+
+    /// 평균 ACK RTT
+    pub fn smoothed_rtt(&self) -> Option<Duration> {
+        self.srtt
+    }
+
+    /// 청크를 보낸 시각을 기록합니다.
+    pub fn on_send(&mut self, chunk_index: u64, now: Instant) {
+        if self.max > 1 {
+            self.in_flight.push_back((chunk_index, now));
+        }
+    }
+
+    /// ACK로 확인된 청크 수를 반영합니다.
+    ///
+    /// # Arguments
+    /// * `acked` - 지금까지 확인된 청크 수 (이 값 미만의 청크가 모두 확인됨)
+    pub fn on_ack(&mut self, acked: u64, now: Instant) {
+        let mut newly_acked = 0;
+        let mut last_sent = None;
+        while let Some(&(chunk_index, sent_at)) = self.in_flight.front() {
+            if chunk_index >= acked {
+                break;
+            }
+            self.in_flight.pop_front();
+            newly_acked += 1;
+            last_sent = Some(sent_at);
+        }
+        let Some(sent_at) = last_sent else {
+            return;
+        };
+
+        let rtt = now.saturating_duration_since(sent_at);
+        let min_rtt = self.min_rtt.map_or(rtt, |min| min.min(rtt));
+        let srtt = self.srtt.map_or(rtt, |srtt| (srtt * (RTT_SMOOTHING - 1) + rtt) / RTT_SMOOTHING);
+        self.min_rtt = Some(min_rtt);
+        self.srtt = Some(srtt);
+
+        if srtt.saturating_sub(min_rtt) > TARGET_QUEUE_DELAY {
+            self.decrease(now, 3, 4);
+            return;
+        }
+
+        self.acked_since_growth += newly_acked;
+        if self.slow_start {
+            // 확인된 만큼 늘려 RTT마다 두 배
+            if self.acked_since_growth >= self.batch {
+                self.grow(self.acked_since_growth / self.batch * self.batch);
+            }
+        } else if self.acked_since_growth >= self.window {
+            self.grow(self.batch);
+        }
+    }
+
+    /// 손실(재전송)을 반영합니다.
+    ///
+    /// # Arguments
+    /// * `resend_from` - 다시 보낼 첫 청크 (이후 청크의 보낸 시각은 버림)
+    pub fn on_loss(&mut self, resend_from: u64, now: Instant) {
+        self.in_flight.retain(|(chunk_index, _)| *chunk_index < resend_from);
+        self.decrease(now, 1, 2);
+    }
+
+    fn grow(&mut self, by: u64) {
+        self.acked_since_growth = 0;
+        let window = (self.window + by).min(self.max);
+        if window != self.window {
+            tracing::trace!("Send window grew to {} chunks", window);
+            self.window = window;
+        }
+    }
+
+    fn decrease(&mut self, now: Instant, numerator: u64, denominator: u64) {
+        self.slow_start = false;
+        self.acked_since_growth = 0;
+
+        let interval = self.srtt.unwrap_or_default();
+        if self.last_decrease.is_some_and(|last| now.saturating_duration_since(last) < interval) {
+            return;
+        }
+        self.last_decrease = Some(now);
+
+        let window = (self.window * numerator / denominator / self.batch * self.batch).max(self.batch);
+        if window != self.window {
+            tracing::debug!("Send window shrank to {} chunks (srtt {:?}, min rtt {:?})", window, self.srtt, self.min_rtt);
+            self.window = window;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MB: u64 = 1024 * 1024;
+
+    /// 창 하나를 보내고 `rtt` 뒤에 모두 확인됐다고 반영합니다.
+    fn round_trip(window: &mut AdaptiveWindow, next: &mut u64, now: &mut Instant, rtt: Duration) {
+        let size = window.size();
+        for _ in 0..size {
+            window.on_send(*next, *now);
+            *next += 1;
+        }
+        *now += rtt;
+        window.on_ack(*next, *now);
+    }
+
+    #[test]
+    fn test_window_grows_until_queue_delay_then_backs_off() {
+        let mut window = AdaptiveWindow::new(8, MB);
+        assert_eq!(window.size(), 16);
+
+        let mut next = 0;
+        let mut now = Instant::now();
+        for _ in 0..10 {
+            round_trip(&mut window, &mut next, &mut now, Duration::from_millis(5));
+        }
+        // 최대 창은 MAX_IN_FLIGHT_BYTES로 제한
+        assert_eq!(window.size(), 64);
+
+        // 대기열이 차서 RTT가 늘어나면 줄이되, RTT마다 한 번만
+        for _ in 0..30 {
+            round_trip(&mut window, &mut next, &mut now, Duration::from_millis(300));
+        }
+        assert_eq!(window.size(), 8);
+        assert_eq!(window.size() % 8, 0);
+
+        // 혼잡이 풀리면 다시 천천히 늘어남 (묶음 하나씩)
+        for _ in 0..18 {
+            round_trip(&mut window, &mut next, &mut now, Duration::from_millis(5));
+        }
+        let recovered = window.size();
+        assert!((9..64).contains(&recovered), "{}", recovered);
+        round_trip(&mut window, &mut next, &mut now, Duration::from_millis(5));
+        assert_eq!(window.size(), recovered + 8);
+    }
+
+    #[test]
+    fn test_loss_halves_window_once_per_rtt() {
+        let mut window = AdaptiveWindow::new(8, MB);
+        let mut next = 0;
+        let mut now = Instant::now();
+        for _ in 0..3 {
+            round_trip(&mut window, &mut next, &mut now, Duration::from_millis(10));
+        }
+        assert_eq!(window.size(), 64);
+
+        window.on_loss(next, now);
+        window.on_loss(next, now + Duration::from_millis(1));
+        assert_eq!(window.size(), 32);
+
+        // 다시 보낼 청크 이후의 기록은 버리고 새로 보낸 시각으로 측정
+        window.on_send(next, now);
+        window.on_send(next + 1, now);
+        window.on_loss(next + 1, now + Duration::from_millis(20));
+        assert_eq!(window.size(), 16);
+        assert_eq!(window.in_flight.len(), 1);
+    }
+
+    #[test]
+    fn test_legacy_receiver_uses_fixed_window() {
+        let mut window = AdaptiveWindow::new(1, MB);
+        let mut next = 0;
+        let mut now = Instant::now();
+        for _ in 0..5 {
+            round_trip(&mut window, &mut next, &mut now, Duration::from_millis(1));
+        }
+        assert_eq!(window.size(), 1);
+
+        // 큰 청크여도 시작 창보다 작아지지 않음
+        assert_eq!(AdaptiveWindow::new(8, 16 * MB).size(), 16);
+    }
+}
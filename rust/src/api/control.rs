@@ -0,0 +1,439 @@
+//! 로컬 제어 API (Local HTTP Control API)
+//!
+//! 헤드리스 데몬(`pebbled serve --control-addr`)을 스크립트, Home Assistant 같은 도구가
+//! FRB 바인딩 없이 제어할 수 있도록 localhost에 JSON HTTP API를 엽니다.
+//!
+//! | 요청 | 동작 |
+//! |------|------|
+//! | `GET /v1/status` | 서비스 상태 (`ServiceStatus`) |
+//! | `GET /v1/devices` | 발견된 기기 목록 |
+//! | `GET /v1/transfers` | 진행 중인 전송 목록 |
+//! | `POST /v1/transfers` | 송신 대기열에 추가 (`{"device_id", "file_path", "priority"?}`) |
+//! | `POST /v1/transfers/{id}/cancel` | 진행 중인 전송 취소 |
+//! | `GET /v1/queue` | 송신 대기열 |
+//! | `DELETE /v1/queue/{id}` | 대기열 항목 삭제 |
+//! | `GET /v1/events` | 이벤트와 진행률 WebSocket 피드 (`feed`) |
+//!
+//! # Process Flow
+//! 1. `load_or_create_token`이 데이터 디렉토리의 토큰 파일(소유자만 읽기)을 읽거나 생성
+//! 2. `start_listener`가 루프백 주소에만 바인딩 (다른 주소는 거부)
+//! 3. 요청마다 `Authorization: Bearer <token>`을 확인 (없거나 다르면 401)
+//!    → 헤더를 지정할 수 없는 브라우저 WebSocket을 위해 `/v1/events`만 `?token=`도 허용
+//! 4. 경로에 맞는 API를 실행하고 JSON으로 응답 (에러는 `PebbleError` 종류에 맞는 상태 코드)
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use super::discovery;
+use super::error::PebbleError;
+use super::feed;
+use super::peers;
+use super::priority::TransferPriority;
+use super::registry;
+use super::sendqueue;
+use super::service;
+
+/// 데이터 디렉토리의 토큰 파일 이름
+pub const TOKEN_FILE_NAME: &str = "control.token";
+
+/// 요청 헤더 최대 크기
+const MAX_HEADER_SIZE: usize = 8 * 1024;
+
+/// 요청 본문 최대 크기
+const MAX_BODY_SIZE: usize = 64 * 1024;
+
+/// 이벤트 피드 경로
+const EVENTS_PATH: &str = "/v1/events";
+
+/// 요청을 다 읽을 때까지 기다리는 시간
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// `POST /v1/transfers` 본문
+#[derive(Debug, Deserialize)]
+struct SendRequest {
+    device_id: String,
+    file_path: String,
+    #[serde(default)]
+    priority: TransferPriority,
+}
+
+/// 에러 응답 본문
+#[derive(Serialize)]
+struct ErrorBody {
+    error: String,
+}
+
+/// 읽은 HTTP 요청
+#[derive(Debug, Default)]
+struct Request {
+    method: String,
+    path: String,
+    authorization: Option<String>,
+    /// `?token=` 값 (이벤트 피드에만 사용)
+    query_token: Option<String>,
+    /// WebSocket 업그레이드 요청의 `Sec-WebSocket-Key`
+    websocket_key: Option<String>,
+    body: Vec<u8>,
+}
+
+/// 데이터 디렉토리의 제어 토큰을 읽거나 새로 생성합니다.
+///
+/// # Security
+/// - 토큰 파일은 소유자만 읽을 수 있도록 만듦 (Unix)
+pub fn load_or_create_token(data_dir: &str) -> Result<String> {
+    let path = Path::new(data_dir).join(TOKEN_FILE_NAME);
+    if let Ok(existing) = fs::read_to_string(&path) {
+        let existing = existing.trim();
+        if existing.len() >= 32 {
+            return Ok(existing.to_string());
+        }
+        tracing::warn!("Invalid control token file, regenerating: {}", path.display());
+    }
+
+    // 권한은 새로 만들 때만 적용되므로 잘못된 파일은 지우고 소유자만 읽을 수 있게 새로 만듦
+    // (쓴 뒤에 권한을 바꾸면 그 사이에 다른 사용자가 읽을 수 있음)
+    match fs::remove_file(&path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            return Err(e).with_context(|| format!("Failed to remove control token: {}", path.display()));
+        }
+        _ => {}
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+    options
+        .open(&path)
+        .and_then(|mut file| file.write_all(token.as_bytes()))
+        .with_context(|| format!("Failed to write control token: {}", path.display()))?;
+
+    Ok(token)
+}
+
+/// 토큰이 맞는지 확인합니다.
+///
+/// # Security
+/// - 해시를 비교하여 토큰을 한 글자씩 추측하는 타이밍 공격 방지 (`blake3::Hash`는 상수 시간 비교)
+fn authorized(request: &Request, token: &str) -> bool {
+    let query_token = request.query_token.as_deref().filter(|_| request.path == EVENTS_PATH);
+    request
+        .authorization
+        .as_deref()
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or(query_token)
+        .is_some_and(|given| blake3::hash(given.trim().as_bytes()) == blake3::hash(token.as_bytes()))
+}
+
+/// 에러 종류에 맞는 HTTP 상태 코드
+fn error_status(error: &PebbleError) -> u16 {
+    match error {
+        PebbleError::InvalidArgument { .. } | PebbleError::Protocol { .. } => 400,
+        PebbleError::Rejected { .. } => 403,
+        PebbleError::NotFound { .. } => 404,
+        PebbleError::QuotaExceeded { .. } => 429,
+        _ => 500,
+    }
+}
+
+fn json<T: Serialize>(status: u16, value: &T) -> Result<(u16, String), PebbleError> {
+    serde_json::to_string(value)
+        .map(|body| (status, body))
+        .map_err(|e| PebbleError::internal(e.to_string()))
+}
+
+/// 요청을 API에 연결합니다.
+///
+/// # Returns
+/// * 상태 코드와 JSON 본문
+fn route(method: &str, path: &str, body: &[u8]) -> Result<(u16, String), PebbleError> {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match (method, segments.as_slice()) {
+        ("GET", ["v1", "status"]) => json(200, &service::status()),
+        ("GET", ["v1", "devices"]) => json(200, &peers::with_labels(discovery::get_discovered_devices()?)?),
+        ("GET", ["v1", "transfers"]) => json(200, &registry::global().list()),
+        ("POST", ["v1", "transfers"]) => {
+            let request: SendRequest = serde_json::from_slice(body)
+                .map_err(|e| PebbleError::invalid_argument(format!("Invalid request body: {}", e)))?;
+            json(202, &sendqueue::enqueue(&request.device_id, &request.file_path, request.priority)?)
+        }
+        ("POST", ["v1", "transfers", id, "cancel"]) => {
+            registry::global().cancel(id)?;
+            json(200, &serde_json::json!({ "cancelled": id }))
+        }
+        ("GET", ["v1", "queue"]) => json(200, &sendqueue::list()?),
+        ("DELETE", ["v1", "queue", id]) => match sendqueue::remove(id)? {
+            true => json(200, &serde_json::json!({ "removed": id })),
+            false => Err(PebbleError::not_found(format!("Queued send {}", id))),
+        },
+        ("GET", ["v1", "events"]) => Err(PebbleError::invalid_argument("WebSocket upgrade required")),
+        _ => Err(PebbleError::not_found(format!("{} {}", method, path))),
+    }
+}
+
+/// 요청 하나를 처리합니다.
+fn respond(request: &Request, token: &str) -> (u16, String) {
+    if !authorized(request, token) {
+        return (401, serde_json::to_string(&ErrorBody { error: "Missing or invalid bearer token".to_string() }).unwrap_or_default());
+    }
+
+    route(&request.method, &request.path, &request.body).unwrap_or_else(|e| {
+        let status = error_status(&e);
+        if status == 500 {
+            tracing::warn!("Control request {} {} failed: {}", request.method, request.path, e);
+        }
+        (status, serde_json::to_string(&ErrorBody { error: e.to_string() }).unwrap_or_default())
+    })
+}
+
+/// 요청 줄, 헤더, 본문을 읽습니다.
+async fn read_request(stream: &mut TcpStream) -> Result<Request> {
+    let mut data = Vec::new();
+    let mut buf = [0u8; 4096];
+    let header_end = loop {
+        if let Some(pos) = data.windows(4).position(|window| window == b"\r\n\r\n") {
+            break pos;
+        }
+        let n = tokio::time::timeout(READ_TIMEOUT, stream.read(&mut buf)).await??;
+        if n == 0 || data.len() + n > MAX_HEADER_SIZE + MAX_BODY_SIZE {
+            anyhow::bail!("Incomplete or oversized request");
+        }
+        data.extend_from_slice(&buf[..n]);
+        if data.len() > MAX_HEADER_SIZE && !data.windows(4).any(|window| window == b"\r\n\r\n") {
+            anyhow::bail!("Oversized request header");
+        }
+    };
+
+    let head = String::from_utf8_lossy(&data[..header_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let target = request_line.next().unwrap_or_default();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let mut request = Request {
+        method,
+        path: path.to_string(),
+        query_token: query.split('&').find_map(|pair| pair.strip_prefix("token=")).map(str::to_string),
+        ..Default::default()
+    };
+
+    let mut content_length = 0;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => content_length = value.trim().parse().context("Invalid Content-Length")?,
+            "authorization" => request.authorization = Some(value.trim().to_string()),
+            "sec-websocket-key" => request.websocket_key = Some(value.trim().to_string()),
+            _ => {}
+        }
+    }
+    if content_length > MAX_BODY_SIZE {
+        anyhow::bail!("Request body too large");
+    }
+
+    let mut body = data.split_off(header_end + 4);
+    while body.len() < content_length {
+        let n = tokio::time::timeout(READ_TIMEOUT, stream.read(&mut buf)).await??;
+        if n == 0 {
+            anyhow::bail!("Incomplete request body");
+        }
+        body.extend_from_slice(&buf[..n]);
+    }
+    body.truncate(content_length);
+    request.body = body;
+
+    Ok(request)
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        429 => "Too Many Requests",
+        _ => "Internal Server Error",
+    }
+}
+
+/// 요청 하나를 읽고 응답한 뒤 연결을 닫습니다.
+async fn handle_http(mut stream: TcpStream, token: &str) -> Result<()> {
+    let (status, body) = match read_request(&mut stream).await {
+        Ok(request) => {
+            if let Some(key) = request.websocket_key.as_deref() {
+                if request.method == "GET" && request.path == EVENTS_PATH && authorized(&request, token) {
+                    return feed::serve(stream, key).await;
+                }
+            }
+            respond(&request, token)
+        }
+        Err(e) => (400, serde_json::to_string(&ErrorBody { error: format!("{:#}", e) })?),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, reason(status), body.len(), body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+
+    Ok(())
+}
+
+/// 실행 중인 제어 API 리스너
+pub struct ControlListener {
+    /// 바인딩된 주소 (포트 0이면 할당된 포트)
+    pub addr: SocketAddr,
+
+    /// 연결 수락 루프 취소 토큰
+    cancel: CancellationToken,
+}
+
+impl ControlListener {
+    /// 새 연결 수락을 멈추고 포트를 닫습니다 (처리 중인 요청은 끝까지 응답).
+    pub fn stop(&self) {
+        self.cancel.cancel();
+    }
+}
+
+/// 제어 API 리스너를 시작합니다.
+///
+/// # Security
+/// - 루프백 주소에만 바인딩할 수 있으며, 모든 요청에 토큰이 필요함
+///
+/// # Returns
+/// * 바인딩된 주소와 중지 핸들
+pub async fn start_listener(addr: SocketAddr, token: String) -> Result<ControlListener> {
+    if !addr.ip().is_loopback() {
+        return Err(PebbleError::invalid_argument(format!("Control API must bind to a loopback address, got {}", addr)).into());
+    }
+
+    let listener = TcpListener::bind(addr).await
+        .with_context(|| format!("Failed to bind control listener on {}", addr))?;
+    let local_addr = listener.local_addr()?;
+
+    tracing::info!("Control API listening on http://{}/v1", local_addr);
+
+    let cancel = CancellationToken::new();
+    let cancelled = cancel.clone();
+    let token: std::sync::Arc<str> = token.into();
+    tokio::spawn(async move {
+        loop {
+            let accepted = tokio::select! {
+                _ = cancelled.cancelled() => break,
+                accepted = listener.accept() => accepted,
+            };
+            match accepted {
+                Ok((stream, peer_addr)) => {
+                    let token = std::sync::Arc::clone(&token);
+                    tokio::spawn(async move {
+                        if let Err(e) = handle_http(stream, &token).await {
+                            tracing::debug!("Control request from {} failed: {:#}", peer_addr, e);
+                        }
+                    });
+                }
+                Err(e) => tracing::warn!("Error accepting control connection: {}", e),
+            }
+        }
+        tracing::info!("Control API on {} stopped", local_addr);
+    });
+
+    Ok(ControlListener { addr: local_addr, cancel })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn call(addr: SocketAddr, request: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[test]
+    fn test_route_errors_map_to_status() {
+        crate::loopback::use_temp_environment();
+
+        assert_eq!(route("GET", "/v1/queue", b"").unwrap().0, 200);
+        assert_eq!(error_status(&route("GET", "/v1/unknown", b"").unwrap_err()), 404);
+        assert_eq!(error_status(&route("POST", "/v1/transfers", b"{\"device_id\": 1}").unwrap_err()), 400);
+
+        let missing = br#"{"device_id": "control-device", "file_path": "/definitely/missing.bin"}"#;
+        assert_eq!(error_status(&route("POST", "/v1/transfers", missing).unwrap_err()), 404);
+        assert_eq!(error_status(&route("DELETE", "/v1/queue/no-such-id", b"").unwrap_err()), 404);
+    }
+
+    #[tokio::test]
+    async fn test_listener_requires_token_and_loopback() {
+        crate::loopback::use_temp_environment();
+        assert!(start_listener("0.0.0.0:0".parse().unwrap(), "t".repeat(32)).await.is_err());
+
+        let token = load_or_create_token(tempfile::TempDir::new().unwrap().path().to_str().unwrap()).unwrap();
+        let listener = start_listener("127.0.0.1:0".parse().unwrap(), token.clone()).await.unwrap();
+        let addr = listener.addr;
+
+        let denied = call(addr, "GET /v1/devices HTTP/1.1\r\nHost: localhost\r\n\r\n").await;
+        assert!(denied.starts_with("HTTP/1.1 401"));
+        let wrong = call(addr, "GET /v1/devices HTTP/1.1\r\nAuthorization: Bearer nope\r\n\r\n").await;
+        assert!(wrong.starts_with("HTTP/1.1 401"));
+
+        let devices = call(addr, &format!("GET /v1/devices HTTP/1.1\r\nAuthorization: Bearer {}\r\n\r\n", token)).await;
+        assert!(devices.starts_with("HTTP/1.1 200"), "{}", devices);
+
+        let body = r#"{"device_id":"x"}"#;
+        let bad = call(addr, &format!(
+            "POST /v1/transfers HTTP/1.1\r\nAuthorization: Bearer {}\r\nContent-Length: {}\r\n\r\n{}",
+            token, body.len(), body
+        )).await;
+        assert!(bad.starts_with("HTTP/1.1 400"), "{}", bad);
+
+        // 중지하면 포트가 닫힘
+        listener.stop();
+        let closed = tokio::time::timeout(Duration::from_secs(5), async {
+            while TcpStream::connect(addr).await.is_ok() {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await;
+        assert!(closed.is_ok());
+    }
+
+    #[test]
+    fn test_token_file_is_created_owner_only() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let data_dir = dir.path().to_str().unwrap();
+        let path = dir.path().join(TOKEN_FILE_NAME);
+
+        // 잘못된 토큰 파일은 새로 만듦
+        fs::write(&path, "short").unwrap();
+        let token = load_or_create_token(data_dir).unwrap();
+        assert_eq!(token.len(), 64);
+        assert_eq!(load_or_create_token(data_dir).unwrap(), token);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+    }
+}
@@ -1,17 +1,220 @@
 use rusqlite::{params, Connection, Result};
+use serde::{Deserialize, Serialize};
 use walkdir::WalkDir;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 
+use super::config;
+use super::integrity;
+use super::error::PebbleError;
+use super::filename;
+use super::paths;
+use super::operations::{self, OperationKind};
+use super::scan;
+use super::locked;
+use super::util::unix_now;
+use super::volume;
+
+/// 파일 동기화 상태
+///
+/// DB에는 상태 이름(`name`)과 실패 사유가 따로 저장되며, 이름은 트리거로 검증됩니다.
+///
+/// # 상태 전이
+/// - 어느 상태에서든 Pending(변경 감지, 재시도), Deleted, Conflicted로 갈 수 있음
+/// - Pending/Failed → Queued → Transferring → Synced 또는 Failed
+/// - Pending, Conflicted(해결됨)에서 바로 Synced로 표시할 수 있음
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SyncStatus {
+    /// 변경이 감지되어 전송을 기다림
+    #[default]
+    Pending,
+    /// 전송 대기열에 들어감
+    Queued,
+    /// 전송 중
+    Transferring,
+    /// 상대 기기와 같은 내용
+    Synced,
+    /// 전송에 실패함
+    Failed { reason: String },
+    /// 디스크에서 삭제됨
+    Deleted,
+    /// 양쪽에서 모두 바뀌어 사용자의 선택이 필요함
+    Conflicted,
+}
+
+impl SyncStatus {
+    /// DB에 저장되는 상태 이름 목록
+    pub const NAMES: [&'static str; 7] =
+        ["Pending", "Queued", "Transferring", "Synced", "Failed", "Deleted", "Conflicted"];
+
+    /// DB에 저장되는 상태 이름
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Pending => "Pending",
+            Self::Queued => "Queued",
+            Self::Transferring => "Transferring",
+            Self::Synced => "Synced",
+            Self::Failed { .. } => "Failed",
+            Self::Deleted => "Deleted",
+            Self::Conflicted => "Conflicted",
+        }
+    }
+
+    /// 실패 사유 (Failed가 아니면 None)
+    pub fn reason(&self) -> Option<&str> {
+        match self {
+            Self::Failed { reason } => Some(reason),
+            _ => None,
+        }
+    }
+
+    /// DB에 저장된 이름과 실패 사유로 상태를 만듭니다.
+    pub fn from_parts(name: &str, reason: Option<String>) -> Option<Self> {
+        Some(match name {
+            "Pending" => Self::Pending,
+            "Queued" => Self::Queued,
+            "Transferring" => Self::Transferring,
+            "Synced" => Self::Synced,
+            "Failed" => Self::Failed { reason: reason.unwrap_or_default() },
+            "Deleted" => Self::Deleted,
+            "Conflicted" => Self::Conflicted,
+            _ => return None,
+        })
+    }
+
+    /// 이 상태에서 `next`로 바꿀 수 있는지 확인합니다.
+    pub fn can_transition_to(&self, next: &SyncStatus) -> bool {
+        use SyncStatus::*;
+
+        matches!(
+            (self, next),
+            (_, Pending | Deleted | Conflicted)
+                | (Pending | Failed { .. }, Queued)
+                | (Pending | Queued | Transferring | Failed { .. }, Transferring)
+                | (Pending | Queued | Transferring | Failed { .. }, Failed { .. })
+                | (Pending | Transferring | Synced | Conflicted, Synced)
+        )
+    }
+}
+
+impl std::fmt::Display for SyncStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Failed { reason } if !reason.is_empty() => write!(f, "Failed ({})", reason),
+            other => f.write_str(other.name()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileMetadata {
     pub path: String,
     pub last_modified: i64,
     pub file_hash: String,
-    pub sync_status: String,
+    pub sync_status: SyncStatus,
+    pub file_size: i64,
+
+    /// 동기화 상태가 마지막으로 바뀐 시각 (Unix timestamp)
+    pub status_changed_at: i64,
+}
+
+impl FileMetadata {
+    /// 새 파일 정보를 만듭니다. 상태 변경 시각은 현재 시각입니다.
+    pub fn new(path: String, last_modified: i64, file_hash: String, sync_status: SyncStatus, file_size: i64) -> Self {
+        Self {
+            path,
+            last_modified,
+            file_hash,
+            sync_status,
+            file_size,
+            status_changed_at: unix_now(),
+        }
+    }
+
+    /// SELECT_FILE_COLUMNS 순서로 조회한 행을 변환합니다.
+    fn from_row(row: &rusqlite::Row) -> Result<Self> {
+        let name: String = row.get(3)?;
+        let sync_status = SyncStatus::from_parts(&name, row.get(5)?).ok_or_else(|| {
+            rusqlite::Error::FromSqlConversionFailure(
+                3,
+                rusqlite::types::Type::Text,
+                format!("Unknown sync_status: {}", name).into(),
+            )
+        })?;
+
+        Ok(Self {
+            path: row.get(0)?,
+            last_modified: row.get(1)?,
+            file_hash: row.get(2)?,
+            sync_status,
+            file_size: row.get(4)?,
+            status_changed_at: row.get(6)?,
+        })
+    }
+}
+
+/// files 테이블 조회 시 사용하는 컬럼 목록 (FileMetadata::from_row와 순서 일치)
+const SELECT_FILE_COLUMNS: &str = "path, last_modified, file_hash, sync_status, file_size, sync_error, status_changed_at";
+
+/// 파일 목록 정렬 기준
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum FileSortKey {
+    Path,
+    LastModified,
+    Size,
+    Status,
+}
+
+impl FileSortKey {
+    fn column(&self) -> &'static str {
+        match self {
+            Self::Path => "path",
+            Self::LastModified => "last_modified",
+            Self::Size => "file_size",
+            Self::Status => "sync_status",
+        }
+    }
+}
+
+/// 페이지 단위 파일 조회 조건
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileQuery {
+    /// 동기화 상태 필터 (None이면 전체, 상태 이름만 비교하므로 Failed의 사유는 무시)
+    pub sync_status: Option<SyncStatus>,
+
+    /// 경로 접두사 필터 (특정 폴더 아래만 조회)
+    pub path_prefix: Option<String>,
+
+    /// 정렬 기준
+    pub sort_by: FileSortKey,
+
+    /// 내림차순 정렬 여부
+    pub descending: bool,
+
+    /// 건너뛸 행 수
+    pub offset: u32,
+
+    /// 최대 행 수
+    pub limit: u32,
+}
+
+/// 조회 결과 한 페이지
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilePage {
+    pub files: Vec<FileMetadata>,
+
+    /// 필터에 맞는 전체 행 수
+    pub total_count: u64,
+}
+
+/// 설정된 경로(`PebbleConfig::db_path`)의 DB 연결을 엽니다.
+pub fn open_connection() -> Result<Connection> {
+    Connection::open(config::current().db_path)
 }
 
 // DB 연결 및 테이블 초기화
 pub fn init_db() -> Result<()> {
-    let conn = Connection::open("pebble.db")?;
+    let conn = open_connection()?;
     conn.execute(
         "CREATE TABLE IF NOT EXISTS files (
             id INTEGER PRIMARY KEY,
@@ -22,27 +225,495 @@ pub fn init_db() -> Result<()> {
         )",
         [],
     )?;
+    ensure_column(&conn, "files", "file_size", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(&conn, "files", "sync_error", "TEXT")?;
+    ensure_column(&conn, "files", "status_changed_at", "INTEGER NOT NULL DEFAULT 0")?;
+    init_sync_status(&conn)?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_files_hash ON files (file_hash)", [])?;
+    normalize_stored_paths(&conn)?;
+    init_path_keys(&conn)?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS transfer_state (
+            transfer_id TEXT PRIMARY KEY,
+            file_path TEXT NOT NULL,
+            file_size INTEGER NOT NULL,
+            total_chunks INTEGER NOT NULL,
+            received_chunks INTEGER NOT NULL,
+            transfer_status TEXT NOT NULL,
+            peer_device_id TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS text_messages (
+            message_id TEXT PRIMARY KEY,
+            direction TEXT NOT NULL,
+            peer_device_id TEXT,
+            peer_address TEXT NOT NULL,
+            text TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS peer_usage (
+            peer_id TEXT PRIMARY KEY,
+            bytes_received INTEGER NOT NULL DEFAULT 0,
+            files_received INTEGER NOT NULL DEFAULT 0,
+            last_received_at INTEGER NOT NULL DEFAULT 0,
+            quota_bytes INTEGER
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS peers (
+            device_id TEXT PRIMARY KEY,
+            device_name TEXT NOT NULL,
+            last_address TEXT NOT NULL,
+            last_seen INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS transfer_history (
+            transfer_id TEXT PRIMARY KEY,
+            direction TEXT NOT NULL,
+            peer TEXT NOT NULL,
+            file_path TEXT NOT NULL,
+            total_bytes INTEGER NOT NULL,
+            bytes_transferred INTEGER NOT NULL,
+            status TEXT NOT NULL,
+            error TEXT,
+            started_at INTEGER NOT NULL,
+            finished_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    ensure_column(&conn, "transfer_history", "thumbnail_path", "TEXT")?;
+    ensure_column(&conn, "transfer_history", "receipt", "TEXT")?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS connection_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            peer_address TEXT NOT NULL,
+            peer_ip TEXT NOT NULL,
+            device_id TEXT,
+            listener TEXT,
+            outcome TEXT NOT NULL,
+            error TEXT,
+            requests INTEGER NOT NULL,
+            bytes_received INTEGER NOT NULL,
+            bytes_sent INTEGER NOT NULL,
+            opened_at INTEGER NOT NULL,
+            closed_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_connection_log_opened ON connection_log(opened_at)",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL,
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS send_queue (
+            id TEXT PRIMARY KEY,
+            device_id TEXT NOT NULL,
+            file_path TEXT NOT NULL,
+            priority TEXT NOT NULL,
+            queued_at INTEGER NOT NULL,
+            session TEXT NOT NULL,
+            attempts INTEGER NOT NULL DEFAULT 0,
+            last_attempt_at INTEGER,
+            last_error TEXT
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS staged_files (
+            id TEXT PRIMARY KEY,
+            transfer_id TEXT NOT NULL,
+            file_path TEXT NOT NULL,
+            file_size INTEGER NOT NULL,
+            sender_device_id TEXT,
+            staged_at INTEGER NOT NULL,
+            completed_at INTEGER,
+            download_dir TEXT,
+            sealed_path TEXT NOT NULL,
+            key BLOB NOT NULL,
+            nonce BLOB NOT NULL,
+            file_hash TEXT
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS peer_probes (
+            device_id TEXT NOT NULL,
+            address TEXT NOT NULL,
+            reachable INTEGER NOT NULL,
+            connect_ms REAL,
+            tls_handshake_ms REAL,
+            pings_sent INTEGER NOT NULL,
+            pings_lost INTEGER NOT NULL,
+            avg_rtt_ms REAL,
+            min_rtt_ms REAL,
+            max_rtt_ms REAL,
+            error TEXT,
+            probed_at INTEGER NOT NULL,
+            PRIMARY KEY (device_id, address)
+        )",
+        [],
+    )?;
+    ensure_column(&conn, "peer_usage", "bytes_sent", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(&conn, "peer_usage", "files_sent", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(&conn, "peers", "pinned_fingerprint", "TEXT")?;
+    ensure_column(&conn, "peers", "last_port", "INTEGER")?;
+    ensure_column(&conn, "peers", "nickname", "TEXT")?;
+    ensure_column(&conn, "peers", "color", "TEXT")?;
+    ensure_column(&conn, "peers", "tags", "TEXT")?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS peer_addresses (
+            device_id TEXT NOT NULL,
+            address TEXT NOT NULL,
+            last_seen INTEGER NOT NULL,
+            PRIMARY KEY (device_id, address)
+        )",
+        [],
+    )?;
+    // 주소 목록 이전의 기록은 마지막 주소 하나로 채움
+    conn.execute(
+        "INSERT OR IGNORE INTO peer_addresses (device_id, address, last_seen)
+         SELECT device_id, last_address, last_seen FROM peers",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS shares (
+            root_path TEXT NOT NULL,
+            peer_device_id TEXT NOT NULL,
+            permission TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            PRIMARY KEY (root_path, peer_device_id)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS sync_pairs (
+            pair_id INTEGER PRIMARY KEY AUTOINCREMENT,
+            local_root TEXT NOT NULL,
+            peer_device_id TEXT NOT NULL,
+            remote_root TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            UNIQUE (local_root, peer_device_id, remote_root)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS bandwidth_usage (
+            day TEXT NOT NULL,
+            peer_id TEXT NOT NULL,
+            bytes_sent INTEGER NOT NULL DEFAULT 0,
+            bytes_received INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY (day, peer_id)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS volume_roots (
+            volume_id TEXT PRIMARY KEY,
+            root_path TEXT NOT NULL,
+            updated_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS revoked_devices (
+            revocation_id TEXT PRIMARY KEY,
+            device_id TEXT NOT NULL,
+            fingerprint TEXT,
+            issued_by TEXT NOT NULL,
+            issued_at INTEGER NOT NULL,
+            signature TEXT NOT NULL,
+            revoked_at INTEGER NOT NULL,
+            restored_at INTEGER
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS clipboard_peers (
+            device_id TEXT PRIMARY KEY,
+            enabled_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS revocation_deliveries (
+            revocation_id TEXT NOT NULL,
+            peer_device_id TEXT NOT NULL,
+            delivered_at INTEGER NOT NULL,
+            PRIMARY KEY (revocation_id, peer_device_id)
+        )",
+        [],
+    )?;
+    ensure_column(&conn, "revoked_devices", "certificate", "TEXT NOT NULL DEFAULT ''")?;
+    ensure_column(&conn, "revoked_devices", "signature_scheme", "TEXT NOT NULL DEFAULT ''")?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS wipe_requests (
+            revocation_id TEXT PRIMARY KEY,
+            device_id TEXT NOT NULL,
+            fingerprint TEXT,
+            issued_by TEXT NOT NULL,
+            issued_at INTEGER NOT NULL,
+            certificate TEXT NOT NULL,
+            signature_scheme TEXT NOT NULL,
+            signature TEXT NOT NULL,
+            received_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+    ensure_column(&conn, "sync_pairs", "local_seq", "INTEGER")?;
+    ensure_column(&conn, "sync_pairs", "remote_journal_id", "TEXT")?;
+    ensure_column(&conn, "sync_pairs", "remote_seq", "INTEGER")?;
+    init_change_journal(&conn)?;
     Ok(())
 }
 
-// 파일 정보 저장 또는 업데이트 (Upsert)
+/// 파일 변경 기록(change journal)을 준비합니다.
+///
+/// `files`의 매니페스트 항목(수정 시간, 해시, 크기, 삭제 여부)이 바뀔 때마다 트리거가
+/// 경로에 새 일련번호를 붙입니다 (경로마다 마지막 변경만 남음). 트리거 안의 `OR REPLACE`는
+/// 바깥 문장(UPSERT)의 충돌 처리로 덮어쓰이므로 지운 뒤 다시 넣습니다. 일련번호는 DB마다 따로
+/// 증가하므로, DB가 새로 만들어진 것을 상대가 알 수 있도록 무작위 `journal_id`를 함께 둡니다.
+/// 기록이 생기기 전부터 있던 파일은 변경 기록이 없으며, 처음 동기화는 항상 전체 매니페스트로 합니다.
+fn init_change_journal(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS file_changes (
+            seq INTEGER PRIMARY KEY AUTOINCREMENT,
+            path TEXT NOT NULL UNIQUE
+        )",
+        [],
+    )?;
+    conn.execute("CREATE TABLE IF NOT EXISTS change_journal (journal_id TEXT NOT NULL)", [])?;
+    conn.execute(
+        "INSERT INTO change_journal (journal_id) SELECT ?1 WHERE NOT EXISTS (SELECT 1 FROM change_journal)",
+        params![uuid::Uuid::new_v4().to_string()],
+    )?;
+
+    conn.execute_batch(
+        "CREATE TRIGGER IF NOT EXISTS files_journal_insert AFTER INSERT ON files
+         BEGIN
+            DELETE FROM file_changes WHERE path = NEW.path;
+            INSERT INTO file_changes (path) VALUES (NEW.path);
+         END;
+         CREATE TRIGGER IF NOT EXISTS files_journal_update AFTER UPDATE ON files
+         WHEN OLD.path IS NOT NEW.path
+            OR OLD.last_modified IS NOT NEW.last_modified
+            OR OLD.file_hash IS NOT NEW.file_hash
+            OR OLD.file_size IS NOT NEW.file_size
+            OR (OLD.sync_status = 'Deleted') IS NOT (NEW.sync_status = 'Deleted')
+         BEGIN
+            DELETE FROM file_changes WHERE path = NEW.path;
+            INSERT INTO file_changes (path) VALUES (NEW.path);
+         END;",
+    )?;
+    Ok(())
+}
+
+/// 변경 기록의 식별자와 마지막 일련번호
+pub fn journal_head() -> Result<(String, i64)> {
+    let conn = open_connection()?;
+    conn.query_row(
+        "SELECT journal_id, (SELECT COALESCE(MAX(seq), 0) FROM file_changes) FROM change_journal",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+}
+
+/// 기존 DB에 컬럼이 없으면 추가합니다 (스키마 마이그레이션).
+fn ensure_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|name| name.ok())
+        .any(|name| name == column);
+
+    if !exists {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+        tracing::info!("Added column {}.{}", table, column);
+    }
+
+    Ok(())
+}
+
+/// 동기화 상태 값을 검증하는 트리거를 만들고 기존 행을 정리합니다.
+///
+/// - 알 수 없는 상태(자유 문자열 시절의 값)는 Pending으로 바꿈
+/// - 이전 실행에서 끝나지 못한 Queued, Transferring은 Pending으로 되돌림
+fn init_sync_status(conn: &Connection) -> Result<()> {
+    let names = SyncStatus::NAMES.iter().map(|name| format!("'{}'", name)).collect::<Vec<_>>().join(", ");
+
+    let reset = conn.execute(
+        &format!(
+            "UPDATE files SET sync_status = 'Pending', sync_error = NULL
+             WHERE sync_status NOT IN ({}) OR sync_status IN ('Queued', 'Transferring')",
+            names
+        ),
+        [],
+    )?;
+    if reset > 0 {
+        tracing::info!("Reset {} file(s) to Pending", reset);
+    }
+
+    for (event, trigger) in [("INSERT", "files_sync_status_insert"), ("UPDATE OF sync_status", "files_sync_status_update")] {
+        conn.execute(
+            &format!(
+                "CREATE TRIGGER IF NOT EXISTS {} BEFORE {} ON files
+                 WHEN NEW.sync_status NOT IN ({})
+                 BEGIN SELECT RAISE(ABORT, 'invalid sync_status'); END",
+                trigger, event, names
+            ),
+            [],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// 경로 정규화 이전에 저장된 행을 정규 형식으로 옮깁니다 (스키마 버전 1).
+///
+/// 같은 파일이 다른 표기로 여러 행에 저장되어 있으면 이미 정규 형식인 행을 남깁니다.
+fn normalize_stored_paths(conn: &Connection) -> Result<()> {
+    let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    if version >= 1 {
+        return Ok(());
+    }
+
+    let stored: Vec<String> = conn
+        .prepare("SELECT path FROM files")?
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<_>>()?;
+
+    let mut migrated = 0;
+    for path in stored {
+        let normalized = paths::normalize(&path);
+        if normalized != path {
+            conn.execute("UPDATE OR IGNORE files SET path = ?1 WHERE path = ?2", params![normalized, path])?;
+            conn.execute("DELETE FROM files WHERE path = ?1", params![path])?;
+            migrated += 1;
+        }
+    }
+
+    conn.execute_batch("PRAGMA user_version = 1")?;
+
+    if migrated > 0 {
+        tracing::info!("Normalized {} stored file paths", migrated);
+    }
+
+    Ok(())
+}
+
+/// 비교 키(`paths::comparison_key`) 컬럼을 만들고 키가 없는 행을 채웁니다.
+///
+/// 경로는 디스크의 표기(NFD일 수 있음) 그대로 두고, 다른 기기와의 비교와 매니페스트 정렬에는 키를 씁니다.
+fn init_path_keys(conn: &Connection) -> Result<()> {
+    ensure_column(conn, "files", "path_key", "TEXT")?;
+    conn.execute("CREATE INDEX IF NOT EXISTS idx_files_path_key ON files (path_key)", [])?;
+
+    let missing: Vec<String> = conn
+        .prepare("SELECT path FROM files WHERE path_key IS NULL")?
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<_>>()?;
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    conn.execute_batch("BEGIN")?;
+    for path in &missing {
+        conn.execute("UPDATE files SET path_key = ?1 WHERE path = ?2", params![paths::comparison_key(path), path])?;
+    }
+    conn.execute_batch("COMMIT")?;
+
+    tracing::info!("Computed comparison keys for {} stored file paths", missing.len());
+    Ok(())
+}
+
+/// 경로에 해당하는 행의 저장된 경로를 찾습니다.
+///
+/// 표기가 정확히 같은 행이 없으면 비교 키가 같은 행(대소문자나 유니코드 정규화 형식만 다른 경로)을 찾고,
+/// 그런 행도 없으면 주어진 경로를 그대로 반환합니다.
+fn stored_path(conn: &Connection, path: &str) -> Result<String> {
+    let found = conn.query_row(
+        "SELECT path FROM files WHERE path = ?1 OR path_key = ?2 ORDER BY path = ?1 DESC LIMIT 1",
+        params![path, paths::comparison_key(path)],
+        |row| row.get(0),
+    );
+    match found {
+        Ok(stored) => Ok(stored),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(path.to_string()),
+        Err(e) => Err(e),
+    }
+}
+
+/// 파일 정보 저장 또는 업데이트 (Upsert)
+///
+/// 경로는 `paths::normalize`로 정규화된 형식으로, 대소문자는 디스크의 표기 그대로 저장됩니다.
+/// 대소문자를 구분하지 않는 파일 시스템에서 표기만 다른 행이 있으면 그 행을 새 표기로 바꿉니다.
+/// 상태 변경 시각은 상태가 실제로 바뀔 때만 `file.status_changed_at`으로 갱신됩니다.
 pub fn upsert_file(file: FileMetadata) -> Result<()> {
-    let conn = Connection::open("pebble.db")?;
+    let path = paths::normalize(&file.path);
+    let conn = open_connection()?;
+    if filename::CASE_INSENSITIVE_FS {
+        let stored = stored_path(&conn, &path)?;
+        if stored != path {
+            conn.execute("UPDATE files SET path = ?1 WHERE path = ?2", params![path, stored])?;
+        }
+    }
     conn.execute(
-        "INSERT INTO files (path, last_modified, file_hash, sync_status)
-         VALUES (?1, ?2, ?3, ?4)
+        "INSERT INTO files (path, last_modified, file_hash, sync_status, file_size, sync_error, status_changed_at, path_key)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
          ON CONFLICT(path) DO UPDATE SET
             last_modified = excluded.last_modified,
             file_hash = excluded.file_hash,
-            sync_status = excluded.sync_status",
-        params![file.path, file.last_modified, file.file_hash, file.sync_status],
+            status_changed_at = CASE
+                WHEN sync_status IS excluded.sync_status AND sync_error IS excluded.sync_error THEN status_changed_at
+                ELSE excluded.status_changed_at END,
+            sync_status = excluded.sync_status,
+            sync_error = excluded.sync_error,
+            file_size = excluded.file_size",
+        params![
+            path,
+            file.last_modified,
+            file.file_hash,
+            file.sync_status.name(),
+            file.file_size,
+            file.sync_status.reason(),
+            file.status_changed_at,
+            paths::comparison_key(&path)
+        ],
     )?;
     Ok(())
 }
 
+/// 같은 내용 해시와 크기를 가진 파일을 조회합니다 (전송 중복 검사용).
+pub fn find_files_by_hash(file_hash: &str, file_size: i64) -> Result<Vec<FileMetadata>> {
+    let conn = open_connection()?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM files WHERE file_hash = ?1 AND file_size = ?2",
+        SELECT_FILE_COLUMNS
+    ))?;
+    let rows = stmt.query_map(params![file_hash, file_size], FileMetadata::from_row)?;
+    rows.collect()
+}
+
 // 동기화가 필요한 파일 목록 가져오기
 pub fn get_pending_files() -> Result<Vec<String>> {
-    let conn = Connection::open("pebble.db")?;
+    let conn = open_connection()?;
     let mut stmt = conn.prepare("SELECT path FROM files WHERE sync_status = 'Pending'")?;
     let rows = stmt.query_map([], |row| row.get(0))?;
 
@@ -53,53 +724,50 @@ pub fn get_pending_files() -> Result<Vec<String>> {
     Ok(paths)
 }
 
-pub fn scan_directory(base_path: &str) -> Result<()> {
-    for entry in WalkDir::new(base_path).into_iter().filter_map(|e| e.ok()) {
-        let path = entry.path();
-
-        if path.is_file() {
-            let metadata = fs::metadata(path).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
-            let last_modified = metadata.modified()
-                .unwrap_or(std::time::SystemTime::now())
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs() as i64;
-
-            let path_str = path.to_string_lossy().to_string();
-
-            let file_hash = "initial_scan".to_string();
-
-            upsert_file(FileMetadata {
-                path: path_str,
-                last_modified,
-                file_hash,
-                sync_status: "Synced".to_string(), // 초기 스캔 시에는 일단 Synced로 간주
-            })?;
-        }
-    }
-    Ok(())
+/// 감시 폴더의 파일을 DB에 기록합니다 (진행 상황 없이 `scan::run` 수행).
+pub fn scan_directory(base_path: &str) -> anyhow::Result<()> {
+    scan::run(base_path, |_| {}).map(|_| ())
 }
 
 /// 특정 파일의 sync_status를 업데이트합니다.
 ///
+/// 상태가 바뀌면 `status_changed_at`을 현재 시각으로 기록합니다.
+///
 /// # Arguments
 /// * `path` - 업데이트할 파일의 경로
-/// * `status` - 새로운 동기화 상태 (예: "Pending", "Synced", "Failed")
+/// * `status` - 새로운 동기화 상태
+///
+/// # Returns
+/// * DB에 없는 파일이면 `QueryReturnedNoRows`
+/// * 허용되지 않는 상태 전이(`SyncStatus::can_transition_to`)면 `PebbleError::InvalidArgument`
 ///
 /// # Security Notes
 /// - SQL Injection 방지를 위해 파라미터화된 쿼리 사용
-/// - 트랜잭션 없이 단일 업데이트만 수행하여 성능 최적화
-pub fn update_sync_status(path: &str, status: &str) -> Result<()> {
-    let conn = Connection::open("pebble.db")?;
-    let rows_affected = conn.execute(
-        "UPDATE files SET sync_status = ?1 WHERE path = ?2",
-        params![status, path],
-    )?;
+pub fn update_sync_status(path: &str, status: &SyncStatus) -> anyhow::Result<()> {
+    let conn = open_connection()?;
+    let path = stored_path(&conn, &paths::normalize(path))?;
 
-    if rows_affected == 0 {
-        return Err(rusqlite::Error::QueryReturnedNoRows);
+    let current = conn.query_row(
+        "SELECT sync_status, sync_error FROM files WHERE path = ?1",
+        params![path],
+        |row| Ok(SyncStatus::from_parts(&row.get::<_, String>(0)?, row.get(1)?)),
+    )?;
+    if let Some(current) = current {
+        if !current.can_transition_to(status) {
+            return Err(PebbleError::invalid_argument(format!(
+                "Cannot change sync status of {} from {} to {}", path, current.name(), status.name()
+            )).into());
+        }
+        if current == *status {
+            return Ok(());
+        }
     }
 
+    conn.execute(
+        "UPDATE files SET sync_status = ?1, sync_error = ?2, status_changed_at = ?3 WHERE path = ?4",
+        params![status.name(), status.reason(), unix_now(), path],
+    )?;
+
     Ok(())
 }
 
@@ -114,11 +782,16 @@ pub fn update_sync_status(path: &str, status: &str) -> Result<()> {
 /// # Security Notes
 /// - 원자적 업데이트로 데이터 무결성 보장
 /// - 파라미터화된 쿼리로 SQL Injection 방지
-pub fn update_file_metadata(path: &str, last_modified: i64, file_hash: &str, sync_status: &str) -> Result<()> {
-    let conn = Connection::open("pebble.db")?;
+pub fn update_file_metadata(path: &str, last_modified: i64, file_hash: &str, sync_status: &SyncStatus) -> Result<()> {
+    let conn = open_connection()?;
+    let path = stored_path(&conn, &paths::normalize(path))?;
     conn.execute(
-        "UPDATE files SET last_modified = ?1, file_hash = ?2, sync_status = ?3 WHERE path = ?4",
-        params![last_modified, file_hash, sync_status, path],
+        "UPDATE files SET last_modified = ?1, file_hash = ?2,
+            status_changed_at = CASE
+                WHEN sync_status IS ?3 AND sync_error IS ?4 THEN status_changed_at ELSE ?5 END,
+            sync_status = ?3, sync_error = ?4
+         WHERE path = ?6",
+        params![last_modified, file_hash, sync_status.name(), sync_status.reason(), unix_now(), path],
     )?;
     Ok(())
 }
@@ -131,21 +804,626 @@ pub fn update_file_metadata(path: &str, last_modified: i64, file_hash: &str, syn
 /// # Returns
 /// * `Option<FileMetadata>` - 파일이 DB에 존재하면 Some, 없으면 None
 pub fn get_file_metadata(path: &str) -> Result<Option<FileMetadata>> {
-    let conn = Connection::open("pebble.db")?;
-    let mut stmt = conn.prepare(
-        "SELECT path, last_modified, file_hash, sync_status FROM files WHERE path = ?1"
-    )?;
+    let path = paths::normalize(path);
+    let conn = open_connection()?;
+    // 표기가 정확히 같은 행이 없으면 유니코드 정규화 형식만 다른 행을 찾음
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM files WHERE path = ?1 OR path_key = ?2 ORDER BY path = ?1 DESC LIMIT 1",
+        SELECT_FILE_COLUMNS
+    ))?;
 
-    let mut rows = stmt.query(params![path])?;
+    let mut rows = stmt.query(params![path, paths::comparison_key(&path)])?;
 
     if let Some(row) = rows.next()? {
-        Ok(Some(FileMetadata {
-            path: row.get(0)?,
-            last_modified: row.get(1)?,
-            file_hash: row.get(2)?,
-            sync_status: row.get(3)?,
-        }))
+        Ok(Some(FileMetadata::from_row(row)?))
     } else {
         Ok(None)
     }
-}
\ No newline at end of file
+}
+
+/// 동기화 대기 중인 파일의 전체 정보를 가져옵니다.
+pub fn get_pending_file_details() -> Result<Vec<FileMetadata>> {
+    let conn = open_connection()?;
+    let mut stmt = conn.prepare(
+        &format!("SELECT {} FROM files WHERE sync_status = 'Pending' ORDER BY path", SELECT_FILE_COLUMNS)
+    )?;
+    let rows = stmt.query_map([], FileMetadata::from_row)?;
+    rows.collect()
+}
+
+/// 조건에 맞는 파일 목록을 페이지 단위로 가져옵니다.
+///
+/// # Security Notes
+/// - 필터 값은 파라미터로 바인딩하고, 정렬 컬럼은 enum에서만 선택하여 SQL Injection 방지
+pub fn query_files(query: &FileQuery) -> Result<FilePage> {
+    let conn = open_connection()?;
+
+    let mut conditions = Vec::new();
+    let mut values: Vec<String> = Vec::new();
+
+    if let Some(ref status) = query.sync_status {
+        values.push(status.name().to_string());
+        conditions.push(format!("sync_status = ?{}", values.len()));
+    }
+    if let Some(ref prefix) = query.path_prefix {
+        values.push(format!("{}%", escape_like(prefix)));
+        conditions.push(format!("path LIKE ?{} ESCAPE '\\'", values.len()));
+    }
+
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+
+    let total_count: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM files {}", where_clause),
+        rusqlite::params_from_iter(values.iter()),
+        |row| row.get(0),
+    )?;
+
+    let sql = format!(
+        "SELECT {} FROM files {} ORDER BY {} {}, path LIMIT {} OFFSET {}",
+        SELECT_FILE_COLUMNS,
+        where_clause,
+        query.sort_by.column(),
+        if query.descending { "DESC" } else { "ASC" },
+        query.limit,
+        query.offset,
+    );
+    let mut stmt = conn.prepare(&sql)?;
+    let files = stmt
+        .query_map(rusqlite::params_from_iter(values.iter()), FileMetadata::from_row)?
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(FilePage {
+        files,
+        total_count: total_count as u64,
+    })
+}
+
+/// LIKE 패턴의 특수 문자(%, _, \)를 이스케이프합니다.
+fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// 테이블의 경로 컬럼에서 `old_root`(와 그 아래) 경로를 `new_root` 기준으로 바꿉니다.
+///
+/// 새 경로에 이미 행이 있으면 옮겨지는 행으로 대체합니다 (`UPDATE OR REPLACE`).
+///
+/// # Returns
+/// * 바뀐 행 수
+pub(crate) fn rebase_paths(conn: &Connection, table: &str, column: &str, old_root: &str, new_root: &str) -> Result<usize> {
+    let prefix = format!("{}{}", old_root.trim_end_matches(['/', '\\']), std::path::MAIN_SEPARATOR);
+    conn.execute(
+        &format!(
+            "UPDATE OR REPLACE {table} SET {column} = ?2 || substr({column}, length(?1) + 1)
+             WHERE {column} = ?1 OR {column} LIKE ?3 ESCAPE '\\'",
+            table = table,
+            column = column
+        ),
+        params![old_root, new_root, format!("{}%", escape_like(&prefix))],
+    )
+}
+
+/// 특정 디렉토리 아래에 있는 모든 파일 정보를 가져옵니다.
+///
+/// # Arguments
+/// * `root` - 조회할 루트 디렉토리 경로
+pub fn list_files_under(root: &str) -> Result<Vec<FileMetadata>> {
+    let root = paths::normalize(root);
+    let conn = open_connection()?;
+    let prefix = format!("{}{}", root.trim_end_matches(['/', '\\']), std::path::MAIN_SEPARATOR);
+    let mut stmt = conn.prepare(
+        &format!("SELECT {} FROM files WHERE path LIKE ?1 ESCAPE '\\'", SELECT_FILE_COLUMNS)
+    )?;
+
+    let rows = stmt.query_map(params![format!("{}%", escape_like(&prefix))], FileMetadata::from_row)?;
+
+    rows.collect()
+}
+
+/// 같은 내용의 파일 묶음 (`find_duplicates`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub file_hash: String,
+    pub file_size: i64,
+
+    /// 같은 내용의 파일 경로 (경로 순, 2개 이상)
+    pub paths: Vec<String>,
+
+    /// 하나만 남기면 확보되는 바이트
+    pub wasted_bytes: i64,
+}
+
+/// `root` 아래에서 저장된 해시가 같은 파일들을 묶습니다.
+///
+/// 크기가 같은 파일이 있는 경우에만 해시를 비교하며, 삭제된 파일과 빈 파일,
+/// 내용을 해시하지 않은 파일(초기 스캔, 크기와 수정 시각만 쓰는 루트)은 제외합니다.
+/// 디스크를 다시 읽지 않으므로 마지막 스캔 이후 바뀐 파일은 이전 해시로 비교됩니다.
+///
+/// # Returns
+/// * 확보할 수 있는 바이트가 큰 순서의 묶음 목록
+pub fn find_duplicates(root: &str) -> Result<Vec<DuplicateGroup>> {
+    let root = paths::normalize(root);
+    let conn = open_connection()?;
+    let prefix = format!("{}{}", root.trim_end_matches(['/', '\\']), std::path::MAIN_SEPARATOR);
+    let mut stmt = conn.prepare(
+        "WITH candidates AS (
+            SELECT path, path_key, file_hash, file_size FROM files
+            WHERE path LIKE ?1 ESCAPE '\\' AND sync_status != 'Deleted' AND file_size > 0 AND file_hash != ''
+              AND file_hash != ?2 AND file_hash NOT LIKE ?3
+         )
+         SELECT path, file_hash, file_size FROM candidates
+         WHERE file_size IN (SELECT file_size FROM candidates GROUP BY file_size HAVING COUNT(*) > 1)
+         ORDER BY file_size, file_hash, path_key",
+    )?;
+    let metadata_hashes = format!("{}%", integrity::METADATA_HASH_PREFIX);
+    let rows = stmt.query_map(params![format!("{}%", escape_like(&prefix)), scan::INITIAL_SCAN_HASH, metadata_hashes], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, i64>(2)?))
+    })?;
+
+    let mut groups: Vec<DuplicateGroup> = Vec::new();
+    for row in rows {
+        let (path, file_hash, file_size) = row?;
+        match groups.last_mut() {
+            Some(group) if group.file_hash == file_hash && group.file_size == file_size => group.paths.push(path),
+            _ => groups.push(DuplicateGroup { file_hash, file_size, paths: vec![path], wasted_bytes: 0 }),
+        }
+    }
+
+    groups.retain(|group| group.paths.len() > 1);
+    for group in &mut groups {
+        group.wasted_bytes = group.file_size * (group.paths.len() as i64 - 1);
+    }
+    groups.sort_by(|a, b| b.wasted_bytes.cmp(&a.wasted_bytes).then_with(|| a.paths[0].cmp(&b.paths[0])));
+    Ok(groups)
+}
+
+/// `root` 아래 파일을 비교 키(`paths::comparison_key`) 순으로 `limit`개씩 조회합니다 (매니페스트 페이지).
+///
+/// 키는 구분자가 '/'이고 NFC로 정규화되어 있어 어느 플랫폼에서나 매니페스트의 상대 경로 순서와 같습니다.
+///
+/// # Arguments
+/// * `since` - 변경 일련번호가 이보다 큰 파일만 조회 (None이면 모든 파일)
+/// * `after` - 이전 페이지의 마지막 경로 (None이면 처음부터)
+/// * `limit` - 한 번에 가져올 최대 개수
+pub fn list_files_page(root: &str, since: Option<i64>, after: Option<&str>, limit: usize) -> Result<Vec<FileMetadata>> {
+    let root = paths::normalize(root);
+    let conn = open_connection()?;
+    let prefix = format!("{}{}", root.trim_end_matches(['/', '\\']), std::path::MAIN_SEPARATOR);
+    let after = after.map(paths::comparison_key);
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM files WHERE path LIKE ?1 ESCAPE '\\' AND (?2 IS NULL OR path_key > ?2)
+         AND (?4 IS NULL OR path IN (SELECT path FROM file_changes WHERE seq > ?4))
+         ORDER BY path_key LIMIT ?3",
+        SELECT_FILE_COLUMNS
+    ))?;
+
+    let rows = stmt.query_map(
+        params![format!("{}%", escape_like(&prefix)), after, limit as i64, since],
+        FileMetadata::from_row,
+    )?;
+
+    rows.collect()
+}
+
+/// 재조정 스캔 결과
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReconcileReport {
+    /// DB에 없던 새 파일 수
+    pub added: u32,
+
+    /// 수정 시간이 바뀐 파일 수
+    pub modified: u32,
+
+    /// 디스크에서 사라진 파일 수
+    pub deleted: u32,
+}
+
+/// 디스크 상태와 DB를 비교하여 감시가 중단된 동안의 변경 사항을 반영합니다.
+///
+/// `scan_directory`와 달리 기존 해시와 상태를 덮어쓰지 않고,
+/// 변경된 파일만 해시를 다시 계산하여 Pending으로 표시합니다.
+/// 크기와 수정 시각만 쓰는 루트(`ChangeDetection::SizeMtime`)의 파일은 해시하지 않습니다.
+///
+/// # Process Flow
+/// 1. 디스크의 파일을 순회하며 DB에 없거나 수정 시간 또는 크기가 다른 파일을 Pending으로 기록
+/// 2. DB에는 있지만 디스크에 없는 파일을 Deleted로 표시
+#[tracing::instrument(name = "sync", skip_all, fields(root = %root, phase = "reconcile"))]
+pub fn reconcile_directory(root: &str) -> anyhow::Result<ReconcileReport> {
+    // 루트가 사라졌을 때 모든 파일을 Deleted로 표시하지 않도록 먼저 확인
+    if !paths::long_path(root).is_dir() {
+        return Err(PebbleError::not_found(format!("Directory {}", root)).into());
+    }
+
+    let known: HashMap<String, FileMetadata> = list_files_under(root)?
+        .into_iter()
+        .map(|f| (f.path.clone(), f))
+        .collect();
+
+    let mut report = ReconcileReport::default();
+    let mut seen = HashSet::new();
+    let config = config::current();
+
+    for entry in WalkDir::new(paths::long_path(root)).into_iter().filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if !path.is_file() || volume::is_marker(path) || locked::is_staging(path) {
+            continue;
+        }
+
+        let path_str = paths::normalize(path);
+        seen.insert(path_str.clone());
+
+        let metadata = fs::metadata(path)?;
+        let last_modified = metadata
+            .modified()
+            .unwrap_or(std::time::SystemTime::now())
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
+
+        let existing = known.get(&path_str);
+        let changed = match existing {
+            None => true,
+            Some(f) => {
+                f.last_modified != last_modified
+                    || f.file_size != metadata.len() as i64
+                    || f.sync_status == SyncStatus::Deleted
+            }
+        };
+        if !changed {
+            continue;
+        }
+
+        let mode = config.change_detection_for(&path_str);
+        let file_hash = integrity::change_hash(path, mode, metadata.len(), last_modified)?;
+        if existing.is_none() {
+            report.added += 1;
+        } else {
+            report.modified += 1;
+        }
+
+        upsert_file(FileMetadata::new(path_str, last_modified, file_hash, SyncStatus::Pending, metadata.len() as i64))?;
+    }
+
+    for (path, file) in &known {
+        if !seen.contains(path) && file.sync_status != SyncStatus::Deleted && !paths::long_path(path).exists() {
+            update_sync_status(path, &SyncStatus::Deleted)?;
+            report.deleted += 1;
+        }
+    }
+
+    tracing::info!(
+        "Reconciled {}: {} added, {} modified, {} deleted",
+        root, report.added, report.modified, report.deleted
+    );
+
+    Ok(report)
+}
+
+/// 무결성 검증 결과
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VerifyReport {
+    /// 해시를 다시 계산한 파일 수
+    pub checked: u32,
+
+    /// 수정 시간은 같지만 해시가 저장된 값과 다른 파일 (비트 부패 의심)
+    pub mismatched: Vec<String>,
+
+    /// DB에는 있지만 디스크에서 사라진 파일
+    pub missing: Vec<String>,
+
+    /// 마지막 기록 이후 수정되어 비교하지 않은 파일 (재조정 스캔 대상)
+    pub modified: Vec<String>,
+
+    /// 크기와 수정 시각만 기록하는 루트라 해시를 비교하지 않은 파일 수
+    pub metadata_only: u32,
+
+    /// 초기 스캔 뒤 아직 해시하지 않아 비교할 해시가 없는 파일 수
+    pub unhashed: u32,
+}
+
+impl VerifyReport {
+    /// 손상되거나 사라진 파일이 없는지 확인합니다.
+    pub fn is_clean(&self) -> bool {
+        self.mismatched.is_empty() && self.missing.is_empty()
+    }
+}
+
+/// 동기화 루트 아래의 모든 파일을 다시 해시하여 저장된 해시와 비교합니다.
+///
+/// DB를 수정하지 않는 읽기 전용 검사입니다. 수정 시간이 바뀐 파일은 정상적인
+/// 변경이므로 불일치로 보고하지 않고 `modified`로 분류합니다. 초기 스캔의 자리 표시 해시만
+/// 있는 파일은 비교할 값이 없으므로 `unhashed`로 셉니다.
+/// 검사 중에는 작업(`operations`)으로 등록되어 진행률을 조회하고 취소할 수 있습니다.
+///
+/// # Arguments
+/// * `root` - 검사할 동기화 루트 디렉토리
+#[tracing::instrument(name = "sync", skip_all, fields(root = %root, phase = "verify"))]
+pub fn verify_tree(root: &str) -> anyhow::Result<VerifyReport> {
+    let operation = operations::begin(OperationKind::Verify, root);
+    let mut report = VerifyReport::default();
+
+    let files = list_files_under(root)?;
+    let total = files.len() as u64;
+    for (index, file) in files.into_iter().enumerate() {
+        operation.check()?;
+        operation.set_progress(index as u64, Some(total));
+
+        if file.sync_status == SyncStatus::Deleted {
+            continue;
+        }
+
+        let path = paths::long_path(&file.path);
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) if metadata.is_file() => metadata,
+            _ => {
+                report.missing.push(file.path);
+                continue;
+            }
+        };
+
+        let last_modified = metadata
+            .modified()
+            .unwrap_or(std::time::SystemTime::now())
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
+        if last_modified != file.last_modified {
+            report.modified.push(file.path);
+            continue;
+        }
+
+        // 해시하지 않는 루트의 파일은 크기만 비교 (크기가 바뀌었으면 재조정 대상)
+        if integrity::is_metadata_hash(&file.file_hash) {
+            if metadata.len() as i64 == file.file_size {
+                report.metadata_only += 1;
+            } else {
+                report.modified.push(file.path);
+            }
+            continue;
+        }
+
+        if file.file_hash == scan::INITIAL_SCAN_HASH {
+            report.unhashed += 1;
+            continue;
+        }
+
+        let file_hash = integrity::calculate_file_hash(&path)?;
+        report.checked += 1;
+        if file_hash != file.file_hash {
+            tracing::warn!("Hash mismatch: {} (stored {}, actual {})", file.path, file.file_hash, file_hash);
+            report.mismatched.push(file.path);
+        }
+    }
+
+    tracing::info!(
+        "Verified {}: {} checked, {} mismatched, {} missing, {} modified, {} unhashed",
+        root, report.checked, report.mismatched.len(), report.missing.len(), report.modified.len(), report.unhashed
+    );
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loopback;
+
+    #[test]
+    fn test_sync_status_transitions_and_validation() {
+        let root = loopback::use_temp_environment().join("sync-status");
+        let path = root.join("a.txt").to_string_lossy().to_string();
+        upsert_file(FileMetadata::new(path.clone(), 1, "hash".to_string(), SyncStatus::Pending, 3)).unwrap();
+
+        update_sync_status(&path, &SyncStatus::Transferring).unwrap();
+        let failed = SyncStatus::Failed { reason: "Connection refused".to_string() };
+        update_sync_status(&path, &failed).unwrap();
+        let stored = get_file_metadata(&path).unwrap().unwrap();
+        assert_eq!(stored.sync_status, failed);
+        assert!(stored.status_changed_at > 0);
+
+        // Failed에서 바로 Synced로는 갈 수 없음
+        let err = update_sync_status(&path, &SyncStatus::Synced).unwrap_err();
+        assert!(matches!(err.downcast_ref::<PebbleError>(), Some(PebbleError::InvalidArgument { .. })));
+        assert!(update_sync_status(&root.join("missing.txt").to_string_lossy(), &SyncStatus::Pending).is_err());
+
+        // 트리거가 알 수 없는 상태 값을 거부
+        let conn = open_connection().unwrap();
+        let raw = conn.execute("UPDATE files SET sync_status = 'Modified' WHERE path = ?1", params![paths::normalize(&path)]);
+        assert!(raw.is_err());
+
+        let query = FileQuery {
+            sync_status: Some(SyncStatus::Failed { reason: String::new() }),
+            path_prefix: Some(paths::normalize(&root)),
+            sort_by: FileSortKey::Path,
+            descending: false,
+            offset: 0,
+            limit: 10,
+        };
+        assert_eq!(query_files(&query).unwrap().total_count, 1);
+    }
+
+    #[test]
+    fn test_updates_find_rows_stored_in_another_form() {
+        let root = loopback::use_temp_environment().join("stored-form");
+        // 디스크에 NFD로 저장된 "한글.txt"
+        let nfd = paths::normalize(root.join("\u{1112}\u{1161}\u{11AB}\u{1100}\u{1173}\u{11AF}.txt"));
+        let nfc = paths::normalize(root.join("한글.txt"));
+        upsert_file(FileMetadata::new(nfd.clone(), 1, "hash".to_string(), SyncStatus::Pending, 3)).unwrap();
+
+        update_sync_status(&nfc, &SyncStatus::Transferring).unwrap();
+        update_file_metadata(&nfc, 2, "new-hash", &SyncStatus::Synced).unwrap();
+
+        let stored = get_file_metadata(&nfd).unwrap().unwrap();
+        assert_eq!(stored.path, nfd);
+        assert_eq!((stored.last_modified, stored.file_hash.as_str()), (2, "new-hash"));
+        assert_eq!(stored.sync_status, SyncStatus::Synced);
+    }
+
+    #[test]
+    fn test_query_files_pages_sorts_and_escapes_prefix() {
+        let env = loopback::use_temp_environment();
+        let root = env.join("page_query");
+        for (name, size) in [("a.bin", 30), ("b.bin", 10), ("c.bin", 20)] {
+            let path = root.join(name).to_string_lossy().to_string();
+            upsert_file(FileMetadata::new(path, 1, "hash".to_string(), SyncStatus::Synced, size)).unwrap();
+        }
+        // `_`가 와일드카드로 해석되면 함께 조회되는 이웃 폴더
+        let sibling = env.join("pageXquery").join("d.bin").to_string_lossy().to_string();
+        upsert_file(FileMetadata::new(sibling, 1, "hash".to_string(), SyncStatus::Synced, 40)).unwrap();
+
+        let mut query = FileQuery {
+            sync_status: None,
+            path_prefix: Some(paths::normalize(&root)),
+            sort_by: FileSortKey::Size,
+            descending: true,
+            offset: 0,
+            limit: 2,
+        };
+        let first = query_files(&query).unwrap();
+        assert_eq!(first.total_count, 3);
+        let sizes: Vec<i64> = first.files.iter().map(|f| f.file_size).collect();
+        assert_eq!(sizes, vec![30, 20]);
+
+        query.offset = 2;
+        let second = query_files(&query).unwrap();
+        assert_eq!(second.files.len(), 1);
+        assert_eq!(second.files[0].file_size, 10);
+
+        query.sync_status = Some(SyncStatus::Pending);
+        assert_eq!(query_files(&query).unwrap().total_count, 0);
+    }
+
+    #[test]
+    fn test_find_duplicates_groups_by_hash_and_size() {
+        let root = loopback::use_temp_environment().join("duplicates");
+        let file = |name: &str, hash: &str, size: i64, status: SyncStatus| {
+            let path = root.join(name).to_string_lossy().to_string();
+            upsert_file(FileMetadata::new(path, 1, hash.to_string(), status, size)).unwrap();
+        };
+        file("photos/a.jpg", "h-photo", 4000, SyncStatus::Synced);
+        file("backup/a copy.jpg", "h-photo", 4000, SyncStatus::Pending);
+        file("old/a.jpg", "h-photo", 4000, SyncStatus::Deleted);
+        file("video.mp4", "h-video", 90000, SyncStatus::Synced);
+        file("video (2).mp4", "h-video", 90000, SyncStatus::Synced);
+        file("video (3).mp4", "h-video", 90000, SyncStatus::Synced);
+        // 크기는 같지만 내용이 다른 파일, 빈 파일
+        file("notes.txt", "h-notes", 4000, SyncStatus::Synced);
+        file("empty-1", "h-empty", 0, SyncStatus::Synced);
+        file("empty-2", "h-empty", 0, SyncStatus::Synced);
+        // 내용을 해시하지 않은 파일은 크기가 같아도 묶지 않음
+        file("scan-1.raw", scan::INITIAL_SCAN_HASH, 7000, SyncStatus::Synced);
+        file("scan-2.raw", scan::INITIAL_SCAN_HASH, 7000, SyncStatus::Synced);
+        file("meta-1.mov", &integrity::metadata_hash(8000, 1), 8000, SyncStatus::Synced);
+        file("meta-2.mov", &integrity::metadata_hash(8000, 1), 8000, SyncStatus::Synced);
+        let outside = loopback::use_temp_environment().join("duplicates-other").join("a.jpg");
+        upsert_file(FileMetadata::new(outside.to_string_lossy().to_string(), 1, "h-photo".to_string(), SyncStatus::Synced, 4000)).unwrap();
+
+        let groups = find_duplicates(&root.to_string_lossy()).unwrap();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].file_hash, "h-video");
+        assert_eq!(groups[0].paths.len(), 3);
+        assert_eq!(groups[0].wasted_bytes, 180000);
+
+        let names: Vec<String> = groups[1].paths.iter().map(paths::comparison_key).collect();
+        assert_eq!(groups[1].file_hash, "h-photo");
+        assert_eq!(groups[1].wasted_bytes, 4000);
+        assert_eq!(names.len(), 2);
+        assert!(names[0].ends_with("backup/a copy.jpg") && names[1].ends_with("photos/a.jpg"));
+    }
+
+    #[test]
+    fn test_verify_tree_reports_corruption_and_missing() {
+        let root = loopback::use_temp_environment().join("verify-tree");
+        fs::create_dir_all(&root).unwrap();
+        let root_str = root.to_string_lossy().to_string();
+
+        for name in ["intact.txt", "corrupt.txt", "missing.txt", "edited.txt"] {
+            fs::write(root.join(name), b"original contents").unwrap();
+        }
+        reconcile_directory(&root_str).unwrap();
+
+        // 수정 시간을 유지한 채 내용만 바꿔 비트 부패를 흉내냄
+        let corrupt = root.join("corrupt.txt");
+        let mtime = fs::metadata(&corrupt).unwrap().modified().unwrap();
+        fs::write(&corrupt, b"0riginal contents").unwrap();
+        fs::File::options().write(true).open(&corrupt).unwrap().set_modified(mtime).unwrap();
+
+        fs::remove_file(root.join("missing.txt")).unwrap();
+
+        let edited = root.join("edited.txt");
+        fs::write(&edited, b"new contents").unwrap();
+        let later = mtime + std::time::Duration::from_secs(10);
+        fs::File::options().write(true).open(&edited).unwrap().set_modified(later).unwrap();
+
+        let report = verify_tree(&root_str).unwrap();
+
+        assert_eq!(report.checked, 2);
+        assert_eq!(report.mismatched, vec![paths::normalize(&corrupt)]);
+        assert_eq!(report.missing, vec![paths::normalize(root.join("missing.txt"))]);
+        assert_eq!(report.modified, vec![paths::normalize(&edited)]);
+        assert!(!report.is_clean());
+    }
+
+    #[test]
+    fn test_reconcile_reports_changes_made_while_not_watching() {
+        let root = loopback::use_temp_environment().join("reconcile");
+        fs::create_dir_all(&root).unwrap();
+        let root_str = root.to_string_lossy().to_string();
+        for name in ["kept.txt", "edited.txt", "removed.txt"] {
+            fs::write(root.join(name), b"before pause").unwrap();
+        }
+        let first = reconcile_directory(&root_str).unwrap();
+        assert_eq!((first.added, first.modified, first.deleted), (3, 0, 0));
+
+        // 일시정지 동안의 변경
+        let edited = root.join("edited.txt");
+        let mtime = fs::metadata(&edited).unwrap().modified().unwrap();
+        fs::write(&edited, b"edited while paused").unwrap();
+        fs::File::options().write(true).open(&edited).unwrap()
+            .set_modified(mtime + std::time::Duration::from_secs(10)).unwrap();
+        fs::remove_file(root.join("removed.txt")).unwrap();
+        fs::write(root.join("added.txt"), b"new").unwrap();
+
+        let report = reconcile_directory(&root_str).unwrap();
+        assert_eq!((report.added, report.modified, report.deleted), (1, 1, 1));
+        let removed = get_file_metadata(&root.join("removed.txt").to_string_lossy()).unwrap().unwrap();
+        assert_eq!(removed.sync_status, SyncStatus::Deleted);
+        let edited = get_file_metadata(&edited.to_string_lossy()).unwrap().unwrap();
+        assert_eq!(edited.sync_status, SyncStatus::Pending);
+
+        // 변경이 없으면 다시 세지 않음
+        let again = reconcile_directory(&root_str).unwrap();
+        assert_eq!((again.added, again.modified, again.deleted), (0, 0, 0));
+
+        // 루트가 사라지면 파일을 모두 삭제로 표시하지 않고 실패
+        assert!(reconcile_directory(&root.join("gone").to_string_lossy()).is_err());
+    }
+
+    #[test]
+    fn test_verify_tree_skips_unhashed_scan_entries() {
+        let root = loopback::use_temp_environment().join("verify-scanned");
+        fs::create_dir_all(&root).unwrap();
+        let root_str = root.to_string_lossy().to_string();
+        for name in ["a.txt", "b.txt"] {
+            fs::write(root.join(name), b"scanned contents").unwrap();
+        }
+        scan::run(&root_str, |_| {}).unwrap();
+
+        // 초기 스캔만 한 트리는 손상이 아니라 아직 해시하지 않은 상태
+        let report = verify_tree(&root_str).unwrap();
+        assert_eq!(report.unhashed, 2);
+        assert_eq!(report.checked, 0);
+        assert!(report.mismatched.is_empty());
+        assert!(report.is_clean());
+    }
+}
@@ -0,0 +1,423 @@
+//! 수신측 내용 주소 청크 저장소 (Content-addressed Dedup Store)
+//!
+//! 받은 청크를 blake3 해시로 색인해 저장해 두고, 다음 수신에서 같은 청크가 나오면
+//! 네트워크로 받지 않고 저장소에서 복사합니다. VM 이미지나 백업처럼 대부분이 이전과
+//! 같은 파일을 다시 받을 때 전송량이 바뀐 청크만큼으로 줄어듭니다.
+//!
+//! 설정의 `dedup_store_dir`이 있을 때만 사용합니다.
+//!
+//! # Process Flow
+//! 1. 수신측이 수락 응답에서 요청하면 송신측이 청크별 blake3 해시 목록(`ChunkManifest`)을
+//!    `MANIFEST_PAGE_CHUNKS`개씩 나눠 보냄
+//! 2. 수신측이 저장소에 없는 청크 범위(`ChunkNeeds`)를 응답
+//! 3. 송신측은 필요한 청크만 보내고, 수신측은 나머지를 저장소에서 채움
+//! 4. 네트워크로 받은 청크는 해시를 확인한 뒤 저장소에 추가
+//! 5. 유지보수 태스크(`history::run_maintenance`)가 저장소가 `dedup_store_max_bytes`를 넘으면
+//!    가장 오래 쓰지 않은 청크부터 지움 (`ChunkStore::prune`)
+//!
+//! # Security
+//! - 해시 목록은 송신측이 알리는 값이므로, 받은 청크는 목록의 해시와 일치할 때만 저장합니다.
+//!   다른 내용을 같은 해시로 저장해 이후 수신을 오염시키지 못합니다.
+
+use anyhow::{Context, Result};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::config;
+use super::error::PebbleError;
+use super::integrity::HashAlgo;
+use super::paths;
+use super::transfer::AckRange;
+
+/// 청크 색인에 사용하는 해시 (충돌에 강해야 하므로 협상과 무관하게 고정)
+pub const STORE_HASH: HashAlgo = HashAlgo::Blake3;
+
+/// `ChunkManifest` 메시지 하나에 담는 최대 해시 수 (메시지 약 1MB)
+pub const MANIFEST_PAGE_CHUNKS: usize = 16 * 1024;
+
+/// 저장소 기본 최대 크기 (10 GiB)
+pub const DEFAULT_MAX_BYTES: u64 = 10 * 1024 * 1024 * 1024;
+
+/// 이 시간 안에 쓴 청크는 한도를 넘어도 지우지 않음 (받는 중인 전송이 저장소에서 채울 청크)
+const RECENT_GRACE: Duration = Duration::from_secs(60 * 60);
+
+/// 저장소 정리 결과
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StorePruneReport {
+    /// 지운 청크 수
+    pub chunks_removed: u64,
+
+    /// 확보한 용량 (bytes)
+    pub bytes_freed: u64,
+
+    /// 정리 후 남은 용량 (bytes)
+    pub bytes_kept: u64,
+}
+
+/// 해시로 색인된 청크 저장소
+#[derive(Debug, Clone)]
+pub struct ChunkStore {
+    root: PathBuf,
+}
+
+impl ChunkStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// 설정된 저장소를 엽니다. 설정하지 않았으면 None.
+    pub fn configured() -> Option<Self> {
+        config::current().dedup_store_dir.map(Self::new)
+    }
+
+    /// 청크 파일 경로 (`<root>/<앞 2글자>/<해시>`)
+    fn path_for(&self, hash: &str) -> Result<PathBuf> {
+        if hash.len() != 64 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(PebbleError::protocol(format!("Invalid chunk hash: {}", hash)).into());
+        }
+        Ok(self.root.join(&hash[..2]).join(hash))
+    }
+
+    /// 같은 해시와 크기의 청크가 있는지 확인합니다.
+    ///
+    /// 있으면 수정 시각을 지금으로 바꿔 정리(`prune`)에서 가장 늦게 지워지도록 합니다.
+    pub fn contains(&self, hash: &str, size: u64) -> bool {
+        let Ok(path) = self.path_for(hash) else {
+            return false;
+        };
+        let found = fs::metadata(paths::long_path(&path)).is_ok_and(|metadata| metadata.len() == size);
+        if found {
+            let touched = File::options()
+                .write(true)
+                .open(paths::long_path(&path))
+                .and_then(|file| file.set_modified(SystemTime::now()));
+            if let Err(e) = touched {
+                tracing::debug!("Failed to mark chunk {} as used: {}", hash, e);
+            }
+        }
+        found
+    }
+
+    /// 저장된 청크를 읽습니다.
+    pub fn read(&self, hash: &str, size: u64) -> Result<Vec<u8>> {
+        let path = self.path_for(hash)?;
+        let data = fs::read(paths::long_path(&path))
+            .with_context(|| format!("Failed to read stored chunk: {}", path.display()))?;
+        if data.len() as u64 != size {
+            anyhow::bail!("Stored chunk {} has {} bytes, expected {}", hash, data.len(), size);
+        }
+        Ok(data)
+    }
+
+    /// 청크를 저장합니다. 내용이 해시와 다르면 저장하지 않고 에러를 반환합니다.
+    ///
+    /// 임시 파일에 쓴 뒤 rename하므로 중간에 종료되어도 잘린 청크가 남지 않습니다.
+    pub fn insert(&self, hash: &str, data: &[u8]) -> Result<()> {
+        let path = self.path_for(hash)?;
+        if self.contains(hash, data.len() as u64) {
+            return Ok(());
+        }
+        if STORE_HASH.digest(data) != hash {
+            return Err(PebbleError::protocol(format!("Chunk does not match manifest hash {}", hash)).into());
+        }
+
+        let dir = path.parent().context("Chunk path has no parent")?;
+        fs::create_dir_all(paths::long_path(dir))
+            .with_context(|| format!("Failed to create chunk store directory: {}", dir.display()))?;
+
+        let tmp_path = path.with_extension(format!("{}.tmp", uuid::Uuid::new_v4().simple()));
+        fs::write(paths::long_path(&tmp_path), data)
+            .with_context(|| format!("Failed to write chunk: {}", tmp_path.display()))?;
+        fs::rename(paths::long_path(&tmp_path), paths::long_path(&path))
+            .with_context(|| format!("Failed to store chunk: {}", path.display()))?;
+
+        Ok(())
+    }
+
+    /// 저장소가 `max_bytes` 이하가 되도록 가장 오래 쓰지 않은 청크부터 지웁니다.
+    ///
+    /// 청크의 수정 시각(저장하거나 `contains`로 찾은 시각) 순으로 지우며, `RECENT_GRACE` 안에 쓴 청크는 남깁니다.
+    /// 쓰다 만 임시 파일도 같은 기준으로 지웁니다.
+    pub fn prune(&self, max_bytes: u64) -> Result<StorePruneReport> {
+        let mut report = StorePruneReport::default();
+        let buckets = match fs::read_dir(paths::long_path(&self.root)) {
+            Ok(buckets) => buckets,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(report),
+            Err(e) => {
+                return Err(anyhow::Error::from(e).context(format!("Failed to read chunk store: {}", self.root.display())))
+            }
+        };
+
+        let mut chunks = Vec::new();
+        let mut total = 0;
+        for bucket in buckets {
+            let bucket = bucket?;
+            if !bucket.file_type()?.is_dir() {
+                continue;
+            }
+            for entry in fs::read_dir(bucket.path())? {
+                let entry = entry?;
+                let metadata = entry.metadata()?;
+                if !metadata.is_file() {
+                    continue;
+                }
+                total += metadata.len();
+                chunks.push((metadata.modified().unwrap_or(UNIX_EPOCH), metadata.len(), entry.path()));
+            }
+        }
+
+        chunks.sort_by_key(|(used_at, ..)| *used_at);
+        let recent = SystemTime::now() - RECENT_GRACE;
+        for (used_at, len, path) in chunks {
+            if total <= max_bytes || used_at > recent {
+                break;
+            }
+            match fs::remove_file(paths::long_path(&path)) {
+                Ok(()) => {
+                    total -= len;
+                    report.chunks_removed += 1;
+                    report.bytes_freed += len;
+                }
+                Err(e) => tracing::warn!("Failed to remove stored chunk {}: {}", path.display(), e),
+            }
+        }
+        report.bytes_kept = total;
+
+        if report.chunks_removed > 0 {
+            tracing::info!(
+                "Pruned dedup store {}: {} chunks ({} bytes) removed, {} bytes kept",
+                self.root.display(), report.chunks_removed, report.bytes_freed, report.bytes_kept
+            );
+        }
+        Ok(report)
+    }
+}
+
+/// 파일의 청크별 저장소 해시 목록을 계산합니다 (송신측).
+///
+/// # Arguments
+/// * `file_path` - 전송할 파일
+/// * `chunk_size` - 전송에 사용하는 청크 크기
+/// * `first` - 목록을 시작할 청크 (이어보내기 위치)
+pub fn chunk_manifest(file_path: &str, chunk_size: u64, first: u64) -> Result<Vec<String>> {
+    let mut file = File::open(paths::long_path(file_path))
+        .with_context(|| format!("Failed to open file: {}", file_path))?;
+    file.seek(SeekFrom::Start(first * chunk_size))?;
+    let mut buffer = vec![0u8; chunk_size as usize];
+    let mut hashes = Vec::new();
+
+    loop {
+        let mut filled = 0;
+        while filled < buffer.len() {
+            match file.read(&mut buffer[filled..])? {
+                0 => break,
+                n => filled += n,
+            }
+        }
+        if filled == 0 {
+            break;
+        }
+        hashes.push(STORE_HASH.digest(&buffer[..filled]));
+        if filled < buffer.len() {
+            break;
+        }
+    }
+
+    Ok(hashes)
+}
+
+/// 한 전송의 중복 제거 계획: 수신측이 저장소에서 채우는 청크
+///
+/// 수신측은 해시 목록과 저장소로, 송신측은 수신측이 보낸 `ChunkNeeds`로 만들며
+/// 양측이 같은 청크를 건너뜁니다. 인덱스는 파일 전체 기준입니다.
+#[derive(Debug, Clone, Default)]
+pub struct DedupPlan {
+    /// 청크를 읽고 쓸 저장소 (수신측만)
+    pub store: Option<ChunkStore>,
+
+    /// 계획이 시작하는 청크 (이어받기 위치)
+    pub first: u64,
+
+    /// `first`부터의 청크별 저장소 해시 (송신측은 비어 있음)
+    pub hashes: Vec<String>,
+
+    /// `first`부터 청크별로 저장소에서 채우는지 여부
+    pub present: Vec<bool>,
+}
+
+impl DedupPlan {
+    /// 해시 목록을 저장소와 대조합니다 (수신측).
+    ///
+    /// # Arguments
+    /// * `first` - 해시 목록이 시작하는 청크
+    /// * `file_size` - 파일 크기 (마지막 청크 크기 계산용)
+    /// * `chunk_size` - 청크 크기
+    pub fn new(store: ChunkStore, first: u64, hashes: Vec<String>, file_size: u64, chunk_size: u64) -> Self {
+        let present = hashes
+            .iter()
+            .enumerate()
+            .map(|(offset, hash)| store.contains(hash, chunk_len(first + offset as u64, file_size, chunk_size)))
+            .collect();
+        Self { store: Some(store), first, hashes, present }
+    }
+
+    /// 수신측이 보낸 필요 범위로 계획을 만듭니다 (송신측).
+    ///
+    /// # Returns
+    /// * 범위가 `first..total` 밖이거나 순서가 어긋나면 `PebbleError::Protocol`
+    pub fn from_needs(first: u64, total: u64, needs: &[AckRange]) -> Result<Self> {
+        let mut present = vec![true; total.saturating_sub(first) as usize];
+        let mut next = first;
+        for range in needs {
+            if range.start < next || range.end <= range.start || range.end > total {
+                return Err(PebbleError::protocol(format!(
+                    "Invalid needed chunk range {}..{} (from {}, total {})", range.start, range.end, next, total
+                )).into());
+            }
+            for index in range.start..range.end {
+                present[(index - first) as usize] = false;
+            }
+            next = range.end;
+        }
+        Ok(Self { store: None, first, hashes: Vec::new(), present })
+    }
+
+    /// 저장소에서 채우는 청크인지 확인합니다.
+    pub fn is_present(&self, index: u64) -> bool {
+        index
+            .checked_sub(self.first)
+            .and_then(|offset| self.present.get(offset as usize).copied())
+            .unwrap_or(false)
+    }
+
+    /// 청크의 저장소 해시
+    pub fn hash(&self, index: u64) -> Option<&str> {
+        let offset = index.checked_sub(self.first)?;
+        self.hashes.get(offset as usize).map(String::as_str)
+    }
+
+    /// 네트워크로 받아야 하는 청크 범위
+    pub fn needed_ranges(&self) -> Vec<AckRange> {
+        let mut ranges: Vec<AckRange> = Vec::new();
+        for (offset, present) in self.present.iter().enumerate() {
+            if *present {
+                continue;
+            }
+            let index = self.first + offset as u64;
+            match ranges.last_mut() {
+                Some(last) if last.end == index => last.end = index + 1,
+                _ => ranges.push(AckRange { start: index, end: index + 1 }),
+            }
+        }
+        ranges
+    }
+
+    /// 저장소에서 채우는 청크 수
+    pub fn present_count(&self) -> usize {
+        self.present.iter().filter(|present| **present).count()
+    }
+
+    /// 저장소에서 청크를 읽습니다. 저장소에서 채우지 않는 청크면 None.
+    pub fn read_present(&self, index: u64, file_size: u64, chunk_size: u64) -> Result<Option<Vec<u8>>> {
+        if !self.is_present(index) {
+            return Ok(None);
+        }
+        let (Some(store), Some(hash)) = (&self.store, self.hash(index)) else {
+            return Ok(None);
+        };
+        store.read(hash, chunk_len(index, file_size, chunk_size)).map(Some)
+    }
+
+    /// 네트워크로 받은 청크를 저장소에 추가합니다.
+    ///
+    /// 저장소는 최적화이므로 실패해도 수신은 계속하고 경고만 남깁니다.
+    pub fn remember(&self, index: u64, data: &[u8]) {
+        let (Some(store), Some(hash)) = (&self.store, self.hash(index)) else {
+            return;
+        };
+        if let Err(e) = store.insert(hash, data) {
+            tracing::warn!("Failed to store chunk {}: {:#}", index, e);
+        }
+    }
+}
+
+/// 청크 하나의 크기 (마지막 청크는 짧을 수 있음)
+pub fn chunk_len(index: u64, file_size: u64, chunk_size: u64) -> u64 {
+    file_size.saturating_sub(index * chunk_size).min(chunk_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_store_and_plan() {
+        let dir = TempDir::new().unwrap();
+        let store = ChunkStore::new(dir.path());
+
+        let a = vec![1u8; 100];
+        let b = vec![2u8; 100];
+        let tail = vec![3u8; 40];
+        let hashes: Vec<String> = [&a, &b, &a, &tail].iter().map(|c| STORE_HASH.digest(c)).collect();
+
+        store.insert(&hashes[0], &a).unwrap();
+        store.insert(&hashes[3], &tail).unwrap();
+        // 해시와 다른 내용은 저장하지 않음
+        assert!(store.insert(&hashes[1], &a).is_err());
+        assert!(store.insert("../../etc/passwd", &a).is_err());
+
+        let plan = DedupPlan::new(store.clone(), 0, hashes.clone(), 340, 100);
+        assert_eq!(plan.present, vec![true, false, true, true]);
+        assert_eq!(plan.needed_ranges(), vec![AckRange { start: 1, end: 2 }]);
+        assert_eq!(store.read(&hashes[3], 40).unwrap(), tail);
+
+        // 이어받기 위치부터의 계획과 송신측 복원
+        let resumed = DedupPlan::new(store.clone(), 1, hashes[1..].to_vec(), 340, 100);
+        assert!(!resumed.is_present(0) && !resumed.is_present(1) && resumed.is_present(2));
+        let sender = DedupPlan::from_needs(1, 4, &resumed.needed_ranges()).unwrap();
+        assert_eq!(sender.present, resumed.present);
+        assert!(DedupPlan::from_needs(1, 4, &[AckRange { start: 0, end: 2 }]).is_err());
+        assert!(DedupPlan::from_needs(1, 4, &[AckRange { start: 3, end: 5 }]).is_err());
+    }
+
+    #[test]
+    fn test_prune_removes_least_recently_used_chunks() {
+        let dir = TempDir::new().unwrap();
+        let store = ChunkStore::new(dir.path());
+        assert_eq!(ChunkStore::new(dir.path().join("missing")).prune(0).unwrap(), StorePruneReport::default());
+
+        let chunks: Vec<Vec<u8>> = (1..=3u8).map(|byte| vec![byte; 100]).collect();
+        let hashes: Vec<String> = chunks.iter().map(|chunk| STORE_HASH.digest(chunk)).collect();
+        for (hash, chunk) in hashes.iter().zip(&chunks) {
+            store.insert(hash, chunk).unwrap();
+        }
+        // 첫 청크가 가장 오래전에 저장됨
+        let age = |hash: &str, hours: u64| {
+            File::options()
+                .write(true)
+                .open(store.path_for(hash).unwrap())
+                .unwrap()
+                .set_modified(SystemTime::now() - Duration::from_secs(hours * 60 * 60))
+                .unwrap();
+        };
+        age(&hashes[0], 3);
+        age(&hashes[1], 2);
+
+        // 다시 쓴 청크는 남고, 최근에 저장한 청크는 한도를 넘어도 남음
+        assert!(store.contains(&hashes[0], 100));
+        let report = store.prune(100).unwrap();
+        assert_eq!(report, StorePruneReport { chunks_removed: 1, bytes_freed: 100, bytes_kept: 200 });
+        assert!(store.contains(&hashes[0], 100));
+        assert!(!store.contains(&hashes[1], 100));
+        assert!(store.contains(&hashes[2], 100));
+
+        // 한도 안이면 지우지 않음
+        age(&hashes[0], 5);
+        assert_eq!(store.prune(200).unwrap().chunks_removed, 0);
+        assert_eq!(store.prune(100).unwrap().chunks_removed, 1);
+        assert!(!store.contains(&hashes[0], 100));
+    }
+}
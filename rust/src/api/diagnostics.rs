@@ -0,0 +1,252 @@
+use serde::{Deserialize, Serialize};
+use std::io::ErrorKind;
+use std::net::{SocketAddrV4, TcpListener, UdpSocket};
+use std::path::{Path, PathBuf};
+
+use super::certificate::CertificateManager;
+use super::util::unix_now;
+use super::{config, db, discovery, service};
+
+/// 여유 공간이 이보다 적으면 경고 (1GB)
+const LOW_DISK_SPACE_WARN: u64 = 1024 * 1024 * 1024;
+
+/// 여유 공간이 이보다 적으면 실패 (100MB)
+const LOW_DISK_SPACE_FAIL: u64 = 100 * 1024 * 1024;
+
+/// 시스템 시계가 이 시각(2024-01-01 UTC)보다 이전이면 잘못된 것으로 간주
+const MIN_SANE_TIMESTAMP: i64 = 1_704_067_200;
+
+/// 시스템 시계가 이 시각(2100-01-01 UTC)보다 이후이면 잘못된 것으로 간주
+const MAX_SANE_TIMESTAMP: i64 = 4_102_444_800;
+
+/// 개별 점검 결과
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// 개별 점검 항목
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticCheck {
+    /// 점검 항목 이름 (예: "database", "udp_broadcast")
+    pub name: String,
+
+    pub status: CheckStatus,
+
+    /// 사용자에게 보여줄 설명
+    pub message: String,
+}
+
+/// 자가 진단 보고서
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiagnosticsReport {
+    pub checks: Vec<DiagnosticCheck>,
+
+    /// 모든 항목이 Fail이 아닌지 여부
+    pub healthy: bool,
+
+    /// 보고서 생성 시각 (Unix timestamp)
+    pub generated_at: i64,
+}
+
+impl DiagnosticCheck {
+    fn new(name: &str, status: CheckStatus, message: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+/// 전체 자가 진단을 실행합니다.
+///
+/// # Arguments
+/// * `app_data_dir` - 인증서가 저장된 앱 데이터 디렉토리 (None이면 인증서 점검 생략)
+pub fn run(app_data_dir: Option<&str>) -> DiagnosticsReport {
+    let checks = vec![
+        check_database(),
+        check_certificate(app_data_dir),
+        check_udp_broadcast(),
+        check_transfer_port(),
+        check_discovery_port(),
+        check_disk_space(),
+        check_clock(),
+    ];
+
+    for check in &checks {
+        match check.status {
+            CheckStatus::Pass => tracing::info!("Diagnostics [{}] pass: {}", check.name, check.message),
+            CheckStatus::Warn => tracing::warn!("Diagnostics [{}] warn: {}", check.name, check.message),
+            CheckStatus::Fail => tracing::error!("Diagnostics [{}] fail: {}", check.name, check.message),
+        }
+    }
+
+    DiagnosticsReport {
+        healthy: checks.iter().all(|c| c.status != CheckStatus::Fail),
+        checks,
+        generated_at: unix_now(),
+    }
+}
+
+/// DB 파일을 열고 조회할 수 있는지 확인합니다.
+fn check_database() -> DiagnosticCheck {
+    let db_path = config::current().db_path;
+
+    let result = db::open_connection().and_then(|conn| {
+        conn.query_row("SELECT COUNT(*) FROM files", [], |row| row.get::<_, i64>(0))
+    });
+
+    match result {
+        Ok(count) => DiagnosticCheck::new(
+            "database",
+            CheckStatus::Pass,
+            format!("{} ({} files tracked)", db_path, count),
+        ),
+        Err(e) => DiagnosticCheck::new("database", CheckStatus::Fail, format!("{}: {:#}", db_path, e)),
+    }
+}
+
+/// 저장된 인증서를 로드하고 TLS 서버 설정을 만들 수 있는지 확인합니다.
+fn check_certificate(app_data_dir: Option<&str>) -> DiagnosticCheck {
+    let Some(app_data_dir) = app_data_dir else {
+        return DiagnosticCheck::new("certificate", CheckStatus::Warn, "Skipped: app data directory not given");
+    };
+
+    let cert_dir = Path::new(app_data_dir).join(service::CERT_DIR_NAME);
+    let manager = CertificateManager::new(cert_dir.to_string_lossy().to_string());
+
+    match manager.load_existing() {
+        Ok(Some(cert)) => match cert.build_server_config() {
+            Ok(_) => DiagnosticCheck::new(
+                "certificate",
+                CheckStatus::Pass,
+                format!("Fingerprint {}", cert.fingerprint),
+            ),
+            Err(e) => DiagnosticCheck::new("certificate", CheckStatus::Fail, format!("Unusable certificate: {:#}", e)),
+        },
+        Ok(None) => DiagnosticCheck::new(
+            "certificate",
+            CheckStatus::Warn,
+            "No certificate yet; one will be generated on start",
+        ),
+        Err(e) => DiagnosticCheck::new("certificate", CheckStatus::Fail, format!("{:#}", e)),
+    }
+}
+
+/// 브로드캐스트 소켓을 만들 수 있고 로컬 IP가 있는지 확인합니다.
+fn check_udp_broadcast() -> DiagnosticCheck {
+    let socket = match UdpSocket::bind("0.0.0.0:0").and_then(|s| s.set_broadcast(true).map(|_| s)) {
+        Ok(socket) => socket,
+        Err(e) => {
+            return DiagnosticCheck::new("udp_broadcast", CheckStatus::Fail, format!("Cannot enable broadcast: {}", e));
+        }
+    };
+    drop(socket);
+
+    match local_ip_address::local_ip() {
+        Ok(ip) => DiagnosticCheck::new("udp_broadcast", CheckStatus::Pass, format!("Local IP {}", ip)),
+        Err(e) => DiagnosticCheck::new(
+            "udp_broadcast",
+            CheckStatus::Warn,
+            format!("Broadcast enabled but no local network address: {}", e),
+        ),
+    }
+}
+
+/// 전송 포트에 바인딩할 수 있는지 확인합니다.
+fn check_transfer_port() -> DiagnosticCheck {
+    let port = config::current().transfer_port;
+
+    if service::transfer_server_port() == Some(port) {
+        return DiagnosticCheck::new("transfer_port", CheckStatus::Pass, format!("TCP {} in use by Pebble", port));
+    }
+
+    match TcpListener::bind(SocketAddrV4::new([0, 0, 0, 0].into(), port)) {
+        Ok(_) => DiagnosticCheck::new("transfer_port", CheckStatus::Pass, format!("TCP {} available", port)),
+        Err(e) if e.kind() == ErrorKind::AddrInUse => DiagnosticCheck::new(
+            "transfer_port",
+            CheckStatus::Fail,
+            format!("TCP {} is in use by another process", port),
+        ),
+        Err(e) => DiagnosticCheck::new("transfer_port", CheckStatus::Fail, format!("TCP {}: {}", port, e)),
+    }
+}
+
+/// 비콘 포트에 바인딩할 수 있는지 확인합니다 (수신기와 같이 SO_REUSEADDR 사용).
+fn check_discovery_port() -> DiagnosticCheck {
+    let port = config::current().discovery_port;
+
+    if discovery::is_running() {
+        return DiagnosticCheck::new("discovery_port", CheckStatus::Pass, format!("UDP {} in use by Pebble", port));
+    }
+
+    let result = socket2::Socket::new(socket2::Domain::IPV4, socket2::Type::DGRAM, Some(socket2::Protocol::UDP))
+        .and_then(|socket| {
+            socket.set_reuse_address(true)?;
+            socket.bind(&socket2::SockAddr::from(SocketAddrV4::new([0, 0, 0, 0].into(), port)))
+        });
+
+    match result {
+        Ok(_) => DiagnosticCheck::new("discovery_port", CheckStatus::Pass, format!("UDP {} available", port)),
+        Err(e) => DiagnosticCheck::new("discovery_port", CheckStatus::Fail, format!("UDP {}: {}", port, e)),
+    }
+}
+
+/// 다운로드 디렉토리의 여유 공간을 확인합니다.
+fn check_disk_space() -> DiagnosticCheck {
+    let dir = PathBuf::from(config::current().receive_dir());
+
+    // 아직 생성되지 않은 디렉토리는 가장 가까운 상위 디렉토리 기준으로 확인
+    let existing = dir.ancestors().find(|p| p.exists()).unwrap_or(Path::new("."));
+
+    match fs4::available_space(existing) {
+        Ok(available) => {
+            let status = if available < LOW_DISK_SPACE_FAIL {
+                CheckStatus::Fail
+            } else if available < LOW_DISK_SPACE_WARN {
+                CheckStatus::Warn
+            } else {
+                CheckStatus::Pass
+            };
+            DiagnosticCheck::new(
+                "disk_space",
+                status,
+                format!("{} MB free in {}", available / (1024 * 1024), dir.display()),
+            )
+        }
+        Err(e) => DiagnosticCheck::new("disk_space", CheckStatus::Warn, format!("{}: {}", dir.display(), e)),
+    }
+}
+
+/// 시스템 시계가 합리적인 범위인지 확인합니다.
+///
+/// 시계가 크게 틀어지면 비콘 타임스탬프와 기기 타임아웃 판정이 어긋납니다.
+fn check_clock() -> DiagnosticCheck {
+    let now = unix_now();
+
+    if (MIN_SANE_TIMESTAMP..MAX_SANE_TIMESTAMP).contains(&now) {
+        let utc = chrono::DateTime::from_timestamp(now, 0)
+            .map(|t| t.to_rfc3339())
+            .unwrap_or_default();
+        DiagnosticCheck::new("clock", CheckStatus::Pass, utc)
+    } else {
+        DiagnosticCheck::new(
+            "clock",
+            CheckStatus::Fail,
+            format!("System clock looks wrong (unix time {})", now),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock_is_sane() {
+        assert_eq!(check_clock().status, CheckStatus::Pass);
+    }
+}
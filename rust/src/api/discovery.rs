@@ -1,25 +1,254 @@
 use anyhow::{Context, Result};
+use chacha20poly1305::aead::{Aead, AeadCore, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit, Nonce};
 use hmac::{Hmac, Mac};
 use serde::{Deserialize, Serialize};
 use sha2::Sha256;
 use std::collections::HashMap;
-use std::net::{SocketAddr, UdpSocket};
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::Notify;
 use tokio::time::interval;
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
+use super::config;
+use super::error::PebbleError;
+use super::events::{self, PebbleEvent};
+use super::peers::{self, PeerLabel};
+use super::revocation;
+use super::service::{self, ServiceKind};
+use super::supervisor::TaskSupervisor;
+use super::transfer::TransferClient;
+
 /// HMAC-SHA256 타입 별칭
 type HmacSha256 = Hmac<Sha256>;
 
-/// UDP 브로드캐스트 포트
-const DISCOVERY_PORT: u16 = 37845;
+/// 비콘/전송 프로토콜 버전
+pub const PROTOCOL_VERSION: &str = "1.1.0";
+
+/// UDP 브로드캐스트 포트 (기본값)
+pub const DISCOVERY_PORT: u16 = 37845;
 const TEST_PORT: u16 = 40000;
-/// 비콘 전송 주기 (초)
-const BEACON_INTERVAL_SECS: u64 = 5;
+/// 비콘 전송 주기 (초, 기본값)
+pub const BEACON_INTERVAL_SECS: u64 = 5;
+
+/// 기기 타임아웃 시간 (초, 기본값) - 마지막 비콘 이후 이 시간이 지나면 오프라인으로 간주
+pub const DEVICE_TIMEOUT_SECS: u64 = 15;
+
+/// 비콘 패킷 최대 크기 (수신 버퍼 크기)
+pub const MAX_BEACON_SIZE: usize = 4096;
+
+/// 비콘 문자열 필드별 최대 길이
+const MAX_BEACON_FIELD_LEN: usize = 256;
+
+/// 암호화된 비콘 패킷의 시작 표시 (평문 비콘은 JSON이므로 `{`로 시작)
+const SEALED_BEACON_MAGIC: &[u8] = b"PBE1";
+
+/// 암호화된 비콘의 nonce 길이 (ChaCha20-Poly1305)
+const SEALED_NONCE_LEN: usize = 12;
+
+/// 비공개 비콘 ID 길이 (hex 문자 수)
+const PRIVATE_ID_LEN: usize = 32;
+
+/// 이름을 알아내지 못한 비공개 비콘 기기에 다시 묻기까지 기다리는 시간 (초)
+const IDENTITY_RETRY_SECS: u64 = 60;
+
+/// 저전력 모드에서 비콘 주기 배수
+const LOW_POWER_INTERVAL_MULTIPLIER: u64 = 6;
 
-/// 기기 타임아웃 시간 (초) - 마지막 비콘 이후 이 시간이 지나면 오프라인으로 간주
-const DEVICE_TIMEOUT_SECS: u64 = 15;
+/// 비콘 전송 시각에 더하는 무작위 지연의 상한 (주기의 1/5, 기기들의 비콘이 한꺼번에 몰리지 않도록)
+const BEACON_JITTER_DIVISOR: u64 = 5;
+
+/// 비콘 검증 횟수를 세는 구간
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// 한 출발 주소에서 구간마다 검증하는 최대 비콘 수
+const MAX_BEACONS_PER_SOURCE: u32 = 5;
+
+/// 구간마다 검증하는 전체 비콘 수 (검증 예산)
+const BEACON_VERIFY_BUDGET: u32 = 200;
+
+/// 폴링 한 번에 소켓에서 꺼내는 최대 패킷 수
+const MAX_PACKETS_PER_POLL: usize = 64;
+
+/// 수신 소켓 폴링 간격 (일반 / 저전력)
+const RECEIVE_POLL_MS: u64 = 100;
+const LOW_POWER_RECEIVE_POLL_MS: u64 = 1000;
+
+/// 저전력 모드 여부 (앱이 백그라운드에 있을 때)
+static LOW_POWER: AtomicBool = AtomicBool::new(false);
+
+/// 주기와 무관하게 즉시 비콘을 보내도록 송신 태스크를 깨우는 알림
+static ANNOUNCE_NOW: once_cell::sync::Lazy<Notify> = once_cell::sync::Lazy::new(Notify::new);
+
+/// TLS 연결로 이름을 알려 줄 이 기기의 신원
+struct LocalIdentity {
+    device_id: String,
+    device_name: String,
+    secret_key: String,
+}
+
+/// 실행 중인 발견 서비스의 신원 (중지 상태면 None)
+static LOCAL_IDENTITY: once_cell::sync::Lazy<Mutex<Option<LocalIdentity>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(None));
+
+/// 비공개 비콘에 싣는 불투명 ID를 만듭니다.
+///
+/// 같은 비밀 키를 가진 기기만 알려진 기기 ID에서 같은 값을 계산해 누구인지 알 수 있습니다.
+pub fn private_beacon_id(device_id: &str, secret_key: &str) -> Result<String> {
+    let mut id = BeaconMessage::generate_signature(&format!("private-id:{}", device_id), secret_key)?;
+    id.truncate(PRIVATE_ID_LEN);
+    Ok(id)
+}
+
+/// 비밀 키에서 비콘 암호화 키를 만듭니다.
+fn beacon_cipher(secret_key: &str) -> Result<ChaCha20Poly1305> {
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(secret_key.as_bytes())
+        .context("Invalid HMAC key length")?;
+    mac.update(b"pebble-beacon-encryption");
+    let key = mac.finalize().into_bytes();
+    Ok(ChaCha20Poly1305::new(Key::from_slice(&key)))
+}
+
+/// 신원 요청(`IdentityRequest`)에 실어 비밀 키를 가졌음을 증명하는 값
+pub fn identity_proof(nonce: &str, secret_key: &str) -> Result<String> {
+    BeaconMessage::generate_signature(&format!("identity:{}", nonce), secret_key)
+}
+
+/// 신원 요청에 답할 이 기기의 ID와 이름을 반환합니다.
+///
+/// # Security
+/// - 같은 비밀 키로 만든 증명이 있어야만 이름을 알려 줌
+///
+/// # Returns
+/// * 발견 서비스가 실행 중이 아니거나 증명이 틀리면 None
+pub fn answer_identity(nonce: &str, proof: &str) -> Option<(String, String)> {
+    let identity = LOCAL_IDENTITY.lock().unwrap();
+    let identity = identity.as_ref()?;
+    let expected = identity_proof(nonce, &identity.secret_key).ok()?;
+    (expected == proof).then(|| (identity.device_id.clone(), identity.device_name.clone()))
+}
+
+/// 저전력 모드를 설정합니다.
+///
+/// 저전력 모드에서는 비콘 주기가 늘어나고 수신 소켓 폴링 간격이 길어집니다.
+/// 해제 시에는 다른 기기가 바로 다시 발견할 수 있도록 즉시 비콘을 보냅니다.
+pub fn set_low_power(enabled: bool) {
+    let previous = LOW_POWER.swap(enabled, Ordering::SeqCst);
+    if previous != enabled {
+        tracing::info!("Discovery low power mode: {}", enabled);
+        if !enabled {
+            announce_now();
+        }
+    }
+}
+
+/// 다음 주기를 기다리지 않고 즉시 비콘을 전송하도록 요청합니다.
+pub fn announce_now() {
+    ANNOUNCE_NOW.notify_one();
+}
+
+/// 비콘 전송 전에 기다릴 무작위 지연 (0 ~ 주기의 1/5)
+fn beacon_jitter(interval_secs: u64) -> Duration {
+    let max_ms = interval_secs * 1000 / BEACON_JITTER_DIVISOR;
+    let random = uuid::Uuid::new_v4().as_u64_pair().0;
+    Duration::from_millis(random % (max_ms + 1))
+}
+
+/// 비콘 검증(복호화, HMAC) 횟수 제한
+///
+/// 비콘을 쏟아붓는 기기가 수신측 CPU를 점유하지 못하도록 출발 주소별 한도와
+/// 전체 검증 예산을 두고 `RATE_WINDOW`마다 초기화합니다.
+struct BeaconLimiter {
+    window_start: Instant,
+    per_source: HashMap<IpAddr, u32>,
+    verified: u32,
+    dropped: u64,
+}
+
+impl BeaconLimiter {
+    fn new(now: Instant) -> Self {
+        Self { window_start: now, per_source: HashMap::new(), verified: 0, dropped: 0 }
+    }
+
+    /// 이 주소에서 온 비콘을 검증해도 되는지 확인합니다.
+    fn allow(&mut self, source: IpAddr, now: Instant) -> bool {
+        if now.duration_since(self.window_start) >= RATE_WINDOW {
+            if self.dropped > 0 {
+                tracing::warn!("Dropped {} beacons over the rate limit", self.dropped);
+            }
+            *self = Self::new(now);
+        }
+
+        let count = self.per_source.get(&source).copied().unwrap_or(0);
+        if self.verified >= BEACON_VERIFY_BUDGET || count >= MAX_BEACONS_PER_SOURCE {
+            self.dropped += 1;
+            return false;
+        }
+        self.per_source.insert(source, count + 1);
+        self.verified += 1;
+        true
+    }
+}
+
+/// 현재 모드에 맞는 비콘 주기(초)를 계산합니다.
+fn effective_beacon_interval(base_secs: u64) -> u64 {
+    if LOW_POWER.load(Ordering::SeqCst) {
+        base_secs * LOW_POWER_INTERVAL_MULTIPLIER
+    } else {
+        base_secs
+    }
+}
+
+/// 기기가 비콘으로 알리는 기능
+///
+/// UI는 상대 기기가 지원하지 않는 기능의 버튼을 미리 비활성화하는 데 사용합니다.
+/// 비콘에는 이름 문자열로 실리며, 이 버전이 모르는 이름은 무시합니다.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    /// 폴더를 통째로 보내기
+    FolderTransfer,
+
+    /// 청크 해시 목록(`ChunkManifest`)으로 바뀐 청크만 보내기
+    DeltaSync,
+
+    /// 전송 중 압축
+    Compression,
+
+    /// 텍스트 메시지 (`SendText`)
+    TextMessages,
+
+    /// 클립보드 동기화 (`ClipboardUpdate`)
+    ClipboardSync,
+}
+
+impl Capability {
+    /// 비콘에 싣는 이름
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::FolderTransfer => "folder_transfer",
+            Self::DeltaSync => "delta_sync",
+            Self::Compression => "compression",
+            Self::TextMessages => "text_messages",
+            Self::ClipboardSync => "clipboard_sync",
+        }
+    }
+
+    /// 비콘의 이름을 기능으로 바꿉니다 (모르는 이름이면 None).
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Self::FolderTransfer, Self::DeltaSync, Self::Compression, Self::TextMessages, Self::ClipboardSync]
+            .into_iter()
+            .find(|capability| capability.name() == name)
+    }
+}
+
+/// 이 기기가 지원하는 기능
+pub const LOCAL_CAPABILITIES: &[Capability] =
+    &[Capability::FolderTransfer, Capability::DeltaSync, Capability::TextMessages, Capability::ClipboardSync];
 
 /// Pebble 기기 발견을 위한 비콘 메시지
 ///
@@ -43,6 +272,36 @@ pub struct BeaconMessage {
 
     /// HMAC-SHA256 서명 (hex 인코딩)
     pub signature: String,
+
+    /// 전송 서버 인증서 핑거프린트 (수신측이 인증서 고정에 사용, 구버전은 누락)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cert_fingerprint: Option<String>,
+
+    /// 핑거프린트까지 포함한 HMAC-SHA256 서명
+    ///
+    /// 구버전 기기가 `signature`만으로 검증할 수 있도록 별도 필드로 둡니다.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fingerprint_signature: Option<String>,
+
+    /// 전송 서버가 현재 바인딩된 포트 (서버가 없거나 구버전이면 누락)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transfer_port: Option<u16>,
+
+    /// 전송 포트의 HMAC-SHA256 서명 (구버전 호환을 위해 별도 필드)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port_signature: Option<String>,
+
+    /// 지원하는 기능 이름 (`Capability::name`, 구버전은 누락)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub capabilities: Vec<String>,
+
+    /// 기능 목록의 HMAC-SHA256 서명 (구버전 호환을 위해 별도 필드)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capabilities_signature: Option<String>,
+
+    /// 비공개 비콘 여부 (`device_id`는 `private_beacon_id`로 만든 불투명 ID, `device_name`은 빈 문자열)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub private: bool,
 }
 
 impl BeaconMessage {
@@ -61,7 +320,7 @@ impl BeaconMessage {
             .context("Failed to get system time")?
             .as_secs();
 
-        let protocol_version = "1.0.0".to_string();
+        let protocol_version = PROTOCOL_VERSION.to_string();
 
         // 서명할 데이터 생성
         let data_to_sign = format!("{}{}{}{}", device_id, device_name, timestamp, protocol_version);
@@ -75,12 +334,87 @@ impl BeaconMessage {
             timestamp,
             protocol_version,
             signature,
+            cert_fingerprint: None,
+            fingerprint_signature: None,
+            transfer_port: None,
+            port_signature: None,
+            capabilities: Vec::new(),
+            capabilities_signature: None,
+            private: false,
         })
     }
 
+    /// 기기 이름 없이 불투명 ID만 담은 비공개 비콘을 생성합니다.
+    ///
+    /// # Arguments
+    /// * `device_id` - 기기 고유 ID (비콘에는 싣지 않음)
+    /// * `secret_key` - HMAC 서명을 위한 비밀 키
+    pub fn new_private(device_id: &str, secret_key: &str) -> Result<Self> {
+        let mut beacon = Self::new(private_beacon_id(device_id, secret_key)?, String::new(), secret_key)?;
+        beacon.private = true;
+        Ok(beacon)
+    }
+
+    /// 인증서 핑거프린트를 비콘에 추가하고 서명합니다.
+    ///
+    /// # Arguments
+    /// * `fingerprint` - 전송 서버 인증서의 SHA-256 핑거프린트
+    /// * `secret_key` - HMAC 서명을 위한 비밀 키
+    pub fn with_cert_fingerprint(mut self, fingerprint: String, secret_key: &str) -> Result<Self> {
+        self.fingerprint_signature = Some(Self::generate_signature(&self.fingerprint_data(&fingerprint), secret_key)?);
+        self.cert_fingerprint = Some(fingerprint);
+        Ok(self)
+    }
+
+    /// 전송 서버 포트를 비콘에 추가하고 서명합니다.
+    ///
+    /// # Arguments
+    /// * `port` - 전송 서버가 바인딩된 포트
+    /// * `secret_key` - HMAC 서명을 위한 비밀 키
+    pub fn with_transfer_port(mut self, port: u16, secret_key: &str) -> Result<Self> {
+        self.port_signature = Some(Self::generate_signature(&self.port_data(port), secret_key)?);
+        self.transfer_port = Some(port);
+        Ok(self)
+    }
+
+    /// 지원하는 기능 목록을 비콘에 추가하고 서명합니다.
+    ///
+    /// # Arguments
+    /// * `capabilities` - 이 기기가 지원하는 기능
+    /// * `secret_key` - HMAC 서명을 위한 비밀 키
+    pub fn with_capabilities(mut self, capabilities: &[Capability], secret_key: &str) -> Result<Self> {
+        self.capabilities = capabilities.iter().map(|capability| capability.name().to_string()).collect();
+        self.capabilities_signature = Some(Self::generate_signature(&self.capabilities_data(), secret_key)?);
+        Ok(self)
+    }
+
+    /// 기능 목록 서명 대상 데이터
+    fn capabilities_data(&self) -> String {
+        format!(
+            "{}{}{}{}caps:{}",
+            self.device_id, self.device_name, self.timestamp, self.protocol_version, self.capabilities.join(",")
+        )
+    }
+
+    /// 포트 서명 대상 데이터
+    fn port_data(&self, port: u16) -> String {
+        format!(
+            "{}{}{}{}port:{}",
+            self.device_id, self.device_name, self.timestamp, self.protocol_version, port
+        )
+    }
+
+    /// 핑거프린트 서명 대상 데이터
+    fn fingerprint_data(&self, fingerprint: &str) -> String {
+        format!(
+            "{}{}{}{}{}",
+            self.device_id, self.device_name, self.timestamp, self.protocol_version, fingerprint
+        )
+    }
+
     /// HMAC-SHA256 서명을 생성합니다.
     fn generate_signature(data: &str, secret_key: &str) -> Result<String> {
-        let mut mac = HmacSha256::new_from_slice(secret_key.as_bytes())
+        let mut mac = <HmacSha256 as Mac>::new_from_slice(secret_key.as_bytes())
             .context("Invalid HMAC key length")?;
 
         mac.update(data.as_bytes());
@@ -109,8 +443,8 @@ impl BeaconMessage {
             .context("Failed to get system time")?
             .as_secs();
 
-        if current_time > self.timestamp + 30 {
-            log::warn!("Beacon message is too old: {} seconds", current_time - self.timestamp);
+        if current_time > self.timestamp.saturating_add(30) {
+            tracing::warn!("Beacon message is too old: {} seconds", current_time - self.timestamp);
             return Ok(false);
         }
 
@@ -123,7 +457,34 @@ impl BeaconMessage {
         let expected_signature = Self::generate_signature(&data_to_sign, secret_key)?;
 
         // 서명 비교 (타이밍 공격 방지를 위한 constant-time 비교)
-        Ok(expected_signature == self.signature)
+        if expected_signature != self.signature {
+            return Ok(false);
+        }
+
+        // 핑거프린트는 별도 서명이 있어야만 신뢰 (위조된 핑거프린트로 인증서 고정을 속이지 못하도록)
+        let fingerprint_valid = match (&self.cert_fingerprint, &self.fingerprint_signature) {
+            (None, _) => true,
+            (Some(fingerprint), Some(signature)) => {
+                Self::generate_signature(&self.fingerprint_data(fingerprint), secret_key)? == *signature
+            }
+            (Some(_), None) => false,
+        };
+
+        // 포트도 별도 서명이 있어야만 신뢰 (다른 포트로 연결을 유도하지 못하도록)
+        let port_valid = match (self.transfer_port, &self.port_signature) {
+            (None, _) => true,
+            (Some(port), Some(signature)) => Self::generate_signature(&self.port_data(port), secret_key)? == *signature,
+            (Some(_), None) => false,
+        };
+
+        // 기능 목록도 별도 서명이 있어야만 신뢰
+        let capabilities_valid = match (self.capabilities.is_empty(), &self.capabilities_signature) {
+            (true, _) => true,
+            (false, Some(signature)) => Self::generate_signature(&self.capabilities_data(), secret_key)? == *signature,
+            (false, None) => false,
+        };
+
+        Ok(fingerprint_valid && port_valid && capabilities_valid)
     }
 
     /// 메시지를 JSON으로 직렬화합니다.
@@ -135,6 +496,90 @@ impl BeaconMessage {
     pub fn from_json(json: &str) -> Result<Self> {
         serde_json::from_str(json).context("Failed to deserialize beacon message")
     }
+
+    /// 비콘을 비밀 키에서 만든 키로 암호화한 UDP 패킷을 만듭니다.
+    ///
+    /// # Security
+    /// - 같은 비밀 키가 없으면 기기 ID, 이름, 포트를 볼 수 없음
+    /// - 패킷마다 무작위 nonce 사용
+    pub fn seal(&self, secret_key: &str) -> Result<Vec<u8>> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = beacon_cipher(secret_key)?
+            .encrypt(&nonce, self.to_json()?.as_bytes())
+            .map_err(|_| anyhow::anyhow!("Failed to encrypt beacon"))?;
+        Ok([SEALED_BEACON_MAGIC, nonce.as_slice(), &ciphertext].concat())
+    }
+
+    /// 수신한 UDP 패킷을 디코딩합니다. 암호화된 비콘이면 먼저 복호화합니다.
+    ///
+    /// # Arguments
+    /// * `bytes` - 수신한 패킷 데이터 (평문 JSON 또는 `seal`로 만든 패킷)
+    /// * `secret_key` - 복호화 키를 만들 비밀 키
+    ///
+    /// # Returns
+    /// * 다른 비밀 키로 암호화되었거나 위변조된 패킷이면 `PebbleError::Protocol`
+    pub fn open(bytes: &[u8], secret_key: &str) -> Result<Self> {
+        let Some(sealed) = bytes.strip_prefix(SEALED_BEACON_MAGIC) else {
+            return Self::decode(bytes);
+        };
+        if bytes.len() > MAX_BEACON_SIZE {
+            return Err(PebbleError::protocol(format!("Beacon too large: {} bytes", bytes.len())).into());
+        }
+        if sealed.len() < SEALED_NONCE_LEN {
+            return Err(PebbleError::protocol("Encrypted beacon too short").into());
+        }
+
+        let (nonce, ciphertext) = sealed.split_at(SEALED_NONCE_LEN);
+        let plaintext = beacon_cipher(secret_key)?
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| PebbleError::protocol("Failed to decrypt beacon (different secret key?)"))?;
+        Self::decode(&plaintext)
+    }
+
+    /// 수신한 UDP 패킷을 디코딩하고 필드 길이를 검증합니다. (I/O 없음)
+    ///
+    /// # Arguments
+    /// * `bytes` - 수신한 패킷 데이터
+    ///
+    /// # Returns
+    /// * `Result<Self>` - 서명 검증 전의 비콘 메시지
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() > MAX_BEACON_SIZE {
+            return Err(PebbleError::protocol(format!("Beacon too large: {} bytes", bytes.len())).into());
+        }
+
+        let json = std::str::from_utf8(bytes).context("Beacon is not valid UTF-8")?;
+        let beacon = Self::from_json(json)?;
+
+        let fields = [
+            ("device_id", beacon.device_id.as_str()),
+            ("device_name", beacon.device_name.as_str()),
+            ("protocol_version", beacon.protocol_version.as_str()),
+            ("signature", beacon.signature.as_str()),
+            ("cert_fingerprint", beacon.cert_fingerprint.as_deref().unwrap_or_default()),
+            ("fingerprint_signature", beacon.fingerprint_signature.as_deref().unwrap_or_default()),
+            ("port_signature", beacon.port_signature.as_deref().unwrap_or_default()),
+            ("capabilities", &beacon.capabilities.join(",")),
+            ("capabilities_signature", beacon.capabilities_signature.as_deref().unwrap_or_default()),
+        ];
+        for (name, value) in fields {
+            if value.len() > MAX_BEACON_FIELD_LEN {
+                return Err(PebbleError::protocol(format!("Beacon field {} too long", name)).into());
+            }
+        }
+
+        Ok(beacon)
+    }
+}
+
+/// 기기의 비콘이 도착한 주소 하나
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceAddress {
+    /// 비콘의 출발 IP 주소
+    pub ip_address: String,
+
+    /// 이 주소로 비콘을 마지막으로 받은 시간 (Unix timestamp)
+    pub last_seen: u64,
 }
 
 /// 발견된 Pebble 기기 정보
@@ -146,9 +591,13 @@ pub struct DiscoveredDevice {
     /// 기기 이름
     pub device_name: String,
 
-    /// 기기 IP 주소
+    /// 기기 IP 주소 (가장 최근에 비콘이 도착한 주소)
     pub ip_address: String,
 
+    /// 비콘이 도착한 모든 주소 (Wi-Fi와 유선 등, 최근 순)
+    #[serde(default)]
+    pub addresses: Vec<DeviceAddress>,
+
     /// 프로토콜 버전
     pub protocol_version: String,
 
@@ -157,6 +606,19 @@ pub struct DiscoveredDevice {
 
     /// 기기가 온라인 상태인지 여부
     pub is_online: bool,
+
+    /// 비콘으로 알린 인증서 핑거프린트 (서명 검증됨, 구버전 기기는 None)
+    pub cert_fingerprint: Option<String>,
+
+    /// 비콘으로 알린 전송 서버 포트 (None이면 설정의 transfer_port 사용)
+    pub transfer_port: Option<u16>,
+
+    /// 비콘으로 알린 지원 기능 (구버전 기기는 알 수 없어 None)
+    pub capabilities: Option<Vec<Capability>>,
+
+    /// 사용자가 붙인 별명, 색상, 태그 (`peers::with_labels`로 채움, 없으면 None)
+    #[serde(default)]
+    pub label: Option<PeerLabel>,
 }
 
 impl DiscoveredDevice {
@@ -165,13 +627,85 @@ impl DiscoveredDevice {
         Self {
             device_id: beacon.device_id.clone(),
             device_name: beacon.device_name.clone(),
+            addresses: vec![DeviceAddress {
+                ip_address: ip_address.clone(),
+                last_seen: beacon.timestamp,
+            }],
             ip_address,
             protocol_version: beacon.protocol_version.clone(),
             last_seen: beacon.timestamp,
             is_online: true,
+            cert_fingerprint: beacon.cert_fingerprint.clone(),
+            transfer_port: beacon.transfer_port,
+            capabilities: Self::advertised_capabilities(beacon),
+            label: None,
         }
     }
 
+    /// 비콘에 실린 기능 목록 (기능 목록이 없는 구버전 비콘이면 None)
+    fn advertised_capabilities(beacon: &BeaconMessage) -> Option<Vec<Capability>> {
+        beacon.capabilities_signature.as_ref()?;
+        Some(beacon.capabilities.iter().filter_map(|name| Capability::from_name(name)).collect())
+    }
+
+    /// 상대 기기가 기능을 지원하는지 확인합니다.
+    ///
+    /// 기능 목록을 알리지 않는 구버전 기기는 막지 않도록 true를 반환합니다.
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.capabilities.as_ref().is_none_or(|capabilities| capabilities.contains(&capability))
+    }
+
+    /// 전송 서버 주소 (비콘에 포트가 없으면 설정의 transfer_port)
+    pub fn transfer_addr(&self) -> Result<SocketAddr> {
+        let port = self.transfer_port.unwrap_or_else(|| config::current().transfer_port);
+        format!("{}:{}", self.ip_address, port)
+            .parse()
+            .with_context(|| format!("Invalid device address: {}", self.ip_address))
+    }
+
+    /// 알려진 모든 주소의 전송 서버 주소 (최근 순, 현재 주소가 먼저)
+    pub fn transfer_addrs(&self) -> Result<Vec<SocketAddr>> {
+        let mut addrs = vec![self.transfer_addr()?];
+        let port = addrs[0].port();
+        for address in &self.addresses {
+            let Ok(ip) = address.ip_address.parse::<IpAddr>() else {
+                continue;
+            };
+            let addr = SocketAddr::new(ip, port);
+            if !addrs.contains(&addr) {
+                addrs.push(addr);
+            }
+        }
+        Ok(addrs)
+    }
+
+    /// 비콘이 도착한 주소를 기록하고 현재 주소로 삼습니다.
+    ///
+    /// # Returns
+    /// * 처음 보는 주소면 true
+    pub fn observe_address(&mut self, ip_address: &str, timestamp: u64) -> bool {
+        let is_new = match self.addresses.iter().position(|a| a.ip_address == ip_address) {
+            Some(index) => {
+                self.addresses.remove(index);
+                false
+            }
+            None => true,
+        };
+        self.addresses.insert(0, DeviceAddress {
+            ip_address: ip_address.to_string(),
+            last_seen: timestamp,
+        });
+        self.ip_address = ip_address.to_string();
+        is_new
+    }
+
+    /// 타임아웃 동안 비콘이 오지 않은 주소를 지웁니다. 현재 주소는 남깁니다.
+    pub fn expire_addresses(&mut self, current_time: u64, timeout_secs: u64) {
+        let current = self.ip_address.clone();
+        self.addresses
+            .retain(|a| a.ip_address == current || current_time <= a.last_seen.saturating_add(timeout_secs));
+    }
+
     /// 기기의 마지막 본 시간을 업데이트합니다.
     pub fn update_last_seen(&mut self, timestamp: u64) {
         self.last_seen = timestamp;
@@ -179,8 +713,8 @@ impl DiscoveredDevice {
     }
 
     /// 기기가 타임아웃되었는지 확인합니다.
-    pub fn is_timeout(&self, current_time: u64) -> bool {
-        current_time > self.last_seen + DEVICE_TIMEOUT_SECS
+    pub fn is_timeout(&self, current_time: u64, timeout_secs: u64) -> bool {
+        current_time > self.last_seen + timeout_secs
     }
 }
 
@@ -197,11 +731,14 @@ pub struct DiscoveryService {
     /// 인증 비밀 키
     secret_key: String,
 
+    /// 비콘에 포함할 전송 서버 인증서 핑거프린트
+    cert_fingerprint: Option<String>,
+
     /// 발견된 기기 목록 (device_id -> DiscoveredDevice)
     discovered_devices: Arc<Mutex<HashMap<String, DiscoveredDevice>>>,
 
-    /// 서비스 실행 여부
-    is_running: Arc<Mutex<bool>>,
+    /// 실행 중인 송수신 태스크 (중지 상태면 None)
+    tasks: Mutex<Option<TaskSupervisor>>,
 }
 
 impl DiscoveryService {
@@ -215,17 +752,31 @@ impl DiscoveryService {
     /// - secret_key는 모든 Pebble 기기가 공유하는 Pre-Shared Key (PSK)입니다
     /// - 실제 배포 시 안전한 키 관리 시스템 사용 권장
     pub fn new(device_name: String, secret_key: String) -> Self {
-        let device_id = Uuid::new_v4().to_string();
+        Self::with_device_id(Uuid::new_v4().to_string(), device_name, secret_key)
+    }
 
+    /// 지정한 기기 ID로 발견 서비스를 생성합니다.
+    ///
+    /// 인증서와 비콘이 같은 ID를 쓰도록 영구 저장된 기기 ID를 사용할 때 호출합니다.
+    pub fn with_device_id(device_id: String, device_name: String, secret_key: String) -> Self {
         Self {
             device_id,
             device_name,
             secret_key,
+            cert_fingerprint: None,
             discovered_devices: Arc::new(Mutex::new(HashMap::new())),
-            is_running: Arc::new(Mutex::new(false)),
+            tasks: Mutex::new(None),
         }
     }
 
+    /// 비콘에 전송 서버 인증서 핑거프린트를 포함하도록 설정합니다.
+    ///
+    /// 상대 기기는 이 값으로 인증서를 고정하여 전송할 수 있습니다.
+    pub fn with_cert_fingerprint(mut self, fingerprint: String) -> Self {
+        self.cert_fingerprint = Some(fingerprint);
+        self
+    }
+
     /// 기기 ID를 반환합니다.
     pub fn get_device_id(&self) -> String {
         self.device_id.clone()
@@ -238,60 +789,74 @@ impl DiscoveryService {
     ///   1. 비콘 송신기: 주기적으로 UDP 브로드캐스트 전송
     ///   2. 비콘 수신기: UDP 브로드캐스트 수신 및 기기 목록 업데이트
     pub async fn start(&self) -> Result<()> {
-        let mut is_running = self.is_running.lock().unwrap();
-        if *is_running {
+        let mut tasks = self.tasks.lock().unwrap();
+        if tasks.is_some() {
             anyhow::bail!("Discovery service is already running");
         }
-        *is_running = true;
-        drop(is_running);
 
-        log::info!("Starting discovery service for device: {}", self.device_name);
+        tracing::info!("Starting discovery service for device: {}", self.device_name);
+
+        *LOCAL_IDENTITY.lock().unwrap() = Some(LocalIdentity {
+            device_id: self.device_id.clone(),
+            device_name: self.device_name.clone(),
+            secret_key: self.secret_key.clone(),
+        });
+
+        let supervisor = TaskSupervisor::new(ServiceKind::Discovery);
 
         // 비콘 송신 태스크
         let device_id = self.device_id.clone();
         let device_name = self.device_name.clone();
         let secret_key = self.secret_key.clone();
-        let is_running_tx = Arc::clone(&self.is_running);
+        let cert_fingerprint = self.cert_fingerprint.clone();
 
-        tokio::spawn(async move {
-            if let Err(e) = Self::beacon_sender(device_id, device_name, secret_key, is_running_tx).await {
-                log::error!("Beacon sender error: {}", e);
-            }
+        supervisor.spawn("beacon_sender", |token| {
+            Self::beacon_sender(device_id, device_name, secret_key, cert_fingerprint, token)
         });
 
         // 비콘 수신 태스크
         let discovered_devices = Arc::clone(&self.discovered_devices);
         let secret_key = self.secret_key.clone();
         let device_id = self.device_id.clone();
-        let is_running_rx = Arc::clone(&self.is_running);
 
-        tokio::spawn(async move {
-            if let Err(e) = Self::beacon_receiver(discovered_devices, secret_key, device_id, is_running_rx).await {
-                log::error!("Beacon receiver error: {}", e);
-            }
+        supervisor.spawn("beacon_receiver", |token| {
+            Self::beacon_receiver(discovered_devices, secret_key, device_id, token)
         });
 
-        log::info!("Discovery service started successfully");
+        *tasks = Some(supervisor);
+
+        tracing::info!("Discovery service started successfully");
 
         Ok(())
     }
 
-    /// 발견 서비스를 중지합니다.
-    pub fn stop(&self) -> Result<()> {
-        let mut is_running = self.is_running.lock().unwrap();
-        *is_running = false;
-        log::info!("Discovery service stopped");
+    /// 발견 서비스를 중지하고 송수신 태스크가 끝날 때까지 기다립니다.
+    ///
+    /// # Returns
+    /// * `Result<()>` - 실행 중 에러로 끝난 태스크가 있으면 에러
+    pub async fn stop(&self) -> Result<()> {
+        let supervisor = self.tasks.lock().unwrap().take();
+
+        if let Some(supervisor) = supervisor {
+            *LOCAL_IDENTITY.lock().unwrap() = None;
+            supervisor.shutdown().await?;
+            tracing::info!("Discovery service stopped");
+        }
+
         Ok(())
     }
 
     /// 비콘 송신 태스크
     ///
-    /// 주기적으로 UDP 브로드캐스트를 전송합니다.
+    /// 주기적으로 UDP 브로드캐스트를 전송합니다. 전송 서버 포트는 매번 현재 값을 읽으므로
+    /// 서버가 다시 바인딩되면 다음 비콘부터 새 포트를 알립니다.
+    #[tracing::instrument(name = "discovery", skip_all, fields(task = "beacon_sender", device_id = %device_id))]
     async fn beacon_sender(
         device_id: String,
         device_name: String,
         secret_key: String,
-        is_running: Arc<Mutex<bool>>,
+        cert_fingerprint: Option<String>,
+        token: CancellationToken,
     ) -> Result<()> {
         let socket = UdpSocket::bind("0.0.0.0:0")
             .context("Failed to bind UDP socket for sending")?;
@@ -299,66 +864,102 @@ impl DiscoveryService {
         socket.set_broadcast(true)
             .context("Failed to set broadcast mode")?;
 
-        let broadcast_addr: SocketAddr = format!("255.255.255.255:{}", DISCOVERY_PORT).parse()
-            .context("Failed to parse broadcast address")?;
-
-        let mut interval = interval(Duration::from_secs(BEACON_INTERVAL_SECS));
+        let mut beacon_interval_secs = effective_beacon_interval(config::current().beacon_interval_secs);
+        let mut interval = interval(Duration::from_secs(beacon_interval_secs));
 
         loop {
-            interval.tick().await;
-
-            // 실행 중인지 확인
-            {
-                let running = is_running.lock().unwrap();
-                if !*running {
-                    break;
+            let jitter = tokio::select! {
+                _ = interval.tick() => beacon_jitter(beacon_interval_secs),
+                _ = ANNOUNCE_NOW.notified() => {
+                    tracing::debug!("Out-of-cycle beacon requested");
+                    Duration::ZERO
                 }
+                _ = token.cancelled() => break,
+            };
+            tokio::select! {
+                _ = tokio::time::sleep(jitter) => {}
+                _ = token.cancelled() => break,
+            }
+
+            // 설정 및 저전력 모드 변경 반영 (비콘 주기, 포트)
+            let current_config = config::current();
+            let wanted_interval = effective_beacon_interval(current_config.beacon_interval_secs);
+            if wanted_interval != beacon_interval_secs {
+                beacon_interval_secs = wanted_interval;
+                interval = tokio::time::interval(Duration::from_secs(beacon_interval_secs));
+                interval.tick().await;
+                tracing::info!("Beacon interval changed to {}s", beacon_interval_secs);
             }
 
-            // 비콘 메시지 생성
-            let beacon = match BeaconMessage::new(device_id.clone(), device_name.clone(), &secret_key) {
+            let broadcast_addr: SocketAddr = format!("255.255.255.255:{}", current_config.discovery_port).parse()
+                .context("Failed to parse broadcast address")?;
+
+            // 비콘 메시지 생성 (비공개 모드에서는 이름 대신 불투명 ID만)
+            let beacon = if current_config.beacon_privacy {
+                BeaconMessage::new_private(&device_id, &secret_key)
+            } else {
+                BeaconMessage::new(device_id.clone(), device_name.clone(), &secret_key)
+            };
+            let beacon = beacon
+                .and_then(|b| match &cert_fingerprint {
+                    Some(fingerprint) => b.with_cert_fingerprint(fingerprint.clone(), &secret_key),
+                    None => Ok(b),
+                })
+                .and_then(|b| b.with_capabilities(LOCAL_CAPABILITIES, &secret_key))
+                .and_then(|b| match service::transfer_server_port() {
+                    Some(port) => b.with_transfer_port(port, &secret_key),
+                    None => Ok(b),
+                });
+            let beacon = match beacon {
                 Ok(b) => b,
                 Err(e) => {
-                    log::error!("Failed to create beacon message: {}", e);
+                    tracing::error!("Failed to create beacon message: {}", e);
                     continue;
                 }
             };
 
-            let json_data = match beacon.to_json() {
-                Ok(j) => j,
+            let payload = if current_config.beacon_encryption {
+                beacon.seal(&secret_key)
+            } else {
+                beacon.to_json().map(String::into_bytes)
+            };
+            let payload = match payload {
+                Ok(p) => p,
                 Err(e) => {
-                    log::error!("Failed to serialize beacon: {}", e);
+                    tracing::error!("Failed to serialize beacon: {}", e);
                     continue;
                 }
             };
 
             // UDP 브로드캐스트 전송
-            match socket.send_to(json_data.as_bytes(), broadcast_addr) {
+            match socket.send_to(&payload, broadcast_addr) {
                 Ok(bytes_sent) => {
-                    log::debug!("Sent beacon: {} bytes to {}", bytes_sent, broadcast_addr);
+                    tracing::debug!("Sent beacon: {} bytes to {}", bytes_sent, broadcast_addr);
                 }
                 Err(e) => {
-                    log::error!("Failed to send beacon: {}", e);
+                    tracing::error!("Failed to send beacon: {}", e);
+                    service::record_error(ServiceKind::Discovery, format!("Failed to send beacon: {}", e));
                 }
             }
         }
 
-        log::info!("Beacon sender stopped");
+        tracing::info!("Beacon sender stopped");
         Ok(())
     }
 
     /// 비콘 수신 태스크
     ///
     /// UDP 브로드캐스트를 수신하고 발견된 기기 목록을 업데이트합니다.
+    #[tracing::instrument(name = "discovery", skip_all, fields(task = "beacon_receiver", device_id = %own_device_id))]
     async fn beacon_receiver(
         discovered_devices: Arc<Mutex<HashMap<String, DiscoveredDevice>>>,
         secret_key: String,
         own_device_id: String,
-        is_running: Arc<Mutex<bool>>,
+        token: CancellationToken,
     ) -> Result<()> {
         use std::net::SocketAddrV4;
 
-        let ports_to_try = [DISCOVERY_PORT, TEST_PORT];
+        let ports_to_try = [config::current().discovery_port, TEST_PORT];
         let mut bound = None;
         for port in ports_to_try {
             // SO_REUSEADDR 설정으로 여러 프로세스가 같은 포트 사용 가능
@@ -371,12 +972,12 @@ impl DiscoveryService {
             let addr: SocketAddrV4 = format!("0.0.0.0:{}", port).parse()?;
             match socket.bind(&socket2::SockAddr::from(addr)) {
                 Ok(_) => {
-                    log::info!("Listening for beacons on UDP port {}", port);
+                    tracing::info!("Listening for beacons on UDP port {}", port);
                     bound = Some(socket);
                     break;
                 }
                 Err(e) => {
-                    log::warn!("Failed to bind to port {}: {}", port, e);
+                    tracing::warn!("Failed to bind to port {}: {}", port, e);
                     continue;
                 }
             }
@@ -384,19 +985,22 @@ impl DiscoveryService {
         let socket = bound.context("Failed to bind UDP socket for receiving")?;
         socket.set_nonblocking(true)?;
         let socket: UdpSocket = socket.into();
-        let mut buffer = vec![0u8; 4096];
+        let mut buffer = vec![0u8; MAX_BEACON_SIZE];
+        let own_private_id = private_beacon_id(&own_device_id, &secret_key)?;
+        let pending_identities = Arc::new(Mutex::new(HashMap::new()));
+        let mut limiter = BeaconLimiter::new(Instant::now());
         let mut last_cleanup = SystemTime::now();
 
         loop {
-            // 논블로킹 체크를 위한 짧은 대기
-            tokio::time::sleep(Duration::from_millis(100)).await;
-
-            // 실행 중인지 확인
-            {
-                let running = is_running.lock().unwrap();
-                if !*running {
-                    break;
-                }
+            // 논블로킹 체크를 위한 짧은 대기 (저전력 모드에서는 길게)
+            let poll_ms = if LOW_POWER.load(Ordering::SeqCst) {
+                LOW_POWER_RECEIVE_POLL_MS
+            } else {
+                RECEIVE_POLL_MS
+            };
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_millis(poll_ms)) => {}
+                _ = token.cancelled() => break,
             }
 
             // 기기 타임아웃 정리 (5초마다)
@@ -407,73 +1011,199 @@ impl DiscoveryService {
                 }
             }
 
-            // UDP 패킷 수신
-            match socket.recv_from(&mut buffer) {
-                Ok((bytes_received, src_addr)) => {
-                    let data = &buffer[..bytes_received];
-                    let json_str = match std::str::from_utf8(data) {
-                        Ok(s) => s,
-                        Err(e) => {
-                            log::warn!("Received invalid UTF-8 data: {}", e);
+            // 쌓인 UDP 패킷 수신 (폴링 한 번에 최대 MAX_PACKETS_PER_POLL개)
+            for _ in 0..MAX_PACKETS_PER_POLL {
+                match socket.recv_from(&mut buffer) {
+                    Ok((bytes_received, src_addr)) => {
+                        // 검증(HMAC, 복호화) 전에 출발 주소별 한도와 전체 예산 확인
+                        if !limiter.allow(src_addr.ip(), Instant::now()) {
                             continue;
                         }
-                    };
 
-                    // 비콘 메시지 파싱
-                    let beacon = match BeaconMessage::from_json(json_str) {
-                        Ok(b) => b,
-                        Err(e) => {
-                            log::warn!("Failed to parse beacon message: {}", e);
+                        // 비콘 메시지 파싱
+                        let beacon = match BeaconMessage::open(&buffer[..bytes_received], &secret_key) {
+                            Ok(b) => b,
+                            Err(e) => {
+                                tracing::warn!("Failed to parse beacon message from {}: {:#}", src_addr, e);
+                                continue;
+                            }
+                        };
+
+                        // 자기 자신의 비콘은 무시
+                        if beacon.device_id == own_device_id {
                             continue;
                         }
-                    };
 
-                    // 자기 자신의 비콘은 무시
-                    if beacon.device_id == own_device_id {
-                        continue;
-                    }
-
-                    // 서명 검증
-                    let is_valid = match beacon.verify(&secret_key) {
-                        Ok(v) => v,
-                        Err(e) => {
-                            log::error!("Failed to verify beacon signature: {}", e);
+                        // 서명 검증
+                        let is_valid = match beacon.verify(&secret_key) {
+                            Ok(v) => v,
+                            Err(e) => {
+                                tracing::error!("Failed to verify beacon signature: {}", e);
+                                continue;
+                            }
+                        };
+
+                        if !is_valid {
+                            tracing::warn!("Received invalid beacon from {}", src_addr);
                             continue;
                         }
-                    };
 
-                    if !is_valid {
-                        log::warn!("Received invalid beacon from {}", src_addr);
-                        continue;
-                    }
+                        // 비공개 비콘은 알려진 기기면 ID와 이름을 채우고, 처음 보는 기기면 TLS로 물어봄
+                        let mut beacon = beacon;
+                        if beacon.private {
+                            if beacon.device_id == own_private_id {
+                                continue;
+                            }
+                            match peers::find_by_private_id(&beacon.device_id, &secret_key) {
+                                Ok(Some(peer)) => {
+                                    beacon.device_id = peer.device_id;
+                                    beacon.device_name = peer.device_name;
+                                }
+                                Ok(None) => {
+                                    Self::request_identity(&discovered_devices, &pending_identities, beacon, src_addr.ip(), &secret_key);
+                                    continue;
+                                }
+                                Err(e) => {
+                                    tracing::warn!("Failed to look up private beacon from {}: {:#}", src_addr, e);
+                                    continue;
+                                }
+                            }
+                        }
 
-                    // 발견된 기기 목록 업데이트
-                    let ip_address = src_addr.ip().to_string();
-                    let mut devices = discovered_devices.lock().unwrap();
-
-                    if let Some(device) = devices.get_mut(&beacon.device_id) {
-                        device.update_last_seen(beacon.timestamp);
-                        log::debug!("Updated device: {} ({})", device.device_name, ip_address);
-                    } else {
-                        let device = DiscoveredDevice::new(&beacon, ip_address.clone());
-                        log::info!("Discovered new device: {} ({}) at {}", device.device_name, device.device_id, ip_address);
-                        devices.insert(beacon.device_id.clone(), device);
+                        Self::record_beacon(&discovered_devices, &beacon, src_addr.ip().to_string());
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        // 데이터 없음, 다음 폴링까지 대기
+                        break;
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to receive UDP packet: {}", e);
+                        service::record_error(ServiceKind::Discovery, format!("Failed to receive UDP packet: {}", e));
+                        break;
                     }
-                }
-                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                    // 데이터 없음, 계속 대기
-                    continue;
-                }
-                Err(e) => {
-                    log::error!("Failed to receive UDP packet: {}", e);
                 }
             }
         }
 
-        log::info!("Beacon receiver stopped");
+        tracing::info!("Beacon receiver stopped");
         Ok(())
     }
 
+    /// 서명이 검증된 비콘으로 발견된 기기 목록을 업데이트합니다.
+    fn record_beacon(
+        discovered_devices: &Arc<Mutex<HashMap<String, DiscoveredDevice>>>,
+        beacon: &BeaconMessage,
+        ip_address: String,
+    ) {
+        match revocation::is_revoked(&beacon.device_id, beacon.cert_fingerprint.as_deref()) {
+            Ok(true) => {
+                tracing::debug!("Ignoring beacon from revoked device {} at {}", beacon.device_id, ip_address);
+                return;
+            }
+            Ok(false) => {}
+            Err(e) => tracing::warn!("Failed to check revocation of {}: {:#}", beacon.device_id, e),
+        }
+
+        let mut devices = discovered_devices.lock().unwrap();
+
+        if let Some(device) = devices.get_mut(&beacon.device_id) {
+            device.update_last_seen(beacon.timestamp);
+            tracing::debug!("Updated device: {} ({})", device.device_name, ip_address);
+
+            // DHCP 갱신이나 다른 네트워크(Wi-Fi와 유선 등)로 새 주소가 보였거나,
+            // 전송 서버가 다시 바인딩되었거나, 인증서가 재생성됨.
+            // 이미 아는 주소 사이를 오가는 것은 이동으로 보지 않음
+            let previous = device.ip_address.clone();
+            let new_address = device.observe_address(&ip_address, beacon.timestamp);
+            device.expire_addresses(beacon.timestamp, config::current().device_timeout_secs);
+            let moved = new_address || device.transfer_port != beacon.transfer_port;
+            let rekeyed = device.cert_fingerprint != beacon.cert_fingerprint;
+            if moved {
+                tracing::info!(
+                    "Device {} seen at {}:{:?} (previously {}:{:?}, {} known address(es))",
+                    device.device_id, ip_address, beacon.transfer_port, previous, device.transfer_port,
+                    device.addresses.len()
+                );
+            }
+            device.transfer_port = beacon.transfer_port;
+            device.cert_fingerprint = beacon.cert_fingerprint.clone();
+            device.capabilities = DiscoveredDevice::advertised_capabilities(beacon);
+
+            if moved || rekeyed {
+                let device = device.clone();
+                drop(devices);
+                Self::remember_peer(&device);
+            }
+        } else {
+            let device = DiscoveredDevice::new(beacon, ip_address.clone());
+            tracing::info!("Discovered new device: {} ({}) at {}", device.device_name, device.device_id, ip_address);
+            devices.insert(beacon.device_id.clone(), device.clone());
+            drop(devices);
+            Self::remember_peer(&device);
+
+            // 같은 비밀 키로 서명된 비콘이므로 전송 가능한 기기
+            events::emit(PebbleEvent::DevicePaired {
+                device_id: beacon.device_id.clone(),
+                device_name: beacon.device_name.clone(),
+                ip_address,
+            });
+        }
+    }
+
+    /// 처음 보는 비공개 비콘 기기에 TLS로 ID와 이름을 물어 목록에 추가합니다.
+    ///
+    /// # Security
+    /// - 서명된 비콘의 인증서 핑거프린트로 고정한 연결에서만 물어봄 (핑거프린트가 없으면 묻지 않음)
+    /// - 답한 기기 ID로 계산한 불투명 ID가 비콘과 같아야만 신뢰
+    fn request_identity(
+        discovered_devices: &Arc<Mutex<HashMap<String, DiscoveredDevice>>>,
+        pending: &Arc<Mutex<HashMap<String, u64>>>,
+        beacon: BeaconMessage,
+        ip: IpAddr,
+        secret_key: &str,
+    ) {
+        let Some(fingerprint) = beacon.cert_fingerprint.clone() else {
+            return;
+        };
+        {
+            let mut pending = pending.lock().unwrap();
+            if pending.get(&beacon.device_id).is_some_and(|&asked| beacon.timestamp < asked.saturating_add(IDENTITY_RETRY_SECS)) {
+                return;
+            }
+            pending.insert(beacon.device_id.clone(), beacon.timestamp);
+        }
+
+        let port = beacon.transfer_port.unwrap_or_else(|| config::current().transfer_port);
+        let discovered_devices = Arc::clone(discovered_devices);
+        let pending = Arc::clone(pending);
+        let secret_key = secret_key.to_string();
+        tokio::spawn(async move {
+            let client = TransferClient::new(Some(fingerprint));
+            let (device_id, device_name) = match client.fetch_identity(SocketAddr::new(ip, port), &secret_key).await {
+                Ok(identity) => identity,
+                Err(e) => {
+                    tracing::debug!("Failed to ask {} for its identity: {:#}", ip, e);
+                    return;
+                }
+            };
+            if !private_beacon_id(&device_id, &secret_key).is_ok_and(|id| id == beacon.device_id) {
+                tracing::warn!("{} answered with identity {} that does not match its beacon", ip, device_id);
+                return;
+            }
+
+            pending.lock().unwrap().remove(&beacon.device_id);
+            let beacon = BeaconMessage { device_id, device_name, ..beacon };
+            Self::record_beacon(&discovered_devices, &beacon, ip.to_string());
+        });
+    }
+
+    /// 기기 ID로 전송할 수 있도록 마지막 주소와 인증서 핑거프린트를 기록합니다.
+    fn remember_peer(device: &DiscoveredDevice) {
+        if let Err(e) = peers::record_seen(device) {
+            tracing::warn!("Failed to record peer {}: {:#}", device.device_id, e);
+        }
+    }
+
     /// 타임아웃된 기기를 정리합니다.
     fn cleanup_timeout_devices(discovered_devices: &Arc<Mutex<HashMap<String, DiscoveredDevice>>>) {
         let current_time = SystemTime::now()
@@ -481,16 +1211,30 @@ impl DiscoveryService {
             .unwrap()
             .as_secs();
 
+        let timeout_secs = config::current().device_timeout_secs;
         let mut devices = discovered_devices.lock().unwrap();
 
+        let mut offline = Vec::new();
         devices.retain(|device_id, device| {
-            if device.is_timeout(current_time) {
-                log::info!("Device timed out: {} ({})", device.device_name, device_id);
+            if device.is_timeout(current_time, timeout_secs) {
+                tracing::info!("Device timed out: {} ({})", device.device_name, device_id);
+                offline.push((device_id.clone(), device.device_name.clone()));
                 false
             } else {
+                device.expire_addresses(current_time, timeout_secs);
                 true
             }
         });
+        drop(devices);
+
+        for (device_id, device_name) in offline {
+            events::emit(PebbleEvent::DeviceOffline { device_id, device_name });
+        }
+    }
+
+    /// 서비스가 실행 중인지 확인합니다.
+    pub fn is_running(&self) -> bool {
+        self.tasks.lock().unwrap().as_ref().is_some_and(TaskSupervisor::is_running)
     }
 
     /// 발견된 기기 목록을 반환합니다.
@@ -513,9 +1257,32 @@ static DISCOVERY_SERVICE: once_cell::sync::Lazy<Arc<Mutex<Option<DiscoveryServic
 /// # Returns
 /// * `Result<String>` - 성공 시 기기 ID 반환
 pub async fn start_discovery(device_name: String, secret_key: String) -> Result<String> {
-    let service = DiscoveryService::new(device_name, secret_key);
+    start_service(DiscoveryService::new(device_name, secret_key)).await
+}
+
+/// 지정한 기기 ID로 발견 서비스를 시작합니다.
+///
+/// # Arguments
+/// * `cert_fingerprint` - 비콘으로 알릴 전송 서버 인증서 핑거프린트 (전송 서버가 없으면 None)
+pub async fn start_discovery_with_id(
+    device_id: String,
+    device_name: String,
+    secret_key: String,
+    cert_fingerprint: Option<String>,
+) -> Result<String> {
+    let mut service = DiscoveryService::with_device_id(device_id, device_name, secret_key);
+    if let Some(fingerprint) = cert_fingerprint {
+        service = service.with_cert_fingerprint(fingerprint);
+    }
+    start_service(service).await
+}
+
+async fn start_service(service: DiscoveryService) -> Result<String> {
     let device_id = service.get_device_id();
 
+    // 이전 서비스가 실행 중이면 태스크가 남지 않도록 먼저 중지
+    stop_discovery().await?;
+
     service.start().await?;
 
     let mut instance = DISCOVERY_SERVICE
@@ -524,26 +1291,46 @@ pub async fn start_discovery(device_name: String, secret_key: String) -> Result<
 
     *instance = Some(service);
 
-    log::info!("Discovery service started with device ID: {}", device_id);
+    tracing::info!("Discovery service started with device ID: {}", device_id);
 
     Ok(device_id)
 }
 
-/// 발견 서비스를 중지합니다.
-pub fn stop_discovery() -> Result<()> {
-    let mut instance = DISCOVERY_SERVICE
+/// 발견 서비스를 중지하고 송수신 태스크가 끝날 때까지 기다립니다.
+pub async fn stop_discovery() -> Result<()> {
+    let service = DISCOVERY_SERVICE
         .lock()
-        .map_err(|e| anyhow::anyhow!("Failed to acquire discovery lock: {}", e))?;
+        .map_err(|e| anyhow::anyhow!("Failed to acquire discovery lock: {}", e))?
+        .take();
 
-    if let Some(service) = instance.as_ref() {
-        service.stop()?;
-        *instance = None;
-        log::info!("Discovery service stopped");
+    if let Some(service) = service {
+        service.stop().await?;
     }
 
     Ok(())
 }
 
+/// 실행 중인 발견 서비스를 같은 기기 정보로 다시 시작합니다 (비콘 포트 변경 반영).
+///
+/// 발견된 기기 목록은 유지됩니다.
+///
+/// # Returns
+/// * 실행 중이 아니어서 다시 시작하지 않았으면 false
+pub async fn restart_discovery() -> Result<bool> {
+    let service = DISCOVERY_SERVICE
+        .lock()
+        .map_err(|e| anyhow::anyhow!("Failed to acquire discovery lock: {}", e))?
+        .take();
+
+    let Some(service) = service else {
+        return Ok(false);
+    };
+    service.stop().await?;
+    start_service(service).await?;
+
+    Ok(true)
+}
+
 /// 발견된 기기 목록을 가져옵니다.
 pub fn get_discovered_devices() -> Result<Vec<DiscoveredDevice>> {
     let instance = DISCOVERY_SERVICE
@@ -556,3 +1343,211 @@ pub fn get_discovered_devices() -> Result<Vec<DiscoveredDevice>> {
         Ok(Vec::new())
     }
 }
+
+/// 발견된 기기 목록에서 기기를 뺍니다 (신뢰를 철회한 기기).
+pub fn forget_device(device_id: &str) {
+    if let Ok(instance) = DISCOVERY_SERVICE.lock() {
+        if let Some(service) = instance.as_ref() {
+            service.discovered_devices.lock().unwrap().remove(device_id);
+        }
+    }
+}
+
+/// 발견된 기기의 전송 서버 주소를 찾습니다.
+///
+/// 비콘에 포트가 없으면(구버전) 설정의 transfer_port를 사용합니다.
+pub fn resolve_transfer_addr(device_id: &str) -> Result<SocketAddr> {
+    get_discovered_devices()?
+        .into_iter()
+        .find(|d| d.device_id == device_id)
+        .ok_or_else(|| PebbleError::not_found(format!("Device {}", device_id)))?
+        .transfer_addr()
+}
+
+/// 발견 서비스가 실행 중인지 확인합니다.
+pub fn is_running() -> bool {
+    DISCOVERY_SERVICE
+        .lock()
+        .map(|instance| instance.as_ref().is_some_and(|service| service.is_running()))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_beacon_decode_roundtrip() {
+        let beacon = BeaconMessage::new("id".to_string(), "name".to_string(), "secret").unwrap();
+        let decoded = BeaconMessage::decode(beacon.to_json().unwrap().as_bytes()).unwrap();
+        assert!(decoded.verify("secret").unwrap());
+    }
+
+    #[test]
+    fn test_beacon_decode_rejects_malformed_input() {
+        assert!(BeaconMessage::decode(&[0xff, 0xfe]).is_err());
+        assert!(BeaconMessage::decode(&vec![b' '; MAX_BEACON_SIZE + 1]).is_err());
+
+        let long_name = BeaconMessage::new("id".to_string(), "x".repeat(MAX_BEACON_FIELD_LEN + 1), "secret").unwrap();
+        assert!(BeaconMessage::decode(long_name.to_json().unwrap().as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_beacon_fingerprint_is_signed() {
+        let beacon = BeaconMessage::new("id".to_string(), "name".to_string(), "secret")
+            .unwrap()
+            .with_cert_fingerprint("ab".repeat(32), "secret")
+            .unwrap();
+        let decoded = BeaconMessage::decode(beacon.to_json().unwrap().as_bytes()).unwrap();
+        assert!(decoded.verify("secret").unwrap());
+        assert_eq!(decoded.cert_fingerprint, Some("ab".repeat(32)));
+
+        // 구버전 기기는 기본 서명만 검증하므로 그대로 통과
+        let mut legacy = decoded.clone();
+        legacy.cert_fingerprint = None;
+        legacy.fingerprint_signature = None;
+        assert!(legacy.verify("secret").unwrap());
+
+        // 핑거프린트만 바꿔치기하면 거부
+        let mut forged = decoded;
+        forged.cert_fingerprint = Some("cd".repeat(32));
+        assert!(!forged.verify("secret").unwrap());
+    }
+
+    #[test]
+    fn test_beacon_port_is_signed() {
+        let beacon = BeaconMessage::new("id".to_string(), "name".to_string(), "secret")
+            .unwrap()
+            .with_transfer_port(41234, "secret")
+            .unwrap();
+        let decoded = BeaconMessage::decode(beacon.to_json().unwrap().as_bytes()).unwrap();
+        assert!(decoded.verify("secret").unwrap());
+
+        let device = DiscoveredDevice::new(&decoded, "192.168.0.5".to_string());
+        assert_eq!(device.transfer_addr().unwrap().to_string(), "192.168.0.5:41234");
+
+        // 포트만 바꿔치기하면 거부
+        let mut forged = decoded;
+        forged.transfer_port = Some(22);
+        assert!(!forged.verify("secret").unwrap());
+    }
+
+    #[test]
+    fn test_beacon_capabilities_are_signed() {
+        let beacon = BeaconMessage::new("id".to_string(), "name".to_string(), "secret")
+            .unwrap()
+            .with_capabilities(&[Capability::TextMessages, Capability::FolderTransfer], "secret")
+            .unwrap();
+        let mut decoded = BeaconMessage::decode(beacon.to_json().unwrap().as_bytes()).unwrap();
+        assert!(decoded.verify("secret").unwrap());
+
+        // 이 버전이 모르는 기능 이름은 무시
+        decoded.capabilities = vec!["text_messages".to_string(), "teleport".to_string()];
+        decoded.capabilities_signature = Some(BeaconMessage::generate_signature(&decoded.capabilities_data(), "secret").unwrap());
+        assert!(decoded.verify("secret").unwrap());
+        let device = DiscoveredDevice::new(&decoded, "192.168.0.5".to_string());
+        assert_eq!(device.capabilities, Some(vec![Capability::TextMessages]));
+        assert!(device.supports(Capability::TextMessages));
+        assert!(!device.supports(Capability::Compression));
+
+        // 서명 없이 기능을 덧붙이면 거부, 기능 목록이 없는 구버전 기기는 막지 않음
+        let mut forged = BeaconMessage::new("id".to_string(), "name".to_string(), "secret").unwrap();
+        forged.capabilities.push("compression".to_string());
+        assert!(!forged.verify("secret").unwrap());
+        let old = BeaconMessage::new("id".to_string(), "name".to_string(), "secret").unwrap();
+        assert!(DiscoveredDevice::new(&old, "192.168.0.6".to_string()).supports(Capability::Compression));
+    }
+
+    #[test]
+    fn test_beacon_limiter() {
+        let start = Instant::now();
+        let mut limiter = BeaconLimiter::new(start);
+        let flooder: IpAddr = "192.168.0.66".parse().unwrap();
+        let peer: IpAddr = "192.168.0.7".parse().unwrap();
+
+        let allowed = (0..100).filter(|_| limiter.allow(flooder, start)).count();
+        assert_eq!(allowed, MAX_BEACONS_PER_SOURCE as usize);
+        assert!(limiter.allow(peer, start));
+
+        // 구간이 지나면 다시 허용
+        assert!(limiter.allow(flooder, start + RATE_WINDOW));
+
+        // 주소를 바꿔 가며 보내도 전체 예산을 넘지 못함
+        let mut limiter = BeaconLimiter::new(start);
+        let allowed = (0..1000u32)
+            .filter(|i| limiter.allow(IpAddr::from((i + 1).to_be_bytes()), start))
+            .count();
+        assert_eq!(allowed, BEACON_VERIFY_BUDGET as usize);
+
+        assert!(beacon_jitter(5) <= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_sealed_beacon_roundtrip() {
+        let beacon = BeaconMessage::new("sealed-id".to_string(), "Laptop".to_string(), "secret")
+            .unwrap()
+            .with_transfer_port(41234, "secret")
+            .unwrap();
+        let packet = beacon.seal("secret").unwrap();
+        assert!(!String::from_utf8_lossy(&packet).contains("Laptop"));
+        assert_ne!(packet, beacon.seal("secret").unwrap());
+
+        let opened = BeaconMessage::open(&packet, "secret").unwrap();
+        assert_eq!(opened.device_name, "Laptop");
+        assert_eq!(opened.transfer_port, Some(41234));
+        assert!(opened.verify("secret").unwrap());
+
+        // 다른 비밀 키나 변조된 패킷은 거부, 평문 비콘은 그대로 받음
+        assert!(BeaconMessage::open(&packet, "other").is_err());
+        let mut tampered = packet.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(BeaconMessage::open(&tampered, "secret").is_err());
+        assert!(BeaconMessage::open(beacon.to_json().unwrap().as_bytes(), "secret").is_ok());
+    }
+
+    #[test]
+    fn test_private_beacon_hides_name() {
+        let beacon = BeaconMessage::new_private("device-1", "secret").unwrap();
+        let json = beacon.to_json().unwrap();
+        assert!(!json.contains("device-1"));
+
+        let decoded = BeaconMessage::decode(json.as_bytes()).unwrap();
+        assert!(decoded.private);
+        assert!(decoded.device_name.is_empty());
+        assert!(decoded.verify("secret").unwrap());
+        assert_eq!(decoded.device_id, private_beacon_id("device-1", "secret").unwrap());
+        assert_ne!(decoded.device_id, private_beacon_id("device-1", "other").unwrap());
+
+        // 공개 비콘에는 필드가 실리지 않음
+        let public = BeaconMessage::new("id".to_string(), "name".to_string(), "secret").unwrap();
+        assert!(!public.to_json().unwrap().contains("private"));
+    }
+
+    #[test]
+    fn test_device_tracks_multiple_addresses() {
+        let beacon = BeaconMessage::new("id".to_string(), "name".to_string(), "secret")
+            .unwrap()
+            .with_transfer_port(41234, "secret")
+            .unwrap();
+        let mut device = DiscoveredDevice::new(&beacon, "192.168.0.5".to_string());
+
+        // 유선으로도 비콘이 도착하면 두 주소를 모두 유지하고, 아는 주소로 돌아오는 것은 새 주소가 아님
+        assert!(device.observe_address("10.0.0.5", 100));
+        assert!(!device.observe_address("192.168.0.5", 101));
+        let addrs: Vec<String> = device.transfer_addrs().unwrap().iter().map(|a| a.to_string()).collect();
+        assert_eq!(addrs, vec!["192.168.0.5:41234", "10.0.0.5:41234"]);
+
+        // 오래 비콘이 오지 않은 주소는 지우지만 현재 주소는 남김
+        device.expire_addresses(100 + 31, 30);
+        assert_eq!(device.addresses.len(), 1);
+        device.expire_addresses(1000, 30);
+        assert_eq!(device.addresses[0].ip_address, "192.168.0.5");
+    }
+
+    #[test]
+    fn test_verify_handles_extreme_timestamp() {
+        let mut beacon = BeaconMessage::new("id".to_string(), "name".to_string(), "secret").unwrap();
+        beacon.timestamp = u64::MAX;
+        assert!(!beacon.verify("secret").unwrap());
+    }
+}
@@ -0,0 +1,224 @@
+use std::io::ErrorKind;
+
+/// Flutter에 노출되는 Pebble 공용 에러 타입
+///
+/// 모든 FRB API는 `Result<T, PebbleError>`를 반환하며,
+/// Dart 측에서는 variant별로 에러 종류를 구분하여 처리할 수 있습니다.
+///
+/// # Examples
+/// ```dart
+/// try {
+///   await api.sendFile(serverIp: "192.168.1.100", filePath: "/path/to/file.pdf");
+/// } on PebbleError_Network catch (e) {
+///   print("Network problem: ${e.message}");
+/// } on PebbleError_Rejected catch (e) {
+///   print("Rejected by peer: ${e.reason}");
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum PebbleError {
+    /// 파일 시스템 I/O 에러
+    #[error("I/O error: {message}")]
+    Io { message: String },
+
+    /// SQLite DB 에러
+    #[error("Database error: {message}")]
+    Database { message: String },
+
+    /// 연결 실패, 연결 끊김 등 네트워크 에러
+    #[error("Network error: {message}")]
+    Network { message: String },
+
+    /// TLS 핸드셰이크 및 인증서 검증 에러
+    #[error("TLS error: {message}")]
+    Tls { message: String },
+
+    /// 상대 기기가 요청을 거부함
+    #[error("Rejected: {reason}")]
+    Rejected { reason: String },
+
+    /// 파일, 기기, 레코드 등을 찾을 수 없음
+    #[error("Not found: {what}")]
+    NotFound { what: String },
+
+    /// 잘못된 인자 (주소 형식, 경로 등)
+    #[error("Invalid argument: {message}")]
+    InvalidArgument { message: String },
+
+    /// 상대 기기의 저장 한도를 넘어 수신측이 거부함
+    #[error("Quota exceeded: {message}")]
+    QuotaExceeded { message: String },
+
+    /// 프로토콜 위반 또는 예상하지 못한 메시지
+    #[error("Protocol error: {message}")]
+    Protocol { message: String },
+
+    /// 사용자 요청으로 작업이 취소됨
+    #[error("Cancelled: {message}")]
+    Cancelled { message: String },
+
+    /// 분류되지 않은 내부 에러
+    #[error("Internal error: {message}")]
+    Internal { message: String },
+}
+
+/// FRB API용 Result 별칭
+pub type PebbleResult<T> = std::result::Result<T, PebbleError>;
+
+impl PebbleError {
+    pub fn io(message: impl Into<String>) -> Self {
+        Self::Io { message: message.into() }
+    }
+
+    pub fn network(message: impl Into<String>) -> Self {
+        Self::Network { message: message.into() }
+    }
+
+    pub fn rejected(reason: impl Into<String>) -> Self {
+        Self::Rejected { reason: reason.into() }
+    }
+
+    pub fn not_found(what: impl Into<String>) -> Self {
+        Self::NotFound { what: what.into() }
+    }
+
+    pub fn invalid_argument(message: impl Into<String>) -> Self {
+        Self::InvalidArgument { message: message.into() }
+    }
+
+    pub fn quota_exceeded(message: impl Into<String>) -> Self {
+        Self::QuotaExceeded { message: message.into() }
+    }
+
+    pub fn protocol(message: impl Into<String>) -> Self {
+        Self::Protocol { message: message.into() }
+    }
+
+    pub fn cancelled(message: impl Into<String>) -> Self {
+        Self::Cancelled { message: message.into() }
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::Internal { message: message.into() }
+    }
+
+    /// io::Error를 종류(kind)에 따라 분류합니다.
+    fn classify_io(e: &std::io::Error, message: String) -> Self {
+        // TLS 에러는 tokio-rustls에서 io::Error로 감싸져 전달됨
+        if e.get_ref().is_some_and(|inner| inner.is::<rustls::Error>()) {
+            return Self::Tls { message };
+        }
+
+        match e.kind() {
+            ErrorKind::NotFound => Self::NotFound { what: message },
+            ErrorKind::ConnectionRefused
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::NotConnected
+            | ErrorKind::AddrInUse
+            | ErrorKind::AddrNotAvailable
+            | ErrorKind::BrokenPipe
+            | ErrorKind::TimedOut
+            | ErrorKind::UnexpectedEof => Self::Network { message },
+            _ => Self::Io { message },
+        }
+    }
+}
+
+impl From<std::io::Error> for PebbleError {
+    fn from(e: std::io::Error) -> Self {
+        let message = e.to_string();
+        Self::classify_io(&e, message)
+    }
+}
+
+impl From<rusqlite::Error> for PebbleError {
+    fn from(e: rusqlite::Error) -> Self {
+        match e {
+            rusqlite::Error::QueryReturnedNoRows => Self::NotFound {
+                what: "No matching database record".to_string(),
+            },
+            other => Self::Database { message: other.to_string() },
+        }
+    }
+}
+
+impl From<std::net::AddrParseError> for PebbleError {
+    fn from(e: std::net::AddrParseError) -> Self {
+        Self::InvalidArgument { message: e.to_string() }
+    }
+}
+
+/// 내부 모듈의 anyhow 에러를 원인 체인을 기준으로 분류합니다.
+///
+/// 체인에서 가장 먼저 발견되는 구체 타입(PebbleError, io, rusqlite, rustls)을
+/// 기준으로 variant를 정하고, 메시지에는 전체 컨텍스트 체인을 보존합니다.
+impl From<anyhow::Error> for PebbleError {
+    fn from(e: anyhow::Error) -> Self {
+        let message = format!("{:#}", e);
+
+        for cause in e.chain() {
+            if let Some(pebble) = cause.downcast_ref::<PebbleError>() {
+                return pebble.clone();
+            }
+            if let Some(io) = cause.downcast_ref::<std::io::Error>() {
+                return Self::classify_io(io, message);
+            }
+            if let Some(db) = cause.downcast_ref::<rusqlite::Error>() {
+                return match db {
+                    rusqlite::Error::QueryReturnedNoRows => Self::NotFound { what: message },
+                    _ => Self::Database { message },
+                };
+            }
+            if cause.is::<rustls::Error>() {
+                return Self::Tls { message };
+            }
+        }
+
+        Self::Internal { message }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_io_error_kinds() {
+        let e: PebbleError = std::io::Error::new(ErrorKind::ConnectionRefused, "refused").into();
+        assert!(matches!(e, PebbleError::Network { .. }));
+
+        let e: PebbleError = std::io::Error::new(ErrorKind::NotFound, "missing").into();
+        assert!(matches!(e, PebbleError::NotFound { .. }));
+
+        let e: PebbleError = std::io::Error::new(ErrorKind::PermissionDenied, "denied").into();
+        assert!(matches!(e, PebbleError::Io { .. }));
+    }
+
+    #[test]
+    fn test_anyhow_chain_keeps_context() {
+        let result: anyhow::Result<()> = Err(std::io::Error::new(ErrorKind::TimedOut, "timed out"))
+            .context("Failed to connect to 10.0.0.2:37846");
+
+        match PebbleError::from(result.unwrap_err()) {
+            PebbleError::Network { message } => {
+                assert!(message.contains("Failed to connect"));
+                assert!(message.contains("timed out"));
+            }
+            other => panic!("unexpected variant: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_anyhow_preserves_pebble_error() {
+        let e = anyhow::Error::new(PebbleError::rejected("disk full")).context("Transfer failed");
+        assert_eq!(PebbleError::from(e), PebbleError::rejected("disk full"));
+    }
+
+    #[test]
+    fn test_anyhow_database_error() {
+        let e = anyhow::Error::new(rusqlite::Error::InvalidQuery).context("query failed");
+        assert!(matches!(PebbleError::from(e), PebbleError::Database { .. }));
+    }
+}
@@ -0,0 +1,245 @@
+//! 알림용 이벤트 스트림
+//!
+//! 청크 단위 진행률과 달리 사용자에게 알릴 만한 일(전송 완료/실패, 새 기기, 이름 충돌, 승인 대기)만
+//! 전달합니다. Flutter는 OS 알림을 띄우는 데, pebbled는 로그로 남기는 데 사용합니다.
+
+use serde::{Deserialize, Serialize};
+
+use super::presence::PresenceChange;
+use super::registry::TransferDirection;
+use super::transfer::TransferStatus;
+use super::util::Subscribers;
+
+/// 알림 이벤트
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum PebbleEvent {
+    /// 전송이 끝까지 완료됨
+    TransferCompleted {
+        transfer_id: String,
+        direction: TransferDirection,
+        /// 상대 기기 주소 (IP:Port)
+        peer: String,
+        /// 송신 파일 또는 수신 파일이 저장된 경로
+        file_path: String,
+        total_bytes: u64,
+        /// 소요 시간 (초)
+        elapsed_secs: u64,
+    },
+
+    /// 전송이 실패하거나 취소됨
+    TransferFailed {
+        transfer_id: String,
+        direction: TransferDirection,
+        peer: String,
+        file_path: String,
+        /// Failed 또는 Cancelled
+        status: TransferStatus,
+        /// 전송 중단 시점까지 전송된 바이트 수 (이어받기 가능)
+        bytes_transferred: u64,
+        error: Option<String>,
+    },
+
+    /// 같은 비밀 키를 쓰는 기기가 처음 발견되어 전송할 수 있게 됨
+    DevicePaired {
+        device_id: String,
+        device_name: String,
+        ip_address: String,
+    },
+
+    /// 기기의 비콘이 `device_timeout_secs` 동안 보이지 않아 목록에서 뺌
+    DeviceOffline {
+        device_id: String,
+        device_name: String,
+    },
+
+    /// 분실한 기기의 신뢰를 철회함 (이 기기에서 요청했거나 다른 기기가 알려 옴)
+    DeviceRevoked {
+        device_id: String,
+        /// 철회를 요청한 기기 ID
+        issued_by: String,
+    },
+
+    /// 다른 기기가 철회 요청을 받아 적용했음을 확인함
+    RevocationConfirmed {
+        /// 철회한 기기 ID
+        device_id: String,
+        /// 확인한 기기 ID
+        peer_device_id: String,
+    },
+
+    /// 다른 기기가 이 기기의 신뢰를 철회함 (앱은 사용자에게 확인을 받아
+    /// `confirm_pairing_wipe` 또는 `dismiss_pairing_wipe`를 호출해야 함)
+    WipeRequested {
+        revocation_id: String,
+        issued_by: String,
+    },
+
+    /// 이 기기가 다른 기기에서 철회되어 페어링 기록을 지우고 기기 탐색을 멈춤
+    /// (앱은 저장해 둔 비밀 키를 지워야 함)
+    PairingWiped {
+        issued_by: String,
+    },
+
+    /// 접속 규칙(`presence_rules`)의 동작을 실행함
+    PresenceRuleFired {
+        rule: String,
+        device_id: String,
+        change: PresenceChange,
+        /// 성공했으면 결과 요약
+        detail: Option<String>,
+        /// 실패했으면 사유
+        error: Option<String>,
+    },
+
+    /// 수신 파일과 같은 이름의 파일이 있어 다른 이름으로 저장함
+    ConflictDetected {
+        /// 원래 저장하려던 경로
+        path: String,
+        /// 실제로 저장한 경로
+        saved_as: String,
+    },
+
+    /// 받을 파일이 다른 앱에 잠겨 있어 임시 파일로 받고 잠금이 풀리기를 기다림
+    DestinationLocked {
+        transfer_id: String,
+        /// 원래 저장하려던 경로
+        path: String,
+        /// 대신 받은 임시 파일 경로
+        staged_path: String,
+    },
+
+    /// 받은 파일을 암호화된 대기 영역에 두고 승인을 기다림
+    FileStaged {
+        staged_id: String,
+        file_name: String,
+        file_size: u64,
+        sender_device_id: Option<String>,
+    },
+
+    /// 받은 파일이 검사 명령(`scan_hook`)을 통과하지 못해 격리함
+    FileQuarantined {
+        transfer_id: String,
+        /// 받은 파일 경로
+        path: String,
+        /// 격리한 파일 경로
+        quarantined_path: String,
+        /// 검사 명령의 종료 상태와 출력 첫 줄
+        reason: String,
+    },
+
+    /// 감시 폴더를 읽을 수 없게 되어 감시를 멈춤 (외장 드라이브 분리 등)
+    WatchRootLost {
+        root: String,
+    },
+
+    /// 사라졌던 감시 폴더가 다시 나타나 재조정 스캔 후 감시를 재개함
+    WatchRootRestored {
+        root: String,
+        /// 재조정 스캔 결과 (새 파일, 바뀐 파일, 사라진 파일 수)
+        added: u32,
+        modified: u32,
+        deleted: u32,
+    },
+
+    /// 절전에서 복귀하여 기기 탐색과 진행 중인 전송을 다시 확인함
+    SystemWoke {
+        /// 감지한 절전 시간 (초, 대략값)
+        slept_secs: u64,
+    },
+}
+
+impl PebbleEvent {
+    /// 로그에 남길 한 줄 요약
+    pub fn summary(&self) -> String {
+        match self {
+            Self::TransferCompleted { direction, peer, file_path, total_bytes, .. } => {
+                format!("{:?} of {} ({} bytes) with {} completed", direction, file_path, total_bytes, peer)
+            }
+            Self::TransferFailed { direction, peer, file_path, status, error, .. } => format!(
+                "{:?} of {} with {} {}: {}",
+                direction,
+                file_path,
+                peer,
+                status.to_string().to_lowercase(),
+                error.as_deref().unwrap_or("interrupted")
+            ),
+            Self::DevicePaired { device_id, device_name, ip_address } => {
+                format!("Device {} ({}) available at {}", device_name, device_id, ip_address)
+            }
+            Self::DeviceOffline { device_id, device_name } => {
+                format!("Device {} ({}) went offline", device_name, device_id)
+            }
+            Self::DeviceRevoked { device_id, issued_by } => {
+                format!("Device {} is no longer trusted (revoked by {})", device_id, issued_by)
+            }
+            Self::RevocationConfirmed { device_id, peer_device_id } => {
+                format!("{} confirmed the revocation of {}", peer_device_id, device_id)
+            }
+            Self::WipeRequested { issued_by, .. } => {
+                format!("{} revoked this device, waiting for confirmation to wipe pairing", issued_by)
+            }
+            Self::PairingWiped { issued_by } => format!("This device was revoked by {}, pairing wiped", issued_by),
+            Self::PresenceRuleFired { rule, device_id, change, detail, error } => match error {
+                Some(error) => format!("Presence rule {} for {} ({:?}) failed: {}", rule, device_id, change, error),
+                None => format!(
+                    "Presence rule {} for {} ({:?}) ran: {}",
+                    rule, device_id, change, detail.as_deref().unwrap_or("ok")
+                ),
+            },
+            Self::ConflictDetected { path, saved_as } => {
+                format!("{} already exists, saved as {}", path, saved_as)
+            }
+            Self::DestinationLocked { transfer_id, path, .. } => {
+                format!("{} is in use by another app, transfer {} waiting to replace it", path, transfer_id)
+            }
+            Self::FileStaged { file_name, file_size, .. } => {
+                format!("{} ({} bytes) is waiting for approval", file_name, file_size)
+            }
+            Self::FileQuarantined { path, quarantined_path, reason, .. } => {
+                format!("{} failed the file scan ({}), quarantined as {}", path, reason, quarantined_path)
+            }
+            Self::WatchRootLost { root } => format!("Watch folder {} is unavailable, watching paused", root),
+            Self::WatchRootRestored { root, added, modified, deleted } => format!(
+                "Watch folder {} is available again ({} added, {} modified, {} deleted)",
+                root, added, modified, deleted
+            ),
+            Self::SystemWoke { slept_secs } => format!("System woke after ~{}s", slept_secs),
+        }
+    }
+}
+
+static SUBSCRIBERS: Subscribers<PebbleEvent> = Subscribers::new();
+
+/// 이벤트 구독자를 등록합니다.
+pub fn subscribe<F>(subscriber: F)
+where
+    F: Fn(&PebbleEvent) -> bool + Send + 'static,
+{
+    SUBSCRIBERS.subscribe(subscriber);
+}
+
+/// 구독자 모두에게 이벤트를 전달합니다.
+pub fn emit(event: PebbleEvent) {
+    tracing::debug!("Event: {}", event.summary());
+
+    SUBSCRIBERS.notify(&event);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_json_is_tagged() {
+        let event = PebbleEvent::ConflictDetected {
+            path: "/dl/a.txt".to_string(),
+            saved_as: "/dl/a (2).txt".to_string(),
+        };
+
+        let json: serde_json::Value = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], "ConflictDetected");
+        assert_eq!(json["saved_as"], "/dl/a (2).txt");
+        assert!(event.summary().contains("a (2).txt"));
+    }
+}
@@ -0,0 +1,117 @@
+use anyhow::Result;
+use std::future::Future;
+use std::time::Duration;
+
+use super::error::PebbleError;
+
+/// 전송 장애 주입 계획
+///
+/// 이어받기, 재시도, 해시 불일치 처리를 재현 가능하게 시험하기 위해
+/// TransferClient/TransferServer에 `set_fault_plan`으로 설정합니다. 서버 없이 스트림을 직접
+/// 처리할 때(`TransferServer::handle_stream`)는 `scope` 안에서 실행하면 수신측에 적용됩니다.
+/// 각 측은 자신에게 해당하는 항목만 적용합니다.
+/// 테스트 빌드와 `fault-injection` 기능을 켠 빌드에만 포함됩니다.
+///
+/// | 항목 | 송신측 | 수신측 |
+/// |------|--------|--------|
+/// | `drop_after_chunks` | N개 전송 후 연결 끊기 | N개 수신 후 연결 끊기 |
+/// | `corrupt_chunk` | 해당 청크의 데이터를 해시 계산 후 변조 | - |
+/// | `ack_delay` | - | ACK 전송 전 지연 |
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FaultPlan {
+    /// 이 개수만큼 청크를 처리한 뒤 연결을 끊음
+    pub drop_after_chunks: Option<u64>,
+
+    /// 이 인덱스의 청크 데이터를 변조함
+    pub corrupt_chunk: Option<u64>,
+
+    /// ACK마다 추가할 지연 시간
+    pub ack_delay: Option<Duration>,
+}
+
+impl FaultPlan {
+    /// 장애가 하나도 설정되지 않았는지 확인합니다.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// 처리한 청크 수가 한도에 도달했으면 연결 끊김 에러를 반환합니다.
+    pub fn check_drop(&self, processed_chunks: u64) -> Result<()> {
+        match self.drop_after_chunks {
+            Some(limit) if processed_chunks >= limit => {
+                tracing::warn!("Fault injection: dropping connection after {} chunks", processed_chunks);
+                Err(PebbleError::network(format!(
+                    "Injected fault: connection dropped after {} chunks", processed_chunks
+                )).into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// 대상 청크이면 데이터의 첫 바이트를 뒤집어 돌려줍니다.
+    pub fn corrupted(&self, chunk_index: u64, mut data: Vec<u8>) -> Vec<u8> {
+        if self.corrupt_chunk == Some(chunk_index) {
+            if let Some(first) = data.first_mut() {
+                tracing::warn!("Fault injection: corrupting chunk {}", chunk_index);
+                *first ^= 0xff;
+            }
+        }
+        data
+    }
+
+    /// ACK 지연이 설정되어 있으면 대기합니다.
+    pub async fn delay_ack(&self) {
+        if let Some(delay) = self.ack_delay {
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+tokio::task_local! {
+    /// 현재 작업의 수신측 장애 주입 계획
+    static RECEIVER_PLAN: FaultPlan;
+}
+
+/// 수신측 처리가 `plan`을 따르도록 하고 `future`를 실행합니다.
+pub async fn scope<F: Future>(plan: FaultPlan, future: F) -> F::Output {
+    RECEIVER_PLAN.scope(plan, future).await
+}
+
+/// 현재 작업에 적용된 수신측 계획 (`scope` 밖이면 빈 계획)
+pub fn receiver_plan() -> FaultPlan {
+    RECEIVER_PLAN.try_with(FaultPlan::clone).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_drop() {
+        let plan = FaultPlan {
+            drop_after_chunks: Some(2),
+            ..Default::default()
+        };
+        assert!(plan.check_drop(1).is_ok());
+        let err = plan.check_drop(2).unwrap_err();
+        assert!(matches!(PebbleError::from(err), PebbleError::Network { .. }));
+        assert!(FaultPlan::default().check_drop(100).is_ok());
+    }
+
+    #[test]
+    fn test_corrupt_only_target_chunk() {
+        let plan = FaultPlan {
+            corrupt_chunk: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(plan.corrupted(0, vec![1u8, 2, 3]), vec![1, 2, 3]);
+        assert_eq!(plan.corrupted(1, vec![1u8, 2, 3]), vec![0xfe, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_receiver_plan_follows_scope() {
+        let plan = FaultPlan { drop_after_chunks: Some(3), ..Default::default() };
+        assert!(receiver_plan().is_empty());
+        assert_eq!(scope(plan.clone(), async { receiver_plan() }).await, plan);
+    }
+}
@@ -0,0 +1,133 @@
+//! 이벤트 피드 (WebSocket Event Feed)
+//!
+//! 제어 API(`control`)의 `GET /v1/events`를 WebSocket으로 업그레이드하여, Flutter UI가
+//! `create_event_stream`으로 받는 것과 같은 알림 이벤트(`PebbleEvent` JSON)와 진행 중인 전송의
+//! 진행률을 보냅니다. 외부 대시보드나 데스크톱 알림 도구가 데몬의 상태를 따라갈 수 있습니다.
+//!
+//! # Process Flow
+//! 1. 제어 API가 토큰을 확인한 뒤 `serve`로 연결을 넘김 (브라우저는 `?token=`으로 전달)
+//! 2. 101 응답으로 WebSocket 핸드셰이크를 마치고 이벤트 구독
+//! 3. 이벤트마다 `PebbleEvent` JSON을 텍스트 메시지로 전송
+//! 4. 전송이 진행 중이면 `PROGRESS_INTERVAL`마다 `{"type": "Progress", "transfers": [...]}` 전송
+//!    (마지막 전송이 끝나면 빈 목록을 한 번 더 보냄)
+//! 5. 클라이언트가 닫거나 보내기에 실패하면 종료 (구독은 다음 이벤트 때 해제됨)
+//!
+//! # Security
+//! - 클라이언트가 느려 대기열(`QUEUE_SIZE`)이 가득 차면 이벤트를 버림 (데몬 메모리 보호)
+
+use anyhow::Result;
+use futures::{SinkExt, StreamExt};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::{Message, Role};
+use tokio_tungstenite::WebSocketStream;
+
+use super::events;
+use super::registry::{self, ActiveTransfer};
+
+/// 진행률을 보내는 주기
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 클라이언트 하나에 쌓아 둘 수 있는 이벤트 수
+const QUEUE_SIZE: usize = 256;
+
+/// 진행률 메시지
+fn progress_message(transfers: &[ActiveTransfer]) -> String {
+    serde_json::json!({ "type": "Progress", "transfers": transfers }).to_string()
+}
+
+/// WebSocket 핸드셰이크를 마치고 연결이 닫힐 때까지 이벤트를 보냅니다.
+///
+/// # Arguments
+/// * `key` - 요청의 `Sec-WebSocket-Key`
+pub(crate) async fn serve(mut stream: TcpStream, key: &str) -> Result<()> {
+    let response = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        derive_accept_key(key.as_bytes())
+    );
+    stream.write_all(response.as_bytes()).await?;
+
+    let (mut outgoing, mut incoming) = WebSocketStream::from_raw_socket(stream, Role::Server, None).await.split();
+
+    let (tx, mut rx) = mpsc::channel(QUEUE_SIZE);
+    events::subscribe(move |event| match serde_json::to_string(event) {
+        Ok(json) => match tx.try_send(json) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                tracing::debug!("Event feed client is behind, dropping {}", event.summary());
+                true
+            }
+            Err(TrySendError::Closed(_)) => false,
+        },
+        Err(e) => {
+            tracing::error!("Failed to serialize event: {}", e);
+            true
+        }
+    });
+
+    let mut ticker = tokio::time::interval(PROGRESS_INTERVAL);
+    let mut was_active = false;
+    loop {
+        tokio::select! {
+            Some(json) = rx.recv() => outgoing.send(Message::Text(json)).await?,
+            _ = ticker.tick() => {
+                let transfers = registry::global().list();
+                if !transfers.is_empty() || was_active {
+                    outgoing.send(Message::Text(progress_message(&transfers))).await?;
+                }
+                was_active = !transfers.is_empty();
+            }
+            message = incoming.next() => match message {
+                None | Some(Ok(Message::Close(_))) => break,
+                Some(Err(e)) => return Err(e.into()),
+                // Ping에는 tungstenite가 다음 전송 때 Pong으로 답함
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::control;
+    use crate::api::events::PebbleEvent;
+
+    #[tokio::test]
+    async fn test_feed_streams_events() {
+        crate::loopback::use_temp_environment();
+        let token = "f".repeat(32);
+        let addr = control::start_listener("127.0.0.1:0".parse().unwrap(), token.clone()).await.unwrap().addr;
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let url = format!("ws://{}/v1/events?token={}", addr, token);
+        let (mut ws, _) = tokio_tungstenite::client_async(url, stream).await.unwrap();
+
+        let event = PebbleEvent::ConflictDetected {
+            path: "/dl/feed.txt".to_string(),
+            saved_as: "/dl/feed (2).txt".to_string(),
+        };
+        events::emit(event.clone());
+
+        // 다른 테스트의 이벤트나 진행률이 섞일 수 있으므로 보낸 이벤트가 올 때까지 읽음
+        let received = tokio::time::timeout(Duration::from_secs(5), async {
+            while let Some(Ok(Message::Text(json))) = ws.next().await {
+                if serde_json::from_str::<PebbleEvent>(&json).ok().as_ref() == Some(&event) {
+                    return true;
+                }
+            }
+            false
+        })
+        .await;
+        assert_eq!(received.ok(), Some(true));
+
+        // 토큰이 없으면 업그레이드하지 않음
+        let stream = TcpStream::connect(addr).await.unwrap();
+        assert!(tokio_tungstenite::client_async(format!("ws://{}/v1/events", addr), stream).await.is_err());
+    }
+}
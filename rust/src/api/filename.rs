@@ -0,0 +1,326 @@
+//! 수신 파일 이름 정리 및 충돌 회피
+//!
+//! 상대 기기가 보낸 파일 이름은 신뢰할 수 없고 OS마다 허용 문자가 다르므로,
+//! 저장하기 전에 어느 플랫폼에서도 안전한 이름으로 바꾸고 기존 파일과 겹치지 않는
+//! 경로("report (2).pdf")를 고릅니다.
+//!
+//! 여러 기기가 같은 이름으로 동시에 보내면 아직 파일이 만들어지기 전이라 같은 경로를 고를 수
+//! 있으므로, 수신 중인 경로를 점유(`DestinationClaim`)해 두고 다른 수신은 번호를 붙인 경로로
+//! 받습니다. 한 파일에 두 전송이 섞여 쓰이는 일은 없습니다.
+//!
+//! Linux에서는 `README.md`와 `Readme.md`가 다른 파일이지만 Windows와 macOS에서는 같은 파일이므로,
+//! 대소문자만 다른 이름의 파일이 이미 있으면(`find_case_variant`) 덮어쓰지 않고 설정의
+//! `CaseCollisionPolicy`에 따라 번호를 붙여 받거나, 건너뛰거나, 실패 처리합니다.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use unicode_normalization::UnicodeNormalization;
+
+use super::locked;
+use super::paths;
+
+/// 정리 후 이름이 비어 있을 때 사용하는 이름
+pub const FALLBACK_FILE_NAME: &str = "received_file";
+
+/// 대부분의 파일 시스템이 허용하는 파일 이름 최대 길이 (bytes)
+const MAX_NAME_BYTES: usize = 255;
+
+/// 이 플랫폼의 기본 파일 시스템이 대소문자를 구분하지 않는지 여부 (NTFS, APFS 기본 설정)
+pub const CASE_INSENSITIVE_FS: bool = cfg!(any(windows, target_os = "macos", target_os = "ios"));
+
+/// 충돌 회피 시 시도할 최대 번호
+const MAX_COLLISION_INDEX: u32 = 9999;
+
+/// Windows에서 장치 이름으로 예약된 이름 (확장자와 무관)
+const WINDOWS_RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9", "LPT1",
+    "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// 파일 이름 하나를 모든 플랫폼에서 안전한 형태로 정리합니다.
+///
+/// - 유니코드를 NFC로 정규화 (macOS의 NFD 이름이 다른 파일로 취급되지 않도록)
+/// - 제어 문자 제거, 경로 구분자와 Windows 금지 문자(`<>:"/\|?*`)는 `_`로 치환
+/// - 앞뒤 공백과 끝의 `.` 제거 (Windows에서 무시되어 다른 파일을 가리킬 수 있음)
+/// - Windows 예약 이름(`CON`, `nul.txt` 등)은 앞에 `_`를 붙임
+/// - 확장자를 유지하며 255바이트로 자름
+pub fn sanitize(name: &str) -> String {
+    let cleaned: String = name
+        .nfc()
+        .filter(|c| !c.is_control())
+        .map(|c| if matches!(c, '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*') { '_' } else { c })
+        .collect();
+
+    let trimmed = cleaned.trim().trim_end_matches(['.', ' ']);
+    if trimmed.is_empty() || trimmed.chars().all(|c| c == '.') {
+        return FALLBACK_FILE_NAME.to_string();
+    }
+
+    let stem = trimmed.split('.').next().unwrap_or_default().trim_end();
+    let mut name = if WINDOWS_RESERVED_NAMES.iter().any(|reserved| reserved.eq_ignore_ascii_case(stem)) {
+        format!("_{}", trimmed)
+    } else {
+        trimmed.to_string()
+    };
+
+    truncate_keeping_extension(&mut name, MAX_NAME_BYTES);
+    name
+}
+
+/// 대소문자만 다른 이름의 파일이 이미 있을 때의 처리
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaseCollisionPolicy {
+    /// 번호를 붙인 이름으로 받음 (`README (2).md`)
+    #[default]
+    Rename,
+    /// 받지 않고 기존 파일을 유지
+    Skip,
+    /// 전송(또는 동기화 예상)을 실패 처리
+    Error,
+}
+
+/// 이름을 대소문자 구분 없이 비교하는 키 (NFC 정규화 후 소문자)
+pub fn case_fold(name: &str) -> String {
+    name.nfc().flat_map(char::to_lowercase).collect()
+}
+
+/// `path`와 대소문자만 다른 이름의 파일이 같은 폴더에 있으면 그 경로를 반환합니다.
+///
+/// 유니코드 정규화 형식만 다른 이름(NFD/NFC)은 같은 이름으로 보고 반환하지 않습니다.
+pub fn find_case_variant(path: &Path) -> Option<PathBuf> {
+    let name: String = path.file_name()?.to_string_lossy().nfc().collect();
+    let folded = case_fold(&name);
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+
+    std::fs::read_dir(paths::long_path(dir))
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .find(|existing| case_fold(existing) == folded && existing.nfc().collect::<String>() != name)
+        .map(|existing| path.with_file_name(existing))
+}
+
+/// 상대 기기가 보낸 상대 경로(폴더 전송)를 구성 요소별로 정리합니다.
+///
+/// `..`, 루트, 드라이브 접두사는 제거되므로 결과는 항상 기준 디렉토리 아래를 가리킵니다.
+///
+/// # Returns
+/// * `Option<PathBuf>` - 남는 구성 요소가 없으면 None
+pub fn sanitize_relative_path(path: &str) -> Option<PathBuf> {
+    let components: Vec<String> = path
+        .split(['/', '\\'])
+        .filter(|component| !component.is_empty() && *component != "." && *component != "..")
+        .filter(|component| !(component.len() == 2 && component.ends_with(':')))
+        .map(sanitize)
+        .collect();
+
+    if components.is_empty() {
+        None
+    } else {
+        Some(components.iter().collect())
+    }
+}
+
+/// 수신 중인 저장 경로 (정규화된 경로)
+static CLAIMED: once_cell::sync::Lazy<Mutex<HashSet<String>>> = once_cell::sync::Lazy::new(Default::default);
+
+/// 수신 중인 저장 경로의 점유 (drop 시 해제)
+#[derive(Debug)]
+pub struct DestinationClaim {
+    key: String,
+}
+
+impl Drop for DestinationClaim {
+    fn drop(&mut self) {
+        if let Ok(mut claimed) = CLAIMED.lock() {
+            claimed.remove(&self.key);
+        }
+    }
+}
+
+fn claimed() -> std::sync::MutexGuard<'static, HashSet<String>> {
+    CLAIMED.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// 점유 키 (대소문자를 구분하지 않는 파일 시스템에서는 대소문자만 다른 경로도 같은 키)
+fn claim_key(path: &Path) -> String {
+    let key = paths::normalize(path);
+    if CASE_INSENSITIVE_FS {
+        case_fold(&key)
+    } else {
+        key
+    }
+}
+
+/// 경로를 점유합니다. 다른 수신이 이미 쓰고 있으면 None.
+pub fn claim(path: &Path) -> Option<DestinationClaim> {
+    let key = claim_key(path);
+    // 실패 시 만든 점유가 drop되며 다른 수신의 점유를 해제하지 않도록 먼저 확인
+    let inserted = claimed().insert(key.clone());
+    if inserted {
+        Some(DestinationClaim { key })
+    } else {
+        None
+    }
+}
+
+/// `dir` 아래에 `name`으로 저장할 때 기존 파일, 수신 중인 경로와 모두 겹치지 않는 경로를 골라 점유합니다.
+pub fn claim_unique(dir: &Path, name: &str) -> (PathBuf, DestinationClaim) {
+    let mut claimed = claimed();
+    // 이어받기를 기다리는 임시 파일이 있는 이름도 사용 중으로 봄
+    let path = unique_path_where(dir, name, |candidate| {
+        paths::long_path(candidate).exists()
+            || paths::long_path(locked::staging_path(candidate)).exists()
+            || claimed.contains(&claim_key(candidate))
+    });
+    let key = claim_key(&path);
+    claimed.insert(key.clone());
+    (path, DestinationClaim { key })
+}
+
+/// 경로를 점유합니다. 다른 수신이 쓰고 있으면 같은 폴더에서 번호를 붙인 경로를 점유합니다.
+///
+/// 기존 파일은 덮어쓰는 경로(송신측 경로 그대로 저장)에 사용합니다.
+pub fn claim_or_rename(path: &Path) -> (PathBuf, DestinationClaim) {
+    if let Some(claim) = claim(path) {
+        return (path.to_path_buf(), claim);
+    }
+    let dir = path.parent().unwrap_or(Path::new(""));
+    let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    claim_unique(dir, &name)
+}
+
+/// `dir` 아래에 `name`으로 저장할 때 기존 파일과 겹치지 않는 경로를 반환합니다.
+///
+/// 이미 있으면 확장자 앞에 번호를 붙입니다: `report.pdf` → `report (2).pdf` → `report (3).pdf`
+pub fn unique_path(dir: &Path, name: &str) -> PathBuf {
+    unique_path_where(dir, name, |candidate| paths::long_path(candidate).exists())
+}
+
+/// `taken`이 false인 첫 경로를 `unique_path`와 같은 번호 순서로 찾습니다.
+fn unique_path_where(dir: &Path, name: &str, taken: impl Fn(&Path) -> bool) -> PathBuf {
+    let candidate = dir.join(name);
+    if !taken(&candidate) {
+        return candidate;
+    }
+
+    let (stem, extension) = split_extension(name);
+    for index in 2..=MAX_COLLISION_INDEX {
+        let mut numbered = format!("{} ({}){}", stem, index, extension);
+        truncate_keeping_extension(&mut numbered, MAX_NAME_BYTES);
+
+        let candidate = dir.join(numbered);
+        if !taken(&candidate) {
+            return candidate;
+        }
+    }
+
+    dir.join(format!("{} ({}){}", stem, uuid::Uuid::new_v4(), extension))
+}
+
+/// 이름을 (확장자 앞부분, `.`을 포함한 확장자)로 나눕니다. `.bashrc`처럼 점으로 시작하는 이름은 확장자가 없습니다.
+fn split_extension(name: &str) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(dot) if dot > 0 => name.split_at(dot),
+        _ => (name, ""),
+    }
+}
+
+/// 확장자를 유지하면서 UTF-8 문자 경계에 맞춰 `max_bytes` 이하로 자릅니다.
+fn truncate_keeping_extension(name: &mut String, max_bytes: usize) {
+    if name.len() <= max_bytes {
+        return;
+    }
+
+    let (stem, extension) = split_extension(name);
+    // 확장자가 지나치게 길면 확장자째로 자름
+    let extension = if extension.len() < max_bytes / 2 { extension.to_string() } else { String::new() };
+
+    let mut end = max_bytes - extension.len();
+    while !stem.is_char_boundary(end) {
+        end -= 1;
+    }
+    *name = format!("{}{}", &stem[..end], extension);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_platform_hazards() {
+        assert_eq!(sanitize("report.pdf"), "report.pdf");
+        assert_eq!(sanitize("bad\u{0}na\u{7}me\n.txt"), "badname.txt");
+        assert_eq!(sanitize("..\\..\\evil.sh"), ".._.._evil.sh");
+        assert_eq!(sanitize("  notes.txt. . "), "notes.txt");
+        assert_eq!(sanitize("nul"), "_nul");
+        assert_eq!(sanitize("Com3.tar.gz"), "_Com3.tar.gz");
+        assert_eq!(sanitize("console.log"), "console.log");
+        assert_eq!(sanitize(".."), FALLBACK_FILE_NAME);
+        assert_eq!(sanitize(""), FALLBACK_FILE_NAME);
+
+        // NFD("한") → NFC
+        assert_eq!(sanitize("\u{1112}\u{1161}\u{11AB}.txt"), "한.txt");
+
+        let long = format!("{}.jpeg", "가".repeat(200));
+        let truncated = sanitize(&long);
+        assert!(truncated.len() <= MAX_NAME_BYTES);
+        assert!(truncated.ends_with("가.jpeg"));
+    }
+
+    #[test]
+    fn test_sanitize_relative_path_stays_inside() {
+        assert_eq!(sanitize_relative_path("../a/./b\\c?.txt"), Some(PathBuf::from("a").join("b").join("c_.txt")));
+        assert_eq!(sanitize_relative_path("C:\\Users\\x.txt"), Some(PathBuf::from("Users").join("x.txt")));
+        assert_eq!(sanitize_relative_path("/../.."), None);
+    }
+
+    #[test]
+    fn test_unique_path_numbers_collisions() {
+        let dir = tempfile::TempDir::new().unwrap();
+
+        assert_eq!(unique_path(dir.path(), "report.pdf"), dir.path().join("report.pdf"));
+        std::fs::write(dir.path().join("report.pdf"), b"1").unwrap();
+        assert_eq!(unique_path(dir.path(), "report.pdf"), dir.path().join("report (2).pdf"));
+        std::fs::write(dir.path().join("report (2).pdf"), b"2").unwrap();
+        assert_eq!(unique_path(dir.path(), "report.pdf"), dir.path().join("report (3).pdf"));
+
+        std::fs::write(dir.path().join(".bashrc"), b"").unwrap();
+        assert_eq!(unique_path(dir.path(), ".bashrc"), dir.path().join(".bashrc (2)"));
+    }
+
+    #[test]
+    fn test_concurrent_claims_get_distinct_paths() {
+        let dir = tempfile::TempDir::new().unwrap();
+
+        // 아직 파일이 없어도 수신 중인 경로는 건너뜀
+        let (first, first_claim) = claim_unique(dir.path(), "photo.jpg");
+        let (second, _second_claim) = claim_unique(dir.path(), "photo.jpg");
+        assert_eq!(first, dir.path().join("photo.jpg"));
+        assert_eq!(second, dir.path().join("photo (2).jpg"));
+
+        // 덮어쓰는 경로도 다른 수신이 쓰는 중이면 번호를 붙임
+        let (renamed, _renamed_claim) = claim_or_rename(&first);
+        assert_eq!(renamed, dir.path().join("photo (3).jpg"));
+        assert!(claim(&first).is_none());
+
+        drop(first_claim);
+        assert!(claim(&first).is_some());
+    }
+
+    #[test]
+    fn test_find_case_variant() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("Readme.md"), b"").unwrap();
+        std::fs::write(dir.path().join("\u{1112}\u{1161}\u{11AB}.txt"), b"").unwrap();
+
+        assert_eq!(find_case_variant(&dir.path().join("README.md")), Some(dir.path().join("Readme.md")));
+        assert_eq!(find_case_variant(&dir.path().join("Readme.md")), None);
+        assert_eq!(find_case_variant(&dir.path().join("notes.md")), None);
+        // 정규화 형식만 다른 이름은 대소문자 충돌이 아님
+        assert_eq!(find_case_variant(&dir.path().join("한.txt")), None);
+    }
+}
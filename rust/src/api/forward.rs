@@ -0,0 +1,327 @@
+//! 감시 폴더 자동 전달 (Watch-and-forward)
+//!
+//! 파일 감시가 Pending으로 기록한 파일을 지정한 기기로 자동 전송합니다.
+//! "드롭 폴더"처럼 폴더에 넣기만 하면 상대 기기로 보내지는 가장 단순한 사용 사례입니다.
+//!
+//! # Process Flow
+//! 1. 감시 루트 아래의 Pending 파일을 주기적으로 조회
+//! 2. 쓰기가 끝난(수정 시간이 일정 시간 이상 지난) 파일만 전송
+//! 3. 성공하면 Synced로 표시, 실패하면 지수 백오프로 재시도
+//! 4. 최대 시도 횟수를 넘으면 Failed로 표시
+//! 5. 모든 결과를 전달 기록(JSON 줄)에 남김
+//!
+//! 전송 일정상 중지 시간대이거나 상대 기기의 데이터 사용 한도에 도달하면 전달을 멈춥니다.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::future::Future;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use super::bandwidth;
+use super::config;
+use super::db::{self, FileMetadata, SyncStatus};
+use super::paths;
+use super::priority::TransferPriority;
+use super::schedule::{self, ScheduleWindow};
+use super::transfer::{self, TransferClient};
+use super::util::unix_now;
+
+/// Pending 파일 조회 주기
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 마지막 수정 후 이 시간이 지나야 쓰기가 끝난 것으로 간주 (초)
+const SETTLE_SECS: i64 = 2;
+
+/// 재시도 대기 시간 (첫 재시도 / 최대)
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(2);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(300);
+
+/// 전달 기록 파일 이름 (기본값)
+pub const FORWARD_LOG_FILE_NAME: &str = "forward.jsonl";
+
+/// 자동 전달 설정
+#[derive(Debug, Clone)]
+pub struct ForwardOptions {
+    /// 감시 루트 디렉토리
+    pub root: String,
+
+    /// 수신 기기 주소
+    pub peer: SocketAddr,
+
+    /// 수신 기기 인증서 핑거프린트 (Certificate Pinning)
+    pub fingerprint: Option<String>,
+
+    /// 파일당 최대 전송 시도 횟수
+    pub max_attempts: u32,
+
+    /// 전달 기록 파일 (None이면 로그로만 출력)
+    pub log_path: Option<PathBuf>,
+
+    /// 이 동기화 쌍의 전송 일정 (None이면 전역 설정의 일정)
+    pub schedule: Option<Vec<ScheduleWindow>>,
+}
+
+/// 전달 결과
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ForwardOutcome {
+    /// 전송 성공
+    Sent,
+    /// 실패, 나중에 재시도
+    Retry,
+    /// 최대 시도 횟수 초과로 포기
+    Failed,
+}
+
+/// 전달 기록 한 줄
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ForwardRecord {
+    /// 기록 시각 (Unix timestamp)
+    pub timestamp: i64,
+    pub path: String,
+    pub peer: String,
+    pub file_size: i64,
+    /// 이번 시도 번호 (1부터)
+    pub attempt: u32,
+    pub outcome: ForwardOutcome,
+    pub error: Option<String>,
+}
+
+/// 파일별 재시도 상태
+#[derive(Debug, Clone)]
+struct RetryState {
+    attempts: u32,
+    next_attempt: Instant,
+}
+
+/// 실패 횟수에 따른 재시도 대기 시간 (2초부터 두 배씩, 최대 5분)
+fn retry_delay(attempts: u32) -> Duration {
+    let exponent = attempts.saturating_sub(1).min(16);
+    RETRY_BASE_DELAY.saturating_mul(1 << exponent).min(RETRY_MAX_DELAY)
+}
+
+/// 감시 폴더의 Pending 파일을 상대 기기로 전달합니다.
+pub struct Forwarder {
+    options: ForwardOptions,
+    client: TransferClient,
+    retries: HashMap<String, RetryState>,
+    /// 일정에 따라 중지된 상태 (상태가 바뀔 때만 로그)
+    paused: bool,
+    /// 데이터 사용 한도에 도달해 중지된 상태 (상태가 바뀔 때만 로그)
+    cap_reached: bool,
+}
+
+impl Forwarder {
+    pub fn new(options: ForwardOptions) -> Self {
+        let mut client = TransferClient::new(options.fingerprint.clone());
+        client.set_schedule(options.schedule.clone());
+        // 백그라운드 전달은 사용자가 직접 보내는 전송에 양보
+        client.set_priority(TransferPriority::Low);
+        Self {
+            options,
+            client,
+            retries: HashMap::new(),
+            paused: false,
+            cap_reached: false,
+        }
+    }
+
+    /// `shutdown`이 완료될 때까지 주기적으로 전달합니다.
+    pub async fn run_until<F: Future>(mut self, shutdown: F) -> Result<()> {
+        tokio::pin!(shutdown);
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+
+        loop {
+            tokio::select! {
+                _ = &mut shutdown => break,
+                _ = interval.tick() => {
+                    if let Err(e) = self.run_once().await {
+                        tracing::error!("Forwarding pass failed: {:#}", e);
+                    }
+                }
+            }
+        }
+
+        tracing::info!("Forwarder stopped");
+        Ok(())
+    }
+
+    /// Pending 파일을 한 번 훑어 전송 가능한 파일을 모두 보냅니다.
+    ///
+    /// 일정상 중지 시간대이거나 데이터 사용 한도에 도달했으면 아무것도 보내지 않습니다.
+    ///
+    /// # Returns
+    /// * `Result<Vec<ForwardRecord>>` - 이번 패스에서 시도한 파일의 결과
+    pub async fn run_once(&mut self) -> Result<Vec<ForwardRecord>> {
+        let paused = schedule::current(self.options.schedule.as_deref()).paused;
+        if paused != self.paused {
+            self.paused = paused;
+            if paused {
+                tracing::info!("Forwarding {} paused by schedule", self.options.root);
+            } else {
+                tracing::info!("Forwarding {} resumed by schedule", self.options.root);
+            }
+        }
+        if paused || self.check_cap_reached()? {
+            return Ok(Vec::new());
+        }
+
+        let now = unix_now();
+        let pending: Vec<FileMetadata> = db::list_files_under(&self.options.root)?
+            .into_iter()
+            .filter(|f| f.sync_status == SyncStatus::Pending && now - f.last_modified >= SETTLE_SECS)
+            .collect();
+
+        // Pending이 아닌 파일(다른 곳에서 처리됨)의 재시도 상태 정리
+        self.retries.retain(|path, _| pending.iter().any(|f| &f.path == path));
+
+        let mut records = Vec::new();
+        for file in pending {
+            if self.retries.get(&file.path).is_some_and(|r| Instant::now() < r.next_attempt) {
+                continue;
+            }
+
+            let record = self.forward(&file).await;
+            self.write_record(&record);
+            records.push(record);
+        }
+
+        Ok(records)
+    }
+
+    /// 상대 기기의 데이터 사용 한도에 도달했는지 확인합니다.
+    ///
+    /// 자동 전달은 한도를 무시하지 않으므로, 다음 달이 되거나 한도를 늘릴 때까지 멈춥니다.
+    fn check_cap_reached(&mut self) -> Result<bool> {
+        let caps = config::current().data_caps;
+        let reached = if caps.is_empty() {
+            None
+        } else {
+            let peer_id = transfer::sent_peer_id(&self.options.peer.to_string());
+            bandwidth::reached_cap(&caps, &peer_id, bandwidth::today())?
+        };
+
+        if reached.is_some() != self.cap_reached {
+            self.cap_reached = reached.is_some();
+            match &reached {
+                Some(status) => tracing::warn!(
+                    "Forwarding {} paused: data cap of {} bytes reached ({} bytes used)",
+                    self.options.root, status.cap.limit_bytes, status.used_bytes
+                ),
+                None => tracing::info!("Forwarding {} resumed: under data cap", self.options.root),
+            }
+        }
+        Ok(reached.is_some())
+    }
+
+    /// 파일 하나를 전송하고 결과에 따라 DB 상태를 갱신합니다.
+    async fn forward(&mut self, file: &FileMetadata) -> ForwardRecord {
+        let attempt = self.retries.get(&file.path).map_or(0, |r| r.attempts) + 1;
+        if let Err(e) = mark_if_unchanged(file, SyncStatus::Transferring) {
+            tracing::warn!("Failed to mark {} as transferring: {:#}", file.path, e);
+        }
+        let result = self.client.send_file(self.options.peer, &file.path).await;
+
+        let (outcome, error) = match result {
+            Ok(()) => {
+                self.retries.remove(&file.path);
+                // 전송 중에 파일이 다시 바뀌었으면 Pending으로 남겨 다음 패스에 보냄
+                if let Err(e) = mark_if_unchanged(file, SyncStatus::Synced) {
+                    tracing::warn!("Failed to mark {} as synced: {:#}", file.path, e);
+                }
+                (ForwardOutcome::Sent, None)
+            }
+            Err(e) if attempt >= self.options.max_attempts => {
+                self.retries.remove(&file.path);
+                let reason = format!("{:#}", e);
+                if let Err(e) = mark_if_unchanged(file, SyncStatus::Failed { reason: reason.clone() }) {
+                    tracing::warn!("Failed to mark {} as failed: {:#}", file.path, e);
+                }
+                (ForwardOutcome::Failed, Some(reason))
+            }
+            Err(e) => {
+                self.retries.insert(file.path.clone(), RetryState {
+                    attempts: attempt,
+                    next_attempt: Instant::now() + retry_delay(attempt),
+                });
+                // 다음 패스에서 다시 보내도록 대기 상태로 되돌림
+                if let Err(e) = mark_if_unchanged(file, SyncStatus::Pending) {
+                    tracing::warn!("Failed to mark {} as pending: {:#}", file.path, e);
+                }
+                (ForwardOutcome::Retry, Some(format!("{:#}", e)))
+            }
+        };
+
+        ForwardRecord {
+            timestamp: unix_now(),
+            path: file.path.clone(),
+            peer: self.options.peer.to_string(),
+            file_size: file.file_size,
+            attempt,
+            outcome,
+            error,
+        }
+    }
+
+    /// 전달 기록을 로그와 기록 파일에 남깁니다.
+    fn write_record(&self, record: &ForwardRecord) {
+        match record.outcome {
+            ForwardOutcome::Sent => tracing::info!("Forwarded {} to {}", record.path, record.peer),
+            ForwardOutcome::Retry => tracing::warn!(
+                "Forwarding {} failed (attempt {}), will retry: {}",
+                record.path, record.attempt, record.error.as_deref().unwrap_or_default()
+            ),
+            ForwardOutcome::Failed => tracing::error!(
+                "Giving up forwarding {} after {} attempts: {}",
+                record.path, record.attempt, record.error.as_deref().unwrap_or_default()
+            ),
+        }
+
+        if let Some(log_path) = &self.options.log_path {
+            if let Err(e) = append_record(log_path, record) {
+                tracing::warn!("Failed to write forward log: {:#}", e);
+            }
+        }
+    }
+}
+
+/// DB의 해시가 전송한 시점과 같을 때만 상태를 바꿉니다.
+fn mark_if_unchanged(sent: &FileMetadata, status: SyncStatus) -> Result<()> {
+    match db::get_file_metadata(&sent.path)? {
+        Some(current) if current.file_hash == sent.file_hash && current.last_modified == sent.last_modified => {
+            db::update_sync_status(&sent.path, &status)?;
+        }
+        _ => tracing::debug!("{} changed while forwarding, keeping it pending", sent.path),
+    }
+    Ok(())
+}
+
+fn append_record(log_path: &PathBuf, record: &ForwardRecord) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(paths::long_path(log_path))
+        .with_context(|| format!("Failed to open forward log: {}", log_path.display()))?;
+
+    writeln!(file, "{}", serde_json::to_string(record)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay_backs_off_to_cap() {
+        assert_eq!(retry_delay(1), Duration::from_secs(2));
+        assert_eq!(retry_delay(2), Duration::from_secs(4));
+        assert_eq!(retry_delay(5), Duration::from_secs(32));
+        assert_eq!(retry_delay(20), RETRY_MAX_DELAY);
+        assert_eq!(retry_delay(u32::MAX), RETRY_MAX_DELAY);
+    }
+}
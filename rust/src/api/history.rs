@@ -0,0 +1,444 @@
+//! 전송 기록과 보존 정책 (Transfer History Retention)
+//!
+//! 끝난 송수신 전송을 `transfer_history` 테이블에 기록하고(보낸 전송은 수신측의 수령증 포함), 설정의 보존 정책
+//! (`PebbleConfig::history_retention`)에 따라 오래된 기록과 이어받기 상태
+//! (`transfer_state`), 연결 감사 기록(`connection_log`)을 정리합니다. 삭제한 기록의 이미지 미리보기도 함께 지웁니다.
+//!
+//! # Process Flow
+//! 1. 전송이 끝나면(성공, 실패, 취소) `record`로 한 줄 기록 (보낸 전송은 확인한 수령증을 `set_receipt`로 덧붙임)
+//! 2. 전송 서버와 함께 시작되는 유지보수 태스크가 `MAINTENANCE_INTERVAL`마다 `prune` 실행
+//!    (그 전에 오래 이어받지 않은 미완성 수신을 `partials::clean`으로 정리)
+//! 3. 최근 `max_age_days`일 이내, 최신 `max_entries`개만 남기고 삭제 (0이면 해당 기준 없음)
+//!    (끊긴 수신의 이어받기 상태는 받다 만 파일과 함께 `partials::clean`만 지움)
+
+use anyhow::Result;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+use super::config;
+use super::db;
+use super::dedup::ChunkStore;
+use super::error::PebbleError;
+use super::partials;
+use super::receipts::TransferReceipt;
+use super::thumbnails;
+use super::registry::{ActiveTransfer, TransferDirection};
+use super::transfer::TransferStatus;
+use super::util::unix_now;
+
+/// 자동 정리 주기
+pub const MAINTENANCE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// 전송 기록 보존 정책
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionPolicy {
+    /// 남길 최대 기록 수 (0이면 개수 제한 없음)
+    pub max_entries: u32,
+
+    /// 기록을 남길 기간 (일, 0이면 기간 제한 없음)
+    pub max_age_days: u32,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            max_entries: 1000,
+            max_age_days: 90,
+        }
+    }
+}
+
+/// 끝난 전송 하나의 기록
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransferRecord {
+    pub transfer_id: String,
+    pub direction: TransferDirection,
+
+    /// 상대 기기 주소 (IP:Port)
+    pub peer: String,
+
+    pub file_path: String,
+    pub total_bytes: u64,
+    pub bytes_transferred: u64,
+
+    /// 최종 상태 (Completed, Failed, Cancelled)
+    pub status: TransferStatus,
+
+    /// 실패 원인
+    pub error: Option<String>,
+
+    /// 시작 시각 (Unix timestamp)
+    pub started_at: i64,
+
+    /// 종료 시각 (Unix timestamp)
+    pub finished_at: i64,
+
+    /// 받은 이미지의 미리보기 파일 경로 (`thumbnails`, 만들지 않았으면 None)
+    pub thumbnail_path: Option<String>,
+
+    /// 보낸 전송에 대해 수신측이 서명한 수령증 (구버전 수신측이거나 받지 못했으면 None)
+    pub receipt: Option<TransferReceipt>,
+}
+
+/// 정리 결과
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PruneReport {
+    /// 삭제한 전송 기록 수
+    pub history_removed: u64,
+
+    /// 삭제한 이어받기 상태 수
+    pub states_removed: u64,
+
+    /// 삭제한 연결 감사 기록 수
+    pub connections_removed: u64,
+}
+
+fn parse_status(value: &str) -> TransferStatus {
+    match value {
+        "Completed" => TransferStatus::Completed,
+        "Cancelled" => TransferStatus::Cancelled,
+        _ => TransferStatus::Failed,
+    }
+}
+
+/// 끝난 전송을 기록합니다.
+///
+/// # Arguments
+/// * `info` - 레지스트리의 전송 정보 (종료 직전 스냅샷)
+/// * `result` - 전송 결과 (취소 에러면 Cancelled, 그 밖의 에러는 Failed)
+pub fn record(info: &ActiveTransfer, result: &Result<()>) -> Result<()> {
+    let (status, error) = match result {
+        Ok(()) => (TransferStatus::Completed, None),
+        Err(e) if matches!(e.downcast_ref::<PebbleError>(), Some(PebbleError::Cancelled { .. })) => {
+            (TransferStatus::Cancelled, Some(format!("{:#}", e)))
+        }
+        Err(e) => (TransferStatus::Failed, Some(format!("{:#}", e))),
+    };
+
+    let conn = db::open_connection()?;
+    conn.execute(
+        "INSERT OR REPLACE INTO transfer_history
+         (transfer_id, direction, peer, file_path, total_bytes, bytes_transferred, status, error, started_at, finished_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            info.transfer_id,
+            if info.direction == TransferDirection::Send { "Send" } else { "Receive" },
+            info.peer,
+            info.file_path,
+            info.total_bytes as i64,
+            info.bytes_transferred as i64,
+            status.to_string(),
+            error,
+            info.started_at,
+            unix_now(),
+        ],
+    )?;
+    Ok(())
+}
+
+/// 전송 기록을 최신순으로 가져옵니다.
+pub fn list(limit: u32, offset: u32) -> Result<Vec<TransferRecord>> {
+    let conn = db::open_connection()?;
+    let mut stmt = conn.prepare(
+        "SELECT transfer_id, direction, peer, file_path, total_bytes, bytes_transferred, status, error, started_at, finished_at,
+                thumbnail_path, receipt
+         FROM transfer_history ORDER BY finished_at DESC, rowid DESC LIMIT ?1 OFFSET ?2",
+    )?;
+
+    let rows = stmt.query_map(params![limit, offset], |row| {
+        let direction: String = row.get(1)?;
+        Ok(TransferRecord {
+            transfer_id: row.get(0)?,
+            direction: if direction == "Send" { TransferDirection::Send } else { TransferDirection::Receive },
+            peer: row.get(2)?,
+            file_path: row.get(3)?,
+            total_bytes: row.get::<_, i64>(4)? as u64,
+            bytes_transferred: row.get::<_, i64>(5)? as u64,
+            status: parse_status(&row.get::<_, String>(6)?),
+            error: row.get(7)?,
+            started_at: row.get(8)?,
+            finished_at: row.get(9)?,
+            thumbnail_path: row.get(10)?,
+            receipt: parse_receipt(row.get(11)?),
+        })
+    })?;
+
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+/// 전송 기록에 받은 이미지의 미리보기 경로를 남깁니다.
+///
+/// # Returns
+/// * 그 전송의 기록이 있었으면 true
+pub fn set_thumbnail(transfer_id: &str, thumbnail_path: &str) -> Result<bool> {
+    let conn = db::open_connection()?;
+    let updated = conn.execute(
+        "UPDATE transfer_history SET thumbnail_path = ?2 WHERE transfer_id = ?1",
+        params![transfer_id, thumbnail_path],
+    )?;
+    Ok(updated > 0)
+}
+
+fn parse_receipt(value: Option<String>) -> Option<TransferReceipt> {
+    value.and_then(|json| serde_json::from_str(&json).ok())
+}
+
+/// 보낸 전송의 기록에 수신측이 서명한 수령증을 남깁니다.
+///
+/// # Returns
+/// * 그 전송의 기록이 있었으면 true
+pub fn set_receipt(receipt: &TransferReceipt) -> Result<bool> {
+    let conn = db::open_connection()?;
+    let updated = conn.execute(
+        "UPDATE transfer_history SET receipt = ?2 WHERE transfer_id = ?1",
+        params![receipt.transfer_id, serde_json::to_string(receipt)?],
+    )?;
+    Ok(updated > 0)
+}
+
+/// 전송 기록에 저장된 수령증을 가져옵니다.
+pub fn receipt(transfer_id: &str) -> Result<Option<TransferReceipt>> {
+    let conn = db::open_connection()?;
+    let receipt = conn
+        .query_row(
+            "SELECT receipt FROM transfer_history WHERE transfer_id = ?1",
+            params![transfer_id],
+            |row| row.get::<_, Option<String>>(0),
+        )
+        .optional()?;
+    Ok(parse_receipt(receipt.flatten()))
+}
+
+/// 전송 기록을 모두 삭제합니다. 이어받기 상태는 유지합니다.
+pub fn clear() -> Result<()> {
+    let conn = db::open_connection()?;
+    conn.execute("DELETE FROM transfer_history", [])?;
+    thumbnails::remove_unreferenced(&conn)?;
+    Ok(())
+}
+
+/// 테이블 하나를 보존 정책에 맞게 정리합니다.
+///
+/// # Arguments
+/// * `key` - 행을 구분하는 컬럼
+/// * `time_column` - 정렬과 기간 판정에 사용할 시각 컬럼
+fn prune_table(conn: &Connection, table: &str, key: &str, time_column: &str, policy: &RetentionPolicy, now: i64) -> Result<u64> {
+    let mut removed = 0;
+
+    if policy.max_age_days > 0 {
+        let cutoff = now - i64::from(policy.max_age_days) * 24 * 60 * 60;
+        removed += conn.execute(&format!("DELETE FROM {} WHERE {} < ?1", table, time_column), params![cutoff])?;
+    }
+
+    if policy.max_entries > 0 {
+        removed += conn.execute(
+            &format!(
+                "DELETE FROM {table} WHERE {key} NOT IN
+                 (SELECT {key} FROM {table} ORDER BY {time} DESC, rowid DESC LIMIT ?1)",
+                table = table,
+                key = key,
+                time = time_column
+            ),
+            params![policy.max_entries],
+        )?;
+    }
+
+    Ok(removed as u64)
+}
+
+/// 이어받기 상태를 보존 정책에 맞게 정리합니다.
+///
+/// 끊긴 전송은 다시 보내면 같은 전송 ID로 이어받으므로, 기간과 개수 기준 모두 끝난 수신의 상태만 지웁니다.
+/// 끝나지 않은 상태는 받다 만 파일과 함께 미완성 수신 정리(`partials::clean`)로만 지웁니다
+/// (상태만 지우면 그 상태로 찾는 받다 만 파일을 아무도 지우지 못함).
+fn prune_states(conn: &Connection, policy: &RetentionPolicy, now: i64) -> Result<u64> {
+    let mut removed = 0;
+
+    if policy.max_age_days > 0 {
+        let cutoff = now - i64::from(policy.max_age_days) * 24 * 60 * 60;
+        removed += conn.execute(
+            "DELETE FROM transfer_state WHERE updated_at < ?1 AND transfer_status = ?2",
+            params![cutoff, TransferStatus::Completed.to_string()],
+        )?;
+    }
+
+    if policy.max_entries > 0 {
+        removed += conn.execute(
+            "DELETE FROM transfer_state WHERE transfer_status = ?2 AND transfer_id NOT IN
+             (SELECT transfer_id FROM transfer_state ORDER BY updated_at DESC, rowid DESC LIMIT ?1)",
+            params![policy.max_entries, TransferStatus::Completed.to_string()],
+        )?;
+    }
+
+    Ok(removed as u64)
+}
+
+/// 전송 기록, 이어받기 상태, 연결 감사 기록을 보존 정책에 맞게 정리합니다.
+///
+/// 이어받기 상태는 끝난 수신의 상태만 지우므로 진행 중이거나 끊긴 전송은 지워지지 않습니다.
+pub fn prune(policy: &RetentionPolicy) -> Result<PruneReport> {
+    let conn = db::open_connection()?;
+    let now = unix_now();
+
+    let report = PruneReport {
+        history_removed: prune_table(&conn, "transfer_history", "transfer_id", "finished_at", policy, now)?,
+        states_removed: prune_states(&conn, policy, now)?,
+        connections_removed: prune_table(&conn, "connection_log", "id", "opened_at", policy, now)?,
+    };
+    if report.history_removed > 0 {
+        thumbnails::remove_unreferenced(&conn)?;
+    }
+
+    if report.history_removed > 0 || report.states_removed > 0 || report.connections_removed > 0 {
+        tracing::info!("Pruned {} transfer record(s), {} resume state(s) and {} connection record(s)",
+            report.history_removed, report.states_removed, report.connections_removed);
+    }
+
+    Ok(report)
+}
+
+/// 취소될 때까지 주기적으로 미완성 수신을 정리하고 설정의 보존 정책과 중복 제거 저장소 한도를 적용합니다.
+pub async fn run_maintenance(token: CancellationToken) -> Result<()> {
+    loop {
+        let current = config::current();
+        // 끊긴 수신의 이어받기 상태는 미완성 파일 정리만 지움
+        if current.partial_max_age_hours > 0 {
+            if let Err(e) = partials::clean(Duration::from_secs(current.partial_max_age_hours * 60 * 60)) {
+                tracing::warn!("Failed to clean partial transfers: {:#}", e);
+            }
+        }
+        if let Err(e) = prune(&current.history_retention) {
+            tracing::warn!("Failed to prune transfer history: {:#}", e);
+        }
+        if let Some(store) = ChunkStore::configured().filter(|_| current.dedup_store_max_bytes > 0) {
+            if let Err(e) = store.prune(current.dedup_store_max_bytes) {
+                tracing::warn!("Failed to prune dedup store: {:#}", e);
+            }
+        }
+
+        tokio::select! {
+            _ = token.cancelled() => return Ok(()),
+            _ = tokio::time::sleep(MAINTENANCE_INTERVAL) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(conn: &Connection, transfer_id: &str, finished_at: i64) {
+        conn.execute(
+            "INSERT INTO transfer_history
+             (transfer_id, direction, peer, file_path, total_bytes, bytes_transferred, status, error, started_at, finished_at)
+             VALUES (?1, 'Send', 'peer', 'file', 1, 1, 'Completed', NULL, ?2, ?2)",
+            params![transfer_id, finished_at],
+        )
+        .unwrap();
+    }
+
+    fn exists(conn: &Connection, table: &str, transfer_id: &str) -> bool {
+        conn.query_row(
+            &format!("SELECT COUNT(*) FROM {} WHERE transfer_id = ?1", table),
+            params![transfer_id],
+            |row| row.get::<_, i64>(0),
+        )
+        .unwrap()
+            > 0
+    }
+
+    #[test]
+    fn test_receipt_stored_with_record() {
+        use crate::api::certificate::TlsCertificate;
+        use crate::api::receipts;
+
+        crate::loopback::use_temp_environment();
+        let conn = db::open_connection().unwrap();
+        // 개수 기준 정리 테스트와 겹쳐도 지워지지 않도록 가장 최근 기록으로 남김
+        insert(&conn, "history-receipt", unix_now() + 24 * 60 * 60);
+
+        let cert = TlsCertificate::generate_self_signed("history-receiver", "Receiver").unwrap();
+        let receipt = receipts::sign(&cert, "history-receipt", "abc", 1, None).unwrap();
+        assert!(set_receipt(&receipt).unwrap());
+        assert!(!set_receipt(&TransferReceipt { transfer_id: "history-missing".to_string(), ..receipt.clone() }).unwrap());
+
+        assert_eq!(super::receipt("history-receipt").unwrap(), Some(receipt.clone()));
+        let record = list(u32::MAX, 0).unwrap().into_iter().find(|r| r.transfer_id == "history-receipt").unwrap();
+        assert_eq!(record.receipt, Some(receipt));
+    }
+
+    #[test]
+    fn test_prune_by_age_and_count() {
+        crate::loopback::use_temp_environment();
+        let conn = db::open_connection().unwrap();
+        let now = unix_now();
+
+        // 기간 기준: 오래된 기록과 오래전에 끝난 수신의 상태 삭제
+        insert(&conn, "history-old", now - 40 * 24 * 60 * 60);
+        insert(&conn, "history-recent", now);
+        conn.execute(
+            "INSERT INTO transfer_state
+             (transfer_id, file_path, file_size, total_chunks, received_chunks, transfer_status, peer_device_id, created_at, updated_at)
+             VALUES ('state-old', 'file', 0, 0, 1, 'Completed', '', 1, 1)",
+            [],
+        )
+        .unwrap();
+
+        let report = prune(&RetentionPolicy { max_entries: 0, max_age_days: 30 }).unwrap();
+        assert!(report.history_removed >= 1 && report.states_removed >= 1);
+        assert!(!exists(&conn, "transfer_history", "history-old"));
+        assert!(!exists(&conn, "transfer_state", "state-old"));
+        assert!(exists(&conn, "transfer_history", "history-recent"));
+
+        // 개수 기준: 최신 기록만 남김 (다른 테스트의 기록보다 나중 시각)
+        for index in 0..3 {
+            insert(&conn, &format!("history-count-{}", index), now + 1_000_000 + index);
+        }
+        let policy = RetentionPolicy { max_entries: 2, max_age_days: 0 };
+        prune_table(&conn, "transfer_history", "transfer_id", "finished_at", &policy, now).unwrap();
+        assert!(!exists(&conn, "transfer_history", "history-count-0"));
+        assert!(exists(&conn, "transfer_history", "history-count-2"));
+        assert_eq!(list(1, 0).unwrap()[0].transfer_id, "history-count-2");
+    }
+
+    #[test]
+    fn test_count_prune_keeps_resumable_states() {
+        // 다른 테스트의 이어받기 상태를 지우지 않도록 별도 DB에서 확인
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE transfer_state (
+                transfer_id TEXT PRIMARY KEY, file_path TEXT NOT NULL, file_size INTEGER NOT NULL,
+                total_chunks INTEGER NOT NULL, received_chunks INTEGER NOT NULL, transfer_status TEXT NOT NULL,
+                peer_device_id TEXT NOT NULL, created_at INTEGER NOT NULL, updated_at INTEGER NOT NULL
+            )",
+            [],
+        )
+        .unwrap();
+        let state = |transfer_id: &str, status: TransferStatus, updated_at: i64| {
+            conn.execute(
+                "INSERT INTO transfer_state VALUES (?1, 'file', 0, 0, 1, ?2, '', ?3, ?3)",
+                params![transfer_id, status.to_string(), updated_at],
+            )
+            .unwrap();
+        };
+        let now = unix_now();
+        state("interrupted", TransferStatus::InProgress, now - 10);
+        state("done-old", TransferStatus::Completed, now - 5);
+        state("done-new", TransferStatus::Completed, now);
+
+        let removed = prune_states(&conn, &RetentionPolicy { max_entries: 1, max_age_days: 0 }, now).unwrap();
+        assert_eq!(removed, 1);
+        assert!(exists(&conn, "transfer_state", "interrupted"));
+        assert!(!exists(&conn, "transfer_state", "done-old"));
+        assert!(exists(&conn, "transfer_state", "done-new"));
+
+        // 기간 기준으로도 끝난 수신의 상태만 지움 (끝나지 않은 상태는 받다 만 파일과 함께 `partials::clean`이 지움)
+        let removed = prune_states(&conn, &RetentionPolicy { max_entries: 0, max_age_days: 1 }, now + 2 * 24 * 60 * 60).unwrap();
+        assert_eq!(removed, 1);
+        assert!(exists(&conn, "transfer_state", "interrupted"));
+        assert!(!exists(&conn, "transfer_state", "done-new"));
+    }
+}
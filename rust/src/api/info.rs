@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+
+use super::discovery::{LOCAL_CAPABILITIES, PROTOCOL_VERSION};
+use super::integrity::HashAlgo;
+use super::manifest::PAGE_COMPRESSION;
+
+/// 앱 버전 및 빌드 정보
+///
+/// UI의 정보 화면과 상대 기기와의 호환성 경고에 사용됩니다.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppInfo {
+    /// 네이티브 라이브러리(crate) 버전
+    pub version: String,
+
+    /// 비콘/전송 프로토콜 버전
+    pub protocol_version: String,
+
+    /// 지원하는 전송 방식
+    pub transports: Vec<String>,
+
+    /// 지원하는 해시 알고리즘
+    pub hash_algorithms: Vec<String>,
+
+    /// 지원하는 압축 방식 (비어 있으면 압축 미지원)
+    pub compression: Vec<String>,
+
+    /// 지원하는 부가 기능 (이어받기, 텍스트 메시지 등)
+    pub features: Vec<String>,
+
+    /// 운영체제 (예: "android", "ios", "windows")
+    pub os: String,
+
+    /// CPU 아키텍처 (예: "aarch64", "x86_64")
+    pub arch: String,
+
+    /// 디버그 빌드 여부
+    pub debug_build: bool,
+}
+
+/// 비콘 기능 외에 전송 프로토콜 자체가 지원하는 기능
+const TRANSFER_FEATURES: &[&str] = &["resume", "pause_cancel"];
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|v| v.to_string()).collect()
+}
+
+/// 협상할 수 있는 해시 알고리즘 (청크 해시 제안 목록 + 수락 응답에서만 고르는 CRC32C)
+fn hash_algorithms() -> Vec<String> {
+    HashAlgo::chunk_preferences(true)
+        .into_iter()
+        .chain([HashAlgo::Crc32c])
+        .map(|algo| algo.name().to_string())
+        .collect()
+}
+
+/// 비콘으로 알리는 기능과 전송 프로토콜 기능
+fn features() -> Vec<String> {
+    LOCAL_CAPABILITIES
+        .iter()
+        .map(|capability| capability.name())
+        .chain(TRANSFER_FEATURES.iter().copied())
+        .map(str::to_string)
+        .collect()
+}
+
+/// 현재 빌드의 앱 정보를 반환합니다.
+pub fn app_info() -> AppInfo {
+    AppInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        protocol_version: PROTOCOL_VERSION.to_string(),
+        transports: strings(&["tcp+tls1.3"]),
+        hash_algorithms: hash_algorithms(),
+        compression: strings(&[PAGE_COMPRESSION]),
+        features: features(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        debug_build: cfg!(debug_assertions),
+    }
+}
+
+/// 상대 기기의 프로토콜 버전이 현재 버전과 호환되는지 확인합니다.
+///
+/// 주 버전(major)이 같으면 호환되는 것으로 간주합니다.
+pub fn is_protocol_compatible(peer_version: &str) -> bool {
+    let major = |v: &str| v.split('.').next().map(str::to_string);
+    major(peer_version).is_some_and(|m| Some(m) == major(PROTOCOL_VERSION))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protocol_compatibility() {
+        assert!(is_protocol_compatible(PROTOCOL_VERSION));
+        assert!(is_protocol_compatible("1.9.0"));
+        assert!(!is_protocol_compatible("2.0.0"));
+        assert!(!is_protocol_compatible(""));
+    }
+
+    #[test]
+    fn test_app_info_matches_negotiation() {
+        let info = app_info();
+        assert_eq!(info.protocol_version, PROTOCOL_VERSION);
+        for algo in [HashAlgo::Sha256, HashAlgo::Blake3, HashAlgo::Xxh3, HashAlgo::Crc32c] {
+            assert!(info.hash_algorithms.contains(&algo.name().to_string()), "{}", algo.name());
+        }
+        assert_eq!(info.compression, vec!["zstd".to_string()]);
+        for capability in LOCAL_CAPABILITIES {
+            assert!(info.features.contains(&capability.name().to_string()));
+        }
+        assert!(info.features.contains(&"resume".to_string()));
+    }
+}
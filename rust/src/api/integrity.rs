@@ -1,22 +1,181 @@
 use anyhow::{Context, Result};
 use blake3::Hasher;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::fs::File;
 use std::io::{BufReader, Read};
 use std::path::Path;
 
+use super::crc32c;
+use super::paths;
+
+/// 메모리 맵 + 멀티스레드 해시를 사용하는 최소 파일 크기 (16MB)
+///
+/// 이보다 작은 파일은 mmap 설정과 스레드 분배 비용이 이득보다 커서 순차 읽기가 더 빠릅니다.
+pub const MMAP_HASH_THRESHOLD: u64 = 16 * 1024 * 1024;
+
+/// 진행률을 보고하며 해시할 때 큰 파일에 사용하는 블록 크기 (8MB)
+///
+/// 블록 하나를 rayon으로 병렬 해시한 뒤 진행률을 보고합니다.
+const PROGRESS_BLOCK_SIZE: usize = 8 * 1024 * 1024;
+
+/// 순차 읽기 버퍼 크기 (64KB, 성능과 메모리 사용량의 균형)
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+/// 파일 해시 길이 (bytes, 128자리 16진수)
+const FILE_HASH_LEN: usize = 64;
+
+/// 청크 무결성 검증에 사용하는 해시 알고리즘
+///
+/// 파일 전체 해시는 항상 blake3를 사용하고, 청크 해시는 전송 요청/수락 과정에서
+/// 양측이 지원하는 알고리즘 중 하나로 합의합니다. 필드가 없는 구버전 기기와는 Sha256을 사용합니다.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgo {
+    /// 구버전 호환용 기본값
+    #[default]
+    Sha256,
+
+    /// 암호학적 해시 (기본 청크 해시)
+    Blake3,
+
+    /// 비암호학적 고속 해시 (신뢰하는 LAN 전용, 우발적 손상만 검출)
+    Xxh3,
+
+    /// CPU 명령으로 계산하는 체크섬 (저전력 수신 기기용, 우발적 손상만 검출)
+    ///
+    /// 구버전이 읽지 못하므로 제안 목록(`chunk_hash_algos`)에는 넣지 않고,
+    /// 송신측이 요청의 `crc32c`로 지원을 밝히면 수신측이 수락 응답에서 고릅니다.
+    Crc32c,
+}
+
+impl HashAlgo {
+    /// 프로토콜과 로그에 사용하는 이름
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Sha256 => "sha256",
+            Self::Blake3 => "blake3",
+            Self::Xxh3 => "xxh3",
+            Self::Crc32c => "crc32c",
+        }
+    }
+
+    /// 의도적인 변조까지 검출할 수 있는 암호학적 해시인지 확인합니다.
+    pub fn is_cryptographic(&self) -> bool {
+        !matches!(self, Self::Xxh3 | Self::Crc32c)
+    }
+
+    /// 데이터의 해시를 16진수 문자열로 계산합니다.
+    pub fn digest(&self, data: &[u8]) -> String {
+        match self {
+            Self::Sha256 => hex::encode(Sha256::digest(data)),
+            Self::Blake3 => blake3::hash(data).to_hex().to_string(),
+            Self::Xxh3 => format!("{:016x}", xxhash_rust::xxh3::xxh3_64(data)),
+            Self::Crc32c => format!("{:08x}", crc32c::checksum(data)),
+        }
+    }
+
+    /// 이 기기가 청크 해시로 사용할 알고리즘 목록 (선호 순서)
+    ///
+    /// # Arguments
+    /// * `allow_fast` - xxh3 사용 허용 여부 (설정의 `fast_chunk_hash`)
+    pub fn chunk_preferences(allow_fast: bool) -> Vec<HashAlgo> {
+        if allow_fast {
+            vec![Self::Xxh3, Self::Blake3, Self::Sha256]
+        } else {
+            vec![Self::Blake3, Self::Sha256]
+        }
+    }
+
+    /// 송신측 제안 목록에서 수신측도 지원하는 첫 알고리즘을 고릅니다.
+    ///
+    /// 일치하는 항목이 없으면 모든 버전이 지원하는 Sha256을 사용합니다.
+    pub fn negotiate(offered: &[HashAlgo], supported: &[HashAlgo]) -> HashAlgo {
+        offered
+            .iter()
+            .copied()
+            .find(|algo| supported.contains(algo))
+            .unwrap_or_default()
+    }
+}
+
+/// 크기와 수정 시각만으로 기록한 파일의 해시 접두사 (`ChangeDetection::SizeMtime`)
+pub const METADATA_HASH_PREFIX: &str = "meta:";
+
+/// 동기화 루트의 변경 감지 방식
+///
+/// 테라바이트 단위의 바뀌지 않는 미디어 보관함은 파일마다 해시하면 스캔이 몇 시간씩 걸리므로,
+/// 루트별로 크기와 수정 시각만 비교하도록 설정할 수 있습니다. 이런 파일은 내용 해시 대신
+/// `metadata_hash`로 기록되어 중복 검사, 무결성 검사, 같은 내용 찾기에서 제외됩니다.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeDetection {
+    /// 바뀐 파일의 내용을 blake3로 해시 (기본값)
+    #[default]
+    ContentHash,
+
+    /// 크기와 수정 시각만 비교하고 해시하지 않음 (수정 시각을 유지한 채 바뀐 내용은 놓침)
+    SizeMtime,
+}
+
+/// 루트 하나의 변경 감지 방식 (설정의 `change_detection`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RootChangeDetection {
+    /// 동기화 루트 디렉토리 (하위 폴더 포함)
+    pub root: String,
+    pub mode: ChangeDetection,
+}
+
+impl RootChangeDetection {
+    /// 설정 값의 유효성을 검사합니다.
+    pub fn validate(&self) -> Result<()> {
+        if self.root.trim().is_empty() {
+            anyhow::bail!("change_detection root must not be empty");
+        }
+        Ok(())
+    }
+}
+
+/// 크기와 수정 시각으로 만든 자리 표시 해시 (같은 크기와 수정 시각이면 같은 값)
+pub fn metadata_hash(file_size: u64, last_modified: i64) -> String {
+    format!("{}{}:{}", METADATA_HASH_PREFIX, file_size, last_modified)
+}
+
+/// 내용 대신 크기와 수정 시각으로 기록한 해시인지 확인합니다.
+pub fn is_metadata_hash(hash: &str) -> bool {
+    hash.starts_with(METADATA_HASH_PREFIX)
+}
+
+/// 변경 감지 방식에 따라 기록할 파일 해시를 구합니다.
+///
+/// # Arguments
+/// * `file_path` - 파일 경로
+/// * `mode` - 파일이 속한 루트의 변경 감지 방식
+/// * `file_size`, `last_modified` - 이미 읽은 파일 크기와 수정 시각 (`SizeMtime`이면 이 값만 사용)
+pub fn change_hash<P: AsRef<Path>>(file_path: P, mode: ChangeDetection, file_size: u64, last_modified: i64) -> Result<String> {
+    match mode {
+        ChangeDetection::ContentHash => calculate_file_hash(file_path),
+        ChangeDetection::SizeMtime => Ok(metadata_hash(file_size, last_modified)),
+    }
+}
+
 /// blake3를 사용하여 파일의 해시값을 계산합니다.
 ///
 /// # Arguments
 /// * `file_path` - 해시를 계산할 파일의 경로
 ///
 /// # Returns
-/// * `Result<String>` - 성공 시 16진수 문자열 형태의 해시값, 실패 시 에러
+/// * `Result<String>` - 성공 시 16진수 문자열 형태의 해시값 (512비트), 실패 시 에러
 ///
 /// # Security
 /// - blake3는 암호학적으로 안전한 해시 함수로, 파일 무결성 검증에 적합합니다
 /// - 충돌 공격에 강하며, SHA-256보다 빠른 성능을 제공합니다
+///
+/// # Performance
+/// - `MMAP_HASH_THRESHOLD` 이상인 파일은 메모리 맵으로 열어 rayon 스레드 풀에서 병렬 해시합니다
+/// - mmap을 사용할 수 없는 경우(네트워크 파일 시스템 등) 순차 읽기로 대체합니다
 pub fn calculate_file_hash<P: AsRef<Path>>(file_path: P) -> Result<String> {
-    let path = file_path.as_ref();
+    let path = &paths::long_path(file_path);
 
     // 파일 존재 여부 확인
     if !path.exists() {
@@ -27,31 +186,176 @@ pub fn calculate_file_hash<P: AsRef<Path>>(file_path: P) -> Result<String> {
         anyhow::bail!("Path is not a file: {}", path.display());
     }
 
+    let file_size = path.metadata()
+        .with_context(|| format!("Failed to read metadata: {}", path.display()))?
+        .len();
+
+    if file_size >= MMAP_HASH_THRESHOLD {
+        let mut hasher = Hasher::new();
+        match hasher.update_mmap_rayon(path) {
+            Ok(_) => return Ok(file_hash_hex(&hasher)),
+            Err(e) => {
+                tracing::debug!("Memory-mapped hashing failed for {}, falling back to streaming: {}", path.display(), e);
+            }
+        }
+    }
+
     // 파일 열기
     let file = File::open(path)
         .with_context(|| format!("Failed to open file: {}", path.display()))?;
 
-    let mut reader = BufReader::new(file);
-    let mut hasher = Hasher::new();
+    hash_reader(file).with_context(|| format!("Failed to read file: {}", path.display()))
+}
+
+/// 진행률을 보고하며 파일의 blake3 해시값을 계산합니다.
+///
+/// 초기 스캔이나 무결성 검사처럼 오래 걸리는 해시 작업에서 진행률 표시줄을
+/// 갱신할 때 사용합니다. 결과는 `calculate_file_hash`와 같습니다.
+///
+/// # Arguments
+/// * `file_path` - 해시를 계산할 파일의 경로
+/// * `on_progress` - 블록을 처리할 때마다 `(해시한 바이트 수, 전체 바이트 수)`로 호출됨
+///
+/// # Returns
+/// * `Result<String>` - 성공 시 16진수 문자열 형태의 해시값, 실패 시 에러
+pub fn hash_file_with_progress<P, F>(file_path: P, on_progress: F) -> Result<String>
+where
+    P: AsRef<Path>,
+    F: FnMut(u64, u64),
+{
+    let path = &paths::long_path(file_path);
+
+    let file = File::open(path)
+        .with_context(|| format!("Failed to open file: {}", path.display()))?;
+    let metadata = file.metadata()
+        .with_context(|| format!("Failed to read metadata: {}", path.display()))?;
 
-    // 버퍼 크기는 64KB로 설정 (성능과 메모리 사용량의 균형)
-    let mut buffer = vec![0u8; 65536];
+    if !metadata.is_file() {
+        anyhow::bail!("Path is not a file: {}", path.display());
+    }
+
+    // 큰 파일은 블록 단위로 병렬 해시
+    let block_size = if metadata.len() >= MMAP_HASH_THRESHOLD {
+        PROGRESS_BLOCK_SIZE
+    } else {
+        READ_BUFFER_SIZE
+    };
+
+    hash_blocks(file, block_size, metadata.len(), on_progress)
+        .with_context(|| format!("Failed to read file: {}", path.display()))
+}
+
+/// 스트림 끝까지 읽어 blake3 해시값을 계산합니다.
+///
+/// 경로가 없는 입력(호스트가 넘긴 파일 디스크립터 등)에 사용합니다.
+pub fn hash_reader<R: Read>(reader: R) -> Result<String> {
+    hash_blocks(reader, READ_BUFFER_SIZE, 0, |_, _| {})
+}
+
+/// 받는 순서대로 데이터를 넣어 파일 전체 해시를 계산합니다.
+///
+/// 결과는 같은 내용에 대한 `calculate_file_hash`와 같아서, 수신이 끝나는 즉시
+/// 파일을 다시 읽지 않고 송신측 해시와 비교할 수 있습니다.
+#[derive(Debug, Clone, Default)]
+pub struct RunningHash {
+    hasher: Hasher,
+}
+
+impl RunningHash {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 이어받기처럼 이미 받은 앞부분이 있으면 `len` 바이트를 먼저 읽어 해시합니다.
+    ///
+    /// # Returns
+    /// * 앞부분이 `len`보다 짧으면 에러
+    pub fn with_prefix<R: Read>(reader: R, len: u64) -> Result<Self> {
+        let mut running = Self::new();
+        let mut reader = BufReader::new(reader.take(len));
+        let mut buffer = vec![0u8; READ_BUFFER_SIZE];
+        let mut hashed = 0u64;
+
+        loop {
+            let bytes_read = reader.read(&mut buffer)?;
+            if bytes_read == 0 {
+                break;
+            }
+            running.update(&buffer[..bytes_read]);
+            hashed += bytes_read as u64;
+        }
+
+        if hashed < len {
+            anyhow::bail!("Expected {} bytes already received, found {}", len, hashed);
+        }
+        Ok(running)
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.hasher.update(data);
+    }
+
+    /// 파일의 구멍처럼 데이터 없이 받은 0 바이트 `len`개를 넣습니다.
+    pub fn update_zeroes(&mut self, len: u64) {
+        const ZEROES: [u8; 4096] = [0; 4096];
+        let mut remaining = len;
+        while remaining > 0 {
+            let step = remaining.min(ZEROES.len() as u64) as usize;
+            self.hasher.update(&ZEROES[..step]);
+            remaining -= step as u64;
+        }
+    }
+
+    /// 지금까지 넣은 데이터의 해시 (16진수 문자열)
+    pub fn finalize(&self) -> String {
+        file_hash_hex(&self.hasher)
+    }
+}
+
+/// 블록 단위로 읽어 해시하고, 블록마다 진행률 콜백을 호출합니다.
+fn hash_blocks<R, F>(reader: R, block_size: usize, total_bytes: u64, mut on_progress: F) -> Result<String>
+where
+    R: Read,
+    F: FnMut(u64, u64),
+{
+    let mut reader = BufReader::new(reader);
+    let mut hasher = Hasher::new();
+    let mut buffer = vec![0u8; block_size];
+    let mut hashed = 0u64;
 
-    // 파일을 청크 단위로 읽어 해시 계산
     loop {
-        let bytes_read = reader.read(&mut buffer)
-            .with_context(|| format!("Failed to read file: {}", path.display()))?;
+        // 블록을 가능한 한 가득 채워서 병렬 해시의 효율을 높임
+        let mut filled = 0;
+        while filled < buffer.len() {
+            let bytes_read = reader.read(&mut buffer[filled..])?;
+            if bytes_read == 0 {
+                break;
+            }
+            filled += bytes_read;
+        }
 
-        if bytes_read == 0 {
+        if filled == 0 {
             break;
         }
 
-        hasher.update(&buffer[..bytes_read]);
+        if block_size > READ_BUFFER_SIZE {
+            hasher.update_rayon(&buffer[..filled]);
+        } else {
+            hasher.update(&buffer[..filled]);
+        }
+
+        hashed += filled as u64;
+        on_progress(hashed, total_bytes.max(hashed));
     }
 
-    // 해시 값을 16진수 문자열로 변환
-    let hash = hasher.finalize();
-    Ok(hash.to_hex().to_string())
+    Ok(file_hash_hex(&hasher))
+}
+
+/// 파일 해시 값을 16진수 문자열로 변환합니다 (blake3 확장 출력 512비트).
+fn file_hash_hex(hasher: &Hasher) -> String {
+    let mut hash = [0u8; FILE_HASH_LEN];
+    hasher.finalize_xof().fill(&mut hash);
+    hex::encode(hash)
 }
 
 #[cfg(test)]
@@ -62,7 +366,7 @@ mod tests {
 
     #[test]
     fn test_calculate_hash_empty_file() {
-        let mut temp_file = NamedTempFile::new().unwrap();
+        let temp_file = NamedTempFile::new().unwrap();
         let hash = calculate_file_hash(temp_file.path()).unwrap();
 
         // blake3의 빈 파일 해시값
@@ -97,4 +401,81 @@ mod tests {
         let result = calculate_file_hash("/nonexistent/path/to/file.txt");
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_large_file_matches_streaming_hash() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        let chunk: Vec<u8> = (0..=255u8).cycle().take(1024 * 1024).collect();
+        for _ in 0..(MMAP_HASH_THRESHOLD / chunk.len() as u64 + 1) {
+            temp_file.write_all(&chunk).unwrap();
+        }
+        temp_file.flush().unwrap();
+
+        let mmap_hash = calculate_file_hash(temp_file.path()).unwrap();
+        let streaming_hash = hash_reader(File::open(temp_file.path()).unwrap()).unwrap();
+
+        assert_eq!(mmap_hash, streaming_hash);
+    }
+
+    #[test]
+    fn test_running_hash_matches_file_hash() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        let data: Vec<u8> = (0..=255u8).cycle().take(READ_BUFFER_SIZE * 2 + 17).collect();
+        temp_file.write_all(&data).unwrap();
+        temp_file.flush().unwrap();
+        let expected = calculate_file_hash(temp_file.path()).unwrap();
+
+        let mut running = RunningHash::new();
+        for chunk in data.chunks(1000) {
+            running.update(chunk);
+        }
+        assert_eq!(running.finalize(), expected);
+
+        // 이어받기: 앞부분은 파일에서 읽고 나머지만 넣음
+        let split = READ_BUFFER_SIZE as u64 + 5;
+        let mut resumed = RunningHash::with_prefix(File::open(temp_file.path()).unwrap(), split).unwrap();
+        resumed.update(&data[split as usize..]);
+        assert_eq!(resumed.finalize(), expected);
+
+        assert!(RunningHash::with_prefix(File::open(temp_file.path()).unwrap(), data.len() as u64 + 1).is_err());
+    }
+
+    #[test]
+    fn test_hash_with_progress_reports_every_byte() {
+        let mut temp_file = NamedTempFile::new().unwrap();
+        temp_file.write_all(&vec![7u8; READ_BUFFER_SIZE * 3 + 10]).unwrap();
+        temp_file.flush().unwrap();
+
+        let mut reports = Vec::new();
+        let hash = hash_file_with_progress(temp_file.path(), |hashed, total| reports.push((hashed, total))).unwrap();
+
+        let total = (READ_BUFFER_SIZE * 3 + 10) as u64;
+        assert_eq!(hash, calculate_file_hash(temp_file.path()).unwrap());
+        assert_eq!(reports.len(), 4);
+        assert_eq!(reports.last(), Some(&(total, total)));
+        assert!(reports.windows(2).all(|w| w[0].0 < w[1].0));
+    }
+
+    #[test]
+    fn test_hash_algo_negotiation() {
+        let fast = HashAlgo::chunk_preferences(true);
+        let safe = HashAlgo::chunk_preferences(false);
+
+        assert_eq!(HashAlgo::negotiate(&fast, &fast), HashAlgo::Xxh3);
+        assert_eq!(HashAlgo::negotiate(&fast, &safe), HashAlgo::Blake3);
+        assert_eq!(HashAlgo::negotiate(&safe, &fast), HashAlgo::Blake3);
+        // 구버전 송신측은 목록을 보내지 않음
+        assert_eq!(HashAlgo::negotiate(&[], &fast), HashAlgo::Sha256);
+    }
+
+    #[test]
+    fn test_hash_algo_digest() {
+        assert_eq!(HashAlgo::Sha256.digest(b"").len(), 64);
+        assert_eq!(HashAlgo::Blake3.digest(b""), "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262");
+        assert_eq!(HashAlgo::Xxh3.digest(b"").len(), 16);
+        assert_ne!(HashAlgo::Xxh3.digest(b"a"), HashAlgo::Xxh3.digest(b"b"));
+        assert!(!HashAlgo::Xxh3.is_cryptographic());
+        assert_eq!(HashAlgo::Crc32c.digest(b"123456789"), "e3069283");
+        assert!(!HashAlgo::Crc32c.is_cryptographic());
+    }
 }
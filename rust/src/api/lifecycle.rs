@@ -0,0 +1,108 @@
+use anyhow::Result;
+use std::sync::Mutex;
+
+use super::db::{self, ReconcileReport};
+use super::{discovery, pool, watcher};
+
+/// 백그라운드 전환 시 중지한 작업을 재개하기 위한 상태
+#[derive(Debug, Default)]
+struct PausedState {
+    /// 일시정지 전에 감시 중이던 경로
+    watch_path: Option<String>,
+}
+
+/// 앱 일시정지 상태 (None이면 포그라운드)
+static PAUSED: once_cell::sync::Lazy<Mutex<Option<PausedState>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(None));
+
+/// 앱이 백그라운드로 전환될 때 호출됩니다.
+///
+/// # Behavior
+/// - 기기 탐색을 저전력 모드로 전환 (비콘 주기 증가, 수신 폴링 완화)
+/// - 파일 감시를 중지하고 감시 경로를 기억
+/// - 전송 서버는 진행 중인 수신을 위해 유지
+///
+/// 이미 일시정지 상태면 아무 작업도 하지 않습니다.
+pub async fn pause() -> Result<()> {
+    if is_paused() {
+        return Ok(());
+    }
+
+    discovery::set_low_power(true);
+
+    // 백그라운드에서는 OS가 소켓을 끊을 수 있으므로 유휴 연결을 보관하지 않음
+    pool::global().clear();
+
+    let watch_path = watcher::current_watch_path();
+    if watch_path.is_some() {
+        watcher::stop_watching().await?;
+    }
+
+    tracing::info!("App paused (watcher was {})", if watch_path.is_some() { "active" } else { "inactive" });
+
+    *PAUSED
+        .lock()
+        .map_err(|e| anyhow::anyhow!("Failed to acquire lifecycle lock: {}", e))? = Some(PausedState { watch_path });
+
+    Ok(())
+}
+
+/// 앱이 포그라운드로 돌아올 때 호출됩니다.
+///
+/// # Behavior
+/// - 기기 탐색을 일반 모드로 복구하고 즉시 비콘 전송
+/// - 일시정지 전에 감시하던 폴더의 감시를 재개
+/// - 백그라운드 동안 놓친 변경 사항을 재조정 스캔으로 반영
+///
+/// # Returns
+/// * `Result<Option<ReconcileReport>>` - 감시 중이던 폴더가 있으면 재조정 결과
+pub fn resume() -> Result<Option<ReconcileReport>> {
+    let state = PAUSED
+        .lock()
+        .map_err(|e| anyhow::anyhow!("Failed to acquire lifecycle lock: {}", e))?
+        .take();
+
+    let Some(state) = state else {
+        return Ok(None);
+    };
+
+    discovery::set_low_power(false);
+
+    let Some(watch_path) = state.watch_path else {
+        tracing::info!("App resumed");
+        return Ok(None);
+    };
+
+    // 스캔 도중의 변경도 놓치지 않도록 감시를 먼저 재개
+    watcher::start_watching(&watch_path)?;
+    let report = db::reconcile_directory(&watch_path)?;
+
+    tracing::info!("App resumed, reconciled {}", watch_path);
+
+    Ok(Some(report))
+}
+
+/// 앱이 일시정지 상태인지 확인합니다.
+pub fn is_paused() -> bool {
+    PAUSED.lock().map(|p| p.is_some()).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_pause_and_resume_without_watcher() {
+        assert!(resume().unwrap().is_none());
+
+        pause().await.unwrap();
+        assert!(is_paused());
+        // 이미 일시정지 상태면 아무 작업도 하지 않음
+        pause().await.unwrap();
+        assert!(is_paused());
+
+        // 감시하던 폴더가 없으면 재조정할 것이 없음
+        assert!(resume().unwrap().is_none());
+        assert!(!is_paused());
+    }
+}
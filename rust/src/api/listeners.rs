@@ -0,0 +1,217 @@
+//! 추가 전송 서버 (Additional Listeners)
+//!
+//! 기본 전송 서버(`service::start_transfer_server`)와 별도로, LAN과 VPN 인터페이스처럼
+//! 주소마다 다른 저장 폴더와 수락 정책을 쓰는 전송 서버를 여러 개 실행합니다.
+//! 모든 서버는 같은 기기 인증서를 사용하므로 상대 기기의 인증서 고정은 그대로 동작합니다.
+//!
+//! # Process Flow
+//! 1. 기본 전송 서버가 시작될 때 인증서를 `set_certificate`로 보관
+//! 2. 설정의 `listeners` 또는 `start`로 이름별 서버를 바인딩하고 백그라운드에서 실행
+//! 3. 연결마다 서버의 `ListenerConfig`로 저장 폴더와 수락 정책을 결정 (None이면 전역 설정)
+//! 4. `list`로 상태를 조회하고 `stop` / `stop_all`로 중지
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use super::accept::AcceptPolicy;
+use super::certificate::TlsCertificate;
+use super::error::PebbleError;
+use super::service::ServiceKind;
+use super::supervisor::TaskSupervisor;
+use super::transfer::TransferServer;
+
+/// 전송 서버 하나의 설정
+///
+/// 저장 폴더와 수락 정책이 None이면 전역 설정(`download_dir`, `accept_policy`)을 따릅니다.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ListenerConfig {
+    /// 서버 이름 (상태 조회와 중지에 사용, 서버마다 달라야 함)
+    pub name: String,
+
+    /// 바인딩할 주소 (예: "10.8.0.2:37846", 포트가 0이면 임의 포트)
+    pub bind_addr: String,
+
+    /// 이 서버로 받은 파일의 저장 디렉토리
+    pub download_dir: Option<String>,
+
+    /// 이 서버로 받은 전송의 수락 정책
+    pub accept_policy: Option<AcceptPolicy>,
+}
+
+impl ListenerConfig {
+    /// 설정 값을 검증하고 바인딩 주소를 반환합니다.
+    pub fn validate(&self) -> Result<SocketAddr> {
+        if self.name.trim().is_empty() {
+            return Err(PebbleError::invalid_argument("Listener name must not be empty").into());
+        }
+        self.bind_addr
+            .parse()
+            .map_err(|_| PebbleError::invalid_argument(format!("Invalid bind address for listener {}: {}", self.name, self.bind_addr)).into())
+    }
+}
+
+/// 실행 중인 추가 전송 서버의 상태
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListenerStatus {
+    pub name: String,
+
+    /// 실제로 바인딩된 주소
+    pub local_addr: String,
+
+    pub download_dir: Option<String>,
+
+    /// 서버 고유의 수락 정책이 있는지 여부 (false면 전역 정책)
+    pub custom_accept_policy: bool,
+
+    /// 연결을 받고 있는지 여부 (실행 중 에러로 멈췄으면 false)
+    pub running: bool,
+}
+
+/// 실행 중인 추가 전송 서버
+struct RunningListener {
+    config: Arc<ListenerConfig>,
+    local_addr: SocketAddr,
+    tasks: TaskSupervisor,
+}
+
+impl RunningListener {
+    fn status(&self) -> ListenerStatus {
+        ListenerStatus {
+            name: self.config.name.clone(),
+            local_addr: self.local_addr.to_string(),
+            download_dir: self.config.download_dir.clone(),
+            custom_accept_policy: self.config.accept_policy.is_some(),
+            running: self.tasks.is_running(),
+        }
+    }
+}
+
+/// 이름별 추가 전송 서버
+static LISTENERS: once_cell::sync::Lazy<Mutex<HashMap<String, RunningListener>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 추가 전송 서버가 사용할 기기 인증서 (기본 전송 서버가 시작되면 설정됨)
+static CERTIFICATE: once_cell::sync::Lazy<Mutex<Option<TlsCertificate>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(None));
+
+/// 추가 전송 서버가 사용할 기기 인증서를 보관합니다.
+pub fn set_certificate(cert: TlsCertificate) {
+    *CERTIFICATE.lock().unwrap() = Some(cert);
+}
+
+/// 추가 전송 서버를 바인딩하고 백그라운드에서 실행합니다.
+///
+/// 같은 이름의 서버가 실행 중이면 중지한 뒤 새 설정으로 교체합니다.
+///
+/// # Returns
+/// * 기본 전송 서버가 시작되지 않아 인증서가 없으면 `PebbleError::InvalidArgument`
+pub async fn start(config: ListenerConfig) -> Result<ListenerStatus> {
+    let bind_addr = config.validate()?;
+    let cert = CERTIFICATE
+        .lock()
+        .unwrap()
+        .clone()
+        .ok_or_else(|| PebbleError::invalid_argument("Start the transfer server before adding listeners"))?;
+
+    stop(&config.name).await?;
+
+    let config = Arc::new(config);
+    let mut server = TransferServer::new(cert);
+    server.set_listener(Arc::clone(&config));
+    let listener = server.listen(bind_addr).await?;
+    let local_addr = listener.local_addr().context("Failed to read listener address")?;
+
+    let tasks = TaskSupervisor::new(ServiceKind::TransferServer);
+    tasks.spawn("transfer_listener", |token| async move { server.serve(listener, token).await });
+
+    let running = RunningListener { config, local_addr, tasks };
+    let status = running.status();
+    LISTENERS.lock().unwrap().insert(status.name.clone(), running);
+
+    tracing::info!("Listener {} started on {}", status.name, status.local_addr);
+
+    Ok(status)
+}
+
+/// 설정의 추가 전송 서버를 모두 시작합니다.
+///
+/// 인터페이스가 아직 없는 VPN 주소처럼 바인딩할 수 없는 서버는 건너뛰고 에러를 모아 반환합니다.
+pub async fn start_configured(configs: &[ListenerConfig]) -> Vec<(String, anyhow::Error)> {
+    let mut failures = Vec::new();
+    for config in configs {
+        if let Err(e) = start(config.clone()).await {
+            tracing::warn!("Failed to start listener {}: {:#}", config.name, e);
+            failures.push((config.name.clone(), e));
+        }
+    }
+    failures
+}
+
+/// 추가 전송 서버를 중지하고 연결 처리가 끝날 때까지 기다립니다.
+///
+/// # Returns
+/// * 그 이름의 서버가 실행 중이었으면 true
+pub async fn stop(name: &str) -> Result<bool> {
+    let running = LISTENERS.lock().unwrap().remove(name);
+    let Some(running) = running else {
+        return Ok(false);
+    };
+
+    running.tasks.shutdown().await?;
+    tracing::info!("Listener {} on {} stopped", name, running.local_addr);
+    Ok(true)
+}
+
+/// 모든 추가 전송 서버를 중지합니다.
+pub async fn stop_all() -> Result<()> {
+    let running: Vec<RunningListener> = LISTENERS.lock().unwrap().drain().map(|(_, running)| running).collect();
+
+    let mut errors = Vec::new();
+    for listener in running {
+        if let Err(e) = listener.tasks.shutdown().await {
+            errors.push(format!("{}: {:#}", listener.config.name, e));
+        }
+    }
+    if !errors.is_empty() {
+        anyhow::bail!("Failed to stop some listeners: {}", errors.join(", "));
+    }
+    Ok(())
+}
+
+/// 실행 중인 추가 전송 서버 목록 (이름 순)
+pub fn list() -> Vec<ListenerStatus> {
+    let mut listeners: Vec<ListenerStatus> = LISTENERS.lock().unwrap().values().map(RunningListener::status).collect();
+    listeners.sort_by(|a, b| a.name.cmp(&b.name));
+    listeners
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_listener_lifecycle() {
+        let config = ListenerConfig {
+            name: "vpn-test".to_string(),
+            bind_addr: "127.0.0.1:0".to_string(),
+            download_dir: Some("/tmp/vpn".to_string()),
+            accept_policy: None,
+        };
+        assert!(ListenerConfig { bind_addr: "nowhere".to_string(), ..config.clone() }.validate().is_err());
+        assert!(ListenerConfig { name: " ".to_string(), ..config.clone() }.validate().is_err());
+
+        set_certificate(TlsCertificate::generate_self_signed("listener-test", "Listener").unwrap());
+        let status = start(config).await.unwrap();
+        assert!(status.running);
+        assert_ne!(status.local_addr, "127.0.0.1:0");
+        assert!(list().contains(&status));
+
+        assert!(stop("vpn-test").await.unwrap());
+        assert!(!stop("vpn-test").await.unwrap());
+        assert!(!list().iter().any(|listener| listener.name == "vpn-test"));
+    }
+}
@@ -0,0 +1,143 @@
+//! 잠긴 수신 파일 재시도 (Locked-file Retry)
+//!
+//! 수신은 항상 같은 폴더의 임시 파일(`staging_path`)에 받고, 검증과 검사를 마친 뒤 원래 이름으로
+//! 옮깁니다. Windows에서는 다른 앱이 열어 둔 파일(문서 편집기, 미디어 플레이어 등)을 바꿀 수 없어
+//! 옮기기가 실패하므로, 잠금이 풀릴 때까지 원래 파일 자리로 옮기기를 다시 시도합니다.
+//!
+//! # Process Flow
+//! 1. 수신을 마치고 옮길 때 잠김 에러(`is_locked`)면 임시 파일을 남겨 둠
+//! 2. 전송 상태를 WaitingForUnlock으로 바꾸고 `DestinationLocked` 이벤트 발행
+//! 3. `replace_when_unlocked`가 지수 백오프로 임시 파일을 원래 자리로 옮기기를 재시도
+//! 4. 옮기면 전송 완료, `MAX_WAIT`이 지나도록 잠겨 있으면 임시 파일을 남기고 전송 실패
+
+use anyhow::{Context, Result};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use super::error::PebbleError;
+use super::paths;
+
+/// 받는 중이거나 잠긴 파일 대신 받은 임시 파일의 접미사
+pub const STAGING_SUFFIX: &str = ".pebble-locked";
+
+/// 첫 재시도 대기 시간
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(2);
+
+/// 재시도 대기 시간 상한
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// 잠금이 풀리기를 기다리는 최대 시간
+pub const MAX_WAIT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// 다른 프로세스가 파일을 잠가서 생긴 에러인지 확인합니다.
+pub fn is_locked(error: &io::Error) -> bool {
+    // ERROR_SHARING_VIOLATION, ERROR_LOCK_VIOLATION
+    if cfg!(windows) && matches!(error.raw_os_error(), Some(32) | Some(33)) {
+        return true;
+    }
+    matches!(error.kind(), io::ErrorKind::ResourceBusy | io::ErrorKind::ExecutableFileBusy)
+}
+
+/// anyhow 에러 체인에 잠김 에러가 있는지 확인합니다.
+pub fn is_locked_error(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| cause.downcast_ref::<io::Error>().is_some_and(is_locked))
+}
+
+/// 수신 중에 기록할 임시 파일 경로 (같은 폴더라 옮기기가 원자적)
+pub fn staging_path(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(STAGING_SUFFIX);
+    dest.with_file_name(name)
+}
+
+/// 경로가 수신 중인 임시 파일인지 확인합니다 (스캔, 파일 감시에서 제외).
+pub fn is_staging(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().ends_with(STAGING_SUFFIX))
+}
+
+/// `attempt`번째 재시도 전 대기 시간 (1부터 시작)
+fn retry_delay(attempt: u32) -> Duration {
+    let exponent = attempt.saturating_sub(1).min(16);
+    RETRY_BASE_DELAY.saturating_mul(1 << exponent).min(RETRY_MAX_DELAY)
+}
+
+/// 잠금이 풀릴 때까지 임시 파일을 원래 자리로 옮기기를 재시도합니다.
+///
+/// # Arguments
+/// * `staged` - 대신 받은 임시 파일
+/// * `dest` - 원래 저장하려던 경로
+///
+/// # Returns
+/// * `MAX_WAIT` 동안 잠겨 있으면 `PebbleError::Io` (임시 파일은 남겨 둠)
+pub async fn replace_when_unlocked(staged: &Path, dest: &Path) -> Result<()> {
+    let started = Instant::now();
+    let mut attempt = 0;
+
+    loop {
+        let error = match std::fs::rename(paths::long_path(staged), paths::long_path(dest)) {
+            Ok(()) => {
+                tracing::info!("Moved {} into place after {} retries", dest.display(), attempt);
+                return Ok(());
+            }
+            Err(e) => e,
+        };
+
+        // Windows는 열린 파일을 덮어쓰는 이동을 접근 거부로 알리기도 함
+        let locked = is_locked(&error) || (cfg!(windows) && error.kind() == io::ErrorKind::PermissionDenied);
+        if !locked {
+            return Err(error).with_context(|| format!("Failed to move {} to {}", staged.display(), dest.display()));
+        }
+        if started.elapsed() >= MAX_WAIT {
+            return Err(PebbleError::io(format!(
+                "{} stayed locked; received file kept at {}",
+                dest.display(),
+                staged.display()
+            ))
+            .into());
+        }
+
+        attempt += 1;
+        let delay = retry_delay(attempt);
+        tracing::debug!("{} is locked, retrying in {:?} (attempt {})", dest.display(), delay, attempt);
+        tokio::time::sleep(delay).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_staging_path_and_delay() {
+        let dest = Path::new("downloads").join("report.docx");
+        let staged = staging_path(&dest);
+        assert_eq!(staged, Path::new("downloads").join("report.docx.pebble-locked"));
+        assert!(is_staging(&staged));
+        assert!(!is_staging(&dest));
+
+        assert_eq!(retry_delay(1), RETRY_BASE_DELAY);
+        assert_eq!(retry_delay(2), RETRY_BASE_DELAY * 2);
+        assert_eq!(retry_delay(100), RETRY_MAX_DELAY);
+
+        assert!(is_locked(&io::Error::from(io::ErrorKind::ResourceBusy)));
+        assert!(!is_locked(&io::Error::from(io::ErrorKind::NotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_replace_moves_staged_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let dest = dir.path().join("notes.txt");
+        std::fs::write(&dest, b"old").unwrap();
+        let staged = staging_path(&dest);
+        std::fs::write(&staged, b"new").unwrap();
+
+        replace_when_unlocked(&staged, &dest).await.unwrap();
+        assert_eq!(std::fs::read(&dest).unwrap(), b"new");
+        assert!(!staged.exists());
+
+        // 잠김이 아닌 에러는 기다리지 않고 실패
+        assert!(replace_when_unlocked(&staged, &dest).await.is_err());
+    }
+}
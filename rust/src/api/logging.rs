@@ -0,0 +1,452 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tracing_subscriber::filter::{filter_fn, EnvFilter, LevelFilter};
+use tracing_subscriber::fmt::{self as tracing_fmt, format::FmtSpan, MakeWriter};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, Layer, Registry};
+
+use super::util::Subscribers;
+
+/// 로그 파일 이름
+pub const LOG_FILE_NAME: &str = "pebble.log";
+
+/// 로그 하위 디렉토리 이름 (앱 데이터 디렉토리 아래)
+pub const LOG_DIR_NAME: &str = "logs";
+
+/// 로그 파일 하나의 최대 크기 (5MB)
+pub const MAX_LOG_FILE_SIZE: u64 = 5 * 1024 * 1024;
+
+/// 보관할 이전 로그 파일 개수 (pebble.log.1 ~ pebble.log.N)
+pub const MAX_LOG_FILES: usize = 3;
+
+/// span/이벤트 내보내기 파일 이름 (한 줄에 JSON 하나)
+pub const TRACE_FILE_NAME: &str = "pebble-trace.jsonl";
+
+/// 로그 레벨
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Off => LevelFilter::OFF,
+            LogLevel::Error => LevelFilter::ERROR,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Trace => LevelFilter::TRACE,
+        }
+    }
+}
+
+/// 크기 기준으로 순환(rotate)하는 로그 파일
+struct RotatingFile {
+    path: PathBuf,
+    max_size: u64,
+    max_files: usize,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, max_size: u64, max_files: usize) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create log directory: {}", parent.display()))?;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open log file: {}", path.display()))?;
+        let size = file.metadata()?.len();
+
+        Ok(Self { path, max_size, max_files, file, size })
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        if self.size + line.len() as u64 + 1 > self.max_size && self.size > 0 {
+            self.rotate()?;
+        }
+
+        writeln!(self.file, "{}", line)?;
+        self.size += line.len() as u64 + 1;
+
+        Ok(())
+    }
+
+    /// pebble.log → pebble.log.1 → ... → pebble.log.N 순으로 밀어내고 새 파일을 엽니다.
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        let rotated = |index: usize| {
+            let mut name = self.path.as_os_str().to_owned();
+            name.push(format!(".{}", index));
+            PathBuf::from(name)
+        };
+
+        let _ = fs::remove_file(rotated(self.max_files));
+        for index in (1..self.max_files).rev() {
+            let from = rotated(index);
+            if from.exists() {
+                fs::rename(&from, rotated(index + 1))?;
+            }
+        }
+        fs::rename(&self.path, rotated(1))?;
+
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+
+        Ok(())
+    }
+}
+
+/// Registry 바로 위에 올라가는 선택적 레이어 (OTLP 내보내기)
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// 전역 필터 교체 함수 (reload 핸들의 타입을 숨김)
+type FilterReloader = Box<dyn Fn(EnvFilter) -> Result<()> + Send + Sync>;
+
+/// 포맷된 로그 줄과 span 기록을 받는 출력 대상들
+struct LogSinks {
+    file: Mutex<Option<RotatingFile>>,
+    trace_file: Mutex<Option<RotatingFile>>,
+    subscribers: Subscribers<str>,
+}
+
+static SINKS: once_cell::sync::Lazy<LogSinks> = once_cell::sync::Lazy::new(|| LogSinks {
+    file: Mutex::new(None),
+    trace_file: Mutex::new(None),
+    subscribers: Subscribers::new(),
+});
+
+static FILTER_RELOADER: once_cell::sync::OnceCell<FilterReloader> = once_cell::sync::OnceCell::new();
+
+/// span/이벤트 JSON 내보내기 활성화 여부
+static TRACE_EXPORT_ENABLED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(feature = "otlp")]
+static OTLP_PROVIDER: Mutex<Option<opentelemetry_sdk::trace::SdkTracerProvider>> = Mutex::new(None);
+
+/// 사람이 읽는 로그 줄을 stderr, 순환 로그 파일, 구독자(Dart 스트림)로 동시에 기록합니다.
+///
+/// fmt 레이어는 이벤트 하나를 한 번의 write로 기록하므로 write 단위가 곧 로그 줄입니다.
+struct LineWriter;
+
+impl Write for LineWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        let line = text.trim_end_matches('\n');
+
+        eprintln!("{}", line);
+
+        // 로거 내부에서는 tracing 매크로를 사용하지 않음 (재귀 방지)
+        if let Ok(mut file) = SINKS.file.lock() {
+            if let Some(file) = file.as_mut() {
+                if let Err(e) = file.write_line(line) {
+                    eprintln!("Failed to write log file: {}", e);
+                }
+            }
+        }
+
+        SINKS.subscribers.notify(line);
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        flush_file(&SINKS.file)
+    }
+}
+
+/// span 시작/종료와 이벤트를 JSON 줄로 내보내기 파일에 기록합니다.
+struct TraceWriter;
+
+impl Write for TraceWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Ok(mut file) = SINKS.trace_file.lock() {
+            if let Some(file) = file.as_mut() {
+                file.write_line(String::from_utf8_lossy(buf).trim_end_matches('\n'))?;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        flush_file(&SINKS.trace_file)
+    }
+}
+
+fn flush_file(file: &Mutex<Option<RotatingFile>>) -> io::Result<()> {
+    if let Ok(mut file) = file.lock() {
+        if let Some(file) = file.as_mut() {
+            file.file.flush()?;
+        }
+    }
+    Ok(())
+}
+
+struct MakeLineWriter;
+
+impl<'a> MakeWriter<'a> for MakeLineWriter {
+    type Writer = LineWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        LineWriter
+    }
+}
+
+struct MakeTraceWriter;
+
+impl<'a> MakeWriter<'a> for MakeTraceWriter {
+    type Writer = TraceWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        TraceWriter
+    }
+}
+
+/// 기본 필터: `RUST_LOG`(예: "debug", "native::api::transfer=trace")가 있으면 따르고, 없으면 Info
+fn default_filter() -> EnvFilter {
+    EnvFilter::builder()
+        .with_default_directive(LevelFilter::INFO.into())
+        .from_env_lossy()
+}
+
+/// 전역 subscriber를 설치합니다. 이미 설치되어 있으면 에러를 반환합니다.
+fn install(otel_layer: Option<BoxedLayer>) -> Result<()> {
+    let (filter, reload_handle) = reload::Layer::new(default_filter());
+
+    let line_layer = tracing_fmt::layer()
+        .with_ansi(false)
+        .with_writer(MakeLineWriter);
+
+    let trace_layer = tracing_fmt::layer()
+        .json()
+        .with_current_span(true)
+        .with_span_list(true)
+        .with_span_events(FmtSpan::NEW | FmtSpan::CLOSE)
+        .with_writer(MakeTraceWriter)
+        .with_filter(filter_fn(|_| TRACE_EXPORT_ENABLED.load(Ordering::Relaxed)));
+
+    // log 크레이트를 사용하는 의존성의 기록도 tracing 이벤트로 전달됨 (tracing-log)
+    tracing_subscriber::registry()
+        .with(otel_layer)
+        .with(filter)
+        .with(line_layer)
+        .with(trace_layer)
+        .try_init()
+        .context("Global tracing subscriber is already installed")?;
+
+    let _ = FILTER_RELOADER.set(Box::new(move |filter| {
+        reload_handle
+            .reload(filter)
+            .map_err(|e| anyhow::anyhow!("Failed to reload log filter: {}", e))
+    }));
+
+    Ok(())
+}
+
+/// 전역 subscriber를 설치합니다. 이미 설치된 경우 아무것도 하지 않습니다.
+///
+/// 기본 레벨은 Info이며, `RUST_LOG`가 설정되어 있으면 EnvFilter 문법으로 해석합니다.
+pub fn init() {
+    if FILTER_RELOADER.get().is_none() {
+        let _ = install(None);
+    }
+}
+
+/// span을 OTLP(HTTP/protobuf) 수집기로 내보내도록 subscriber를 설치합니다.
+///
+/// `init()`보다 먼저 호출해야 합니다. 종료 전에 `shutdown()`을 호출하여
+/// 남은 span을 전송하세요.
+///
+/// # Arguments
+/// * `endpoint` - 수집기 주소 (예: "http://localhost:4318/v1/traces")
+#[cfg(feature = "otlp")]
+pub fn init_with_otlp(endpoint: &str) -> Result<()> {
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry_otlp::WithExportConfig;
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()
+        .context("Failed to create OTLP exporter")?;
+
+    let provider = opentelemetry_sdk::trace::SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(opentelemetry_sdk::Resource::builder().with_service_name("pebble").build())
+        .build();
+
+    let layer = tracing_opentelemetry::layer()
+        .with_tracer(provider.tracer("pebble"))
+        .boxed();
+
+    install(Some(layer))?;
+
+    *OTLP_PROVIDER
+        .lock()
+        .map_err(|e| anyhow::anyhow!("Failed to acquire OTLP provider lock: {}", e))? = Some(provider);
+
+    tracing::info!("OTLP span export enabled: {}", endpoint);
+
+    Ok(())
+}
+
+/// 버퍼에 남은 로그와 span을 내보냅니다. 프로세스 종료 직전에 호출합니다.
+pub fn shutdown() {
+    let _ = flush_file(&SINKS.file);
+    let _ = flush_file(&SINKS.trace_file);
+
+    #[cfg(feature = "otlp")]
+    if let Some(provider) = OTLP_PROVIDER.lock().ok().and_then(|mut provider| provider.take()) {
+        if let Err(e) = provider.shutdown() {
+            eprintln!("Failed to flush OTLP spans: {}", e);
+        }
+    }
+}
+
+/// 로그 레벨을 변경합니다.
+pub fn set_level(level: LogLevel) {
+    init();
+
+    let filter = EnvFilter::default().add_directive(LevelFilter::from(level).into());
+    if let Some(reload) = FILTER_RELOADER.get() {
+        if let Err(e) = reload(filter) {
+            eprintln!("{:#}", e);
+            return;
+        }
+    }
+
+    tracing::info!("Log level set to {:?}", level);
+}
+
+/// 로그 파일 기록을 시작합니다.
+///
+/// # Arguments
+/// * `log_dir` - 로그 파일을 저장할 디렉토리
+///
+/// # Returns
+/// * 로그 파일 경로
+pub fn enable_file_logging<P: AsRef<Path>>(log_dir: P) -> Result<PathBuf> {
+    init();
+
+    let path = log_dir.as_ref().join(LOG_FILE_NAME);
+    let file = RotatingFile::open(path.clone(), MAX_LOG_FILE_SIZE, MAX_LOG_FILES)?;
+
+    *SINKS
+        .file
+        .lock()
+        .map_err(|e| anyhow::anyhow!("Failed to acquire log file lock: {}", e))? = Some(file);
+
+    tracing::info!("File logging enabled: {}", path.display());
+
+    Ok(path)
+}
+
+/// span 시작/종료와 이벤트를 JSON 줄로 파일에 내보내기 시작합니다.
+///
+/// 각 줄에는 현재 span 목록(transfer_id, peer, sync 경로 등)이 포함되어
+/// 여러 기기의 기록을 transfer_id로 맞춰 볼 수 있습니다. 파일은 로그 파일과
+/// 같은 크기 기준으로 순환됩니다.
+///
+/// # Arguments
+/// * `dir` - 내보내기 파일을 저장할 디렉토리
+///
+/// # Returns
+/// * 내보내기 파일 경로
+pub fn enable_trace_export<P: AsRef<Path>>(dir: P) -> Result<PathBuf> {
+    init();
+
+    let path = dir.as_ref().join(TRACE_FILE_NAME);
+    let file = RotatingFile::open(path.clone(), MAX_LOG_FILE_SIZE, MAX_LOG_FILES)?;
+
+    *SINKS
+        .trace_file
+        .lock()
+        .map_err(|e| anyhow::anyhow!("Failed to acquire trace file lock: {}", e))? = Some(file);
+    TRACE_EXPORT_ENABLED.store(true, Ordering::Relaxed);
+
+    tracing::info!("Trace export enabled: {}", path.display());
+
+    Ok(path)
+}
+
+/// 현재 기록 중인 로그 파일 경로를 반환합니다.
+pub fn log_file_path() -> Option<String> {
+    SINKS
+        .file
+        .lock()
+        .ok()
+        .and_then(|file| file.as_ref().map(|f| f.path.to_string_lossy().to_string()))
+}
+
+/// 로그 줄 구독자를 등록합니다. 구독자가 false를 반환하면 자동으로 해제됩니다.
+pub fn subscribe<F>(subscriber: F)
+where
+    F: Fn(&str) -> bool + Send + 'static,
+{
+    init();
+
+    SINKS.subscribers.subscribe(subscriber);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_rotation_keeps_max_files() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(LOG_FILE_NAME);
+        let mut file = RotatingFile::open(path.clone(), 64, 2).unwrap();
+
+        for i in 0..20 {
+            file.write_line(&format!("log line number {:04}", i)).unwrap();
+        }
+
+        assert!(path.exists());
+        assert!(dir.path().join("pebble.log.1").exists());
+        assert!(dir.path().join("pebble.log.2").exists());
+        assert!(!dir.path().join("pebble.log.3").exists());
+        assert!(fs::metadata(&path).unwrap().len() <= 64);
+
+        let latest = fs::read_to_string(&path).unwrap();
+        assert!(latest.contains("0019"));
+    }
+
+    #[test]
+    fn test_trace_export_includes_span_fields() {
+        let dir = TempDir::new().unwrap();
+        let path = enable_trace_export(dir.path()).unwrap();
+
+        tracing::info_span!("transfer", transfer_id = "trace-test-id").in_scope(|| {
+            tracing::info!("chunk received");
+        });
+        shutdown();
+
+        let exported = fs::read_to_string(&path).unwrap();
+        let line = exported
+            .lines()
+            .find(|line| line.contains("chunk received"))
+            .expect("event should be exported");
+        let json: serde_json::Value = serde_json::from_str(line).unwrap();
+        assert_eq!(json["span"]["transfer_id"], "trace-test-id");
+    }
+}
@@ -0,0 +1,708 @@
+//! 폴더 매니페스트 (Folder Manifest)
+//!
+//! 동기화 루트 아래의 파일 목록(상대 경로, 크기, 해시, 수정 시간)을 DB에서 만들고,
+//! 상대 기기에서 받은 목록과 비교해 어느 쪽으로 어떤 파일을 보내야 하는지 계산합니다.
+//!
+//! 파일이 수십만 개인 루트는 매니페스트가 수십 MB가 되므로, 경로 순으로 `PAGE_ENTRIES`개씩
+//! 나눈 페이지를 zstd로 압축해 보내고, 받는 쪽은 페이지가 도착하는 대로 로컬 DB의 같은
+//! 구간과 비교합니다 (`ManifestMerger`). 어느 쪽도 매니페스트 전체를 메모리에 올리지 않습니다.
+//!
+//! # Process Flow
+//! 1. 요청측이 `ManifestRequest`로 상대 기기의 루트 경로 매니페스트를 요청
+//! 2. 응답측은 요청 기기에 그 루트의 읽기 권한(`shares::can_read`)이 있을 때만 응답
+//! 3. 응답측은 DB를 경로 순으로 한 페이지씩 읽어 압축한 `ManifestPage`를 차례로 전송
+//!    (마지막 페이지는 `last`)
+//! 4. 요청측은 페이지마다 로컬 DB의 앞선 경로까지 병합 비교해 올릴 파일, 받을 파일, 충돌을 구분
+//!
+//! 이전 동기화 때 받은 변경 기록 위치(`JournalCursor`)를 `since`로 보내면, 응답측은 그 뒤에
+//! 바뀐 파일만 보냅니다 (`incremental`). 응답측 DB가 새로 만들어져 기록 식별자가 다르면
+//! 전체 매니페스트로 응답하며, 요청측은 `diff_changes`로 양쪽 변경분만 비교합니다.
+//!
+//! 크기와 수정 시각만 기록하는 루트(`ChangeDetection::SizeMtime`)의 항목은 해시가 내용 해시가 아니므로,
+//! 응답측은 페이지에 루트의 변경 감지 방식을 싣고, 비교할 때는 한쪽이라도 그런 항목이면
+//! 해시 대신 크기와 수정 시각으로 같은지 판단합니다.
+//!
+//! 매니페스트의 상대 경로는 NFC로 정규화합니다. macOS가 NFD로 저장한 이름도 다른 기기의 같은
+//! 이름과 같은 경로로 비교되며, 디스크의 파일은 원래 표기 그대로 둡니다 (`paths::comparison_key`).
+//!
+//! # Security
+//! - 기기 ID를 보내지 않는 요청(구버전, start_pebble 이전)은 거부합니다.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::io::Read;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use unicode_normalization::UnicodeNormalization;
+use uuid::Uuid;
+
+use super::config;
+use super::db::{self, SyncStatus};
+use super::error::PebbleError;
+use super::integrity::{self, ChangeDetection};
+use super::paths;
+use super::service;
+use super::shares;
+use super::transfer::{self, TransferMessage};
+
+/// 매니페스트 페이지 하나의 응답 대기 시간
+pub const MANIFEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 페이지 하나에 담는 항목 수
+pub const PAGE_ENTRIES: usize = 1000;
+
+/// 압축을 푼 페이지의 최대 크기 (압축 폭탄 방지)
+const MAX_PAGE_BYTES: u64 = 16 * 1024 * 1024;
+
+/// 페이지 압축 방식 (`get_app_info`가 알리는 이름)
+pub const PAGE_COMPRESSION: &str = "zstd";
+
+/// 페이지 압축 수준 (zstd 기본값)
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// 매니페스트의 파일 하나
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// 루트 기준 상대 경로 ('/' 구분, NFC)
+    pub path: String,
+
+    pub file_size: u64,
+    pub file_hash: String,
+
+    /// 수정 시각 (Unix timestamp, 삭제 기록이면 삭제된 시각)
+    pub last_modified: i64,
+
+    /// 삭제 기록 여부 (상대에게 삭제를 알리기 위해 남김)
+    pub deleted: bool,
+}
+
+/// 두 매니페스트의 차이
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestDiff {
+    /// 상대 기기로 보내야 하는 파일 (로컬 항목)
+    pub upload: Vec<ManifestEntry>,
+
+    /// 상대 기기에서 받아야 하는 파일 (상대 항목)
+    pub download: Vec<ManifestEntry>,
+
+    /// 양쪽 내용이 다르고 수정 시각이 같아 방향을 정할 수 없는 경로
+    pub conflicts: Vec<String>,
+}
+
+/// 변경 기록(change journal)의 위치
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalCursor {
+    /// 변경 기록 식별자 (DB마다 다름)
+    pub journal_id: String,
+
+    /// 마지막으로 반영한 변경 일련번호
+    pub seq: i64,
+}
+
+/// 이 기기의 변경 기록 위치
+pub fn journal_head() -> Result<JournalCursor> {
+    let (journal_id, seq) = db::journal_head()?;
+    Ok(JournalCursor { journal_id, seq })
+}
+
+/// 매니페스트 응답 정보 (마지막 페이지 기준)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestHead {
+    /// 응답측의 변경 기록 위치 (다음 요청의 `since`로 사용)
+    pub cursor: Option<JournalCursor>,
+
+    /// 변경분만 받았는지 여부 (false면 전체 매니페스트)
+    pub incremental: bool,
+
+    /// 응답측 루트의 변경 감지 방식 (`SizeMtime`이면 내용이 아닌 크기와 수정 시각으로만 비교됨)
+    pub change_detection: ChangeDetection,
+}
+
+/// 매니페스트 비교 결과 하나
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ManifestChange {
+    /// 상대 기기로 보내야 하는 파일 (로컬 항목)
+    Upload(ManifestEntry),
+    /// 상대 기기에서 받아야 하는 파일 (상대 항목)
+    Download(ManifestEntry),
+    /// 방향을 정할 수 없는 경로
+    Conflict(String),
+}
+
+impl ManifestDiff {
+    fn push(&mut self, change: ManifestChange) {
+        match change {
+            ManifestChange::Upload(entry) => self.upload.push(entry),
+            ManifestChange::Download(entry) => self.download.push(entry),
+            ManifestChange::Conflict(path) => self.conflicts.push(path),
+        }
+    }
+}
+
+/// 루트 기준 상대 경로를 '/' 구분, NFC로 만듭니다. 루트 밖이면 None.
+fn relative_path(path: &str, root: &str) -> Option<String> {
+    let relative = Path::new(path).strip_prefix(Path::new(root)).ok()?;
+    let parts: Vec<_> = relative.iter().map(|part| part.to_string_lossy()).collect();
+    (!parts.is_empty()).then(|| parts.join("/").nfc().collect())
+}
+
+/// DB 기록을 매니페스트 항목으로 바꿉니다.
+fn entry_from(path: String, file: db::FileMetadata) -> ManifestEntry {
+    let deleted = file.sync_status == SyncStatus::Deleted;
+    ManifestEntry {
+        path,
+        file_size: file.file_size.max(0) as u64,
+        file_hash: file.file_hash,
+        last_modified: if deleted { file.status_changed_at } else { file.last_modified },
+        deleted,
+    }
+}
+
+/// DB에 기록된 루트 아래 파일을 경로 순으로 한 페이지씩 읽습니다.
+pub struct LocalPages {
+    root: String,
+    /// 이 일련번호 뒤에 바뀐 파일만 읽음 (None이면 모두)
+    since: Option<i64>,
+    /// 마지막으로 읽은 절대 경로
+    after: Option<String>,
+    buffer: VecDeque<ManifestEntry>,
+    done: bool,
+}
+
+impl LocalPages {
+    pub fn new(root: &str) -> Self {
+        Self::changed_since(root, None)
+    }
+
+    /// 변경 일련번호가 `since`보다 큰 파일만 읽습니다.
+    pub fn changed_since(root: &str, since: Option<i64>) -> Self {
+        Self {
+            root: paths::normalize(root),
+            since,
+            after: None,
+            buffer: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    /// 다음 페이지를 읽습니다. 더 읽을 항목이 없으면 None.
+    pub fn next_page(&mut self) -> Result<Option<Vec<ManifestEntry>>> {
+        if self.done {
+            return Ok(None);
+        }
+
+        let files = db::list_files_page(&self.root, self.since, self.after.as_deref(), PAGE_ENTRIES)?;
+        self.done = files.len() < PAGE_ENTRIES;
+        self.after = files.last().map(|file| file.path.clone());
+
+        let root = &self.root;
+        let entries: Vec<ManifestEntry> = files
+            .into_iter()
+            .filter_map(|file| Some(entry_from(relative_path(&file.path, root)?, file)))
+            .collect();
+
+        Ok((!entries.is_empty() || !self.done).then_some(entries))
+    }
+
+    /// 다음 항목을 꺼내지 않고 확인합니다 (필요하면 다음 페이지를 읽음).
+    fn peek(&mut self) -> Result<Option<&ManifestEntry>> {
+        while self.buffer.is_empty() {
+            match self.next_page()? {
+                Some(page) => self.buffer.extend(page),
+                None => return Ok(None),
+            }
+        }
+        Ok(self.buffer.front())
+    }
+
+    fn pop(&mut self) -> Option<ManifestEntry> {
+        self.buffer.pop_front()
+    }
+}
+
+/// DB에 기록된 루트 아래 파일로 매니페스트 전체를 만듭니다 (상대 경로 순).
+pub fn local_manifest(root: &str) -> Result<Vec<ManifestEntry>> {
+    let mut pages = LocalPages::new(root);
+    let mut entries = Vec::new();
+    while let Some(page) = pages.next_page()? {
+        entries.extend(page);
+    }
+    Ok(entries)
+}
+
+/// 두 항목의 내용이 같은지 확인합니다.
+///
+/// 한쪽이라도 크기와 수정 시각만 기록한 항목이면 해시를 비교할 수 없으므로 크기와 수정 시각으로 판단합니다.
+fn same_content(local: &ManifestEntry, remote: &ManifestEntry) -> bool {
+    if integrity::is_metadata_hash(&local.file_hash) || integrity::is_metadata_hash(&remote.file_hash) {
+        local.file_size == remote.file_size && local.last_modified == remote.last_modified
+    } else {
+        local.file_hash == remote.file_hash && local.file_size == remote.file_size
+    }
+}
+
+/// 같은 경로의 로컬 항목과 상대 항목을 비교합니다.
+///
+/// 내용이 다르면 더 최근에 바뀐 쪽을 따르고, 삭제 기록이 더 최근이면
+/// 살아 있는 쪽의 파일을 보내지 않습니다 (삭제는 데이터 전송 없이 반영).
+fn compare(path: &str, local: Option<&ManifestEntry>, remote: Option<&ManifestEntry>) -> Option<ManifestChange> {
+    match (local.filter(|e| !e.deleted), remote.filter(|e| !e.deleted)) {
+        (None, None) => None,
+        (Some(l), None) => remote
+            .is_none_or(|r| l.last_modified > r.last_modified)
+            .then(|| ManifestChange::Upload(l.clone())),
+        (None, Some(r)) => local
+            .is_none_or(|l| r.last_modified > l.last_modified)
+            .then(|| ManifestChange::Download(r.clone())),
+        (Some(l), Some(r)) if same_content(l, r) => None,
+        (Some(l), Some(r)) => match l.last_modified.cmp(&r.last_modified) {
+            std::cmp::Ordering::Greater => Some(ManifestChange::Upload(l.clone())),
+            std::cmp::Ordering::Less => Some(ManifestChange::Download(r.clone())),
+            std::cmp::Ordering::Equal => Some(ManifestChange::Conflict(path.to_string())),
+        },
+    }
+}
+
+/// 이전 동기화 뒤의 양쪽 변경분을 비교합니다.
+///
+/// 한쪽에서만 바뀐 경로는 다른 쪽이 이전 동기화 때 상태 그대로라고 보고, 상대만 바꾼 경로는
+/// 이 기기의 현재 DB 기록과 비교합니다.
+///
+/// # Arguments
+/// * `local_root` - 이 기기의 루트
+/// * `local` - 이 기기에서 바뀐 항목 (`LocalPages::changed_since`)
+/// * `remote` - 상대 기기에서 바뀐 항목 (incremental 응답)
+pub fn diff_changes(local_root: &str, local: &[ManifestEntry], remote: &[ManifestEntry]) -> Result<ManifestDiff> {
+    let local_by_path: BTreeMap<&str, &ManifestEntry> = local.iter().map(|e| (e.path.as_str(), e)).collect();
+    let remote_by_path: BTreeMap<&str, &ManifestEntry> = remote.iter().map(|e| (e.path.as_str(), e)).collect();
+    let all_paths: BTreeSet<&str> = local_by_path.keys().chain(remote_by_path.keys()).copied().collect();
+
+    let mut result = ManifestDiff::default();
+    for path in all_paths {
+        let local = local_by_path.get(path).copied();
+        let change = match remote_by_path.get(path).copied() {
+            Some(remote) if local.is_none() => {
+                let current = current_entry(local_root, path)?;
+                compare(path, current.as_ref(), Some(remote))
+            }
+            remote => compare(path, local, remote),
+        };
+        if let Some(change) = change {
+            result.push(change);
+        }
+    }
+    Ok(result)
+}
+
+/// 이 기기 DB에 기록된 상대 경로의 현재 항목
+fn current_entry(root: &str, relative: &str) -> Result<Option<ManifestEntry>> {
+    let path = paths::normalize(Path::new(root).join(relative));
+    Ok(db::get_file_metadata(&path)?.map(|file| entry_from(relative.to_string(), file)))
+}
+
+/// 메모리에 있는 로컬과 상대 매니페스트를 비교합니다 (비교 규칙은 `ManifestMerger`와 같음).
+pub fn diff(local: &[ManifestEntry], remote: &[ManifestEntry]) -> ManifestDiff {
+    let local_by_path: BTreeMap<&str, &ManifestEntry> = local.iter().map(|e| (e.path.as_str(), e)).collect();
+    let remote_by_path: BTreeMap<&str, &ManifestEntry> = remote.iter().map(|e| (e.path.as_str(), e)).collect();
+    let all_paths: BTreeSet<&str> = local_by_path.keys().chain(remote_by_path.keys()).copied().collect();
+
+    let mut result = ManifestDiff::default();
+    for path in all_paths {
+        if let Some(change) = compare(path, local_by_path.get(path).copied(), remote_by_path.get(path).copied()) {
+            result.push(change);
+        }
+    }
+    result
+}
+
+/// 상대 매니페스트 페이지를 받는 대로 로컬 DB와 병합 비교합니다.
+///
+/// 상대 페이지는 경로 순이어야 하며, 로컬 항목은 상대 페이지의 마지막 경로까지만 읽으므로
+/// 메모리에는 양쪽 한 페이지 정도만 남습니다.
+pub struct ManifestMerger {
+    local: LocalPages,
+    last_remote: Option<String>,
+}
+
+impl ManifestMerger {
+    /// # Arguments
+    /// * `local_root` - 비교할 이 기기의 루트
+    pub fn new(local_root: &str) -> Self {
+        Self {
+            local: LocalPages::new(local_root),
+            last_remote: None,
+        }
+    }
+
+    /// 상대 페이지 하나를 비교해 변경을 `sink`로 넘깁니다.
+    ///
+    /// # Returns
+    /// * 페이지가 경로 순이 아니면 `PebbleError::Protocol`
+    pub fn push_remote(
+        &mut self,
+        page: Vec<ManifestEntry>,
+        sink: &mut dyn FnMut(ManifestChange) -> Result<()>,
+    ) -> Result<()> {
+        for remote in page {
+            if self.last_remote.as_ref().is_some_and(|last| remote.path <= *last) {
+                return Err(PebbleError::protocol(format!("Manifest is not sorted at {}", remote.path)).into());
+            }
+
+            // 상대 경로보다 앞선 로컬 항목은 상대에 없는 파일
+            while let Some(local) = self.local.peek()? {
+                if local.path >= remote.path {
+                    break;
+                }
+                let local = self.local.pop().expect("peeked entry");
+                if let Some(change) = compare(&local.path, Some(&local), None) {
+                    sink(change)?;
+                }
+            }
+
+            let local = match self.local.peek()? {
+                Some(local) if local.path == remote.path => self.local.pop(),
+                _ => None,
+            };
+            if let Some(change) = compare(&remote.path, local.as_ref(), Some(&remote)) {
+                sink(change)?;
+            }
+            self.last_remote = Some(remote.path);
+        }
+        Ok(())
+    }
+
+    /// 상대 매니페스트가 끝난 뒤 남은 로컬 항목(상대에 없는 파일)을 넘깁니다.
+    pub fn finish(mut self, sink: &mut dyn FnMut(ManifestChange) -> Result<()>) -> Result<()> {
+        while self.local.peek()?.is_some() {
+            let local = self.local.pop().expect("peeked entry");
+            if let Some(change) = compare(&local.path, Some(&local), None) {
+                sink(change)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 페이지를 JSON으로 직렬화해 zstd로 압축합니다.
+fn encode_page(entries: &[ManifestEntry]) -> Result<Vec<u8>> {
+    let json = serde_json::to_vec(entries)?;
+    Ok(zstd::encode_all(json.as_slice(), COMPRESSION_LEVEL)?)
+}
+
+/// 압축된 페이지를 풉니다.
+///
+/// 경로를 NFC로 정규화하지 않는 구버전 기기의 항목도 같은 형식으로 맞춥니다.
+fn decode_page(data: &[u8]) -> Result<Vec<ManifestEntry>> {
+    let mut json = Vec::new();
+    zstd::stream::read::Decoder::new(data)?
+        .take(MAX_PAGE_BYTES + 1)
+        .read_to_end(&mut json)
+        .map_err(|e| PebbleError::protocol(format!("Invalid manifest page: {}", e)))?;
+    if json.len() as u64 > MAX_PAGE_BYTES {
+        return Err(PebbleError::protocol("Manifest page is too large").into());
+    }
+    let mut entries: Vec<ManifestEntry> =
+        serde_json::from_slice(&json).map_err(|e| PebbleError::protocol(format!("Invalid manifest page: {}", e)))?;
+    for entry in &mut entries {
+        entry.path = entry.path.nfc().collect();
+    }
+    Ok(entries)
+}
+
+/// 상대 기기의 매니페스트 요청에 응답합니다.
+pub(crate) async fn serve<S>(
+    stream: &mut S,
+    peer_addr: SocketAddr,
+    request_id: String,
+    root: String,
+    sender_device_id: Option<String>,
+    since: Option<JournalCursor>,
+) -> Result<()>
+where
+    S: AsyncReadExt + AsyncWriteExt + Unpin,
+{
+    let allowed = match sender_device_id.as_deref() {
+        Some(sender) => shares::can_read(&root, sender)?,
+        None => false,
+    };
+    if !allowed {
+        tracing::warn!("Manifest request for {} from {} rejected", root, peer_addr);
+        let reject = TransferMessage::TransferReject {
+            transfer_id: request_id,
+            reason: format!("{} is not shared with this device", root),
+            code: None,
+        };
+        stream.write_all(&reject.to_bytes()?).await?;
+        return Ok(());
+    }
+
+    // 기록 위치는 페이지를 읽기 전에 잡아 둠 (읽는 동안 바뀐 파일은 다음 요청에서 다시 보냄)
+    let head = journal_head()?;
+    let since = since.filter(|cursor| cursor.journal_id == head.journal_id && cursor.seq <= head.seq);
+    let incremental = since.is_some();
+    let change_detection = config::current().change_detection_for(&root);
+
+    let mut pages = LocalPages::changed_since(&root, since.map(|cursor| cursor.seq));
+    let (mut page, mut sent) = (0u32, 0usize);
+    let mut next = pages.next_page()?;
+    loop {
+        let entries = next.take().unwrap_or_default();
+        next = pages.next_page()?;
+        sent += entries.len();
+
+        let reply = TransferMessage::ManifestPage {
+            request_id: request_id.clone(),
+            page,
+            last: next.is_none(),
+            data: encode_page(&entries)?,
+            incremental,
+            cursor: next.is_none().then(|| head.clone()),
+            change_detection,
+        };
+        stream.write_all(&reply.to_bytes()?).await?;
+        if next.is_none() {
+            break;
+        }
+        page += 1;
+    }
+    stream.flush().await?;
+
+    tracing::info!(
+        "Sent {} manifest of {} ({} entries, {} page(s)) to {}",
+        if incremental { "incremental" } else { "full" }, root, sent, page + 1, peer_addr
+    );
+    Ok(())
+}
+
+/// 연결된 상대 기기에 루트 경로의 매니페스트를 요청하고, 페이지가 도착할 때마다 `on_page`를 호출합니다.
+///
+/// # Arguments
+/// * `root` - 상대 기기의 루트 경로 (상대가 이 기기에 공유한 폴더)
+/// * `since` - 이전 동기화 때 받은 기록 위치 (None이면 전체 매니페스트)
+/// * `on_page` - 변경분만 받는지 여부와 압축을 푼 페이지 (경로 순)
+///
+/// # Returns
+/// * `Result<ManifestHead>` - 응답측의 기록 위치와 변경분 여부
+/// * 공유되지 않은 루트면 `PebbleError::Rejected`
+pub async fn request<S, F>(stream: &mut S, root: &str, since: Option<&JournalCursor>, mut on_page: F) -> Result<ManifestHead>
+where
+    S: AsyncReadExt + AsyncWriteExt + Unpin,
+    F: FnMut(bool, Vec<ManifestEntry>) -> Result<()>,
+{
+    let request_id = Uuid::new_v4().to_string();
+    let request = TransferMessage::ManifestRequest {
+        request_id: request_id.clone(),
+        root: root.to_string(),
+        sender_device_id: service::device_id(),
+        since: since.cloned(),
+    };
+
+    stream.write_all(&request.to_bytes()?).await?;
+    stream.flush().await?;
+
+    let mut expected = 0u32;
+    loop {
+        let message = match tokio::time::timeout(MANIFEST_TIMEOUT, TransferMessage::from_stream(stream)).await {
+            Ok(message) => message?,
+            Err(_) => return Err(PebbleError::network("Manifest request timed out").into()),
+        };
+
+        match message {
+            TransferMessage::ManifestPage { request_id: reply, page, last, data, incremental, cursor, change_detection } if reply == request_id => {
+                if page != expected {
+                    return Err(PebbleError::protocol(format!("Expected manifest page {}, got {}", expected, page)).into());
+                }
+                on_page(incremental, decode_page(&data)?)?;
+                if last {
+                    return Ok(ManifestHead { cursor, incremental, change_detection });
+                }
+                expected += 1;
+            }
+            TransferMessage::TransferReject { reason, code, .. } => {
+                return Err(transfer::reject_error(reason, code).into());
+            }
+            other => {
+                return Err(PebbleError::protocol(format!("Expected ManifestPage, got {:?}", other)).into());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, hash: &str, last_modified: i64, deleted: bool) -> ManifestEntry {
+        ManifestEntry {
+            path: path.to_string(),
+            file_size: 10,
+            file_hash: hash.to_string(),
+            last_modified,
+            deleted,
+        }
+    }
+
+    #[test]
+    fn test_diff_directions() {
+        let local = vec![
+            entry("same.txt", "a", 1, false),
+            entry("local_only.txt", "b", 1, false),
+            entry("local_newer.txt", "c2", 5, false),
+            entry("remote_newer.txt", "d1", 1, false),
+            entry("conflict.txt", "e1", 3, false),
+            entry("deleted_here.txt", "f", 9, true),
+            entry("revived_there.txt", "g", 2, true),
+        ];
+        let remote = vec![
+            entry("same.txt", "a", 7, false),
+            entry("remote_only.txt", "h", 1, false),
+            entry("local_newer.txt", "c1", 1, false),
+            entry("remote_newer.txt", "d2", 5, false),
+            entry("conflict.txt", "e2", 3, false),
+            entry("deleted_here.txt", "f", 4, false),
+            entry("revived_there.txt", "g2", 8, false),
+        ];
+
+        let diff = diff(&local, &remote);
+        let paths = |entries: &[ManifestEntry]| entries.iter().map(|e| e.path.clone()).collect::<Vec<_>>();
+
+        assert_eq!(paths(&diff.upload), vec!["local_newer.txt", "local_only.txt"]);
+        assert_eq!(paths(&diff.download), vec!["remote_newer.txt", "remote_only.txt", "revived_there.txt"]);
+        assert_eq!(diff.conflicts, vec!["conflict.txt"]);
+    }
+
+    #[test]
+    fn test_merger_matches_diff_across_pages() {
+        crate::loopback::use_temp_environment();
+        let root = tempfile::TempDir::new().unwrap();
+        let root_path = paths::normalize(root.path());
+        for (name, hash, modified) in [("a.txt", "m-a", 5), ("c/d.txt", "m-d1", 1), ("e.txt", "m-e", 1)] {
+            let path = paths::normalize(root.path().join(name));
+            db::upsert_file(db::FileMetadata::new(path, modified, hash.to_string(), SyncStatus::Synced, 10)).unwrap();
+        }
+
+        let local = local_manifest(&root_path).unwrap();
+        assert_eq!(local.iter().map(|e| e.path.as_str()).collect::<Vec<_>>(), vec!["a.txt", "c/d.txt", "e.txt"]);
+
+        let remote = vec![
+            entry("b.txt", "m-b", 1, false),
+            entry("c/d.txt", "m-d2", 7, false),
+            entry("e.txt", "m-e", 1, false),
+            entry("f.txt", "m-f", 1, false),
+        ];
+        let mut merged = ManifestDiff::default();
+        let mut merger = ManifestMerger::new(&root_path);
+        for page in remote.chunks(2) {
+            // 압축한 페이지를 풀어서 넣음
+            let page = decode_page(&encode_page(page).unwrap()).unwrap();
+            merger.push_remote(page, &mut |change| {
+                merged.push(change);
+                Ok(())
+            }).unwrap();
+        }
+        merger.finish(&mut |change| {
+            merged.push(change);
+            Ok(())
+        }).unwrap();
+
+        assert_eq!(merged, diff(&local, &remote));
+        assert_eq!(merged.upload.len(), 1);
+        assert_eq!(merged.download.len(), 3);
+
+        // 정렬되지 않은 페이지는 거부
+        let mut merger = ManifestMerger::new(&root_path);
+        let unsorted = vec![entry("z.txt", "z", 1, false), entry("y.txt", "y", 1, false)];
+        assert!(merger.push_remote(unsorted, &mut |_| Ok(())).is_err());
+    }
+
+    #[test]
+    fn test_changes_since_journal_cursor() {
+        crate::loopback::use_temp_environment();
+        let root = tempfile::TempDir::new().unwrap();
+        let root_path = paths::normalize(root.path());
+        let file = |name: &str| paths::normalize(root.path().join(name));
+        for name in ["kept.txt", "edited.txt", "removed.txt"] {
+            db::upsert_file(db::FileMetadata::new(file(name), 1, format!("j-{}", name), SyncStatus::Synced, 10)).unwrap();
+        }
+        let head = journal_head().unwrap();
+
+        // 상태만 바뀐 파일은 매니페스트 항목이 같으므로 기록되지 않음
+        db::update_sync_status(&file("kept.txt"), &SyncStatus::Pending).unwrap();
+        db::upsert_file(db::FileMetadata::new(file("edited.txt"), 5, "j-edited2".to_string(), SyncStatus::Pending, 12)).unwrap();
+        db::update_sync_status(&file("removed.txt"), &SyncStatus::Deleted).unwrap();
+        db::upsert_file(db::FileMetadata::new(file("added.txt"), 5, "j-added".to_string(), SyncStatus::Pending, 3)).unwrap();
+
+        let mut pages = LocalPages::changed_since(&root_path, Some(head.seq));
+        let changed = pages.next_page().unwrap().unwrap();
+        let names: Vec<_> = changed.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(names, vec!["added.txt", "edited.txt", "removed.txt"]);
+        assert!(journal_head().unwrap().seq > head.seq);
+
+        // 상대는 kept.txt만 바꿈 → 이 기기의 현재 기록과 비교
+        let remote = vec![entry("kept.txt", "j-kept2", 9, false)];
+        let diff = diff_changes(&root_path, &changed, &remote).unwrap();
+        let paths = |entries: &[ManifestEntry]| entries.iter().map(|e| e.path.clone()).collect::<Vec<_>>();
+        assert_eq!(paths(&diff.upload), vec!["added.txt", "edited.txt"]);
+        assert_eq!(paths(&diff.download), vec!["kept.txt"]);
+        assert!(diff.conflicts.is_empty());
+    }
+
+    #[test]
+    fn test_nfd_local_name_matches_nfc_remote() {
+        crate::loopback::use_temp_environment();
+        let root = tempfile::TempDir::new().unwrap();
+        let root_path = paths::normalize(root.path());
+        // macOS가 저장한 NFD 이름 ("한글.txt")
+        let nfd = paths::normalize(root.path().join("\u{1112}\u{1161}\u{11AB}\u{1100}\u{1173}\u{11AF}.txt"));
+        db::upsert_file(db::FileMetadata::new(nfd.clone(), 3, "nfc-hash".to_string(), SyncStatus::Synced, 10)).unwrap();
+
+        let local = local_manifest(&root_path).unwrap();
+        assert_eq!(local[0].path, "한글.txt");
+
+        let mut changes = Vec::new();
+        let mut merger = ManifestMerger::new(&root_path);
+        let mut sink = |change| {
+            changes.push(change);
+            Ok(())
+        };
+        merger.push_remote(vec![entry("한글.txt", "nfc-hash", 3, false)], &mut sink).unwrap();
+        merger.finish(&mut sink).unwrap();
+        assert!(changes.is_empty());
+
+        // NFC 경로로 찾아도 디스크 표기 그대로 돌려줌
+        let nfc = paths::normalize(root.path().join("한글.txt"));
+        assert_eq!(db::get_file_metadata(&nfc).unwrap().unwrap().path, nfd);
+    }
+
+    #[test]
+    fn test_metadata_only_entries_compare_size_and_mtime() {
+        let meta = |path: &str, size: u64, modified: i64| ManifestEntry {
+            path: path.to_string(),
+            file_size: size,
+            file_hash: integrity::metadata_hash(size, modified),
+            last_modified: modified,
+            deleted: false,
+        };
+        let local = vec![meta("same.mov", 10, 3), meta("touched.mov", 10, 3), entry("hashed.mov", "c", 3, false)];
+        let remote = vec![meta("same.mov", 10, 3), meta("touched.mov", 10, 9), entry("hashed.mov", "d", 3, false)];
+
+        let diff = diff(&local, &remote);
+        assert!(diff.upload.is_empty());
+        assert_eq!(diff.download.iter().map(|e| e.path.as_str()).collect::<Vec<_>>(), ["touched.mov"]);
+        assert_eq!(diff.conflicts, ["hashed.mov"]);
+
+        // 한쪽만 내용 해시여도 크기와 수정 시각이 같으면 같은 파일로 봄
+        assert!(same_content(&meta("a", 10, 3), &entry("a", "blake3", 3, false)));
+    }
+
+    #[test]
+    fn test_relative_path() {
+        let root = paths::normalize(std::env::temp_dir().join("pebble_root"));
+        let nested = paths::normalize(Path::new(&root).join("a").join("b.txt"));
+        assert_eq!(relative_path(&nested, &root).as_deref(), Some("a/b.txt"));
+        assert_eq!(relative_path(&root, &root), None);
+    }
+}
@@ -0,0 +1,140 @@
+use anyhow::Result;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+
+use super::db;
+use super::registry::TransferDirection;
+use super::util::{unix_now, Subscribers};
+
+/// 텍스트 메시지 최대 길이 (64KB, UTF-8 바이트 기준)
+pub const MAX_TEXT_LENGTH: usize = 64 * 1024;
+
+/// 기기 간에 주고받은 텍스트 메시지 (URL, 클립보드 스니펫 등)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextMessage {
+    pub message_id: String,
+    pub direction: TransferDirection,
+
+    /// 상대 기기 ID (알 수 없으면 None)
+    pub peer_device_id: Option<String>,
+
+    /// 상대 기기 주소 (IP:Port)
+    pub peer_address: String,
+
+    pub text: String,
+
+    /// 보낸/받은 시각 (Unix timestamp)
+    pub created_at: i64,
+}
+
+impl TextMessage {
+    pub fn new(
+        message_id: String,
+        direction: TransferDirection,
+        peer_device_id: Option<String>,
+        peer_address: String,
+        text: String,
+    ) -> Self {
+        let created_at = unix_now();
+
+        Self { message_id, direction, peer_device_id, peer_address, text, created_at }
+    }
+}
+
+/// 텍스트 길이를 검증합니다.
+pub fn validate_text(text: &str) -> Result<()> {
+    if text.is_empty() {
+        anyhow::bail!("Text must not be empty");
+    }
+    if text.len() > MAX_TEXT_LENGTH {
+        anyhow::bail!("Text is too long: {} bytes (max {})", text.len(), MAX_TEXT_LENGTH);
+    }
+    Ok(())
+}
+
+fn direction_to_str(direction: TransferDirection) -> &'static str {
+    match direction {
+        TransferDirection::Send => "Send",
+        TransferDirection::Receive => "Receive",
+    }
+}
+
+/// 메시지를 기록에 저장합니다.
+pub fn save(message: &TextMessage) -> Result<()> {
+    let conn = db::open_connection()?;
+    conn.execute(
+        "INSERT OR IGNORE INTO text_messages
+         (message_id, direction, peer_device_id, peer_address, text, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            message.message_id,
+            direction_to_str(message.direction),
+            message.peer_device_id,
+            message.peer_address,
+            message.text,
+            message.created_at
+        ],
+    )?;
+    Ok(())
+}
+
+/// 최근 메시지 기록을 최신순으로 가져옵니다.
+pub fn history(limit: u32, offset: u32) -> Result<Vec<TextMessage>> {
+    let conn = db::open_connection()?;
+    let mut stmt = conn.prepare(
+        "SELECT message_id, direction, peer_device_id, peer_address, text, created_at
+         FROM text_messages ORDER BY created_at DESC, rowid DESC LIMIT ?1 OFFSET ?2",
+    )?;
+
+    let rows = stmt.query_map(params![limit, offset], |row| {
+        let direction: String = row.get(1)?;
+        Ok(TextMessage {
+            message_id: row.get(0)?,
+            direction: if direction == "Send" { TransferDirection::Send } else { TransferDirection::Receive },
+            peer_device_id: row.get(2)?,
+            peer_address: row.get(3)?,
+            text: row.get(4)?,
+            created_at: row.get(5)?,
+        })
+    })?;
+
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+/// 메시지 기록을 모두 삭제합니다.
+pub fn clear_history() -> Result<()> {
+    let conn = db::open_connection()?;
+    conn.execute("DELETE FROM text_messages", [])?;
+    Ok(())
+}
+
+static SUBSCRIBERS: Subscribers<TextMessage> = Subscribers::new();
+
+/// 수신 메시지 구독자를 등록합니다.
+pub fn subscribe<F>(subscriber: F)
+where
+    F: Fn(&TextMessage) -> bool + Send + 'static,
+{
+    SUBSCRIBERS.subscribe(subscriber);
+}
+
+/// 수신한 메시지를 저장하고 구독자에게 알립니다.
+pub fn deliver_incoming(message: TextMessage) -> Result<()> {
+    save(&message)?;
+
+    SUBSCRIBERS.notify(&message);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_text() {
+        assert!(validate_text("https://example.com").is_ok());
+        assert!(validate_text("").is_err());
+        assert!(validate_text(&"a".repeat(MAX_TEXT_LENGTH + 1)).is_err());
+    }
+}
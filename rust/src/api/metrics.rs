@@ -0,0 +1,259 @@
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+
+use super::config;
+use super::discovery;
+use super::registry::{self, TransferDirection};
+use super::service::ServiceKind;
+use super::transfer::TransferStatus;
+
+/// 전송 결과 라벨 (completed, failed, cancelled)
+const RESULTS: [&str; 3] = ["completed", "failed", "cancelled"];
+
+/// 서비스 라벨 (에러 카운터용)
+const SERVICES: [(ServiceKind, &str); 3] = [
+    (ServiceKind::Discovery, "discovery"),
+    (ServiceKind::TransferServer, "transfer_server"),
+    (ServiceKind::Watcher, "watcher"),
+];
+
+static BYTES_SENT: AtomicU64 = AtomicU64::new(0);
+static BYTES_RECEIVED: AtomicU64 = AtomicU64::new(0);
+static ACTIVE_CONNECTIONS: AtomicI64 = AtomicI64::new(0);
+
+/// [방향(send, receive)][결과] 별 종료된 전송 수
+static TRANSFERS_FINISHED: [[AtomicU64; 3]; 2] = [
+    [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)],
+    [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)],
+];
+
+/// 서비스별 에러 수 (SERVICES 순서)
+static ERRORS: [AtomicU64; 3] = [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)];
+
+fn direction_index(direction: TransferDirection) -> usize {
+    match direction {
+        TransferDirection::Send => 0,
+        TransferDirection::Receive => 1,
+    }
+}
+
+/// 상대에게 전달한 파일 바이트를 기록합니다 (구멍, 중복 제거로 건너뛴 청크, 요청에 담아 보낸 파일 포함).
+pub fn add_bytes_sent(bytes: u64) {
+    BYTES_SENT.fetch_add(bytes, Ordering::Relaxed);
+}
+
+/// 수신하여 디스크에 기록한 파일 바이트를 기록합니다 (구멍, 저장소에서 채운 청크, 요청에 담겨 온 파일 포함).
+pub fn add_bytes_received(bytes: u64) {
+    BYTES_RECEIVED.fetch_add(bytes, Ordering::Relaxed);
+}
+
+/// 지금까지 기록한 (보낸, 받은) 파일 바이트
+#[cfg(test)]
+pub(crate) fn transfer_bytes() -> (u64, u64) {
+    (BYTES_SENT.load(Ordering::Relaxed), BYTES_RECEIVED.load(Ordering::Relaxed))
+}
+
+/// 전송이 끝났을 때 최종 상태를 기록합니다. Completed/Cancelled 외에는 실패로 셉니다.
+pub fn record_transfer_finished(direction: TransferDirection, status: TransferStatus) {
+    let result = match status {
+        TransferStatus::Completed => 0,
+        TransferStatus::Cancelled => 2,
+        _ => 1,
+    };
+    TRANSFERS_FINISHED[direction_index(direction)][result].fetch_add(1, Ordering::Relaxed);
+}
+
+/// 서비스 에러를 기록합니다.
+pub fn record_error(kind: ServiceKind) {
+    if let Some(index) = SERVICES.iter().position(|(k, _)| *k == kind) {
+        ERRORS[index].fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// 열린 전송 연결 수를 추적하는 가드 (drop 시 감소)
+pub struct ConnectionGuard(());
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        ACTIVE_CONNECTIONS.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// 전송 연결 하나가 열렸음을 기록합니다.
+pub fn track_connection() -> ConnectionGuard {
+    ACTIVE_CONNECTIONS.fetch_add(1, Ordering::Relaxed);
+    ConnectionGuard(())
+}
+
+fn write_metric(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// 현재 DB 파일 크기 (WAL 포함)
+fn db_size_bytes() -> u64 {
+    let db_path = config::current().db_path;
+    [db_path.clone(), format!("{}-wal", db_path)]
+        .iter()
+        .filter_map(|path| std::fs::metadata(path).ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// 현재 값을 Prometheus 텍스트 형식(0.0.4)으로 렌더링합니다.
+pub fn render() -> String {
+    let mut out = String::new();
+
+    write_metric(&mut out, "pebble_transfer_bytes_total", "counter", "File bytes transferred, including holes and deduplicated chunks");
+    let _ = writeln!(out, "pebble_transfer_bytes_total{{direction=\"send\"}} {}", BYTES_SENT.load(Ordering::Relaxed));
+    let _ = writeln!(out, "pebble_transfer_bytes_total{{direction=\"receive\"}} {}", BYTES_RECEIVED.load(Ordering::Relaxed));
+
+    write_metric(&mut out, "pebble_transfers_total", "counter", "Finished transfers by direction and result");
+    for (direction, counters) in ["send", "receive"].iter().zip(TRANSFERS_FINISHED.iter()) {
+        for (result, counter) in RESULTS.iter().zip(counters.iter()) {
+            let _ = writeln!(
+                out,
+                "pebble_transfers_total{{direction=\"{}\",result=\"{}\"}} {}",
+                direction, result, counter.load(Ordering::Relaxed)
+            );
+        }
+    }
+
+    write_metric(&mut out, "pebble_active_connections", "gauge", "Open transfer connections");
+    let _ = writeln!(out, "pebble_active_connections {}", ACTIVE_CONNECTIONS.load(Ordering::Relaxed));
+
+    write_metric(&mut out, "pebble_active_transfers", "gauge", "Transfers in progress");
+    let _ = writeln!(out, "pebble_active_transfers {}", registry::global().list().len());
+
+    let devices = discovery::get_discovered_devices().unwrap_or_default();
+    let online = devices.iter().filter(|device| device.is_online).count();
+    write_metric(&mut out, "pebble_discovered_peers", "gauge", "Peers discovered on the local network");
+    let _ = writeln!(out, "pebble_discovered_peers{{state=\"online\"}} {}", online);
+    let _ = writeln!(out, "pebble_discovered_peers{{state=\"offline\"}} {}", devices.len() - online);
+
+    write_metric(&mut out, "pebble_db_size_bytes", "gauge", "SQLite database size including WAL");
+    let _ = writeln!(out, "pebble_db_size_bytes {}", db_size_bytes());
+
+    write_metric(&mut out, "pebble_errors_total", "counter", "Background service errors");
+    for ((_, service), counter) in SERVICES.iter().zip(ERRORS.iter()) {
+        let _ = writeln!(out, "pebble_errors_total{{service=\"{}\"}} {}", service, counter.load(Ordering::Relaxed));
+    }
+
+    out
+}
+
+/// 메트릭 HTTP 리스너를 바인딩하고 백그라운드에서 실행합니다.
+///
+/// `GET /metrics`에 Prometheus 텍스트 형식으로 응답하며, 그 외 경로는 404입니다.
+/// 인증이 없으므로 신뢰할 수 있는 네트워크 주소에만 바인딩하세요.
+///
+/// # Arguments
+/// * `addr` - 바인딩 주소 (예: "0.0.0.0:9464", 포트 0이면 임의 포트)
+///
+/// # Returns
+/// * 실제로 바인딩된 주소
+#[cfg(feature = "metrics")]
+pub async fn start_listener(addr: std::net::SocketAddr) -> anyhow::Result<std::net::SocketAddr> {
+    use anyhow::Context;
+    use tokio::net::TcpListener;
+
+    let listener = TcpListener::bind(addr).await
+        .with_context(|| format!("Failed to bind metrics listener on {}", addr))?;
+    let local_addr = listener.local_addr()?;
+
+    tracing::info!("Metrics listener on http://{}/metrics", local_addr);
+
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, peer_addr)) => {
+                    tokio::spawn(async move {
+                        if let Err(e) = handle_http(stream).await {
+                            tracing::debug!("Metrics request from {} failed: {:#}", peer_addr, e);
+                        }
+                    });
+                }
+                Err(e) => tracing::warn!("Error accepting metrics connection: {}", e),
+            }
+        }
+    });
+
+    Ok(local_addr)
+}
+
+/// 요청 하나를 읽고 응답한 뒤 연결을 닫습니다.
+#[cfg(feature = "metrics")]
+async fn handle_http(mut stream: tokio::net::TcpStream) -> anyhow::Result<()> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// 요청 헤더 최대 크기
+    const MAX_REQUEST_SIZE: usize = 8 * 1024;
+
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let n = tokio::time::timeout(std::time::Duration::from_secs(5), stream.read(&mut buf)).await??;
+        if n == 0 || request.len() + n > MAX_REQUEST_SIZE {
+            anyhow::bail!("Incomplete or oversized request");
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+
+    let request_line = String::from_utf8_lossy(&request);
+    let mut parts = request_line.split_whitespace();
+    let (status, content_type, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", "text/plain; version=0.0.4", render()),
+        _ => ("404 Not Found", "text/plain", "Not Found\n".to_string()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, content_type, body.len(), body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_contains_recorded_values() {
+        add_bytes_sent(1234);
+        record_transfer_finished(TransferDirection::Receive, TransferStatus::Cancelled);
+        record_error(ServiceKind::Watcher);
+        let _connection = track_connection();
+
+        let text = render();
+        let value = |prefix: &str| -> u64 {
+            text.lines()
+                .find_map(|line| line.strip_prefix(prefix))
+                .and_then(|rest| rest.trim().parse().ok())
+                .unwrap_or_else(|| panic!("missing metric {}", prefix))
+        };
+
+        assert!(value("pebble_transfer_bytes_total{direction=\"send\"}") >= 1234);
+        assert!(value("pebble_transfers_total{direction=\"receive\",result=\"cancelled\"}") >= 1);
+        assert!(value("pebble_errors_total{service=\"watcher\"}") >= 1);
+        assert!(text.contains("# TYPE pebble_active_connections gauge"));
+    }
+
+    #[cfg(feature = "metrics")]
+    #[tokio::test]
+    async fn test_listener_serves_metrics() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let addr = start_listener("127.0.0.1:0".parse().unwrap()).await.unwrap();
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("pebble_transfer_bytes_total"));
+    }
+}
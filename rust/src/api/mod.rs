@@ -1,7 +1,68 @@
 pub mod simple;
+pub mod error;
+pub mod logging;
+pub mod config;
+pub mod settings;
+pub mod service;
+pub mod control;
+pub mod feed;
+pub mod supervisor;
+pub mod lifecycle;
+pub mod wake;
+pub mod diagnostics;
+pub mod info;
 pub mod db;
+pub mod scan;
+pub mod rootstats;
 pub mod integrity;
+pub mod crc32c;
+pub mod verifier;
+pub mod paths;
+pub mod filename;
 pub mod watcher;
+pub mod volume;
 pub mod discovery;
+pub mod peers;
+pub mod revocation;
 pub mod certificate;
-pub mod transfer;
\ No newline at end of file
+pub mod transfer;
+pub mod listeners;
+pub mod tuning;
+pub mod pool;
+pub mod congestion;
+pub mod accept;
+pub mod registry;
+pub mod operations;
+pub mod priority;
+pub mod sendqueue;
+pub mod presence;
+pub mod history;
+pub mod receipts;
+pub mod partials;
+pub mod thumbnails;
+pub mod audit;
+pub mod messages;
+pub mod clipboard;
+pub mod events;
+pub mod speedtest;
+pub mod probe;
+pub mod forward;
+pub mod manifest;
+pub mod snapshot;
+pub mod pairs;
+pub mod schedule;
+pub mod dedup;
+pub mod quota;
+pub mod bandwidth;
+pub mod shares;
+pub mod storage;
+pub mod backend;
+pub mod staging;
+pub mod scanhook;
+pub mod locked;
+pub mod xattrs;
+#[cfg(any(test, feature = "fault-injection"))]
+pub mod fault;
+pub mod metrics;
+pub mod util;
+
@@ -0,0 +1,212 @@
+//! 오래 걸리는 작업 관리 (Long-Running Operations)
+//!
+//! 스캔, 무결성 검사, 동기화 예상, 전송처럼 몇 초 이상 걸리는 작업을 ID로 조회하고
+//! 취소할 수 있도록 한곳에 모읍니다. UI는 `list`로 진행 중인 작업과 진행률을 보여주고
+//! `cancel`로 어떤 종류의 작업이든 같은 방식으로 중단합니다.
+//!
+//! # Process Flow
+//! 1. 작업을 시작할 때 `begin`으로 등록하고 `OperationHandle`을 받음
+//! 2. 작업은 진행하며 `set_progress`로 진행률을 갱신하고 `check`(블로킹 작업) 또는
+//!    `run`(비동기 작업)으로 취소 요청을 반영 (취소되면 `PebbleError::Cancelled`)
+//! 3. 핸들이 drop되면 목록에서 제거
+//! 4. 전송은 전송 레지스트리(`registry`)가 관리하므로 `list`와 `cancel`이 레지스트리로 위임
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use super::error::PebbleError;
+use super::registry;
+use super::util::unix_now;
+
+/// 작업 종류
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OperationKind {
+    /// 초기 디렉토리 스캔
+    Scan,
+
+    /// 무결성 검사 (전체 재해시)
+    Verify,
+
+    /// 동기화 쌍 비교
+    Sync,
+
+    /// 파일 송수신
+    Transfer,
+}
+
+/// 진행 중인 작업 하나의 상태
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Operation {
+    /// 작업 ID (전송이면 전송 ID)
+    pub operation_id: String,
+
+    pub kind: OperationKind,
+
+    /// 작업 대상 (스캔/검사 루트, 동기화 쌍, 전송 파일 경로)
+    pub target: String,
+
+    /// 처리한 양 (파일 수 또는 바이트)
+    pub completed: u64,
+
+    /// 전체 양 (아직 알 수 없으면 None)
+    pub total: Option<u64>,
+
+    /// 진행률 (0~100, 전체 양을 모르면 None)
+    pub progress_percent: Option<f64>,
+
+    /// 취소를 요청했지만 아직 멈추지 않음
+    pub cancel_requested: bool,
+
+    /// 시작 시각 (Unix timestamp)
+    pub started_at: i64,
+}
+
+struct Entry {
+    info: Operation,
+    token: CancellationToken,
+}
+
+/// 등록된 작업 (전송 제외)
+static OPERATIONS: once_cell::sync::Lazy<Mutex<HashMap<String, Entry>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 등록된 작업의 핸들 (drop되면 목록에서 제거)
+pub struct OperationHandle {
+    operation_id: String,
+    token: CancellationToken,
+}
+
+/// 새 작업을 등록합니다.
+///
+/// # Arguments
+/// * `kind` - 작업 종류
+/// * `target` - 표시용 작업 대상
+pub fn begin(kind: OperationKind, target: &str) -> OperationHandle {
+    let operation_id = Uuid::new_v4().to_string();
+    let token = CancellationToken::new();
+    let info = Operation {
+        operation_id: operation_id.clone(),
+        kind,
+        target: target.to_string(),
+        completed: 0,
+        total: None,
+        progress_percent: None,
+        cancel_requested: false,
+        started_at: unix_now(),
+    };
+
+    OPERATIONS.lock().unwrap().insert(operation_id.clone(), Entry { info, token: token.clone() });
+    OperationHandle { operation_id, token }
+}
+
+impl OperationHandle {
+    pub fn id(&self) -> &str {
+        &self.operation_id
+    }
+
+    /// 취소 요청 신호 (하위 작업에 넘길 때)
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    /// 진행률을 갱신합니다.
+    pub fn set_progress(&self, completed: u64, total: Option<u64>) {
+        if let Some(entry) = OPERATIONS.lock().unwrap().get_mut(&self.operation_id) {
+            entry.info.completed = completed;
+            entry.info.total = total;
+            entry.info.progress_percent =
+                total.map(|total| if total == 0 { 100.0 } else { completed as f64 / total as f64 * 100.0 });
+        }
+    }
+
+    /// 취소되었으면 `PebbleError::Cancelled`를 반환합니다 (블로킹 작업의 반복마다 호출).
+    pub fn check(&self) -> Result<()> {
+        if self.token.is_cancelled() {
+            return Err(self.cancelled_error().into());
+        }
+        Ok(())
+    }
+
+    /// 비동기 작업을 실행하고, 끝나기 전에 취소되면 중단합니다.
+    pub async fn run<T>(&self, work: impl Future<Output = Result<T>>) -> Result<T> {
+        tokio::select! {
+            result = work => result,
+            _ = self.token.cancelled() => Err(self.cancelled_error().into()),
+        }
+    }
+
+    fn cancelled_error(&self) -> PebbleError {
+        PebbleError::cancelled(format!("Operation {} cancelled", self.operation_id))
+    }
+}
+
+impl Drop for OperationHandle {
+    fn drop(&mut self) {
+        OPERATIONS.lock().unwrap().remove(&self.operation_id);
+    }
+}
+
+/// 진행 중인 작업 목록 (전송 포함, 시작 시각 순)
+pub fn list() -> Vec<Operation> {
+    let mut operations: Vec<Operation> = OPERATIONS.lock().unwrap().values().map(|entry| entry.info.clone()).collect();
+    operations.extend(registry::global().list().into_iter().map(|transfer| Operation {
+        operation_id: transfer.transfer_id,
+        kind: OperationKind::Transfer,
+        target: transfer.file_path,
+        completed: transfer.bytes_transferred,
+        total: Some(transfer.total_bytes),
+        progress_percent: Some(transfer.progress_percent),
+        cancel_requested: false,
+        started_at: transfer.started_at,
+    }));
+
+    operations.sort_by(|a, b| a.started_at.cmp(&b.started_at).then_with(|| a.operation_id.cmp(&b.operation_id)));
+    operations
+}
+
+/// 작업을 취소합니다. 작업은 다음 확인 지점에서 `PebbleError::Cancelled`로 끝납니다.
+///
+/// # Returns
+/// * 그 ID의 작업이나 전송이 없으면 `PebbleError::NotFound`
+pub fn cancel(operation_id: &str) -> Result<()> {
+    if let Some(entry) = OPERATIONS.lock().unwrap().get_mut(operation_id) {
+        entry.info.cancel_requested = true;
+        entry.token.cancel();
+        return Ok(());
+    }
+
+    registry::global()
+        .cancel(operation_id)
+        .map_err(|_| PebbleError::not_found(format!("Operation {}", operation_id)).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_operation_lifecycle() {
+        let handle = begin(OperationKind::Verify, "/tmp/operation-test");
+        handle.set_progress(3, Some(4));
+
+        let info = list().into_iter().find(|op| op.operation_id == handle.id()).unwrap();
+        assert_eq!((info.kind, info.completed, info.progress_percent), (OperationKind::Verify, 3, Some(75.0)));
+        handle.check().unwrap();
+
+        cancel(handle.id()).unwrap();
+        assert!(list().iter().any(|op| op.operation_id == handle.id() && op.cancel_requested));
+        assert!(matches!(PebbleError::from(handle.check().unwrap_err()), PebbleError::Cancelled { .. }));
+        let pending = handle.run(std::future::pending::<Result<()>>()).await;
+        assert!(matches!(PebbleError::from(pending.unwrap_err()), PebbleError::Cancelled { .. }));
+
+        let id = handle.id().to_string();
+        drop(handle);
+        assert!(!list().iter().any(|op| op.operation_id == id));
+        assert!(matches!(PebbleError::from(cancel(&id).unwrap_err()), PebbleError::NotFound { .. }));
+    }
+}
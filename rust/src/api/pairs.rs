@@ -0,0 +1,476 @@
+//! 동기화 쌍과 동기화 크기 예상 (Sync Pairs)
+//!
+//! 이 기기의 폴더(로컬 루트)와 상대 기기의 폴더(상대 루트)를 짝지어 기록합니다.
+//! 상대 루트는 상대 기기가 이 기기에 공유(`shares`)한 폴더여야 매니페스트를 받을 수 있습니다.
+//!
+//! # Process Flow
+//! 1. `add_pair`로 로컬 루트, 상대 기기 ID, 상대 루트를 등록
+//! 2. `estimate_sync`가 상대 매니페스트를 페이지 단위로 받으며 로컬 DB와 병합 비교 (`ManifestMerger`)
+//! 3. 받는 쪽에 같은 내용(해시와 크기)이 이미 있는 파일은 전송 없이 끝나므로 바이트에서 제외
+//!    → UI가 동기화를 시작하기 전에 "12.4 GB를 전송합니다" 같은 경고를 표시
+//! 4. 동기화를 마치면 `mark_synced`로 예상 때 받은 기록 위치(`SyncCursor`)를 저장
+//!    → 다음 예상부터는 양쪽의 변경분만 주고받아 비교
+
+use anyhow::Result;
+use rusqlite::{params, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use super::config;
+use super::db::{self, SyncStatus};
+use super::error::PebbleError;
+use super::filename::{self, CaseCollisionPolicy};
+use super::integrity::{self, ChangeDetection};
+use super::operations::{self, OperationHandle, OperationKind};
+use super::manifest::{self, JournalCursor, LocalPages, ManifestChange, ManifestDiff, ManifestEntry, ManifestMerger};
+use super::peers;
+use super::shares;
+use super::transfer::TransferClient;
+use super::util::unix_now;
+
+/// 동기화 쌍
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncPair {
+    pub pair_id: i64,
+
+    /// 이 기기의 폴더 (정규화된 절대 경로)
+    pub local_root: String,
+
+    /// 상대 기기 ID
+    pub peer_device_id: String,
+
+    /// 상대 기기의 폴더 (상대 기기 기준 경로)
+    pub remote_root: String,
+
+    /// 등록 시각 (Unix timestamp)
+    pub created_at: i64,
+
+    /// 마지막으로 동기화를 마친 양쪽 기록 위치 (None이면 다음 비교는 전체 매니페스트)
+    pub cursor: Option<SyncCursor>,
+}
+
+/// 동기화 쌍의 양쪽 변경 기록 위치
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncCursor {
+    /// 이 기기의 변경 일련번호
+    pub local_seq: i64,
+
+    /// 상대 기기의 기록 위치
+    pub remote: JournalCursor,
+}
+
+/// 동기화를 시작했을 때 오갈 데이터 예상치
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SyncEstimate {
+    pub pair_id: i64,
+
+    /// 상대 기기로 보낼 파일 수와 바이트
+    pub upload_files: u64,
+    pub upload_bytes: u64,
+
+    /// 상대 기기에서 받을 파일 수와 바이트
+    pub download_files: u64,
+    pub download_bytes: u64,
+
+    /// 받는 쪽에 같은 내용이 이미 있어 데이터 전송 없이 끝나는 파일 수 (위 파일 수에 포함)
+    pub deduplicated_files: u64,
+
+    /// 양쪽 내용이 다르고 수정 시각이 같아 방향을 정할 수 없는 경로 (바이트에 포함하지 않음)
+    pub conflicts: Vec<String>,
+
+    /// 받을 파일 중 이 기기의 파일이나 다른 받을 파일과 대소문자만 다른 경로
+    /// (대소문자를 구분하지 않는 파일 시스템에서만, `case_collision_policy`가 Skip이면 위 파일 수에서 뺌)
+    pub case_collisions: Vec<String>,
+
+    /// 이전 동기화 뒤의 변경분만 비교했는지 여부
+    pub incremental: bool,
+
+    /// 상대 루트의 변경 감지 방식 (`SizeMtime`이면 내용이 아닌 크기와 수정 시각으로만 비교함)
+    pub remote_change_detection: ChangeDetection,
+
+    /// 이 예상대로 동기화를 마친 뒤 `mark_synced`에 넘길 기록 위치
+    pub cursor: Option<SyncCursor>,
+}
+
+const SELECT_PAIR_COLUMNS: &str =
+    "pair_id, local_root, peer_device_id, remote_root, created_at, local_seq, remote_journal_id, remote_seq";
+
+fn pair_from_row(row: &Row) -> rusqlite::Result<SyncPair> {
+    let cursor = match (row.get(5)?, row.get(6)?, row.get(7)?) {
+        (Some(local_seq), Some(journal_id), Some(seq)) => Some(SyncCursor {
+            local_seq,
+            remote: JournalCursor { journal_id, seq },
+        }),
+        _ => None,
+    };
+    Ok(SyncPair {
+        pair_id: row.get(0)?,
+        local_root: row.get(1)?,
+        peer_device_id: row.get(2)?,
+        remote_root: row.get(3)?,
+        created_at: row.get(4)?,
+        cursor,
+    })
+}
+
+/// 동기화 쌍을 등록합니다. 같은 쌍이 이미 있으면 그대로 반환합니다.
+///
+/// # Arguments
+/// * `local_root` - 이 기기의 폴더
+/// * `peer_device_id` - 상대 기기 ID
+/// * `remote_root` - 상대 기기의 폴더 (상대 기기가 이 기기에 공유한 폴더)
+pub fn add_pair(local_root: &str, peer_device_id: &str, remote_root: &str) -> Result<SyncPair> {
+    if peer_device_id.trim().is_empty() {
+        return Err(PebbleError::invalid_argument("Peer device ID is empty").into());
+    }
+    if remote_root.trim().is_empty() {
+        return Err(PebbleError::invalid_argument("Remote root is empty").into());
+    }
+    if !Path::new(local_root).is_dir() {
+        return Err(PebbleError::invalid_argument(format!("Local root is not a directory: {}", local_root)).into());
+    }
+
+    let local_root = shares::absolute(local_root)?;
+    let created_at = unix_now();
+
+    let conn = db::open_connection()?;
+    conn.execute(
+        "INSERT INTO sync_pairs (local_root, peer_device_id, remote_root, created_at) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(local_root, peer_device_id, remote_root) DO NOTHING",
+        params![local_root, peer_device_id, remote_root, created_at],
+    )?;
+    let pair = conn.query_row(
+        &format!(
+            "SELECT {} FROM sync_pairs WHERE local_root = ?1 AND peer_device_id = ?2 AND remote_root = ?3",
+            SELECT_PAIR_COLUMNS
+        ),
+        params![local_root, peer_device_id, remote_root],
+        pair_from_row,
+    )?;
+
+    tracing::info!("Sync pair {}: {} <-> {}:{}", pair.pair_id, pair.local_root, pair.peer_device_id, pair.remote_root);
+
+    Ok(pair)
+}
+
+/// 동기화 쌍을 삭제합니다.
+///
+/// # Returns
+/// * `Result<bool>` - 쌍이 있었으면 true
+pub fn remove_pair(pair_id: i64) -> Result<bool> {
+    let conn = db::open_connection()?;
+    let removed = conn.execute("DELETE FROM sync_pairs WHERE pair_id = ?1", params![pair_id])?;
+    Ok(removed > 0)
+}
+
+/// 동기화 쌍을 가져옵니다.
+///
+/// # Returns
+/// * 없으면 `PebbleError::NotFound`
+pub fn get_pair(pair_id: i64) -> Result<SyncPair> {
+    let conn = db::open_connection()?;
+    conn.query_row(
+        &format!("SELECT {} FROM sync_pairs WHERE pair_id = ?1", SELECT_PAIR_COLUMNS),
+        params![pair_id],
+        pair_from_row,
+    )
+    .optional()?
+    .ok_or_else(|| PebbleError::not_found(format!("Sync pair {}", pair_id)).into())
+}
+
+/// 동기화를 마친 기록 위치를 저장합니다. 다음 `estimate_sync`부터는 변경분만 비교합니다.
+///
+/// # Arguments
+/// * `cursor` - 동기화 전에 받은 예상(`SyncEstimate::cursor`)의 기록 위치
+///
+/// # Returns
+/// * 쌍이 없으면 `PebbleError::NotFound`
+pub fn mark_synced(pair_id: i64, cursor: &SyncCursor) -> Result<()> {
+    let conn = db::open_connection()?;
+    let updated = conn.execute(
+        "UPDATE sync_pairs SET local_seq = ?1, remote_journal_id = ?2, remote_seq = ?3 WHERE pair_id = ?4",
+        params![cursor.local_seq, cursor.remote.journal_id, cursor.remote.seq, pair_id],
+    )?;
+    if updated == 0 {
+        return Err(PebbleError::not_found(format!("Sync pair {}", pair_id)).into());
+    }
+    Ok(())
+}
+
+/// 모든 동기화 쌍을 등록 순서로 조회합니다.
+pub fn list_pairs() -> Result<Vec<SyncPair>> {
+    let conn = db::open_connection()?;
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM sync_pairs ORDER BY pair_id", SELECT_PAIR_COLUMNS))?;
+    let rows = stmt.query_map([], pair_from_row)?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// 이 기기에 같은 해시와 크기의 파일이 기록되어 있는지 확인합니다 (루트와 무관).
+fn present_locally(entry: &ManifestEntry) -> Result<bool> {
+    if integrity::is_metadata_hash(&entry.file_hash) {
+        return Ok(false);
+    }
+    Ok(db::find_files_by_hash(&entry.file_hash, entry.file_size as i64)?
+        .iter()
+        .any(|file| file.sync_status != SyncStatus::Deleted))
+}
+
+/// 내용(해시와 크기)의 64비트 요약 (큰 매니페스트에서도 메모리를 적게 쓰도록 해시 문자열 대신 보관)
+fn content_key(entry: &ManifestEntry) -> (u64, u64) {
+    let mut hasher = DefaultHasher::new();
+    entry.file_hash.hash(&mut hasher);
+    (hasher.finish(), entry.file_size)
+}
+
+/// 매니페스트 비교 결과를 받는 대로 전송량을 집계합니다.
+///
+/// 송신측은 보내기 전에 수신측에 같은 내용이 있는지 묻고(`HashQuery`) 있으면 전송을
+/// 건너뛰므로, 상대 매니페스트나 이 기기의 DB에 같은 내용이 있는 파일은 0바이트로 셉니다.
+/// 올릴 파일은 상대 매니페스트를 끝까지 받아야 판단할 수 있어 `finish`에서 셉니다.
+struct EstimateTally {
+    estimate: SyncEstimate,
+    remote_contents: HashSet<(u64, u64)>,
+    uploads: Vec<(u64, u64)>,
+    /// 대소문자 충돌을 확인할 이 기기의 폴더 (메모리 매니페스트만 비교하면 None)
+    local_root: Option<PathBuf>,
+    case_insensitive: bool,
+    policy: CaseCollisionPolicy,
+    /// 받을 파일의 대소문자 무시 경로 -> 실제 경로
+    folded_downloads: HashMap<String, String>,
+}
+
+impl EstimateTally {
+    fn new(pair_id: i64, local_root: Option<&str>) -> Self {
+        Self {
+            estimate: SyncEstimate { pair_id, ..Default::default() },
+            remote_contents: HashSet::new(),
+            uploads: Vec::new(),
+            local_root: local_root.map(PathBuf::from),
+            case_insensitive: filename::CASE_INSENSITIVE_FS,
+            policy: config::current().case_collision_policy,
+            folded_downloads: HashMap::new(),
+        }
+    }
+
+    /// 받을 파일이 대소문자만 다른 다른 파일과 겹치는지 확인합니다.
+    fn collides_by_case(&mut self, path: &str) -> bool {
+        if !self.case_insensitive {
+            return false;
+        }
+        let other_download = self.folded_downloads
+            .insert(filename::case_fold(path), path.to_string())
+            .is_some_and(|previous| previous != path);
+        other_download || self.local_root.as_ref()
+            .is_some_and(|root| filename::find_case_variant(&root.join(path)).is_some())
+    }
+
+    fn note_remote(&mut self, page: &[ManifestEntry]) {
+        // 크기와 수정 시각만 기록한 항목은 내용이 같은지 알 수 없음
+        self.remote_contents.extend(
+            page.iter()
+                .filter(|entry| !entry.deleted && !integrity::is_metadata_hash(&entry.file_hash))
+                .map(content_key),
+        );
+    }
+
+    fn add(&mut self, change: ManifestChange) -> Result<()> {
+        match change {
+            ManifestChange::Upload(entry) => self.uploads.push(content_key(&entry)),
+            ManifestChange::Download(entry) => {
+                if self.collides_by_case(&entry.path) {
+                    match self.policy {
+                        CaseCollisionPolicy::Rename => self.estimate.case_collisions.push(entry.path.clone()),
+                        CaseCollisionPolicy::Skip => {
+                            self.estimate.case_collisions.push(entry.path);
+                            return Ok(());
+                        }
+                        CaseCollisionPolicy::Error => {
+                            return Err(PebbleError::io(format!(
+                                "{} differs only in letter case from another file", entry.path
+                            )).into());
+                        }
+                    }
+                }
+                self.estimate.download_files += 1;
+                if present_locally(&entry)? {
+                    self.estimate.deduplicated_files += 1;
+                } else {
+                    self.estimate.download_bytes += entry.file_size;
+                }
+            }
+            ManifestChange::Conflict(path) => self.estimate.conflicts.push(path),
+        }
+        Ok(())
+    }
+
+    fn add_diff(&mut self, diff: ManifestDiff) -> Result<()> {
+        let changes = diff.upload.into_iter().map(ManifestChange::Upload)
+            .chain(diff.download.into_iter().map(ManifestChange::Download))
+            .chain(diff.conflicts.into_iter().map(ManifestChange::Conflict));
+        for change in changes {
+            self.add(change)?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> SyncEstimate {
+        for key in &self.uploads {
+            self.estimate.upload_files += 1;
+            if self.remote_contents.contains(key) {
+                self.estimate.deduplicated_files += 1;
+            } else {
+                self.estimate.upload_bytes += key.1;
+            }
+        }
+        self.estimate
+    }
+}
+
+/// 메모리에 있는 양쪽 매니페스트로 전송량을 예상합니다.
+pub fn estimate(pair_id: i64, local: &[ManifestEntry], remote: &[ManifestEntry]) -> Result<SyncEstimate> {
+    let mut tally = EstimateTally::new(pair_id, None);
+    tally.note_remote(remote);
+    tally.add_diff(manifest::diff(local, remote))?;
+    Ok(tally.finish())
+}
+
+/// 동기화 쌍을 지금 동기화하면 오갈 파일 수와 바이트를 예상합니다.
+///
+/// 상대 기기에 연결해 상대 루트의 매니페스트를 받아오므로 상대가 온라인이어야 합니다.
+/// 이전에 `mark_synced`로 기록 위치를 저장했으면 양쪽의 변경분만 비교합니다.
+/// 비교하는 동안 작업(`operations`)으로 등록되어 받은 항목 수를 조회하고 취소할 수 있습니다.
+pub async fn estimate_sync(pair_id: i64) -> Result<SyncEstimate> {
+    let pair = get_pair(pair_id)?;
+    let operation = operations::begin(OperationKind::Sync, &pair.local_root);
+    operation.run(estimate_pair(pair, &operation)).await
+}
+
+/// 상대 루트의 매니페스트를 받아 동기화 쌍을 비교합니다 (`estimate_sync`).
+async fn estimate_pair(pair: SyncPair, operation: &OperationHandle) -> Result<SyncEstimate> {
+    let pair_id = pair.pair_id;
+    let peer = peers::resolve(&pair.peer_device_id)?;
+    let client = TransferClient::new(peer.fingerprint.clone());
+    let local_head = manifest::journal_head()?;
+
+    let mut merger = ManifestMerger::new(&pair.local_root);
+    let mut tally = EstimateTally::new(pair_id, Some(&pair.local_root));
+    let mut remote_changes = Vec::new();
+    let since = pair.cursor.as_ref().map(|cursor| &cursor.remote);
+    let mut received = 0;
+    let head = client
+        .fetch_manifest_any(&peer.addrs, &pair.remote_root, since, |incremental, page| {
+            received += page.len() as u64;
+            operation.set_progress(received, None);
+            tally.note_remote(&page);
+            if incremental {
+                remote_changes.extend(page);
+                Ok(())
+            } else {
+                merger.push_remote(page, &mut |change| tally.add(change))
+            }
+        })
+        .await?;
+
+    match pair.cursor.as_ref().filter(|_| head.incremental) {
+        Some(cursor) => {
+            let mut local_pages = LocalPages::changed_since(&pair.local_root, Some(cursor.local_seq));
+            let mut local_changes = Vec::new();
+            while let Some(page) = local_pages.next_page()? {
+                local_changes.extend(page);
+            }
+            tally.add_diff(manifest::diff_changes(&pair.local_root, &local_changes, &remote_changes)?)?;
+        }
+        None => merger.finish(&mut |change| tally.add(change))?,
+    }
+
+    let mut estimate = tally.finish();
+    estimate.incremental = head.incremental;
+    estimate.remote_change_detection = head.change_detection;
+    estimate.cursor = head.cursor.map(|remote| SyncCursor { local_seq: local_head.seq, remote });
+    tracing::info!(
+        "Sync pair {} estimate: upload {} file(s) / {} bytes, download {} file(s) / {} bytes, {} conflict(s)",
+        pair_id, estimate.upload_files, estimate.upload_bytes,
+        estimate.download_files, estimate.download_bytes, estimate.conflicts.len()
+    );
+
+    Ok(estimate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, hash: &str, file_size: u64, last_modified: i64) -> ManifestEntry {
+        ManifestEntry {
+            path: path.to_string(),
+            file_size,
+            file_hash: hash.to_string(),
+            last_modified,
+            deleted: false,
+        }
+    }
+
+    #[test]
+    fn test_pair_lifecycle() {
+        crate::loopback::use_temp_environment();
+        let root = tempfile::TempDir::new().unwrap();
+        let root_path = root.path().to_string_lossy().to_string();
+
+        let pair = add_pair(&root_path, "pair-peer", "/remote/photos").unwrap();
+        assert_eq!(add_pair(&root_path, "pair-peer", "/remote/photos").unwrap(), pair);
+        assert_eq!(get_pair(pair.pair_id).unwrap(), pair);
+        assert!(list_pairs().unwrap().contains(&pair));
+
+        assert!(remove_pair(pair.pair_id).unwrap());
+        let err = PebbleError::from(get_pair(pair.pair_id).unwrap_err());
+        assert!(matches!(err, PebbleError::NotFound { .. }));
+        assert!(add_pair(&root_path, "", "/remote").is_err());
+    }
+
+    #[test]
+    fn test_estimate_skips_content_already_present() {
+        crate::loopback::use_temp_environment();
+        let local = vec![
+            entry("new.bin", "estimate-new", 1000, 5),
+            // 상대에 다른 이름으로 이미 있는 내용
+            entry("renamed.bin", "estimate-shared", 300, 5),
+        ];
+        let remote = vec![
+            entry("old_name.bin", "estimate-shared", 300, 1),
+            entry("remote.bin", "estimate-remote", 2000, 1),
+        ];
+
+        let estimate = estimate(7, &local, &remote).unwrap();
+        assert_eq!(estimate.upload_files, 2);
+        assert_eq!(estimate.upload_bytes, 1000);
+        // old_name.bin의 내용은 이 기기 DB에 기록되지 않았으므로 받아야 함
+        assert_eq!(estimate.download_files, 2);
+        assert_eq!(estimate.download_bytes, 2300);
+        assert_eq!(estimate.deduplicated_files, 1);
+    }
+
+    #[test]
+    fn test_estimate_reports_case_collisions() {
+        crate::loopback::use_temp_environment();
+        let root = tempfile::TempDir::new().unwrap();
+        std::fs::write(root.path().join("readme.md"), b"local").unwrap();
+
+        let mut tally = EstimateTally::new(3, Some(&root.path().to_string_lossy()));
+        tally.case_insensitive = true;
+        for path in ["README.md", "Photo.JPG", "photo.jpg", "notes.txt"] {
+            tally.add(ManifestChange::Download(entry(path, path, 10, 1))).unwrap();
+        }
+        let estimate = tally.finish();
+        assert_eq!(estimate.case_collisions, vec!["README.md".to_string(), "photo.jpg".to_string()]);
+        assert_eq!(estimate.download_files, 4);
+
+        let mut tally = EstimateTally::new(3, Some(&root.path().to_string_lossy()));
+        tally.case_insensitive = true;
+        tally.policy = CaseCollisionPolicy::Skip;
+        tally.add(ManifestChange::Download(entry("README.md", "skip", 10, 1))).unwrap();
+        assert_eq!(tally.finish().download_files, 0);
+    }
+}
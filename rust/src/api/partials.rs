@@ -0,0 +1,180 @@
+//! 미완성 수신 정리 (Stale Partial Cleanup)
+//!
+//! 수신은 저장 위치와 같은 폴더의 임시 파일에 기록하고 이어받기 상태
+//! (`transfer_state`)를 남기므로, 보낸 기기가 다시 시도하지 않은 수신은 미완성 파일과 상태가
+//! 계속 남습니다. 마지막으로 청크를 받은 뒤 설정한 시간(`PebbleConfig::partial_max_age_hours`)이
+//! 지나도록 이어받지 않은 수신의 파일과 상태를 지웁니다.
+//!
+//! # Process Flow
+//! 1. 완료되지 않았고 `max_age`보다 오래 갱신되지 않은 이어받기 상태를 조회
+//! 2. 진행 중인 전송(`registry`)의 상태는 건너뜀
+//! 3. 저장 경로와 잠금 임시 파일 중 그 뒤로 수정되지 않은 파일만 삭제 (사용자가 바꾼 파일은 남김)
+//! 4. 이어받기 상태 삭제 (파일을 지우지 못하면 다음 정리 때 다시 시도하도록 남김)
+//! 5. 유지보수 태스크(`history::run_maintenance`)가 주기적으로 실행
+
+use anyhow::Result;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::db;
+use super::locked;
+use super::paths;
+use super::registry;
+use super::transfer::TransferStatus;
+
+/// 미완성 수신을 남겨 두는 기본 시간
+pub const DEFAULT_MAX_AGE_HOURS: u64 = 72;
+
+/// 정리 결과
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PartialCleanupReport {
+    /// 삭제한 이어받기 상태 수
+    pub states_removed: u64,
+
+    /// 삭제한 미완성 파일 수
+    pub files_removed: u64,
+
+    /// 삭제한 파일의 전체 크기 (bytes)
+    pub bytes_freed: u64,
+}
+
+/// 파일이 있고 `cutoff` 이후로 수정되지 않았으면 지웁니다.
+///
+/// # Returns
+/// * 지운 파일의 크기 (없거나 최근에 수정되었으면 None)
+fn remove_if_stale(path: &Path, cutoff: SystemTime) -> io::Result<Option<u64>> {
+    let path = paths::long_path(path);
+    let metadata = match fs::metadata(&path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    if !metadata.is_file() || metadata.modified()? > cutoff {
+        return Ok(None);
+    }
+
+    fs::remove_file(&path)?;
+    Ok(Some(metadata.len()))
+}
+
+/// `max_age`보다 오래 이어받지 않은 수신의 미완성 파일과 이어받기 상태를 지웁니다.
+///
+/// # Arguments
+/// * `max_age` - 마지막으로 청크를 받은 뒤 지나야 하는 시간 (0이면 진행 중이 아닌 미완성 수신 모두)
+pub fn clean(max_age: Duration) -> Result<PartialCleanupReport> {
+    let cutoff = SystemTime::now() - max_age;
+    let cutoff_secs = cutoff.duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0);
+
+    let conn = db::open_connection()?;
+    let stale = conn
+        .prepare("SELECT transfer_id, file_path FROM transfer_state WHERE transfer_status != ?1 AND updated_at <= ?2")?
+        .query_map(params![TransferStatus::Completed.to_string(), cutoff_secs], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let active: HashSet<String> = registry::global().list().into_iter().map(|transfer| transfer.transfer_id).collect();
+
+    let mut report = PartialCleanupReport::default();
+    'states: for (transfer_id, file_path) in stale {
+        if active.contains(&transfer_id) {
+            continue;
+        }
+
+        if !file_path.is_empty() {
+            let dest = PathBuf::from(&file_path);
+            for path in [locked::staging_path(&dest), dest] {
+                match remove_if_stale(&path, cutoff) {
+                    Ok(Some(size)) => {
+                        report.files_removed += 1;
+                        report.bytes_freed += size;
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        tracing::warn!("Failed to remove partial file {}: {}", path.display(), e);
+                        continue 'states;
+                    }
+                }
+            }
+        }
+
+        report.states_removed += conn.execute("DELETE FROM transfer_state WHERE transfer_id = ?1", params![transfer_id])? as u64;
+    }
+
+    if report.states_removed > 0 {
+        tracing::info!("Removed {} stale partial transfer(s) and {} file(s) ({} bytes)",
+            report.states_removed, report.files_removed, report.bytes_freed);
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::util::unix_now;
+
+    fn insert_state(transfer_id: &str, file_path: &Path, status: TransferStatus, updated_at: i64) {
+        db::open_connection()
+            .unwrap()
+            .execute(
+                "INSERT OR REPLACE INTO transfer_state
+                 (transfer_id, file_path, file_size, total_chunks, received_chunks, transfer_status, peer_device_id, created_at, updated_at)
+                 VALUES (?1, ?2, 0, 0, 1, ?3, '', ?4, ?4)",
+                params![transfer_id, file_path.to_string_lossy(), status.to_string(), updated_at],
+            )
+            .unwrap();
+    }
+
+    fn state_exists(transfer_id: &str) -> bool {
+        db::open_connection()
+            .unwrap()
+            .query_row("SELECT COUNT(*) FROM transfer_state WHERE transfer_id = ?1", params![transfer_id], |row| {
+                row.get::<_, i64>(0)
+            })
+            .unwrap()
+            > 0
+    }
+
+    fn write_old(path: &Path, len: usize) {
+        fs::write(path, vec![0u8; len]).unwrap();
+        let old = SystemTime::now() - Duration::from_secs(10 * 24 * 60 * 60);
+        fs::File::options().write(true).open(path).unwrap().set_modified(old).unwrap();
+    }
+
+    #[test]
+    fn test_clean_stale_partials() {
+        crate::loopback::use_temp_environment();
+        let dir = tempfile::TempDir::new().unwrap();
+        let old = unix_now() - 5 * 24 * 60 * 60;
+
+        // 오래된 미완성 수신: 파일과 잠금 임시 파일, 상태 모두 삭제
+        let abandoned = dir.path().join("abandoned.bin");
+        write_old(&abandoned, 100);
+        write_old(&locked::staging_path(&abandoned), 20);
+        insert_state("partial-abandoned", &abandoned, TransferStatus::InProgress, old);
+
+        // 완료된 수신, 최근 수신, 상태 이후에 사용자가 바꾼 파일은 남김
+        let completed = dir.path().join("completed.bin");
+        write_old(&completed, 10);
+        insert_state("partial-completed", &completed, TransferStatus::Completed, old);
+        let recent = dir.path().join("recent.bin");
+        fs::write(&recent, b"recent").unwrap();
+        insert_state("partial-recent", &recent, TransferStatus::InProgress, old + 5 * 24 * 60 * 60);
+        let touched = dir.path().join("touched.bin");
+        fs::write(&touched, b"edited by user").unwrap();
+        insert_state("partial-touched", &touched, TransferStatus::InProgress, old);
+
+        let report = clean(Duration::from_secs(24 * 60 * 60)).unwrap();
+        assert!(report.states_removed >= 2 && report.files_removed >= 2 && report.bytes_freed >= 120);
+        assert!(!abandoned.exists() && !locked::staging_path(&abandoned).exists());
+        assert!(!state_exists("partial-abandoned"));
+        assert!(completed.exists() && state_exists("partial-completed"));
+        assert!(recent.exists() && state_exists("partial-recent"));
+        assert!(touched.exists() && !state_exists("partial-touched"));
+    }
+}
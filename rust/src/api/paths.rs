@@ -0,0 +1,262 @@
+use std::path::{Path, PathBuf};
+use unicode_normalization::UnicodeNormalization;
+
+/// Win32 API의 기본 경로 길이 제한 (MAX_PATH)
+pub const WINDOWS_MAX_PATH: usize = 260;
+
+/// 경로 표기 방식
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PathStyle {
+    /// `/` 구분자, 대소문자 구분
+    Posix,
+    /// `\` 구분자(`/`도 허용), 드라이브/UNC 접두사, 대소문자 구분 없음
+    Windows,
+}
+
+const NATIVE_STYLE: PathStyle = if cfg!(windows) { PathStyle::Windows } else { PathStyle::Posix };
+
+/// 경로를 DB 키로 쓰는 정규 형식으로 변환합니다.
+///
+/// 같은 파일이 다른 문자열로 기록되어 중복 행이 생기지 않도록 db, watcher, transfer가
+/// 공통으로 사용합니다. 파일 시스템에 접근하지 않는 순수한 문자열 변환입니다.
+///
+/// - 중복 구분자, `.` 제거 및 `..` 해석, 끝의 구분자 제거
+/// - Windows: `/`를 `\`로 통일, `\\?\` / `\\?\UNC\` 접두사 제거 (대소문자는 사용자가 쓴 그대로 유지)
+/// - `content://` 같은 URI(호스트 저장소 문서)는 그대로 유지
+pub fn normalize<P: AsRef<Path>>(path: P) -> String {
+    let path = path.as_ref().to_string_lossy();
+    if is_uri(&path) {
+        return path.into_owned();
+    }
+    canonical_form(&path, NATIVE_STYLE)
+}
+
+/// 다른 기기의 경로와 비교하고 정렬하는 키를 만듭니다.
+///
+/// macOS는 파일 이름을 NFD로, Linux와 Windows는 보통 NFC로 저장하므로 같은 이름이 다른 바이트가
+/// 됩니다. 키는 비교와 정렬에만 쓰고, 파일 시스템 작업에는 디스크에 있는 원래 경로를 그대로 씁니다.
+///
+/// Windows는 대소문자를 구분하지 않으므로 키만 소문자로 통일하고, 저장하는 경로는 원래 표기를 유지합니다.
+///
+/// - `normalize` 후 유니코드 NFC로 정규화
+/// - Windows: 구분자를 `/`로 통일하고 소문자로 통일
+pub fn comparison_key<P: AsRef<Path>>(path: P) -> String {
+    key_form(&normalize(path), NATIVE_STYLE)
+}
+
+fn key_form(normalized: &str, style: PathStyle) -> String {
+    let nfc: String = normalized.nfc().collect();
+    match style {
+        PathStyle::Posix => nfc,
+        PathStyle::Windows => nfc.replace('\\', "/").to_lowercase(),
+    }
+}
+
+/// `scheme://` 형태의 URI인지 확인합니다. (드라이브 문자 `C:`와 구분하기 위해 두 글자 이상의 scheme만 인정)
+pub fn is_uri(path: &str) -> bool {
+    match path.split_once("://") {
+        Some((scheme, _)) => {
+            scheme.len() > 1
+                && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        }
+        None => false,
+    }
+}
+
+/// 파일 시스템 API에 넘길 경로를 만듭니다.
+///
+/// Windows에서 절대 경로가 MAX_PATH(260자) 이상이면 `\\?\`(UNC는 `\\?\UNC\`) 접두사를
+/// 붙여 긴 경로 제한을 우회합니다. 다른 플랫폼에서는 경로를 그대로 반환합니다.
+pub fn long_path<P: AsRef<Path>>(path: P) -> PathBuf {
+    match NATIVE_STYLE {
+        PathStyle::Posix => path.as_ref().to_path_buf(),
+        PathStyle::Windows => PathBuf::from(windows_long_path(&path.as_ref().to_string_lossy())),
+    }
+}
+
+/// 다른 기기에서 받은 경로의 마지막 구성 요소(파일 이름)를 가져옵니다.
+///
+/// 상대 기기의 OS를 알 수 없으므로 `/`와 `\`를 모두 구분자로 취급합니다.
+pub fn remote_file_name(path: &str) -> Option<&str> {
+    path.rsplit(['/', '\\'])
+        .find(|component| !component.is_empty())
+        .filter(|name| *name != "." && *name != "..")
+}
+
+fn windows_long_path(path: &str) -> String {
+    let canonical = canonical_form(path, PathStyle::Windows);
+
+    let is_verbatim = canonical.starts_with(r"\\?\");
+    let is_absolute = canonical.starts_with(r"\\") || has_drive_prefix(&canonical);
+    if is_verbatim || !is_absolute || canonical.len() < WINDOWS_MAX_PATH {
+        return canonical;
+    }
+
+    match canonical.strip_prefix(r"\\") {
+        Some(unc) => format!(r"\\?\UNC\{}", unc),
+        None => format!(r"\\?\{}", canonical),
+    }
+}
+
+fn has_drive_prefix(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+}
+
+/// Windows 경로를 (접두사, 나머지)로 나눕니다. 접두사는 `c:` 또는 `\\server\share` 형태입니다.
+fn split_windows_prefix(path: &str) -> (String, &str) {
+    // 구분자는 이미 `\`로 통일되어 있어야 함
+    let path = match path.strip_prefix(r"\\?\UNC\") {
+        Some(unc) => return split_unc(unc),
+        None => path.strip_prefix(r"\\?\").unwrap_or(path),
+    };
+
+    if let Some(unc) = path.strip_prefix(r"\\") {
+        return split_unc(unc);
+    }
+
+    if has_drive_prefix(path) {
+        return (path[..2].to_string(), &path[2..]);
+    }
+
+    (String::new(), path)
+}
+
+fn split_unc(unc: &str) -> (String, &str) {
+    let mut parts = unc.splitn(3, '\\');
+    let server = parts.next().unwrap_or_default();
+    let share = parts.next().unwrap_or_default();
+    let rest = parts.next().unwrap_or_default();
+    (format!(r"\\{}\{}", server, share), rest)
+}
+
+/// 정규 형식을 만듭니다. 대소문자는 바꾸지 않습니다.
+fn canonical_form(path: &str, style: PathStyle) -> String {
+    match style {
+        PathStyle::Posix => canonical_components("", path, '/'),
+        PathStyle::Windows => {
+            let unified = path.replace('/', "\\");
+            let (prefix, rest) = split_windows_prefix(&unified);
+            canonical_components(&prefix, rest, '\\')
+        }
+    }
+}
+
+fn canonical_components(prefix: &str, rest: &str, separator: char) -> String {
+    let is_unc = prefix.starts_with(r"\\");
+    let rooted = rest.starts_with(separator) || is_unc;
+
+    let mut components: Vec<&str> = Vec::new();
+    for component in rest.split(separator) {
+        match component {
+            "" | "." => {}
+            ".." => match components.last() {
+                Some(&last) if last != ".." => {
+                    components.pop();
+                }
+                // 루트 위로는 올라갈 수 없음
+                _ if rooted => {}
+                _ => components.push(".."),
+            },
+            other => components.push(other),
+        }
+    }
+
+    let sep = separator.to_string();
+    let joined = components.join(&sep);
+
+    match (rooted, joined.is_empty()) {
+        (true, _) if is_unc && joined.is_empty() => prefix.to_string(),
+        (true, _) => format!("{}{}{}", prefix, separator, joined),
+        (false, true) if prefix.is_empty() => ".".to_string(),
+        (false, _) => format!("{}{}", prefix, joined),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn windows(path: &str) -> String {
+        canonical_form(path, PathStyle::Windows)
+    }
+
+    fn windows_key(path: &str) -> String {
+        key_form(&windows(path), PathStyle::Windows)
+    }
+
+    #[test]
+    fn test_posix_normalization() {
+        let posix = |path: &str| canonical_form(path, PathStyle::Posix);
+        assert_eq!(posix("/home//user/./docs/"), "/home/user/docs");
+        assert_eq!(posix("/home/user/../other/a.txt"), "/home/other/a.txt");
+        assert_eq!(posix("/../a"), "/a");
+        assert_eq!(posix("a/../../b"), "../b");
+        assert_eq!(posix("/"), "/");
+        assert_eq!(posix("./"), ".");
+        assert_eq!(posix("/Docs/A.txt"), "/Docs/A.txt");
+    }
+
+    #[test]
+    fn test_windows_keeps_casing() {
+        let expected = r"C:\Users\Me\Docs\Report.pdf";
+        assert_eq!(windows(r"C:\Users\Me\Docs\Report.pdf"), expected);
+        assert_eq!(windows("C:/Users/Me/Docs/Report.pdf"), expected);
+        assert_eq!(windows(r"C:\Users\Me\\Docs\.\x\..\Report.pdf"), expected);
+        assert_eq!(windows(r"\\?\C:\Users\Me\Docs\Report.pdf"), expected);
+        assert_eq!(windows(r"C:\"), r"C:\");
+    }
+
+    #[test]
+    fn test_windows_variants_share_one_key() {
+        let expected = "c:/users/me/docs/report.pdf";
+        assert_eq!(windows_key(r"C:\Users\Me\Docs\report.pdf"), expected);
+        assert_eq!(windows_key("C:/Users/me/Docs/Report.PDF"), expected);
+        assert_eq!(windows_key(r"c:\users\me\\docs\.\x\..\report.pdf"), expected);
+        assert_eq!(windows_key(r"\\?\C:\Users\Me\Docs\report.pdf"), expected);
+        assert_eq!(key_form("/Docs/A.txt", PathStyle::Posix), "/Docs/A.txt");
+    }
+
+    #[test]
+    fn test_windows_unc_paths() {
+        assert_eq!(windows(r"\\NAS\Share\Photos\a.jpg"), r"\\NAS\Share\Photos\a.jpg");
+        assert_eq!(windows(r"\\?\UNC\NAS\Share\Photos\a.jpg"), r"\\NAS\Share\Photos\a.jpg");
+        assert_eq!(windows_key(r"\\NAS\Share\Photos\a.jpg"), "//nas/share/photos/a.jpg");
+        assert_eq!(windows("//nas/share/"), r"\\nas\share");
+    }
+
+    #[test]
+    fn test_windows_long_path_prefix() {
+        let short = r"C:\Users\me\a.txt";
+        assert_eq!(windows_long_path(short), short);
+
+        let long_dir = "d".repeat(WINDOWS_MAX_PATH);
+        let long = format!(r"C:\{}\a.txt", long_dir);
+        assert_eq!(windows_long_path(&long), format!(r"\\?\C:\{}\a.txt", long_dir));
+
+        let long_unc = format!(r"\\nas\share\{}", long_dir);
+        assert_eq!(windows_long_path(&long_unc), format!(r"\\?\UNC\nas\share\{}", long_dir));
+
+        // 상대 경로는 접두사를 붙일 수 없음
+        assert_eq!(windows_long_path(&long_dir), long_dir);
+    }
+
+    #[test]
+    fn test_uris_are_kept() {
+        let uri = "content://com.android.externalstorage.documents/document/primary%3ADownload%2Fa.jpg";
+        assert!(is_uri(uri));
+        assert_eq!(normalize(uri), uri);
+        assert!(!is_uri("C://Users"));
+        assert!(!is_uri("/home/user/a://b"));
+    }
+
+    #[test]
+    fn test_remote_file_name() {
+        assert_eq!(remote_file_name(r"C:\Users\me\report.pdf"), Some("report.pdf"));
+        assert_eq!(remote_file_name("/home/me/photo.jpg"), Some("photo.jpg"));
+        assert_eq!(remote_file_name("dir/"), Some("dir"));
+        assert_eq!(remote_file_name(".."), None);
+        assert_eq!(remote_file_name(""), None);
+    }
+}
@@ -0,0 +1,776 @@
+//! 기기 ID로 전송하기 위한 알려진 기기(peer) 기록
+//!
+//! DHCP로 IP 주소가 바뀌어도 UI가 주소를 추적하지 않도록, 비콘으로 확인한 기기의
+//! 주소들을 DB에 남기고 기기 ID로 현재 주소와 인증서 핑거프린트를 찾습니다.
+//! Wi-Fi와 유선처럼 여러 주소로 동시에 보이는 기기는 모든 주소를 기록하고,
+//! 전송할 때 최근 순(측정 결과가 있으면 측정 순)으로 동시에 연결을 시도합니다.
+//!
+//! 사용자가 붙인 별명, 색상, 태그(`PeerLabel`)도 같은 테이블에 남아, 상대 기기가 비콘으로
+//! 알리는 이름이 바뀌어도 UI는 발견 목록과 기기 목록에서 같은 별명을 보여 줄 수 있습니다.
+//!
+//! 서명이 검증된 비콘에 실린 인증서 핑거프린트는 기기별로 고정(pin)되어,
+//! 기기 ID로 보내는 전송은 기본적으로 인증서 고정을 사용합니다.
+//!
+//! # Process Flow
+//! 1. 발견 서비스가 새 기기, 주소 변경, 핑거프린트 변경을 `peers` 테이블에 기록
+//! 2. 전송 시 실시간 발견 목록 → `peer_addresses` 테이블 순으로 주소들을 찾음
+//! 3. 주소들에 Happy Eyeballs 방식으로 연결해 먼저 연결된 주소로 전송
+//! 4. 모두 연결하지 못하면 비콘으로 새 주소가 알려질 때까지 기다렸다가 다시 시도
+
+use anyhow::{Context, Result};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use super::config;
+use super::db;
+use super::discovery::{self, DiscoveredDevice};
+use super::error::PebbleError;
+use super::priority::TransferPriority;
+use super::probe;
+use super::quota;
+use super::registry::{self, ActiveTransfer};
+use super::shares::{self, Share};
+use super::transfer::TransferClient;
+use super::util::unix_now;
+
+/// 주소를 바꿔 가며 전송을 시도하는 최대 횟수
+pub const MAX_SEND_ATTEMPTS: usize = 3;
+
+/// 연결 실패 후 새 주소를 기다리는 시간 (비콘 두 주기)
+const ADDRESS_CHANGE_WAIT: Duration = Duration::from_secs(discovery::BEACON_INTERVAL_SECS * 2);
+
+/// 새 주소 확인 간격
+const ADDRESS_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 기기별로 DB에 남기는 최대 주소 수
+pub const MAX_PEER_ADDRESSES: usize = 8;
+
+/// 별명 최대 길이 (문자 수)
+pub const MAX_NICKNAME_CHARS: usize = 64;
+
+/// 기기 하나에 붙일 수 있는 최대 태그 수
+pub const MAX_PEER_TAGS: usize = 16;
+
+/// 태그 최대 길이 (문자 수)
+pub const MAX_TAG_CHARS: usize = 32;
+
+/// 사용자가 기기에 붙인 로컬 표시 정보 (상대 기기에는 알리지 않음)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PeerLabel {
+    /// 별명 (예: "엄마 노트북", None이면 비콘의 기기 이름 사용)
+    pub nickname: Option<String>,
+
+    /// 표시 색상 (`#RRGGBB`)
+    pub color: Option<String>,
+
+    /// 태그 (예: ["가족", "백업"])
+    pub tags: Vec<String>,
+}
+
+impl PeerLabel {
+    /// 아무것도 지정하지 않았는지 확인합니다.
+    pub fn is_empty(&self) -> bool {
+        self.nickname.is_none() && self.color.is_none() && self.tags.is_empty()
+    }
+
+    /// 앞뒤 공백을 지우고, 빈 값은 None으로, 중복 태그는 하나로 정리한 뒤 검증합니다.
+    ///
+    /// # Returns
+    /// * 길이나 개수 제한을 넘거나 색상 형식이 틀리면 `PebbleError::InvalidArgument`
+    pub fn normalized(&self) -> Result<Self> {
+        let trimmed = |value: &Option<String>| value.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(str::to_string);
+        let nickname = trimmed(&self.nickname);
+        let color = trimmed(&self.color).map(|color| color.to_ascii_lowercase());
+        let mut tags: Vec<String> = Vec::new();
+        for tag in self.tags.iter().map(|tag| tag.trim()).filter(|tag| !tag.is_empty()) {
+            if !tags.iter().any(|existing| existing == tag) {
+                tags.push(tag.to_string());
+            }
+        }
+
+        if nickname.as_ref().is_some_and(|name| name.chars().count() > MAX_NICKNAME_CHARS) {
+            return Err(PebbleError::invalid_argument(format!("Nickname must be at most {} characters", MAX_NICKNAME_CHARS)).into());
+        }
+        if color.as_ref().is_some_and(|color| {
+            !(color.len() == 7 && color.starts_with('#') && color[1..].chars().all(|c| c.is_ascii_hexdigit()))
+        }) {
+            return Err(PebbleError::invalid_argument("Color must be in #RRGGBB form").into());
+        }
+        if tags.len() > MAX_PEER_TAGS {
+            return Err(PebbleError::invalid_argument(format!("At most {} tags per device", MAX_PEER_TAGS)).into());
+        }
+        if let Some(tag) = tags.iter().find(|tag| tag.chars().count() > MAX_TAG_CHARS) {
+            return Err(PebbleError::invalid_argument(format!("Tag '{}' is longer than {} characters", tag, MAX_TAG_CHARS)).into());
+        }
+
+        Ok(Self { nickname, color, tags })
+    }
+}
+
+/// 비콘으로 확인한 적 있는 기기
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KnownPeer {
+    /// 기기 고유 ID
+    pub device_id: String,
+
+    /// 기기 이름
+    pub device_name: String,
+
+    /// 마지막으로 확인한 IP 주소
+    pub last_address: String,
+
+    /// 마지막으로 주소를 확인한 시각 (Unix timestamp)
+    pub last_seen: i64,
+
+    /// 검증된 비콘으로 받은 인증서 핑거프린트 (구버전 기기는 None)
+    pub pinned_fingerprint: Option<String>,
+
+    /// 비콘으로 마지막으로 알린 전송 서버 포트 (구버전 기기는 None)
+    pub last_port: Option<u16>,
+
+    /// 사용자가 붙인 별명, 색상, 태그 (없으면 None)
+    #[serde(default)]
+    pub label: Option<PeerLabel>,
+}
+
+/// 전송에 사용할 기기 주소와 인증서 핑거프린트
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedPeer {
+    /// 가장 먼저 시도할 전송 서버 주소 (`addrs`의 첫 주소)
+    pub addr: SocketAddr,
+
+    /// 시도할 전송 서버 주소 (우선순위 순)
+    pub addrs: Vec<SocketAddr>,
+
+    /// 고정할 인증서 핑거프린트 (알 수 없으면 None)
+    pub fingerprint: Option<String>,
+
+    /// 실시간 발견 목록에서 찾았는지 여부 (false면 DB에 남은 마지막 주소)
+    pub online: bool,
+}
+
+/// 발견된 기기의 주소와 인증서 핑거프린트를 기록합니다.
+///
+/// # Security
+/// - 비밀 키로 서명이 검증된 비콘에서만 호출해야 합니다. 핑거프린트가 바뀌면
+///   (인증서 재생성) 새 값으로 고정하고 경고를 남깁니다.
+/// - 핑거프린트를 알리지 않는 비콘은 기존에 고정된 값을 지우지 않습니다.
+pub fn record_seen(device: &DiscoveredDevice) -> Result<()> {
+    let conn = db::open_connection()?;
+
+    if let Some(fingerprint) = &device.cert_fingerprint {
+        let previous = pinned_fingerprint_with(&conn, &device.device_id)?;
+        if previous.as_ref().is_some_and(|pinned| pinned != fingerprint) {
+            tracing::warn!("Certificate of {} changed, re-pinning to {}", device.device_id, fingerprint);
+        }
+    }
+
+    conn.execute(
+        "INSERT INTO peers (device_id, device_name, last_address, last_seen, pinned_fingerprint, last_port)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT(device_id) DO UPDATE SET
+            device_name = excluded.device_name,
+            last_address = excluded.last_address,
+            last_seen = excluded.last_seen,
+            pinned_fingerprint = COALESCE(excluded.pinned_fingerprint, pinned_fingerprint),
+            last_port = COALESCE(excluded.last_port, last_port)",
+        params![
+            device.device_id,
+            device.device_name,
+            device.ip_address,
+            unix_now(),
+            device.cert_fingerprint,
+            device.transfer_port
+        ],
+    )?;
+
+    conn.execute(
+        "INSERT INTO peer_addresses (device_id, address, last_seen) VALUES (?1, ?2, ?3)
+         ON CONFLICT(device_id, address) DO UPDATE SET last_seen = excluded.last_seen",
+        params![device.device_id, device.ip_address, unix_now()],
+    )?;
+    conn.execute(
+        "DELETE FROM peer_addresses WHERE device_id = ?1 AND address NOT IN
+         (SELECT address FROM peer_addresses WHERE device_id = ?1 ORDER BY last_seen DESC, rowid DESC LIMIT ?2)",
+        params![device.device_id, MAX_PEER_ADDRESSES as i64],
+    )?;
+    Ok(())
+}
+
+/// 기기에서 비콘을 받은 적 있는 IP 주소를 최근 순으로 조회합니다.
+pub fn known_addresses(device_id: &str) -> Result<Vec<String>> {
+    let conn = db::open_connection()?;
+    let mut stmt = conn.prepare(
+        "SELECT address FROM peer_addresses WHERE device_id = ?1 ORDER BY last_seen DESC, rowid DESC",
+    )?;
+    let rows = stmt.query_map(params![device_id], |row| row.get(0))?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// 기기에 고정된 인증서 핑거프린트를 조회합니다.
+pub fn pinned_fingerprint(device_id: &str) -> Result<Option<String>> {
+    pinned_fingerprint_with(&db::open_connection()?, device_id)
+}
+
+fn pinned_fingerprint_with(conn: &rusqlite::Connection, device_id: &str) -> Result<Option<String>> {
+    let pinned = conn
+        .query_row(
+            "SELECT pinned_fingerprint FROM peers WHERE device_id = ?1",
+            params![device_id],
+            |row| row.get::<_, Option<String>>(0),
+        )
+        .optional()?;
+    Ok(pinned.flatten())
+}
+
+/// 명시한 핑거프린트가 없으면 기기에 고정된 핑거프린트를 사용합니다.
+///
+/// # Arguments
+/// * `device_id` - 상대 기기 ID
+/// * `explicit` - 호출자가 지정한 핑거프린트 (우선 사용)
+pub fn fingerprint_for(device_id: &str, explicit: Option<String>) -> Option<String> {
+    explicit.or_else(|| {
+        pinned_fingerprint(device_id).unwrap_or_else(|e| {
+            tracing::warn!("Failed to read pinned fingerprint of {}: {:#}", device_id, e);
+            None
+        })
+    })
+}
+
+/// 고정된 핑거프린트를 지웁니다. 다음 비콘에서 다시 고정됩니다.
+pub fn clear_pin(device_id: &str) -> Result<()> {
+    let conn = db::open_connection()?;
+    conn.execute("UPDATE peers SET pinned_fingerprint = NULL WHERE device_id = ?1", params![device_id])?;
+    tracing::info!("Cleared pinned certificate of {}", device_id);
+    Ok(())
+}
+
+/// 기기에 별명, 색상, 태그를 붙입니다. 빈 값을 넘기면 지웁니다.
+///
+/// # Returns
+/// * 비콘으로 확인한 적 없는 기기면 `PebbleError::NotFound`
+pub fn set_label(device_id: &str, label: &PeerLabel) -> Result<()> {
+    let label = label.normalized()?;
+    let tags = if label.tags.is_empty() { None } else { Some(serde_json::to_string(&label.tags)?) };
+
+    let conn = db::open_connection()?;
+    let updated = conn.execute(
+        "UPDATE peers SET nickname = ?2, color = ?3, tags = ?4 WHERE device_id = ?1",
+        params![device_id, label.nickname, label.color, tags],
+    )?;
+    if updated == 0 {
+        return Err(PebbleError::not_found(format!("Device {}", device_id)).into());
+    }
+
+    tracing::info!("Updated label of {}", device_id);
+    Ok(())
+}
+
+/// 별명, 색상, 태그가 붙은 기기들의 표시 정보를 조회합니다.
+pub fn labels() -> Result<HashMap<String, PeerLabel>> {
+    let conn = db::open_connection()?;
+    let mut stmt = conn.prepare(
+        "SELECT device_id, nickname, color, tags FROM peers
+         WHERE nickname IS NOT NULL OR color IS NOT NULL OR tags IS NOT NULL",
+    )?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, label_from_row(row, 1)?)))?;
+
+    let mut labels = HashMap::new();
+    for row in rows {
+        if let (device_id, Some(label)) = row? {
+            labels.insert(device_id, label);
+        }
+    }
+    Ok(labels)
+}
+
+/// 발견된 기기에 사용자가 붙인 표시 정보를 채웁니다.
+pub fn with_labels(mut devices: Vec<DiscoveredDevice>) -> Result<Vec<DiscoveredDevice>> {
+    if devices.is_empty() {
+        return Ok(devices);
+    }
+    let mut labels = labels()?;
+    for device in &mut devices {
+        device.label = labels.remove(&device.device_id);
+    }
+    Ok(devices)
+}
+
+/// 알려진 기기를 조회합니다.
+pub fn get(device_id: &str) -> Result<Option<KnownPeer>> {
+    let conn = db::open_connection()?;
+    let peer = conn
+        .query_row(
+            "SELECT device_id, device_name, last_address, last_seen, pinned_fingerprint, last_port, nickname, color, tags
+             FROM peers WHERE device_id = ?1",
+            params![device_id],
+            from_row,
+        )
+        .optional()?;
+    Ok(peer)
+}
+
+/// 주소에서 가장 최근에 본 기기의 ID를 조회합니다.
+pub fn device_id_at(ip_address: &str) -> Result<Option<String>> {
+    let conn = db::open_connection()?;
+    let device_id = conn
+        .query_row(
+            "SELECT device_id FROM peer_addresses WHERE address = ?1 ORDER BY last_seen DESC LIMIT 1",
+            params![ip_address],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(device_id)
+}
+
+/// 비공개 비콘의 불투명 ID에 해당하는 알려진 기기를 찾습니다.
+///
+/// # Arguments
+/// * `private_id` - 비콘에 실린 `discovery::private_beacon_id` 값
+/// * `secret_key` - 비콘 HMAC 인증을 위한 비밀 키
+pub fn find_by_private_id(private_id: &str, secret_key: &str) -> Result<Option<KnownPeer>> {
+    for peer in list()? {
+        if discovery::private_beacon_id(&peer.device_id, secret_key)? == private_id {
+            return Ok(Some(peer));
+        }
+    }
+    Ok(None)
+}
+
+/// 알려진 기기 목록을 최근에 본 순으로 조회합니다.
+pub fn list() -> Result<Vec<KnownPeer>> {
+    let conn = db::open_connection()?;
+    let mut stmt = conn.prepare(
+        "SELECT device_id, device_name, last_address, last_seen, pinned_fingerprint, last_port, nickname, color, tags
+         FROM peers ORDER BY last_seen DESC, device_id",
+    )?;
+    let rows = stmt.query_map([], from_row)?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+fn from_row(row: &rusqlite::Row) -> rusqlite::Result<KnownPeer> {
+    Ok(KnownPeer {
+        device_id: row.get(0)?,
+        device_name: row.get(1)?,
+        last_address: row.get(2)?,
+        last_seen: row.get(3)?,
+        pinned_fingerprint: row.get(4)?,
+        last_port: row.get(5)?,
+        label: label_from_row(row, 6)?,
+    })
+}
+
+/// `nickname, color, tags` 열에서 표시 정보를 읽습니다 (모두 비어 있으면 None).
+fn label_from_row(row: &rusqlite::Row, first: usize) -> rusqlite::Result<Option<PeerLabel>> {
+    let tags: Option<String> = row.get(first + 2)?;
+    let label = PeerLabel {
+        nickname: row.get(first)?,
+        color: row.get(first + 1)?,
+        // 읽을 수 없는 태그 목록은 빈 목록으로 취급
+        tags: tags.and_then(|tags| serde_json::from_str(&tags).ok()).unwrap_or_default(),
+    };
+    Ok(Some(label).filter(|label| !label.is_empty()))
+}
+
+/// 기기 하나에 대한 상세 정보 (UI 기기 화면용)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceDetails {
+    /// 기기 고유 ID
+    pub device_id: String,
+
+    /// 기기 이름
+    pub device_name: String,
+
+    /// 사용자가 붙인 별명, 색상, 태그 (없으면 None)
+    pub label: Option<PeerLabel>,
+
+    /// 알려진 IP 주소 (현재 주소가 먼저)
+    pub addresses: Vec<String>,
+
+    /// 실시간 발견 목록에 있는지 여부
+    pub online: bool,
+
+    /// 비콘으로 알린 프로토콜 버전 (오프라인이면 None)
+    pub protocol_version: Option<String>,
+
+    /// 고정된 인증서 핑거프린트 (None이면 인증서 고정 없이 연결)
+    pub pinned_fingerprint: Option<String>,
+
+    /// 수락 정책의 신뢰 기기 목록에 있는지 여부 (자동 수락)
+    pub trusted: bool,
+
+    /// 마지막으로 본 시각 (Unix timestamp)
+    pub last_seen: i64,
+
+    /// 이 기기로 보낸 바이트 수와 파일 수
+    pub bytes_sent: u64,
+    pub files_sent: u64,
+
+    /// 이 기기에서 받은 바이트 수와 파일 수
+    pub bytes_received: u64,
+    pub files_received: u64,
+
+    /// 이 기기와 진행 중인 전송
+    pub active_transfers: Vec<ActiveTransfer>,
+
+    /// 이 기기와 공유 중인 폴더
+    pub shares: Vec<Share>,
+}
+
+/// 기기의 신원, 주소, 신뢰 상태, 사용량, 진행 중인 전송, 공유 폴더를 모아 반환합니다.
+///
+/// # Returns
+/// * 발견 목록과 DB 어디에도 없는 기기면 `PebbleError::NotFound`
+pub fn get_device_details(device_id: &str) -> Result<DeviceDetails> {
+    let live = discovery::get_discovered_devices()?.into_iter().find(|d| d.device_id == device_id);
+    let known = get(device_id)?;
+
+    let (device_name, last_seen) = match (&live, &known) {
+        (Some(device), _) => (device.device_name.clone(), device.last_seen as i64),
+        (None, Some(peer)) => (peer.device_name.clone(), peer.last_seen),
+        (None, None) => return Err(PebbleError::not_found(format!("Device {}", device_id)).into()),
+    };
+
+    let mut addresses: Vec<String> = live
+        .iter()
+        .flat_map(|d| std::iter::once(d.ip_address.clone()).chain(d.addresses.iter().map(|a| a.ip_address.clone())))
+        .collect();
+    addresses.extend(known_addresses(device_id)?);
+    if let Some(peer) = &known {
+        addresses.push(peer.last_address.clone());
+    }
+    let mut seen = std::collections::HashSet::new();
+    addresses.retain(|address| seen.insert(address.clone()));
+
+    let usage = quota::usage(device_id)?;
+    let active_transfers = registry::global()
+        .list()
+        .into_iter()
+        .filter(|transfer| {
+            transfer
+                .peer
+                .parse::<SocketAddr>()
+                .is_ok_and(|addr| addresses.contains(&addr.ip().to_string()))
+        })
+        .collect();
+    let shares = shares::list_shares()?
+        .into_iter()
+        .filter(|share| share.peer_device_id == device_id)
+        .collect();
+
+    Ok(DeviceDetails {
+        device_id: device_id.to_string(),
+        device_name,
+        online: live.is_some(),
+        protocol_version: live.as_ref().map(|d| d.protocol_version.clone()),
+        label: known.as_ref().and_then(|peer| peer.label.clone()),
+        pinned_fingerprint: known.and_then(|peer| peer.pinned_fingerprint),
+        trusted: config::current().accept_policy.trusted_devices.iter().any(|id| id == device_id),
+        last_seen,
+        bytes_sent: usage.as_ref().map_or(0, |u| u.bytes_sent),
+        files_sent: usage.as_ref().map_or(0, |u| u.files_sent),
+        bytes_received: usage.as_ref().map_or(0, |u| u.bytes_received),
+        files_received: usage.as_ref().map_or(0, |u| u.files_received),
+        addresses,
+        active_transfers,
+        shares,
+    })
+}
+
+/// 기기 ID로 현재 전송 주소와 핑거프린트를 찾습니다.
+///
+/// 실시간 발견 목록을 먼저 보고, 없으면 DB에 남은 주소들을 사용합니다.
+/// 주소는 최근 순이며, 측정 결과(`probe::rank_addresses`)가 있으면 도달 가능하고
+/// 빠른 주소가 앞에 옵니다.
+/// 비콘으로 알린 포트가 없으면(구버전) 설정의 transfer_port를 사용합니다.
+/// 비콘에 핑거프린트가 없으면 고정된 핑거프린트를 사용합니다.
+pub fn resolve(device_id: &str) -> Result<ResolvedPeer> {
+    if let Some(device) = discovery::get_discovered_devices()?.into_iter().find(|d| d.device_id == device_id) {
+        let addrs = rank(device_id, device.transfer_addrs()?);
+        return Ok(ResolvedPeer {
+            addr: addrs[0],
+            addrs,
+            fingerprint: device.cert_fingerprint.or_else(|| fingerprint_for(device_id, None)),
+            online: true,
+        });
+    }
+
+    let peer = get(device_id)?.ok_or_else(|| PebbleError::not_found(format!("Device {}", device_id)))?;
+
+    let port = peer.last_port.unwrap_or_else(|| config::current().transfer_port);
+    let mut addrs = Vec::new();
+    for address in known_addresses(device_id)?.iter().chain([&peer.last_address]) {
+        let addr = parse_addr(address, port)?;
+        if !addrs.contains(&addr) {
+            addrs.push(addr);
+        }
+    }
+    let addrs = rank(device_id, addrs);
+
+    Ok(ResolvedPeer {
+        addr: addrs[0],
+        addrs,
+        fingerprint: peer.pinned_fingerprint,
+        online: false,
+    })
+}
+
+/// 측정 결과로 주소 순서를 정합니다. 조회에 실패하면 최근 순을 유지합니다.
+fn rank(device_id: &str, addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    probe::rank_addresses(device_id, &addrs).unwrap_or_else(|e| {
+        tracing::warn!("Failed to rank addresses of {}: {:#}", device_id, e);
+        addrs
+    })
+}
+
+fn parse_addr(ip_address: &str, port: u16) -> Result<SocketAddr> {
+    format!("{}:{}", ip_address, port)
+        .parse()
+        .with_context(|| format!("Invalid device address: {}", ip_address))
+}
+
+/// 기기 ID로 파일을 전송합니다.
+///
+/// 알려진 주소들에 동시에 연결을 시도하고, 모두 연결하지 못하면
+/// 비콘으로 새 주소가 알려질 때까지 기다렸다가 최대
+/// `MAX_SEND_ATTEMPTS`번까지 다시 시도합니다. 거부나 해시 불일치처럼 주소와 무관한
+/// 실패는 다시 시도하지 않습니다.
+///
+/// # Arguments
+/// * `device_id` - 수신 기기 ID
+/// * `file_path` - 전송할 파일 경로
+/// * `priority` - 송신 우선순위
+/// * `cap_override` - 데이터 사용 한도에 도달해도 보낼지 여부 (사용자가 직접 허용한 경우)
+///
+/// # Returns
+/// * `Result<SocketAddr>` - 전송에 성공한 주소
+pub async fn send_file_to_device(
+    device_id: &str,
+    file_path: &str,
+    priority: TransferPriority,
+    cap_override: bool,
+) -> Result<SocketAddr> {
+    let mut peer = resolve(device_id)?;
+
+    for attempt in 1..=MAX_SEND_ATTEMPTS {
+        let mut client = TransferClient::new(peer.fingerprint.clone());
+        client.set_target_device(device_id);
+        client.set_priority(priority);
+        client.set_cap_override(cap_override);
+
+        let error = match client.send_file_any(&peer.addrs, file_path).await {
+            Ok(addr) => return Ok(addr),
+            Err(e) => e,
+        };
+
+        if attempt == MAX_SEND_ATTEMPTS || !is_unreachable(&error) {
+            return Err(error);
+        }
+
+        tracing::warn!("Send to {} at {:?} failed (attempt {}): {:#}", device_id, peer.addrs, attempt, error);
+
+        match wait_for_new_address(device_id, &peer.addrs).await {
+            Some(moved) => {
+                tracing::info!("Device {} moved from {:?} to {:?}, retrying", device_id, peer.addrs, moved.addrs);
+                peer = moved;
+            }
+            None => return Err(error),
+        }
+    }
+
+    unreachable!("loop returns on the last attempt")
+}
+
+/// 발견 목록에 시도하지 않은 주소가 나타날 때까지 기다립니다.
+async fn wait_for_new_address(device_id: &str, previous: &[SocketAddr]) -> Option<ResolvedPeer> {
+    let deadline = Instant::now() + ADDRESS_CHANGE_WAIT;
+
+    while Instant::now() < deadline {
+        tokio::time::sleep(ADDRESS_POLL_INTERVAL).await;
+
+        if let Ok(peer) = resolve(device_id) {
+            if peer.online && peer.addrs.iter().any(|addr| !previous.contains(addr)) {
+                return Some(peer);
+            }
+        }
+    }
+
+    None
+}
+
+/// 주소가 바뀌어 연결하지 못했을 가능성이 있는 에러인지 확인합니다.
+fn is_unreachable(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause.downcast_ref::<std::io::Error>().is_some_and(|io| {
+            matches!(
+                io.kind(),
+                ErrorKind::ConnectionRefused
+                    | ErrorKind::ConnectionReset
+                    | ErrorKind::ConnectionAborted
+                    | ErrorKind::TimedOut
+                    | ErrorKind::HostUnreachable
+                    | ErrorKind::NetworkUnreachable
+                    | ErrorKind::AddrNotAvailable
+            )
+        }) || matches!(cause.downcast_ref::<PebbleError>(), Some(PebbleError::Network { .. }))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loopback;
+
+    fn device(device_id: &str, ip_address: &str) -> DiscoveredDevice {
+        DiscoveredDevice {
+            device_id: device_id.to_string(),
+            device_name: "Laptop".to_string(),
+            ip_address: ip_address.to_string(),
+            addresses: Vec::new(),
+            protocol_version: discovery::PROTOCOL_VERSION.to_string(),
+            last_seen: 0,
+            is_online: true,
+            cert_fingerprint: Some("ab".repeat(32)),
+            transfer_port: None,
+            capabilities: None,
+            label: None,
+        }
+    }
+
+    #[test]
+    fn test_resolve_falls_back_to_last_address() {
+        loopback::use_temp_environment();
+
+        record_seen(&device("peers-test", "192.168.0.10")).unwrap();
+        record_seen(&device("peers-test", "192.168.0.23")).unwrap();
+
+        let peer = resolve("peers-test").unwrap();
+        assert_eq!(peer.addr.ip().to_string(), "192.168.0.23");
+        assert_eq!(peer.addr.port(), config::current().transfer_port);
+
+        // 이전 주소도 다음 후보로 남음
+        let ips: Vec<String> = peer.addrs.iter().map(|addr| addr.ip().to_string()).collect();
+        assert_eq!(ips, vec!["192.168.0.23", "192.168.0.10"]);
+        assert_eq!(known_addresses("peers-test").unwrap().len(), 2);
+        assert_eq!(device_id_at("192.168.0.10").unwrap().as_deref(), Some("peers-test"));
+        assert!(!peer.online);
+        assert_eq!(peer.fingerprint, Some("ab".repeat(32)));
+
+        assert!(list().unwrap().iter().any(|p| p.device_id == "peers-test"));
+
+        // 비콘으로 알린 포트는 다음 비콘에 포트가 없어도 유지
+        let mut rebound = device("peers-test", "192.168.0.23");
+        rebound.transfer_port = Some(41234);
+        record_seen(&rebound).unwrap();
+        record_seen(&device("peers-test", "192.168.0.23")).unwrap();
+        assert_eq!(resolve("peers-test").unwrap().addr.port(), 41234);
+        assert!(resolve("unknown-device").is_err());
+    }
+
+    #[test]
+    fn test_verified_fingerprint_is_pinned() {
+        loopback::use_temp_environment();
+
+        let mut laptop = device("pin-test", "192.168.0.10");
+        record_seen(&laptop).unwrap();
+        assert_eq!(pinned_fingerprint("pin-test").unwrap(), Some("ab".repeat(32)));
+
+        // 핑거프린트를 알리지 않는 비콘은 고정 값을 유지
+        laptop.cert_fingerprint = None;
+        record_seen(&laptop).unwrap();
+        assert_eq!(fingerprint_for("pin-test", None), Some("ab".repeat(32)));
+        assert_eq!(fingerprint_for("pin-test", Some("cd".repeat(32))), Some("cd".repeat(32)));
+
+        // 인증서가 재생성되면 새 값으로 고정
+        laptop.cert_fingerprint = Some("ef".repeat(32));
+        record_seen(&laptop).unwrap();
+        assert_eq!(pinned_fingerprint("pin-test").unwrap(), Some("ef".repeat(32)));
+
+        clear_pin("pin-test").unwrap();
+        assert_eq!(fingerprint_for("pin-test", None), None);
+    }
+
+    #[test]
+    fn test_device_details_aggregates_usage_transfers_and_shares() {
+        loopback::use_temp_environment();
+        let share_root = tempfile::TempDir::new().unwrap();
+
+        record_seen(&device("details-test", "192.168.0.77")).unwrap();
+        assert_eq!(device_id_at("192.168.0.77").unwrap().as_deref(), Some("details-test"));
+
+        quota::record_sent("details-test", 4096, true).unwrap();
+        quota::record_received("details-test", 1024, false).unwrap();
+        shares::share_folder(&share_root.path().to_string_lossy(), "details-test", shares::SharePermission::ReadOnly)
+            .unwrap();
+        let _transfer = registry::global().register(
+            "details-transfer",
+            "192.168.0.77:37846",
+            "/tmp/a.bin",
+            registry::TransferDirection::Send,
+            10,
+        ).unwrap();
+
+        let details = get_device_details("details-test").unwrap();
+        assert_eq!(details.addresses, vec!["192.168.0.77".to_string()]);
+        assert!(!details.online);
+        assert_eq!(details.pinned_fingerprint, Some("ab".repeat(32)));
+        assert_eq!((details.bytes_sent, details.files_sent), (4096, 1));
+        assert_eq!((details.bytes_received, details.files_received), (1024, 0));
+        assert_eq!(details.active_transfers.len(), 1);
+        assert_eq!(details.shares.len(), 1);
+
+        let missing = get_device_details("unknown-device").unwrap_err();
+        assert!(matches!(missing.downcast_ref::<PebbleError>(), Some(PebbleError::NotFound { .. })));
+    }
+
+    #[test]
+    fn test_label_survives_name_change() {
+        loopback::use_temp_environment();
+
+        let mut laptop = device("label-test", "192.168.0.31");
+        record_seen(&laptop).unwrap();
+        let label = PeerLabel {
+            nickname: Some("  Mom's laptop ".to_string()),
+            color: Some("#FF8800".to_string()),
+            tags: vec!["family".to_string(), " ".to_string(), "family".to_string(), "backup".to_string()],
+        };
+        set_label("label-test", &label).unwrap();
+
+        // 상대가 이름을 바꿔도 별명은 유지
+        laptop.device_name = "DESKTOP-7Q2K".to_string();
+        record_seen(&laptop).unwrap();
+        let expected = PeerLabel {
+            nickname: Some("Mom's laptop".to_string()),
+            color: Some("#ff8800".to_string()),
+            tags: vec!["family".to_string(), "backup".to_string()],
+        };
+        assert_eq!(get("label-test").unwrap().unwrap().label, Some(expected.clone()));
+        assert_eq!(get_device_details("label-test").unwrap().label, Some(expected.clone()));
+        let labeled = with_labels(vec![laptop.clone(), device("unlabeled-test", "192.168.0.32")]).unwrap();
+        assert_eq!(labeled[0].label, Some(expected));
+        assert_eq!(labeled[1].label, None);
+
+        // 빈 표시 정보는 지움
+        set_label("label-test", &PeerLabel::default()).unwrap();
+        assert_eq!(get("label-test").unwrap().unwrap().label, None);
+
+        let bad_color = PeerLabel { color: Some("orange".to_string()), ..Default::default() };
+        assert!(set_label("label-test", &bad_color).is_err());
+        let missing = set_label("unknown-device", &PeerLabel::default()).unwrap_err();
+        assert!(matches!(missing.downcast_ref::<PebbleError>(), Some(PebbleError::NotFound { .. })));
+    }
+
+    #[test]
+    fn test_unreachable_errors() {
+        let refused = anyhow::Error::new(std::io::Error::from(ErrorKind::ConnectionRefused)).context("Failed to connect");
+        assert!(is_unreachable(&refused));
+
+        let rejected = anyhow::Error::new(PebbleError::rejected("Declined"));
+        assert!(!is_unreachable(&rejected));
+    }
+}
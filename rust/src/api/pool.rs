@@ -0,0 +1,178 @@
+//! 최근 사용한 기기로의 유휴 연결 풀
+//!
+//! 파일마다 TCP 연결과 TLS 핸드셰이크를 새로 하면 작은 파일을 연달아 보낼 때
+//! 지연의 대부분이 연결 수립에 쓰입니다. 전송이 끝난 연결을 잠시 보관했다가
+//! 같은 기기(같은 인증서 고정 조건)로의 다음 전송에 재사용합니다.
+//!
+//! # Process Flow
+//! 1. 전송이 성공하면 연결을 풀에 반환 (기기당 최대 `MAX_IDLE_PER_PEER`개)
+//! 2. 다음 전송 시 `POOL_IDLE_TIMEOUT` 이내의 연결을 꺼냄
+//! 3. Ping/Pong으로 상대가 아직 연결을 유지하는지 확인 후 사용
+//! 4. 확인에 실패하면 버리고 새로 연결 (연결 재사용을 모르는 구버전 포함)
+
+use anyhow::Result;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use super::error::PebbleError;
+use super::transfer::TransferMessage;
+
+/// 기기당 보관하는 최대 유휴 연결 수
+pub const MAX_IDLE_PER_PEER: usize = 2;
+
+/// 유휴 연결 보관 시간 (수신측 `CONNECTION_IDLE_TIMEOUT`보다 짧아야 함)
+pub const POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 재사용 전 상태 확인 응답 대기 시간
+pub const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 풀에 보관하는 TLS 연결
+pub type PooledStream = tokio_rustls::client::TlsStream<TcpStream>;
+
+/// 연결을 재사용할 수 있는 조건 (주소와 인증서 고정 핑거프린트가 모두 같아야 함)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct PoolKey {
+    addr: SocketAddr,
+    fingerprint: Option<String>,
+}
+
+struct IdleConnection<S> {
+    stream: S,
+    idle_since: Instant,
+}
+
+/// 기기별 유휴 연결 풀
+pub struct ConnectionPool<S = PooledStream> {
+    idle: Mutex<HashMap<PoolKey, Vec<IdleConnection<S>>>>,
+}
+
+impl<S> Default for ConnectionPool<S> {
+    fn default() -> Self {
+        Self { idle: Mutex::new(HashMap::new()) }
+    }
+}
+
+impl<S> ConnectionPool<S> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 가장 최근에 반환된 유휴 연결을 꺼냅니다. 만료된 연결은 버립니다.
+    pub fn take(&self, addr: SocketAddr, fingerprint: &Option<String>) -> Option<S> {
+        let mut idle = self.idle.lock().ok()?;
+        let key = PoolKey { addr, fingerprint: fingerprint.clone() };
+        let connections = idle.get_mut(&key)?;
+
+        connections.retain(|connection| connection.idle_since.elapsed() < POOL_IDLE_TIMEOUT);
+        let stream = connections.pop().map(|connection| connection.stream);
+        if connections.is_empty() {
+            idle.remove(&key);
+        }
+
+        stream
+    }
+
+    /// 전송이 끝난 연결을 풀에 반환합니다. 기기당 한도를 넘으면 가장 오래된 연결을 닫습니다.
+    pub fn put(&self, addr: SocketAddr, fingerprint: &Option<String>, stream: S) {
+        let Ok(mut idle) = self.idle.lock() else {
+            return;
+        };
+
+        let connections = idle.entry(PoolKey { addr, fingerprint: fingerprint.clone() }).or_default();
+        connections.retain(|connection| connection.idle_since.elapsed() < POOL_IDLE_TIMEOUT);
+        connections.push(IdleConnection { stream, idle_since: Instant::now() });
+        if connections.len() > MAX_IDLE_PER_PEER {
+            connections.remove(0);
+        }
+    }
+
+    /// 보관 중인 유휴 연결 수
+    pub fn idle_count(&self) -> usize {
+        self.idle
+            .lock()
+            .map(|idle| idle.values().map(Vec::len).sum())
+            .unwrap_or(0)
+    }
+
+    /// 모든 유휴 연결을 닫습니다.
+    pub fn clear(&self) {
+        if let Ok(mut idle) = self.idle.lock() {
+            idle.clear();
+        }
+    }
+}
+
+/// 상대가 아직 연결을 유지하고 요청을 받을 수 있는지 Ping/Pong으로 확인합니다.
+pub async fn check_health<S>(stream: &mut S) -> Result<()>
+where
+    S: AsyncReadExt + AsyncWriteExt + Unpin,
+{
+    let nonce = uuid::Uuid::new_v4().as_u64_pair().0;
+
+    let exchange = async {
+        stream.write_all(&TransferMessage::Ping { nonce }.to_bytes()?).await?;
+        stream.flush().await?;
+        TransferMessage::from_stream(stream).await
+    };
+
+    match tokio::time::timeout(HEALTH_CHECK_TIMEOUT, exchange).await {
+        Ok(Ok(TransferMessage::Pong { nonce: reply })) if reply == nonce => Ok(()),
+        Ok(Ok(other)) => Err(PebbleError::protocol(format!("Expected Pong, got {:?}", other)).into()),
+        Ok(Err(e)) => Err(e),
+        Err(_) => Err(PebbleError::network("Pooled connection did not answer health check").into()),
+    }
+}
+
+/// 전역 연결 풀
+static POOL: once_cell::sync::Lazy<ConnectionPool> = once_cell::sync::Lazy::new(ConnectionPool::new);
+
+/// 전역 연결 풀을 반환합니다.
+pub fn global() -> &'static ConnectionPool {
+    &POOL
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loopback;
+    use crate::api::transfer::TransferServer;
+
+    #[test]
+    fn test_pool_keys_and_limits() {
+        let pool: ConnectionPool<u32> = ConnectionPool::new();
+        let addr: SocketAddr = "10.0.0.2:37846".parse().unwrap();
+        let pinned = Some("ab".repeat(32));
+
+        for id in 1..=3 {
+            pool.put(addr, &None, id);
+        }
+        assert_eq!(pool.idle_count(), MAX_IDLE_PER_PEER);
+
+        // 다른 핑거프린트로 연 연결은 재사용하지 않음
+        assert_eq!(pool.take(addr, &pinned), None);
+        assert_eq!(pool.take(addr, &None), Some(3));
+        assert_eq!(pool.take(addr, &None), Some(2));
+        assert_eq!(pool.take(addr, &None), None);
+    }
+
+    #[tokio::test]
+    async fn test_health_check_against_server() {
+        loopback::use_temp_environment();
+        let (mut client_io, server_io) = loopback::stream_pair();
+        let server = tokio::spawn(TransferServer::handle_stream(
+            server_io,
+            "127.0.0.1:1".parse().unwrap(),
+            None,
+        ));
+
+        check_health(&mut client_io).await.unwrap();
+        check_health(&mut client_io).await.unwrap();
+
+        drop(client_io);
+        server.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,580 @@
+//! 기기 접속 규칙 (Presence Rules)
+//!
+//! 기기가 나타나거나(`DevicePaired`) 사라질 때(`DeviceOffline`) 설정한 동작을 실행합니다.
+//! 예: 노트북이 보이면 동기화 쌍의 차이를 추정, NAS가 사라지면 로컬 웹훅 호출.
+//!
+//! # Process Flow
+//! 1. `run`이 이벤트를 구독하여 접속 변화를 채널로 받음
+//! 2. 설정의 `presence_rules`에서 변화 종류와 기기 ID 또는 태그(`PeerLabel.tags`)가 맞는 규칙을 찾음
+//! 3. 같은 규칙과 기기로 `cooldown_secs` 안에 다시 실행하지 않음 (비콘이 잠깐 끊겼다 이어지는 경우)
+//! 4. 동작을 별도 작업으로 실행하고 결과를 `PresenceRuleFired` 이벤트로 발행
+//!
+//! # Security
+//! - 웹훅은 루프백 주소(127.0.0.1, ::1, localhost)의 http URL만 허용 (기기 정보를 밖으로 보내지 않음)
+//! - 명령은 셸을 거치지 않고 실행하며, 기기 정보는 인자가 아닌 환경 변수로 전달
+//!   (`PEBBLE_DEVICE_ID`, `PEBBLE_DEVICE_NAME`, `PEBBLE_PRESENCE`)
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::process::Command;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use super::config;
+use super::error::PebbleError;
+use super::events::{self, PebbleEvent};
+use super::pairs;
+use super::peers;
+use super::priority::TransferPriority;
+use super::sendqueue;
+
+/// 기본 재실행 대기 시간 (초)
+pub const DEFAULT_COOLDOWN_SECS: u64 = 300;
+
+/// 명령 기본 제한 시간 (초)
+pub const DEFAULT_COMMAND_TIMEOUT_SECS: u64 = 60;
+
+/// 명령에 허용하는 최대 제한 시간 (초)
+pub const MAX_COMMAND_TIMEOUT_SECS: u64 = 3600;
+
+/// 웹훅 연결과 응답 제한 시간
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 접속 변화 종류
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PresenceChange {
+    /// 기기가 발견됨 (처음 또는 시간 초과로 사라진 뒤 다시)
+    Online,
+    /// 기기의 비콘이 `device_timeout_secs` 동안 보이지 않음
+    Offline,
+}
+
+impl PresenceChange {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Online => "online",
+            Self::Offline => "offline",
+        }
+    }
+}
+
+/// 규칙이 맞을 때 실행할 동작
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum PresenceAction {
+    /// 동기화 쌍을 상대 루트와 비교해 보낼 파일, 받을 파일, 충돌 수를 이벤트로 알림
+    ///
+    /// 비교만 하며 파일을 주고받지는 않습니다 (`pairs::estimate_sync`).
+    /// 예전 이름 `SyncPair`로 저장된 규칙도 읽습니다.
+    #[serde(alias = "SyncPair")]
+    EstimateSync {
+        pair_id: i64,
+    },
+    /// 파일을 그 기기로 보내도록 송신 대기열에 추가
+    SendFile {
+        path: String,
+    },
+    /// 로컬 웹훅에 접속 변화를 JSON으로 POST
+    Webhook {
+        /// `http://127.0.0.1:<port>/<path>` 형식 (루프백만 허용)
+        url: String,
+    },
+    /// 외부 명령 실행
+    Command {
+        program: String,
+        #[serde(default)]
+        args: Vec<String>,
+        /// 제한 시간 (초, 넘기면 종료하고 실패로 알림)
+        #[serde(default = "default_command_timeout")]
+        timeout_secs: u64,
+    },
+}
+
+fn default_command_timeout() -> u64 {
+    DEFAULT_COMMAND_TIMEOUT_SECS
+}
+
+fn default_cooldown() -> u64 {
+    DEFAULT_COOLDOWN_SECS
+}
+
+/// 접속 규칙 하나
+///
+/// `device_id`와 `tag`가 모두 없으면 모든 기기에 적용되고, 둘 다 있으면 둘 다 맞아야 합니다.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PresenceRule {
+    /// 규칙 이름 (이벤트와 로그에 표시, 설정 안에서 고유)
+    pub name: String,
+
+    /// 대상 기기 ID
+    #[serde(default)]
+    pub device_id: Option<String>,
+
+    /// 대상 기기 태그 (`peers::set_label`로 붙인 태그)
+    #[serde(default)]
+    pub tag: Option<String>,
+
+    /// 반응할 접속 변화
+    pub on: PresenceChange,
+
+    pub action: PresenceAction,
+
+    /// 같은 기기로 다시 실행하기까지 기다릴 시간 (초, 0이면 매번 실행)
+    #[serde(default = "default_cooldown")]
+    pub cooldown_secs: u64,
+}
+
+impl PresenceRule {
+    /// 설정 값을 검증합니다.
+    pub fn validate(&self) -> Result<()> {
+        if self.name.trim().is_empty() {
+            return Err(PebbleError::invalid_argument("presence_rules.name must not be empty").into());
+        }
+        if self.device_id.as_deref().is_some_and(|id| id.trim().is_empty()) {
+            return Err(PebbleError::invalid_argument(format!(
+                "Presence rule {}: device_id must not be empty (use null for any device)", self.name
+            )).into());
+        }
+        if self.tag.as_deref().is_some_and(|tag| tag.trim().is_empty()) {
+            return Err(PebbleError::invalid_argument(format!(
+                "Presence rule {}: tag must not be empty (use null for any device)", self.name
+            )).into());
+        }
+
+        match &self.action {
+            PresenceAction::EstimateSync { .. } => {}
+            PresenceAction::SendFile { path } => {
+                if path.trim().is_empty() {
+                    return Err(PebbleError::invalid_argument(format!("Presence rule {}: path must not be empty", self.name)).into());
+                }
+            }
+            PresenceAction::Webhook { url } => {
+                WebhookUrl::parse(url).with_context(|| format!("Presence rule {}", self.name))?;
+            }
+            PresenceAction::Command { program, timeout_secs, .. } => {
+                if program.trim().is_empty() {
+                    return Err(PebbleError::invalid_argument(format!("Presence rule {}: program must not be empty", self.name)).into());
+                }
+                if !(1..=MAX_COMMAND_TIMEOUT_SECS).contains(timeout_secs) {
+                    return Err(PebbleError::invalid_argument(format!(
+                        "Presence rule {}: timeout_secs must be between 1 and {}", self.name, MAX_COMMAND_TIMEOUT_SECS
+                    )).into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn matches(&self, presence: &Presence, tags: &[String]) -> bool {
+        self.on == presence.change
+            && self.device_id.as_deref().is_none_or(|id| id == presence.device_id)
+            && self.tag.as_deref().is_none_or(|tag| tags.iter().any(|t| t.eq_ignore_ascii_case(tag.trim())))
+    }
+}
+
+/// 규칙 목록 전체를 검증합니다 (이름 중복 포함).
+pub fn validate_rules(rules: &[PresenceRule]) -> Result<()> {
+    let mut names = HashSet::new();
+    for rule in rules {
+        rule.validate()?;
+        if !names.insert(rule.name.as_str()) {
+            anyhow::bail!("Duplicate presence rule name: {}", rule.name);
+        }
+    }
+    Ok(())
+}
+
+/// 기기의 접속 변화
+#[derive(Debug, Clone, PartialEq)]
+struct Presence {
+    change: PresenceChange,
+    device_id: String,
+    device_name: String,
+}
+
+impl Presence {
+    fn from_event(event: &PebbleEvent) -> Option<Self> {
+        let (change, device_id, device_name) = match event {
+            PebbleEvent::DevicePaired { device_id, device_name, .. } => (PresenceChange::Online, device_id, device_name),
+            PebbleEvent::DeviceOffline { device_id, device_name } => (PresenceChange::Offline, device_id, device_name),
+            _ => return None,
+        };
+        Some(Self { change, device_id: device_id.clone(), device_name: device_name.clone() })
+    }
+}
+
+/// 규칙별 마지막 실행 시각 (재실행 대기용)
+#[derive(Debug, Default)]
+struct RuleTracker {
+    last_fired: HashMap<(String, String), Instant>,
+}
+
+impl RuleTracker {
+    /// 접속 변화에 맞고 재실행 대기 중이 아닌 규칙을 고르고 실행 시각을 기록합니다.
+    fn select(&mut self, rules: &[PresenceRule], presence: &Presence, tags: &[String], now: Instant) -> Vec<PresenceRule> {
+        let mut selected = Vec::new();
+        for rule in rules.iter().filter(|rule| rule.matches(presence, tags)) {
+            let key = (rule.name.clone(), presence.device_id.clone());
+            let cooldown = Duration::from_secs(rule.cooldown_secs);
+            if self.last_fired.get(&key).is_some_and(|last| now.saturating_duration_since(*last) < cooldown) {
+                tracing::debug!("Presence rule {} for {} is cooling down", rule.name, presence.device_id);
+                continue;
+            }
+            self.last_fired.insert(key, now);
+            selected.push(rule.clone());
+        }
+        selected
+    }
+}
+
+/// 웹훅 주소
+#[derive(Debug, PartialEq)]
+struct WebhookUrl {
+    host: String,
+    port: u16,
+    /// Host 헤더 값
+    authority: String,
+    path: String,
+}
+
+impl WebhookUrl {
+    fn parse(url: &str) -> Result<Self> {
+        let invalid = |reason: &str| PebbleError::invalid_argument(format!("Invalid webhook url {}: {}", url, reason));
+        let rest = url.strip_prefix("http://").ok_or_else(|| invalid("only http:// is supported"))?;
+        let (authority, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        if path.chars().any(|c| c.is_whitespace() || c.is_control()) {
+            return Err(invalid("path must not contain whitespace").into());
+        }
+
+        let (host, port) = match authority.strip_prefix('[') {
+            Some(bracketed) => {
+                let (host, port) = bracketed.split_once(']').ok_or_else(|| invalid("unclosed '['"))?;
+                (host, port.strip_prefix(':'))
+            }
+            None => match authority.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (authority, None),
+            },
+        };
+        let port = match port {
+            Some(port) => port.parse::<u16>().ok().filter(|port| *port != 0).ok_or_else(|| invalid("invalid port"))?,
+            None => 80,
+        };
+
+        let loopback = host.eq_ignore_ascii_case("localhost") || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback());
+        if !loopback {
+            return Err(invalid("only loopback hosts are allowed").into());
+        }
+
+        Ok(Self { host: host.to_string(), port, authority: authority.to_string(), path: path.to_string() })
+    }
+
+    /// 루프백으로 확인되는 주소만 가져옵니다 (`localhost`가 다른 곳을 가리키는 경우 대비).
+    async fn resolve(&self) -> Result<Vec<SocketAddr>> {
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((self.host.as_str(), self.port))
+            .await
+            .with_context(|| format!("Failed to resolve {}", self.host))?
+            .filter(|addr| addr.ip().is_loopback())
+            .collect();
+        if addrs.is_empty() {
+            return Err(PebbleError::network(format!("{} does not resolve to a loopback address", self.host)).into());
+        }
+        Ok(addrs)
+    }
+}
+
+/// 웹훅에 JSON을 POST합니다.
+///
+/// # Returns
+/// * 응답 상태 줄 (2xx가 아니면 에러)
+async fn post_webhook(url: &str, body: &serde_json::Value) -> Result<String> {
+    let url = WebhookUrl::parse(url)?;
+    let body = body.to_string();
+
+    let exchange = async {
+        let addrs = url.resolve().await?;
+        let mut stream = TcpStream::connect(addrs.as_slice())
+            .await
+            .with_context(|| format!("Failed to connect to webhook {}", url.authority))?;
+        let request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: pebble\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            url.path, url.authority, body.len(), body
+        );
+        stream.write_all(request.as_bytes()).await?;
+
+        let mut status_line = String::new();
+        BufReader::new(stream).read_line(&mut status_line).await?;
+        Ok::<_, anyhow::Error>(status_line.trim_end().to_string())
+    };
+    let status_line = tokio::time::timeout(WEBHOOK_TIMEOUT, exchange)
+        .await
+        .map_err(|_| PebbleError::network(format!("Webhook {} timed out", url.authority)))??;
+
+    let status = status_line.split_whitespace().nth(1).and_then(|code| code.parse::<u16>().ok());
+    match status {
+        Some(200..=299) => Ok(status_line),
+        Some(_) => Err(PebbleError::rejected(format!("Webhook responded {}", status_line)).into()),
+        None => Err(PebbleError::protocol(format!("Invalid webhook response: {:?}", status_line)).into()),
+    }
+}
+
+/// 명령을 실행합니다.
+///
+/// # Returns
+/// * 종료 상태 (0이 아니거나 시간을 넘기면 에러)
+async fn run_command(program: &str, args: &[String], timeout_secs: u64, presence: &Presence) -> Result<String> {
+    let child = Command::new(program)
+        .args(args)
+        .env("PEBBLE_DEVICE_ID", &presence.device_id)
+        .env("PEBBLE_DEVICE_NAME", &presence.device_name)
+        .env("PEBBLE_PRESENCE", presence.change.as_str())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Cannot run {}", program))?;
+
+    // 시간을 넘기면 future가 버려지면서 프로세스도 종료됨 (kill_on_drop)
+    let output = tokio::time::timeout(Duration::from_secs(timeout_secs), child.wait_with_output())
+        .await
+        .map_err(|_| PebbleError::cancelled(format!("{} timed out after {}s", program, timeout_secs)))?
+        .with_context(|| format!("Failed to wait for {}", program))?;
+    if output.status.success() {
+        return Ok(output.status.to_string());
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    Err(PebbleError::rejected(match stderr.lines().map(str::trim).find(|line| !line.is_empty()) {
+        Some(detail) => format!("{} {} ({})", program, output.status, detail),
+        None => format!("{} {}", program, output.status),
+    }).into())
+}
+
+/// 동작을 실행합니다.
+///
+/// # Returns
+/// * 결과 요약
+async fn execute(rule: &PresenceRule, presence: &Presence) -> Result<String> {
+    match &rule.action {
+        PresenceAction::EstimateSync { pair_id } => {
+            let estimate = pairs::estimate_sync(*pair_id).await?;
+            Ok(format!(
+                "Pair {} (estimate only): {} file(s) to send, {} to receive, {} conflict(s)",
+                pair_id, estimate.upload_files, estimate.download_files, estimate.conflicts.len()
+            ))
+        }
+        PresenceAction::SendFile { path } => {
+            let queued = sendqueue::enqueue(&presence.device_id, path, TransferPriority::Low)?;
+            Ok(format!("Queued {} as {}", queued.file_path, queued.id))
+        }
+        PresenceAction::Webhook { url } => {
+            let body = serde_json::json!({
+                "rule": rule.name,
+                "presence": presence.change.as_str(),
+                "device_id": presence.device_id,
+                "device_name": presence.device_name,
+            });
+            post_webhook(url, &body).await
+        }
+        PresenceAction::Command { program, args, timeout_secs } => run_command(program, args, *timeout_secs, presence).await,
+    }
+}
+
+/// 규칙을 실행하고 결과를 이벤트로 알립니다.
+async fn fire(rule: PresenceRule, presence: Presence) {
+    tracing::info!("Presence rule {} fired: {} is {}", rule.name, presence.device_id, presence.change.as_str());
+    let (detail, error) = match execute(&rule, &presence).await {
+        Ok(detail) => (Some(detail), None),
+        Err(e) => {
+            tracing::warn!("Presence rule {} failed: {:#}", rule.name, e);
+            (None, Some(format!("{:#}", e)))
+        }
+    };
+    events::emit(PebbleEvent::PresenceRuleFired {
+        rule: rule.name,
+        device_id: presence.device_id,
+        change: presence.change,
+        detail,
+        error,
+    });
+}
+
+/// 기기 태그 (태그 규칙이 있을 때만 조회)
+fn device_tags(rules: &[PresenceRule], device_id: &str) -> Vec<String> {
+    if rules.iter().all(|rule| rule.tag.is_none()) {
+        return Vec::new();
+    }
+    match peers::labels() {
+        Ok(mut labels) => labels.remove(device_id).map(|label| label.tags).unwrap_or_default(),
+        Err(e) => {
+            tracing::warn!("Failed to load peer tags for presence rules: {:#}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// 접속 변화를 받아 규칙을 실행합니다 (서비스 실행 중 백그라운드 작업).
+pub async fn run(token: CancellationToken) -> Result<()> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    events::subscribe(move |event| match Presence::from_event(event) {
+        Some(presence) => tx.send(presence).is_ok(),
+        None => !tx.is_closed(),
+    });
+
+    let mut tracker = RuleTracker::default();
+    loop {
+        let presence = tokio::select! {
+            _ = token.cancelled() => return Ok(()),
+            presence = rx.recv() => match presence {
+                Some(presence) => presence,
+                None => return Ok(()),
+            },
+        };
+
+        let rules = config::current().presence_rules;
+        if rules.is_empty() {
+            continue;
+        }
+        let tags = device_tags(&rules, &presence.device_id);
+        for rule in tracker.select(&rules, &presence, &tags, Instant::now()) {
+            tokio::spawn(fire(rule, presence.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    fn rule(name: &str, device_id: Option<&str>, tag: Option<&str>, on: PresenceChange) -> PresenceRule {
+        PresenceRule {
+            name: name.to_string(),
+            device_id: device_id.map(str::to_string),
+            tag: tag.map(str::to_string),
+            on,
+            action: PresenceAction::EstimateSync { pair_id: 1 },
+            cooldown_secs: 60,
+        }
+    }
+
+    fn presence(change: PresenceChange, device_id: &str) -> Presence {
+        Presence { change, device_id: device_id.to_string(), device_name: "Laptop".to_string() }
+    }
+
+    #[test]
+    fn test_rules_match_device_tag_and_cool_down() {
+        let rules = vec![
+            rule("laptop-online", Some("laptop"), None, PresenceChange::Online),
+            rule("family-online", None, Some("family"), PresenceChange::Online),
+            rule("laptop-offline", Some("laptop"), None, PresenceChange::Offline),
+        ];
+        let mut tracker = RuleTracker::default();
+        let now = Instant::now();
+        let names = |selected: Vec<PresenceRule>| selected.into_iter().map(|rule| rule.name).collect::<Vec<_>>();
+
+        let online = presence(PresenceChange::Online, "laptop");
+        let tags = vec!["Family".to_string()];
+        assert_eq!(names(tracker.select(&rules, &online, &tags, now)), ["laptop-online", "family-online"]);
+
+        // 재실행 대기 중에는 다시 실행하지 않고, 다른 기기나 변화 종류는 별개
+        assert!(tracker.select(&rules, &online, &tags, now + Duration::from_secs(30)).is_empty());
+        assert_eq!(names(tracker.select(&rules, &presence(PresenceChange::Online, "phone"), &tags, now)), ["family-online"]);
+        assert_eq!(names(tracker.select(&rules, &presence(PresenceChange::Offline, "laptop"), &[], now)), ["laptop-offline"]);
+        assert_eq!(names(tracker.select(&rules, &online, &[], now + Duration::from_secs(61))), ["laptop-online"]);
+    }
+
+    #[test]
+    fn test_validate_rules() {
+        let webhook = |url: &str| PresenceRule {
+            action: PresenceAction::Webhook { url: url.to_string() },
+            ..rule("hook", None, None, PresenceChange::Online)
+        };
+        assert!(webhook("http://127.0.0.1:8123/api/pebble").validate().is_ok());
+        assert!(webhook("http://localhost/hook").validate().is_ok());
+        assert!(webhook("http://[::1]:9000").validate().is_ok());
+        assert!(webhook("http://192.168.0.10:8123/hook").validate().is_err());
+        assert!(webhook("https://127.0.0.1/hook").validate().is_err());
+        assert!(webhook("http://127.0.0.1:0/hook").validate().is_err());
+
+        let command = PresenceRule {
+            action: PresenceAction::Command { program: "rsync".to_string(), args: Vec::new(), timeout_secs: 0 },
+            ..rule("cmd", None, None, PresenceChange::Online)
+        };
+        assert!(command.validate().is_err());
+
+        let duplicate = vec![rule("a", None, None, PresenceChange::Online), rule("a", None, None, PresenceChange::Offline)];
+        assert!(validate_rules(&duplicate).is_err());
+
+        // 설정 JSON에서는 동작 종류를 `type`으로 구분
+        let parsed: PresenceRule = serde_json::from_str(
+            r#"{"name": "nas", "device_id": "nas-1", "on": "Offline", "action": {"type": "Command", "program": "/usr/local/bin/unmount-nas"}}"#,
+        ).unwrap();
+        assert_eq!(parsed.cooldown_secs, DEFAULT_COOLDOWN_SECS);
+        assert!(matches!(parsed.action, PresenceAction::Command { timeout_secs: DEFAULT_COMMAND_TIMEOUT_SECS, .. }));
+
+        // 예전 이름으로 저장된 비교 동작도 읽음
+        let legacy: PresenceAction = serde_json::from_str(r#"{"type": "SyncPair", "pair_id": 7}"#).unwrap();
+        assert_eq!(legacy, PresenceAction::EstimateSync { pair_id: 7 });
+    }
+
+    #[tokio::test]
+    async fn test_webhook_posts_presence() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 1024];
+            while !String::from_utf8_lossy(&request).contains("\"device_id\"") {
+                let read = stream.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(&buffer[..read]);
+            }
+            stream.write_all(b"HTTP/1.1 204 No Content\r\n\r\n").await.unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        let hook = PresenceRule {
+            action: PresenceAction::Webhook { url: format!("http://127.0.0.1:{}/presence", port) },
+            ..rule("hook", Some("laptop"), None, PresenceChange::Online)
+        };
+        let status = execute(&hook, &presence(PresenceChange::Online, "laptop")).await.unwrap();
+        assert_eq!(status, "HTTP/1.1 204 No Content");
+
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /presence HTTP/1.1\r\n"));
+        let body: serde_json::Value = serde_json::from_str(request.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+        assert_eq!(body["presence"], "online");
+        assert_eq!(body["device_id"], "laptop");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_command_receives_device_in_environment() {
+        let command = |script: &str| PresenceRule {
+            action: PresenceAction::Command {
+                program: "/bin/sh".to_string(),
+                args: vec!["-c".to_string(), script.to_string()],
+                timeout_secs: 1,
+            },
+            ..rule("cmd", None, None, PresenceChange::Offline)
+        };
+        let offline = presence(PresenceChange::Offline, "laptop");
+
+        assert!(execute(&command("[ \"$PEBBLE_DEVICE_ID:$PEBBLE_PRESENCE\" = laptop:offline ]"), &offline).await.is_ok());
+        let err = execute(&command("echo 'mount busy' >&2; exit 3"), &offline).await.unwrap_err();
+        assert!(err.to_string().contains("mount busy"));
+        assert!(execute(&command("sleep 5"), &offline).await.is_err());
+    }
+}
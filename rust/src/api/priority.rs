@@ -0,0 +1,102 @@
+//! 전송 우선순위 (Transfer Priority)
+//!
+//! 사용자가 직접 보낸 파일이 백그라운드 동기화(감시 폴더 전달 등)에 밀려 늦어지지
+//! 않도록 송신 전송에 우선순위를 붙입니다. 높은 우선순위의 송신이 진행 중이면
+//! 낮은 우선순위의 송신은 취소되지 않고 청크 전송만 멈췄다가, 높은 우선순위의
+//! 송신이 모두 끝나면 이어서 보냅니다.
+//!
+//! # Process Flow
+//! 1. 송신을 시작할 때 `begin`으로 우선순위별 진행 중 송신 수를 올림 (guard drop 시 내림)
+//! 2. 송신 루프가 청크마다 `is_preempted`로 더 높은 우선순위의 송신이 있는지 확인
+//! 3. 있으면 `wait_turn`으로 해당 송신이 모두 끝날 때까지 대기 (상태는 Paused로 표시)
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+/// 송신 전송 우선순위
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum TransferPriority {
+    /// 사용자가 직접 시작한 전송
+    High,
+    /// 우선순위를 지정하지 않은 전송
+    #[default]
+    Normal,
+    /// 백그라운드 동기화 전송
+    Low,
+}
+
+impl TransferPriority {
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// 우선순위별 진행 중인 송신 수 (`TransferPriority` 순서)
+type ActiveCounts = [usize; 3];
+
+static ACTIVE: once_cell::sync::Lazy<watch::Sender<ActiveCounts>> =
+    once_cell::sync::Lazy::new(|| watch::channel([0; 3]).0);
+
+/// 진행 중인 송신 하나 (drop되면 집계에서 빠짐)
+#[derive(Debug)]
+pub struct PriorityGuard {
+    priority: TransferPriority,
+}
+
+impl Drop for PriorityGuard {
+    fn drop(&mut self) {
+        let index = self.priority.index();
+        ACTIVE.send_modify(|counts| counts[index] = counts[index].saturating_sub(1));
+    }
+}
+
+/// 송신 시작을 집계합니다. 반환된 guard를 송신이 끝날 때까지 유지해야 합니다.
+pub fn begin(priority: TransferPriority) -> PriorityGuard {
+    ACTIVE.send_modify(|counts| counts[priority.index()] += 1);
+    PriorityGuard { priority }
+}
+
+fn has_higher(counts: &ActiveCounts, priority: TransferPriority) -> bool {
+    counts[..priority.index()].iter().any(|&count| count > 0)
+}
+
+/// 더 높은 우선순위의 송신이 진행 중인지 확인합니다.
+pub fn is_preempted(priority: TransferPriority) -> bool {
+    has_higher(&ACTIVE.borrow(), priority)
+}
+
+/// 더 높은 우선순위의 송신이 모두 끝날 때까지 기다립니다.
+pub async fn wait_turn(priority: TransferPriority) {
+    let mut rx = ACTIVE.subscribe();
+    // 송신측(static)은 닫히지 않으므로 에러가 나지 않음
+    let _ = rx.wait_for(|counts| !has_higher(counts, priority)).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_has_higher() {
+        assert!(has_higher(&[1, 0, 0], TransferPriority::Low));
+        assert!(has_higher(&[0, 1, 0], TransferPriority::Low));
+        assert!(!has_higher(&[1, 1, 1], TransferPriority::High));
+        assert!(!has_higher(&[0, 0, 1], TransferPriority::Normal));
+    }
+
+    #[tokio::test]
+    async fn test_normal_priority_waits_for_high() {
+        // 다른 테스트의 송신은 Normal이므로 High가 끝나면 Normal 대기가 풀림
+        let high = begin(TransferPriority::High);
+        assert!(is_preempted(TransferPriority::Normal));
+        assert!(!is_preempted(TransferPriority::High));
+
+        let waiter = tokio::spawn(wait_turn(TransferPriority::Normal));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiter.is_finished());
+
+        drop(high);
+        tokio::time::timeout(Duration::from_secs(5), waiter).await.unwrap().unwrap();
+    }
+}
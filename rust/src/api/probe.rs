@@ -0,0 +1,376 @@
+//! 상대 기기 지연 시간/도달성 측정 (Peer Probing)
+//!
+//! 상대 기기의 전송 서버에 연결해 TCP 연결 시간, TLS 핸드셰이크 시간을 재고,
+//! 같은 연결로 작은 Ping을 여러 번 보내 왕복 시간과 손실률을 측정합니다.
+//! 결과는 주소별로 `peer_probes` 테이블에 저장되며, 기기에 주소가 여럿이면
+//! `rank_addresses`로 빠르고 안정적인 경로를 먼저 고릅니다.
+//!
+//! # Process Flow
+//! 1. TCP 연결 (`CONNECT_TIMEOUT` 안에 안 되면 도달 불가로 기록)
+//! 2. TLS 핸드셰이크 (고정된 인증서가 있으면 핑거프린트 검증)
+//! 3. `Ping`을 하나씩 보내고 `PING_TIMEOUT` 안에 `Pong`이 오지 않으면 손실로 집계
+//! 4. 결과를 저장하고 반환
+
+use anyhow::{Context, Result};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+
+use super::certificate::TlsCertificate;
+use super::db;
+use super::error::PebbleError;
+use super::peers;
+use super::transfer::TransferMessage;
+use super::util::unix_now;
+
+/// 기본 Ping 횟수
+pub const DEFAULT_PROBE_PINGS: u32 = 10;
+
+/// 허용하는 최대 Ping 횟수
+pub const MAX_PROBE_PINGS: u32 = 100;
+
+/// TCP 연결 + TLS 핸드셰이크 제한 시간
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Ping 하나의 응답 대기 시간 (넘으면 손실)
+pub const PING_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// 주소 하나에 대한 측정 결과
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProbeResult {
+    pub device_id: String,
+
+    /// 측정한 전송 서버 주소 (IP:Port)
+    pub address: String,
+
+    /// 연결과 핸드셰이크에 성공했는지 여부
+    pub reachable: bool,
+
+    /// TCP 연결 시간 (ms)
+    pub connect_ms: Option<f64>,
+
+    /// TLS 핸드셰이크 시간 (ms)
+    pub tls_handshake_ms: Option<f64>,
+
+    pub pings_sent: u32,
+    pub pings_lost: u32,
+
+    /// 손실률 (%, 도달 불가면 100)
+    pub loss_percent: f64,
+
+    /// 응답받은 Ping의 평균/최소/최대 왕복 시간 (ms)
+    pub avg_rtt_ms: Option<f64>,
+    pub min_rtt_ms: Option<f64>,
+    pub max_rtt_ms: Option<f64>,
+
+    /// 연결 실패 원인
+    pub error: Option<String>,
+
+    /// 측정 시각 (Unix timestamp)
+    pub probed_at: i64,
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// 열린 연결로 Ping을 `count`번 보내고 각 왕복 시간을 반환합니다 (응답이 없으면 None).
+///
+/// 늦게 도착한 이전 Ping의 응답은 건너뜁니다. 연결이 끊기면 남은 Ping은 모두 손실입니다.
+pub async fn ping_series<S>(stream: &mut S, count: u32, timeout: Duration) -> Vec<Option<Duration>>
+where
+    S: AsyncReadExt + AsyncWriteExt + Unpin,
+{
+    let mut rtts = Vec::with_capacity(count as usize);
+    let mut outstanding = HashSet::new();
+
+    for _ in 0..count {
+        let nonce = uuid::Uuid::new_v4().as_u64_pair().0;
+        let started = Instant::now();
+
+        let exchange = async {
+            stream.write_all(&TransferMessage::Ping { nonce }.to_bytes()?).await?;
+            stream.flush().await?;
+            loop {
+                match TransferMessage::from_stream(stream).await? {
+                    TransferMessage::Pong { nonce: reply } if reply == nonce => return anyhow::Ok(()),
+                    TransferMessage::Pong { nonce: late } if outstanding.contains(&late) => continue,
+                    other => return Err(PebbleError::protocol(format!("Expected Pong, got {:?}", other)).into()),
+                }
+            }
+        };
+
+        match tokio::time::timeout(timeout, exchange).await {
+            Ok(Ok(())) => rtts.push(Some(started.elapsed())),
+            Ok(Err(e)) => {
+                tracing::debug!("Probe ping failed: {:#}", e);
+                rtts.resize(count as usize, None);
+                break;
+            }
+            Err(_) => {
+                outstanding.insert(nonce);
+                rtts.push(None);
+            }
+        }
+    }
+
+    rtts
+}
+
+/// 주소 하나를 측정하고 결과를 저장합니다.
+///
+/// 연결할 수 없으면 에러 대신 `reachable: false`인 결과를 반환합니다.
+///
+/// # Arguments
+/// * `device_id` - 결과를 저장할 기기 ID
+/// * `addr` - 상대 기기의 전송 서버 주소
+/// * `fingerprint` - 상대 인증서 핑거프린트 (Certificate Pinning용, Optional)
+/// * `pings` - 보낼 Ping 수
+pub async fn probe_address(device_id: &str, addr: SocketAddr, fingerprint: Option<String>, pings: u32) -> Result<ProbeResult> {
+    let mut result = ProbeResult {
+        device_id: device_id.to_string(),
+        address: addr.to_string(),
+        reachable: false,
+        connect_ms: None,
+        tls_handshake_ms: None,
+        pings_sent: pings,
+        pings_lost: pings,
+        loss_percent: 100.0,
+        avg_rtt_ms: None,
+        min_rtt_ms: None,
+        max_rtt_ms: None,
+        error: None,
+        probed_at: unix_now(),
+    };
+
+    let connected = tokio::time::timeout(CONNECT_TIMEOUT, async {
+        // TCP 연결
+        let started = Instant::now();
+        let tcp_stream = TcpStream::connect(addr).await
+            .with_context(|| format!("Failed to connect to {}", addr))?;
+        tcp_stream.set_nodelay(true)?;
+        let connect = started.elapsed();
+
+        // TLS 핸드셰이크
+        let started = Instant::now();
+        let connector = TlsConnector::from(TlsCertificate::build_client_config(fingerprint)?);
+        let domain = rustls::pki_types::ServerName::try_from("pebble.local")
+            .map_err(|_| anyhow::anyhow!("Invalid DNS name"))?;
+        let stream = connector.connect(domain, tcp_stream).await
+            .context("TLS handshake failed")?;
+
+        anyhow::Ok((stream, connect, started.elapsed()))
+    })
+    .await;
+
+    match connected {
+        Ok(Ok((mut stream, connect, tls_handshake))) => {
+            result.reachable = true;
+            result.connect_ms = Some(millis(connect));
+            result.tls_handshake_ms = Some(millis(tls_handshake));
+
+            let rtts = ping_series(&mut stream, pings, PING_TIMEOUT).await;
+            summarize(&mut result, &rtts);
+            let _ = stream.shutdown().await;
+        }
+        Ok(Err(e)) => result.error = Some(format!("{:#}", e)),
+        Err(_) => result.error = Some(format!("No response within {}s", CONNECT_TIMEOUT.as_secs())),
+    }
+
+    save(&result)?;
+
+    tracing::info!("Probed {} at {}: reachable = {}, rtt = {:?} ms, loss = {:.0}%",
+        device_id, addr, result.reachable, result.avg_rtt_ms, result.loss_percent);
+
+    Ok(result)
+}
+
+/// Ping 왕복 시간으로 손실률과 통계를 채웁니다.
+fn summarize(result: &mut ProbeResult, rtts: &[Option<Duration>]) {
+    let answered: Vec<f64> = rtts.iter().flatten().map(|rtt| millis(*rtt)).collect();
+
+    result.pings_sent = rtts.len() as u32;
+    result.pings_lost = (rtts.len() - answered.len()) as u32;
+    result.loss_percent = if rtts.is_empty() {
+        0.0
+    } else {
+        result.pings_lost as f64 / rtts.len() as f64 * 100.0
+    };
+
+    if !answered.is_empty() {
+        result.avg_rtt_ms = Some(answered.iter().sum::<f64>() / answered.len() as f64);
+        result.min_rtt_ms = answered.iter().copied().reduce(f64::min);
+        result.max_rtt_ms = answered.iter().copied().reduce(f64::max);
+    }
+}
+
+/// 기기 ID로 현재 주소를 찾아 측정합니다.
+///
+/// # Arguments
+/// * `device_id` - 발견되었거나 이전에 본 적이 있는 기기 ID
+/// * `pings` - 보낼 Ping 수 (1 ~ `MAX_PROBE_PINGS`)
+pub async fn probe_device(device_id: &str, pings: u32) -> Result<ProbeResult> {
+    if !(1..=MAX_PROBE_PINGS).contains(&pings) {
+        return Err(PebbleError::invalid_argument(format!(
+            "Probe ping count must be between 1 and {}", MAX_PROBE_PINGS
+        )).into());
+    }
+
+    let peer = peers::resolve(device_id)?;
+    probe_address(device_id, peer.addr, peer.fingerprint, pings).await
+}
+
+/// 측정 결과를 저장합니다 (주소별로 최신 결과만 유지).
+fn save(result: &ProbeResult) -> Result<()> {
+    let conn = db::open_connection()?;
+    conn.execute(
+        "INSERT OR REPLACE INTO peer_probes
+         (device_id, address, reachable, connect_ms, tls_handshake_ms, pings_sent, pings_lost, avg_rtt_ms, min_rtt_ms, max_rtt_ms, error, probed_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            result.device_id,
+            result.address,
+            result.reachable,
+            result.connect_ms,
+            result.tls_handshake_ms,
+            result.pings_sent,
+            result.pings_lost,
+            result.avg_rtt_ms,
+            result.min_rtt_ms,
+            result.max_rtt_ms,
+            result.error,
+            result.probed_at,
+        ],
+    )?;
+    Ok(())
+}
+
+/// 기기의 주소별 최신 측정 결과를 가져옵니다.
+pub fn results(device_id: &str) -> Result<Vec<ProbeResult>> {
+    let conn = db::open_connection()?;
+    let mut stmt = conn.prepare(
+        "SELECT device_id, address, reachable, connect_ms, tls_handshake_ms, pings_sent, pings_lost,
+                avg_rtt_ms, min_rtt_ms, max_rtt_ms, error, probed_at
+         FROM peer_probes WHERE device_id = ?1 ORDER BY probed_at DESC",
+    )?;
+
+    let rows = stmt.query_map(params![device_id], |row| {
+        let reachable: bool = row.get(2)?;
+        let pings_sent: u32 = row.get(5)?;
+        let pings_lost: u32 = row.get(6)?;
+        Ok(ProbeResult {
+            device_id: row.get(0)?,
+            address: row.get(1)?,
+            reachable,
+            connect_ms: row.get(3)?,
+            tls_handshake_ms: row.get(4)?,
+            pings_sent,
+            pings_lost,
+            loss_percent: if !reachable {
+                100.0
+            } else if pings_sent == 0 {
+                0.0
+            } else {
+                pings_lost as f64 / pings_sent as f64 * 100.0
+            },
+            avg_rtt_ms: row.get(7)?,
+            min_rtt_ms: row.get(8)?,
+            max_rtt_ms: row.get(9)?,
+            error: row.get(10)?,
+            probed_at: row.get(11)?,
+        })
+    })?;
+
+    Ok(rows.collect::<rusqlite::Result<Vec<_>>>()?)
+}
+
+/// 측정 결과를 기준으로 주소를 선호 순서로 정렬합니다.
+///
+/// 도달 가능한 주소, 손실률이 낮은 주소, 왕복 시간이 짧은 주소 순이며,
+/// 측정한 적 없는 주소는 도달 가능한 주소 다음, 도달 불가 주소 앞에 원래 순서대로 둡니다.
+pub fn rank_addresses(device_id: &str, addrs: &[SocketAddr]) -> Result<Vec<SocketAddr>> {
+    let measured = results(device_id)?;
+
+    // (그룹, 손실률, 왕복 시간): 그룹 0 = 도달 가능, 1 = 미측정, 2 = 도달 불가
+    let key = |addr: &SocketAddr| -> (u8, f64, f64) {
+        match measured.iter().find(|result| result.address == addr.to_string()) {
+            Some(result) if result.reachable => (0, result.loss_percent, result.avg_rtt_ms.unwrap_or(f64::MAX)),
+            Some(_) => (2, 100.0, f64::MAX),
+            None => (1, 0.0, 0.0),
+        }
+    };
+
+    let mut ranked = addrs.to_vec();
+    ranked.sort_by(|a, b| {
+        let (a, b) = (key(a), key(b));
+        a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)).then(a.2.total_cmp(&b.2))
+    });
+    Ok(ranked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loopback;
+    use crate::api::transfer::TransferServer;
+
+    #[tokio::test]
+    async fn test_ping_series_over_loopback() {
+        let (mut client_io, server_io) = loopback::stream_pair();
+        let server = tokio::spawn(TransferServer::handle_stream(server_io, loopback::loopback_peer(), None));
+
+        let rtts = ping_series(&mut client_io, 3, PING_TIMEOUT).await;
+        assert_eq!(rtts.len(), 3);
+        assert!(rtts.iter().all(Option::is_some));
+
+        drop(client_io);
+        server.await.unwrap().unwrap();
+
+        // 응답이 없는 상대: 모두 손실
+        let (mut client_io, _silent) = loopback::stream_pair();
+        let rtts = ping_series(&mut client_io, 2, Duration::from_millis(20)).await;
+        assert_eq!(rtts, vec![None, None]);
+    }
+
+    #[test]
+    fn test_rank_addresses_prefers_fast_reachable_paths() {
+        loopback::use_temp_environment();
+        let addr = |s: &str| -> SocketAddr { s.parse().unwrap() };
+        let result = |address: &str, reachable: bool, rtt: f64, lost: u32| ProbeResult {
+            device_id: "probe-rank".to_string(),
+            address: address.to_string(),
+            reachable,
+            connect_ms: None,
+            tls_handshake_ms: None,
+            pings_sent: 10,
+            pings_lost: lost,
+            loss_percent: 0.0,
+            avg_rtt_ms: Some(rtt),
+            min_rtt_ms: None,
+            max_rtt_ms: None,
+            error: None,
+            probed_at: unix_now(),
+        };
+
+        save(&result("10.0.0.1:37846", true, 40.0, 0)).unwrap();
+        save(&result("10.0.0.2:37846", true, 2.0, 0)).unwrap();
+        save(&result("10.0.0.3:37846", true, 1.0, 5)).unwrap();
+        save(&result("10.0.0.4:37846", false, 0.0, 10)).unwrap();
+
+        let ranked = rank_addresses(
+            "probe-rank",
+            &[addr("10.0.0.4:37846"), addr("10.0.0.3:37846"), addr("10.0.0.5:37846"), addr("10.0.0.1:37846"), addr("10.0.0.2:37846")],
+        )
+        .unwrap();
+        assert_eq!(
+            ranked,
+            vec![addr("10.0.0.2:37846"), addr("10.0.0.1:37846"), addr("10.0.0.3:37846"), addr("10.0.0.5:37846"), addr("10.0.0.4:37846")]
+        );
+        assert_eq!(results("probe-rank").unwrap().len(), 4);
+    }
+}
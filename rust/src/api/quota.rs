@@ -0,0 +1,201 @@
+use anyhow::Result;
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use super::db;
+use super::error::PebbleError;
+use super::util::unix_now;
+
+/// 상대 기기별 수신 사용량과 저장 한도
+///
+/// 상대 기기는 전송 요청의 `sender_device_id`로 식별하며, 보내지 않는 구버전 기기는
+/// IP 주소로 집계합니다. 기기 ID는 송신측이 스스로 알리는 값이므로 악의적인 기기를
+/// 막는 보안 수단이 아니라 사용량 관리 용도입니다.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeerUsage {
+    /// 상대 기기 ID (또는 IP 주소)
+    pub peer_id: String,
+
+    /// 지금까지 받은 바이트 수 (중단된 전송의 부분 수신 포함)
+    pub bytes_received: u64,
+
+    /// 수신을 완료한 파일 수
+    pub files_received: u64,
+
+    /// 마지막 수신 시각 (Unix timestamp, 받은 적이 없으면 0)
+    pub last_received_at: i64,
+
+    /// 저장 한도 (bytes, None이면 무제한)
+    pub quota_bytes: Option<u64>,
+
+    /// 지금까지 보낸 바이트 수 (중단된 전송 포함, 한도와 무관)
+    pub bytes_sent: u64,
+
+    /// 송신을 완료한 파일 수
+    pub files_sent: u64,
+}
+
+impl PeerUsage {
+    /// 한도까지 남은 바이트 수 (무제한이면 None)
+    pub fn remaining_bytes(&self) -> Option<u64> {
+        self.quota_bytes.map(|quota| quota.saturating_sub(self.bytes_received))
+    }
+}
+
+/// 상대 기기의 사용량을 조회합니다.
+pub fn usage(peer_id: &str) -> Result<Option<PeerUsage>> {
+    let conn = db::open_connection()?;
+    let usage = conn
+        .query_row(
+            "SELECT peer_id, bytes_received, files_received, last_received_at, quota_bytes, bytes_sent, files_sent
+             FROM peer_usage WHERE peer_id = ?1",
+            params![peer_id],
+            from_row,
+        )
+        .optional()?;
+    Ok(usage)
+}
+
+/// 모든 상대 기기의 사용량을 받은 바이트 수가 많은 순으로 조회합니다.
+pub fn list_usage() -> Result<Vec<PeerUsage>> {
+    let conn = db::open_connection()?;
+    let mut stmt = conn.prepare(
+        "SELECT peer_id, bytes_received, files_received, last_received_at, quota_bytes, bytes_sent, files_sent
+         FROM peer_usage ORDER BY bytes_received DESC, peer_id",
+    )?;
+    let rows = stmt.query_map([], from_row)?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+fn from_row(row: &rusqlite::Row) -> rusqlite::Result<PeerUsage> {
+    Ok(PeerUsage {
+        peer_id: row.get(0)?,
+        bytes_received: row.get::<_, i64>(1)? as u64,
+        files_received: row.get::<_, i64>(2)? as u64,
+        last_received_at: row.get(3)?,
+        quota_bytes: row.get::<_, Option<i64>>(4)?.map(|q| q as u64),
+        bytes_sent: row.get::<_, i64>(5)? as u64,
+        files_sent: row.get::<_, i64>(6)? as u64,
+    })
+}
+
+/// 상대 기기의 저장 한도를 설정합니다.
+///
+/// # Arguments
+/// * `peer_id` - 상대 기기 ID
+/// * `quota_bytes` - 저장 한도 (None이면 무제한)
+pub fn set_quota(peer_id: &str, quota_bytes: Option<u64>) -> Result<()> {
+    let conn = db::open_connection()?;
+    conn.execute(
+        "INSERT INTO peer_usage (peer_id, quota_bytes) VALUES (?1, ?2)
+         ON CONFLICT(peer_id) DO UPDATE SET quota_bytes = excluded.quota_bytes",
+        params![peer_id, quota_bytes.map(|q| q as i64)],
+    )?;
+
+    tracing::info!("Quota for {} set to {:?} bytes", peer_id, quota_bytes);
+
+    Ok(())
+}
+
+/// 상대 기기의 사용량을 0으로 되돌립니다. 한도 설정은 유지됩니다.
+pub fn reset_usage(peer_id: &str) -> Result<()> {
+    let conn = db::open_connection()?;
+    conn.execute(
+        "UPDATE peer_usage SET bytes_received = 0, files_received = 0 WHERE peer_id = ?1",
+        params![peer_id],
+    )?;
+    Ok(())
+}
+
+/// 수신 전에 한도를 확인합니다.
+///
+/// # Arguments
+/// * `peer_id` - 상대 기기 ID
+/// * `incoming_bytes` - 이번 전송으로 받을 바이트 수 (이어받기면 남은 양)
+///
+/// # Returns
+/// * 한도를 넘으면 `PebbleError::QuotaExceeded`
+pub fn check(peer_id: &str, incoming_bytes: u64) -> Result<()> {
+    let Some(usage) = usage(peer_id)? else {
+        return Ok(());
+    };
+
+    match usage.remaining_bytes() {
+        Some(remaining) if incoming_bytes > remaining => Err(PebbleError::quota_exceeded(format!(
+            "{} has {} of {} bytes left, transfer needs {}",
+            peer_id,
+            remaining,
+            usage.quota_bytes.unwrap_or_default(),
+            incoming_bytes
+        ))
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+/// 받은 바이트 수를 사용량에 더합니다.
+///
+/// # Arguments
+/// * `peer_id` - 상대 기기 ID
+/// * `bytes` - 이번 연결에서 받은 바이트 수
+/// * `completed` - 파일 수신을 완료했는지 여부
+pub fn record_received(peer_id: &str, bytes: u64, completed: bool) -> Result<()> {
+    let conn = db::open_connection()?;
+    conn.execute(
+        "INSERT INTO peer_usage (peer_id, bytes_received, files_received, last_received_at)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(peer_id) DO UPDATE SET
+            bytes_received = bytes_received + excluded.bytes_received,
+            files_received = files_received + excluded.files_received,
+            last_received_at = excluded.last_received_at",
+        params![peer_id, bytes as i64, completed as i64, unix_now()],
+    )?;
+    Ok(())
+}
+
+/// 보낸 바이트 수를 사용량에 더합니다.
+///
+/// # Arguments
+/// * `peer_id` - 상대 기기 ID
+/// * `bytes` - 이번 전송에서 보낸 바이트 수
+/// * `completed` - 파일 송신을 완료했는지 여부
+pub fn record_sent(peer_id: &str, bytes: u64, completed: bool) -> Result<()> {
+    let conn = db::open_connection()?;
+    conn.execute(
+        "INSERT INTO peer_usage (peer_id, bytes_sent, files_sent) VALUES (?1, ?2, ?3)
+         ON CONFLICT(peer_id) DO UPDATE SET
+            bytes_sent = bytes_sent + excluded.bytes_sent,
+            files_sent = files_sent + excluded.files_sent",
+        params![peer_id, bytes as i64, completed as i64],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loopback;
+
+    #[test]
+    fn test_quota_enforced_after_usage() {
+        loopback::use_temp_environment();
+        let peer = "quota-test-peer";
+
+        // 한도가 없으면 항상 허용
+        record_received(peer, 700, true).unwrap();
+        assert!(check(peer, u64::MAX).is_ok());
+
+        set_quota(peer, Some(1000)).unwrap();
+        assert!(check(peer, 300).is_ok());
+        let err = check(peer, 301).unwrap_err();
+        assert!(matches!(PebbleError::from(err), PebbleError::QuotaExceeded { .. }));
+
+        let usage = usage(peer).unwrap().unwrap();
+        assert_eq!(usage.bytes_received, 700);
+        assert_eq!(usage.files_received, 1);
+        assert_eq!(usage.remaining_bytes(), Some(300));
+
+        reset_usage(peer).unwrap();
+        assert!(check(peer, 1000).is_ok());
+    }
+}
@@ -0,0 +1,213 @@
+//! 수신측이 서명한 전송 수령증 (Transfer Receipts)
+//!
+//! 파일을 받은 기기가 전체 파일 해시를 확인한 뒤 파일 해시, 크기, 받은 시각, 자신의 기기 ID를
+//! TLS 인증서의 개인 키로 서명해 보냅니다. 송신측은 서명과 인증서 핑거프린트를 확인하고
+//! 수령증을 `transfer_history`에 남겨, 파일이 온전히 도착했다는 증거로 사용합니다.
+//!
+//! # Process Flow
+//! 1. 수신측이 검증과 검사를 마치면 `sign`으로 수령증을 만들어 `TransferComplete`에 담음
+//!    (전송 서버가 실행 중이라 기기 인증서가 있고, 전체 파일 해시를 계산한 경우에만)
+//! 2. 송신측은 `verify`로 서명을 확인하고, 보낸 파일의 해시/크기와 고정된 핑거프린트를 비교
+//! 3. 확인되면 `history::set_receipt`로 전송 기록에 저장 (확인되지 않으면 버리고 전송은 성공으로 둠)
+//!
+//! # Security
+//! - 서명은 수신 기기의 인증서 키로 만들므로, 인증서 핑거프린트를 아는 누구나 나중에 다시 확인 가능
+//! - 인증서는 수령증에 함께 담기며, 핑거프린트가 연결할 때 고정한 값과 다르면 받지 않음
+
+use anyhow::{Context, Result};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::SignatureScheme;
+use serde::{Deserialize, Serialize};
+
+use super::certificate::TlsCertificate;
+use super::error::PebbleError;
+use super::util::unix_now;
+
+/// 서명에 쓰는 방식 (기기 인증서의 키 종류에 따라 선택)
+const SIGNATURE_SCHEMES: &[SignatureScheme] = &[
+    SignatureScheme::ECDSA_NISTP256_SHA256,
+    SignatureScheme::ECDSA_NISTP384_SHA384,
+    SignatureScheme::ED25519,
+    SignatureScheme::RSA_PSS_SHA256,
+    SignatureScheme::RSA_PKCS1_SHA256,
+];
+
+/// 수신측이 서명한 전송 수령증
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransferReceipt {
+    pub transfer_id: String,
+
+    /// 받은 파일의 전체 해시 (blake3)
+    pub file_hash: String,
+
+    pub file_size: u64,
+
+    /// 받은 시각 (Unix timestamp)
+    pub received_at: i64,
+
+    /// 받은 기기 ID (start_pebble 이전이면 None)
+    pub receiver_device_id: Option<String>,
+
+    /// 받은 기기의 인증서 (DER, hex)
+    pub certificate: String,
+
+    /// 서명 방식 (예: "ECDSA_NISTP256_SHA256")
+    pub signature_scheme: String,
+
+    /// 서명 (hex)
+    pub signature: String,
+}
+
+impl TransferReceipt {
+    /// 서명 대상 문자열
+    pub fn signed_data(&self) -> String {
+        format!(
+            "pebble-receipt\n{}\n{}\n{}\n{}\n{}",
+            self.transfer_id,
+            self.file_hash,
+            self.file_size,
+            self.received_at,
+            self.receiver_device_id.as_deref().unwrap_or("")
+        )
+    }
+
+    /// 받은 기기의 인증서 핑거프린트 (SHA-256)
+    pub fn fingerprint(&self) -> Result<String> {
+        let cert_der = hex::decode(&self.certificate).context("Receipt certificate is not valid hex")?;
+        TlsCertificate::calculate_fingerprint(&cert_der)
+    }
+
+    /// 수령증의 서명이 담긴 인증서의 키로 만든 것인지 확인합니다.
+    ///
+    /// # Returns
+    /// * 서명한 인증서의 핑거프린트
+    pub fn verify(&self) -> Result<String> {
+        verify_signed(&self.certificate, &self.signature_scheme, &self.signature, self.signed_data().as_bytes())
+            .map_err(|e| e.context(format!("Receipt for {} has an invalid signature", self.transfer_id)))
+    }
+
+    /// 서명과 함께 보낸 파일, 고정된 핑거프린트와 맞는지 확인합니다.
+    ///
+    /// # Arguments
+    /// * `transfer_id`, `file_hash`, `file_size` - 보낸 파일 (해시가 비어 있으면 확인할 수 없으므로 거부)
+    /// * `pinned_fingerprint` - 연결할 때 고정한 수신 기기의 핑거프린트 (없으면 비교하지 않음)
+    pub fn check(&self, transfer_id: &str, file_hash: &str, file_size: u64, pinned_fingerprint: Option<&str>) -> Result<()> {
+        if file_hash.is_empty() {
+            return Err(PebbleError::rejected(format!("No file hash to check the receipt for {} against", transfer_id)).into());
+        }
+        let fingerprint = self.verify()?;
+        if self.transfer_id != transfer_id || self.file_size != file_size || self.file_hash != file_hash {
+            return Err(PebbleError::rejected(format!("Receipt does not match the file sent in {}", transfer_id)).into());
+        }
+        if pinned_fingerprint.is_some_and(|pinned| pinned != fingerprint) {
+            return Err(PebbleError::rejected(format!("Receipt for {} is signed by another device", transfer_id)).into());
+        }
+        Ok(())
+    }
+}
+
+/// 받은 파일의 수령증을 기기 인증서로 서명합니다.
+///
+/// # Arguments
+/// * `cert` - 이 기기의 인증서 (전송 서버의 인증서)
+/// * `transfer_id` - 전송 ID
+/// * `file_hash` - 받은 뒤 계산한 전체 파일 해시
+/// * `file_size` - 파일 크기
+/// * `receiver_device_id` - 이 기기 ID
+pub fn sign(
+    cert: &TlsCertificate,
+    transfer_id: &str,
+    file_hash: &str,
+    file_size: u64,
+    receiver_device_id: Option<String>,
+) -> Result<TransferReceipt> {
+    let mut receipt = TransferReceipt {
+        transfer_id: transfer_id.to_string(),
+        file_hash: file_hash.to_string(),
+        file_size,
+        received_at: unix_now(),
+        receiver_device_id,
+        certificate: hex::encode(&cert.cert_der),
+        signature_scheme: String::new(),
+        signature: String::new(),
+    };
+    (receipt.signature_scheme, receipt.signature) = sign_with(cert, receipt.signed_data().as_bytes())?;
+    Ok(receipt)
+}
+
+/// 데이터를 기기 인증서의 개인 키로 서명합니다 (수령증, 철회 요청).
+///
+/// # Returns
+/// * (서명 방식, hex 인코딩한 서명)
+pub(crate) fn sign_with(cert: &TlsCertificate, data: &[u8]) -> Result<(String, String)> {
+    let key = PrivateKeyDer::try_from(cert.key_der.clone())
+        .map_err(|e| anyhow::anyhow!("Invalid private key: {}", e))?;
+    let signer = rustls::crypto::aws_lc_rs::sign::any_supported_type(&key)
+        .context("Unsupported certificate key")?
+        .choose_scheme(SIGNATURE_SCHEMES)
+        .context("Certificate key cannot sign")?;
+    Ok((format!("{:?}", signer.scheme()), hex::encode(signer.sign(data)?)))
+}
+
+/// 서명이 함께 담긴 인증서의 키로 만든 것인지 확인합니다.
+///
+/// # Arguments
+/// * `certificate` - 서명한 기기의 인증서 (DER, hex)
+/// * `signature_scheme` - 서명 방식 (`sign_with`가 돌려준 이름)
+/// * `signature` - 서명 (hex)
+/// * `data` - 서명한 데이터
+///
+/// # Returns
+/// * 서명한 인증서의 핑거프린트
+pub(crate) fn verify_signed(certificate: &str, signature_scheme: &str, signature: &str, data: &[u8]) -> Result<String> {
+    let cert_der = hex::decode(certificate).context("Certificate is not valid hex")?;
+    let signature = hex::decode(signature).context("Signature is not valid hex")?;
+    let scheme = SIGNATURE_SCHEMES
+        .iter()
+        .find(|scheme| format!("{:?}", scheme) == signature_scheme)
+        .ok_or_else(|| PebbleError::rejected(format!("Unsupported signature scheme: {}", signature_scheme)))?;
+
+    let cert = CertificateDer::from(cert_der.as_slice());
+    let end_entity = webpki::EndEntityCert::try_from(&cert)
+        .map_err(|e| PebbleError::rejected(format!("Invalid certificate: {}", e)))?;
+    let algorithms = rustls::crypto::aws_lc_rs::default_provider().signature_verification_algorithms;
+    let verified = algorithms
+        .mapping
+        .iter()
+        .filter(|(supported, _)| supported == scheme)
+        .flat_map(|(_, algorithms)| algorithms.iter())
+        .any(|algorithm| end_entity.verify_signature(*algorithm, data, &signature).is_ok());
+    if !verified {
+        return Err(PebbleError::rejected("Signature does not match the certificate").into());
+    }
+
+    TlsCertificate::calculate_fingerprint(&cert_der)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_receipt_signature_and_checks() {
+        let cert = TlsCertificate::generate_self_signed("receipt-device", "Receiver").unwrap();
+        let receipt = sign(&cert, "t-receipt", "abc123", 42, Some("receipt-device".to_string())).unwrap();
+
+        assert_eq!(receipt.verify().unwrap(), cert.fingerprint);
+        assert_eq!(receipt.fingerprint().unwrap(), cert.fingerprint);
+        receipt.check("t-receipt", "abc123", 42, Some(&cert.fingerprint)).unwrap();
+
+        // 보낸 파일의 해시가 없거나 다른 파일, 다른 기기의 핑거프린트, 바뀐 내용은 받지 않음
+        assert!(receipt.check("t-receipt", "", 42, None).is_err());
+        assert!(receipt.check("t-receipt", "other", 42, None).is_err());
+        assert!(receipt.check("t-receipt", "abc123", 41, None).is_err());
+        assert!(receipt.check("t-receipt", "abc123", 42, Some("00ff")).is_err());
+        let tampered = TransferReceipt { file_size: 43, ..receipt.clone() };
+        assert!(tampered.verify().is_err());
+
+        // 다른 인증서로 바꿔 담으면 서명이 맞지 않음
+        let other = TlsCertificate::generate_self_signed("other-device", "Other").unwrap();
+        let swapped = TransferReceipt { certificate: hex::encode(&other.cert_der), ..receipt };
+        assert!(swapped.verify().is_err());
+    }
+}
@@ -0,0 +1,474 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+use super::error::PebbleError;
+use super::events::{self, PebbleEvent};
+use super::integrity::HashAlgo;
+use super::metrics;
+use super::priority::TransferPriority;
+use super::transfer::TransferStatus;
+use super::util::unix_now;
+
+/// 전송 방향
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TransferDirection {
+    /// 이 기기에서 상대 기기로 송신
+    Send,
+    /// 상대 기기로부터 수신
+    Receive,
+}
+
+/// 진행 중인 전송 정보 (Dart 목록 표시용)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActiveTransfer {
+    pub transfer_id: String,
+
+    /// 상대 기기 주소 (IP:Port)
+    pub peer: String,
+
+    pub file_path: String,
+    pub direction: TransferDirection,
+    pub total_bytes: u64,
+    pub bytes_transferred: u64,
+    pub progress_percent: f64,
+    pub status: TransferStatus,
+
+    /// 전송 시작 시각 (Unix timestamp)
+    pub started_at: i64,
+
+    /// 합의된 청크 해시 알고리즘
+    pub chunk_hash_algo: HashAlgo,
+
+    /// 송신 우선순위 (수신 전송은 Normal)
+    #[serde(default)]
+    pub priority: TransferPriority,
+}
+
+/// 전송 루프에 전달되는 제어 명령
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ControlCommand {
+    Run,
+    Pause,
+    Cancel,
+}
+
+struct Entry {
+    info: ActiveTransfer,
+    control: watch::Sender<ControlCommand>,
+    /// 멈춘 소켓 읽기/쓰기까지 끊기 위한 신호 (`interrupt`)
+    interrupt: CancellationToken,
+}
+
+/// 레지스트리 항목 키 (자기 자신에게 보내면 송신과 수신이 같은 전송 ID를 씀)
+type EntryKey = (String, TransferDirection);
+
+/// 진행 중인 송수신 전송을 한곳에서 관리하는 레지스트리
+///
+/// TransferClient와 TransferServer는 전송을 시작할 때 `register`로 등록하고,
+/// 반환된 `TransferHandle`로 진행률을 갱신하며 청크마다 `checkpoint`를 호출하여
+/// 일시정지/취소 요청을 반영합니다. 핸들이 drop되면 레지스트리에서 제거됩니다.
+///
+/// 전송 ID는 같은 파일을 다시 보내면 같아지므로(`transfer::stable_transfer_id`), 같은 방향으로
+/// 이미 진행 중인 ID는 등록을 거부합니다. 제어 명령은 ID가 같은 모든 방향의 항목에 적용됩니다.
+#[derive(Clone, Default)]
+pub struct TransferRegistry {
+    entries: Arc<Mutex<HashMap<EntryKey, Entry>>>,
+}
+
+impl TransferRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 새 전송을 등록합니다.
+    ///
+    /// # Returns
+    /// * 같은 ID의 같은 방향 전송이 진행 중이면 `PebbleError::InvalidArgument`
+    pub fn register(
+        &self,
+        transfer_id: &str,
+        peer: &str,
+        file_path: &str,
+        direction: TransferDirection,
+        total_bytes: u64,
+    ) -> Result<TransferHandle> {
+        let (control, control_rx) = watch::channel(ControlCommand::Run);
+
+        let started_at = unix_now();
+
+        let info = ActiveTransfer {
+            transfer_id: transfer_id.to_string(),
+            peer: peer.to_string(),
+            file_path: file_path.to_string(),
+            direction,
+            total_bytes,
+            bytes_transferred: 0,
+            progress_percent: 0.0,
+            status: TransferStatus::Pending,
+            started_at,
+            chunk_hash_algo: HashAlgo::default(),
+            priority: TransferPriority::default(),
+        };
+
+        let interrupt = CancellationToken::new();
+        let mut entries = self
+            .entries
+            .lock()
+            .map_err(|e| anyhow::anyhow!("Failed to acquire transfer registry lock: {}", e))?;
+        let key = (transfer_id.to_string(), direction);
+        if entries.contains_key(&key) {
+            return Err(PebbleError::invalid_argument(format!("Transfer {} is already in progress", transfer_id)).into());
+        }
+        entries.insert(key, Entry { info, control, interrupt: interrupt.clone() });
+
+        Ok(TransferHandle {
+            registry: self.clone(),
+            transfer_id: transfer_id.to_string(),
+            direction,
+            control_rx,
+            interrupt,
+            error: None,
+        })
+    }
+
+    /// 같은 ID의 전송이 그 방향으로 진행 중인지 확인합니다.
+    pub fn is_active(&self, transfer_id: &str, direction: TransferDirection) -> bool {
+        self.entries
+            .lock()
+            .map(|entries| entries.contains_key(&(transfer_id.to_string(), direction)))
+            .unwrap_or(false)
+    }
+
+    /// 진행 중인 전송 목록을 시작 시각 순으로 반환합니다.
+    pub fn list(&self) -> Vec<ActiveTransfer> {
+        let mut transfers: Vec<ActiveTransfer> = self
+            .entries
+            .lock()
+            .map(|entries| entries.values().map(|entry| entry.info.clone()).collect())
+            .unwrap_or_default();
+
+        transfers.sort_by(|a, b| a.started_at.cmp(&b.started_at).then_with(|| a.transfer_id.cmp(&b.transfer_id)));
+        transfers
+    }
+
+    /// 전송을 일시정지합니다. 현재 청크가 끝난 뒤 멈춥니다.
+    pub fn pause(&self, transfer_id: &str) -> Result<()> {
+        self.send_command(transfer_id, ControlCommand::Pause)
+    }
+
+    /// 일시정지된 전송을 재개합니다.
+    pub fn resume(&self, transfer_id: &str) -> Result<()> {
+        self.send_command(transfer_id, ControlCommand::Run)
+    }
+
+    /// 전송을 취소합니다. 이미 받은 청크는 이어받기를 위해 보존됩니다.
+    pub fn cancel(&self, transfer_id: &str) -> Result<()> {
+        self.send_command(transfer_id, ControlCommand::Cancel)
+    }
+
+    /// 응답 없는 연결에서 기다리는 전송을 네트워크 에러로 끊습니다.
+    ///
+    /// 취소와 달리 청크 사이가 아니어도 즉시 끊기며, 이어받기 상태는 보존되므로
+    /// 송신측이 다시 연결하면 이어서 전송합니다.
+    pub fn interrupt(&self, transfer_id: &str) -> Result<()> {
+        let entries = self
+            .entries
+            .lock()
+            .map_err(|e| anyhow::anyhow!("Failed to acquire transfer registry lock: {}", e))?;
+
+        let matching = Self::matching(&entries, transfer_id)?;
+        for entry in matching {
+            entry.interrupt.cancel();
+        }
+
+        tracing::info!("Transfer {} interrupted", transfer_id);
+
+        Ok(())
+    }
+
+    fn send_command(&self, transfer_id: &str, command: ControlCommand) -> Result<()> {
+        let entries = self
+            .entries
+            .lock()
+            .map_err(|e| anyhow::anyhow!("Failed to acquire transfer registry lock: {}", e))?;
+
+        for entry in Self::matching(&entries, transfer_id)? {
+            entry.control.send_replace(command);
+        }
+
+        tracing::info!("Transfer {} control: {:?}", transfer_id, command);
+
+        Ok(())
+    }
+
+    /// ID가 같은 모든 방향의 항목 (없으면 `PebbleError::NotFound`)
+    fn matching<'a>(entries: &'a HashMap<EntryKey, Entry>, transfer_id: &str) -> Result<Vec<&'a Entry>> {
+        let matching: Vec<&Entry> = entries
+            .iter()
+            .filter(|((id, _), _)| id == transfer_id)
+            .map(|(_, entry)| entry)
+            .collect();
+        if matching.is_empty() {
+            return Err(PebbleError::not_found(format!("Active transfer {}", transfer_id)).into());
+        }
+        Ok(matching)
+    }
+
+    fn update<F: FnOnce(&mut ActiveTransfer)>(&self, key: &EntryKey, f: F) {
+        if let Ok(mut entries) = self.entries.lock() {
+            if let Some(entry) = entries.get_mut(key) {
+                f(&mut entry.info);
+            }
+        }
+    }
+
+    fn remove(&self, key: &EntryKey) -> Option<ActiveTransfer> {
+        self.entries
+            .lock()
+            .ok()
+            .and_then(|mut entries| entries.remove(key))
+            .map(|entry| entry.info)
+    }
+}
+
+/// 레지스트리에 등록된 전송 하나에 대한 핸들
+pub struct TransferHandle {
+    registry: TransferRegistry,
+    transfer_id: String,
+    direction: TransferDirection,
+    control_rx: watch::Receiver<ControlCommand>,
+    interrupt: CancellationToken,
+    error: Option<String>,
+}
+
+impl TransferHandle {
+    fn key(&self) -> EntryKey {
+        (self.transfer_id.clone(), self.direction)
+    }
+
+    /// 전송 상태를 갱신합니다.
+    pub fn set_status(&self, status: TransferStatus) {
+        self.registry.update(&self.key(), |info| info.status = status);
+    }
+
+    /// 합의된 청크 해시 알고리즘을 기록합니다.
+    pub fn set_chunk_hash_algo(&self, algo: HashAlgo) {
+        self.registry.update(&self.key(), |info| info.chunk_hash_algo = algo);
+    }
+
+    /// 송신 우선순위를 기록합니다.
+    pub fn set_priority(&self, priority: TransferPriority) {
+        self.registry.update(&self.key(), |info| info.priority = priority);
+    }
+
+    /// 전송된 바이트 수를 갱신합니다.
+    pub fn set_progress(&self, bytes_transferred: u64) {
+        self.registry.update(&self.key(), |info| {
+            info.bytes_transferred = bytes_transferred;
+            info.progress_percent = if info.total_bytes == 0 {
+                100.0
+            } else {
+                (bytes_transferred as f64 / info.total_bytes as f64) * 100.0
+            };
+        });
+    }
+
+    /// 전송을 중단시킨 에러를 기록합니다. 핸들이 drop될 때 실패 이벤트에 포함됩니다.
+    pub fn set_error(&mut self, error: &anyhow::Error) {
+        self.error = Some(format!("{:#}", error));
+    }
+
+    /// 레지스트리에 등록된 현재 전송 정보
+    pub fn info(&self) -> Option<ActiveTransfer> {
+        self.registry
+            .entries
+            .lock()
+            .ok()
+            .and_then(|entries| entries.get(&self.key()).map(|entry| entry.info.clone()))
+    }
+
+    /// 현재까지 전송된 바이트 수 (이어받기 이전 분량 포함)
+    pub fn bytes_transferred(&self) -> u64 {
+        self.registry
+            .entries
+            .lock()
+            .ok()
+            .and_then(|entries| entries.get(&self.key()).map(|entry| entry.info.bytes_transferred))
+            .unwrap_or(0)
+    }
+
+    /// `TransferRegistry::interrupt`로 끊으라는 요청을 받으면 완료되는 토큰
+    pub fn interrupted(&self) -> CancellationToken {
+        self.interrupt.clone()
+    }
+
+    /// 청크 사이에서 호출하여 제어 명령을 반영합니다.
+    ///
+    /// 일시정지 상태면 재개 또는 취소될 때까지 대기하고,
+    /// 취소되었으면 `PebbleError::Cancelled`를 반환합니다.
+    pub async fn checkpoint(&mut self) -> Result<()> {
+        loop {
+            let command = *self.control_rx.borrow_and_update();
+
+            match command {
+                ControlCommand::Run => {
+                    self.set_status(TransferStatus::InProgress);
+                    return Ok(());
+                }
+                ControlCommand::Cancel => {
+                    self.set_status(TransferStatus::Cancelled);
+                    return Err(PebbleError::cancelled(format!("Transfer {} was cancelled", self.transfer_id)).into());
+                }
+                ControlCommand::Pause => {
+                    self.set_status(TransferStatus::Paused);
+                    if self.control_rx.changed().await.is_err() {
+                        return Err(PebbleError::internal("Transfer control channel closed").into());
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Drop for TransferHandle {
+    fn drop(&mut self) {
+        if let Some(info) = self.registry.remove(&self.key()) {
+            metrics::record_transfer_finished(info.direction, info.status.clone());
+            events::emit(finished_event(info, self.error.take()));
+        }
+    }
+}
+
+/// 레지스트리에서 빠진 전송의 최종 상태로 알림 이벤트를 만듭니다.
+///
+/// Completed가 아닌 채로 핸들이 drop되었으면 중간에 에러로 빠져나간 것이므로 실패로 봅니다.
+fn finished_event(info: ActiveTransfer, error: Option<String>) -> PebbleEvent {
+    match info.status {
+        TransferStatus::Completed => {
+            let now = unix_now();
+
+            PebbleEvent::TransferCompleted {
+                transfer_id: info.transfer_id,
+                direction: info.direction,
+                peer: info.peer,
+                file_path: info.file_path,
+                total_bytes: info.total_bytes,
+                elapsed_secs: now.saturating_sub(info.started_at).max(0) as u64,
+            }
+        }
+        status => PebbleEvent::TransferFailed {
+            transfer_id: info.transfer_id,
+            direction: info.direction,
+            peer: info.peer,
+            file_path: info.file_path,
+            status: if status == TransferStatus::Cancelled { status } else { TransferStatus::Failed },
+            bytes_transferred: info.bytes_transferred,
+            error,
+        },
+    }
+}
+
+/// 전역 전송 레지스트리
+static REGISTRY: once_cell::sync::Lazy<TransferRegistry> = once_cell::sync::Lazy::new(TransferRegistry::new);
+
+/// 전역 전송 레지스트리를 반환합니다.
+pub fn global() -> &'static TransferRegistry {
+    &REGISTRY
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_register_and_drop() {
+        let registry = TransferRegistry::new();
+        let handle = registry.register("t1", "10.0.0.2:37846", "/tmp/a.bin", TransferDirection::Send, 200).unwrap();
+
+        handle.set_progress(50);
+        let list = registry.list();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].bytes_transferred, 50);
+        assert_eq!(list[0].progress_percent, 25.0);
+
+        drop(handle);
+        assert!(registry.list().is_empty());
+    }
+
+    #[test]
+    fn test_unfinished_transfer_reported_as_failed() {
+        let registry = TransferRegistry::new();
+        let handle = registry.register("t3", "peer", "file", TransferDirection::Send, 10).unwrap();
+        handle.set_status(TransferStatus::InProgress);
+        let info = registry.list().remove(0);
+
+        match finished_event(info.clone(), Some("connection reset".to_string())) {
+            PebbleEvent::TransferFailed { status, error, .. } => {
+                assert_eq!(status, TransferStatus::Failed);
+                assert_eq!(error.as_deref(), Some("connection reset"));
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+
+        let completed = ActiveTransfer { status: TransferStatus::Completed, ..info };
+        assert!(matches!(finished_event(completed, None), PebbleEvent::TransferCompleted { total_bytes: 10, .. }));
+    }
+
+    #[tokio::test]
+    async fn test_pause_resume_cancel() {
+        let registry = TransferRegistry::new();
+        let mut handle = registry.register("t2", "peer", "file", TransferDirection::Receive, 10).unwrap();
+
+        handle.checkpoint().await.unwrap();
+        registry.pause("t2").unwrap();
+
+        let waiter = tokio::spawn(async move {
+            let result = handle.checkpoint().await;
+            (handle, result)
+        });
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(registry.list()[0].status, TransferStatus::Paused);
+
+        registry.resume("t2").unwrap();
+        let (mut handle, result) = waiter.await.unwrap();
+        assert!(result.is_ok());
+
+        registry.cancel("t2").unwrap();
+        let err = handle.checkpoint().await.unwrap_err();
+        assert!(matches!(PebbleError::from(err), PebbleError::Cancelled { .. }));
+    }
+
+    #[test]
+    fn test_duplicate_registration_is_rejected() {
+        let registry = TransferRegistry::new();
+        let first = registry.register("t4", "peer", "file", TransferDirection::Send, 10).unwrap();
+        let err = registry.register("t4", "peer", "file", TransferDirection::Send, 10).err().unwrap();
+        assert!(matches!(PebbleError::from(err), PebbleError::InvalidArgument { .. }));
+
+        // 자기 자신에게 보내면 같은 ID로 수신도 등록되고, 명령은 양쪽에 적용됨
+        let receiving = registry.register("t4", "peer", "file", TransferDirection::Receive, 10).unwrap();
+        registry.pause("t4").unwrap();
+        assert!(registry.list().iter().all(|transfer| transfer.transfer_id == "t4"));
+        assert_eq!(registry.list().len(), 2);
+
+        // 한쪽이 끝나도 다른 쪽 항목은 남음
+        drop(receiving);
+        assert!(registry.is_active("t4", TransferDirection::Send));
+        assert!(!registry.is_active("t4", TransferDirection::Receive));
+        drop(first);
+        assert!(registry.list().is_empty());
+    }
+
+    #[test]
+    fn test_unknown_transfer() {
+        let registry = TransferRegistry::new();
+        let err = registry.cancel("missing").unwrap_err();
+        assert!(matches!(PebbleError::from(err), PebbleError::NotFound { .. }));
+    }
+}
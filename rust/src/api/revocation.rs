@@ -0,0 +1,725 @@
+//! 분실한 기기의 신뢰 철회 (Remote Revocation)
+//!
+//! 기기를 잃어버리거나 도난당했을 때, 남은 기기 중 아무 기기에서나 그 기기의 신뢰를 철회합니다.
+//! 이 기기의 인증서 키로 서명한 `RevokeMe` 메시지를 다른 기기들에 보내, 모두가 그 기기의 기록(주소,
+//! 고정된 인증서 핑거프린트, 별명, 공유 권한)을 지우고 이후 비콘과 요청을 무시하게 합니다.
+//! 잃어버린 기기가 메시지를 받으면 사용자에게 확인을 받은 뒤 페어링을 지웁니다
+//! (`WipeRequested` → `confirm_wipe` → `PairingWiped`).
+//!
+//! # Process Flow
+//! 1. `revoke_device`: 철회할 기기의 고정된 핑거프린트를 담아 철회 요청을 만들고 인증서 키로 서명
+//! 2. 이 기기에 먼저 적용 (`revoked_devices`에 기록, 기기 기록 삭제, `DeviceRevoked` 이벤트)
+//! 3. 알려진 다른 기기들과 잃어버린 기기에 `RevokeMe` 전송
+//!    → 받은 기기는 서명을 발급 기기에 고정된 핑거프린트와 비교하고 발급 시각을 확인해 적용한 뒤 응답
+//!    → 잃어버린 기기는 `wipe_requests`에 남기고 사용자 확인을 기다림 (`WipeRequested`)
+//!    → 응답을 받으면 `revocation_deliveries`에 기록하고 `RevocationConfirmed` 이벤트
+//! 4. 받지 못한 기기는 다음에 발견될 때(`DevicePaired`) 철회 요청을 가진 기기가 다시 보냄 (`run`)
+//!
+//! # Security
+//! - 철회 요청은 발급 기기의 인증서 키로 서명하며, 받는 기기는 그 기기에 고정된 핑거프린트로 확인
+//!   (비밀 키는 잃어버린 기기도 가지고 있으므로 서명에 쓰지 않음, 고정되지 않은 기기의 요청은 거부)
+//! - 이미 철회된 기기가 발급한 요청은 받지 않음
+//! - 오래된 요청(`MAX_AGE_SECS`)은 받지 않으며, 같은 요청 ID는 한 번만 적용 (복원 후 재생 방지)
+//! - 이 기기가 철회 대상이어도 사용자가 확인하기 전에는 페어링을 지우지 않음
+//! - 잃어버린 기기도 비밀 키를 가지고 있으므로, 철회 후에는 남은 기기들의 비밀 키를 바꾸는 것을 권장
+//! - 철회한 기기의 핑거프린트와 같은 인증서로 알리는 비콘과 연결(TLS 클라이언트 인증서)은
+//!   다른 기기 ID를 알려도 거부 (`check_peer`)
+
+use anyhow::Result;
+use futures::future::join_all;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use super::db;
+use super::discovery;
+use super::error::PebbleError;
+use super::events::{self, PebbleEvent};
+use super::peers;
+use super::receipts;
+use super::service;
+use super::transfer::TransferClient;
+use super::util::unix_now;
+
+/// 받아들이는 철회 요청의 최대 나이 (30일, 꺼져 있던 기기에 늦게 전달되는 경우 포함)
+pub const MAX_AGE_SECS: i64 = 30 * 24 * 60 * 60;
+
+/// 발급 시각이 미래인 요청을 허용하는 범위 (기기 간 시계 차이)
+const CLOCK_SKEW_SECS: i64 = 5 * 60;
+
+/// 서명된 철회 요청
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Revocation {
+    pub revocation_id: String,
+
+    /// 신뢰를 철회할 기기 ID
+    pub device_id: String,
+
+    /// 철회할 기기의 고정된 인증서 핑거프린트 (발급한 기기가 알고 있었으면)
+    pub fingerprint: Option<String>,
+
+    /// 철회를 요청한 기기 ID
+    pub issued_by: String,
+
+    /// 발급 시각 (Unix timestamp)
+    pub issued_at: i64,
+
+    /// 발급 기기의 인증서 (DER, hex)
+    #[serde(default)]
+    pub certificate: String,
+
+    /// 서명 방식 (예: "ECDSA_NISTP256_SHA256")
+    #[serde(default)]
+    pub signature_scheme: String,
+
+    /// 위 필드를 발급 기기의 인증서 키로 만든 서명 (hex)
+    pub signature: String,
+}
+
+impl Revocation {
+    /// 서명할 내용
+    pub fn signed_data(&self) -> String {
+        format!(
+            "{}:{}:{}:{}:{}",
+            self.revocation_id,
+            self.device_id,
+            self.fingerprint.as_deref().unwrap_or_default(),
+            self.issued_by,
+            self.issued_at
+        )
+    }
+
+    /// 서명이 담긴 인증서의 키로 만든 것인지 확인합니다.
+    ///
+    /// # Returns
+    /// * 서명한 인증서의 핑거프린트
+    pub fn verify(&self) -> Result<String> {
+        receipts::verify_signed(&self.certificate, &self.signature_scheme, &self.signature, self.signed_data().as_bytes())
+            .map_err(|e| e.context(format!("Revocation {} has an invalid signature", self.revocation_id)))
+    }
+}
+
+/// 받은 철회 요청의 처리 결과
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevocationOutcome {
+    /// 처음 받아 적용함
+    Applied,
+    /// 이미 받은 요청 (다른 기기가 먼저 전달함)
+    AlreadyKnown,
+    /// 이 기기가 철회 대상 (응답 후 `request_wipe`로 사용자 확인을 요청해야 함)
+    ThisDevice,
+}
+
+/// 철회 요청 결과
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RevocationReport {
+    pub revocation: Revocation,
+
+    /// 적용을 확인한 기기 ID
+    pub confirmed: Vec<String>,
+
+    /// 아직 전달하지 못한 기기 ID (다음에 발견되면 다시 보냄)
+    pub pending: Vec<String>,
+
+    /// 잃어버린 기기에도 전달되었는지 여부 (그 기기의 사용자가 확인하면 페어링을 지움)
+    pub lost_device_notified: bool,
+}
+
+const SELECT_COLUMNS: &str =
+    "revocation_id, device_id, fingerprint, issued_by, issued_at, certificate, signature_scheme, signature";
+
+fn from_row(row: &Row) -> rusqlite::Result<Revocation> {
+    Ok(Revocation {
+        revocation_id: row.get(0)?,
+        device_id: row.get(1)?,
+        fingerprint: row.get(2)?,
+        issued_by: row.get(3)?,
+        issued_at: row.get(4)?,
+        certificate: row.get(5)?,
+        signature_scheme: row.get(6)?,
+        signature: row.get(7)?,
+    })
+}
+
+/// 연결한 기기가 철회되지 않았고, 알린 기기 ID가 그 연결의 인증서와 맞는지 확인합니다.
+///
+/// 요청의 기기 ID는 송신측이 스스로 알리는 값이므로, 철회 여부는 TLS 클라이언트 인증서의
+/// 핑거프린트로 확인합니다. 인증서를 고정한 기기의 ID를 알리면서 다른 인증서로 연결하거나
+/// 인증서 없이 연결한 요청도 거부합니다.
+///
+/// # Arguments
+/// * `sender_device_id` - 요청에 담긴 송신 기기 ID
+/// * `peer_fingerprint` - 연결의 TLS 클라이언트 인증서 핑거프린트 (인증서가 없으면 None)
+pub fn check_peer(sender_device_id: Option<&str>, peer_fingerprint: Option<&str>) -> Result<()> {
+    let sender = sender_device_id.unwrap_or_default();
+    if is_revoked(sender, peer_fingerprint)? {
+        return Err(PebbleError::rejected(format!("Device {} has been revoked", sender)).into());
+    }
+    if sender.is_empty() {
+        return Ok(());
+    }
+    match peers::pinned_fingerprint(sender)? {
+        Some(pinned) if peer_fingerprint != Some(pinned.as_str()) => {
+            Err(PebbleError::rejected(format!("Connection is not authenticated as device {}", sender)).into())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// 기기나 인증서가 철회되었는지 확인합니다.
+///
+/// # Arguments
+/// * `device_id` - 기기 ID
+/// * `fingerprint` - 그 기기가 알린 인증서 핑거프린트 (있으면 철회한 기기의 핑거프린트와도 비교)
+pub fn is_revoked(device_id: &str, fingerprint: Option<&str>) -> Result<bool> {
+    let conn = db::open_connection()?;
+    let revoked = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM revoked_devices
+                        WHERE restored_at IS NULL AND (device_id = ?1 OR (?2 IS NOT NULL AND fingerprint = ?2)))",
+        params![device_id, fingerprint],
+        |row| row.get(0),
+    )?;
+    Ok(revoked)
+}
+
+/// 철회된 기기 목록을 철회한 순서대로 가져옵니다 (복원한 기기 제외).
+pub fn list() -> Result<Vec<Revocation>> {
+    let conn = db::open_connection()?;
+    let revocations = conn
+        .prepare(&format!(
+            "SELECT {} FROM revoked_devices WHERE restored_at IS NULL ORDER BY revoked_at, rowid",
+            SELECT_COLUMNS
+        ))?
+        .query_map([], from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(revocations)
+}
+
+/// 이 기기에서만 철회를 취소합니다 (잘못 철회했거나 기기를 되찾은 경우).
+///
+/// 다른 기기에는 전달되지 않으며, 다음 검증된 비콘에서 다시 기기로 기록됩니다.
+///
+/// # Returns
+/// * 철회된 기기였으면 true
+pub fn restore(device_id: &str) -> Result<bool> {
+    let conn = db::open_connection()?;
+    let restored = conn.execute(
+        "UPDATE revoked_devices SET restored_at = ?2 WHERE device_id = ?1 AND restored_at IS NULL",
+        params![device_id, unix_now()],
+    )?;
+    if restored > 0 {
+        tracing::info!("Restored trust in revoked device {}", device_id);
+    }
+    Ok(restored > 0)
+}
+
+/// 철회 요청을 검사하고 이 기기에 적용합니다 (서명은 호출한 쪽에서 확인).
+fn accept(revocation: &Revocation, own_device_id: Option<&str>, now: i64) -> Result<RevocationOutcome> {
+    if revocation.issued_at > now + CLOCK_SKEW_SECS || now - revocation.issued_at > MAX_AGE_SECS {
+        return Err(PebbleError::rejected(format!("Revocation {} has expired", revocation.revocation_id)).into());
+    }
+    if revocation.device_id == revocation.issued_by {
+        return Err(PebbleError::rejected("A device cannot revoke itself").into());
+    }
+    if own_device_id == Some(revocation.device_id.as_str()) {
+        return Ok(RevocationOutcome::ThisDevice);
+    }
+
+    let mut conn = db::open_connection()?;
+    let tx = conn.transaction()?;
+    let inserted = tx.execute(
+        "INSERT OR IGNORE INTO revoked_devices
+            (revocation_id, device_id, fingerprint, issued_by, issued_at, certificate, signature_scheme, signature, revoked_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            revocation.revocation_id,
+            revocation.device_id,
+            revocation.fingerprint,
+            revocation.issued_by,
+            revocation.issued_at,
+            revocation.certificate,
+            revocation.signature_scheme,
+            revocation.signature,
+            now,
+        ],
+    )?;
+    if inserted == 0 {
+        return Ok(RevocationOutcome::AlreadyKnown);
+    }
+    forget_peer(&tx, &revocation.device_id)?;
+    tx.commit()?;
+
+    discovery::forget_device(&revocation.device_id);
+    tracing::warn!("Revoked trust in {} (requested by {})", revocation.device_id, revocation.issued_by);
+    events::emit(PebbleEvent::DeviceRevoked {
+        device_id: revocation.device_id.clone(),
+        issued_by: revocation.issued_by.clone(),
+    });
+    Ok(RevocationOutcome::Applied)
+}
+
+/// 기기의 주소, 고정된 핑거프린트, 별명, 측정 결과, 공유 권한, 클립보드 동기화 설정을 지웁니다.
+fn forget_peer(conn: &Connection, device_id: &str) -> Result<()> {
+    conn.execute("DELETE FROM peers WHERE device_id = ?1", params![device_id])?;
+    conn.execute("DELETE FROM peer_addresses WHERE device_id = ?1", params![device_id])?;
+    conn.execute("DELETE FROM peer_probes WHERE device_id = ?1", params![device_id])?;
+    conn.execute("DELETE FROM shares WHERE peer_device_id = ?1", params![device_id])?;
+    conn.execute("DELETE FROM clipboard_peers WHERE device_id = ?1", params![device_id])?;
+    Ok(())
+}
+
+/// 상대 기기가 보낸 철회 요청을 확인하고 적용합니다.
+///
+/// # Returns
+/// * 처리 결과 (`ThisDevice`면 응답한 뒤 `request_wipe` 호출)
+///
+/// # Security
+/// - 서명한 인증서가 발급 기기에 고정된 핑거프린트와 같아야 함 (고정되지 않았으면 거부)
+/// - 이미 철회된 기기가 발급한 요청은 거부
+pub fn receive(revocation: &Revocation) -> Result<RevocationOutcome> {
+    verify_issuer(revocation)?;
+    accept(revocation, service::device_id().as_deref(), unix_now())
+}
+
+/// 철회 요청이 발급 기기의 고정된 인증서 키로 서명되었는지 확인합니다.
+fn verify_issuer(revocation: &Revocation) -> Result<()> {
+    let pinned = peers::pinned_fingerprint(&revocation.issued_by)?.ok_or_else(|| {
+        PebbleError::rejected(format!("No pinned certificate for {}", revocation.issued_by))
+    })?;
+    let fingerprint = revocation.verify()?;
+    if fingerprint != pinned {
+        return Err(PebbleError::rejected(format!(
+            "Revocation {} is not signed by {}",
+            revocation.revocation_id, revocation.issued_by
+        ))
+        .into());
+    }
+    if is_revoked(&revocation.issued_by, Some(&fingerprint))? {
+        return Err(PebbleError::rejected(format!("{} has been revoked", revocation.issued_by)).into());
+    }
+    Ok(())
+}
+
+/// 이 기기를 철회한다는 요청을 남기고 사용자에게 확인을 요청합니다 (`WipeRequested`).
+///
+/// 서명이 맞는 요청이어도 페어링은 사용자가 `confirm_wipe`로 확인한 뒤에만 지웁니다.
+pub fn request_wipe(revocation: &Revocation) -> Result<()> {
+    let conn = db::open_connection()?;
+    conn.execute(
+        "INSERT OR IGNORE INTO wipe_requests
+            (revocation_id, device_id, fingerprint, issued_by, issued_at, certificate, signature_scheme, signature, received_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            revocation.revocation_id,
+            revocation.device_id,
+            revocation.fingerprint,
+            revocation.issued_by,
+            revocation.issued_at,
+            revocation.certificate,
+            revocation.signature_scheme,
+            revocation.signature,
+            unix_now(),
+        ],
+    )?;
+
+    tracing::warn!("This device was revoked by {}, waiting for the user to confirm", revocation.issued_by);
+    events::emit(PebbleEvent::WipeRequested {
+        revocation_id: revocation.revocation_id.clone(),
+        issued_by: revocation.issued_by.clone(),
+    });
+    Ok(())
+}
+
+/// 사용자 확인을 기다리는 이 기기의 철회 요청 목록 (받은 순서)
+pub fn pending_wipes() -> Result<Vec<Revocation>> {
+    let conn = db::open_connection()?;
+    let revocations = conn
+        .prepare(&format!("SELECT {} FROM wipe_requests ORDER BY received_at, rowid", SELECT_COLUMNS))?
+        .query_map([], from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(revocations)
+}
+
+fn take_wipe_request(revocation_id: &str) -> Result<Option<Revocation>> {
+    let conn = db::open_connection()?;
+    let revocation = conn
+        .query_row(
+            &format!("SELECT {} FROM wipe_requests WHERE revocation_id = ?1", SELECT_COLUMNS),
+            params![revocation_id],
+            from_row,
+        )
+        .optional()?;
+    conn.execute("DELETE FROM wipe_requests WHERE revocation_id = ?1", params![revocation_id])?;
+    Ok(revocation)
+}
+
+/// 사용자가 확인한 철회 요청에 따라 페어링을 지웁니다.
+///
+/// # Returns
+/// * 확인을 기다리던 요청이 없으면 NotFound 에러
+pub async fn confirm_wipe(revocation_id: &str) -> Result<()> {
+    let revocation = take_wipe_request(revocation_id)?
+        .ok_or_else(|| PebbleError::not_found(format!("Wipe request {}", revocation_id)))?;
+    wipe_pairing(&revocation).await
+}
+
+/// 철회 요청을 무시하고 페어링을 유지합니다 (기기를 잃어버리지 않은 경우).
+///
+/// # Returns
+/// * 확인을 기다리던 요청이었으면 true
+pub fn dismiss_wipe(revocation_id: &str) -> Result<bool> {
+    let dismissed = take_wipe_request(revocation_id)?.is_some();
+    if dismissed {
+        tracing::info!("Dismissed wipe request {}", revocation_id);
+    }
+    Ok(dismissed)
+}
+
+/// 이 기기가 철회 대상이 되었을 때 모든 페어링 기록을 지우고 기기 탐색을 멈춥니다.
+///
+/// 비밀 키는 앱이 가지고 있으므로 `PairingWiped` 이벤트를 받은 앱이 지워야 합니다.
+async fn wipe_pairing(revocation: &Revocation) -> Result<()> {
+    tracing::warn!("This device was revoked by {}, wiping pairing", revocation.issued_by);
+    let conn = db::open_connection()?;
+    conn.execute_batch(
+        "BEGIN;
+         DELETE FROM peers;
+         DELETE FROM peer_addresses;
+         DELETE FROM peer_probes;
+         DELETE FROM shares;
+         DELETE FROM clipboard_peers;
+         DELETE FROM wipe_requests;
+         COMMIT;",
+    )?;
+    discovery::stop_discovery().await?;
+
+    events::emit(PebbleEvent::PairingWiped { issued_by: revocation.issued_by.clone() });
+    Ok(())
+}
+
+/// 아직 전달하지 못한 기기 ID (알려진 기기 중 발급 기기와 철회 대상 제외)
+fn pending_peers(revocation: &Revocation) -> Result<Vec<String>> {
+    let conn = db::open_connection()?;
+    let peers = conn
+        .prepare(
+            "SELECT device_id FROM peers
+             WHERE device_id != ?2 AND device_id != ?3
+               AND device_id NOT IN (SELECT peer_device_id FROM revocation_deliveries WHERE revocation_id = ?1)
+             ORDER BY device_id",
+        )?
+        .query_map(params![revocation.revocation_id, revocation.device_id, revocation.issued_by], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    Ok(peers)
+}
+
+/// 기기에 아직 전달하지 못한 철회 요청
+fn pending_for(peer_device_id: &str) -> Result<Vec<Revocation>> {
+    let conn = db::open_connection()?;
+    let revocations = conn
+        .prepare(&format!(
+            "SELECT {} FROM revoked_devices
+             WHERE restored_at IS NULL AND device_id != ?1 AND issued_by != ?1
+               AND revocation_id NOT IN (SELECT revocation_id FROM revocation_deliveries WHERE peer_device_id = ?1)
+             ORDER BY revoked_at, rowid",
+            SELECT_COLUMNS
+        ))?
+        .query_map(params![peer_device_id], from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(revocations)
+}
+
+fn mark_delivered(revocation: &Revocation, peer_device_id: &str) -> Result<()> {
+    let conn = db::open_connection()?;
+    conn.execute(
+        "INSERT OR IGNORE INTO revocation_deliveries (revocation_id, peer_device_id, delivered_at) VALUES (?1, ?2, ?3)",
+        params![revocation.revocation_id, peer_device_id, unix_now()],
+    )?;
+    Ok(())
+}
+
+/// 철회 요청을 기기 하나에 보냅니다.
+async fn send(revocation: &Revocation, peer: &peers::ResolvedPeer) -> Result<()> {
+    TransferClient::new(peer.fingerprint.clone()).send_revocation(&peer.addrs, revocation).await
+}
+
+/// 철회 요청을 기기 하나에 보내고 확인되면 기록합니다.
+async fn deliver(revocation: &Revocation, peer_device_id: &str) -> Result<()> {
+    let peer = peers::resolve(peer_device_id)?;
+    send(revocation, &peer).await?;
+    mark_delivered(revocation, peer_device_id)?;
+
+    tracing::info!("{} confirmed revocation of {}", peer_device_id, revocation.device_id);
+    events::emit(PebbleEvent::RevocationConfirmed {
+        device_id: revocation.device_id.clone(),
+        peer_device_id: peer_device_id.to_string(),
+    });
+    Ok(())
+}
+
+/// 기기의 신뢰를 철회하고 다른 기기들에 알립니다.
+///
+/// # Arguments
+/// * `device_id` - 잃어버린 기기 ID
+///
+/// # Returns
+/// * 확인한 기기와 아직 전달하지 못한 기기 (전달하지 못한 기기는 다음에 발견되면 다시 보냄)
+///
+/// # Security
+/// - `start_pebble`로 시작되어 전송 서버의 인증서 키로 서명할 수 있어야 함
+pub async fn revoke_device(device_id: &str) -> Result<RevocationReport> {
+    let own_device_id = service::device_id()
+        .ok_or_else(|| PebbleError::invalid_argument("Start Pebble before revoking a device"))?;
+    if own_device_id == device_id {
+        return Err(PebbleError::invalid_argument("Cannot revoke this device").into());
+    }
+
+    // 기록을 지우기 전에 잃어버린 기기의 주소와 핑거프린트를 찾아 둠
+    let lost_device = peers::resolve(device_id).ok();
+    let mut revocation = Revocation {
+        revocation_id: Uuid::new_v4().to_string(),
+        device_id: device_id.to_string(),
+        fingerprint: peers::pinned_fingerprint(device_id)?,
+        issued_by: own_device_id.clone(),
+        issued_at: unix_now(),
+        certificate: String::new(),
+        signature_scheme: String::new(),
+        signature: String::new(),
+    };
+    let cert = service::certificate()
+        .ok_or_else(|| PebbleError::invalid_argument("Start the transfer server before revoking a device"))?;
+    revocation.certificate = hex::encode(&cert.cert_der);
+    (revocation.signature_scheme, revocation.signature) = receipts::sign_with(&cert, revocation.signed_data().as_bytes())?;
+    accept(&revocation, Some(&own_device_id), revocation.issued_at)?;
+
+    let peers = pending_peers(&revocation)?;
+    let results = join_all(peers.iter().map(|peer| deliver(&revocation, peer))).await;
+    let mut confirmed = Vec::new();
+    let mut pending = Vec::new();
+    for (peer, result) in peers.into_iter().zip(results) {
+        match result {
+            Ok(()) => confirmed.push(peer),
+            Err(e) => {
+                tracing::info!("Revocation of {} not yet delivered to {}: {:#}", device_id, peer, e);
+                pending.push(peer);
+            }
+        }
+    }
+
+    let lost_device_notified = match &lost_device {
+        Some(peer) => match send(&revocation, peer).await {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::info!("Could not reach revoked device {}: {:#}", device_id, e);
+                false
+            }
+        },
+        None => false,
+    };
+
+    Ok(RevocationReport { revocation, confirmed, pending, lost_device_notified })
+}
+
+/// 다시 발견된 기기에 아직 전달하지 못한 철회 요청을 보냅니다.
+async fn deliver_pending(peer_device_id: String) {
+    let revocations = match pending_for(&peer_device_id) {
+        Ok(revocations) => revocations,
+        Err(e) => {
+            tracing::warn!("Failed to load pending revocations: {:#}", e);
+            return;
+        }
+    };
+    for revocation in revocations {
+        if let Err(e) = deliver(&revocation, &peer_device_id).await {
+            tracing::info!("Revocation of {} not yet delivered to {}: {:#}", revocation.device_id, peer_device_id, e);
+        }
+    }
+}
+
+/// 기기가 발견될 때마다 전달하지 못한 철회 요청을 보냅니다 (서비스 실행 중 백그라운드 작업).
+pub async fn run(token: CancellationToken) -> Result<()> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    events::subscribe(move |event| match event {
+        PebbleEvent::DevicePaired { device_id, .. } => tx.send(device_id.clone()).is_ok(),
+        _ => !tx.is_closed(),
+    });
+
+    loop {
+        tokio::select! {
+            _ = token.cancelled() => return Ok(()),
+            device_id = rx.recv() => match device_id {
+                Some(device_id) => {
+                    tokio::spawn(deliver_pending(device_id));
+                }
+                None => return Ok(()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::certificate::TlsCertificate;
+
+    fn revocation(id: &str, device_id: &str, issued_by: &str, issued_at: i64) -> Revocation {
+        Revocation {
+            revocation_id: id.to_string(),
+            device_id: device_id.to_string(),
+            fingerprint: Some("ab".repeat(32)),
+            issued_by: issued_by.to_string(),
+            issued_at,
+            certificate: String::new(),
+            signature_scheme: String::new(),
+            signature: "sig".to_string(),
+        }
+    }
+
+    fn signed(revocation: Revocation, cert: &TlsCertificate) -> Revocation {
+        let mut revocation = Revocation { certificate: hex::encode(&cert.cert_der), ..revocation };
+        (revocation.signature_scheme, revocation.signature) =
+            receipts::sign_with(cert, revocation.signed_data().as_bytes()).unwrap();
+        revocation
+    }
+
+    fn pin(device_id: &str, fingerprint: &str) {
+        add_peer(device_id);
+        let conn = db::open_connection().unwrap();
+        conn.execute("UPDATE peers SET pinned_fingerprint = ?2 WHERE device_id = ?1", params![device_id, fingerprint]).unwrap();
+    }
+
+    fn add_peer(device_id: &str) {
+        let conn = db::open_connection().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO peers (device_id, device_name, last_address, last_seen) VALUES (?1, ?1, '10.0.0.9', 0)",
+            params![device_id],
+        ).unwrap();
+    }
+
+    #[test]
+    fn test_revocation_drops_peer_and_blocks_fingerprint() {
+        crate::loopback::use_temp_environment();
+        add_peer("rv-lost");
+        peers::set_label("rv-lost", &peers::PeerLabel { nickname: Some("Stolen phone".to_string()), ..Default::default() }).unwrap();
+        add_peer("rv-desktop");
+
+        let now = unix_now();
+        let lost = revocation("rv-1", "rv-lost", "rv-laptop", now);
+        assert_eq!(accept(&lost, Some("rv-desktop"), now).unwrap(), RevocationOutcome::Applied);
+        assert!(peers::get("rv-lost").unwrap().is_none());
+        assert!(is_revoked("rv-lost", None).unwrap());
+        // 같은 인증서로 다른 ID를 알려도 철회된 기기로 봄
+        assert!(is_revoked("rv-renamed", Some(&"ab".repeat(32))).unwrap());
+        assert!(!is_revoked("rv-desktop", Some(&"cd".repeat(32))).unwrap());
+
+        // 다른 기기가 전달한 같은 요청은 한 번만 적용
+        assert_eq!(accept(&lost, Some("rv-desktop"), now).unwrap(), RevocationOutcome::AlreadyKnown);
+        assert!(pending_for("rv-desktop").unwrap().iter().any(|r| r.revocation_id == "rv-1"));
+        assert!(pending_for("rv-laptop").unwrap().iter().all(|r| r.revocation_id != "rv-1"));
+        mark_delivered(&lost, "rv-desktop").unwrap();
+        assert!(pending_for("rv-desktop").unwrap().iter().all(|r| r.revocation_id != "rv-1"));
+        assert!(!pending_peers(&lost).unwrap().contains(&"rv-desktop".to_string()));
+
+        // 복원한 뒤에는 같은 요청을 다시 보내도 철회되지 않음
+        assert!(restore("rv-lost").unwrap());
+        assert!(!restore("rv-lost").unwrap());
+        assert!(!is_revoked("rv-lost", None).unwrap());
+        assert_eq!(accept(&lost, Some("rv-desktop"), now).unwrap(), RevocationOutcome::AlreadyKnown);
+        assert!(!is_revoked("rv-lost", None).unwrap());
+        assert!(list().unwrap().iter().all(|r| r.device_id != "rv-lost"));
+    }
+
+    #[test]
+    fn test_receive_requires_issuer_certificate() {
+        crate::loopback::use_temp_environment();
+        let issuer = TlsCertificate::generate_self_signed("rv-issuer", "Issuer").unwrap();
+        let lost = TlsCertificate::generate_self_signed("rv-stolen", "Stolen").unwrap();
+        pin("rv-issuer", &issuer.fingerprint);
+        pin("rv-stolen", &lost.fingerprint);
+        let now = unix_now();
+
+        // 비밀 키만 아는 기기(서명 없음)나 다른 기기의 인증서로 서명한 요청은 거부
+        let unsigned = revocation("rv-unsigned", "rv-victim", "rv-issuer", now);
+        assert!(receive(&unsigned).is_err());
+        let forged = signed(revocation("rv-forged", "rv-victim", "rv-issuer", now), &lost);
+        assert!(matches!(
+            receive(&forged).unwrap_err().downcast_ref::<PebbleError>(),
+            Some(PebbleError::Rejected { .. })
+        ));
+        let unpinned = signed(revocation("rv-unpinned", "rv-victim", "rv-nobody", now), &issuer);
+        assert!(receive(&unpinned).is_err());
+        let mut tampered = signed(revocation("rv-tampered", "rv-other", "rv-issuer", now), &issuer);
+        tampered.device_id = "rv-victim".to_string();
+        assert!(receive(&tampered).is_err());
+        assert!(!is_revoked("rv-victim", None).unwrap());
+
+        let genuine = signed(revocation("rv-genuine", "rv-stolen", "rv-issuer", now), &issuer);
+        assert_eq!(receive(&genuine).unwrap(), RevocationOutcome::Applied);
+        assert!(is_revoked("rv-stolen", Some(&lost.fingerprint)).unwrap());
+        assert_eq!(list().unwrap().iter().find(|r| r.revocation_id == "rv-genuine"), Some(&genuine));
+
+        // 철회된 기기의 인증서로는 다른 기기를 철회할 수 없음 (고정을 다시 남겨도)
+        pin("rv-stolen", &lost.fingerprint);
+        let revenge = signed(revocation("rv-revenge", "rv-issuer", "rv-stolen", now), &lost);
+        assert!(receive(&revenge).is_err());
+    }
+
+    #[test]
+    fn test_check_peer_uses_connection_certificate() {
+        crate::loopback::use_temp_environment();
+        pin("rv-pinned-peer", &"12".repeat(32));
+        let revoked = "34".repeat(32);
+        crate::api::db::open_connection().unwrap().execute(
+            "INSERT OR IGNORE INTO revoked_devices
+                (revocation_id, device_id, fingerprint, issued_by, issued_at, signature, revoked_at)
+             VALUES ('rv-check-peer', 'rv-gone', ?1, 'rv-laptop', 0, '', 0)",
+            params![revoked],
+        ).unwrap();
+
+        check_peer(Some("rv-pinned-peer"), Some(&"12".repeat(32))).unwrap();
+        check_peer(Some("rv-unknown-peer"), None).unwrap();
+        check_peer(None, None).unwrap();
+
+        // 고정된 기기의 ID를 다른 인증서나 인증서 없이 알리면 거부
+        assert!(check_peer(Some("rv-pinned-peer"), Some(&"56".repeat(32))).is_err());
+        assert!(check_peer(Some("rv-pinned-peer"), None).is_err());
+        // 철회된 기기의 인증서는 새 기기 ID를 알려도 거부
+        assert!(check_peer(Some("rv-fresh-id"), Some(&revoked)).is_err());
+        assert!(check_peer(None, Some(&revoked)).is_err());
+    }
+
+    #[test]
+    fn test_wipe_waits_for_confirmation() {
+        crate::loopback::use_temp_environment();
+        let notice = revocation("rv-wipe-me", "rv-this", "rv-owner", unix_now());
+        assert_eq!(accept(&notice, Some("rv-this"), unix_now()).unwrap(), RevocationOutcome::ThisDevice);
+        request_wipe(&notice).unwrap();
+        request_wipe(&notice).unwrap();
+
+        let pending: Vec<_> = pending_wipes().unwrap().into_iter().filter(|r| r.revocation_id == "rv-wipe-me").collect();
+        assert_eq!(pending, vec![notice.clone()]);
+
+        assert!(dismiss_wipe("rv-wipe-me").unwrap());
+        assert!(!dismiss_wipe("rv-wipe-me").unwrap());
+        assert!(pending_wipes().unwrap().iter().all(|r| r.revocation_id != "rv-wipe-me"));
+    }
+
+    #[test]
+    fn test_stale_self_and_own_revocations() {
+        crate::loopback::use_temp_environment();
+        let now = unix_now();
+
+        let stale = revocation("rv-old", "rv-a", "rv-b", now - MAX_AGE_SECS - 1);
+        assert!(matches!(
+            accept(&stale, None, now).unwrap_err().downcast_ref::<PebbleError>(),
+            Some(PebbleError::Rejected { .. })
+        ));
+        assert!(accept(&revocation("rv-future", "rv-a", "rv-b", now + 3600), None, now).is_err());
+        assert!(accept(&revocation("rv-self", "rv-a", "rv-a", now), None, now).is_err());
+
+        // 잃어버린 기기는 자기 기록을 남기지 않고 페어링을 지워야 함
+        assert_eq!(accept(&revocation("rv-me", "rv-a", "rv-b", now), Some("rv-a"), now).unwrap(), RevocationOutcome::ThisDevice);
+        assert!(!is_revoked("rv-a", None).unwrap());
+    }
+}
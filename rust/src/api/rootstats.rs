@@ -0,0 +1,204 @@
+//! 동기화 루트의 저장 공간 사용량 (Root Stats)
+//!
+//! 감시자가 최신으로 유지하는 `files` 테이블만으로 루트 아래의 파일 수, 전체 크기,
+//! 확장자/종류별 크기, 가장 큰 파일을 계산합니다. 파일 시스템을 다시 훑지 않으므로
+//! 큰 폴더에서도 UI가 바로 사용량을 보여 줄 수 있습니다.
+//!
+//! # Process Flow
+//! 1. `db::list_files_under`로 루트 아래의 파일을 가져옴 (삭제된 파일 제외)
+//! 2. 확장자(소문자)별로 파일 수와 크기를 더하고, 확장자로 종류(`FileCategory`)를 정함
+//! 3. 크기 순으로 가장 큰 파일 `LARGEST_FILES`개를 남김
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::path::Path;
+
+use super::db::{self, SyncStatus};
+
+/// 반환하는 가장 큰 파일 수
+pub const LARGEST_FILES: usize = 20;
+
+/// 확장자로 나눈 파일 종류
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FileCategory {
+    Image,
+    Video,
+    Audio,
+    Document,
+    Archive,
+    Code,
+    Executable,
+    Other,
+}
+
+impl FileCategory {
+    /// 확장자(소문자, 점 제외)의 종류
+    pub fn from_extension(extension: &str) -> Self {
+        match extension {
+            "jpg" | "jpeg" | "png" | "gif" | "webp" | "heic" | "heif" | "bmp" | "tif" | "tiff" | "svg" | "raw"
+            | "cr2" | "nef" | "arw" | "dng" => Self::Image,
+            "mp4" | "mov" | "mkv" | "avi" | "webm" | "m4v" | "wmv" | "flv" | "3gp" => Self::Video,
+            "mp3" | "m4a" | "aac" | "flac" | "wav" | "ogg" | "opus" | "wma" | "aiff" => Self::Audio,
+            "pdf" | "doc" | "docx" | "xls" | "xlsx" | "ppt" | "pptx" | "odt" | "ods" | "odp" | "txt" | "md"
+            | "rtf" | "csv" | "epub" | "hwp" | "pages" | "numbers" | "key" => Self::Document,
+            "zip" | "7z" | "rar" | "tar" | "gz" | "tgz" | "bz2" | "xz" | "zst" | "iso" => Self::Archive,
+            "rs" | "c" | "h" | "cpp" | "hpp" | "cs" | "java" | "kt" | "swift" | "dart" | "go" | "py" | "js"
+            | "ts" | "jsx" | "tsx" | "rb" | "php" | "html" | "css" | "json" | "yaml" | "yml" | "toml" | "xml"
+            | "sql" => Self::Code,
+            "exe" | "msi" | "dll" | "so" | "dylib" | "apk" | "app" | "dmg" | "pkg" | "deb" | "rpm" | "bat"
+            | "cmd" | "sh" | "ps1" | "jar" => Self::Executable,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// 확장자별 사용량
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExtensionUsage {
+    /// 소문자 확장자 (점 제외, 확장자가 없으면 빈 문자열)
+    pub extension: String,
+    pub category: FileCategory,
+    pub file_count: u64,
+    pub total_bytes: u64,
+}
+
+/// 종류별 사용량
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CategoryUsage {
+    pub category: FileCategory,
+    pub file_count: u64,
+    pub total_bytes: u64,
+}
+
+/// 큰 파일
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LargeFile {
+    pub path: String,
+    pub file_size: u64,
+    pub last_modified: i64,
+}
+
+/// 루트의 사용량 요약
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RootStats {
+    pub root: String,
+    pub file_count: u64,
+    pub total_bytes: u64,
+
+    /// 크기가 큰 순서
+    pub by_category: Vec<CategoryUsage>,
+
+    /// 크기가 큰 순서
+    pub by_extension: Vec<ExtensionUsage>,
+
+    /// 가장 큰 파일 (최대 `LARGEST_FILES`개, 크기가 큰 순서)
+    pub largest_files: Vec<LargeFile>,
+}
+
+fn extension_of(path: &str) -> String {
+    Path::new(path)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// 루트 아래의 사용량을 DB에서 계산합니다.
+///
+/// # Arguments
+/// * `root` - 동기화 루트 디렉토리
+pub fn get_root_stats(root: &str) -> Result<RootStats> {
+    let mut file_count = 0;
+    let mut total_bytes = 0;
+    let mut extensions: HashMap<String, ExtensionUsage> = HashMap::new();
+    let mut largest = BinaryHeap::with_capacity(LARGEST_FILES + 1);
+
+    for file in db::list_files_under(root)? {
+        if file.sync_status == SyncStatus::Deleted {
+            continue;
+        }
+        let size = file.file_size.max(0) as u64;
+        file_count += 1;
+        total_bytes += size;
+
+        let extension = extension_of(&file.path);
+        let usage = extensions.entry(extension.clone()).or_insert_with(|| ExtensionUsage {
+            category: FileCategory::from_extension(&extension),
+            extension,
+            file_count: 0,
+            total_bytes: 0,
+        });
+        usage.file_count += 1;
+        usage.total_bytes += size;
+
+        // 가장 작은 항목이 맨 위에 오도록 뒤집어 두고 넘치면 버림
+        largest.push(Reverse((size, Reverse(file.path), file.last_modified)));
+        if largest.len() > LARGEST_FILES {
+            largest.pop();
+        }
+    }
+
+    let mut categories: HashMap<FileCategory, CategoryUsage> = HashMap::new();
+    for usage in extensions.values() {
+        let category = categories.entry(usage.category).or_insert(CategoryUsage {
+            category: usage.category,
+            file_count: 0,
+            total_bytes: 0,
+        });
+        category.file_count += usage.file_count;
+        category.total_bytes += usage.total_bytes;
+    }
+
+    let mut by_extension: Vec<ExtensionUsage> = extensions.into_values().collect();
+    by_extension.sort_by(|a, b| b.total_bytes.cmp(&a.total_bytes).then_with(|| a.extension.cmp(&b.extension)));
+    let mut by_category: Vec<CategoryUsage> = categories.into_values().collect();
+    by_category.sort_by(|a, b| b.total_bytes.cmp(&a.total_bytes).then_with(|| b.file_count.cmp(&a.file_count)));
+    let largest_files = largest
+        .into_sorted_vec()
+        .into_iter()
+        .map(|Reverse((file_size, Reverse(path), last_modified))| LargeFile { path, file_size, last_modified })
+        .collect();
+
+    Ok(RootStats { root: root.to_string(), file_count, total_bytes, by_category, by_extension, largest_files })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::db::FileMetadata;
+
+    #[test]
+    fn test_root_stats_breakdown() {
+        let root = crate::loopback::use_temp_environment().join("root-stats");
+        let file = |name: &str, size: i64, status: SyncStatus| {
+            let path = root.join(name).to_string_lossy().to_string();
+            db::upsert_file(FileMetadata::new(path, 7, format!("h-{}", name), status, size)).unwrap();
+        };
+        file("a.JPG", 3000, SyncStatus::Synced);
+        file("b.jpg", 1000, SyncStatus::Synced);
+        file("trip.mp4", 50000, SyncStatus::Pending);
+        file("notes.txt", 200, SyncStatus::Synced);
+        file("Makefile", 100, SyncStatus::Synced);
+        file("old.mp4", 90000, SyncStatus::Deleted);
+        for i in 0..LARGEST_FILES {
+            file(&format!("logs/{}.log", i), 10, SyncStatus::Synced);
+        }
+
+        let stats = get_root_stats(&root.to_string_lossy()).unwrap();
+        assert_eq!(stats.file_count, 5 + LARGEST_FILES as u64);
+        assert_eq!(stats.total_bytes, 54300 + 10 * LARGEST_FILES as u64);
+
+        assert_eq!(stats.by_category[0], CategoryUsage { category: FileCategory::Video, file_count: 1, total_bytes: 50000 });
+        assert_eq!(stats.by_category[1], CategoryUsage { category: FileCategory::Image, file_count: 2, total_bytes: 4000 });
+        let jpg = stats.by_extension.iter().find(|usage| usage.extension == "jpg").unwrap();
+        assert_eq!((jpg.file_count, jpg.total_bytes), (2, 4000));
+        assert!(stats.by_extension.iter().any(|usage| usage.extension.is_empty() && usage.category == FileCategory::Other));
+
+        assert_eq!(stats.largest_files.len(), LARGEST_FILES);
+        let sizes: Vec<u64> = stats.largest_files.iter().take(5).map(|file| file.file_size).collect();
+        assert_eq!(sizes, [50000, 3000, 1000, 200, 100]);
+        assert!(stats.largest_files[0].path.ends_with("trip.mp4"));
+        assert_eq!(stats.largest_files[0].last_modified, 7);
+    }
+}
@@ -0,0 +1,259 @@
+//! 초기 디렉토리 스캔 (Initial Directory Scan)
+//!
+//! 감시를 시작하기 전에 감시 폴더의 파일을 DB에 기록합니다. 큰 폴더는 몇 분이 걸릴 수 있으므로
+//! 진행 상황(찾은 파일 수, 기록한 파일 수, 현재 경로)을 콜백으로 알리고, 루트 경로나 작업 ID
+//! (`operations::cancel`)로 취소할 수 있습니다.
+//! 초기 스캔은 해시를 계산하지 않고 자리 표시 해시(`INITIAL_SCAN_HASH`)로 기록합니다.
+//! 크기와 수정 시각만 쓰는 루트(`ChangeDetection::SizeMtime`)는 `integrity::metadata_hash`로 기록합니다.
+//!
+//! # Process Flow
+//! 1. 작업(`operations`)으로 등록하고 루트별로 기록 (같은 루트의 스캔이 진행 중이면 거절)
+//! 2. 폴더를 순회하며 파일 목록 수집 (Discovering)
+//! 3. 수집한 파일을 DB에 Synced로 기록 (Indexing)
+//! 4. `PROGRESS_INTERVAL`마다 진행 상황을 알리고, 끝나면 Finished로 한 번 더 알림
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+use super::config;
+use super::db::{self, FileMetadata, SyncStatus};
+use super::error::PebbleError;
+use super::integrity::{self, ChangeDetection};
+use super::locked;
+use super::operations::{self, OperationHandle, OperationKind};
+use super::paths;
+use super::volume;
+
+/// 초기 스캔으로 기록한 파일의 해시 자리 표시 값
+pub const INITIAL_SCAN_HASH: &str = "initial_scan";
+
+/// 진행 상황을 알리는 최소 간격
+pub const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 스캔 단계
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScanPhase {
+    /// 폴더를 순회하며 파일을 찾는 중
+    Discovering,
+
+    /// 찾은 파일을 DB에 기록하는 중
+    Indexing,
+
+    /// 스캔 완료
+    Finished,
+}
+
+/// 스캔 진행 상황
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanProgress {
+    /// 작업 ID (`operations::cancel`로 취소할 때)
+    pub operation_id: String,
+
+    pub root: String,
+    pub phase: ScanPhase,
+
+    /// 지금까지 찾은 파일 수
+    pub files_discovered: u64,
+
+    /// 지금까지 찾은 파일의 전체 크기 (bytes)
+    pub bytes_discovered: u64,
+
+    /// DB에 기록한 파일 수 (Indexing 단계의 진행률 = files_indexed / files_discovered)
+    pub files_indexed: u64,
+
+    /// 마지막으로 처리한 경로 (Finished면 None)
+    pub current_path: Option<String>,
+}
+
+/// 루트별 진행 중인 스캔의 작업 ID
+static ACTIVE_SCANS: once_cell::sync::Lazy<Mutex<HashMap<String, String>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 진행 중인 스캔 (drop되면 루트 등록과 작업 등록을 해제)
+struct ActiveScan {
+    /// `ACTIVE_SCANS`에 등록한 루트의 비교 키
+    key: String,
+    operation: OperationHandle,
+}
+
+impl ActiveScan {
+    fn register(root: &str) -> Result<Self> {
+        let key = paths::comparison_key(root);
+        let mut scans = ACTIVE_SCANS.lock().unwrap();
+        if scans.contains_key(&key) {
+            return Err(PebbleError::invalid_argument(format!("A scan of {} is already running", root)).into());
+        }
+        let operation = operations::begin(OperationKind::Scan, root);
+        scans.insert(key.clone(), operation.id().to_string());
+        Ok(Self { key, operation })
+    }
+}
+
+impl Drop for ActiveScan {
+    fn drop(&mut self) {
+        ACTIVE_SCANS.lock().unwrap().remove(&self.key);
+    }
+}
+
+/// 진행 상황 콜백과 작업 진행률 갱신을 `PROGRESS_INTERVAL` 간격으로 제한합니다.
+struct Reporter<'a, F> {
+    scan: &'a ActiveScan,
+    progress: ScanProgress,
+    on_progress: F,
+    last_report: Option<Instant>,
+}
+
+impl<F: FnMut(&ScanProgress)> Reporter<'_, F> {
+    fn report(&mut self, force: bool) {
+        if force || self.last_report.is_none_or(|last| last.elapsed() >= PROGRESS_INTERVAL) {
+            let total = (self.progress.phase != ScanPhase::Discovering).then_some(self.progress.files_discovered);
+            let completed = if total.is_some() { self.progress.files_indexed } else { self.progress.files_discovered };
+            self.scan.operation.set_progress(completed, total);
+            (self.on_progress)(&self.progress);
+            self.last_report = Some(Instant::now());
+        }
+    }
+}
+
+/// 찾은 파일 하나
+struct DiscoveredFile {
+    path: PathBuf,
+    last_modified: i64,
+    size: u64,
+}
+
+/// 폴더를 스캔하여 파일을 DB에 기록합니다.
+///
+/// # Arguments
+/// * `root` - 스캔할 디렉토리
+/// * `on_progress` - 진행 상황 콜백 (`PROGRESS_INTERVAL` 간격, 단계가 바뀔 때와 끝날 때는 항상)
+///
+/// # Returns
+/// * 마지막 진행 상황 (Finished)
+/// * `cancel` 또는 `operations::cancel`로 취소되면 `PebbleError::Cancelled` (취소 전까지 기록한 파일은 남음)
+pub fn run<F: FnMut(&ScanProgress)>(root: &str, on_progress: F) -> Result<ScanProgress> {
+    let root = paths::normalize(root);
+    let scan = ActiveScan::register(&root)?;
+
+    let mut reporter = Reporter {
+        scan: &scan,
+        progress: ScanProgress {
+            operation_id: scan.operation.id().to_string(),
+            root: root.clone(),
+            phase: ScanPhase::Discovering,
+            files_discovered: 0,
+            bytes_discovered: 0,
+            files_indexed: 0,
+            current_path: None,
+        },
+        on_progress,
+        last_report: None,
+    };
+
+    let mut files = Vec::new();
+    for entry in WalkDir::new(paths::long_path(&root)).into_iter().filter_map(|e| e.ok()) {
+        scan.operation.check()?;
+
+        let path = entry.path();
+        if !path.is_file() || volume::is_marker(path) || locked::is_staging(path) {
+            continue;
+        }
+
+        let metadata = fs::metadata(path)?;
+        let last_modified = metadata.modified()
+            .unwrap_or(std::time::SystemTime::now())
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64;
+
+        reporter.progress.files_discovered += 1;
+        reporter.progress.bytes_discovered += metadata.len();
+        reporter.progress.current_path = Some(paths::normalize(path));
+        reporter.report(false);
+
+        files.push(DiscoveredFile { path: path.to_path_buf(), last_modified, size: metadata.len() });
+    }
+
+    reporter.progress.phase = ScanPhase::Indexing;
+    reporter.report(true);
+
+    let config = config::current();
+    for file in files {
+        scan.operation.check()?;
+
+        let path = paths::normalize(&file.path);
+        // 크기와 수정 시각만 쓰는 루트는 자리 표시 대신 그 값으로 바로 기록
+        let file_hash = match config.change_detection_for(&path) {
+            ChangeDetection::ContentHash => INITIAL_SCAN_HASH.to_string(),
+            ChangeDetection::SizeMtime => integrity::metadata_hash(file.size, file.last_modified),
+        };
+        // 초기 스캔 시에는 일단 Synced로 간주
+        db::upsert_file(FileMetadata::new(
+            path.clone(),
+            file.last_modified,
+            file_hash,
+            SyncStatus::Synced,
+            file.size as i64,
+        ))?;
+
+        reporter.progress.files_indexed += 1;
+        reporter.progress.current_path = Some(path);
+        reporter.report(false);
+    }
+
+    reporter.progress.phase = ScanPhase::Finished;
+    reporter.progress.current_path = None;
+    reporter.report(true);
+
+    tracing::info!("Scanned {}: {} files ({} bytes)",
+        root, reporter.progress.files_discovered, reporter.progress.bytes_discovered);
+
+    Ok(reporter.progress)
+}
+
+/// 진행 중인 스캔을 취소합니다.
+///
+/// # Returns
+/// * 그 루트의 스캔이 진행 중이었으면 true
+pub fn cancel(root: &str) -> bool {
+    let operation_id = ACTIVE_SCANS.lock().unwrap().get(&paths::comparison_key(root)).cloned();
+    operation_id.is_some_and(|operation_id| operations::cancel(&operation_id).is_ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_reports_progress_and_cancels() {
+        crate::loopback::use_temp_environment();
+        let dir = tempfile::TempDir::new().unwrap();
+        for index in 0..5 {
+            fs::write(dir.path().join(format!("scan_{}.txt", index)), vec![0u8; 10]).unwrap();
+        }
+        let root = dir.path().to_string_lossy().to_string();
+
+        let mut phases = Vec::new();
+        let finished = run(&root, |progress| phases.push(progress.phase)).unwrap();
+        assert_eq!(phases.first(), Some(&ScanPhase::Discovering));
+        assert!(phases.contains(&ScanPhase::Indexing));
+        assert_eq!(phases.last(), Some(&ScanPhase::Finished));
+        assert_eq!((finished.files_discovered, finished.files_indexed, finished.bytes_discovered), (5, 5, 50));
+        assert!(!cancel(&root));
+
+        // 진행 중에 취소하면 Cancelled
+        let result = run(&root, |progress| {
+            if progress.phase == ScanPhase::Indexing {
+                assert!(cancel(&progress.root));
+            }
+        });
+        assert!(matches!(PebbleError::from(result.unwrap_err()), PebbleError::Cancelled { .. }));
+        assert!(!cancel(&root));
+    }
+}
@@ -0,0 +1,243 @@
+//! 수신 파일 검사 명령 (Scan Hook)
+//!
+//! 받은 파일을 저장 위치에 확정하기 전에 설정한 외부 명령(백신 CLI 등)으로 검사합니다.
+//! 명령이 0이 아닌 코드로 끝나거나 제한 시간 안에 끝나지 않으면 파일을 격리 폴더로 옮기고,
+//! 송신측에는 정책 거부(`RejectCode::PolicyDenied`)로 알립니다.
+//!
+//! # Process Flow
+//! 1. 수신과 해시 검증이 끝나면 저장 위치로 옮기기 전의 임시 파일로 `check` 호출
+//! 2. `<program> <args...> <파일 경로>` 실행 (환경 변수 `PEBBLE_TRANSFER_ID`, `PEBBLE_SENDER_DEVICE_ID` 전달)
+//! 3. 종료 코드가 0이면 통과, 아니면 격리 폴더로 옮기고 `FileQuarantined` 이벤트 발행
+//! 4. 최종 결과를 기다리는 송신측(`verdict` 합의)에 거부를 보냄
+//!
+//! # Security
+//! - 명령을 실행할 수 없거나 시간을 넘기면 검사 실패로 처리 (fail-closed)
+//! - 셸을 거치지 않고 실행하므로 파일 이름이 명령으로 해석되지 않음
+//! - 격리한 파일은 소유자만 읽고 쓸 수 있도록 권한을 0600으로 바꿈
+//! - 검사 명령의 출력은 로컬 이벤트에만 남기고 송신측에는 보내지 않음
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+use super::config;
+use super::error::PebbleError;
+use super::events::{self, PebbleEvent};
+use super::paths;
+
+/// 기본 제한 시간 (초)
+pub const DEFAULT_TIMEOUT_SECS: u64 = 60;
+
+/// 허용하는 최대 제한 시간 (초, 송신측은 이보다 조금 더 기다림)
+pub const MAX_TIMEOUT_SECS: u64 = 600;
+
+/// 격리 폴더 기본 이름 (DB 파일과 같은 폴더 아래)
+const QUARANTINE_DIR_NAME: &str = "quarantine";
+
+/// 받은 파일을 검사하는 외부 명령
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanHook {
+    /// 실행 파일 경로 (예: "/usr/bin/clamdscan")
+    pub program: String,
+
+    /// 파일 경로 앞에 넘길 인자 (예: ["--no-summary"])
+    pub args: Vec<String>,
+
+    /// 제한 시간 (초, 넘기면 검사 실패)
+    pub timeout_secs: u64,
+
+    /// 검사에 실패한 파일을 옮길 폴더 (None이면 DB 파일 옆의 `quarantine`)
+    pub quarantine_dir: Option<String>,
+}
+
+impl Default for ScanHook {
+    fn default() -> Self {
+        Self {
+            program: String::new(),
+            args: Vec::new(),
+            timeout_secs: DEFAULT_TIMEOUT_SECS,
+            quarantine_dir: None,
+        }
+    }
+}
+
+impl ScanHook {
+    /// 설정 값을 검증합니다.
+    pub fn validate(&self) -> Result<()> {
+        if self.program.trim().is_empty() {
+            return Err(PebbleError::invalid_argument("scan_hook.program must not be empty").into());
+        }
+        if !(1..=MAX_TIMEOUT_SECS).contains(&self.timeout_secs) {
+            return Err(PebbleError::invalid_argument(format!(
+                "scan_hook.timeout_secs must be between 1 and {}", MAX_TIMEOUT_SECS
+            )).into());
+        }
+        if self.quarantine_dir.as_deref().is_some_and(|dir| dir.trim().is_empty()) {
+            return Err(PebbleError::invalid_argument("scan_hook.quarantine_dir must not be empty (use null for the default)").into());
+        }
+        Ok(())
+    }
+
+    fn quarantine_dir(&self) -> PathBuf {
+        match &self.quarantine_dir {
+            Some(dir) => PathBuf::from(dir),
+            None => Path::new(&config::current().db_path)
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join(QUARANTINE_DIR_NAME),
+        }
+    }
+
+    /// 명령을 실행합니다.
+    ///
+    /// # Returns
+    /// * 검사에 실패했으면 실패 사유
+    async fn run(&self, path: &Path, transfer_id: &str, sender_device_id: Option<&str>) -> Option<String> {
+        let child = Command::new(&self.program)
+            .args(&self.args)
+            .arg(paths::long_path(path))
+            .env("PEBBLE_TRANSFER_ID", transfer_id)
+            .env("PEBBLE_SENDER_DEVICE_ID", sender_device_id.unwrap_or_default())
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn();
+        let child = match child {
+            Ok(child) => child,
+            Err(e) => return Some(format!("Cannot run {}: {}", self.program, e)),
+        };
+
+        // 시간을 넘기면 future가 버려지면서 프로세스도 종료됨 (kill_on_drop)
+        match tokio::time::timeout(Duration::from_secs(self.timeout_secs), child.wait_with_output()).await {
+            Ok(Ok(output)) if output.status.success() => None,
+            Ok(Ok(output)) => {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let detail = stderr.lines().chain(stdout.lines()).map(str::trim).find(|line| !line.is_empty());
+                Some(match detail {
+                    Some(detail) => format!("{} ({})", output.status, detail),
+                    None => output.status.to_string(),
+                })
+            }
+            Ok(Err(e)) => Some(format!("Failed to wait for {}: {}", self.program, e)),
+            Err(_) => Some(format!("{} timed out after {}s", self.program, self.timeout_secs)),
+        }
+    }
+
+    /// 파일을 검사하고, 실패하면 격리 폴더로 옮깁니다.
+    ///
+    /// # Returns
+    /// * 검사에 실패하면 `PebbleError::Rejected` (파일은 격리됨)
+    pub async fn scan(&self, path: &Path, transfer_id: &str, sender_device_id: Option<&str>) -> Result<()> {
+        let Some(reason) = self.run(path, transfer_id, sender_device_id).await else {
+            tracing::debug!("Scan hook passed {}", path.display());
+            return Ok(());
+        };
+
+        tracing::warn!("Scan hook rejected {}: {}", path.display(), reason);
+        let quarantined = self.quarantine(path, transfer_id)?;
+        events::emit(PebbleEvent::FileQuarantined {
+            transfer_id: transfer_id.to_string(),
+            path: path.to_string_lossy().to_string(),
+            quarantined_path: quarantined.to_string_lossy().to_string(),
+            reason,
+        });
+
+        let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        Err(PebbleError::rejected(format!("{} was rejected by the receiver's file scan", name)).into())
+    }
+
+    /// 파일을 격리 폴더로 옮깁니다 (이름 앞에 전송 ID를 붙여 겹치지 않게 함).
+    fn quarantine(&self, path: &Path, transfer_id: &str) -> Result<PathBuf> {
+        let dir = self.quarantine_dir();
+        fs::create_dir_all(paths::long_path(&dir))
+            .with_context(|| format!("Failed to create quarantine directory: {}", dir.display()))?;
+
+        let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        let target = dir.join(format!("{}-{}", transfer_id, name));
+        // 다른 볼륨이면 이름만 바꿀 수 없으므로 복사 후 삭제
+        if fs::rename(paths::long_path(path), paths::long_path(&target)).is_err() {
+            fs::copy(paths::long_path(path), paths::long_path(&target))
+                .with_context(|| format!("Failed to quarantine {}", path.display()))?;
+            fs::remove_file(paths::long_path(path))
+                .with_context(|| format!("Failed to remove {} after quarantining", path.display()))?;
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&target, fs::Permissions::from_mode(0o600))?;
+        }
+
+        tracing::info!("Quarantined {} as {}", path.display(), target.display());
+        Ok(target)
+    }
+}
+
+/// 설정된 검사 명령으로 받은 파일을 검사합니다.
+///
+/// 검사 명령이 없거나 로컬 파일이 아니면(호스트 저장소 문서, 암호화된 대기 영역) 바로 통과합니다.
+/// 암호화된 대기 영역의 파일은 승인할 때 복호화한 뒤 검사합니다(`staging::approve`).
+pub async fn check(path: &Path, transfer_id: &str, sender_device_id: Option<&str>) -> Result<()> {
+    let Some(hook) = config::current().scan_hook else {
+        return Ok(());
+    };
+    if !paths::long_path(path).is_file() {
+        tracing::debug!("Not scanning {}: not a local file", path.display());
+        return Ok(());
+    }
+    hook.scan(path, transfer_id, sender_device_id).await
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn hook(script: &str, quarantine_dir: &Path) -> ScanHook {
+        ScanHook {
+            program: "/bin/sh".to_string(),
+            args: vec!["-c".to_string(), script.to_string(), "scan".to_string()],
+            timeout_secs: 1,
+            quarantine_dir: Some(quarantine_dir.to_string_lossy().to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_scan_quarantines_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let quarantine = dir.path().join("quarantine");
+        let detect = hook("if grep -q EICAR \"$1\"; then echo 'Eicar-Signature FOUND' >&2; exit 1; fi", &quarantine);
+
+        let clean = dir.path().join("report.pdf");
+        fs::write(&clean, b"quarterly report").unwrap();
+        detect.scan(&clean, "t-clean", None).await.unwrap();
+        assert!(clean.exists());
+
+        let infected = dir.path().join("setup.exe");
+        fs::write(&infected, b"X5O!P%@AP EICAR").unwrap();
+        let err = detect.scan(&infected, "t-infected", Some("peer")).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<PebbleError>(), Some(PebbleError::Rejected { .. })));
+        assert!(!err.to_string().contains("Eicar"));
+        assert!(!infected.exists());
+        assert!(quarantine.join("t-infected-setup.exe").exists());
+
+        // 시간을 넘기면 검사 실패
+        let slow = dir.path().join("slow.bin");
+        fs::write(&slow, b"data").unwrap();
+        assert!(hook("sleep 5", &quarantine).scan(&slow, "t-slow", None).await.is_err());
+        assert!(quarantine.join("t-slow-slow.bin").exists());
+    }
+
+    #[test]
+    fn test_validate() {
+        let dir = tempfile::TempDir::new().unwrap();
+        assert!(hook("exit 0", dir.path()).validate().is_ok());
+        assert!(ScanHook::default().validate().is_err());
+        assert!(ScanHook { timeout_secs: 0, ..hook("exit 0", dir.path()) }.validate().is_err());
+    }
+}
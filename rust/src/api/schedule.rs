@@ -0,0 +1,199 @@
+//! 시간대별 전송 일정 (Schedule Windows)
+//!
+//! 하루 중 특정 시간대에 자동 동기화를 멈추거나 전송 속도를 제한합니다.
+//! 예: 업무 시간(09:00–18:00)에는 1 MB/s로 제한, 06:00–01:00에는 자동 전달 중지
+//! (= 01:00–06:00에만 동기화).
+//!
+//! 일정은 전역 설정(`PebbleConfig::schedule`)에 두고, 동기화 쌍(`ForwardOptions`)마다
+//! 별도 일정으로 대체할 수 있습니다.
+//!
+//! # Process Flow
+//! 1. 현재 로컬 시각과 요일에 해당하는 창을 모두 찾음
+//! 2. `Pause` 창이 하나라도 있으면 자동 동기화가 새 전송을 시작하지 않음
+//! 3. `Throttle` 창과 `max_transfer_rate` 중 가장 낮은 속도로 청크 전송 속도를 제한
+//!    (진행 중인 전송에도 시간대가 바뀌는 즉시 반영)
+
+use anyhow::Result;
+use chrono::{Datelike, Duration as ChronoDuration, NaiveDateTime, NaiveTime, Timelike};
+use serde::{Deserialize, Serialize};
+
+use super::config;
+
+/// 시간대에 적용할 동작
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ScheduleAction {
+    /// 자동 동기화가 새 전송을 시작하지 않음 (사용자가 직접 보내는 전송은 허용)
+    Pause,
+    /// 전송 속도를 제한
+    Throttle {
+        /// 최대 전송 속도 (bytes/sec)
+        bytes_per_sec: u64,
+    },
+}
+
+/// 일정 창 하나
+///
+/// `end`가 `start`보다 이르면 자정을 넘어 다음 날 `end`까지 적용됩니다.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleWindow {
+    /// 시작 시각 ("HH:MM", 로컬 시간, 포함)
+    pub start: String,
+
+    /// 끝 시각 ("HH:MM", 로컬 시간, 제외)
+    pub end: String,
+
+    /// 적용 요일 (1=월요일 … 7=일요일, 비어 있으면 매일). 자정을 넘는 창은 시작한 날 기준
+    #[serde(default)]
+    pub days: Vec<u8>,
+
+    pub action: ScheduleAction,
+}
+
+/// 특정 시각에 적용되는 일정
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScheduleDecision {
+    /// 자동 동기화 중지 여부
+    pub paused: bool,
+
+    /// 전송 속도 제한 (bytes/sec, 0이면 무제한)
+    pub rate_limit: u64,
+}
+
+fn parse_time(value: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M")
+        .map_err(|_| anyhow::anyhow!("Invalid schedule time (expected HH:MM): {}", value))
+}
+
+impl ScheduleWindow {
+    /// 시각 형식, 요일 범위, 속도 값을 검사합니다.
+    pub fn validate(&self) -> Result<()> {
+        let start = parse_time(&self.start)?;
+        let end = parse_time(&self.end)?;
+        if start == end {
+            anyhow::bail!("Schedule window {}–{} is empty", self.start, self.end);
+        }
+        if let Some(day) = self.days.iter().find(|day| !(1..=7).contains(*day)) {
+            anyhow::bail!("Schedule day must be between 1 (Mon) and 7 (Sun), got {}", day);
+        }
+        if let ScheduleAction::Throttle { bytes_per_sec: 0 } = self.action {
+            anyhow::bail!("Schedule throttle must be at least 1 byte/sec");
+        }
+        Ok(())
+    }
+
+    fn applies_on(&self, weekday: u32) -> bool {
+        self.days.is_empty() || self.days.iter().any(|day| u32::from(*day) == weekday)
+    }
+
+    /// 창이 `at` 시각을 포함하는지 확인합니다. 형식이 잘못된 창은 적용하지 않습니다.
+    pub fn contains(&self, at: NaiveDateTime) -> bool {
+        let (Ok(start), Ok(end)) = (parse_time(&self.start), parse_time(&self.end)) else {
+            return false;
+        };
+        let time = NaiveTime::from_hms_opt(at.hour(), at.minute(), 0).unwrap_or(at.time());
+        let today = at.weekday().number_from_monday();
+
+        if start < end {
+            start <= time && time < end && self.applies_on(today)
+        } else if time >= start {
+            self.applies_on(today)
+        } else if time < end {
+            // 전날 시작해 자정을 넘어온 창
+            let yesterday = (at - ChronoDuration::days(1)).weekday().number_from_monday();
+            self.applies_on(yesterday)
+        } else {
+            false
+        }
+    }
+}
+
+/// 일정 목록을 `at` 시각에 평가합니다.
+pub fn evaluate(windows: &[ScheduleWindow], at: NaiveDateTime) -> ScheduleDecision {
+    windows
+        .iter()
+        .filter(|window| window.contains(at))
+        .fold(ScheduleDecision::default(), |mut decision, window| {
+            match window.action {
+                ScheduleAction::Pause => decision.paused = true,
+                ScheduleAction::Throttle { bytes_per_sec } => {
+                    decision.rate_limit = min_limit(decision.rate_limit, bytes_per_sec);
+                }
+            }
+            decision
+        })
+}
+
+/// 두 속도 제한 중 더 낮은 값 (0은 무제한)
+fn min_limit(a: u64, b: u64) -> u64 {
+    match (a, b) {
+        (0, other) | (other, 0) => other,
+        (a, b) => a.min(b),
+    }
+}
+
+/// 지금 적용되는 일정을 반환합니다.
+///
+/// # Arguments
+/// * `windows` - 동기화 쌍의 일정 (None이면 전역 설정의 일정)
+pub fn current(windows: Option<&[ScheduleWindow]>) -> ScheduleDecision {
+    let config = config::current();
+    let windows = windows.unwrap_or(&config.schedule);
+    let mut decision = evaluate(windows, chrono::Local::now().naive_local());
+    decision.rate_limit = min_limit(decision.rate_limit, config.max_transfer_rate);
+    decision
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(day: u32, time: &str) -> NaiveDateTime {
+        // 2024-01-01은 월요일
+        NaiveDate::from_ymd_opt(2024, 1, day)
+            .unwrap()
+            .and_time(parse_time(time).unwrap())
+    }
+
+    fn window(start: &str, end: &str, days: Vec<u8>, action: ScheduleAction) -> ScheduleWindow {
+        ScheduleWindow { start: start.to_string(), end: end.to_string(), days, action }
+    }
+
+    #[test]
+    fn test_windows_wrap_midnight_and_combine() {
+        let windows = vec![
+            // 01:00–06:00에만 동기화
+            window("06:00", "01:00", vec![], ScheduleAction::Pause),
+            // 평일 업무 시간에는 1 MB/s
+            window("09:00", "18:00", vec![1, 2, 3, 4, 5], ScheduleAction::Throttle { bytes_per_sec: 1_000_000 }),
+            window("12:00", "13:00", vec![], ScheduleAction::Throttle { bytes_per_sec: 250_000 }),
+        ];
+
+        assert_eq!(evaluate(&windows, at(1, "03:00")), ScheduleDecision { paused: false, rate_limit: 0 });
+        assert_eq!(evaluate(&windows, at(1, "00:30")), ScheduleDecision { paused: true, rate_limit: 0 });
+        assert_eq!(evaluate(&windows, at(1, "10:00")), ScheduleDecision { paused: true, rate_limit: 1_000_000 });
+        assert_eq!(evaluate(&windows, at(1, "12:30")).rate_limit, 250_000);
+        assert_eq!(evaluate(&windows, at(1, "18:00")).rate_limit, 0);
+        // 토요일은 업무 시간 제한 없음
+        assert_eq!(evaluate(&windows, at(6, "10:00")).rate_limit, 0);
+
+        // 금요일 밤에 시작한 창은 토요일 새벽까지, 토요일 밤에는 시작하지 않음
+        let friday_night = window("22:00", "02:00", vec![5], ScheduleAction::Pause);
+        assert!(friday_night.contains(at(5, "23:00")));
+        assert!(friday_night.contains(at(6, "01:59")));
+        assert!(!friday_night.contains(at(6, "23:00")));
+        assert!(!friday_night.contains(at(5, "01:00")));
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_windows() {
+        let valid = window("22:00", "06:00", vec![1, 7], ScheduleAction::Pause);
+        valid.validate().unwrap();
+
+        assert!(window("25:00", "06:00", vec![], ScheduleAction::Pause).validate().is_err());
+        assert!(window("06:00", "06:00", vec![], ScheduleAction::Pause).validate().is_err());
+        assert!(window("01:00", "06:00", vec![0], ScheduleAction::Pause).validate().is_err());
+        assert!(window("01:00", "06:00", vec![], ScheduleAction::Throttle { bytes_per_sec: 0 }).validate().is_err());
+    }
+}
@@ -0,0 +1,325 @@
+//! 송신 대기열 (Persistent Send Queue)
+//!
+//! 상대 기기가 지금 없어도 보낼 파일을 대기열에 넣어 두면, 기기가 발견될 때 차례로 보냅니다.
+//! 대기열은 `send_queue` 테이블에 저장되므로 앱을 닫았다 열어도 남으며, 이전 실행에서 넣은 항목은
+//! 복원된 항목(`restored`)으로 표시되어 UI가 검토하고 지울 수 있습니다(`purge_restored`).
+//!
+//! # Process Flow
+//! 1. `enqueue`로 기기 ID, 파일, 우선순위와 이번 실행의 세션 ID를 저장하고 처리 태스크를 깨움
+//! 2. 서비스가 시작되면 `run`이 이전 세션에서 남은 항목까지 포함해 대기열 처리 시작
+//! 3. 발견 목록에 있는 기기의 항목만 우선순위, 넣은 순서대로 기기당 하나씩 전송
+//! 4. 성공하면 대기열에서 삭제, 실패하면 에러를 기록하고 `RETRY_DELAY` 뒤에 다시 시도
+//!    (`MAX_ATTEMPTS`번 실패하면 삭제, 결과는 전송 기록에 남음)
+//!    다시 보낼 때는 앱을 다시 시작했거나 기기 주소가 바뀌었어도 전송 ID가 같아 수신측이 받은 청크 뒤부터 이어받음
+
+use anyhow::Result;
+use rusqlite::{params, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use super::db;
+use super::discovery;
+use super::error::PebbleError;
+use super::paths;
+use super::peers;
+use super::priority::TransferPriority;
+use super::util::unix_now;
+
+/// 발견 목록을 다시 확인하는 주기
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 실패한 항목을 다시 보내기까지 기다리는 시간 (초)
+const RETRY_DELAY: i64 = 30;
+
+/// 항목 하나를 보내는 최대 시도 횟수
+pub const MAX_ATTEMPTS: u32 = 3;
+
+/// 대기열의 송신 하나
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueuedSend {
+    pub id: String,
+
+    /// 수신 기기 ID
+    pub device_id: String,
+
+    pub file_path: String,
+    pub priority: TransferPriority,
+
+    /// 대기열에 넣은 시각 (Unix timestamp)
+    pub queued_at: i64,
+
+    /// 이전 실행에서 남아 복원된 항목인지 여부
+    pub restored: bool,
+
+    /// 지금까지 시도한 횟수
+    pub attempts: u32,
+
+    /// 마지막 시도 시각 (Unix timestamp)
+    pub last_attempt_at: Option<i64>,
+
+    /// 마지막 실패 원인
+    pub last_error: Option<String>,
+}
+
+/// 이번 실행의 세션 ID (다른 세션에서 넣은 항목은 복원된 항목)
+static SESSION: once_cell::sync::Lazy<String> = once_cell::sync::Lazy::new(|| Uuid::new_v4().to_string());
+
+/// 새 항목을 넣었거나 전송이 끝나 대기열을 다시 확인할 때
+static WAKE: once_cell::sync::Lazy<Notify> = once_cell::sync::Lazy::new(Notify::new);
+
+/// 대기열에서 보내는 중인 기기 (기기당 하나씩 보냄)
+static SENDING: once_cell::sync::Lazy<Mutex<HashSet<String>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashSet::new()));
+
+const SELECT_COLUMNS: &str = "id, device_id, file_path, priority, queued_at, session != ?1, attempts, last_attempt_at, last_error";
+
+fn priority_name(priority: TransferPriority) -> &'static str {
+    match priority {
+        TransferPriority::High => "High",
+        TransferPriority::Normal => "Normal",
+        TransferPriority::Low => "Low",
+    }
+}
+
+fn parse_priority(value: &str) -> TransferPriority {
+    match value {
+        "High" => TransferPriority::High,
+        "Low" => TransferPriority::Low,
+        _ => TransferPriority::Normal,
+    }
+}
+
+fn from_row(row: &Row) -> rusqlite::Result<QueuedSend> {
+    Ok(QueuedSend {
+        id: row.get(0)?,
+        device_id: row.get(1)?,
+        file_path: row.get(2)?,
+        priority: parse_priority(&row.get::<_, String>(3)?),
+        queued_at: row.get(4)?,
+        restored: row.get(5)?,
+        attempts: row.get(6)?,
+        last_attempt_at: row.get(7)?,
+        last_error: row.get(8)?,
+    })
+}
+
+/// 파일을 송신 대기열에 넣습니다.
+///
+/// # Returns
+/// * 파일이 없으면 `PebbleError::NotFound`
+pub fn enqueue(device_id: &str, file_path: &str, priority: TransferPriority) -> Result<QueuedSend> {
+    let file_path = paths::normalize(file_path);
+    if !paths::long_path(Path::new(&file_path)).is_file() {
+        return Err(PebbleError::not_found(format!("File {}", file_path)).into());
+    }
+
+    let item = QueuedSend {
+        id: Uuid::new_v4().to_string(),
+        device_id: device_id.to_string(),
+        file_path,
+        priority,
+        queued_at: unix_now(),
+        restored: false,
+        attempts: 0,
+        last_attempt_at: None,
+        last_error: None,
+    };
+    db::open_connection()?.execute(
+        "INSERT INTO send_queue (id, device_id, file_path, priority, queued_at, session)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![item.id, item.device_id, item.file_path, priority_name(priority), item.queued_at, SESSION.as_str()],
+    )?;
+
+    tracing::info!("Queued {} for {}", item.file_path, item.device_id);
+    WAKE.notify_one();
+
+    Ok(item)
+}
+
+/// 대기열을 보낼 순서(우선순위, 넣은 순서)대로 가져옵니다.
+pub fn list() -> Result<Vec<QueuedSend>> {
+    let conn = db::open_connection()?;
+    let mut items = conn
+        .prepare(&format!("SELECT {} FROM send_queue ORDER BY queued_at, rowid", SELECT_COLUMNS))?
+        .query_map(params![SESSION.as_str()], from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    items.sort_by_key(|item| item.priority);
+    Ok(items)
+}
+
+/// 대기열에서 항목을 지웁니다 (이미 보내는 중이면 전송은 계속됨).
+///
+/// # Returns
+/// * 그 ID의 항목이 있었으면 true
+pub fn remove(id: &str) -> Result<bool> {
+    let removed = db::open_connection()?.execute("DELETE FROM send_queue WHERE id = ?1", params![id])?;
+    Ok(removed > 0)
+}
+
+/// 이전 실행에서 복원된 항목을 모두 지웁니다.
+///
+/// # Returns
+/// * 지운 항목 수
+pub fn purge_restored() -> Result<u64> {
+    let removed = db::open_connection()?.execute("DELETE FROM send_queue WHERE session != ?1", params![SESSION.as_str()])?;
+    Ok(removed as u64)
+}
+
+/// 지금 보낼 수 있는 항목 (기기가 발견되어 있고, 그 기기로 보내는 중이 아니며, 재시도 대기 중이 아닌 항목)
+///
+/// 기기마다 가장 앞의 항목 하나만 고릅니다.
+fn ready(items: Vec<QueuedSend>, available: &HashSet<String>, sending: &HashSet<String>, now: i64) -> Vec<QueuedSend> {
+    let mut chosen = HashSet::new();
+    items
+        .into_iter()
+        .filter(|item| available.contains(&item.device_id) && !sending.contains(&item.device_id))
+        .filter(|item| item.last_attempt_at.is_none_or(|at| now - at >= RETRY_DELAY))
+        .filter(|item| chosen.insert(item.device_id.clone()))
+        .collect()
+}
+
+/// 항목 하나를 보내고 결과를 대기열에 반영합니다.
+async fn send(item: QueuedSend) {
+    let result = peers::send_file_to_device(&item.device_id, &item.file_path, item.priority, false).await;
+
+    let update = db::open_connection().map_err(anyhow::Error::from).and_then(|conn| {
+        match &result {
+            Ok(addr) => {
+                tracing::info!("Sent queued {} to {} ({})", item.file_path, item.device_id, addr);
+                conn.execute("DELETE FROM send_queue WHERE id = ?1", params![item.id])?;
+            }
+            Err(e) if item.attempts + 1 >= MAX_ATTEMPTS => {
+                tracing::warn!("Dropping queued {} for {} after {} attempts: {:#}",
+                    item.file_path, item.device_id, MAX_ATTEMPTS, e);
+                conn.execute("DELETE FROM send_queue WHERE id = ?1", params![item.id])?;
+            }
+            Err(e) => {
+                tracing::warn!("Failed to send queued {} to {}: {:#}", item.file_path, item.device_id, e);
+                conn.execute(
+                    "UPDATE send_queue SET attempts = attempts + 1, last_attempt_at = ?2, last_error = ?3 WHERE id = ?1",
+                    params![item.id, unix_now(), format!("{:#}", e)],
+                )?;
+            }
+        }
+        Ok(())
+    });
+    if let Err(e) = update {
+        tracing::warn!("Failed to update send queue: {:#}", e);
+    }
+
+    SENDING.lock().unwrap().remove(&item.device_id);
+    WAKE.notify_one();
+}
+
+/// 보낼 수 있는 항목의 전송을 시작합니다.
+fn dispatch() -> Result<()> {
+    let available: HashSet<String> = discovery::get_discovered_devices()
+        .map(|devices| devices.into_iter().map(|device| device.device_id).collect())
+        .unwrap_or_default();
+    if available.is_empty() {
+        return Ok(());
+    }
+
+    let mut sending = SENDING.lock().unwrap();
+    for item in ready(list()?, &available, &sending, unix_now()) {
+        sending.insert(item.device_id.clone());
+        tokio::spawn(send(item));
+    }
+    Ok(())
+}
+
+/// 취소될 때까지 대기열의 항목을 기기가 발견되는 대로 보냅니다 (`start_pebble`이 실행).
+///
+/// 취소되어도 이미 시작한 전송은 끝까지 진행합니다.
+pub async fn run(token: CancellationToken) -> Result<()> {
+    let restored = list()?.iter().filter(|item| item.restored).count();
+    if restored > 0 {
+        tracing::info!("Restored {} queued send(s) from the previous session", restored);
+    }
+
+    loop {
+        if let Err(e) = dispatch() {
+            tracing::warn!("Failed to dispatch send queue: {:#}", e);
+        }
+
+        tokio::select! {
+            _ = token.cancelled() => return Ok(()),
+            _ = WAKE.notified() => {}
+            _ = tokio::time::sleep(POLL_INTERVAL) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: &str, device_id: &str, priority: TransferPriority, last_attempt_at: Option<i64>) -> QueuedSend {
+        QueuedSend {
+            id: id.to_string(),
+            device_id: device_id.to_string(),
+            file_path: format!("/tmp/{}", id),
+            priority,
+            queued_at: 0,
+            restored: false,
+            attempts: 0,
+            last_attempt_at,
+            last_error: None,
+        }
+    }
+
+    #[test]
+    fn test_queue_persists_and_restores() {
+        crate::loopback::use_temp_environment();
+        let dir = tempfile::TempDir::new().unwrap();
+        let file = dir.path().join("queued.txt");
+        std::fs::write(&file, b"queued").unwrap();
+        let file = file.to_string_lossy().to_string();
+
+        assert!(matches!(
+            PebbleError::from(enqueue("queue-device", &dir.path().join("missing").to_string_lossy(), TransferPriority::Low).unwrap_err()),
+            PebbleError::NotFound { .. }
+        ));
+
+        let low = enqueue("queue-device", &file, TransferPriority::Low).unwrap();
+        let high = enqueue("queue-device", &file, TransferPriority::High).unwrap();
+        let ours: Vec<QueuedSend> = list().unwrap().into_iter().filter(|i| i.device_id == "queue-device").collect();
+        assert_eq!(ours.iter().map(|i| i.id.as_str()).collect::<Vec<_>>(), vec![high.id.as_str(), low.id.as_str()]);
+        assert!(ours.iter().all(|i| !i.restored));
+
+        // 이전 실행에서 넣은 항목은 복원된 항목으로 보이고, 검토 후 지울 수 있음
+        db::open_connection()
+            .unwrap()
+            .execute("UPDATE send_queue SET session = 'previous' WHERE id = ?1", params![low.id])
+            .unwrap();
+        let restored: Vec<String> = list().unwrap().into_iter().filter(|i| i.restored).map(|i| i.id).collect();
+        assert!(restored.contains(&low.id) && !restored.contains(&high.id));
+        assert!(purge_restored().unwrap() >= 1);
+        assert!(!list().unwrap().iter().any(|i| i.id == low.id));
+        assert!(remove(&high.id).unwrap());
+        assert!(!remove(&high.id).unwrap());
+    }
+
+    #[test]
+    fn test_ready_respects_availability_and_retry_delay() {
+        let items = vec![
+            item("a1", "device-a", TransferPriority::High, None),
+            item("a2", "device-a", TransferPriority::Normal, None),
+            item("b1", "device-b", TransferPriority::Normal, Some(1_000)),
+            item("c1", "device-c", TransferPriority::Normal, None),
+            item("d1", "device-d", TransferPriority::Low, None),
+        ];
+        let available: HashSet<String> = ["device-a", "device-b", "device-c"].iter().map(|s| s.to_string()).collect();
+        let sending: HashSet<String> = ["device-c".to_string()].into_iter().collect();
+
+        let ids = |items: Vec<QueuedSend>| items.into_iter().map(|i| i.id).collect::<Vec<_>>();
+        assert_eq!(ids(ready(items.clone(), &available, &sending, 1_010)), vec!["a1"]);
+        assert_eq!(ids(ready(items, &available, &sending, 1_000 + RETRY_DELAY)), vec!["a1", "b1"]);
+    }
+}
@@ -0,0 +1,543 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::Path;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+use super::certificate::{CertificateManager, TlsCertificate};
+use super::config::{self, PebbleConfig};
+use super::error::PebbleError;
+use super::supervisor::TaskSupervisor;
+use super::transfer::TransferServer;
+use super::listeners::{self, ListenerStatus};
+use super::{db, discovery, history, lifecycle, logging, metrics, pool, presence, revocation, sendqueue, settings, staging, volume, wake, watcher};
+use super::util::unix_now;
+
+/// 기기 ID 파일 이름 (앱 데이터 디렉토리에 저장)
+const DEVICE_ID_FILE_NAME: &str = "device_id";
+
+/// 인증서 하위 디렉토리 이름
+pub const CERT_DIR_NAME: &str = "certs";
+
+/// Pebble 전체 서비스 시작 옵션
+#[derive(Debug, Clone)]
+pub struct PebbleStartOptions {
+    /// 설정, 기기 ID, 인증서를 저장할 앱 데이터 디렉토리
+    pub app_data_dir: String,
+
+    /// 현재 기기의 이름
+    pub device_name: String,
+
+    /// 비콘 HMAC 인증을 위한 비밀 키
+    pub secret_key: String,
+
+    /// 실시간 감시할 동기화 폴더 (None이면 감시하지 않음)
+    pub watch_path: Option<String>,
+
+    /// 적용할 설정 (None이면 앱 데이터 디렉토리의 설정 파일 사용)
+    pub config: Option<PebbleConfig>,
+}
+
+/// 서비스 시작 결과
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PebbleStartInfo {
+    /// 기기 고유 ID (재시작 후에도 유지됨)
+    pub device_id: String,
+
+    /// TLS 인증서 핑거프린트
+    pub fingerprint: String,
+
+    /// 실제로 바인딩된 전송 서버 포트
+    pub transfer_port: u16,
+}
+
+/// 상태를 추적하는 서비스 종류
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ServiceKind {
+    Discovery,
+    TransferServer,
+    Watcher,
+}
+
+/// 개별 서비스의 상태
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComponentStatus {
+    /// 실행 중 여부
+    pub running: bool,
+
+    /// 마지막으로 발생한 에러 메시지
+    pub last_error: Option<String>,
+
+    /// 마지막 에러 발생 시간 (Unix timestamp)
+    pub last_error_at: Option<i64>,
+}
+
+/// 전체 서비스 상태
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceStatus {
+    /// start_pebble로 시작된 상태인지 여부
+    pub pebble_running: bool,
+
+    /// 현재 기기 ID (start_pebble로 시작된 경우)
+    pub device_id: Option<String>,
+
+    /// 앱이 백그라운드로 전환되어 일시정지된 상태인지 여부
+    pub app_paused: bool,
+
+    pub discovery: ComponentStatus,
+    pub transfer_server: ComponentStatus,
+    pub watcher: ComponentStatus,
+
+    /// 전송 서버가 바인딩된 포트
+    pub transfer_port: Option<u16>,
+
+    /// 감시 중인 폴더 경로
+    pub watch_path: Option<String>,
+
+    /// 실행 중인 추가 전송 서버
+    pub listeners: Vec<ListenerStatus>,
+
+    /// 현재 발견된 기기 수
+    pub discovered_device_count: u32,
+}
+
+/// 실행 중인 서비스 핸들
+struct RunningServices {
+    info: PebbleStartInfo,
+    watching: bool,
+    /// 설정 변경 반영, 송신 대기열 처리 태스크
+    background_tasks: TaskSupervisor,
+}
+
+/// 실행 중인 전송 서버 핸들
+struct ServerHandle {
+    port: u16,
+    cert: TlsCertificate,
+    tasks: TaskSupervisor,
+}
+
+/// 전역 실행 상태
+static RUNNING: once_cell::sync::Lazy<Mutex<Option<RunningServices>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(None));
+
+/// 전송 서버 핸들 (start_pebble과 start_transfer_server가 공유)
+static TRANSFER_SERVER: once_cell::sync::Lazy<Mutex<Option<ServerHandle>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(None));
+
+/// 서비스별 마지막 에러 (메시지, 발생 시간)
+static LAST_ERRORS: once_cell::sync::Lazy<Mutex<HashMap<ServiceKind, (String, i64)>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 서비스의 에러를 기록합니다.
+///
+/// 백그라운드 태스크에서 발생해 호출자에게 전달되지 않는 에러를
+/// `get_service_status`로 확인할 수 있도록 보관합니다.
+pub fn record_error(kind: ServiceKind, message: impl Into<String>) {
+    metrics::record_error(kind);
+
+    let now = unix_now();
+
+    if let Ok(mut errors) = LAST_ERRORS.lock() {
+        errors.insert(kind, (message.into(), now));
+    }
+}
+
+/// 설정의 `transfer_bind`를 바인딩할 IP 주소로 바꿉니다.
+///
+/// IP 주소가 아니면 네트워크 인터페이스 이름으로 보고 그 인터페이스의 주소(IPv4 우선)를 사용합니다.
+///
+/// # Returns
+/// * None이면 모든 인터페이스(0.0.0.0), 그런 인터페이스가 없으면 `PebbleError::InvalidArgument`
+pub fn resolve_bind_ip(bind: Option<&str>) -> Result<IpAddr> {
+    let Some(bind) = bind.map(str::trim) else {
+        return Ok(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+    };
+    if let Ok(ip) = bind.parse() {
+        return Ok(ip);
+    }
+
+    let interfaces = local_ip_address::list_afinet_netifas()
+        .map_err(|e| PebbleError::network(format!("Failed to list network interfaces: {}", e)))?;
+    let addresses: Vec<IpAddr> = interfaces.into_iter().filter(|(name, _)| name == bind).map(|(_, ip)| ip).collect();
+    addresses
+        .iter()
+        .find(|ip| ip.is_ipv4())
+        .or(addresses.first())
+        .copied()
+        .ok_or_else(|| PebbleError::invalid_argument(format!("Unknown network interface: {}", bind)).into())
+}
+
+/// 전송 서버를 바인딩하고 백그라운드에서 실행합니다.
+///
+/// 이미 실행 중인 서버가 있으면 중지한 뒤 새 서버로 교체합니다.
+/// 설정의 `transfer_bind`가 있으면 그 주소나 인터페이스에만 바인딩합니다.
+///
+/// # Returns
+/// * `Result<u16>` - 실제로 바인딩된 포트 (0을 지정하면 임의 포트)
+pub async fn start_transfer_server(cert: TlsCertificate, port: u16) -> Result<u16> {
+    stop_transfer_server().await?;
+
+    let bind_ip = resolve_bind_ip(config::current().transfer_bind.as_deref())
+        .inspect_err(|e| record_error(ServiceKind::TransferServer, format!("{:#}", e)))?;
+    let bind_addr = SocketAddr::new(bind_ip, port);
+    listeners::set_certificate(cert.clone());
+    staging::set_device_key(&cert.key_der);
+    let server = TransferServer::new(cert.clone());
+    let listener = server.listen(bind_addr).await
+        .inspect_err(|e| record_error(ServiceKind::TransferServer, format!("{:#}", e)))?;
+    let bound_port = listener.local_addr()?.port();
+
+    let tasks = TaskSupervisor::new(ServiceKind::TransferServer);
+    tasks.spawn("transfer_server", |token| async move { server.serve(listener, token).await });
+    tasks.spawn("history_maintenance", history::run_maintenance);
+    tasks.spawn("wake_watchdog", wake::run_watchdog);
+
+    *TRANSFER_SERVER
+        .lock()
+        .map_err(|e| anyhow::anyhow!("Failed to acquire server lock: {}", e))? = Some(ServerHandle {
+        port: bound_port,
+        cert,
+        tasks,
+    });
+
+    // 상대 기기가 이전 포트로 연결하지 않도록 주기를 기다리지 않고 새 포트를 알림
+    discovery::announce_now();
+
+    Ok(bound_port)
+}
+
+/// 실행 중인 전송 서버를 중지하고 연결 처리가 끝날 때까지 기다립니다.
+pub async fn stop_transfer_server() -> Result<()> {
+    let handle = TRANSFER_SERVER
+        .lock()
+        .map_err(|e| anyhow::anyhow!("Failed to acquire server lock: {}", e))?
+        .take();
+
+    if let Some(handle) = handle {
+        handle.tasks.shutdown().await?;
+        tracing::info!("Transfer server on port {} stopped", handle.port);
+    }
+
+    Ok(())
+}
+
+/// 실행 중인 전송 서버를 같은 인증서로 다시 시작합니다 (포트, 바인딩 주소, 소켓 설정 변경 반영).
+///
+/// # Returns
+/// * 새로 바인딩된 포트, 실행 중인 서버가 없으면 None
+pub async fn restart_transfer_server(port: u16) -> Result<Option<u16>> {
+    let cert = TRANSFER_SERVER
+        .lock()
+        .map_err(|e| anyhow::anyhow!("Failed to acquire server lock: {}", e))?
+        .as_ref()
+        .map(|handle| handle.cert.clone());
+
+    let Some(cert) = cert else {
+        return Ok(None);
+    };
+    let bound_port = start_transfer_server(cert, port).await?;
+
+    if let Ok(mut running) = RUNNING.lock() {
+        if let Some(running) = running.as_mut() {
+            running.info.transfer_port = bound_port;
+        }
+    }
+
+    Ok(Some(bound_port))
+}
+
+/// 실행 중인 전송 서버의 인증서 (이 기기의 신원)
+pub fn certificate() -> Option<TlsCertificate> {
+    let server = TRANSFER_SERVER.lock().ok()?;
+    server.as_ref().map(|handle| handle.cert.clone())
+}
+
+/// 현재 실행 중인 전송 서버의 포트를 반환합니다.
+pub fn transfer_server_port() -> Option<u16> {
+    let server = TRANSFER_SERVER.lock().ok()?;
+    server
+        .as_ref()
+        .filter(|handle| handle.tasks.is_running())
+        .map(|handle| handle.port)
+}
+
+/// 앱 데이터 디렉토리에 저장된 기기 ID를 읽거나 새로 생성합니다.
+pub fn load_or_create_device_id(app_data_dir: &str) -> Result<String> {
+    let path = Path::new(app_data_dir).join(DEVICE_ID_FILE_NAME);
+
+    if let Ok(existing) = fs::read_to_string(&path) {
+        let existing = existing.trim();
+        if Uuid::parse_str(existing).is_ok() {
+            return Ok(existing.to_string());
+        }
+        tracing::warn!("Invalid device ID file, regenerating: {}", path.display());
+    }
+
+    fs::create_dir_all(app_data_dir)
+        .with_context(|| format!("Failed to create app data directory: {}", app_data_dir))?;
+
+    let device_id = Uuid::new_v4().to_string();
+    fs::write(&path, &device_id)
+        .with_context(|| format!("Failed to write device ID: {}", path.display()))?;
+
+    tracing::info!("Generated new device ID: {}", device_id);
+
+    Ok(device_id)
+}
+
+/// 모든 서비스를 의존성 순서대로 시작합니다.
+///
+/// # Process Flow
+/// 1. 설정 로드 (및 전달된 설정 적용)
+/// 2. DB 초기화 및 DB에 저장된 런타임 설정 적용
+/// 3. 기기 ID 및 TLS 인증서 준비
+/// 4. 전송 서버 바인딩 및 실행
+/// 5. 기기 탐색 시작
+/// 6. 초기 스캔 및 파일 감시 시작 (watch_path가 있는 경우)
+/// 7. 설정 변경을 실행 중인 서비스에 반영하는 태스크 시작
+///
+/// 중간 단계에서 실패하면 이미 시작된 서비스를 정리한 뒤 에러를 반환합니다.
+pub async fn start(options: PebbleStartOptions) -> Result<PebbleStartInfo> {
+    if is_running() {
+        anyhow::bail!("Pebble services are already running");
+    }
+
+    let log_dir = Path::new(&options.app_data_dir).join(logging::LOG_DIR_NAME);
+    if let Err(e) = logging::enable_file_logging(&log_dir) {
+        tracing::warn!("Failed to enable file logging: {:#}", e);
+    }
+
+    config::load(&options.app_data_dir).context("Failed to load configuration")?;
+    if let Some(new_config) = options.config.clone() {
+        config::update(new_config).context("Failed to apply configuration")?;
+    }
+
+    db::init_db().context("Failed to initialize database")?;
+    settings::apply_stored().context("Failed to apply stored settings")?;
+    let current_config = config::current();
+
+    let device_id = load_or_create_device_id(&options.app_data_dir)?;
+
+    let cert_dir = Path::new(&options.app_data_dir).join(CERT_DIR_NAME);
+    let cert = CertificateManager::new(cert_dir.to_string_lossy().to_string())
+        .get_or_create_certificate(&device_id, &options.device_name)
+        .context("Failed to prepare TLS certificate")?;
+    let fingerprint = cert.fingerprint.clone();
+
+    let transfer_port = start_transfer_server(cert, current_config.transfer_port).await
+        .context("Failed to start transfer server")?;
+
+    // 추가 전송 서버는 하나가 실패해도 나머지 서비스는 시작
+    for (name, e) in listeners::start_configured(&current_config.listeners).await {
+        record_error(ServiceKind::TransferServer, format!("Listener {}: {:#}", name, e));
+    }
+
+    if let Err(e) = discovery::start_discovery_with_id(
+        device_id.clone(),
+        options.device_name.clone(),
+        options.secret_key.clone(),
+        Some(fingerprint.clone()),
+    )
+    .await
+    {
+        record_error(ServiceKind::Discovery, format!("{:#}", e));
+        let _ = listeners::stop_all().await;
+        let _ = stop_transfer_server().await;
+        return Err(e.context("Failed to start device discovery"));
+    }
+
+    let mut watching = false;
+    if let Some(ref watch_path) = options.watch_path {
+        if let Err(e) = volume::attach_root(watch_path) {
+            tracing::warn!("Failed to attach volume identity to {}: {:#}", watch_path, e);
+        }
+        let watch_result = db::scan_directory(watch_path)
+            .context("Failed to perform initial directory scan")
+            .and_then(|_| watcher::start_watching(watch_path));
+
+        if let Err(e) = watch_result {
+            record_error(ServiceKind::Watcher, format!("{:#}", e));
+            let _ = discovery::stop_discovery().await;
+            let _ = listeners::stop_all().await;
+            let _ = stop_transfer_server().await;
+            return Err(e.context("Failed to start file watcher"));
+        }
+        watching = true;
+    }
+
+    let info = PebbleStartInfo {
+        device_id,
+        fingerprint,
+        transfer_port,
+    };
+
+    let background_tasks = TaskSupervisor::new(ServiceKind::TransferServer);
+    background_tasks.spawn("settings_watcher", settings::run_watcher);
+    background_tasks.spawn("send_queue", sendqueue::run);
+    background_tasks.spawn("presence_rules", presence::run);
+    background_tasks.spawn("revocation_delivery", revocation::run);
+
+    *RUNNING
+        .lock()
+        .map_err(|e| anyhow::anyhow!("Failed to acquire service lock: {}", e))? = Some(RunningServices {
+        info: info.clone(),
+        watching,
+        background_tasks,
+    });
+
+    tracing::info!("Pebble services started (device: {}, port: {})", info.device_id, info.transfer_port);
+
+    Ok(info)
+}
+
+/// 실행 중인 모든 서비스를 시작의 역순으로 중지합니다.
+///
+/// 각 서비스의 백그라운드 태스크가 끝날 때까지 기다리며, 일부 서비스가 에러를
+/// 보고해도 나머지 서비스는 계속 중지합니다. 실행 중이 아니면 아무 작업도 하지 않습니다.
+pub async fn stop() -> Result<()> {
+    let running = RUNNING
+        .lock()
+        .map_err(|e| anyhow::anyhow!("Failed to acquire service lock: {}", e))?
+        .take();
+
+    let Some(running) = running else {
+        return Ok(());
+    };
+
+    let mut errors = Vec::new();
+
+    // 중지하는 동안 설정 변경으로 서비스가 다시 시작되거나 대기열의 전송이 시작되지 않도록 먼저 중지
+    if let Err(e) = running.background_tasks.shutdown().await {
+        errors.push(format!("background tasks: {:#}", e));
+    }
+
+    if running.watching {
+        if let Err(e) = watcher::stop_watching().await {
+            errors.push(format!("watcher: {:#}", e));
+        }
+    }
+
+    if let Err(e) = discovery::stop_discovery().await {
+        errors.push(format!("discovery: {:#}", e));
+    }
+
+    if let Err(e) = listeners::stop_all().await {
+        errors.push(format!("listeners: {:#}", e));
+    }
+
+    if let Err(e) = stop_transfer_server().await {
+        errors.push(format!("transfer server: {:#}", e));
+    }
+
+    pool::global().clear();
+
+    if !errors.is_empty() {
+        anyhow::bail!("Failed to stop some services: {}", errors.join(", "));
+    }
+
+    tracing::info!("Pebble services stopped (device: {})", running.info.device_id);
+
+    Ok(())
+}
+
+/// 서비스가 실행 중인지 확인합니다.
+pub fn is_running() -> bool {
+    RUNNING.lock().map(|r| r.is_some()).unwrap_or(false)
+}
+
+/// start_pebble로 시작된 경우 현재 기기 ID를 반환합니다.
+pub fn device_id() -> Option<String> {
+    RUNNING
+        .lock()
+        .ok()
+        .and_then(|r| r.as_ref().map(|running| running.info.device_id.clone()))
+}
+
+/// 모든 서비스의 현재 상태를 수집합니다.
+pub fn status() -> ServiceStatus {
+    let errors = LAST_ERRORS.lock().map(|e| e.clone()).unwrap_or_default();
+    let component = |kind: ServiceKind, running: bool| {
+        let last = errors.get(&kind);
+        ComponentStatus {
+            running,
+            last_error: last.map(|(message, _)| message.clone()),
+            last_error_at: last.map(|(_, at)| *at),
+        }
+    };
+
+    let device_id = device_id();
+
+    let transfer_port = transfer_server_port();
+    let watch_path = watcher::current_watch_path();
+    let discovered_device_count = discovery::get_discovered_devices()
+        .map(|devices| devices.len() as u32)
+        .unwrap_or(0);
+
+    ServiceStatus {
+        pebble_running: device_id.is_some(),
+        device_id,
+        app_paused: lifecycle::is_paused(),
+        discovery: component(ServiceKind::Discovery, discovery::is_running()),
+        transfer_server: component(ServiceKind::TransferServer, transfer_port.is_some()),
+        watcher: component(ServiceKind::Watcher, watch_path.is_some()),
+        transfer_port,
+        watch_path,
+        listeners: listeners::list(),
+        discovered_device_count,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_bind_ip() {
+        assert_eq!(resolve_bind_ip(None).unwrap(), IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        assert_eq!(resolve_bind_ip(Some("127.0.0.1")).unwrap(), IpAddr::V4(Ipv4Addr::LOCALHOST));
+        assert_eq!(resolve_bind_ip(Some("::1")).unwrap(), "::1".parse::<IpAddr>().unwrap());
+
+        let err = PebbleError::from(resolve_bind_ip(Some("no-such-interface0")).unwrap_err());
+        assert!(matches!(err, PebbleError::InvalidArgument { .. } | PebbleError::Network { .. }));
+    }
+
+    #[test]
+    fn test_device_id_is_kept_and_invalid_file_regenerated() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let app_data_dir = dir.path().join("app").to_string_lossy().to_string();
+
+        let device_id = load_or_create_device_id(&app_data_dir).unwrap();
+        assert!(Uuid::parse_str(&device_id).is_ok());
+        assert_eq!(load_or_create_device_id(&app_data_dir).unwrap(), device_id);
+
+        fs::write(Path::new(&app_data_dir).join(DEVICE_ID_FILE_NAME), "not-a-uuid").unwrap();
+        let regenerated = load_or_create_device_id(&app_data_dir).unwrap();
+        assert_ne!(regenerated, device_id);
+        assert!(Uuid::parse_str(&regenerated).is_ok());
+    }
+
+    #[test]
+    fn test_status_reports_last_error_per_service() {
+        record_error(ServiceKind::Discovery, "Multicast join failed");
+        let status = status();
+
+        // 다른 테스트가 같은 서비스의 에러를 기록할 수 있으므로 종류별 보관 여부만 확인
+        assert!(status.discovery.last_error.is_some());
+        assert!(status.discovery.last_error_at.is_some_and(|at| at > 0));
+        assert!(!status.pebble_running);
+        assert!(status.device_id.is_none());
+
+        let json = serde_json::to_value(&status).unwrap();
+        assert!(json["discovery"]["last_error"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_stop_without_start_is_a_no_op() {
+        assert!(!is_running());
+        stop().await.unwrap();
+        assert!(device_id().is_none());
+    }
+}
@@ -0,0 +1,307 @@
+//! 런타임 설정 (Runtime Settings)
+//!
+//! 설정 키별 값을 DB의 `settings` 테이블에 저장해 재시작 후에도 유지하고, 바뀐 값을 실행 중인
+//! 구성 요소에 알립니다. 설정 파일(`config`)의 값이 기본이고, 저장된 설정이 그 위에 덮어씁니다.
+//!
+//! 전송 속도 제한이나 비콘 주기처럼 사용할 때마다 `config::current()`를 읽는 값은 바로 반영되고,
+//! 전송 서버 포트, 바인딩 주소, 소켓 설정, 추가 전송 서버, 비콘 포트처럼 시작할 때 정해지는 값은
+//! `run_watcher`가 해당 서비스를 다시 시작해 반영합니다 (진행 중인 수신은 끊김).
+//!
+//! # Process Flow
+//! 1. `set`으로 키 하나의 값(JSON)을 현재 설정에 덮어써 검증한 뒤 DB에 저장하고 전역 설정에 적용
+//! 2. 전역 설정의 watch 채널로 구독자에게 알림 (`watch`는 관심 있는 값이 바뀔 때만 깨어남)
+//! 3. 서비스를 시작할 때 `apply_stored`로 저장된 설정을 설정 파일 값 위에 다시 적용
+//! 4. `reset`은 저장된 값을 지우고 설정 파일의 값으로 되돌림
+
+use anyhow::Result;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+use super::config::{self, PebbleConfig};
+use super::db;
+use super::error::PebbleError;
+use super::service::{self, ServiceKind};
+use super::{discovery, listeners};
+use super::util::unix_now;
+
+/// DB에 저장할 수 없는 설정 키 (DB 위치는 설정 파일에만 둠)
+const FILE_ONLY_KEYS: &[&str] = &["db_path"];
+
+/// DB에 저장된 설정 하나
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Setting {
+    /// 설정 키 (`PebbleConfig`의 필드 이름, 예: "max_transfer_rate")
+    pub key: String,
+
+    /// 값 (JSON)
+    pub value: String,
+
+    /// 마지막으로 바꾼 시각 (Unix timestamp)
+    pub updated_at: i64,
+}
+
+fn config_fields(config: &PebbleConfig) -> Result<Map<String, Value>> {
+    match serde_json::to_value(config)? {
+        Value::Object(fields) => Ok(fields),
+        _ => anyhow::bail!("Configuration is not a JSON object"),
+    }
+}
+
+fn check_key(fields: &Map<String, Value>, key: &str) -> Result<()> {
+    if !fields.contains_key(key) || FILE_ONLY_KEYS.contains(&key) {
+        return Err(PebbleError::not_found(format!("Setting {}", key)).into());
+    }
+    Ok(())
+}
+
+/// 설정 하나를 덮어쓴 새 설정을 만들고 검증합니다.
+fn overlay(base: &PebbleConfig, key: &str, value: Value) -> Result<PebbleConfig> {
+    let mut fields = config_fields(base)?;
+    check_key(&fields, key)?;
+    fields.insert(key.to_string(), value);
+
+    let config: PebbleConfig = serde_json::from_value(Value::Object(fields))
+        .map_err(|e| PebbleError::invalid_argument(format!("Invalid value for {}: {}", key, e)))?;
+    config.validate().map_err(|e| PebbleError::invalid_argument(e.to_string()))?;
+    Ok(config)
+}
+
+fn parse_value(value: &str) -> Result<Value> {
+    serde_json::from_str(value)
+        .map_err(|e| PebbleError::invalid_argument(format!("Setting value is not valid JSON: {}", e)).into())
+}
+
+/// 설정 값을 바꾸고 DB에 저장합니다.
+///
+/// # Arguments
+/// * `key` - `PebbleConfig`의 필드 이름 (`db_path` 제외)
+/// * `value` - 새 값 (JSON, 예: `5000000`, `"en0"`, `null`)
+///
+/// # Returns
+/// * 적용된 전체 설정
+/// * 모르는 키면 `PebbleError::NotFound`, 값이 맞지 않거나 검증에 실패하면 `PebbleError::InvalidArgument`
+pub fn set(key: &str, value: &str) -> Result<PebbleConfig> {
+    let value = parse_value(value)?;
+    let config = overlay(&config::current(), key, value.clone())?;
+
+    let updated_at = unix_now();
+    db::open_connection()?.execute(
+        "INSERT INTO settings (key, value, updated_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+        params![key, value.to_string(), updated_at],
+    )?;
+
+    if config::apply(config.clone()) {
+        tracing::info!("Setting {} changed to {}", key, value);
+    }
+
+    Ok(config)
+}
+
+/// 현재 적용된 설정 값을 JSON으로 반환합니다 (저장된 설정이 없으면 설정 파일의 값).
+///
+/// # Returns
+/// * 모르는 키면 `PebbleError::NotFound`
+pub fn get(key: &str) -> Result<String> {
+    let fields = config_fields(&config::current())?;
+    check_key(&fields, key)?;
+    Ok(fields[key].to_string())
+}
+
+/// DB에 저장된 설정 목록 (키 순)
+pub fn list() -> Result<Vec<Setting>> {
+    let conn = db::open_connection()?;
+    let mut stmt = conn.prepare("SELECT key, value, updated_at FROM settings ORDER BY key")?;
+    let settings = stmt
+        .query_map([], |row| {
+            Ok(Setting {
+                key: row.get(0)?,
+                value: row.get(1)?,
+                updated_at: row.get(2)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(settings)
+}
+
+/// 저장된 설정을 지우고 설정 파일의 값으로 되돌립니다.
+///
+/// # Returns
+/// * 저장된 값이 있었으면 true
+pub fn reset(key: &str) -> Result<bool> {
+    let file_fields = config_fields(&config::file_config()?)?;
+    check_key(&file_fields, key)?;
+
+    let removed = db::open_connection()?.execute("DELETE FROM settings WHERE key = ?1", params![key])? > 0;
+    if removed {
+        let config = overlay(&config::current(), key, file_fields[key].clone())?;
+        if config::apply(config) {
+            tracing::info!("Setting {} reset to {}", key, file_fields[key]);
+        }
+    }
+
+    Ok(removed)
+}
+
+/// DB에 저장된 설정을 현재 설정 위에 적용합니다 (설정 로드와 DB 초기화 이후).
+///
+/// 더 이상 없는 키나 검증에 실패하는 값은 건너뜁니다.
+///
+/// # Returns
+/// * 적용한 설정 수
+pub fn apply_stored() -> Result<usize> {
+    let mut config = config::current();
+    let mut applied = 0;
+
+    for setting in list()? {
+        match parse_value(&setting.value).and_then(|value| overlay(&config, &setting.key, value)) {
+            Ok(next) => {
+                config = next;
+                applied += 1;
+            }
+            Err(e) => tracing::warn!("Ignoring stored setting {}: {:#}", setting.key, e),
+        }
+    }
+
+    config::apply(config);
+
+    Ok(applied)
+}
+
+/// 설정 전체를 바꿀 때(`update_config`) 새 설정과 값이 다른 저장된 설정을 지웁니다.
+///
+/// 지우지 않으면 다음 시작 때 저장된 값이 새 설정을 다시 덮어씁니다.
+///
+/// # Returns
+/// * 지운 설정 수
+pub fn discard_overridden(config: &PebbleConfig) -> Result<usize> {
+    let fields = config_fields(config)?;
+    let conn = db::open_connection()?;
+
+    let mut removed = 0;
+    for setting in list()? {
+        let value = serde_json::from_str::<Value>(&setting.value).ok();
+        if value.as_ref() != fields.get(&setting.key) {
+            removed += conn.execute("DELETE FROM settings WHERE key = ?1", params![setting.key])?;
+        }
+    }
+
+    Ok(removed)
+}
+
+/// 설정 일부의 변경 알림
+///
+/// 전역 설정이 바뀌어도 `select`로 고른 값이 그대로면 깨어나지 않습니다.
+pub struct SettingWatcher<T> {
+    receiver: watch::Receiver<PebbleConfig>,
+    select: fn(&PebbleConfig) -> T,
+    last: T,
+}
+
+/// 설정 일부의 변경을 구독합니다.
+///
+/// # Arguments
+/// * `select` - 관심 있는 값을 고르는 함수 (예: `|config| config.transfer_port`)
+pub fn watch<T: PartialEq + Clone>(select: fn(&PebbleConfig) -> T) -> SettingWatcher<T> {
+    let mut receiver = config::subscribe();
+    let last = select(&receiver.borrow_and_update());
+    SettingWatcher { receiver, select, last }
+}
+
+impl<T: PartialEq + Clone> SettingWatcher<T> {
+    /// 마지막으로 확인한 값
+    pub fn current(&self) -> &T {
+        &self.last
+    }
+
+    /// 고른 값이 바뀔 때까지 기다려 새 값을 반환합니다 (취소해도 알림을 놓치지 않음).
+    pub async fn changed(&mut self) -> T {
+        loop {
+            if self.receiver.changed().await.is_err() {
+                // 전역 설정의 송신자는 닫히지 않으므로 도달하지 않음
+                std::future::pending::<()>().await;
+            }
+
+            let value = (self.select)(&self.receiver.borrow_and_update());
+            if value != self.last {
+                self.last = value.clone();
+                return value;
+            }
+        }
+    }
+}
+
+/// 시작할 때 정해지는 설정이 바뀌면 해당 서비스를 다시 시작합니다 (`start_pebble`이 실행).
+pub async fn run_watcher(token: CancellationToken) -> Result<()> {
+    let mut server = watch(|config| (config.transfer_port, config.transfer_bind.clone(), config.socket_tuning.clone()));
+    let mut extra_listeners = watch(|config| config.listeners.clone());
+    let mut discovery_port = watch(|config| config.discovery_port);
+
+    loop {
+        tokio::select! {
+            _ = token.cancelled() => break,
+            (port, _, _) = server.changed() => match service::restart_transfer_server(port).await {
+                Ok(Some(bound_port)) => tracing::info!("Transfer server restarted on port {} after settings change", bound_port),
+                Ok(None) => {}
+                Err(e) => service::record_error(ServiceKind::TransferServer, format!("Failed to restart transfer server: {:#}", e)),
+            },
+            configs = extra_listeners.changed() => {
+                if let Err(e) = listeners::stop_all().await {
+                    service::record_error(ServiceKind::TransferServer, format!("Failed to stop listeners: {:#}", e));
+                }
+                for (name, e) in listeners::start_configured(&configs).await {
+                    service::record_error(ServiceKind::TransferServer, format!("Listener {}: {:#}", name, e));
+                }
+            }
+            port = discovery_port.changed() => match discovery::restart_discovery().await {
+                Ok(restarted) => if restarted {
+                    tracing::info!("Discovery restarted on UDP port {} after settings change", port);
+                },
+                Err(e) => service::record_error(ServiceKind::Discovery, format!("Failed to restart discovery: {:#}", e)),
+            },
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_settings_persist_and_notify() {
+        crate::loopback::use_temp_environment();
+        let original = config::current().device_timeout_secs;
+        let mut timeout = watch(|config| config.device_timeout_secs);
+        assert_eq!(*timeout.current(), original);
+
+        let applied = set("device_timeout_secs", "45").unwrap();
+        assert_eq!(applied.device_timeout_secs, 45);
+        assert_eq!(get("device_timeout_secs").unwrap(), "45");
+        let changed = tokio::time::timeout(Duration::from_secs(5), timeout.changed()).await.unwrap();
+        assert_eq!(changed, 45);
+
+        let stored = list().unwrap().into_iter().find(|s| s.key == "device_timeout_secs").unwrap();
+        assert_eq!(stored.value, "45");
+
+        // 잘못된 키와 값은 저장하지 않음
+        assert!(matches!(PebbleError::from(set("db_path", "\"other.db\"").unwrap_err()), PebbleError::NotFound { .. }));
+        assert!(matches!(PebbleError::from(set("device_timeout_secs", "1").unwrap_err()), PebbleError::InvalidArgument { .. }));
+        assert!(matches!(PebbleError::from(set("device_timeout_secs", "abc").unwrap_err()), PebbleError::InvalidArgument { .. }));
+        assert_eq!(config::current().device_timeout_secs, 45);
+
+        // 저장된 값은 설정을 다시 로드한 뒤에도 적용됨
+        config::apply(PebbleConfig { device_timeout_secs: original, ..config::current() });
+        assert!(apply_stored().unwrap() >= 1);
+        assert_eq!(config::current().device_timeout_secs, 45);
+
+        assert!(reset("device_timeout_secs").unwrap());
+        assert!(!reset("device_timeout_secs").unwrap());
+        assert_eq!(config::current().device_timeout_secs, config::file_config().unwrap().device_timeout_secs);
+    }
+}
@@ -0,0 +1,223 @@
+//! 폴더 공유와 기기별 권한 (Share ACL)
+//!
+//! 폴더(공유 루트)를 특정 기기에 읽기 전용 또는 읽기/쓰기로 공유합니다.
+//! 공유 루트 아래로 들어오는 수신(push)은 보낸 기기에 쓰기 권한이 있을 때만 허용되고,
+//! 읽기(browse/pull) 요청은 `can_read`로 권한을 확인합니다.
+//!
+//! 공유하지 않은 경로는 이 모듈의 영향을 받지 않습니다 (기존 동작 유지).
+//!
+//! # Security
+//! - 기기는 송신측이 스스로 알리는 기기 ID로 식별합니다. 같은 비밀 키를 가진 기기가
+//!   다른 기기의 ID를 사칭하는 것까지 막지는 못합니다.
+//! - 기기 ID를 보내지 않는 구버전 기기는 공유 루트에 쓸 수 없습니다.
+
+use anyhow::{Context, Result};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use super::db;
+use super::error::PebbleError;
+use super::paths;
+use super::util::unix_now;
+
+/// 공유 권한
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SharePermission {
+    /// 탐색과 가져오기만 허용
+    ReadOnly,
+    /// 공유 루트 아래로의 수신도 허용
+    ReadWrite,
+}
+
+impl SharePermission {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::ReadOnly => "ReadOnly",
+            Self::ReadWrite => "ReadWrite",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "ReadWrite" => Self::ReadWrite,
+            // 알 수 없는 값은 더 좁은 권한으로 취급
+            _ => Self::ReadOnly,
+        }
+    }
+}
+
+/// 기기 하나에 대한 폴더 공유
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Share {
+    /// 공유 루트 (정규화된 절대 경로)
+    pub root_path: String,
+
+    /// 공유받는 기기 ID
+    pub peer_device_id: String,
+
+    pub permission: SharePermission,
+
+    /// 공유 시작 시각 (Unix timestamp)
+    pub created_at: i64,
+}
+
+/// 경로를 정규화된 절대 경로로 바꿉니다. 파일 시스템에는 접근하지 않습니다.
+pub(crate) fn absolute(path: &str) -> Result<String> {
+    if paths::is_uri(path) {
+        return Ok(path.to_string());
+    }
+    let absolute = std::path::absolute(path).with_context(|| format!("Invalid path: {}", path))?;
+    Ok(paths::normalize(absolute))
+}
+
+/// `path`가 `root`와 같거나 그 아래에 있는지 비교 키로 확인합니다 (Windows에서는 대소문자 구분 없음).
+fn is_within(path: &str, root: &str) -> bool {
+    Path::new(&paths::comparison_key(path)).starts_with(paths::comparison_key(root))
+}
+
+/// 폴더를 기기와 공유합니다. 이미 공유 중이면 권한을 바꿉니다.
+///
+/// # Arguments
+/// * `root_path` - 공유할 폴더
+/// * `peer_device_id` - 공유받을 기기 ID
+/// * `permission` - 공유 권한
+pub fn share_folder(root_path: &str, peer_device_id: &str, permission: SharePermission) -> Result<Share> {
+    if peer_device_id.trim().is_empty() {
+        return Err(PebbleError::invalid_argument("Peer device ID is empty").into());
+    }
+    if !Path::new(root_path).is_dir() {
+        return Err(PebbleError::invalid_argument(format!("Share root is not a directory: {}", root_path)).into());
+    }
+
+    let share = Share {
+        root_path: absolute(root_path)?,
+        peer_device_id: peer_device_id.to_string(),
+        permission,
+        created_at: unix_now(),
+    };
+
+    let conn = db::open_connection()?;
+    conn.execute(
+        "INSERT INTO shares (root_path, peer_device_id, permission, created_at) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(root_path, peer_device_id) DO UPDATE SET permission = excluded.permission",
+        params![share.root_path, share.peer_device_id, permission.as_str(), share.created_at],
+    )?;
+
+    tracing::info!("Shared {} with {} ({:?})", share.root_path, share.peer_device_id, permission);
+
+    Ok(share)
+}
+
+/// 기기와의 폴더 공유를 해제합니다.
+///
+/// # Returns
+/// * `Result<bool>` - 공유가 있었으면 true
+pub fn unshare_folder(root_path: &str, peer_device_id: &str) -> Result<bool> {
+    let conn = db::open_connection()?;
+    let removed = conn.execute(
+        "DELETE FROM shares WHERE root_path = ?1 AND peer_device_id = ?2",
+        params![absolute(root_path)?, peer_device_id],
+    )?;
+    Ok(removed > 0)
+}
+
+/// 모든 공유를 루트, 기기 순으로 조회합니다.
+pub fn list_shares() -> Result<Vec<Share>> {
+    let conn = db::open_connection()?;
+    let mut stmt = conn.prepare(
+        "SELECT root_path, peer_device_id, permission, created_at FROM shares ORDER BY root_path, peer_device_id",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(Share {
+            root_path: row.get(0)?,
+            peer_device_id: row.get(1)?,
+            permission: SharePermission::parse(&row.get::<_, String>(2)?),
+            created_at: row.get(3)?,
+        })
+    })?;
+    Ok(rows.collect::<rusqlite::Result<_>>()?)
+}
+
+/// 경로를 포함하는 공유 목록
+fn shares_covering(path: &str) -> Result<Vec<Share>> {
+    let path = absolute(path)?;
+    Ok(list_shares()?
+        .into_iter()
+        .filter(|share| is_within(&path, &share.root_path))
+        .collect())
+}
+
+/// 상대 기기가 보낸 파일을 `dest_path`에 저장해도 되는지 확인합니다.
+///
+/// 공유 루트 아래가 아니면 허용합니다. 공유 루트 아래면 보낸 기기가 그 루트에
+/// `ReadWrite` 권한을 가진 경우에만 허용합니다.
+///
+/// # Returns
+/// * 허용되지 않으면 `PebbleError::Rejected`
+pub fn check_write(dest_path: &str, sender_device_id: Option<&str>) -> Result<()> {
+    let covering = shares_covering(dest_path)?;
+    if covering.is_empty() {
+        return Ok(());
+    }
+
+    let writable = sender_device_id.is_some_and(|sender| {
+        covering
+            .iter()
+            .any(|share| share.peer_device_id == sender && share.permission == SharePermission::ReadWrite)
+    });
+
+    if writable {
+        Ok(())
+    } else {
+        Err(PebbleError::rejected(format!(
+            "{} is in a folder shared read-only with {}",
+            PathBuf::from(dest_path).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+            sender_device_id.unwrap_or("unknown device")
+        ))
+        .into())
+    }
+}
+
+/// 상대 기기가 `path`를 탐색하거나 가져갈 수 있는지 확인합니다.
+pub fn can_read(path: &str, peer_device_id: &str) -> Result<bool> {
+    Ok(shares_covering(path)?
+        .iter()
+        .any(|share| share.peer_device_id == peer_device_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_share_acl() {
+        crate::loopback::use_temp_environment();
+        let root = tempfile::TempDir::new().unwrap();
+        let root_path = root.path().to_string_lossy().to_string();
+        let inside = root.path().join("docs").join("plan.txt").to_string_lossy().to_string();
+        let outside = std::env::temp_dir().join("elsewhere.txt").to_string_lossy().to_string();
+
+        share_folder(&root_path, "reader", SharePermission::ReadOnly).unwrap();
+        share_folder(&root_path, "writer", SharePermission::ReadWrite).unwrap();
+
+        assert!(check_write(&inside, Some("reader")).is_err());
+        assert!(check_write(&inside, None).is_err());
+        assert!(check_write(&inside, Some("stranger")).is_err());
+        check_write(&inside, Some("writer")).unwrap();
+        check_write(&outside, Some("reader")).unwrap();
+
+        // 이름이 비슷한 형제 폴더는 공유 루트 밖
+        assert!(check_write(&format!("{}-other/a.txt", root_path), Some("reader")).is_ok());
+
+        assert!(can_read(&inside, "reader").unwrap());
+        assert!(!can_read(&inside, "stranger").unwrap());
+
+        // 권한 변경과 해제
+        share_folder(&root_path, "reader", SharePermission::ReadWrite).unwrap();
+        check_write(&inside, Some("reader")).unwrap();
+        assert!(unshare_folder(&root_path, "reader").unwrap());
+        assert!(unshare_folder(&root_path, "writer").unwrap());
+        check_write(&inside, Some("reader")).unwrap();
+    }
+}
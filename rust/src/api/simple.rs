@@ -1,23 +1,75 @@
-use crate::api::{db, watcher, discovery};
-use crate::api::db::FileMetadata;
+use crate::api::{accept, audit, bandwidth, clipboard, operations, scan, config, settings, partials, sendqueue, snapshot, staging, db, diagnostics, events, history, integrity, watcher, discovery, lifecycle, listeners, logging, messages, pairs, peers, probe, quota, revocation, rootstats, service, shares, speedtest, storage, volume};
+use crate::api::audit::{AuditPage, AuditQuery};
+use crate::api::bandwidth::{BandwidthUsage, CapStatus, UsageRange};
+use crate::api::clipboard::{ClipboardKind, ClipboardPublishReport};
+use crate::api::config::PebbleConfig;
+use crate::api::settings::Setting;
+use crate::api::partials::PartialCleanupReport;
+use crate::api::db::{DuplicateGroup, FileMetadata, FilePage, FileQuery, ReconcileReport, SyncStatus, VerifyReport};
+use crate::api::diagnostics::DiagnosticsReport;
 use crate::api::discovery::DiscoveredDevice;
+use crate::api::history::{PruneReport, TransferRecord};
+use crate::api::receipts::TransferReceipt;
+use crate::api::listeners::{ListenerConfig, ListenerStatus};
+use crate::api::info::{self, AppInfo};
+use crate::api::error::PebbleError;
+use crate::api::logging::LogLevel;
+use crate::api::messages::TextMessage;
+use crate::api::pairs::{SyncCursor, SyncEstimate, SyncPair};
+use crate::api::peers::{DeviceDetails, KnownPeer, PeerLabel};
+use crate::api::priority::TransferPriority;
+use crate::api::probe::ProbeResult;
+use crate::api::revocation::{Revocation, RevocationReport};
+use crate::api::rootstats::RootStats;
+use crate::api::sendqueue::QueuedSend;
+use crate::api::snapshot::{SnapshotFormat, SnapshotVerifyReport};
+use crate::api::staging::StagedFile;
+use crate::api::quota::PeerUsage;
+use crate::api::shares::{Share, SharePermission};
+use crate::api::operations::Operation;
+use crate::api::registry::{self, ActiveTransfer};
+use crate::api::scan::ScanProgress;
+use crate::api::service::{PebbleStartInfo, PebbleStartOptions, ServiceStatus};
+use crate::api::speedtest::SpeedTestReport;
+use crate::api::transfer::DirectoryTransferReport;
+use crate::frb_generated::StreamSink;
 
 #[flutter_rust_bridge::frb(sync)]
 pub fn greet(name: String) -> String {
     format!("Hello, {name}!")
 }
 
+/// 앱 버전, 프로토콜 버전, 지원 기능, 플랫폼 정보를 가져옵니다.
+///
+/// # Examples
+/// ```dart
+/// final appInfo = api.getAppInfo();
+/// print("Pebble ${appInfo.version} (protocol ${appInfo.protocolVersion}) on ${appInfo.os}");
+/// ```
+#[flutter_rust_bridge::frb(sync)]
+pub fn get_app_info() -> AppInfo {
+    info::app_info()
+}
+
+/// 상대 기기의 프로토콜 버전이 현재 버전과 호환되는지 확인합니다.
+///
+/// 발견된 기기의 `protocol_version`으로 호환성 경고를 표시할 때 사용합니다.
+#[flutter_rust_bridge::frb(sync)]
+pub fn is_protocol_compatible(peer_version: String) -> bool {
+    info::is_protocol_compatible(&peer_version)
+}
+
 #[flutter_rust_bridge::frb(init)]
 pub fn init_app() {
     flutter_rust_bridge::setup_default_user_utils();
 
     // 로깅 초기화 (이미 초기화된 경우 무시)
-    let _ = env_logger::try_init();
+    logging::init();
 
     if let Err(e) = db::init_db() {
-        log::error!("Failed to initialize database: {}", e);
+        tracing::error!("Failed to initialize database: {}", e);
     } else {
-        log::info!("Database initialized successfully.");
+        tracing::info!("Database initialized successfully.");
     }
 }
 
@@ -27,16 +79,12 @@ pub fn init_app() {
 /// 이 함수는 이전 버전과의 호환성을 위해 유지되며,
 /// 실시간 감시를 사용하는 경우 start_file_watcher를 사용하세요.
 pub fn record_file_change(path: String, last_modified: i64, file_hash: String) {
-    let file_metadata = FileMetadata {
-        path,
-        last_modified,
-        file_hash,
-        sync_status: "Pending".to_string(),
-    };
+    let file_size = std::fs::metadata(&path).map(|m| m.len() as i64).unwrap_or(0);
+    let file_metadata = FileMetadata::new(path, last_modified, file_hash, SyncStatus::Pending, file_size);
 
     match db::upsert_file(file_metadata) {
-        Ok(_) => log::info!("File change recorded successfully."),
-        Err(e) => log::error!("Failed to record file change: {}", e),
+        Ok(_) => tracing::info!("File change recorded successfully."),
+        Err(e) => tracing::error!("Failed to record file change: {}", e),
     }
 }
 
@@ -46,15 +94,15 @@ pub fn record_file_change(path: String, last_modified: i64, file_hash: String) {
 /// * `watch_path` - 감시할 디렉토리의 절대 경로
 ///
 /// # Returns
-/// * `Result<String, String>` - 성공 시 성공 메시지, 실패 시 에러 메시지
+/// * `Result<String, PebbleError>` - 성공 시 성공 메시지, 실패 시 PebbleError
 ///
 /// # Examples
 /// ```dart
-/// final result = await api.startFileWatcher(watchPath: "/path/to/sync/folder");
-/// if (result.isOk) {
-///   print("Watcher started: ${result.ok}");
-/// } else {
-///   print("Error: ${result.err}");
+/// try {
+///   final msg = await api.startFileWatcher(watchPath: "/path/to/sync/folder");
+///   print("Watcher started: $msg");
+/// } on PebbleError catch (e) {
+///   print("Error: $e");
 /// }
 /// ```
 ///
@@ -62,27 +110,30 @@ pub fn record_file_change(path: String, last_modified: i64, file_hash: String) {
 /// - 경로가 존재하고 디렉토리인지 검증
 /// - 백그라운드 스레드에서 실행되어 UI를 차단하지 않음
 /// - 파일 변경 시 자동으로 blake3 해시 계산 및 DB 업데이트
-pub fn start_file_watcher(watch_path: String) -> Result<String, String> {
-    log::info!("Starting file watcher for: {}", watch_path);
+pub fn start_file_watcher(watch_path: String) -> Result<String, PebbleError> {
+    tracing::info!("Starting file watcher for: {}", watch_path);
+
+    // 다른 경로로 다시 연결된 드라이브면 기존 기록을 새 경로로 옮김
+    if let Err(e) = volume::attach_root(&watch_path) {
+        tracing::warn!("Failed to attach volume identity to {}: {:#}", watch_path, e);
+    }
 
     // 초기 디렉토리 스캔
     if let Err(e) = db::scan_directory(&watch_path) {
-        let error_msg = format!("Failed to perform initial directory scan: {}", e);
-        log::error!("{}", error_msg);
-        return Err(error_msg);
+        tracing::error!("Failed to perform initial directory scan: {:#}", e);
+        return Err(e.into());
     }
 
     // 파일 감시 시작
     match watcher::start_watching(&watch_path) {
         Ok(_) => {
             let success_msg = format!("File watcher started successfully for: {}", watch_path);
-            log::info!("{}", success_msg);
+            tracing::info!("{}", success_msg);
             Ok(success_msg)
         }
         Err(e) => {
-            let error_msg = format!("Failed to start file watcher: {}", e);
-            log::error!("{}", error_msg);
-            Err(error_msg)
+            tracing::error!("Failed to start file watcher: {:#}", e);
+            Err(e.into())
         }
     }
 }
@@ -90,54 +141,115 @@ pub fn start_file_watcher(watch_path: String) -> Result<String, String> {
 /// 실시간 파일 감시를 중지합니다.
 ///
 /// # Returns
-/// * `Result<String, String>` - 성공 시 성공 메시지, 실패 시 에러 메시지
+/// * `Result<String, PebbleError>` - 성공 시 성공 메시지, 실패 시 PebbleError
 ///
 /// # Examples
 /// ```dart
-/// final result = await api.stopFileWatcher();
-/// if (result.isOk) {
-///   print("Watcher stopped: ${result.ok}");
-/// }
+/// final msg = await api.stopFileWatcher();
+/// print("Watcher stopped: $msg");
 /// ```
-pub fn stop_file_watcher() -> Result<String, String> {
-    match watcher::stop_watching() {
+pub async fn stop_file_watcher() -> Result<String, PebbleError> {
+    match watcher::stop_watching().await {
         Ok(_) => {
             let success_msg = "File watcher stopped successfully".to_string();
-            log::info!("{}", success_msg);
+            tracing::info!("{}", success_msg);
             Ok(success_msg)
         }
         Err(e) => {
-            let error_msg = format!("Failed to stop file watcher: {}", e);
-            log::error!("{}", error_msg);
-            Err(error_msg)
+            tracing::error!("Failed to stop file watcher: {:#}", e);
+            Err(e.into())
         }
     }
 }
 
+/// 감시 폴더를 읽을 수 없어(외장 드라이브 분리 등) 감시가 멈춘 상태인지 확인합니다.
+///
+/// 폴더가 다시 나타나면 재조정 스캔 후 자동으로 감시를 재개합니다.
+/// 상태가 바뀔 때 `WatchRootLost` / `WatchRootRestored` 이벤트도 발생합니다.
+#[flutter_rust_bridge::frb(sync)]
+pub fn is_watch_root_lost() -> bool {
+    watcher::is_watch_root_lost()
+}
+
 /// 동기화가 필요한 파일 목록을 가져옵니다.
 ///
 /// # Returns
-/// * `Result<Vec<String>, String>` - 성공 시 파일 경로 목록, 실패 시 에러 메시지
+/// * `Result<Vec<String>, PebbleError>` - 성공 시 파일 경로 목록, 실패 시 PebbleError
 ///
 /// # Examples
 /// ```dart
-/// final result = await api.getPendingFiles();
-/// if (result.isOk) {
-///   for (final filePath in result.ok) {
-///     print("Pending: $filePath");
-///   }
+/// final files = await api.getPendingFiles();
+/// for (final filePath in files) {
+///   print("Pending: $filePath");
 /// }
 /// ```
-pub fn get_pending_files() -> Result<Vec<String>, String> {
+pub fn get_pending_files() -> Result<Vec<String>, PebbleError> {
     match db::get_pending_files() {
         Ok(files) => {
-            log::debug!("Retrieved {} pending files", files.len());
+            tracing::debug!("Retrieved {} pending files", files.len());
+            Ok(files)
+        }
+        Err(e) => {
+            tracing::error!("Failed to get pending files: {:#}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// 동기화가 필요한 파일의 전체 정보(해시, 수정 시간, 크기, 상태)를 가져옵니다.
+///
+/// # Returns
+/// * `Result<Vec<FileMetadata>, PebbleError>` - 경로 순으로 정렬된 파일 정보 목록
+pub fn get_pending_file_details() -> Result<Vec<FileMetadata>, PebbleError> {
+    match db::get_pending_file_details() {
+        Ok(files) => {
+            tracing::debug!("Retrieved {} pending file details", files.len());
             Ok(files)
         }
         Err(e) => {
-            let error_msg = format!("Failed to get pending files: {}", e);
-            log::error!("{}", error_msg);
-            Err(error_msg)
+            tracing::error!("Failed to get pending file details: {:#}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// 특정 파일의 메타데이터를 가져옵니다.
+///
+/// # Returns
+/// * `Result<Option<FileMetadata>, PebbleError>` - DB에 없는 파일이면 None
+pub fn get_file_metadata(path: String) -> Result<Option<FileMetadata>, PebbleError> {
+    match db::get_file_metadata(&path) {
+        Ok(metadata) => Ok(metadata),
+        Err(e) => {
+            tracing::error!("Failed to get file metadata: {:#}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// 조건에 맞는 파일 목록을 페이지 단위로 가져옵니다.
+///
+/// # Examples
+/// ```dart
+/// final page = await api.queryFiles(query: FileQuery(
+///   syncStatus: const SyncStatus.pending(),
+///   sortBy: FileSortKey.size,
+///   descending: true,
+///   offset: 0,
+///   limit: 50,
+/// ));
+/// print("Showing ${page.files.length} of ${page.totalCount}");
+/// ```
+pub fn query_files(query: FileQuery) -> Result<FilePage, PebbleError> {
+    if query.limit == 0 {
+        return Err(PebbleError::invalid_argument("limit must be greater than 0"));
+    }
+
+    match db::query_files(&query) {
+        Ok(page) => Ok(page),
+        Err(e) => {
+            tracing::error!("Failed to query files: {:#}", e);
+            Err(e.into())
         }
     }
 }
@@ -146,25 +258,222 @@ pub fn get_pending_files() -> Result<Vec<String>, String> {
 ///
 /// # Arguments
 /// * `file_path` - 파일 경로
-/// * `status` - 새로운 상태 ("Pending", "Synced", "Failed", "Deleted")
+/// * `status` - 새로운 상태 (허용되지 않는 전이면 InvalidArgument, 예: Deleted → Synced)
 ///
 /// # Returns
-/// * `Result<String, String>` - 성공 시 성공 메시지, 실패 시 에러 메시지
-pub fn update_file_status(file_path: String, status: String) -> Result<String, String> {
+/// * `Result<String, PebbleError>` - 성공 시 성공 메시지, 실패 시 PebbleError
+///
+/// # Examples
+/// ```dart
+/// await api.updateFileStatus(filePath: path, status: const SyncStatus.conflicted());
+/// ```
+pub fn update_file_status(file_path: String, status: SyncStatus) -> Result<String, PebbleError> {
     match db::update_sync_status(&file_path, &status) {
         Ok(_) => {
             let success_msg = format!("Updated {} to status: {}", file_path, status);
-            log::info!("{}", success_msg);
+            tracing::info!("{}", success_msg);
             Ok(success_msg)
         }
         Err(e) => {
-            let error_msg = format!("Failed to update file status: {}", e);
-            log::error!("{}", error_msg);
-            Err(error_msg)
+            tracing::error!("Failed to update file status: {:#}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// 진행률을 보고하며 파일의 blake3 해시를 계산합니다.
+///
+/// 진행률은 `{"bytes_hashed": u64, "total_bytes": u64}` 형태의 JSON으로
+/// 스트림에 전달됩니다. 수 GB 파일을 해시할 때 진행률 표시줄에 사용합니다.
+///
+/// # Returns
+/// * `Result<String, PebbleError>` - 성공 시 16진수 해시값
+///
+/// # Examples
+/// ```dart
+/// final hash = await api.hashFileWithProgress(path: path, sink: controller.sink);
+/// ```
+pub async fn hash_file_with_progress(path: String, sink: StreamSink<String>) -> Result<String, PebbleError> {
+    // 해시 계산은 블로킹 작업이므로 별도 스레드에서 실행
+    let result = tokio::task::spawn_blocking(move || {
+        integrity::hash_file_with_progress(&path, |bytes_hashed, total_bytes| {
+            let progress = serde_json::json!({ "bytes_hashed": bytes_hashed, "total_bytes": total_bytes });
+            let _ = sink.add(progress.to_string());
+        })
+    })
+    .await
+    .map_err(|e| PebbleError::internal(format!("Hash task failed: {}", e)))?;
+
+    match result {
+        Ok(hash) => Ok(hash),
+        Err(e) => {
+            tracing::error!("Failed to hash file: {:#}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// 진행 상황을 보고하며 폴더를 초기 스캔합니다 (`startFileWatcher` 전에 큰 폴더를 미리 스캔할 때).
+///
+/// 진행 상황은 JSON으로 직렬화된 ScanProgress로 스트림에 전달됩니다
+/// (`phase`가 Discovering → Indexing → Finished 순으로 바뀜). `cancelDirectoryScan`으로 취소하면
+/// `PebbleError::Cancelled`를 반환하며, 그때까지 기록한 파일은 남습니다.
+///
+/// # Examples
+/// ```dart
+/// api.scanDirectoryWithProgress(root: root, sink: controller.sink);
+/// controller.stream.listen((json) {
+///   final p = jsonDecode(json);
+///   print("${p['files_indexed']}/${p['files_discovered']} ${p['current_path'] ?? ''}");
+/// });
+/// ```
+pub async fn scan_directory_with_progress(root: String, sink: StreamSink<String>) -> Result<ScanProgress, PebbleError> {
+    // 폴더 순회와 DB 기록은 블로킹 작업이므로 별도 스레드에서 실행
+    let result = tokio::task::spawn_blocking(move || {
+        scan::run(&root, |progress| match serde_json::to_string(progress) {
+            Ok(json) => {
+                let _ = sink.add(json);
+            }
+            Err(e) => tracing::error!("Failed to serialize scan progress: {}", e),
+        })
+    })
+    .await
+    .map_err(|e| PebbleError::internal(format!("Scan task failed: {}", e)))?;
+
+    result.map_err(|e| {
+        tracing::error!("Failed to scan directory: {:#}", e);
+        e.into()
+    })
+}
+
+/// 진행 중인 초기 스캔을 취소합니다 (`startFileWatcher`의 스캔 포함).
+///
+/// # Returns
+/// * 그 폴더의 스캔이 진행 중이었으면 true
+#[flutter_rust_bridge::frb(sync)]
+pub fn cancel_directory_scan(root: String) -> bool {
+    scan::cancel(&root)
+}
+
+/// 동기화 폴더의 모든 파일을 다시 해시하여 손상(비트 부패) 여부를 검사합니다.
+///
+/// # Arguments
+/// * `root` - 검사할 동기화 루트 디렉토리
+///
+/// # Returns
+/// * `Result<VerifyReport, PebbleError>` - 불일치/누락/수정된 파일 목록
+pub async fn verify_tree(root: String) -> Result<VerifyReport, PebbleError> {
+    // 모든 파일의 해시를 계산하므로 블로킹 스레드에서 실행
+    let result = tokio::task::spawn_blocking(move || db::verify_tree(&root))
+        .await
+        .map_err(|e| PebbleError::internal(format!("Verify task failed: {}", e)))?;
+
+    match result {
+        Ok(report) => Ok(report),
+        Err(e) => {
+            tracing::error!("Failed to verify tree: {:#}", e);
+            Err(e.into())
         }
     }
 }
 
+/// 색인된 파일 중 내용이 같은 파일들을 찾습니다.
+///
+/// 스캔할 때 저장한 해시로 비교하므로 파일을 다시 읽지 않습니다. 큰 폴더를 동기화하기 전에
+/// 중복 파일을 정리하는 데 사용합니다. 삭제된 파일과 빈 파일은 제외합니다.
+///
+/// # Arguments
+/// * `root` - 찾을 루트 디렉토리
+///
+/// # Returns
+/// * 확보할 수 있는 바이트(`wasted_bytes`)가 큰 순서의 묶음 목록
+///
+/// # Examples
+/// ```dart
+/// final groups = await api.findDuplicates(root: "/home/user/Photos");
+/// for (final group in groups) {
+///   print("${group.paths.length} copies, ${group.wastedBytes} bytes reclaimable");
+/// }
+/// ```
+pub fn find_duplicates(root: String) -> Result<Vec<DuplicateGroup>, PebbleError> {
+    db::find_duplicates(&root).map_err(|e| {
+        tracing::error!("Failed to find duplicates under {}: {:#}", root, e);
+        e.into()
+    })
+}
+
+/// 동기화 루트의 저장 공간 사용량을 계산합니다.
+///
+/// 감시자가 유지하는 색인에서 계산하므로 파일 시스템을 훑지 않습니다.
+/// 삭제된 파일은 제외합니다.
+///
+/// # Arguments
+/// * `root` - 동기화 루트 디렉토리
+///
+/// # Returns
+/// * 파일 수, 전체 크기, 종류/확장자별 크기, 가장 큰 파일
+///
+/// # Examples
+/// ```dart
+/// final stats = await api.getRootStats(root: "/home/user/Photos");
+/// for (final usage in stats.byCategory) {
+///   print("${usage.category}: ${usage.totalBytes} bytes in ${usage.fileCount} files");
+/// }
+/// ```
+pub async fn get_root_stats(root: String) -> Result<RootStats, PebbleError> {
+    let result = tokio::task::spawn_blocking(move || rootstats::get_root_stats(&root))
+        .await
+        .map_err(|e| PebbleError::internal(format!("Root stats task failed: {}", e)))?;
+
+    result.map_err(|e| {
+        tracing::error!("Failed to compute root stats: {:#}", e);
+        e.into()
+    })
+}
+
+/// 동기화 폴더의 현재 상태(상대 경로, 해시, 크기, 수정 시각)를 서명한 스냅샷으로 내보냅니다.
+///
+/// 감사 기록이나 백업 검증용이며, 서명은 이 기기의 인증서로 하므로 Pebble이 실행 중이어야 합니다.
+///
+/// # Arguments
+/// * `root` - 내보낼 동기화 루트 디렉토리
+/// * `format` - JSON 또는 CSV
+///
+/// # Returns
+/// * `Result<String, PebbleError>` - 스냅샷 파일 내용 (UI가 파일로 저장)
+pub async fn export_manifest(root: String, format: SnapshotFormat) -> Result<String, PebbleError> {
+    let result = tokio::task::spawn_blocking(move || snapshot::export(&root, format))
+        .await
+        .map_err(|e| PebbleError::internal(format!("Export task failed: {}", e)))?;
+
+    result.map_err(|e| {
+        tracing::error!("Failed to export manifest: {:#}", e);
+        e.into()
+    })
+}
+
+/// 이전에 내보낸 스냅샷과 동기화 폴더의 현재 상태를 비교합니다.
+///
+/// 백업을 다른 위치에 복원했어도 복원한 폴더를 `root`로 지정하면 상대 경로로 비교합니다.
+///
+/// # Arguments
+/// * `root` - 비교할 동기화 루트 디렉토리
+/// * `file` - `export_manifest`로 저장한 스냅샷 파일 경로
+///
+/// # Returns
+/// * `Result<SnapshotVerifyReport, PebbleError>` - 바뀐/사라진/새로 생긴 파일 목록
+///   (이 기기의 서명이 아니거나 파일이 수정되었으면 `PebbleError.rejected`)
+pub async fn verify_manifest(root: String, file: String) -> Result<SnapshotVerifyReport, PebbleError> {
+    let result = tokio::task::spawn_blocking(move || snapshot::verify(&root, &file))
+        .await
+        .map_err(|e| PebbleError::internal(format!("Verify task failed: {}", e)))?;
+
+    result.map_err(|e| {
+        tracing::error!("Failed to verify manifest: {:#}", e);
+        e.into()
+    })
+}
+
 // ============================================================================
 // Phase 2: 기기 탐색 (Discovery) API
 // ============================================================================
@@ -176,17 +485,15 @@ pub fn update_file_status(file_path: String, status: String) -> Result<String, S
 /// * `secret_key` - HMAC 인증을 위한 비밀 키 (모든 Pebble 기기가 공유)
 ///
 /// # Returns
-/// * `Result<String, String>` - 성공 시 기기 ID, 실패 시 에러 메시지
+/// * `Result<String, PebbleError>` - 성공 시 기기 ID, 실패 시 PebbleError
 ///
 /// # Examples
 /// ```dart
-/// final result = await api.startDeviceDiscovery(
+/// final deviceId = await api.startDeviceDiscovery(
 ///   deviceName: "My Device",
 ///   secretKey: "my-secret-psk-key-2024"
 /// );
-/// if (result.isOk) {
-///   print("Device ID: ${result.ok}");
-/// }
+/// print("Device ID: $deviceId");
 /// ```
 ///
 /// # Security
@@ -194,19 +501,18 @@ pub fn update_file_status(file_path: String, status: String) -> Result<String, S
 /// - HMAC-SHA256으로 메시지 서명 및 검증
 /// - 타임스탬프로 재생 공격(Replay Attack) 방지
 /// - Pre-Shared Key (PSK) 방식의 인증
-pub async fn start_device_discovery(device_name: String, secret_key: String) -> Result<String, String> {
-    log::info!("Starting device discovery: {}", device_name);
+pub async fn start_device_discovery(device_name: String, secret_key: String) -> Result<String, PebbleError> {
+    tracing::info!("Starting device discovery: {}", device_name);
 
     match discovery::start_discovery(device_name, secret_key).await {
         Ok(device_id) => {
             let success_msg = format!("Device discovery started. Device ID: {}", device_id);
-            log::info!("{}", success_msg);
+            tracing::info!("{}", success_msg);
             Ok(device_id)
         }
         Err(e) => {
-            let error_msg = format!("Failed to start device discovery: {}", e);
-            log::error!("{}", error_msg);
-            Err(error_msg)
+            tracing::error!("Failed to start device discovery: {:#}", e);
+            Err(e.into())
         }
     }
 }
@@ -214,51 +520,52 @@ pub async fn start_device_discovery(device_name: String, secret_key: String) ->
 /// 기기 탐색을 중지합니다.
 ///
 /// # Returns
-/// * `Result<String, String>` - 성공 시 성공 메시지, 실패 시 에러 메시지
+/// * `Result<String, PebbleError>` - 성공 시 성공 메시지, 실패 시 PebbleError
 ///
 /// # Examples
 /// ```dart
-/// final result = await api.stopDeviceDiscovery();
+/// await api.stopDeviceDiscovery();
 /// ```
-pub fn stop_device_discovery() -> Result<String, String> {
-    match discovery::stop_discovery() {
+pub async fn stop_device_discovery() -> Result<String, PebbleError> {
+    match discovery::stop_discovery().await {
         Ok(_) => {
             let success_msg = "Device discovery stopped successfully".to_string();
-            log::info!("{}", success_msg);
+            tracing::info!("{}", success_msg);
             Ok(success_msg)
         }
         Err(e) => {
-            let error_msg = format!("Failed to stop device discovery: {}", e);
-            log::error!("{}", error_msg);
-            Err(error_msg)
+            tracing::error!("Failed to stop device discovery: {:#}", e);
+            Err(e.into())
         }
     }
 }
 
 /// 발견된 Pebble 기기 목록을 가져옵니다.
 ///
+/// 각 기기의 `capabilities`로 상대가 지원하지 않는 기능(폴더 전송, 텍스트 메시지 등)을
+/// 미리 비활성화할 수 있습니다. None이면 기능 목록을 알리지 않는 구버전 기기입니다.
+/// 사용자가 별명을 붙인 기기는 `label`에 별명, 색상, 태그가 담깁니다 (`set_peer_label`).
+///
 /// # Returns
-/// * `Result<Vec<DiscoveredDevice>, String>` - 성공 시 기기 목록, 실패 시 에러 메시지
+/// * `Result<Vec<DiscoveredDevice>, PebbleError>` - 성공 시 기기 목록, 실패 시 PebbleError
 ///
 /// # Examples
 /// ```dart
-/// final result = await api.getDiscoveredDevices();
-/// if (result.isOk) {
-///   for (final device in result.ok) {
-///     print("Device: ${device.deviceName} (${device.ipAddress})");
-///   }
+/// final devices = await api.getDiscoveredDevices();
+/// for (final device in devices) {
+///   print("Device: ${device.label?.nickname ?? device.deviceName} (${device.ipAddress})");
+///   final canText = device.capabilities?.contains(Capability.textMessages) ?? true;
 /// }
 /// ```
-pub fn get_discovered_devices() -> Result<Vec<DiscoveredDevice>, String> {
-    match discovery::get_discovered_devices() {
+pub fn get_discovered_devices() -> Result<Vec<DiscoveredDevice>, PebbleError> {
+    match discovery::get_discovered_devices().and_then(peers::with_labels) {
         Ok(devices) => {
-            log::debug!("Retrieved {} discovered devices", devices.len());
+            tracing::debug!("Retrieved {} discovered devices", devices.len());
             Ok(devices)
         }
         Err(e) => {
-            let error_msg = format!("Failed to get discovered devices: {}", e);
-            log::error!("{}", error_msg);
-            Err(error_msg)
+            tracing::error!("Failed to get discovered devices: {:#}", e);
+            Err(e.into())
         }
     }
 }
@@ -275,7 +582,7 @@ pub fn get_discovered_devices() -> Result<Vec<DiscoveredDevice>, String> {
 /// * `cert_dir` - 인증서 저장 디렉토리
 ///
 /// # Returns
-/// * `Result<String, String>` - 성공 시 인증서 핑거프린트, 실패 시 에러 메시지
+/// * `Result<String, PebbleError>` - 성공 시 인증서 핑거프린트, 실패 시 PebbleError
 ///
 /// # Security
 /// - RSA 2048비트 자기 서명 인증서 생성
@@ -284,20 +591,19 @@ pub fn init_tls_certificate(
     device_id: String,
     device_name: String,
     cert_dir: String,
-) -> Result<String, String> {
+) -> Result<String, PebbleError> {
     use crate::api::certificate::CertificateManager;
 
     let manager = CertificateManager::new(cert_dir);
 
     match manager.get_or_create_certificate(&device_id, &device_name) {
         Ok(cert) => {
-            log::info!("TLS certificate initialized. Fingerprint: {}", cert.fingerprint);
+            tracing::info!("TLS certificate initialized. Fingerprint: {}", cert.fingerprint);
             Ok(cert.fingerprint)
         }
         Err(e) => {
-            let error_msg = format!("Failed to initialize TLS certificate: {}", e);
-            log::error!("{}", error_msg);
-            Err(error_msg)
+            tracing::error!("Failed to initialize TLS certificate: {:#}", e);
+            Err(e.into())
         }
     }
 }
@@ -308,10 +614,10 @@ pub fn init_tls_certificate(
 /// * `device_id` - 기기 고유 ID
 /// * `device_name` - 기기 이름
 /// * `cert_dir` - 인증서 디렉토리
-/// * `bind_port` - 바인딩할 포트 (기본값: 37846)
+/// * `bind_port` - 바인딩할 포트 (None이면 설정의 transfer_port)
 ///
 /// # Returns
-/// * `Result<String, String>` - 성공 시 성공 메시지, 실패 시 에러 메시지
+/// * `Result<String, PebbleError>` - 성공 시 성공 메시지, 실패 시 PebbleError
 ///
 /// # Security
 /// - TLS 1.3 암호화 연결
@@ -322,43 +628,74 @@ pub async fn start_transfer_server(
     device_name: String,
     cert_dir: String,
     bind_port: Option<u16>,
-) -> Result<String, String> {
+) -> Result<String, PebbleError> {
     use crate::api::certificate::CertificateManager;
-    use crate::api::transfer::{TransferServer, TRANSFER_PORT};
-    use std::net::SocketAddr;
 
     let manager = CertificateManager::new(cert_dir);
     let cert = manager.get_or_create_certificate(&device_id, &device_name)
-        .map_err(|e| format!("Failed to load certificate: {}", e))?;
+        .map_err(|e| {
+            tracing::error!("Failed to load certificate: {:#}", e);
+            PebbleError::from(e)
+        })?;
 
-    let port = bind_port.unwrap_or(TRANSFER_PORT);
-    let bind_addr: SocketAddr = format!("0.0.0.0:{}", port).parse()
-        .map_err(|e| format!("Invalid bind address: {}", e))?;
+    let port = bind_port.unwrap_or(config::current().transfer_port);
 
-    let server = TransferServer::new(cert);
-
-    // 백그라운드에서 서버 실행
-    tokio::spawn(async move {
-        if let Err(e) = server.start(bind_addr).await {
-            log::error!("Transfer server error: {}", e);
+    // 바인딩 후 백그라운드에서 서버 실행
+    match service::start_transfer_server(cert, port).await {
+        Ok(bound_port) => {
+            let success_msg = format!("Transfer server started on port {}", bound_port);
+            tracing::info!("{}", success_msg);
+            Ok(success_msg)
         }
-    });
+        Err(e) => {
+            tracing::error!("Failed to start transfer server: {:#}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// 다른 주소(VPN 인터페이스 등)에 추가 전송 서버를 시작합니다.
+///
+/// 기본 전송 서버와 같은 인증서를 사용하며, 저장 폴더와 수락 정책을 따로 정할 수 있습니다.
+/// 같은 이름의 서버가 실행 중이면 새 설정으로 교체합니다.
+///
+/// # Arguments
+/// * `listener` - 서버 이름, 바인딩 주소, 저장 폴더, 수락 정책 (None이면 전역 설정)
+///
+/// # Returns
+/// * `Result<ListenerStatus, PebbleError>` - 실제로 바인딩된 주소를 포함한 상태.
+///   기본 전송 서버가 시작되지 않았으면 `PebbleError::InvalidArgument`
+pub async fn add_transfer_listener(listener: ListenerConfig) -> Result<ListenerStatus, PebbleError> {
+    listeners::start(listener).await.map_err(|e| {
+        tracing::error!("Failed to start listener: {:#}", e);
+        PebbleError::from(e)
+    })
+}
+
+/// 추가 전송 서버를 중지합니다.
+///
+/// # Returns
+/// * `Result<bool, PebbleError>` - 그 이름의 서버가 실행 중이었으면 true
+pub async fn remove_transfer_listener(name: String) -> Result<bool, PebbleError> {
+    Ok(listeners::stop(&name).await?)
+}
 
-    let success_msg = format!("Transfer server started on port {}", port);
-    log::info!("{}", success_msg);
-    Ok(success_msg)
+/// 실행 중인 추가 전송 서버 목록을 가져옵니다.
+#[flutter_rust_bridge::frb(sync)]
+pub fn get_transfer_listeners() -> Vec<ListenerStatus> {
+    listeners::list()
 }
 
 /// 파일을 다른 기기로 전송합니다.
 ///
 /// # Arguments
 /// * `server_ip` - 수신 기기의 IP 주소
-/// * `server_port` - 수신 기기의 포트 (기본값: 37846)
+/// * `server_port` - 수신 기기의 포트 (None이면 설정의 transfer_port)
 /// * `file_path` - 전송할 파일 경로
 /// * `server_fingerprint` - 수신 기기 인증서의 핑거프린트 (Certificate Pinning용, Optional)
 ///
 /// # Returns
-/// * `Result<String, String>` - 성공 시 전송 ID, 실패 시 에러 메시지
+/// * `Result<String, PebbleError>` - 성공 시 전송 ID, 실패 시 PebbleError
 ///
 /// # Examples
 /// ```dart
@@ -374,27 +711,1593 @@ pub async fn send_file(
     server_port: Option<u16>,
     file_path: String,
     server_fingerprint: Option<String>,
-) -> Result<String, String> {
-    use crate::api::transfer::{TransferClient, TRANSFER_PORT};
+) -> Result<String, PebbleError> {
+    use crate::api::transfer::TransferClient;
     use std::net::SocketAddr;
 
-    let port = server_port.unwrap_or(TRANSFER_PORT);
+    let port = server_port.unwrap_or(config::current().transfer_port);
     let server_addr: SocketAddr = format!("{}:{}", server_ip, port).parse()
-        .map_err(|e| format!("Invalid server address: {}", e))?;
+        .map_err(|e| PebbleError::invalid_argument(format!("Invalid server address: {}", e)))?;
 
-    let client = TransferClient::new(server_fingerprint);
+    // 사용자가 직접 보낸 파일은 백그라운드 동기화보다 먼저 전송
+    let mut client = TransferClient::new(server_fingerprint);
+    client.set_priority(TransferPriority::High);
 
     // 파일 전송
     match client.send_file(server_addr, &file_path).await {
         Ok(_) => {
             let success_msg = format!("File sent successfully: {}", file_path);
-            log::info!("{}", success_msg);
+            tracing::info!("{}", success_msg);
+            Ok(success_msg)
+        }
+        Err(e) => {
+            tracing::error!("Failed to send file: {:#}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// 발견된 기기로 기기 ID를 이용해 파일을 전송합니다.
+///
+/// 현재 주소와 인증서 핑거프린트를 발견 목록(없으면 마지막으로 본 주소)에서 찾으므로
+/// UI가 DHCP로 바뀌는 IP 주소를 추적할 필요가 없습니다. 연결에 실패하면 비콘으로
+/// 새 주소가 알려질 때까지 기다렸다가 다시 시도합니다.
+///
+/// 사용자가 직접 보내는 전송이므로 High 우선순위로 보냅니다.
+///
+/// # Arguments
+/// * `device_id` - 수신 기기 ID (get_discovered_devices 결과)
+/// * `file_path` - 전송할 파일 경로
+///
+/// # Returns
+/// * `Result<String, PebbleError>` - 성공 메시지 (전송한 주소 포함)
+///
+/// # Examples
+/// ```dart
+/// await api.sendFileToDevice(deviceId: device.deviceId, filePath: "/path/to/file.pdf");
+/// ```
+pub async fn send_file_to_device(device_id: String, file_path: String) -> Result<String, PebbleError> {
+    send_file_to_device_with_priority(device_id, file_path, TransferPriority::High).await
+}
+
+/// 우선순위를 지정하여 기기 ID로 파일을 전송합니다.
+///
+/// 높은 우선순위의 송신이 진행 중이면 낮은 우선순위의 송신은 취소되지 않고
+/// 청크 전송만 멈췄다가(상태 Paused) 높은 우선순위의 송신이 끝나면 이어서 보냅니다.
+///
+/// # Arguments
+/// * `device_id` - 수신 기기 ID (get_discovered_devices 결과)
+/// * `file_path` - 전송할 파일 경로
+/// * `priority` - 송신 우선순위 (High: 사용자 전송, Low: 백그라운드 동기화)
+///
+/// # Returns
+/// * `Result<String, PebbleError>` - 성공 메시지 (전송한 주소 포함)
+///
+/// # Examples
+/// ```dart
+/// await api.sendFileToDeviceWithPriority(
+///   deviceId: device.deviceId,
+///   filePath: "/path/to/backup.tar",
+///   priority: TransferPriority.low,
+/// );
+/// ```
+pub async fn send_file_to_device_with_priority(
+    device_id: String,
+    file_path: String,
+    priority: TransferPriority,
+) -> Result<String, PebbleError> {
+    send_to_device(device_id, file_path, priority, false).await
+}
+
+/// 데이터 사용 한도를 무시하고 기기 ID로 파일을 전송합니다.
+///
+/// 한도에 도달하면 `send_file_to_device`는 `PebbleError.quotaExceeded`로 실패합니다.
+/// 사용자가 확인한 뒤 이 함수로 다시 보내면 한도와 관계없이 전송합니다
+/// (자동 동기화는 계속 멈춘 상태로 남음).
+///
+/// # Arguments
+/// * `device_id` - 수신 기기 ID
+/// * `file_path` - 전송할 파일 경로
+///
+/// # Returns
+/// * `Result<String, PebbleError>` - 성공 메시지 (전송한 주소 포함)
+///
+/// # Examples (Dart)
+/// ```dart
+/// try {
+///   await api.sendFileToDevice(deviceId: id, filePath: path);
+/// } on PebbleError_QuotaExceeded {
+///   if (await confirmOverCap()) {
+///     await api.sendFileToDeviceOverCap(deviceId: id, filePath: path);
+///   }
+/// }
+/// ```
+pub async fn send_file_to_device_over_cap(device_id: String, file_path: String) -> Result<String, PebbleError> {
+    send_to_device(device_id, file_path, TransferPriority::High, true).await
+}
+
+/// 폴더를 하위 폴더 구조를 유지한 채 기기로 전송합니다.
+///
+/// 폴더 아래의 파일을 하나의 연결로 차례로 보내며, 수신 기기는 받을 폴더 아래에
+/// 같은 이름의 폴더를 만들어 저장합니다. 일부 파일이 거부되어도 나머지는 계속 보냅니다.
+/// 폴더 전송을 모르는 구버전 기기(`Capability::FolderTransfer` 없음)는 모든 파일을 받을 폴더에 바로 저장합니다.
+///
+/// # Arguments
+/// * `device_id` - 수신 기기 ID
+/// * `dir_path` - 전송할 폴더 경로
+///
+/// # Returns
+/// * `DirectoryTransferReport` - 보낸 파일과 보내지 못한 파일
+///
+/// # Examples
+/// ```dart
+/// final report = await api.sendDirectoryToDevice(deviceId: device.deviceId, dirPath: "/path/to/Photos");
+/// print("${report.sent.length} files sent, ${report.failed.length} failed");
+/// ```
+pub async fn send_directory_to_device(device_id: String, dir_path: String) -> Result<DirectoryTransferReport, PebbleError> {
+    use crate::api::transfer::TransferClient;
+
+    let peer = peers::resolve(&device_id).map_err(PebbleError::from)?;
+    let mut client = TransferClient::new(peer.fingerprint);
+    client.set_priority(TransferPriority::High);
+
+    client.send_directory(&peer.addrs, &dir_path).await.map_err(|e| {
+        tracing::error!("Failed to send folder {} to {}: {:#}", dir_path, device_id, e);
+        e.into()
+    })
+}
+
+/// 파일을 송신 대기열에 넣습니다.
+///
+/// 대기열은 앱을 다시 시작해도 유지되며, 상대 기기가 발견되면 우선순위와 넣은 순서대로
+/// 기기당 하나씩 보냅니다. 실패하면 잠시 뒤 다시 시도하고, 여러 번 실패하면 대기열에서
+/// 빠집니다 (결과는 전송 기록에 남음).
+///
+/// # Arguments
+/// * `device_id` - 수신 기기 ID (지금 발견되지 않은 기기여도 됨)
+/// * `file_path` - 전송할 파일 경로
+/// * `priority` - 송신 우선순위
+///
+/// # Returns
+/// * `QueuedSend` - 대기열에 넣은 항목
+///
+/// # Examples
+/// ```dart
+/// await api.queueSend(deviceId: id, filePath: path, priority: TransferPriority.normal);
+/// ```
+pub fn queue_send(device_id: String, file_path: String, priority: TransferPriority) -> Result<QueuedSend, PebbleError> {
+    sendqueue::enqueue(&device_id, &file_path, priority).map_err(|e| {
+        tracing::error!("Failed to queue send to {}: {:#}", device_id, e);
+        e.into()
+    })
+}
+
+/// 송신 대기열을 보낼 순서대로 가져옵니다.
+///
+/// 이전 실행에서 남아 복원된 항목은 `restored`가 true이므로, UI가 앱 시작 후 검토하게 할 수 있습니다.
+pub fn list_send_queue() -> Result<Vec<QueuedSend>, PebbleError> {
+    sendqueue::list().map_err(|e| {
+        tracing::error!("Failed to list send queue: {:#}", e);
+        e.into()
+    })
+}
+
+/// 송신 대기열에서 항목을 지웁니다 (이미 보내는 중인 전송은 `cancel_transfer`로 취소).
+///
+/// # Returns
+/// * 그 ID의 항목이 있었으면 true
+pub fn remove_queued_send(id: String) -> Result<bool, PebbleError> {
+    sendqueue::remove(&id).map_err(|e| {
+        tracing::error!("Failed to remove queued send {}: {:#}", id, e);
+        e.into()
+    })
+}
+
+/// 이전 실행에서 복원된 송신 대기열 항목을 모두 지웁니다.
+///
+/// # Returns
+/// * 지운 항목 수
+pub fn purge_restored_sends() -> Result<u64, PebbleError> {
+    sendqueue::purge_restored().map_err(|e| {
+        tracing::error!("Failed to purge restored sends: {:#}", e);
+        e.into()
+    })
+}
+
+async fn send_to_device(
+    device_id: String,
+    file_path: String,
+    priority: TransferPriority,
+    cap_override: bool,
+) -> Result<String, PebbleError> {
+    match peers::send_file_to_device(&device_id, &file_path, priority, cap_override).await {
+        Ok(addr) => {
+            let success_msg = format!("File sent successfully to {} ({}): {}", device_id, addr, file_path);
+            tracing::info!("{}", success_msg);
             Ok(success_msg)
         }
         Err(e) => {
-            let error_msg = format!("Failed to send file: {}", e);
-            log::error!("{}", error_msg);
-            Err(error_msg)
+            tracing::error!("Failed to send file to {}: {:#}", device_id, e);
+            Err(e.into())
         }
     }
-}
\ No newline at end of file
+}
+
+/// 비콘으로 확인한 적 있는 기기 목록을 최근에 본 순으로 가져옵니다.
+///
+/// 각 기기의 마지막 주소와 고정된 인증서 핑거프린트를 포함합니다.
+pub fn list_known_peers() -> Result<Vec<KnownPeer>, PebbleError> {
+    peers::list().map_err(|e| {
+        tracing::error!("Failed to list known peers: {:#}", e);
+        e.into()
+    })
+}
+
+/// 기기 화면에 필요한 정보를 한 번에 가져옵니다.
+///
+/// 기기 이름과 주소, 온라인 여부, 인증서 고정과 자동 수락 여부, 마지막으로 본 시각,
+/// 주고받은 바이트 수, 진행 중인 전송, 공유 중인 폴더를 포함합니다.
+///
+/// # Arguments
+/// * `device_id` - 기기 ID
+///
+/// # Examples
+/// ```dart
+/// final details = await api.getDeviceDetails(deviceId: device.deviceId);
+/// print("${details.deviceName}: ${details.bytesSent} bytes sent");
+/// ```
+pub fn get_device_details(device_id: String) -> Result<DeviceDetails, PebbleError> {
+    peers::get_device_details(&device_id).map_err(|e| {
+        tracing::error!("Failed to get details of {}: {:#}", device_id, e);
+        e.into()
+    })
+}
+
+/// 기기에 고정된 인증서 핑거프린트를 지웁니다.
+///
+/// 상대 기기가 인증서를 새로 만들었는데 비콘을 아직 받지 못했을 때 사용합니다.
+/// 다음 검증된 비콘에서 다시 고정됩니다.
+pub fn clear_peer_pin(device_id: String) -> Result<(), PebbleError> {
+    peers::clear_pin(&device_id).map_err(|e| {
+        tracing::error!("Failed to clear pin of {}: {:#}", device_id, e);
+        e.into()
+    })
+}
+
+/// 기기에 별명, 색상, 태그를 붙입니다.
+///
+/// 이 기기에만 저장되며, 상대가 기기 이름을 바꿔도 유지됩니다.
+/// 발견 목록, 알려진 기기 목록, 기기 상세 정보의 `label`로 함께 반환됩니다.
+///
+/// # Arguments
+/// * `device_id` - 비콘으로 확인한 적 있는 기기 ID
+/// * `label` - 표시 정보 (모두 비우면 지움, 색상은 `#RRGGBB`)
+///
+/// # Examples
+/// ```dart
+/// await api.setPeerLabel(
+///   deviceId: device.deviceId,
+///   label: PeerLabel(nickname: "Mom's laptop", color: "#ff8800", tags: ["family"]),
+/// );
+/// ```
+pub fn set_peer_label(device_id: String, label: PeerLabel) -> Result<(), PebbleError> {
+    peers::set_label(&device_id, &label).map_err(|e| {
+        tracing::error!("Failed to set label of {}: {:#}", device_id, e);
+        e.into()
+    })
+}
+
+/// 잃어버리거나 도난당한 기기의 신뢰를 철회하고 다른 기기들에 알립니다.
+///
+/// 이 기기와 연결되는 기기들은 그 기기의 주소, 고정된 핑거프린트, 별명, 공유 권한을 지우고
+/// 이후 비콘과 요청을 무시합니다. 지금 연결되지 않는 기기에는 다음에 발견될 때 전달되며,
+/// 확인될 때마다 `RevocationConfirmed` 이벤트가 옵니다. 잃어버린 기기가 받으면 그 기기의
+/// 사용자가 확인한 뒤 페어링을 지웁니다. 잃어버린 기기도 비밀 키를 가지고 있으므로 비밀 키를 바꾸는 것을 권장합니다.
+///
+/// # Arguments
+/// * `device_id` - 잃어버린 기기 ID (이 기기는 철회할 수 없음)
+///
+/// # Examples
+/// ```dart
+/// final report = await api.revokeDevice(deviceId: lostPhone.deviceId);
+/// print("Confirmed by ${report.confirmed.length}, pending ${report.pending.length}");
+/// ```
+pub async fn revoke_device(device_id: String) -> Result<RevocationReport, PebbleError> {
+    revocation::revoke_device(&device_id).await.map_err(|e| {
+        tracing::error!("Failed to revoke {}: {:#}", device_id, e);
+        e.into()
+    })
+}
+
+/// 신뢰를 철회한 기기 목록을 가져옵니다.
+pub fn list_revoked_devices() -> Result<Vec<Revocation>, PebbleError> {
+    revocation::list().map_err(|e| {
+        tracing::error!("Failed to list revoked devices: {:#}", e);
+        e.into()
+    })
+}
+
+/// 이 기기에서만 철회를 취소합니다 (잘못 철회했거나 기기를 되찾은 경우).
+///
+/// # Returns
+/// * 철회된 기기였으면 true
+pub fn restore_revoked_device(device_id: String) -> Result<bool, PebbleError> {
+    revocation::restore(&device_id).map_err(|e| {
+        tracing::error!("Failed to restore {}: {:#}", device_id, e);
+        e.into()
+    })
+}
+
+/// 다른 기기가 이 기기를 철회해 사용자 확인을 기다리는 요청 목록을 가져옵니다.
+///
+/// `WipeRequested` 이벤트를 놓쳤을 때(앱 재시작 등) 확인 화면을 다시 띄우는 데 사용합니다.
+pub fn get_pending_pairing_wipes() -> Result<Vec<Revocation>, PebbleError> {
+    revocation::pending_wipes().map_err(|e| {
+        tracing::error!("Failed to list wipe requests: {:#}", e);
+        e.into()
+    })
+}
+
+/// 사용자가 확인한 철회 요청에 따라 이 기기의 페어링 기록을 지우고 기기 탐색을 멈춥니다.
+///
+/// 완료되면 `PairingWiped` 이벤트가 오며, 앱은 저장해 둔 비밀 키를 지워야 합니다.
+///
+/// # Arguments
+/// * `revocation_id` - `WipeRequested` 이벤트의 요청 ID
+pub async fn confirm_pairing_wipe(revocation_id: String) -> Result<(), PebbleError> {
+    revocation::confirm_wipe(&revocation_id).await.map_err(|e| {
+        tracing::error!("Failed to wipe pairing for {}: {:#}", revocation_id, e);
+        e.into()
+    })
+}
+
+/// 철회 요청을 무시하고 페어링을 유지합니다 (기기를 잃어버리지 않은 경우).
+///
+/// # Returns
+/// * 확인을 기다리던 요청이었으면 true
+pub fn dismiss_pairing_wipe(revocation_id: String) -> Result<bool, PebbleError> {
+    revocation::dismiss_wipe(&revocation_id).map_err(|e| {
+        tracing::error!("Failed to dismiss wipe request {}: {:#}", revocation_id, e);
+        e.into()
+    })
+}
+
+/// 진행 중인 송수신 전송 목록을 가져옵니다.
+///
+/// # Returns
+/// * `Vec<ActiveTransfer>` - 전송 ID, 상대 기기, 파일, 방향, 진행률, 상태
+#[flutter_rust_bridge::frb(sync)]
+pub fn list_active_transfers() -> Vec<ActiveTransfer> {
+    registry::global().list()
+}
+
+/// 진행 중인 전송을 취소합니다.
+///
+/// 이미 받은 청크는 보존되어 같은 전송을 다시 시도하면 이어받기합니다.
+///
+/// # Examples
+/// ```dart
+/// for (final t in api.listActiveTransfers()) {
+///   if (t.direction == TransferDirection.send) {
+///     await api.cancelTransfer(transferId: t.transferId);
+///   }
+/// }
+/// ```
+pub fn cancel_transfer(transfer_id: String) -> Result<(), PebbleError> {
+    registry::global().cancel(&transfer_id).map_err(|e| {
+        tracing::error!("Failed to cancel transfer: {:#}", e);
+        e.into()
+    })
+}
+
+/// 진행 중인 전송을 일시정지합니다. 현재 청크 전송이 끝난 뒤 멈춥니다.
+pub fn pause_transfer(transfer_id: String) -> Result<(), PebbleError> {
+    registry::global().pause(&transfer_id).map_err(|e| {
+        tracing::error!("Failed to pause transfer: {:#}", e);
+        e.into()
+    })
+}
+
+/// 일시정지된 전송을 재개합니다.
+pub fn resume_transfer(transfer_id: String) -> Result<(), PebbleError> {
+    registry::global().resume(&transfer_id).map_err(|e| {
+        tracing::error!("Failed to resume transfer: {:#}", e);
+        e.into()
+    })
+}
+
+// ============================================================================
+// 오래 걸리는 작업 (Long-Running Operations) API
+// ============================================================================
+
+/// 진행 중인 작업(스캔, 무결성 검사, 동기화 비교, 전송) 목록을 시작 시각 순으로 가져옵니다.
+///
+/// 작업을 시작한 API가 끝날 때까지 목록에 남으므로, 주기적으로 조회하여 진행률을 표시합니다.
+///
+/// # Examples
+/// ```dart
+/// for (final op in api.listOperations()) {
+///   print("${op.kind} ${op.target}: ${op.progressPercent?.toStringAsFixed(1) ?? '...'}%");
+/// }
+/// ```
+#[flutter_rust_bridge::frb(sync)]
+pub fn list_operations() -> Vec<Operation> {
+    operations::list()
+}
+
+/// 작업을 취소합니다. 작업을 시작한 API는 `PebbleError::Cancelled`로 끝납니다.
+///
+/// 전송이면 `cancelTransfer`와 같습니다 (받은 청크는 이어받기를 위해 보존).
+pub fn cancel_operation(operation_id: String) -> Result<(), PebbleError> {
+    operations::cancel(&operation_id).map_err(|e| {
+        tracing::error!("Failed to cancel operation: {:#}", e);
+        e.into()
+    })
+}
+
+// ============================================================================
+// 알림 이벤트 (Notification Events) API
+// ============================================================================
+
+/// 알림용 이벤트 스트림을 생성합니다.
+///
+/// 전송 완료/실패, 새 기기 발견, 이름 충돌처럼 사용자에게 알릴 만한 일만 JSON으로
+/// 직렬화된 PebbleEvent로 전달됩니다 (`type` 필드로 종류 구분).
+/// 청크 단위 진행률은 `listActiveTransfers`로 조회하세요.
+///
+/// # Examples
+/// ```dart
+/// api.createEventStream().listen((json) {
+///   final event = jsonDecode(json);
+///   switch (event['type']) {
+///     case 'TransferCompleted':
+///       notifications.show('Received ${basename(event['file_path'])}');
+///     case 'TransferFailed':
+///       notifications.show('Transfer failed: ${event['error']}');
+///   }
+/// });
+/// ```
+pub fn create_event_stream(sink: StreamSink<String>) {
+    events::subscribe(move |event| match serde_json::to_string(event) {
+        Ok(json) => sink.add(json).is_ok(),
+        Err(e) => {
+            tracing::error!("Failed to serialize event: {}", e);
+            true
+        }
+    });
+}
+
+// ============================================================================
+// 수신 승인 (Accept Policy) API
+// ============================================================================
+
+/// 사용자 승인이 필요한 수신 요청 스트림을 생성합니다.
+///
+/// 설정의 `accept_policy`로 자동 수락되지 않은 요청마다 JSON으로 직렬화된
+/// ApprovalRequest가 전달됩니다. 60초 안에 `respondToTransferRequest`로 응답하지
+/// 않거나 구독자가 없으면 거절됩니다.
+///
+/// # Examples
+/// ```dart
+/// api.createApprovalRequestStream().listen((json) async {
+///   final request = jsonDecode(json);
+///   final ok = await showAcceptDialog(request['file_name'], request['file_size']);
+///   await api.respondToTransferRequest(requestId: request['request_id'], approved: ok);
+/// });
+/// ```
+pub fn create_approval_request_stream(sink: StreamSink<String>) {
+    accept::global().subscribe(move |request| match serde_json::to_string(request) {
+        Ok(json) => sink.add(json).is_ok(),
+        Err(e) => {
+            tracing::error!("Failed to serialize approval request: {}", e);
+            true
+        }
+    });
+}
+
+/// 수신 승인 요청에 응답합니다.
+///
+/// # Arguments
+/// * `request_id` - ApprovalRequest의 request_id
+/// * `approved` - 수락 여부 (거절하면 송신측은 `PebbleError.rejected`를 받음)
+pub fn respond_to_transfer_request(request_id: String, approved: bool) -> Result<(), PebbleError> {
+    accept::global().respond(&request_id, approved).map_err(|e| {
+        tracing::error!("Failed to respond to transfer request: {:#}", e);
+        e.into()
+    })
+}
+// ============================================================================
+// 설정 (Configuration) API
+// ============================================================================
+
+/// 앱 데이터 디렉토리에서 설정을 로드하여 적용합니다.
+///
+/// 설정 파일이 없으면 기본값을 사용하며, 이후 `update_config`로 변경한 내용은
+/// 같은 디렉토리의 `pebble_config.json`에 저장됩니다.
+/// DB 경로가 바뀔 수 있으므로 로드 후 DB를 다시 초기화합니다.
+///
+/// # Arguments
+/// * `config_dir` - 설정 파일 디렉토리 (앱 데이터 디렉토리)
+///
+/// # Examples
+/// ```dart
+/// final dir = await getApplicationSupportDirectory();
+/// final config = await api.loadConfig(configDir: dir.path);
+/// print("Transfer port: ${config.transferPort}");
+/// ```
+pub fn load_config(config_dir: String) -> Result<PebbleConfig, PebbleError> {
+    config::load(&config_dir).map_err(|e| {
+        tracing::error!("Failed to load config: {:#}", e);
+        PebbleError::from(e)
+    })?;
+
+    db::init_db().map_err(|e| {
+        tracing::error!("Failed to initialize database: {}", e);
+        PebbleError::from(e)
+    })?;
+
+    settings::apply_stored().map_err(|e| {
+        tracing::error!("Failed to apply stored settings: {:#}", e);
+        PebbleError::from(e)
+    })?;
+
+    Ok(config::current())
+}
+
+/// 현재 적용된 설정을 반환합니다.
+#[flutter_rust_bridge::frb(sync)]
+pub fn get_config() -> PebbleConfig {
+    config::current()
+}
+
+/// 설정을 변경합니다.
+///
+/// 검증에 실패하면 `PebbleError::InvalidArgument`를 반환하며 기존 설정은 유지됩니다.
+/// 변경 사항은 실행 중인 서비스(비콘 주기, 전송 속도 제한 등)에 즉시 반영됩니다.
+/// DB에 저장된 설정(`set_setting`) 중 새 설정과 값이 다른 것은 지워집니다.
+///
+/// # Examples
+/// ```dart
+/// final config = api.getConfig();
+/// await api.updateConfig(config: config.copyWith(maxTransferRate: BigInt.from(5000000)));
+/// ```
+pub fn update_config(new_config: PebbleConfig) -> Result<(), PebbleError> {
+    if let Err(e) = new_config.validate() {
+        tracing::error!("Invalid config: {:#}", e);
+        return Err(PebbleError::invalid_argument(e.to_string()));
+    }
+
+    let db_changed = config::current().db_path != new_config.db_path;
+
+    config::update(new_config).map_err(|e| {
+        tracing::error!("Failed to update config: {:#}", e);
+        PebbleError::from(e)
+    })?;
+
+    if db_changed {
+        db::init_db().map_err(|e| {
+            tracing::error!("Failed to initialize database: {}", e);
+            PebbleError::from(e)
+        })?;
+    }
+
+    settings::discard_overridden(&config::current()).map_err(|e| {
+        tracing::error!("Failed to discard stored settings: {:#}", e);
+        PebbleError::from(e)
+    })?;
+
+    Ok(())
+}
+
+/// 설정 값 하나를 바꾸고 DB에 저장합니다 (재시작 후에도 유지).
+///
+/// 바뀐 값은 실행 중인 서비스에 바로 반영되며, 전송 서버 포트처럼 시작할 때 정해지는 값은
+/// 해당 서비스를 다시 시작해 적용합니다 (`start_pebble`로 시작한 경우).
+///
+/// # Arguments
+/// * `key` - 설정 이름 (`PebbleConfig`의 필드 이름, 예: "max_transfer_rate")
+/// * `value` - 새 값 (JSON)
+///
+/// # Returns
+/// * 적용된 전체 설정
+/// * 모르는 키면 `PebbleError::NotFound`, 잘못된 값이면 `PebbleError::InvalidArgument`
+///
+/// # Examples
+/// ```dart
+/// await api.setSetting(key: "max_transfer_rate", value: "5000000");
+/// ```
+pub fn set_setting(key: String, value: String) -> Result<PebbleConfig, PebbleError> {
+    settings::set(&key, &value).map_err(|e| {
+        tracing::error!("Failed to set {}: {:#}", key, e);
+        e.into()
+    })
+}
+
+/// 받은 파일을 저장할 폴더를 바꾸고 DB에 저장합니다.
+///
+/// 송신측이 보낸 경로의 폴더 부분은 쓰지 않고 파일 이름만 정리하여 이 폴더 아래에 저장합니다.
+/// 폴더가 없으면 만듭니다.
+///
+/// # Arguments
+/// * `download_dir` - 저장 폴더 (None이면 기본 폴더, `get_download_dir` 참고)
+///
+/// # Returns
+/// * 적용된 전체 설정
+///
+/// # Examples
+/// ```dart
+/// await api.setDownloadDir(downloadDir: "/storage/emulated/0/Download/Pebble");
+/// ```
+pub fn set_download_dir(download_dir: Option<String>) -> Result<PebbleConfig, PebbleError> {
+    if let Some(dir) = &download_dir {
+        if dir.trim().is_empty() {
+            return Err(PebbleError::invalid_argument("download_dir must not be empty"));
+        }
+        if let Err(e) = std::fs::create_dir_all(dir) {
+            tracing::error!("Failed to create download dir {}: {}", dir, e);
+            return Err(PebbleError::io(format!("Failed to create {}: {}", dir, e)));
+        }
+    }
+
+    let value = serde_json::to_string(&download_dir).map_err(|e| PebbleError::internal(e.to_string()))?;
+    settings::set("download_dir", &value).map_err(|e| {
+        tracing::error!("Failed to set download dir: {:#}", e);
+        e.into()
+    })
+}
+
+/// 받은 파일이 실제로 저장되는 폴더를 반환합니다 (설정하지 않았으면 기본 폴더).
+#[flutter_rust_bridge::frb(sync)]
+pub fn get_download_dir() -> String {
+    config::current().receive_dir()
+}
+
+/// 현재 적용된 설정 값 하나를 JSON으로 반환합니다.
+pub fn get_setting(key: String) -> Result<String, PebbleError> {
+    settings::get(&key).map_err(|e| {
+        tracing::error!("Failed to get setting {}: {:#}", key, e);
+        e.into()
+    })
+}
+
+/// DB에 저장된 설정 목록을 반환합니다.
+pub fn list_settings() -> Result<Vec<Setting>, PebbleError> {
+    settings::list().map_err(|e| {
+        tracing::error!("Failed to list settings: {:#}", e);
+        e.into()
+    })
+}
+
+/// 저장된 설정을 지우고 설정 파일의 값으로 되돌립니다.
+///
+/// # Returns
+/// * 저장된 값이 있었으면 true
+pub fn reset_setting(key: String) -> Result<bool, PebbleError> {
+    settings::reset(&key).map_err(|e| {
+        tracing::error!("Failed to reset setting {}: {:#}", key, e);
+        e.into()
+    })
+}
+
+// ============================================================================
+// 통합 실행 (Bootstrap) API
+// ============================================================================
+
+/// 설정, DB, 인증서, 전송 서버, 기기 탐색, 파일 감시를 순서대로 시작합니다.
+///
+/// 개별 start_* API를 순서에 맞춰 호출하는 대신 이 함수 하나로 모든 서비스를
+/// 시작할 수 있습니다. 중간 단계가 실패하면 이미 시작된 서비스는 정리됩니다.
+///
+/// # Arguments
+/// * `options` - 앱 데이터 디렉토리, 기기 이름, 비밀 키, 감시 폴더, 설정
+///
+/// # Returns
+/// * `Result<PebbleStartInfo, PebbleError>` - 기기 ID, 인증서 핑거프린트, 바인딩된 포트
+///
+/// # Examples
+/// ```dart
+/// final info = await api.startPebble(options: PebbleStartOptions(
+///   appDataDir: dir.path,
+///   deviceName: "My Device",
+///   secretKey: "my-secret-psk-key-2024",
+///   watchPath: "/path/to/sync/folder",
+/// ));
+/// print("Device ID: ${info.deviceId}, port: ${info.transferPort}");
+/// ```
+pub async fn start_pebble(options: PebbleStartOptions) -> Result<PebbleStartInfo, PebbleError> {
+    match service::start(options).await {
+        Ok(info) => Ok(info),
+        Err(e) => {
+            tracing::error!("Failed to start Pebble: {:#}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// start_pebble로 시작한 모든 서비스를 중지합니다.
+///
+/// 백그라운드 태스크가 모두 끝난 뒤 반환하며, 실행 중 에러로 끝난 태스크가 있으면
+/// 에러로 보고합니다 (이 경우에도 서비스는 모두 중지됨).
+pub async fn stop_pebble() -> Result<(), PebbleError> {
+    match service::stop().await {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            tracing::error!("Failed to stop Pebble: {:#}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// 각 서비스의 실행 여부, 바인딩 포트, 마지막 에러를 조회합니다.
+///
+/// # Examples
+/// ```dart
+/// final status = api.getServiceStatus();
+/// if (!status.transferServer.running) {
+///   print("Server down: ${status.transferServer.lastError}");
+/// }
+/// ```
+#[flutter_rust_bridge::frb(sync)]
+pub fn get_service_status() -> ServiceStatus {
+    service::status()
+}
+
+// ============================================================================
+// 앱 생명주기 (Lifecycle) API
+// ============================================================================
+
+/// 앱이 백그라운드로 전환될 때 호출합니다.
+///
+/// 기기 탐색을 저전력 모드로 낮추고 파일 감시를 일시 중지합니다.
+/// 전송 서버는 진행 중인 수신을 위해 계속 실행됩니다.
+///
+/// # Examples
+/// ```dart
+/// @override
+/// void didChangeAppLifecycleState(AppLifecycleState state) {
+///   if (state == AppLifecycleState.paused) api.onAppPaused();
+///   if (state == AppLifecycleState.resumed) api.onAppResumed();
+/// }
+/// ```
+pub async fn on_app_paused() -> Result<(), PebbleError> {
+    match lifecycle::pause().await {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            tracing::error!("Failed to pause services: {:#}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// 앱이 포그라운드로 돌아올 때 호출합니다.
+///
+/// 기기 탐색과 파일 감시를 복구하고, 백그라운드 동안 놓친 변경 사항을
+/// 재조정 스캔으로 반영합니다.
+///
+/// # Returns
+/// * `Result<Option<ReconcileReport>, PebbleError>` - 감시 중이던 폴더가 있으면 재조정 결과
+pub async fn on_app_resumed() -> Result<Option<ReconcileReport>, PebbleError> {
+    // 재조정 스캔은 파일 해시를 계산하므로 블로킹 스레드에서 실행
+    let result = tokio::task::spawn_blocking(lifecycle::resume)
+        .await
+        .map_err(|e| PebbleError::internal(format!("Resume task failed: {}", e)))?;
+
+    match result {
+        Ok(report) => Ok(report),
+        Err(e) => {
+            tracing::error!("Failed to resume services: {:#}", e);
+            Err(e.into())
+        }
+    }
+}
+
+// ============================================================================
+// 로깅 (Logging) API
+// ============================================================================
+
+/// 앱 데이터 디렉토리에 순환 로그 파일 기록을 시작합니다.
+///
+/// start_pebble은 `<app_data_dir>/logs`에 자동으로 기록하므로,
+/// 서비스 시작 전의 로그도 남기고 싶을 때 사용합니다.
+///
+/// # Arguments
+/// * `log_dir` - 로그 파일을 저장할 디렉토리
+///
+/// # Returns
+/// * `Result<String, PebbleError>` - 성공 시 로그 파일 경로
+pub fn enable_file_logging(log_dir: String) -> Result<String, PebbleError> {
+    match logging::enable_file_logging(&log_dir) {
+        Ok(path) => Ok(path.to_string_lossy().to_string()),
+        Err(e) => {
+            tracing::error!("Failed to enable file logging: {:#}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// 전송/동기화/발견 span과 이벤트를 JSON 줄 파일로 내보내기 시작합니다.
+///
+/// 여러 기기에서 내보낸 파일을 transfer_id로 맞춰 보면 기기 간 전송 과정을
+/// 한 흐름으로 추적할 수 있습니다.
+///
+/// # Arguments
+/// * `dir` - 내보내기 파일을 저장할 디렉토리
+///
+/// # Returns
+/// * `Result<String, PebbleError>` - 성공 시 내보내기 파일 경로
+pub fn enable_trace_export(dir: String) -> Result<String, PebbleError> {
+    match logging::enable_trace_export(&dir) {
+        Ok(path) => Ok(path.to_string_lossy().to_string()),
+        Err(e) => {
+            tracing::error!("Failed to enable trace export: {:#}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// 현재 로그 파일 경로를 가져옵니다 (버그 리포트 첨부용).
+#[flutter_rust_bridge::frb(sync)]
+pub fn get_log_file_path() -> Option<String> {
+    logging::log_file_path()
+}
+
+/// 로그 레벨을 변경합니다.
+#[flutter_rust_bridge::frb(sync)]
+pub fn set_log_level(level: LogLevel) {
+    logging::set_level(level);
+}
+
+/// 로그 줄을 실시간으로 받는 스트림을 생성합니다.
+///
+/// # Examples
+/// ```dart
+/// api.createLogStream().listen((line) {
+///   logBuffer.add(line);
+/// });
+/// ```
+pub fn create_log_stream(sink: StreamSink<String>) {
+    logging::subscribe(move |line| sink.add(line.to_string()).is_ok());
+}
+
+// ============================================================================
+// 진단 (Diagnostics) API
+// ============================================================================
+
+/// 자가 진단을 실행합니다.
+///
+/// DB 접근, 인증서, UDP 브로드캐스트, 포트 바인딩, 다운로드 디렉토리 여유 공간,
+/// 시스템 시계를 점검하여 UI 표시나 이슈 첨부용 보고서를 반환합니다.
+///
+/// # Arguments
+/// * `app_data_dir` - 인증서가 저장된 앱 데이터 디렉토리 (None이면 인증서 점검 생략)
+///
+/// # Examples
+/// ```dart
+/// final report = await api.runDiagnostics(appDataDir: appDir.path);
+/// for (final check in report.checks) {
+///   print("${check.name}: ${check.status} - ${check.message}");
+/// }
+/// ```
+pub async fn run_diagnostics(app_data_dir: Option<String>) -> Result<DiagnosticsReport, PebbleError> {
+    tokio::task::spawn_blocking(move || diagnostics::run(app_data_dir.as_deref()))
+        .await
+        .map_err(|e| PebbleError::internal(format!("Diagnostics task failed: {}", e)))
+}
+
+// ============================================================================
+// 암호화된 수신 대기 영역 (Encrypted Staging) API
+// ============================================================================
+
+/// 설정의 대기 폴더(`staging_dir`)에 암호화해 둔 수신 파일 목록을 가져옵니다.
+///
+/// 받기를 마친 항목(`completed_at`이 있음)만 승인할 수 있습니다.
+/// 새 항목이 생기면 `FileStaged` 이벤트가 발행됩니다.
+pub fn list_staged_files() -> Result<Vec<StagedFile>, PebbleError> {
+    staging::list().map_err(|e| {
+        tracing::error!("Failed to list staged files: {:#}", e);
+        e.into()
+    })
+}
+
+/// 대기 중인 파일을 복호화하여 원래 받을 위치에 저장합니다.
+///
+/// # Returns
+/// * `Result<String, PebbleError>` - 저장한 경로
+///   (받은 뒤 대기 파일이 바뀌었거나 검사 명령을 통과하지 못했으면 `PebbleError.rejected`)
+///
+/// # Examples
+/// ```dart
+/// final savedTo = await api.approveStagedFile(id: staged.id);
+/// ```
+pub async fn approve_staged_file(id: String) -> Result<String, PebbleError> {
+    staging::approve(&id).await.map_err(|e| {
+        tracing::error!("Failed to approve staged file: {:#}", e);
+        e.into()
+    })
+}
+
+/// 대기 중인 파일을 저장하지 않고 지웁니다.
+///
+/// # Returns
+/// * 그 ID의 항목이 있었으면 true
+pub fn discard_staged_file(id: String) -> Result<bool, PebbleError> {
+    staging::discard(&id).map_err(|e| {
+        tracing::error!("Failed to discard staged file {}: {:#}", id, e);
+        e.into()
+    })
+}
+
+// ============================================================================
+// 호스트 저장소 (Android Storage Access Framework) API
+// ============================================================================
+
+/// 수신 파일을 호스트가 만든 문서에 저장하도록 요청 스트림을 생성합니다.
+///
+/// 스트림을 구독하면 호스트 저장소 모드가 켜지고, 파일을 받을 때마다 JSON으로
+/// 직렬화된 StorageRequest가 전달됩니다. 60초 안에 `provideStorageFd` 또는
+/// `rejectStorageRequest`로 응답해야 합니다.
+/// 이어받기 요청(`resume_uri`가 있음)에는 그 문서를 내용을 유지하는 "rw" 모드로 다시 열어 줍니다.
+///
+/// # Examples
+/// ```dart
+/// api.createStorageRequestStream().listen((json) async {
+///   final request = jsonDecode(json);
+///   final resumeUri = request['resume_uri'];
+///   final doc = resumeUri != null
+///       ? Uri.parse(resumeUri)
+///       : await saf.createDocument(treeUri, request['file_name']);
+///   final fd = await saf.openDetachedFd(doc, mode: 'rw');
+///   await api.provideStorageFd(requestId: request['request_id'], fd: fd, uri: doc.toString());
+/// });
+/// ```
+pub fn create_storage_request_stream(sink: StreamSink<String>) {
+    storage::global().subscribe(move |request| match serde_json::to_string(request) {
+        Ok(json) => sink.add(json).is_ok(),
+        Err(e) => {
+            tracing::error!("Failed to serialize storage request: {}", e);
+            true
+        }
+    });
+}
+
+/// 저장소 요청에 호스트가 만든 문서의 파일 디스크립터로 응답합니다.
+///
+/// # Arguments
+/// * `request_id` - StorageRequest의 request_id
+/// * `fd` - `ParcelFileDescriptor.detachFd()`로 얻은 읽기/쓰기 fd (Rust가 소유하고 닫음)
+/// * `uri` - 문서 URI (전송 기록과 진행률에 표시됨)
+pub fn provide_storage_fd(request_id: String, fd: i32, uri: String) -> Result<(), PebbleError> {
+    match storage::global().provide_fd(&request_id, fd, &uri) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            tracing::error!("Failed to provide storage fd: {:#}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// 저장소 요청을 거절합니다. 송신측에는 전송 거절로 전달됩니다.
+pub fn reject_storage_request(request_id: String, reason: String) -> Result<(), PebbleError> {
+    match storage::global().reject(&request_id, &reason) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            tracing::error!("Failed to reject storage request: {:#}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// 호스트 저장소 모드를 끄고 로컬 저장 폴더(`get_download_dir`)에 저장하도록 되돌립니다.
+#[flutter_rust_bridge::frb(sync)]
+pub fn disable_host_storage() {
+    storage::global().disable();
+}
+
+/// 호스트가 감지한 SAF 문서 변경을 기록합니다 (파일 감시 대신 사용).
+///
+/// # Arguments
+/// * `uri` - 문서 URI
+/// * `fd` - 읽기 모드로 연 detached fd (Rust가 소유하고 닫음)
+/// * `last_modified` - 문서 수정 시간 (Unix timestamp)
+pub async fn record_host_file_change(uri: String, fd: i32, last_modified: i64) -> Result<(), PebbleError> {
+    // 해시 계산은 블로킹 작업이므로 별도 스레드에서 실행
+    let result = tokio::task::spawn_blocking(move || watcher::record_host_file_change(&uri, fd, last_modified))
+        .await
+        .map_err(|e| PebbleError::internal(format!("Hash task failed: {}", e)))?;
+
+    match result {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            tracing::error!("Failed to record host file change: {:#}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// 호스트가 감지한 SAF 문서 삭제를 기록합니다.
+pub fn record_host_file_removed(uri: String) -> Result<(), PebbleError> {
+    match watcher::record_host_file_removed(&uri) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            tracing::error!("Failed to record host file removal: {:#}", e);
+            Err(e.into())
+        }
+    }
+}
+
+// ============================================================================
+// 텍스트 메시지 (Text Snippets) API
+// ============================================================================
+
+/// 발견된 기기로 텍스트(URL, 클립보드 스니펫 등)를 보냅니다.
+///
+/// 파일 전송과 같은 TLS 연결을 사용합니다.
+///
+/// # Arguments
+/// * `device_id` - 수신 기기 ID (get_discovered_devices 결과)
+/// * `text` - 보낼 텍스트 (최대 64KB)
+/// * `server_fingerprint` - 수신 기기 인증서의 핑거프린트 (None이면 비콘으로 고정된 핑거프린트 사용)
+///
+/// # Returns
+/// * `Result<TextMessage, PebbleError>` - 기록에 저장된 보낸 메시지
+///
+/// # Examples
+/// ```dart
+/// await api.sendText(deviceId: device.deviceId, text: "https://flutter.dev");
+/// ```
+pub async fn send_text(
+    device_id: String,
+    text: String,
+    server_fingerprint: Option<String>,
+) -> Result<TextMessage, PebbleError> {
+    use crate::api::transfer::TransferClient;
+
+    let server_addr = discovery::resolve_transfer_addr(&device_id).map_err(PebbleError::from)?;
+
+    let client = TransferClient::new(peers::fingerprint_for(&device_id, server_fingerprint));
+
+    match client.send_text(server_addr, Some(device_id), &text).await {
+        Ok(message) => Ok(message),
+        Err(e) => {
+            tracing::error!("Failed to send text: {:#}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// 수신한 텍스트 메시지를 실시간으로 받는 스트림을 생성합니다.
+///
+/// 각 이벤트는 JSON으로 직렬화된 TextMessage입니다.
+///
+/// # Examples
+/// ```dart
+/// api.createIncomingTextStream().listen((json) {
+///   final message = jsonDecode(json);
+///   showSnackBar("From ${message['peer_address']}: ${message['text']}");
+/// });
+/// ```
+pub fn create_incoming_text_stream(sink: StreamSink<String>) {
+    messages::subscribe(move |message| match serde_json::to_string(message) {
+        Ok(json) => sink.add(json).is_ok(),
+        Err(e) => {
+            tracing::error!("Failed to serialize text message: {}", e);
+            true
+        }
+    });
+}
+
+/// 보내고 받은 텍스트 메시지 기록을 최신순으로 가져옵니다.
+pub fn get_text_history(limit: u32, offset: u32) -> Result<Vec<TextMessage>, PebbleError> {
+    match messages::history(limit, offset) {
+        Ok(history) => Ok(history),
+        Err(e) => {
+            tracing::error!("Failed to get text history: {:#}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// 텍스트 메시지 기록을 모두 삭제합니다.
+pub fn clear_text_history() -> Result<(), PebbleError> {
+    messages::clear_history().map_err(|e| {
+        tracing::error!("Failed to clear text history: {:#}", e);
+        e.into()
+    })
+}
+
+// ============================================================================
+// 클립보드 동기화 (Clipboard Sync) API
+// ============================================================================
+
+/// 기기와의 클립보드 동기화를 켜거나 끕니다.
+///
+/// 양쪽 기기 모두 서로에 대해 켜야 내용을 주고받습니다.
+///
+/// # Examples
+/// ```dart
+/// await api.setClipboardSync(deviceId: device.deviceId, enabled: true);
+/// ```
+pub fn set_clipboard_sync(device_id: String, enabled: bool) -> Result<(), PebbleError> {
+    clipboard::set_enabled(&device_id, enabled).map_err(|e| {
+        tracing::error!("Failed to set clipboard sync for {}: {:#}", device_id, e);
+        e.into()
+    })
+}
+
+/// 클립보드 동기화를 켠 기기 ID 목록을 반환합니다.
+pub fn get_clipboard_sync_devices() -> Result<Vec<String>, PebbleError> {
+    clipboard::enabled_peers().map_err(|e| {
+        tracing::error!("Failed to list clipboard sync devices: {:#}", e);
+        e.into()
+    })
+}
+
+/// 이 기기의 클립보드가 바뀌었을 때 호출하여 동기화를 켠 기기들에 보냅니다.
+///
+/// 마지막으로 보내거나 받은 내용과 같으면 보내지 않으므로, 받은 내용을 클립보드에 쓴 뒤
+/// 다시 호출되어도 기기 사이를 돌지 않습니다.
+///
+/// # Arguments
+/// * `kind` - 내용 종류 (텍스트 최대 1MB, 이미지 최대 8MB)
+/// * `mime_type` - MIME 형식 (예: "text/plain", "image/png")
+/// * `data` - 내용 (텍스트는 UTF-8)
+///
+/// # Examples
+/// ```dart
+/// await api.publishClipboard(kind: ClipboardKind.text, mimeType: "text/plain", data: utf8.encode(text));
+/// ```
+pub async fn publish_clipboard(
+    kind: ClipboardKind,
+    mime_type: String,
+    data: Vec<u8>,
+) -> Result<ClipboardPublishReport, PebbleError> {
+    clipboard::publish(kind, mime_type, data).await.map_err(|e| {
+        tracing::error!("Failed to publish clipboard: {:#}", e);
+        e.into()
+    })
+}
+
+/// 다른 기기에서 받은 클립보드 내용을 실시간으로 받는 스트림을 생성합니다.
+///
+/// 각 이벤트는 JSON으로 직렬화된 ClipboardUpdate이며, 앱이 운영체제 클립보드에 씁니다.
+///
+/// # Examples
+/// ```dart
+/// api.createClipboardStream().listen((json) {
+///   final update = jsonDecode(json);
+///   if (update['kind'] == 'text') {
+///     Clipboard.setData(ClipboardData(text: utf8.decode(List<int>.from(update['data']))));
+///   }
+/// });
+/// ```
+pub fn create_clipboard_stream(sink: StreamSink<String>) {
+    clipboard::subscribe(move |update| match serde_json::to_string(update) {
+        Ok(json) => sink.add(json).is_ok(),
+        Err(e) => {
+            tracing::error!("Failed to serialize clipboard update: {}", e);
+            true
+        }
+    });
+}
+
+// ============================================================================
+// 전송 기록 (Transfer History) API
+// ============================================================================
+
+/// 끝난 송수신 전송 기록을 최신순으로 가져옵니다.
+///
+/// 기록은 설정의 `history_retention`(기본: 최근 90일, 최대 1000개)에 따라 자동으로 정리됩니다.
+///
+/// # Examples
+/// ```dart
+/// final records = await api.getTransferHistory(limit: 50, offset: 0);
+/// for (final r in records) {
+///   print("${r.filePath}: ${r.status} (${r.bytesTransferred}/${r.totalBytes})");
+/// }
+/// ```
+pub fn get_transfer_history(limit: u32, offset: u32) -> Result<Vec<TransferRecord>, PebbleError> {
+    history::list(limit, offset).map_err(|e| {
+        tracing::error!("Failed to get transfer history: {:#}", e);
+        e.into()
+    })
+}
+
+/// 보낸 전송의 기록에 저장된 수령증의 서명을 다시 확인합니다.
+///
+/// 수령증은 받은 기기가 전체 파일 해시를 확인한 뒤 자신의 인증서 키로 서명한 것으로,
+/// 반환된 수령증의 인증서 핑거프린트를 그 기기의 핑거프린트와 비교하여 누가 받았는지 확인할 수 있습니다.
+///
+/// # Returns
+/// * 서명이 확인된 수령증
+/// * 기록이나 수령증이 없으면 `PebbleError::NotFound`, 서명이 맞지 않으면 `PebbleError::Rejected`
+///
+/// # Examples
+/// ```dart
+/// final receipt = await api.verifyTransferReceipt(transferId: record.transferId);
+/// print("Received intact by ${receipt.receiverDeviceId} (${receipt.fileHash})");
+/// ```
+pub fn verify_transfer_receipt(transfer_id: String) -> Result<TransferReceipt, PebbleError> {
+    let receipt = history::receipt(&transfer_id)
+        .map_err(PebbleError::from)?
+        .ok_or_else(|| PebbleError::not_found(format!("Receipt for {}", transfer_id)))?;
+
+    match receipt.verify() {
+        Ok(_) => Ok(receipt),
+        Err(e) => {
+            tracing::error!("Receipt for {} failed verification: {:#}", transfer_id, e);
+            Err(e.into())
+        }
+    }
+}
+
+/// 전송 기록을 모두 삭제합니다. 중단된 전송의 이어받기 상태는 유지됩니다.
+pub fn clear_transfer_history() -> Result<(), PebbleError> {
+    history::clear().map_err(|e| {
+        tracing::error!("Failed to clear transfer history: {:#}", e);
+        e.into()
+    })
+}
+
+/// 보존 정책을 지금 적용합니다 (정책 변경 직후 다음 자동 정리를 기다리지 않을 때).
+///
+/// # Returns
+/// * `PruneReport` - 삭제한 전송 기록, 이어받기 상태, 연결 감사 기록 수
+pub fn prune_transfer_history() -> Result<PruneReport, PebbleError> {
+    history::prune(&config::current().history_retention).map_err(|e| {
+        tracing::error!("Failed to prune transfer history: {:#}", e);
+        e.into()
+    })
+}
+
+/// 오래 이어받지 않은 중단된 수신의 미완성 파일과 이어받기 상태를 지웁니다.
+///
+/// 설정의 `partial_max_age_hours`(기본 72시간)마다 자동으로 정리되며, 진행 중인 수신과
+/// 마지막 수신 이후 사용자가 수정한 파일은 지우지 않습니다.
+///
+/// # Arguments
+/// * `max_age_hours` - 마지막으로 청크를 받은 뒤 지나야 하는 시간 (None이면 설정 값, 0이면 진행 중이 아닌 모든 미완성 수신)
+///
+/// # Returns
+/// * `PartialCleanupReport` - 삭제한 이어받기 상태와 파일 수, 확보한 용량
+pub fn clean_partial_transfers(max_age_hours: Option<u64>) -> Result<PartialCleanupReport, PebbleError> {
+    let hours = max_age_hours.unwrap_or_else(|| config::current().partial_max_age_hours);
+    partials::clean(std::time::Duration::from_secs(hours * 60 * 60)).map_err(|e| {
+        tracing::error!("Failed to clean partial transfers: {:#}", e);
+        e.into()
+    })
+}
+
+// ============================================================================
+// 연결 감사 기록 (Connection Audit Log) API
+// ============================================================================
+
+/// 이 기기의 전송 서버가 받은 연결 기록을 최신순으로 가져옵니다.
+///
+/// 성공한 연결뿐 아니라 TLS 핸드셰이크에 실패한 연결도 포함되며, 전송 기록과 같은
+/// 보존 정책(`history_retention`)으로 정리됩니다.
+///
+/// # Examples
+/// ```dart
+/// final page = await api.getConnectionLog(
+///   query: AuditQuery(outcome: ConnectionOutcome.handshakeFailed, offset: 0, limit: 50),
+/// );
+/// for (final c in page.connections) {
+///   print("${c.peerAddress} (${c.deviceId ?? "unknown"}): ${c.outcome}");
+/// }
+/// ```
+pub fn get_connection_log(query: AuditQuery) -> Result<AuditPage, PebbleError> {
+    audit::query(&query).map_err(|e| {
+        tracing::error!("Failed to get connection log: {:#}", e);
+        e.into()
+    })
+}
+
+/// 연결 감사 기록을 모두 삭제합니다.
+pub fn clear_connection_log() -> Result<(), PebbleError> {
+    audit::clear().map_err(|e| {
+        tracing::error!("Failed to clear connection log: {:#}", e);
+        e.into()
+    })
+}
+
+// ============================================================================
+// 기기별 저장 한도 (Storage Quota) API
+// ============================================================================
+
+/// 상대 기기 하나의 수신 사용량과 저장 한도를 가져옵니다 (기기 상세 화면용).
+///
+/// # Arguments
+/// * `peer_id` - 상대 기기 ID (ID를 보내지 않는 구버전 기기는 IP 주소)
+///
+/// # Returns
+/// * `Option<PeerUsage>` - 받은 적도 한도를 설정한 적도 없으면 None
+pub fn get_peer_usage(peer_id: String) -> Result<Option<PeerUsage>, PebbleError> {
+    quota::usage(&peer_id).map_err(|e| {
+        tracing::error!("Failed to get peer usage: {:#}", e);
+        e.into()
+    })
+}
+
+/// 모든 상대 기기의 수신 사용량을 많이 받은 순으로 가져옵니다.
+pub fn list_peer_usage() -> Result<Vec<PeerUsage>, PebbleError> {
+    quota::list_usage().map_err(|e| {
+        tracing::error!("Failed to list peer usage: {:#}", e);
+        e.into()
+    })
+}
+
+/// 상대 기기의 저장 한도를 설정합니다.
+///
+/// 한도를 넘는 전송 요청은 수락 전에 거부되며, 송신측은 `PebbleError.quotaExceeded`를 받습니다.
+///
+/// # Arguments
+/// * `peer_id` - 상대 기기 ID
+/// * `quota_bytes` - 저장 한도 (null이면 무제한)
+///
+/// # Examples
+/// ```dart
+/// // 1 GB로 제한
+/// await api.setPeerQuota(peerId: device.deviceId, quotaBytes: BigInt.from(1 << 30));
+/// ```
+pub fn set_peer_quota(peer_id: String, quota_bytes: Option<u64>) -> Result<(), PebbleError> {
+    quota::set_quota(&peer_id, quota_bytes).map_err(|e| {
+        tracing::error!("Failed to set peer quota: {:#}", e);
+        e.into()
+    })
+}
+
+/// 상대 기기의 사용량을 초기화합니다. 저장 한도 설정은 유지됩니다.
+pub fn reset_peer_usage(peer_id: String) -> Result<(), PebbleError> {
+    quota::reset_usage(&peer_id).map_err(|e| {
+        tracing::error!("Failed to reset peer usage: {:#}", e);
+        e.into()
+    })
+}
+
+// ============================================================================
+// 대역폭 사용량 (Bandwidth Usage) API
+// ============================================================================
+
+/// 기간 동안 보내고 받은 바이트 수를 날짜별, 기기별로 가져옵니다.
+///
+/// 날짜는 이 기기의 로컬 시간 기준이며, 중단된 전송도 실제로 오간 만큼 포함됩니다.
+///
+/// # Arguments
+/// * `range` - 조회 기간
+/// * `peer_id` - 특정 기기만 조회 (null이면 모든 기기)
+///
+/// # Examples
+/// ```dart
+/// final usage = await api.getBandwidthUsage(range: const UsageRange.thisMonth());
+/// print("This month: ${usage.bytesSent} sent, ${usage.bytesReceived} received");
+/// ```
+pub fn get_bandwidth_usage(range: UsageRange, peer_id: Option<String>) -> Result<BandwidthUsage, PebbleError> {
+    bandwidth::usage(&range, peer_id.as_deref()).map_err(|e| {
+        tracing::error!("Failed to get bandwidth usage: {:#}", e);
+        e.into()
+    })
+}
+
+/// 설정된 데이터 사용 한도별 현재 사용량을 가져옵니다.
+///
+/// 한도에 도달한(`reached`) 기기로의 자동 동기화는 멈춰 있습니다.
+///
+/// # Returns
+/// * `Result<Vec<CapStatus>, PebbleError>` - 설정 순서대로 한도와 사용량
+///
+/// # Examples (Dart)
+/// ```dart
+/// final caps = await api.getDataCapStatus();
+/// final blocked = caps.where((c) => c.reached).toList();
+/// ```
+pub fn get_data_cap_status() -> Result<Vec<CapStatus>, PebbleError> {
+    bandwidth::cap_statuses().map_err(|e| {
+        tracing::error!("Failed to get data cap status: {:#}", e);
+        e.into()
+    })
+}
+
+// ============================================================================
+// 폴더 공유 (Share) API
+// ============================================================================
+
+/// 폴더를 기기와 공유합니다. 이미 공유 중이면 권한만 바꿉니다.
+///
+/// 읽기 전용으로 공유한 기기가 그 폴더 안으로 파일을 보내면 수락 전에 거부되며,
+/// 송신측은 `PebbleError.rejected`를 받습니다.
+///
+/// # Arguments
+/// * `root_path` - 공유할 폴더
+/// * `peer_device_id` - 공유받을 기기 ID
+/// * `permission` - ReadOnly 또는 ReadWrite
+///
+/// # Examples
+/// ```dart
+/// await api.shareFolder(
+///   rootPath: "/storage/emulated/0/Pictures",
+///   peerDeviceId: device.deviceId,
+///   permission: SharePermission.readOnly,
+/// );
+/// ```
+pub fn share_folder(root_path: String, peer_device_id: String, permission: SharePermission) -> Result<Share, PebbleError> {
+    shares::share_folder(&root_path, &peer_device_id, permission).map_err(|e| {
+        tracing::error!("Failed to share folder: {:#}", e);
+        e.into()
+    })
+}
+
+/// 기기와의 폴더 공유를 해제합니다.
+///
+/// # Returns
+/// * `Result<bool, PebbleError>` - 공유가 있었으면 true
+pub fn unshare_folder(root_path: String, peer_device_id: String) -> Result<bool, PebbleError> {
+    shares::unshare_folder(&root_path, &peer_device_id).map_err(|e| {
+        tracing::error!("Failed to unshare folder: {:#}", e);
+        e.into()
+    })
+}
+
+/// 모든 폴더 공유를 가져옵니다.
+pub fn list_shares() -> Result<Vec<Share>, PebbleError> {
+    shares::list_shares().map_err(|e| {
+        tracing::error!("Failed to list shares: {:#}", e);
+        e.into()
+    })
+}
+
+// ============================================================================
+// 동기화 쌍 (Sync Pair) API
+// ============================================================================
+
+/// 이 기기의 폴더와 상대 기기의 폴더를 동기화 쌍으로 등록합니다.
+///
+/// 상대 폴더는 상대 기기가 이 기기에 공유한 폴더여야 합니다.
+///
+/// # Arguments
+/// * `local_root` - 이 기기의 폴더
+/// * `peer_device_id` - 상대 기기 ID
+/// * `remote_root` - 상대 기기의 폴더 (상대 기기 기준 경로)
+pub fn add_sync_pair(local_root: String, peer_device_id: String, remote_root: String) -> Result<SyncPair, PebbleError> {
+    pairs::add_pair(&local_root, &peer_device_id, &remote_root).map_err(|e| {
+        tracing::error!("Failed to add sync pair: {:#}", e);
+        e.into()
+    })
+}
+
+/// 동기화 쌍을 삭제합니다.
+///
+/// # Returns
+/// * `Result<bool, PebbleError>` - 쌍이 있었으면 true
+pub fn remove_sync_pair(pair_id: i64) -> Result<bool, PebbleError> {
+    pairs::remove_pair(pair_id).map_err(|e| {
+        tracing::error!("Failed to remove sync pair: {:#}", e);
+        e.into()
+    })
+}
+
+/// 모든 동기화 쌍을 가져옵니다.
+pub fn list_sync_pairs() -> Result<Vec<SyncPair>, PebbleError> {
+    pairs::list_pairs().map_err(|e| {
+        tracing::error!("Failed to list sync pairs: {:#}", e);
+        e.into()
+    })
+}
+
+/// 동기화를 시작하기 전에 오갈 파일 수와 바이트를 예상합니다.
+///
+/// 양쪽 매니페스트를 비교하고, 받는 쪽에 같은 내용이 이미 있는 파일은 바이트에서 제외합니다.
+/// 상대 기기가 온라인이어야 합니다. `mark_sync_pair_synced`로 기록 위치를 저장한 뒤에는
+/// 양쪽의 변경분만 주고받습니다 (`incremental`).
+///
+/// # Examples
+/// ```dart
+/// final estimate = await api.estimateSync(pairId: pair.pairId);
+/// final gb = (estimate.uploadBytes + estimate.downloadBytes) / (1024 * 1024 * 1024);
+/// if (gb > 1) {
+///   showWarning("This will transfer ${gb.toStringAsFixed(1)} GB");
+/// }
+/// ```
+pub async fn estimate_sync(pair_id: i64) -> Result<SyncEstimate, PebbleError> {
+    pairs::estimate_sync(pair_id).await.map_err(|e| {
+        tracing::error!("Failed to estimate sync: {:#}", e);
+        e.into()
+    })
+}
+
+/// 동기화를 마쳤음을 기록합니다. 다음 `estimate_sync`부터는 이후의 변경분만 비교합니다.
+///
+/// # Arguments
+/// * `pair_id` - 동기화 쌍 ID
+/// * `cursor` - 동기화 전에 받은 예상의 기록 위치 (`SyncEstimate.cursor`)
+///
+/// # Examples
+/// ```dart
+/// final estimate = await api.estimateSync(pairId: pair.pairId);
+/// await runSync(pair, estimate);
+/// if (estimate.cursor != null) {
+///   await api.markSyncPairSynced(pairId: pair.pairId, cursor: estimate.cursor!);
+/// }
+/// ```
+pub fn mark_sync_pair_synced(pair_id: i64, cursor: SyncCursor) -> Result<(), PebbleError> {
+    pairs::mark_synced(pair_id, &cursor).map_err(|e| {
+        tracing::error!("Failed to mark sync pair {} as synced: {:#}", pair_id, e);
+        e.into()
+    })
+}
+
+// ============================================================================
+// 속도 측정 (Speed Test) API
+// ============================================================================
+
+/// 발견된 기기와의 전송 속도를 측정합니다.
+///
+/// 생성된 데이터를 실제 전송 경로(TLS, 청크 해시, ACK)로 보내되 디스크 I/O는 제외하여,
+/// 느린 LAN 전송의 원인이 네트워크인지 CPU인지 구분하는 데 사용합니다.
+///
+/// # Arguments
+/// * `device_id` - 측정할 기기 ID (get_discovered_devices 결과)
+/// * `size_mb` - 보낼 데이터 크기 (MB, None이면 64MB)
+/// * `server_fingerprint` - 상대 인증서 핑거프린트 (None이면 비콘으로 고정된 핑거프린트 사용)
+///
+/// # Examples
+/// ```dart
+/// final report = await api.speedTest(deviceId: device.deviceId, sizeMb: 32);
+/// print("${report.throughputMbps.toStringAsFixed(1)} MB/s, TLS ${report.tlsHandshakeMs} ms");
+/// ```
+pub async fn speed_test(
+    device_id: String,
+    size_mb: Option<u32>,
+    server_fingerprint: Option<String>,
+) -> Result<SpeedTestReport, PebbleError> {
+    let server_addr = discovery::resolve_transfer_addr(&device_id).map_err(PebbleError::from)?;
+    let total_bytes = size_mb
+        .map(|mb| mb as u64 * 1024 * 1024)
+        .unwrap_or(speedtest::DEFAULT_SPEED_TEST_BYTES);
+
+    let server_fingerprint = peers::fingerprint_for(&device_id, server_fingerprint);
+
+    match speedtest::run(server_addr, server_fingerprint, total_bytes).await {
+        Ok(report) => Ok(report),
+        Err(e) => {
+            tracing::error!("Speed test failed: {:#}", e);
+            Err(e.into())
+        }
+    }
+}
+
+// ============================================================================
+// 지연 시간 측정 (Peer Probing) API
+// ============================================================================
+
+/// 기기의 TCP 연결 시간, TLS 핸드셰이크 시간, Ping 왕복 시간과 손실률을 측정합니다.
+///
+/// 결과는 주소별로 저장되어, 기기에 주소가 여럿일 때 더 빠른 경로를 고르는 데 사용됩니다.
+/// 연결할 수 없는 기기도 에러 대신 `reachable == false`인 결과를 반환합니다.
+///
+/// # Arguments
+/// * `device_id` - 측정할 기기 ID (발견되었거나 이전에 본 기기)
+/// * `pings` - 보낼 Ping 수 (None이면 10, 최대 100)
+///
+/// # Examples
+/// ```dart
+/// final probe = await api.probeDevice(deviceId: device.deviceId);
+/// if (probe.reachable) {
+///   print("RTT ${probe.avgRttMs?.toStringAsFixed(1)} ms, loss ${probe.lossPercent}%");
+/// }
+/// ```
+pub async fn probe_device(device_id: String, pings: Option<u32>) -> Result<ProbeResult, PebbleError> {
+    probe::probe_device(&device_id, pings.unwrap_or(probe::DEFAULT_PROBE_PINGS))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to probe device: {:#}", e);
+            e.into()
+        })
+}
+
+/// 기기의 주소별 최근 측정 결과를 가져옵니다.
+pub fn get_probe_results(device_id: String) -> Result<Vec<ProbeResult>, PebbleError> {
+    probe::results(&device_id).map_err(|e| {
+        tracing::error!("Failed to get probe results: {:#}", e);
+        e.into()
+    })
+}
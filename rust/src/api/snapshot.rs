@@ -0,0 +1,387 @@
+//! 동기화 루트 스냅샷 (Signed Root Snapshots)
+//!
+//! 동기화 루트의 현재 상태(상대 경로, 해시, 크기, 수정 시각)를 서명한 JSON 또는 CSV 파일로
+//! 내보내고, 나중에 그 파일과 현재 상태를 비교합니다. 감사 기록이나 백업을 복원한 뒤
+//! 내용이 그대로인지 확인하는 용도이며, DB를 수정하지 않는 읽기 전용 작업입니다.
+//!
+//! # Process Flow
+//! 1. `export`가 DB에 기록된 루트의 파일(삭제 기록 제외)로 스냅샷을 만듦
+//! 2. 스냅샷 본문의 정규 JSON을 이 기기 인증서의 개인 키에서 만든 키로 HMAC-SHA256 서명
+//! 3. JSON(본문 + 서명) 또는 CSV(`#` 머리글, 항목 행, 서명 줄)로 인코딩
+//! 4. `verify`가 파일을 읽어 서명을 확인한 뒤 상대 경로 기준으로 현재 상태와 비교
+//!    → 백업을 다른 위치에 복원했어도 그 루트를 지정하면 비교 가능
+
+use anyhow::{Context, Result};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use super::certificate::TlsCertificate;
+use super::error::PebbleError;
+use super::manifest;
+use super::paths;
+use super::service;
+use super::util::unix_now;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 스냅샷 형식 버전
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// CSV 스냅샷의 첫 줄
+const CSV_MAGIC: &str = "# pebble-snapshot";
+
+/// CSV 스냅샷의 항목 열 이름
+const CSV_COLUMNS: &str = "path,file_size,file_hash,last_modified";
+
+/// 스냅샷 파일 형식
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SnapshotFormat {
+    Json,
+    Csv,
+}
+
+/// 스냅샷의 파일 하나
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotEntry {
+    /// 루트 기준 상대 경로 ('/' 구분, NFC)
+    pub path: String,
+
+    pub file_size: u64,
+    pub file_hash: String,
+
+    /// 수정 시각 (Unix timestamp)
+    pub last_modified: i64,
+}
+
+/// 서명 대상인 스냅샷 본문 (필드 순서가 정규 JSON의 순서)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct SnapshotBody {
+    version: u32,
+
+    /// 내보낸 루트 (정규화된 절대 경로)
+    root: String,
+
+    /// 서명한 기기의 인증서 핑거프린트
+    fingerprint: String,
+
+    /// 내보낸 시각 (Unix timestamp)
+    created_at: i64,
+
+    /// 상대 경로 순
+    entries: Vec<SnapshotEntry>,
+}
+
+/// JSON 스냅샷 파일
+#[derive(Serialize, Deserialize)]
+struct SignedSnapshot {
+    #[serde(flatten)]
+    body: SnapshotBody,
+    signature: String,
+}
+
+/// 스냅샷과 현재 상태의 비교 결과
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotVerifyReport {
+    /// 스냅샷을 내보낸 루트
+    pub snapshot_root: String,
+
+    /// 스냅샷을 내보낸 시각 (Unix timestamp)
+    pub created_at: i64,
+
+    /// 내용(해시와 크기)이 같은 파일 수
+    pub unchanged: u64,
+
+    /// 내용이 바뀐 파일 (상대 경로)
+    pub modified: Vec<String>,
+
+    /// 스냅샷에는 있지만 지금은 없는 파일
+    pub missing: Vec<String>,
+
+    /// 스냅샷 이후에 생긴 파일
+    pub added: Vec<String>,
+}
+
+impl SnapshotVerifyReport {
+    /// 스냅샷과 현재 상태가 같은지 확인합니다.
+    pub fn is_clean(&self) -> bool {
+        self.modified.is_empty() && self.missing.is_empty() && self.added.is_empty()
+    }
+}
+
+/// 인증서의 개인 키에서 스냅샷 서명 키를 만듭니다 (개인 키를 그대로 HMAC 키로 쓰지 않음).
+fn signing_key(cert: &TlsCertificate) -> Result<Vec<u8>> {
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(&cert.key_der).context("Invalid HMAC key length")?;
+    mac.update(b"pebble-snapshot-signing");
+    Ok(mac.finalize().into_bytes().to_vec())
+}
+
+fn sign(body: &SnapshotBody, key: &[u8]) -> Result<String> {
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(key).context("Invalid HMAC key length")?;
+    mac.update(&serde_json::to_vec(body)?);
+    Ok(hex::encode(mac.finalize().into_bytes()))
+}
+
+/// 서명을 확인합니다.
+///
+/// # Security
+/// - 상수 시간 비교(`verify_slice`)로 서명을 추측하는 타이밍 공격 방지
+fn check_signature(body: &SnapshotBody, signature: &str, key: &[u8]) -> Result<()> {
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(key).context("Invalid HMAC key length")?;
+    mac.update(&serde_json::to_vec(body)?);
+    let signature = hex::decode(signature).map_err(|_| PebbleError::protocol("Malformed snapshot signature"))?;
+    mac.verify_slice(&signature)
+        .map_err(|_| PebbleError::rejected("Snapshot signature does not match this device or the file was modified"))?;
+    Ok(())
+}
+
+/// CSV 필드 (쉼표, 따옴표, 줄바꿈이 있으면 따옴표로 감쌈)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// CSV 항목 행들을 필드 목록으로 나눕니다 (따옴표 안의 쉼표와 줄바꿈 허용).
+fn csv_records(text: &str) -> Result<Vec<Vec<String>>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, c) => field.push(c),
+            (false, '"') if field.is_empty() => quoted = true,
+            (false, ',') => record.push(std::mem::take(&mut field)),
+            (false, '\r') => {}
+            (false, '\n') => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(PebbleError::protocol("Unterminated quoted field in snapshot").into());
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+fn encode(body: &SnapshotBody, format: SnapshotFormat, key: &[u8]) -> Result<String> {
+    let signature = sign(body, key)?;
+    match format {
+        SnapshotFormat::Json => Ok(serde_json::to_string_pretty(&SignedSnapshot { body: body.clone(), signature })?),
+        SnapshotFormat::Csv => {
+            let mut out = format!(
+                "{} v{}\n# root: {}\n# fingerprint: {}\n# created_at: {}\n{}\n",
+                CSV_MAGIC, body.version, body.root, body.fingerprint, body.created_at, CSV_COLUMNS
+            );
+            for entry in &body.entries {
+                out.push_str(&format!(
+                    "{},{},{},{}\n",
+                    csv_field(&entry.path), entry.file_size, csv_field(&entry.file_hash), entry.last_modified
+                ));
+            }
+            out.push_str(&format!("# signature: {}\n", signature));
+            Ok(out)
+        }
+    }
+}
+
+/// CSV 머리글 줄의 값 (`# name: value`)
+fn header_value<'a>(line: Option<&'a str>, name: &str) -> Result<&'a str> {
+    line.and_then(|line| line.strip_prefix("# "))
+        .and_then(|line| line.strip_prefix(name))
+        .and_then(|line| line.strip_prefix(": "))
+        .ok_or_else(|| PebbleError::protocol(format!("Missing snapshot header: {}", name)).into())
+}
+
+fn decode_csv(text: &str) -> Result<(SnapshotBody, String)> {
+    let malformed = |what: &str| PebbleError::protocol(format!("Malformed snapshot: {}", what));
+
+    let (rest, signature_line) = text.trim_end().rsplit_once('\n').ok_or_else(|| malformed("too short"))?;
+    let signature = header_value(Some(signature_line), "signature")?.to_string();
+
+    let mut lines = rest.splitn(6, '\n');
+    let version = lines
+        .next()
+        .and_then(|line| line.strip_prefix(CSV_MAGIC))
+        .and_then(|line| line.trim().strip_prefix('v'))
+        .and_then(|version| version.parse().ok())
+        .ok_or_else(|| malformed("not a snapshot file"))?;
+    let root = header_value(lines.next(), "root")?.to_string();
+    let fingerprint = header_value(lines.next(), "fingerprint")?.to_string();
+    let created_at = header_value(lines.next(), "created_at")?.parse().map_err(|_| malformed("created_at"))?;
+    if lines.next() != Some(CSV_COLUMNS) {
+        return Err(malformed("unexpected columns").into());
+    }
+
+    let mut entries = Vec::new();
+    for record in csv_records(lines.next().unwrap_or(""))? {
+        let [path, file_size, file_hash, last_modified] = <[String; 4]>::try_from(record)
+            .map_err(|_| malformed("wrong number of fields"))?;
+        entries.push(SnapshotEntry {
+            path,
+            file_size: file_size.parse().map_err(|_| malformed("file_size"))?,
+            file_hash,
+            last_modified: last_modified.parse().map_err(|_| malformed("last_modified"))?,
+        });
+    }
+
+    Ok((SnapshotBody { version, root, fingerprint, created_at, entries }, signature))
+}
+
+/// 스냅샷 파일을 읽고 서명을 확인합니다 (형식은 내용으로 판단).
+fn decode(text: &str, key: &[u8]) -> Result<SnapshotBody> {
+    let (body, signature) = if text.trim_start().starts_with('{') {
+        let signed: SignedSnapshot = serde_json::from_str(text)
+            .map_err(|e| PebbleError::protocol(format!("Malformed snapshot: {}", e)))?;
+        (signed.body, signed.signature)
+    } else {
+        decode_csv(text)?
+    };
+
+    if body.version != SNAPSHOT_VERSION {
+        return Err(PebbleError::protocol(format!("Unsupported snapshot version: {}", body.version)).into());
+    }
+    check_signature(&body, &signature, key)?;
+    Ok(body)
+}
+
+/// 스냅샷 항목과 현재 항목을 상대 경로로 비교합니다.
+fn compare(snapshot: &SnapshotBody, current: &[SnapshotEntry]) -> SnapshotVerifyReport {
+    let mut current: BTreeMap<&str, &SnapshotEntry> = current.iter().map(|entry| (entry.path.as_str(), entry)).collect();
+    let mut report = SnapshotVerifyReport {
+        snapshot_root: snapshot.root.clone(),
+        created_at: snapshot.created_at,
+        ..Default::default()
+    };
+
+    for entry in &snapshot.entries {
+        match current.remove(entry.path.as_str()) {
+            None => report.missing.push(entry.path.clone()),
+            Some(now) if now.file_hash == entry.file_hash && now.file_size == entry.file_size => report.unchanged += 1,
+            Some(_) => report.modified.push(entry.path.clone()),
+        }
+    }
+    report.added = current.into_keys().map(str::to_string).collect();
+    report
+}
+
+/// DB에 기록된 루트의 현재 파일 (삭제 기록 제외, 상대 경로 순)
+fn current_entries(root: &str) -> Result<Vec<SnapshotEntry>> {
+    Ok(manifest::local_manifest(root)?
+        .into_iter()
+        .filter(|entry| !entry.deleted)
+        .map(|entry| SnapshotEntry {
+            path: entry.path,
+            file_size: entry.file_size,
+            file_hash: entry.file_hash,
+            last_modified: entry.last_modified,
+        })
+        .collect())
+}
+
+/// 실행 중인 서비스의 인증서 (스냅샷 서명 키의 원천)
+fn device_certificate() -> Result<TlsCertificate> {
+    service::certificate()
+        .ok_or_else(|| PebbleError::not_found("Device certificate (start Pebble before using snapshots)").into())
+}
+
+/// 동기화 루트의 현재 상태를 서명한 스냅샷으로 내보냅니다.
+///
+/// # Returns
+/// * 스냅샷 파일 내용
+pub fn export(root: &str, format: SnapshotFormat) -> Result<String> {
+    let cert = device_certificate()?;
+    let root = paths::normalize(root);
+    let body = SnapshotBody {
+        version: SNAPSHOT_VERSION,
+        entries: current_entries(&root)?,
+        root,
+        fingerprint: cert.fingerprint.clone(),
+        created_at: unix_now(),
+    };
+
+    tracing::info!("Exported snapshot of {} ({} files)", body.root, body.entries.len());
+    encode(&body, format, &signing_key(&cert)?)
+}
+
+/// 스냅샷 파일의 서명을 확인하고 동기화 루트의 현재 상태와 비교합니다.
+///
+/// # Security
+/// - 이 기기가 내보낸 스냅샷만 검증할 수 있으며, 서명이 맞지 않으면 `PebbleError::Rejected`
+pub fn verify(root: &str, snapshot_path: &str) -> Result<SnapshotVerifyReport> {
+    let text = fs::read_to_string(paths::long_path(Path::new(snapshot_path)))
+        .with_context(|| format!("Failed to read snapshot: {}", snapshot_path))?;
+    let snapshot = decode(&text, &signing_key(&device_certificate()?)?)?;
+
+    let root = paths::normalize(root);
+    let report = compare(&snapshot, &current_entries(&root)?);
+    tracing::info!(
+        "Verified {} against snapshot of {}: {} unchanged, {} modified, {} missing, {} added",
+        root, snapshot.root, report.unchanged, report.modified.len(), report.missing.len(), report.added.len()
+    );
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, hash: &str) -> SnapshotEntry {
+        SnapshotEntry { path: path.to_string(), file_size: 10, file_hash: hash.to_string(), last_modified: 1_700_000_000 }
+    }
+
+    fn body() -> SnapshotBody {
+        SnapshotBody {
+            version: SNAPSHOT_VERSION,
+            root: "/sync/photos".to_string(),
+            fingerprint: "ab:cd".to_string(),
+            created_at: 1_700_000_100,
+            entries: vec![entry("a.jpg", "h1"), entry("dir/b, \"quoted\".jpg", "h2"), entry("c.jpg", "h3")],
+        }
+    }
+
+    #[test]
+    fn test_snapshot_round_trip_and_tamper_detection() {
+        let key = b"snapshot-test-key";
+        for format in [SnapshotFormat::Json, SnapshotFormat::Csv] {
+            let text = encode(&body(), format, key).unwrap();
+            assert_eq!(decode(&text, key).unwrap(), body());
+
+            // 다른 기기의 키나 바뀐 내용은 거부
+            let err = PebbleError::from(decode(&text, b"other-device").unwrap_err());
+            assert!(matches!(err, PebbleError::Rejected { .. }));
+            let tampered = text.replace("h1", "h9");
+            assert!(matches!(PebbleError::from(decode(&tampered, key).unwrap_err()), PebbleError::Rejected { .. }));
+        }
+    }
+
+    #[test]
+    fn test_compare_with_current_state() {
+        let current = vec![entry("a.jpg", "h1"), entry("c.jpg", "changed"), entry("d.jpg", "h4")];
+        let report = compare(&body(), &current);
+        assert_eq!(report.unchanged, 1);
+        assert_eq!(report.modified, vec!["c.jpg"]);
+        assert_eq!(report.missing, vec!["dir/b, \"quoted\".jpg"]);
+        assert_eq!(report.added, vec!["d.jpg"]);
+        assert!(!report.is_clean());
+    }
+}
@@ -0,0 +1,362 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+use uuid::Uuid;
+
+use super::certificate::TlsCertificate;
+use super::config::{self, MAX_CHUNK_SIZE, MIN_CHUNK_SIZE};
+use super::error::PebbleError;
+use super::integrity::HashAlgo;
+use super::transfer::{self, TransferMessage};
+
+/// 속도 측정 기본 데이터 크기 (64MB)
+pub const DEFAULT_SPEED_TEST_BYTES: u64 = 64 * 1024 * 1024;
+
+/// 수신측이 허용하는 최대 측정 데이터 크기 (1GB)
+pub const MAX_SPEED_TEST_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// 속도 측정 결과
+///
+/// 생성된 데이터를 실제 전송과 같은 경로(TLS, 메시지 프레이밍, 청크 해시, ACK)로
+/// 보내되 디스크 I/O는 제외하여 네트워크/CPU 병목을 구분할 수 있게 합니다.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpeedTestReport {
+    pub server_address: String,
+    pub bytes_sent: u64,
+    pub chunk_size: u64,
+
+    /// TCP 연결 시간 (ms)
+    pub connect_ms: f64,
+
+    /// TLS 핸드셰이크 시간 (ms)
+    pub tls_handshake_ms: f64,
+
+    /// 요청 → 수락 왕복 시간 (ms)
+    pub request_rtt_ms: f64,
+
+    /// 데이터 전송 시간 (ms, 마지막 ACK 수신까지)
+    pub transfer_ms: f64,
+
+    /// 청크 하나의 평균 왕복 시간 (ms, 전송 + ACK 대기)
+    pub avg_chunk_rtt_ms: f64,
+
+    /// 처리량 (MB/s)
+    pub throughput_mbps: f64,
+
+    /// 수신측이 측정한 수신 시간 (ms)
+    pub server_receive_ms: f64,
+
+    /// 전송 중 사용한 프로세스 CPU 시간 (ms, 지원하지 않는 플랫폼은 None)
+    pub cpu_time_ms: Option<f64>,
+
+    /// 전송 시간 대비 CPU 사용률 (%, 멀티코어에서는 100을 넘을 수 있음)
+    pub cpu_usage_percent: Option<f64>,
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// 현재 프로세스가 사용한 CPU 시간 (user + system)
+#[cfg(unix)]
+fn process_cpu_time() -> Option<Duration> {
+    let mut usage = std::mem::MaybeUninit::<libc::rusage>::uninit();
+    // SAFETY: getrusage는 성공 시 usage 전체를 채우며, 실패 시에는 읽지 않음
+    let usage = unsafe {
+        if libc::getrusage(libc::RUSAGE_SELF, usage.as_mut_ptr()) != 0 {
+            return None;
+        }
+        usage.assume_init()
+    };
+
+    let to_duration = |tv: libc::timeval| Duration::new(tv.tv_sec as u64, tv.tv_usec as u32 * 1000);
+    Some(to_duration(usage.ru_utime) + to_duration(usage.ru_stime))
+}
+
+#[cfg(not(unix))]
+fn process_cpu_time() -> Option<Duration> {
+    None
+}
+
+fn chunk_hash(data: &[u8]) -> String {
+    use sha2::{Digest, Sha256};
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    hex::encode(hasher.finalize())
+}
+
+/// 상대 기기의 전송 서버로 속도 측정을 실행합니다.
+///
+/// # Arguments
+/// * `server_addr` - 상대 기기의 전송 서버 주소
+/// * `server_fingerprint` - 상대 인증서 핑거프린트 (Certificate Pinning용, Optional)
+/// * `total_bytes` - 보낼 데이터 크기
+pub async fn run(
+    server_addr: SocketAddr,
+    server_fingerprint: Option<String>,
+    total_bytes: u64,
+) -> Result<SpeedTestReport> {
+    if total_bytes == 0 || total_bytes > MAX_SPEED_TEST_BYTES {
+        return Err(PebbleError::invalid_argument(format!(
+            "Speed test size must be between 1 and {} bytes", MAX_SPEED_TEST_BYTES
+        )).into());
+    }
+
+    let chunk_size = config::current().chunk_size;
+    let test_id = Uuid::new_v4().to_string();
+
+    // TCP 연결
+    let started = Instant::now();
+    let tcp_stream = TcpStream::connect(server_addr).await
+        .with_context(|| format!("Failed to connect to {}", server_addr))?;
+    tcp_stream.set_nodelay(true)?;
+    let connect = started.elapsed();
+
+    // TLS 핸드셰이크
+    let started = Instant::now();
+    let connector = TlsConnector::from(TlsCertificate::build_client_config(server_fingerprint)?);
+    let domain = rustls::pki_types::ServerName::try_from("pebble.local")
+        .map_err(|_| anyhow::anyhow!("Invalid DNS name"))?;
+    let mut stream = connector.connect(domain, tcp_stream).await
+        .context("TLS handshake failed")?;
+    let tls_handshake = started.elapsed();
+
+    // 측정 요청
+    let started = Instant::now();
+    let request = TransferMessage::SpeedTestRequest {
+        test_id: test_id.clone(),
+        total_bytes,
+        chunk_size,
+    };
+    stream.write_all(&request.to_bytes()?).await?;
+
+    match TransferMessage::from_stream(&mut stream).await? {
+        TransferMessage::TransferAccept { .. } => {}
+        TransferMessage::TransferReject { reason, code, .. } => return Err(transfer::reject_error(reason, code).into()),
+        other => {
+            return Err(PebbleError::protocol(format!("Expected TransferAccept, got {:?}", other)).into());
+        }
+    }
+    let request_rtt = started.elapsed();
+
+    // 데이터 전송 (실제 전송과 같은 청크 해시 + ACK 경로)
+    let payload: Vec<u8> = (0..chunk_size).map(|i| (i % 251) as u8).collect();
+    let total_chunks = total_bytes.div_ceil(chunk_size);
+
+    let cpu_before = process_cpu_time();
+    let started = Instant::now();
+
+    for chunk_index in 0..total_chunks {
+        let len = (total_bytes - chunk_index * chunk_size).min(chunk_size) as usize;
+        let data = &payload[..len];
+
+        let chunk_msg = TransferMessage::ChunkData {
+            transfer_id: test_id.clone(),
+            chunk_index,
+            chunk_hash: chunk_hash(data),
+            data: data.to_vec(),
+        };
+        stream.write_all(&chunk_msg.to_bytes()?).await?;
+
+        match TransferMessage::from_stream(&mut stream).await? {
+            TransferMessage::ChunkAck { chunk_index: ack_idx, .. } if ack_idx == chunk_index => {}
+            other => return Err(PebbleError::protocol(format!("Expected ChunkAck, got {:?}", other)).into()),
+        }
+    }
+
+    let complete = TransferMessage::TransferComplete { transfer_id: test_id.clone(), receipt: None };
+    stream.write_all(&complete.to_bytes()?).await?;
+
+    let server_receive_ms = match TransferMessage::from_stream(&mut stream).await? {
+        TransferMessage::SpeedTestResult { receive_ms, .. } => receive_ms,
+        other => return Err(PebbleError::protocol(format!("Expected SpeedTestResult, got {:?}", other)).into()),
+    };
+
+    let transfer = started.elapsed();
+    let cpu_time = cpu_before.zip(process_cpu_time()).map(|(before, after)| after.saturating_sub(before));
+
+    let report = SpeedTestReport {
+        server_address: server_addr.to_string(),
+        bytes_sent: total_bytes,
+        chunk_size,
+        connect_ms: millis(connect),
+        tls_handshake_ms: millis(tls_handshake),
+        request_rtt_ms: millis(request_rtt),
+        transfer_ms: millis(transfer),
+        avg_chunk_rtt_ms: millis(transfer) / total_chunks as f64,
+        throughput_mbps: (total_bytes as f64 / transfer.as_secs_f64()) / 1_000_000.0,
+        server_receive_ms,
+        cpu_time_ms: cpu_time.map(millis),
+        cpu_usage_percent: cpu_time.map(|cpu| cpu.as_secs_f64() / transfer.as_secs_f64() * 100.0),
+    };
+
+    tracing::info!(
+        "Speed test to {}: {:.2} MB/s ({} bytes in {:.0} ms)",
+        server_addr, report.throughput_mbps, total_bytes, report.transfer_ms
+    );
+
+    Ok(report)
+}
+
+/// 수신측: 속도 측정 데이터를 받아 해시만 검증하고 버립니다.
+///
+/// 요청한 크기(`MAX_SPEED_TEST_BYTES` 이하)까지만 받고, 그보다 많이 보내면 중단합니다.
+pub(crate) async fn serve<S>(stream: &mut S, test_id: String, total_bytes: u64, chunk_size: u64) -> Result<()>
+where
+    S: AsyncReadExt + AsyncWriteExt + Unpin,
+{
+    if total_bytes == 0 || total_bytes > MAX_SPEED_TEST_BYTES || !(MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE).contains(&chunk_size) {
+        let reject_msg = TransferMessage::TransferReject {
+            transfer_id: test_id,
+            reason: "Unsupported speed test parameters".to_string(),
+            code: None,
+        };
+        stream.write_all(&reject_msg.to_bytes()?).await?;
+        return Err(PebbleError::rejected("Unsupported speed test parameters").into());
+    }
+
+    let accept_msg = TransferMessage::TransferAccept {
+        transfer_id: test_id.clone(),
+        resume_from_chunk: 0,
+        chunk_hash_algo: HashAlgo::default(),
+        ack_interval: 1,
+        want_manifest: false,
+        sparse: false,
+        verdict: false,
+    };
+    stream.write_all(&accept_msg.to_bytes()?).await?;
+
+    let started = Instant::now();
+    let mut bytes_received = 0u64;
+
+    while bytes_received < total_bytes {
+        match TransferMessage::from_stream(stream).await? {
+            TransferMessage::ChunkData { chunk_index, chunk_hash: expected, data, .. } => {
+                let len = data.len() as u64;
+                if len > chunk_size || bytes_received + len > total_bytes {
+                    return Err(PebbleError::protocol(format!(
+                        "Speed test chunk {} exceeds the requested {} bytes", chunk_index, total_bytes
+                    )).into());
+                }
+                if chunk_hash(&data) != expected {
+                    anyhow::bail!("Chunk hash mismatch at index {}", chunk_index);
+                }
+                bytes_received += len;
+
+                let ack_msg = TransferMessage::ChunkAck {
+                    transfer_id: test_id.clone(),
+                    chunk_index,
+                };
+                stream.write_all(&ack_msg.to_bytes()?).await?;
+            }
+            TransferMessage::TransferComplete { .. } => break,
+            other => return Err(PebbleError::protocol(format!("Unexpected message in speed test: {:?}", other)).into()),
+        }
+    }
+
+    // 요청한 크기를 다 받았으면 완료 메시지만 기다림
+    if bytes_received == total_bytes {
+        match TransferMessage::from_stream(stream).await? {
+            TransferMessage::TransferComplete { .. } => {}
+            other => return Err(PebbleError::protocol(format!("Expected TransferComplete, got {:?}", other)).into()),
+        }
+    }
+
+    let result_msg = TransferMessage::SpeedTestResult {
+        test_id,
+        bytes_received,
+        receive_ms: millis(started.elapsed()),
+    };
+    stream.write_all(&result_msg.to_bytes()?).await?;
+
+    tracing::info!("Speed test finished: {} bytes received", bytes_received);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loopback::{read_message, stream_pair, write_message};
+    use tokio::io::DuplexStream;
+
+    fn chunk(chunk_index: u64, data: Vec<u8>) -> TransferMessage {
+        TransferMessage::ChunkData {
+            transfer_id: "speed".to_string(),
+            chunk_index,
+            chunk_hash: chunk_hash(&data),
+            data,
+        }
+    }
+
+    /// 청크를 하나씩 보내고 ACK를 받은 뒤 완료를 보냅니다 (서버가 먼저 끊으면 그 자리에서 멈춤).
+    async fn send(client: &mut DuplexStream, chunks: Vec<TransferMessage>) -> Option<TransferMessage> {
+        assert!(matches!(read_message(client).await.ok()?, TransferMessage::TransferAccept { .. }));
+        for msg in chunks {
+            write_message(client, &msg).await.ok()?;
+            if !matches!(read_message(client).await.ok()?, TransferMessage::ChunkAck { .. }) {
+                return None;
+            }
+        }
+        let complete = TransferMessage::TransferComplete { transfer_id: "speed".to_string(), receipt: None };
+        write_message(client, &complete).await.ok()?;
+        read_message(client).await.ok()
+    }
+
+    /// 서버 스트림을 넘겨받아 끝나면 닫습니다 (오류로 끝나도 클라이언트가 기다리지 않도록).
+    async fn serve_and_close(mut server: DuplexStream, total_bytes: u64) -> Result<()> {
+        serve(&mut server, "speed".to_string(), total_bytes, MIN_CHUNK_SIZE).await
+    }
+
+    #[tokio::test]
+    async fn test_serve_counts_requested_bytes() {
+        let (mut client, server) = stream_pair();
+        let chunks = vec![chunk(0, vec![1; MIN_CHUNK_SIZE as usize]), chunk(1, vec![2; 100])];
+        let total = MIN_CHUNK_SIZE + 100;
+
+        let (served, result) = tokio::join!(
+            serve_and_close(server, total),
+            send(&mut client, chunks),
+        );
+        served.unwrap();
+        assert!(matches!(result, Some(TransferMessage::SpeedTestResult { bytes_received, .. }) if bytes_received == total));
+    }
+
+    #[tokio::test]
+    async fn test_serve_rejects_oversized_request() {
+        let (mut client, mut server) = stream_pair();
+        let e = serve(&mut server, "speed".to_string(), MAX_SPEED_TEST_BYTES + 1, MIN_CHUNK_SIZE).await.unwrap_err();
+        assert!(matches!(e.downcast_ref::<PebbleError>(), Some(PebbleError::Rejected { .. })));
+        assert!(matches!(read_message(&mut client).await.unwrap(), TransferMessage::TransferReject { .. }));
+
+        let e = serve(&mut server, "speed".to_string(), 0, MIN_CHUNK_SIZE).await.unwrap_err();
+        assert!(matches!(e.downcast_ref::<PebbleError>(), Some(PebbleError::Rejected { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_serve_stops_at_requested_bytes() {
+        // 요청한 크기를 넘는 청크
+        let (mut client, server) = stream_pair();
+        let chunks = vec![chunk(0, vec![1; 100]), chunk(1, vec![2; 100])];
+        let (served, _) = tokio::join!(
+            serve_and_close(server, 150),
+            send(&mut client, chunks),
+        );
+        let e = served.unwrap_err();
+        assert!(matches!(e.downcast_ref::<PebbleError>(), Some(PebbleError::Protocol { .. })));
+
+        // 요청한 크기를 다 받은 뒤에 더 보낸 청크
+        let (mut client, server) = stream_pair();
+        let chunks = vec![chunk(0, vec![1; 100]), chunk(1, vec![2; 1])];
+        let (served, _) = tokio::join!(
+            serve_and_close(server, 100),
+            send(&mut client, chunks),
+        );
+        let e = served.unwrap_err();
+        assert!(matches!(e.downcast_ref::<PebbleError>(), Some(PebbleError::Protocol { .. })));
+    }
+}
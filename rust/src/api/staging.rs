@@ -0,0 +1,824 @@
+//! 암호화된 수신 대기 영역 (Encrypted Staging)
+//!
+//! 반쯤 신뢰하는 기기에게서 받는 파일을 바로 받을 폴더에 두지 않고, 설정의 대기 폴더(`staging_dir`)에
+//! 블록 단위로 암호화(ChaCha20-Poly1305)하여 받아 둡니다. 사용자가 승인하면(`approve`) 그때 복호화하여 원래 받을 위치에
+//! 저장하고, 거절하면(`discard`) 지웁니다. 승인 전에는 다른 앱이 내용을 열거나 실행할 수 없습니다.
+//! 저장 위치가 잠겨 대신 받는 임시 파일(`locked`)과는 별개입니다.
+//!
+//! # Process Flow
+//! 1. 설정에 `staging_dir`이 있으면 `backend::current`가 `StagingBackend`를 선택
+//! 2. 수신마다 임의의 salt를 `staged_files`에 기록하고, 기기 키와 salt로 만든 파일 키로 `<id>.sealed`에
+//!    암호화하여 씀
+//!    (이어받기는 같은 전송 ID의 미완료 항목을 다시 엶)
+//! 3. 파일 해시까지 검증되면 완료 시각과 해시를 기록하고 `FileStaged` 이벤트 발행
+//! 4. `approve`: 복호화하며 해시를 다시 확인하고, 외부 검사 명령(`scan_hook`)을 통과하면 로컬 저장 위치(`LocalBackend`)에
+//!    저장한 뒤 대기 파일 삭제
+//! 5. `discard`: 대기 파일과 항목 삭제
+//!
+//! # Security
+//! - 파일 키는 기기 인증서의 개인 키에서 유도하며 DB에 두지 않음 (대기 폴더와 DB를 함께 복사해 가도 볼 수 없음)
+//! - 블록마다 인증 태그를 확인하므로 변조된 블록은 복호화되지 않고, 블록 위치도 인증 데이터에 포함
+//! - 승인할 때 복호화한 내용의 해시가 받을 때와 다르면 저장하지 않음 (해시가 없는 항목은 승인 거부)
+//! - 받을 위치의 공유 폴더 쓰기 권한은 받을 때와 승인할 때 모두 보낸 기기 기준으로 확인
+
+use anyhow::{Context, Result};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit, Nonce};
+use futures::future::BoxFuture;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+use super::backend::{Destination, DestinationRequest, LocalBackend, ReceiveSink, StorageBackend};
+use super::config;
+use super::db;
+use super::error::PebbleError;
+use super::events::{self, PebbleEvent};
+use super::filename::{self, DestinationClaim, FALLBACK_FILE_NAME};
+use super::integrity;
+use super::locked;
+use super::paths;
+use super::scanhook::ScanHook;
+use super::shares;
+use super::util::unix_now;
+
+/// 수신 파일의 표시용 경로 접두사 (`staging://<id>`)
+pub const STAGED_URI_PREFIX: &str = "staging://";
+
+/// 대기 파일 확장자
+const SEALED_EXTENSION: &str = "sealed";
+
+/// 승인할 때 한 번에 복호화하여 옮기는 크기
+const BLOCK_SIZE: usize = 256 * 1024;
+
+/// 대기 파일에서 인증 태그 하나로 보호하는 평문 크기
+const SEAL_BLOCK_SIZE: u64 = 64 * 1024;
+
+/// 블록마다 앞에 두는 nonce 크기
+const NONCE_LEN: usize = 12;
+
+/// 블록마다 평문보다 늘어나는 크기 (nonce + 인증 태그)
+const SEAL_OVERHEAD: u64 = NONCE_LEN as u64 + 16;
+
+/// 파일 키를 유도하는 기기 키 (기본 전송 서버가 시작될 때 인증서 개인 키로 설정)
+static DEVICE_KEY: Mutex<Option<[u8; 32]>> = Mutex::new(None);
+
+/// 승인을 기다리는 수신 파일
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StagedFile {
+    pub id: String,
+    pub transfer_id: String,
+
+    /// 송신측 경로에서 정리한 파일 이름
+    pub file_name: String,
+
+    pub file_size: u64,
+    pub sender_device_id: Option<String>,
+
+    /// 받기 시작한 시각 (Unix timestamp)
+    pub staged_at: i64,
+
+    /// 받기를 마친 시각 (None이면 아직 받는 중이거나 중단됨, 승인할 수 없음)
+    pub completed_at: Option<i64>,
+}
+
+/// DB에 기록된 대기 항목
+struct StagedRecord {
+    file: StagedFile,
+    file_path: String,
+    download_dir: Option<String>,
+    sealed_path: PathBuf,
+
+    /// 이전 형식이 DB에 두던 키 (지금 형식의 항목은 비어 있음)
+    key: Vec<u8>,
+
+    /// 파일 키를 유도할 때 섞는 임의 값
+    salt: Vec<u8>,
+
+    file_hash: Option<String>,
+}
+
+const SELECT_COLUMNS: &str = "id, transfer_id, file_path, file_size, sender_device_id, staged_at, completed_at, \
+                              download_dir, sealed_path, key, nonce, file_hash";
+
+fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+fn display_name(file_path: &str) -> String {
+    filename::sanitize(paths::remote_file_name(file_path).unwrap_or(FALLBACK_FILE_NAME))
+}
+
+fn from_row(row: &Row) -> rusqlite::Result<StagedRecord> {
+    let file_path: String = row.get(2)?;
+    Ok(StagedRecord {
+        file: StagedFile {
+            id: row.get(0)?,
+            transfer_id: row.get(1)?,
+            file_name: display_name(&file_path),
+            file_size: row.get::<_, i64>(3)? as u64,
+            sender_device_id: row.get(4)?,
+            staged_at: row.get(5)?,
+            completed_at: row.get(6)?,
+        },
+        file_path,
+        download_dir: row.get(7)?,
+        sealed_path: PathBuf::from(row.get::<_, String>(8)?),
+        key: row.get(9)?,
+        salt: row.get(10)?,
+        file_hash: row.get(11)?,
+    })
+}
+
+fn load(conn: &Connection, id: &str) -> Result<StagedRecord> {
+    conn.query_row(&format!("SELECT {} FROM staged_files WHERE id = ?1", SELECT_COLUMNS), params![id], from_row)
+        .optional()?
+        .ok_or_else(|| PebbleError::not_found(format!("Staged file {}", id)).into())
+}
+
+/// 대기 파일과 항목을 지웁니다.
+fn remove(conn: &Connection, record: &StagedRecord) -> Result<()> {
+    match fs::remove_file(&record.sealed_path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e).with_context(|| format!("Failed to remove {}", record.sealed_path.display())),
+    }
+    conn.execute("DELETE FROM staged_files WHERE id = ?1", params![record.file.id])?;
+    Ok(())
+}
+
+/// 대기 파일 키를 유도할 기기 키를 설정합니다.
+///
+/// # Arguments
+/// * `secret` - 이 기기만 가진 비밀 (기기 인증서의 개인 키)
+pub fn set_device_key(secret: &[u8]) {
+    *DEVICE_KEY.lock().unwrap() = Some(blake3::derive_key("Pebble staging device key v1", secret));
+}
+
+/// 기기 키와 항목의 ID, salt로 파일 키를 유도합니다.
+fn file_key(id: &str, salt: &[u8]) -> Result<[u8; 32]> {
+    let device_key = DEVICE_KEY
+        .lock()
+        .unwrap()
+        .ok_or_else(|| PebbleError::invalid_argument("Staging needs the device key; start the transfer server first"))?;
+    let mut hasher = blake3::Hasher::new_keyed(&device_key);
+    hasher.update(id.as_bytes());
+    hasher.update(salt);
+    Ok(*hasher.finalize().as_bytes())
+}
+
+/// 평문 길이에 해당하는 대기 파일 크기
+fn sealed_len(len: u64) -> u64 {
+    let partial = len % SEAL_BLOCK_SIZE;
+    len / SEAL_BLOCK_SIZE * (SEAL_BLOCK_SIZE + SEAL_OVERHEAD) + if partial > 0 { partial + SEAL_OVERHEAD } else { 0 }
+}
+
+/// 대기 파일 크기에 해당하는 평문 길이 (블록이 잘려 있으면 오류)
+fn plain_len(sealed: u64) -> io::Result<u64> {
+    let partial = sealed % (SEAL_BLOCK_SIZE + SEAL_OVERHEAD);
+    if partial != 0 && partial <= SEAL_OVERHEAD {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Staged file ends in a truncated block"));
+    }
+    Ok(sealed / (SEAL_BLOCK_SIZE + SEAL_OVERHEAD) * SEAL_BLOCK_SIZE + partial.saturating_sub(SEAL_OVERHEAD))
+}
+
+/// 쓰는 내용은 암호화하고 읽는 내용은 복호화하는 대기 파일
+///
+/// 평문을 `SEAL_BLOCK_SIZE`씩 나눠 블록마다 새 nonce로 봉인하므로 청크를 아무 순서로 써도 됩니다.
+/// 블록 일부만 쓰면 그 블록을 복호화해 고친 뒤 다시 봉인하고, 쓰지 않고 건너뛴 구멍은 0으로 봉인합니다.
+/// 위치와 길이는 모두 평문 기준입니다.
+struct SealedFile {
+    id: String,
+    file: File,
+    cipher: ChaCha20Poly1305,
+    position: u64,
+    len: u64,
+}
+
+impl SealedFile {
+    fn open(record: &StagedRecord, file: File) -> Result<Self> {
+        if !record.key.is_empty() {
+            return Err(PebbleError::rejected(format!(
+                "{} was staged by an older version; discard it and receive it again", record.file.file_name
+            )).into());
+        }
+        let key = file_key(&record.file.id, &record.salt)?;
+        let len = plain_len(file.metadata()?.len())?;
+        Ok(Self {
+            id: record.file.id.clone(),
+            file,
+            cipher: ChaCha20Poly1305::new(Key::from_slice(&key)),
+            position: 0,
+            len,
+        })
+    }
+
+    /// 블록을 다른 파일이나 다른 위치로 옮겨 붙이지 못하도록 인증 데이터에 ID와 블록 번호를 넣습니다.
+    fn associated_data(&self, index: u64) -> Vec<u8> {
+        let mut aad = self.id.as_bytes().to_vec();
+        aad.extend_from_slice(&index.to_be_bytes());
+        aad
+    }
+
+    /// 블록 하나를 복호화합니다 (끝을 넘으면 빈 블록).
+    fn read_block(&mut self, index: u64) -> io::Result<Vec<u8>> {
+        let start = index * SEAL_BLOCK_SIZE;
+        if start >= self.len {
+            return Ok(Vec::new());
+        }
+        let len = (self.len - start).min(SEAL_BLOCK_SIZE);
+        let mut sealed = vec![0u8; (len + SEAL_OVERHEAD) as usize];
+        self.file.seek(SeekFrom::Start(index * (SEAL_BLOCK_SIZE + SEAL_OVERHEAD)))?;
+        self.file.read_exact(&mut sealed)?;
+
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let aad = self.associated_data(index);
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: &aad })
+            .map_err(|_| io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Staged file {} failed authentication at block {}", self.id, index),
+            ))
+    }
+
+    /// 블록 하나를 새 nonce로 봉인하여 씁니다.
+    fn write_block(&mut self, index: u64, block: &[u8]) -> io::Result<()> {
+        let nonce = random_bytes(NONCE_LEN);
+        let aad = self.associated_data(index);
+        let ciphertext = self.cipher
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: block, aad: &aad })
+            .map_err(|_| io::Error::other(format!("Failed to encrypt staged file {}", self.id)))?;
+        self.file.seek(SeekFrom::Start(index * (SEAL_BLOCK_SIZE + SEAL_OVERHEAD)))?;
+        self.file.write_all(&nonce)?;
+        self.file.write_all(&ciphertext)
+    }
+
+    /// 평문 길이를 바꿉니다 (늘어난 부분은 0).
+    fn resize(&mut self, len: u64) -> io::Result<()> {
+        if len < self.len {
+            let index = len / SEAL_BLOCK_SIZE;
+            let keep = (len % SEAL_BLOCK_SIZE) as usize;
+            let last = if keep > 0 { Some(self.read_block(index)?) } else { None };
+            self.file.set_len(index * (SEAL_BLOCK_SIZE + SEAL_OVERHEAD))?;
+            self.len = index * SEAL_BLOCK_SIZE;
+            if let Some(mut last) = last {
+                last.truncate(keep);
+                self.write_block(index, &last)?;
+                self.len = len;
+            }
+        }
+        while self.len < len {
+            let index = self.len / SEAL_BLOCK_SIZE;
+            let mut block = self.read_block(index)?;
+            block.resize((len - index * SEAL_BLOCK_SIZE).min(SEAL_BLOCK_SIZE) as usize, 0);
+            self.write_block(index, &block)?;
+            self.len = index * SEAL_BLOCK_SIZE + block.len() as u64;
+        }
+        debug_assert_eq!(self.file.metadata()?.len(), sealed_len(self.len));
+        Ok(())
+    }
+}
+
+impl Read for SealedFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.position >= self.len {
+            return Ok(0);
+        }
+        let index = self.position / SEAL_BLOCK_SIZE;
+        let offset = (self.position % SEAL_BLOCK_SIZE) as usize;
+        let block = self.read_block(index)?;
+        let read = buf.len().min(block.len() - offset);
+        buf[..read].copy_from_slice(&block[offset..offset + read]);
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Write for SealedFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.position > self.len {
+            self.resize(self.position)?;
+        }
+
+        let index = self.position / SEAL_BLOCK_SIZE;
+        let offset = (self.position % SEAL_BLOCK_SIZE) as usize;
+        let written = buf.len().min(SEAL_BLOCK_SIZE as usize - offset);
+        // 블록 전체를 덮어쓰면 이전 내용을 복호화할 필요가 없음
+        let mut block = if written as u64 == SEAL_BLOCK_SIZE { Vec::new() } else { self.read_block(index)? };
+        if block.len() < offset + written {
+            block.resize(offset + written, 0);
+        }
+        block[offset..offset + written].copy_from_slice(&buf[..written]);
+        self.write_block(index, &block)?;
+
+        self.len = self.len.max(index * SEAL_BLOCK_SIZE + block.len() as u64);
+        self.position += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Seek for SealedFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::Current(delta) => self.position.checked_add_signed(delta),
+            SeekFrom::End(delta) => self.len.checked_add_signed(delta),
+        };
+        self.position = position
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid seek in staged file"))?;
+        Ok(self.position)
+    }
+}
+
+impl ReceiveSink for SealedFile {
+    fn set_len(&mut self, len: u64) -> io::Result<()> {
+        self.resize(len)
+    }
+
+    fn size(&self) -> io::Result<u64> {
+        Ok(self.len)
+    }
+
+    fn finish(&mut self, file_hash: Option<&str>) -> io::Result<()> {
+        self.file.sync_all()?;
+        mark_complete(&self.id, file_hash).map_err(io::Error::other)
+    }
+}
+
+/// 받기를 마친 항목을 기록하고 승인을 요청합니다.
+fn mark_complete(id: &str, file_hash: Option<&str>) -> Result<()> {
+    let conn = db::open_connection()?;
+    conn.execute(
+        "UPDATE staged_files SET completed_at = ?2, file_hash = ?3 WHERE id = ?1",
+        params![id, unix_now(), file_hash],
+    )?;
+
+    let record = load(&conn, id)?;
+    tracing::info!("Staged {} ({} bytes), waiting for approval", record.file.file_name, record.file.file_size);
+    events::emit(PebbleEvent::FileStaged {
+        staged_id: record.file.id,
+        file_name: record.file.file_name,
+        file_size: record.file.file_size,
+        sender_device_id: record.file.sender_device_id,
+    });
+    Ok(())
+}
+
+/// 암호화된 대기 영역 저장소
+#[derive(Debug, Clone)]
+pub struct StagingBackend {
+    dir: PathBuf,
+}
+
+impl StagingBackend {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// 설정의 대기 폴더 (설정되어 있지 않으면 None)
+    pub fn from_config() -> Option<Self> {
+        config::current().staging_dir.map(Self::new)
+    }
+
+    fn open_sealed(&self, request: &DestinationRequest<'_>) -> Result<Destination> {
+        // 승인해도 저장할 수 없는 파일은 받기 전에 거부
+        let requested = request.requested_path();
+        shares::check_write(&requested.to_string_lossy(), request.sender_device_id)?;
+
+        let conn = db::open_connection()?;
+        let pending = conn
+            .query_row(
+                &format!("SELECT {} FROM staged_files WHERE transfer_id = ?1 AND completed_at IS NULL", SELECT_COLUMNS),
+                params![request.transfer_id],
+                from_row,
+            )
+            .optional()?;
+        if let Some(record) = pending {
+            if request.resuming && record.sealed_path.is_file() {
+                let file = OpenOptions::new().read(true).write(true).open(&record.sealed_path)
+                    .with_context(|| format!("Failed to open {}", record.sealed_path.display()))?;
+                return Self::destination(&record, file);
+            }
+            // 처음부터 다시 받으므로 이전 시도에서 남은 항목은 지움
+            remove(&conn, &record)?;
+        }
+
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create staging directory: {}", self.dir.display()))?;
+        let id = Uuid::new_v4().simple().to_string();
+        let sealed_path = self.dir.join(format!("{}.{}", id, SEALED_EXTENSION));
+        let file = OpenOptions::new().create_new(true).read(true).write(true).open(&sealed_path)
+            .with_context(|| format!("Failed to create {}", sealed_path.display()))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&sealed_path, fs::Permissions::from_mode(0o600))?;
+        }
+
+        let record = StagedRecord {
+            file: StagedFile {
+                id,
+                transfer_id: request.transfer_id.to_string(),
+                file_name: request.file_name(),
+                file_size: request.file_size,
+                sender_device_id: request.sender_device_id.map(str::to_string),
+                staged_at: unix_now(),
+                completed_at: None,
+            },
+            file_path: request.file_path.to_string(),
+            download_dir: request.download_dir.clone(),
+            sealed_path,
+            key: Vec::new(),
+            salt: random_bytes(32),
+            file_hash: None,
+        };
+        conn.execute(
+            "INSERT INTO staged_files (id, transfer_id, file_path, file_size, sender_device_id, staged_at,
+                                       download_dir, sealed_path, key, nonce)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                record.file.id,
+                record.file.transfer_id,
+                record.file_path,
+                record.file.file_size as i64,
+                record.file.sender_device_id,
+                record.file.staged_at,
+                record.download_dir,
+                record.sealed_path.to_string_lossy(),
+                record.key,
+                record.salt,
+            ],
+        )?;
+
+        Self::destination(&record, file)
+    }
+
+    fn destination(record: &StagedRecord, file: File) -> Result<Destination> {
+        let sink = SealedFile::open(record, file)?;
+        Ok(Destination::new(sink, format!("{}{}", STAGED_URI_PREFIX, record.file.id)))
+    }
+}
+
+impl StorageBackend for StagingBackend {
+    fn name(&self) -> &str {
+        "staging"
+    }
+
+    fn open<'a>(&'a self, request: DestinationRequest<'a>) -> BoxFuture<'a, Result<Option<Destination>>> {
+        Box::pin(async move { self.open_sealed(&request).map(Some) })
+    }
+}
+
+/// 대기 중인 항목을 받기 시작한 순서대로 가져옵니다 (받는 중이거나 중단된 항목 포함).
+pub fn list() -> Result<Vec<StagedFile>> {
+    let conn = db::open_connection()?;
+    let files = conn
+        .prepare(&format!("SELECT {} FROM staged_files ORDER BY staged_at, rowid", SELECT_COLUMNS))?
+        .query_map([], from_row)?
+        .map(|record| record.map(|record| record.file))
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(files)
+}
+
+/// 저장 위치 옆 임시 파일에 복호화를 마친 대기 항목
+struct Decrypted {
+    record: StagedRecord,
+    path: String,
+    staged: PathBuf,
+    _claim: Option<DestinationClaim>,
+}
+
+/// 대기 파일을 복호화하여 원래 받을 위치에 저장합니다.
+///
+/// # Returns
+/// * 저장한 경로
+///
+/// # Security
+/// - 받을 때 기록한 해시가 없으면 확인할 수 없으므로 승인하지 않음
+/// - 복호화한 내용의 해시가 받을 때와 다르거나 인증에 실패한 블록이 있으면 저장하지 않음
+/// - 외부 검사 명령이 설정되어 있으면 복호화한 임시 파일을 검사하고, 실패하면 격리하여 저장하지 않음
+///   (대기 항목은 남으므로 `discard`로 지울 수 있음)
+pub async fn approve(id: &str) -> Result<String> {
+    approve_scanned(id, config::current().scan_hook).await
+}
+
+/// `scan_hook`으로 검사하며 승인합니다.
+async fn approve_scanned(id: &str, scan_hook: Option<ScanHook>) -> Result<String> {
+    let id = id.to_string();
+    // 복호화와 해시 계산은 블로킹 작업이므로 별도 스레드에서 실행
+    let decrypted = tokio::task::spawn_blocking(move || decrypt(&id)).await??;
+
+    // 수신 중에는 암호화된 대기 파일이라 검사하지 못했으므로 복호화한 임시 파일을 옮기기 전에 검사
+    if let Some(hook) = scan_hook {
+        let record = &decrypted.record.file;
+        if let Err(e) = hook.scan(&decrypted.staged, &record.transfer_id, record.sender_device_id.as_deref()).await {
+            // 격리되지 않고 남은 임시 파일도 저장 위치에 나타나지 않도록 지움
+            let _ = fs::remove_file(paths::long_path(&decrypted.staged));
+            return Err(e);
+        }
+    }
+
+    tokio::task::spawn_blocking(move || save(decrypted)).await?
+}
+
+/// 대기 파일을 저장 위치 옆 임시 파일에 복호화하고 해시를 확인합니다.
+fn decrypt(id: &str) -> Result<Decrypted> {
+    let conn = db::open_connection()?;
+    let record = load(&conn, id)?;
+    if record.file.completed_at.is_none() {
+        return Err(PebbleError::invalid_argument(format!("{} has not been fully received", record.file.file_name)).into());
+    }
+    let Some(expected_hash) = record.file_hash.clone() else {
+        return Err(PebbleError::rejected(format!(
+            "{} has no file hash to verify it against; discard it and receive it again", record.file.file_name
+        )).into());
+    };
+
+    let file = File::open(&record.sealed_path)
+        .with_context(|| format!("Failed to open {}", record.sealed_path.display()))?;
+    let mut sealed = SealedFile::open(&record, file)?;
+
+    let request = DestinationRequest {
+        transfer_id: &record.file.transfer_id,
+        file_path: &record.file_path,
+        file_size: record.file.file_size,
+        resuming: false,
+        download_dir: record.download_dir.clone(),
+        sender_device_id: record.file.sender_device_id.as_deref(),
+    };
+    let Some(destination) = LocalBackend::open_local(&request)? else {
+        return Err(PebbleError::rejected(format!(
+            "A file differing only in letter case from {} already exists", record.file.file_name
+        )).into());
+    };
+    let Destination { sink: mut output, path, staged, claim } = destination;
+    // 확인을 마친 내용만 저장 위치에 나타나도록 같은 폴더의 임시 파일에 쓴 뒤 옮김
+    let staged = staged.ok_or_else(|| PebbleError::io(format!("No temporary file for {}", path)))?;
+
+    let written = (|| -> Result<String> {
+        let mut running_hash = integrity::RunningHash::new();
+        let mut buffer = vec![0u8; BLOCK_SIZE];
+        output.set_len(0)?;
+        loop {
+            let read = sealed.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            running_hash.update(&buffer[..read]);
+            output.write_all(&buffer[..read])?;
+        }
+        Ok(running_hash.finalize())
+    })();
+
+    let verified = written.and_then(|actual| if actual != expected_hash {
+        Err(PebbleError::rejected(format!(
+            "Staged file {} was modified after it was received", record.file.file_name
+        )).into())
+    } else {
+        Ok(actual)
+    });
+    let file_hash = match verified {
+        Ok(file_hash) => file_hash,
+        Err(e) => {
+            drop(output);
+            let _ = fs::remove_file(paths::long_path(&staged));
+            return Err(e);
+        }
+    };
+    output.finish(Some(&file_hash))?;
+    Ok(Decrypted { record, path, staged, _claim: claim })
+}
+
+/// 복호화한 임시 파일을 저장 위치로 옮기고 대기 항목을 지웁니다.
+fn save(decrypted: Decrypted) -> Result<String> {
+    let Decrypted { record, path, staged, _claim } = decrypted;
+    if let Err(e) = fs::rename(paths::long_path(&staged), paths::long_path(Path::new(&path))) {
+        let _ = fs::remove_file(paths::long_path(&staged));
+        if locked::is_locked(&e) {
+            return Err(PebbleError::io(format!("{} is in use by another app", path)).into());
+        }
+        return Err(anyhow::Error::from(e).context(format!("Failed to save {}", path)));
+    }
+
+    remove(&db::open_connection()?, &record)?;
+    tracing::info!("Approved staged file {}, saved to {}", record.file.file_name, path);
+    Ok(path)
+}
+
+/// 대기 파일을 저장하지 않고 지웁니다.
+///
+/// # Returns
+/// * 그 ID의 항목이 있었으면 true
+pub fn discard(id: &str) -> Result<bool> {
+    let conn = db::open_connection()?;
+    let record = match load(&conn, id) {
+        Ok(record) => record,
+        Err(e) if matches!(e.downcast_ref::<PebbleError>(), Some(PebbleError::NotFound { .. })) => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    remove(&conn, &record)?;
+    tracing::info!("Discarded staged file {}", record.file.file_name);
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 청크를 순서 없이, 구멍을 두고 받은 것처럼 씁니다.
+    async fn receive(backend: &StagingBackend, transfer_id: &str, file_path: &str, download_dir: &Path, content: &[u8]) -> String {
+        set_device_key(b"staging test device key");
+        let request = DestinationRequest {
+            transfer_id,
+            file_path,
+            file_size: content.len() as u64,
+            resuming: false,
+            download_dir: Some(download_dir.to_string_lossy().to_string()),
+            sender_device_id: None,
+        };
+        let Destination { mut sink, path, .. } = backend.open(request).await.unwrap().unwrap();
+
+        let half = content.len() / 2;
+        sink.seek(SeekFrom::Start(half as u64)).unwrap();
+        sink.write_all(&content[half..]).unwrap();
+        sink.seek(SeekFrom::Start(0)).unwrap();
+        sink.write_all(&content[..half]).unwrap();
+
+        let mut running_hash = integrity::RunningHash::new();
+        running_hash.update(content);
+        sink.finish(Some(&running_hash.finalize())).unwrap();
+        path.strip_prefix(STAGED_URI_PREFIX).unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_staged_file_is_encrypted_until_approved() {
+        crate::loopback::use_temp_environment();
+        let dir = tempfile::TempDir::new().unwrap();
+        let backend = StagingBackend::new(dir.path().join("staging"));
+        let downloads = dir.path().join("downloads");
+
+        let mut content = vec![0u8; 300 * 1024];
+        content[..4].copy_from_slice(b"MZ\x90\x00");
+        content[200 * 1024..].fill(0x5a);
+        let id = receive(&backend, "staged-1", "C:\\in\\setup.exe", &downloads, &content).await;
+
+        let sealed = fs::read(dir.path().join("staging").join(format!("{}.{}", id, SEALED_EXTENSION))).unwrap();
+        assert_eq!(sealed.len() as u64, sealed_len(content.len() as u64));
+        assert!(!sealed.windows(4).any(|window| window == b"MZ\x90\x00"));
+        let key: Vec<u8> = db::open_connection().unwrap()
+            .query_row("SELECT key FROM staged_files WHERE id = ?1", params![id], |row| row.get(0))
+            .unwrap();
+        assert!(key.is_empty());
+        assert!(!downloads.join("setup.exe").exists());
+
+        let staged = list().unwrap().into_iter().find(|file| file.id == id).unwrap();
+        assert_eq!(staged.file_name, "setup.exe");
+        assert!(staged.completed_at.is_some());
+
+        let saved = approve(&id).await.unwrap();
+        assert_eq!(PathBuf::from(&saved), downloads.join("setup.exe"));
+        assert_eq!(fs::read(&saved).unwrap(), content);
+        assert!(list().unwrap().iter().all(|file| file.id != id));
+        assert!(!dir.path().join("staging").join(format!("{}.{}", id, SEALED_EXTENSION)).exists());
+    }
+
+    #[tokio::test]
+    async fn test_tampered_staged_file_is_not_saved() {
+        crate::loopback::use_temp_environment();
+        let dir = tempfile::TempDir::new().unwrap();
+        let backend = StagingBackend::new(dir.path().join("staging"));
+        let downloads = dir.path().join("downloads");
+
+        let id = receive(&backend, "staged-2", "/in/notes.txt", &downloads, b"quarterly numbers").await;
+        let sealed_path = dir.path().join("staging").join(format!("{}.{}", id, SEALED_EXTENSION));
+        let mut sealed = fs::read(&sealed_path).unwrap();
+        sealed[0] ^= 0xff;
+        fs::write(&sealed_path, sealed).unwrap();
+
+        assert!(approve(&id).await.is_err());
+        assert!(!downloads.join("notes.txt").exists());
+
+        assert!(discard(&id).unwrap());
+        assert!(!discard(&id).unwrap());
+        assert!(!sealed_path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_staged_file_without_hash_is_not_approved() {
+        crate::loopback::use_temp_environment();
+        set_device_key(b"staging test device key");
+        let dir = tempfile::TempDir::new().unwrap();
+        let backend = StagingBackend::new(dir.path().join("staging"));
+        let downloads = dir.path().join("downloads");
+
+        let request = DestinationRequest {
+            transfer_id: "staged-3",
+            file_path: "/in/unverified.txt",
+            file_size: 8,
+            resuming: false,
+            download_dir: Some(downloads.to_string_lossy().to_string()),
+            sender_device_id: None,
+        };
+        let Destination { mut sink, path, .. } = backend.open(request).await.unwrap().unwrap();
+        sink.write_all(b"unsigned").unwrap();
+        sink.finish(None).unwrap();
+        let id = path.strip_prefix(STAGED_URI_PREFIX).unwrap().to_string();
+
+        let e = approve(&id).await.unwrap_err();
+        assert!(matches!(e.downcast_ref::<PebbleError>(), Some(PebbleError::Rejected { .. })));
+        assert!(!downloads.join("unverified.txt").exists());
+        assert!(discard(&id).unwrap());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_approved_file_is_scanned_before_saving() {
+        crate::loopback::use_temp_environment();
+        let dir = tempfile::TempDir::new().unwrap();
+        let backend = StagingBackend::new(dir.path().join("staging"));
+        let downloads = dir.path().join("downloads");
+        let quarantine = dir.path().join("quarantine");
+        let hook = ScanHook {
+            program: "/bin/sh".to_string(),
+            args: vec!["-c".to_string(), "! grep -q EICAR \"$1\"".to_string(), "scan".to_string()],
+            timeout_secs: 5,
+            quarantine_dir: Some(quarantine.to_string_lossy().to_string()),
+        };
+
+        let id = receive(&backend, "staged-4", "/in/infected.exe", &downloads, b"X5O!P%@AP EICAR").await;
+        let e = approve_scanned(&id, Some(hook.clone())).await.unwrap_err();
+        assert!(matches!(e.downcast_ref::<PebbleError>(), Some(PebbleError::Rejected { .. })));
+        assert!(!downloads.join("infected.exe").exists());
+        assert_eq!(fs::read_dir(&quarantine).unwrap().count(), 1);
+        // 저장 폴더에는 임시 파일도 남지 않고, 대기 항목은 지울 수 있도록 남음
+        assert!(fs::read_dir(&downloads).map_or(true, |mut entries| entries.next().is_none()));
+        assert!(discard(&id).unwrap());
+
+        let id = receive(&backend, "staged-5", "/in/clean.txt", &downloads, b"quarterly numbers").await;
+        let saved = approve_scanned(&id, Some(hook)).await.unwrap();
+        assert_eq!(fs::read(saved).unwrap(), b"quarterly numbers");
+    }
+
+    #[test]
+    fn test_sealed_file_rewrites_partial_blocks() {
+        set_device_key(b"staging test device key");
+        let dir = tempfile::TempDir::new().unwrap();
+        let record = StagedRecord {
+            file: StagedFile {
+                id: "sealed-blocks".to_string(),
+                transfer_id: "sealed-blocks".to_string(),
+                file_name: "blocks.bin".to_string(),
+                file_size: 0,
+                sender_device_id: None,
+                staged_at: 0,
+                completed_at: None,
+            },
+            file_path: "blocks.bin".to_string(),
+            download_dir: None,
+            sealed_path: dir.path().join("blocks.sealed"),
+            key: Vec::new(),
+            salt: random_bytes(32),
+            file_hash: None,
+        };
+        let open = || {
+            let file = OpenOptions::new().create(true).truncate(false).read(true).write(true)
+                .open(&record.sealed_path).unwrap();
+            SealedFile::open(&record, file).unwrap()
+        };
+
+        // 블록 경계를 걸치는 쓰기, 구멍, 줄이기를 거친 뒤 다시 열어 읽음
+        let mut expected = vec![0u8; (SEAL_BLOCK_SIZE * 2 + 100) as usize];
+        let mut sealed = open();
+        sealed.seek(SeekFrom::Start(SEAL_BLOCK_SIZE - 10)).unwrap();
+        sealed.write_all(&[1u8; 30]).unwrap();
+        expected[(SEAL_BLOCK_SIZE - 10) as usize..(SEAL_BLOCK_SIZE + 20) as usize].fill(1);
+        sealed.seek(SeekFrom::Start(SEAL_BLOCK_SIZE * 3)).unwrap();
+        sealed.write_all(&[2u8; 10]).unwrap();
+        sealed.set_len(expected.len() as u64).unwrap();
+        sealed.seek(SeekFrom::Start(0)).unwrap();
+        sealed.write_all(b"head").unwrap();
+        expected[..4].copy_from_slice(b"head");
+        assert_eq!(sealed.size().unwrap(), expected.len() as u64);
+        drop(sealed);
+
+        let mut sealed = open();
+        let mut content = Vec::new();
+        sealed.read_to_end(&mut content).unwrap();
+        assert_eq!(content, expected);
+        assert_eq!(fs::metadata(&record.sealed_path).unwrap().len(), sealed_len(expected.len() as u64));
+    }
+}
@@ -0,0 +1,270 @@
+//! 호스트 제공 저장소 (Android Storage Access Framework)
+//!
+//! Android에서는 사용자 폴더의 실제 경로에 접근할 수 없는 경우가 많으므로,
+//! 호스트(Dart)가 SAF로 문서를 만들고 `ParcelFileDescriptor.detachFd()`로 얻은
+//! 파일 디스크립터를 넘겨주면 Rust가 그 디스크립터로 읽고 씁니다.
+//!
+//! # Process Flow
+//! 1. 호스트가 요청 스트림을 구독하면 호스트 저장소 모드가 켜짐
+//! 2. 파일 수신 시 `StorageRequest`를 호스트로 보내고 응답을 기다림
+//! 3. 호스트가 `provide_fd`(문서 URI와 fd) 또는 `reject`로 응답
+//! 4. 받은 fd로 수신 파일을 기록 (fd는 Rust가 소유하고 닫음)
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+use super::error::PebbleError;
+use super::util::Subscribers;
+
+/// 호스트가 요청에 응답할 때까지 기다리는 최대 시간
+pub const HOST_RESPONSE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 호스트에게 보내는 저장소 요청
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageRequest {
+    /// 응답 시 사용할 요청 ID
+    pub request_id: String,
+
+    /// 수신 중인 전송 ID (이어받기 시 같은 문서를 돌려주는 데 사용)
+    pub transfer_id: String,
+
+    /// 만들 문서의 파일 이름
+    pub file_name: String,
+
+    /// 예상 파일 크기 (bytes)
+    pub file_size: u64,
+
+    /// 이어받기이면 이 전송이 처음 받던 문서의 URI
+    ///
+    /// 호스트는 새 문서를 만들지 말고 이 문서를 내용을 유지하는 "rw" 모드로 다시 열어야 합니다
+    /// ("w" 모드는 내용을 지움). 다시 열 수 없어 새 문서를 주면 처음부터 다시 받습니다.
+    #[serde(default)]
+    pub resume_uri: Option<String>,
+}
+
+/// 호스트가 제공한 파일
+#[derive(Debug)]
+pub struct HostFile {
+    /// 읽기/쓰기 가능한 파일 (호스트의 fd를 소유)
+    pub file: File,
+
+    /// 사용자에게 보여줄 문서 위치 (content:// URI 등)
+    pub uri: String,
+}
+
+type PendingResponse = oneshot::Sender<Result<HostFile>>;
+
+/// 호스트 저장소 요청/응답 중계기
+#[derive(Clone, Default)]
+pub struct HostStorage {
+    enabled: Arc<AtomicBool>,
+    subscribers: Arc<Subscribers<StorageRequest>>,
+    pending: Arc<Mutex<HashMap<String, PendingResponse>>>,
+}
+
+impl HostStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 호스트 저장소 모드 여부
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::SeqCst)
+    }
+
+    /// 요청 구독자를 등록하고 호스트 저장소 모드를 켭니다.
+    pub fn subscribe<F>(&self, subscriber: F)
+    where
+        F: Fn(&StorageRequest) -> bool + Send + 'static,
+    {
+        self.subscribers.subscribe(subscriber);
+        self.enabled.store(true, Ordering::SeqCst);
+    }
+
+    /// 호스트 저장소 모드를 끄고 대기 중인 요청을 모두 실패시킵니다.
+    pub fn disable(&self) {
+        self.enabled.store(false, Ordering::SeqCst);
+
+        self.subscribers.clear();
+        if let Ok(mut pending) = self.pending.lock() {
+            for (_, response) in pending.drain() {
+                let _ = response.send(Err(PebbleError::cancelled("Host storage disabled").into()));
+            }
+        }
+    }
+
+    /// 호스트에게 문서를 요청하고 fd를 받을 때까지 기다립니다.
+    ///
+    /// # Arguments
+    /// * `transfer_id` - 수신 중인 전송 ID
+    /// * `file_name` - 만들 문서의 파일 이름
+    /// * `file_size` - 예상 파일 크기
+    /// * `resume_uri` - 이어받기이면 다시 열 기존 문서의 URI (None이면 새 문서)
+    pub async fn request_file(
+        &self,
+        transfer_id: &str,
+        file_name: &str,
+        file_size: u64,
+        resume_uri: Option<&str>,
+    ) -> Result<HostFile> {
+        let request = StorageRequest {
+            request_id: Uuid::new_v4().to_string(),
+            transfer_id: transfer_id.to_string(),
+            file_name: file_name.to_string(),
+            file_size,
+            resume_uri: resume_uri.map(str::to_string),
+        };
+
+        let (tx, rx) = oneshot::channel();
+        self.pending
+            .lock()
+            .map_err(|e| anyhow::anyhow!("Failed to acquire storage lock: {}", e))?
+            .insert(request.request_id.clone(), tx);
+
+        if !self.subscribers.notify(&request) {
+            self.take_pending(&request.request_id);
+            return Err(PebbleError::internal("No host is listening for storage requests").into());
+        }
+
+        tracing::info!("Requested host storage for {} ({} bytes)", file_name, file_size);
+
+        match tokio::time::timeout(HOST_RESPONSE_TIMEOUT, rx).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(PebbleError::internal("Storage request was dropped").into()),
+            Err(_) => {
+                self.take_pending(&request.request_id);
+                Err(PebbleError::network(format!(
+                    "Host did not respond to storage request within {}s", HOST_RESPONSE_TIMEOUT.as_secs()
+                )).into())
+            }
+        }
+    }
+
+    /// 호스트가 만든 문서의 fd로 요청에 응답합니다. fd의 소유권은 Rust로 넘어옵니다.
+    ///
+    /// # Arguments
+    /// * `request_id` - 응답할 요청 ID
+    /// * `fd` - 읽기/쓰기 모드로 연 파일 디스크립터 (호스트에서는 닫지 않아야 함)
+    /// * `uri` - 사용자에게 보여줄 문서 위치
+    pub fn provide_fd(&self, request_id: &str, fd: i32, uri: &str) -> Result<()> {
+        let response = self
+            .take_pending(request_id)
+            .ok_or_else(|| PebbleError::not_found(format!("Storage request {}", request_id)))?;
+
+        let result = file_from_fd(fd).map(|file| HostFile {
+            file,
+            uri: uri.to_string(),
+        });
+        let _ = response.send(result);
+
+        Ok(())
+    }
+
+    /// 요청을 거절합니다 (사용자가 취소했거나 문서를 만들 수 없는 경우).
+    pub fn reject(&self, request_id: &str, reason: &str) -> Result<()> {
+        let response = self
+            .take_pending(request_id)
+            .ok_or_else(|| PebbleError::not_found(format!("Storage request {}", request_id)))?;
+
+        let _ = response.send(Err(PebbleError::rejected(reason).into()));
+
+        Ok(())
+    }
+
+    fn take_pending(&self, request_id: &str) -> Option<PendingResponse> {
+        self.pending.lock().ok()?.remove(request_id)
+    }
+}
+
+/// 호스트가 넘긴 fd를 파일로 감쌉니다.
+///
+/// # Security
+/// - 닫혔거나 잘못된 fd를 소유하지 않도록 fcntl로 먼저 유효성을 확인
+#[cfg(unix)]
+pub fn file_from_fd(fd: i32) -> Result<File> {
+    use std::os::fd::FromRawFd;
+
+    // SAFETY: fcntl(F_GETFD)는 fd 상태를 읽기만 함
+    if fd < 0 || unsafe { libc::fcntl(fd, libc::F_GETFD) } == -1 {
+        return Err(PebbleError::invalid_argument(format!("Invalid file descriptor: {}", fd)).into());
+    }
+
+    // SAFETY: 유효한 fd이며, 호출자(호스트)가 소유권을 넘겼으므로 이후 이 File만 닫음
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+#[cfg(not(unix))]
+pub fn file_from_fd(fd: i32) -> Result<File> {
+    Err(PebbleError::invalid_argument(format!(
+        "File descriptors are not supported on this platform: {}", fd
+    )).into())
+}
+
+/// 전역 호스트 저장소
+static HOST_STORAGE: once_cell::sync::Lazy<HostStorage> = once_cell::sync::Lazy::new(HostStorage::new);
+
+/// 전역 호스트 저장소를 반환합니다.
+pub fn global() -> &'static HostStorage {
+    &HOST_STORAGE
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::io::{Read, Seek, SeekFrom, Write};
+    use std::os::fd::IntoRawFd;
+
+    #[tokio::test]
+    async fn test_request_answered_with_fd() {
+        let storage = HostStorage::new();
+        let dir = tempfile::TempDir::new().unwrap();
+        let doc_path = dir.path().join("doc.bin");
+
+        let host = storage.clone();
+        let path = doc_path.clone();
+        storage.subscribe(move |request| {
+            assert_eq!(request.file_name, "photo.jpg");
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .read(true)
+                .write(true)
+                .open(&path)
+                .unwrap();
+            host.provide_fd(&request.request_id, file.into_raw_fd(), "content://docs/photo.jpg").unwrap();
+            true
+        });
+
+        let mut host_file = storage.request_file("t1", "photo.jpg", 5, None).await.unwrap();
+        assert_eq!(host_file.uri, "content://docs/photo.jpg");
+
+        host_file.file.write_all(b"hello").unwrap();
+        host_file.file.seek(SeekFrom::Start(0)).unwrap();
+        let mut content = String::new();
+        host_file.file.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "hello");
+    }
+
+    #[tokio::test]
+    async fn test_rejected_and_unanswered_requests() {
+        let storage = HostStorage::new();
+        assert!(storage.request_file("t2", "a.txt", 1, None).await.is_err());
+
+        let host = storage.clone();
+        storage.subscribe(move |request| {
+            host.reject(&request.request_id, "User cancelled").unwrap();
+            true
+        });
+        let err = storage.request_file("t2", "a.txt", 1, None).await.unwrap_err();
+        assert!(matches!(PebbleError::from(err), PebbleError::Rejected { .. }));
+
+        assert!(file_from_fd(-1).is_err());
+    }
+}
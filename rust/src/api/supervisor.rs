@@ -0,0 +1,158 @@
+//! 백그라운드 태스크 감독 (Task Supervisor)
+//!
+//! 발견, 파일 감시, 전송 서버의 백그라운드 태스크를 서비스 단위로 묶어 관리합니다.
+//! 중지 요청 시 취소 토큰으로 모든 태스크에 종료를 알리고, 실제로 끝날 때까지
+//! 기다린 뒤 실행 중에 발생한 에러를 모아 반환합니다.
+//!
+//! # Process Flow
+//! 1. 서비스 시작 시 `TaskSupervisor` 생성, `spawn`으로 태스크 등록
+//! 2. 각 태스크는 전달받은 `CancellationToken`이 취소되면 종료
+//! 3. `shutdown`에서 토큰 취소 → 모든 태스크 종료 대기 (`SHUTDOWN_TIMEOUT` 초과 시 강제 중단)
+//! 4. 에러로 끝났거나 패닉한 태스크를 모아 하나의 에러로 보고
+
+use anyhow::Result;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+
+use super::service::{self, ServiceKind};
+
+/// 중지 요청 후 태스크가 스스로 끝나기를 기다리는 최대 시간
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 태스크 이름과 실행 결과
+type TaskOutcome = (&'static str, Result<()>);
+
+/// 서비스 하나의 백그라운드 태스크 묶음
+pub struct TaskSupervisor {
+    kind: ServiceKind,
+    token: CancellationToken,
+    tasks: Mutex<JoinSet<TaskOutcome>>,
+    /// 아직 끝나지 않은 태스크 수
+    live: Arc<AtomicUsize>,
+}
+
+impl TaskSupervisor {
+    pub fn new(kind: ServiceKind) -> Self {
+        Self {
+            kind,
+            token: CancellationToken::new(),
+            tasks: Mutex::new(JoinSet::new()),
+            live: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// 태스크를 등록합니다.
+    ///
+    /// 태스크는 인자로 받은 토큰이 취소되면 정리 작업을 마치고 반환해야 합니다.
+    /// 에러로 끝나면 바로 로그와 서비스 상태(`get_service_status`)에 기록됩니다.
+    ///
+    /// # Arguments
+    /// * `name` - 로그와 에러 보고에 사용할 태스크 이름
+    /// * `task` - 취소 토큰을 받아 태스크 Future를 만드는 함수
+    pub fn spawn<F, Fut>(&self, name: &'static str, task: F)
+    where
+        F: FnOnce(CancellationToken) -> Fut,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let kind = self.kind;
+        let live = Arc::clone(&self.live);
+        let future = task(self.token.child_token());
+
+        live.fetch_add(1, Ordering::SeqCst);
+        let wrapped = async move {
+            let result = future.await;
+            live.fetch_sub(1, Ordering::SeqCst);
+
+            if let Err(e) = &result {
+                tracing::error!("{} task failed: {:#}", name, e);
+                service::record_error(kind, format!("{} task failed: {:#}", name, e));
+            }
+            (name, result)
+        };
+
+        match self.tasks.lock() {
+            Ok(mut tasks) => {
+                tasks.spawn(wrapped);
+            }
+            Err(e) => tracing::error!("Failed to acquire supervisor lock: {}", e),
+        }
+    }
+
+    /// 중지 요청 전이고 끝나지 않은 태스크가 있는지 확인합니다.
+    pub fn is_running(&self) -> bool {
+        !self.token.is_cancelled() && self.live.load(Ordering::SeqCst) > 0
+    }
+
+    /// 모든 태스크에 종료를 알리고 끝날 때까지 기다립니다.
+    ///
+    /// # Returns
+    /// * `Result<()>` - 에러로 끝났거나, 패닉했거나, 시간 내에 끝나지 않은 태스크가 있으면 에러
+    pub async fn shutdown(self) -> Result<()> {
+        self.token.cancel();
+
+        let mut tasks = self
+            .tasks
+            .into_inner()
+            .map_err(|e| anyhow::anyhow!("Failed to acquire supervisor lock: {}", e))?;
+        let mut failures = Vec::new();
+
+        let drained = tokio::time::timeout(SHUTDOWN_TIMEOUT, async {
+            while let Some(joined) = tasks.join_next().await {
+                match joined {
+                    Ok((_, Ok(()))) => {}
+                    Ok((name, Err(e))) => failures.push(format!("{}: {:#}", name, e)),
+                    Err(e) if e.is_panic() => failures.push(format!("task panicked: {}", e)),
+                    Err(_) => {}
+                }
+            }
+        })
+        .await;
+
+        if drained.is_err() {
+            failures.push(format!("{} task(s) did not stop within {}s", tasks.len(), SHUTDOWN_TIMEOUT.as_secs()));
+            tasks.shutdown().await;
+        }
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            anyhow::bail!("{:?} tasks reported errors: {}", self.kind, failures.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_shutdown_waits_and_reports_errors() {
+        let supervisor = TaskSupervisor::new(ServiceKind::Watcher);
+        let finished = Arc::new(AtomicUsize::new(0));
+
+        let counter = Arc::clone(&finished);
+        supervisor.spawn("loop", |token| async move {
+            token.cancelled().await;
+            // 종료 알림 후 정리 작업
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            counter.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        });
+        supervisor.spawn("broken", |_| async { anyhow::bail!("socket closed") });
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(supervisor.is_running());
+
+        let error = supervisor.shutdown().await.unwrap_err();
+        assert_eq!(finished.load(Ordering::SeqCst), 1);
+        assert!(format!("{:#}", error).contains("broken: socket closed"));
+
+        let idle = TaskSupervisor::new(ServiceKind::Discovery);
+        assert!(!idle.is_running());
+        idle.shutdown().await.unwrap();
+    }
+}
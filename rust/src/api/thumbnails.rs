@@ -0,0 +1,219 @@
+//! 받은 이미지 미리보기 (Received Image Thumbnails)
+//!
+//! 이미지 파일을 받으면 작은 미리보기를 캐시 디렉토리(`PebbleConfig::thumbnail_dir`)에 만들고
+//! 전송 기록(`TransferRecord::thumbnail_path`)에 경로를 남겨, UI가 원본 전체를 읽지 않고
+//! 미리보기를 보여줄 수 있게 합니다. 이미지 디코딩은 `thumbnails` 기능(image 크레이트)을 켜고
+//! 빌드한 경우에만 동작하며, 기능이 꺼져 있으면 미리보기를 만들지 않습니다.
+//!
+//! # Process Flow
+//! 1. 수신이 완료되면 `spawn_for`가 백그라운드에서 미리보기 생성 (전송 완료를 늦추지 않음)
+//! 2. 캐시 디렉토리가 설정되어 있고, 확장자가 이미지(`IMAGE_EXTENSIONS`)이며 `MAX_SOURCE_SIZE` 이하인 로컬 파일만 대상
+//! 3. 긴 변이 `thumbnail_size` 이하가 되도록 비율을 유지해 줄인 뒤 PNG로 저장
+//! 4. 전송 기록에 경로를 남기고, 기록이 정리되면 `remove_unreferenced`가 미리보기도 삭제
+
+use anyhow::{Context, Result};
+use rusqlite::Connection;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::config;
+use super::history;
+use super::paths;
+
+/// 이 빌드에서 미리보기를 만들 수 있는지 여부 (`thumbnails` 기능)
+pub const ENABLED: bool = cfg!(feature = "thumbnails");
+
+/// 미리보기를 만드는 파일 확장자
+pub const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "webp"];
+
+/// 미리보기를 만드는 원본의 최대 크기 (디코딩 메모리 제한)
+pub const MAX_SOURCE_SIZE: u64 = 64 * 1024 * 1024;
+
+/// 미리보기 긴 변의 기본값과 허용 범위 (px)
+pub const DEFAULT_SIZE: u32 = 256;
+pub const MIN_SIZE: u32 = 16;
+pub const MAX_SIZE: u32 = 1024;
+
+/// 미리보기 파일 확장자
+const THUMBNAIL_EXTENSION: &str = "png";
+
+/// 미리보기 파일 이름의 해시 길이 (hex)
+const NAME_LEN: usize = 32;
+
+/// 확장자로 미리보기 대상 이미지인지 확인합니다.
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.iter().any(|image| ext.eq_ignore_ascii_case(image)))
+}
+
+/// 전송의 미리보기 파일 경로
+///
+/// # Security
+/// - 전송 ID는 상대 기기가 정하므로 그대로 파일 이름에 쓰지 않고 해시로 바꿉니다 (경로 조작 방지).
+fn thumbnail_path(cache_dir: &Path, transfer_id: &str) -> PathBuf {
+    let hash = blake3::hash(transfer_id.as_bytes()).to_hex();
+    cache_dir.join(format!("{}.{}", &hash[..NAME_LEN], THUMBNAIL_EXTENSION))
+}
+
+/// 이 모듈이 만든 미리보기 파일인지 확인합니다 (캐시 디렉토리의 다른 파일은 건드리지 않음).
+fn is_thumbnail_file(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == THUMBNAIL_EXTENSION)
+        && path.file_stem().and_then(|stem| stem.to_str()).is_some_and(|stem| {
+            stem.len() == NAME_LEN && stem.bytes().all(|b| b.is_ascii_hexdigit())
+        })
+}
+
+/// 이미지를 줄여 PNG로 저장합니다 (임시 파일에 쓴 뒤 rename).
+#[cfg(feature = "thumbnails")]
+fn render(source: &Path, dest: &Path, size: u32) -> Result<()> {
+    let image = image::ImageReader::open(paths::long_path(source))?
+        .with_guessed_format()?
+        .decode()?;
+
+    let tmp_path = dest.with_extension("tmp");
+    image.thumbnail(size, size).save_with_format(&tmp_path, image::ImageFormat::Png)?;
+    fs::rename(&tmp_path, dest)?;
+    Ok(())
+}
+
+#[cfg(not(feature = "thumbnails"))]
+fn render(_source: &Path, _dest: &Path, _size: u32) -> Result<()> {
+    anyhow::bail!("This build does not include thumbnail support")
+}
+
+/// 받은 파일의 미리보기를 지정한 디렉토리에 만듭니다.
+///
+/// # Returns
+/// * 만든 미리보기 경로 (이미지가 아니거나, 로컬 파일이 아니거나, 너무 크거나, 기능이 꺼져 있으면 None)
+fn generate_into(transfer_id: &str, file_path: &str, cache_dir: &Path, size: u32) -> Result<Option<String>> {
+    let source = Path::new(file_path);
+    if !ENABLED || !is_image(source) {
+        return Ok(None);
+    }
+    // 호스트 저장소의 문서 URI는 로컬 파일이 아님
+    let Ok(metadata) = fs::metadata(paths::long_path(source)) else {
+        return Ok(None);
+    };
+    if !metadata.is_file() || metadata.len() > MAX_SOURCE_SIZE {
+        return Ok(None);
+    }
+
+    fs::create_dir_all(cache_dir)
+        .with_context(|| format!("Failed to create thumbnail directory: {}", cache_dir.display()))?;
+    let dest = thumbnail_path(cache_dir, transfer_id);
+    render(source, &dest, size).with_context(|| format!("Failed to create thumbnail for {}", file_path))?;
+
+    let dest = dest.to_string_lossy().to_string();
+    history::set_thumbnail(transfer_id, &dest)?;
+    Ok(Some(dest))
+}
+
+/// 받은 파일의 미리보기를 설정의 캐시 디렉토리에 만들고 전송 기록에 남깁니다.
+///
+/// # Returns
+/// * 만든 미리보기 경로 (캐시 디렉토리가 설정되지 않았거나 대상이 아니면 None)
+pub fn generate(transfer_id: &str, file_path: &str) -> Result<Option<String>> {
+    let config = config::current();
+    match config.thumbnail_dir {
+        Some(cache_dir) => generate_into(transfer_id, file_path, Path::new(&cache_dir), config.thumbnail_size),
+        None => Ok(None),
+    }
+}
+
+/// 수신이 완료된 파일의 미리보기를 백그라운드에서 만듭니다 (실패해도 전송 결과에는 영향 없음).
+pub fn spawn_for(transfer_id: &str, file_path: &str) {
+    if !ENABLED || config::current().thumbnail_dir.is_none() || !is_image(Path::new(file_path)) {
+        return;
+    }
+
+    let transfer_id = transfer_id.to_string();
+    let file_path = file_path.to_string();
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = generate(&transfer_id, &file_path) {
+            tracing::warn!("{:#}", e);
+        }
+    });
+}
+
+fn remove_unreferenced_in(conn: &Connection, cache_dir: &Path) -> Result<u64> {
+    let entries = match fs::read_dir(cache_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+
+    let referenced = conn
+        .prepare("SELECT thumbnail_path FROM transfer_history WHERE thumbnail_path IS NOT NULL")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<HashSet<_>>>()?;
+
+    let mut removed = 0;
+    for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+        if is_thumbnail_file(&path) && !referenced.contains(path.to_string_lossy().as_ref()) && fs::remove_file(&path).is_ok() {
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// 전송 기록에서 사라진 미리보기를 지웁니다 (기록을 정리하거나 비운 뒤).
+///
+/// # Returns
+/// * 지운 미리보기 수
+pub fn remove_unreferenced(conn: &Connection) -> Result<u64> {
+    match config::current().thumbnail_dir {
+        Some(cache_dir) => remove_unreferenced_in(conn, Path::new(&cache_dir)),
+        None => Ok(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::db;
+
+    #[test]
+    fn test_thumbnail_names_and_cleanup() {
+        crate::loopback::use_temp_environment();
+        let dir = tempfile::TempDir::new().unwrap();
+
+        assert!(is_image(Path::new("photo.JPG")) && is_image(Path::new("a/b.webp")));
+        assert!(!is_image(Path::new("notes.txt")) && !is_image(Path::new("png")));
+
+        // 상대가 보낸 전송 ID로 캐시 디렉토리 밖을 가리킬 수 없음
+        let path = thumbnail_path(dir.path(), "../../etc/passwd");
+        assert_eq!(path.parent(), Some(dir.path()));
+        assert!(is_thumbnail_file(&path));
+
+        // 이미지가 아니면 만들지 않음
+        let text = dir.path().join("notes.txt");
+        fs::write(&text, b"not an image").unwrap();
+        assert_eq!(generate_into("thumb-text", &text.to_string_lossy(), dir.path(), DEFAULT_SIZE).unwrap(), None);
+
+        // 기록에 없는 미리보기만 삭제하고 사용자 파일은 남김
+        let orphan = thumbnail_path(dir.path(), "thumb-orphan");
+        let own = dir.path().join("holiday.png");
+        fs::write(&orphan, b"png").unwrap();
+        fs::write(&own, b"png").unwrap();
+        let conn = db::open_connection().unwrap();
+        assert_eq!(remove_unreferenced_in(&conn, dir.path()).unwrap(), 1);
+        assert!(!orphan.exists() && own.exists());
+    }
+
+    #[cfg(feature = "thumbnails")]
+    #[test]
+    fn test_generate_thumbnail() {
+        crate::loopback::use_temp_environment();
+        let dir = tempfile::TempDir::new().unwrap();
+        let source = dir.path().join("wide.png");
+        image::RgbImage::from_pixel(400, 200, image::Rgb([200, 100, 50])).save(&source).unwrap();
+
+        let cache = dir.path().join("thumbs");
+        let created = generate_into("thumb-wide", &source.to_string_lossy(), &cache, 100).unwrap().unwrap();
+        let thumbnail = image::open(&created).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (100, 50));
+    }
+}
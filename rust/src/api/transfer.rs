@@ -1,28 +1,266 @@
 use anyhow::{Context, Result};
 use bytes::{BufMut, Bytes, BytesMut};
-use rusqlite::{params, Connection};
+use rusqlite::params;
 use serde::{Deserialize, Serialize};
-use std::fs::{File, OpenOptions};
-use std::io::{Read, Seek, SeekFrom, Write};
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
 use std::net::SocketAddr;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::mpsc;
+use tokio::task::JoinSet;
 use tokio_rustls::{TlsAcceptor, TlsConnector};
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
+use walkdir::WalkDir;
 
+use super::accept::{self, AcceptDecision, ApprovalRequest};
+use super::audit::{ConnectionAudit, ConnectionOutcome};
+use super::backend::{self, Destination, DestinationRequest, ReceiveSink};
+use super::bandwidth;
 use super::certificate::TlsCertificate;
-use super::integrity;
-
-/// 청크 크기 (1MB)
+use super::config::{self, MAX_CHUNK_SIZE, MIN_CHUNK_SIZE};
+use super::congestion::AdaptiveWindow;
+use super::db;
+use super::discovery;
+use super::error::PebbleError;
+use super::events::{self, PebbleEvent};
+#[cfg(any(test, feature = "fault-injection"))]
+use super::fault::{self, FaultPlan};
+use super::filename::{self, DestinationClaim};
+use super::integrity::{self, ChangeDetection, HashAlgo};
+use super::listeners::ListenerConfig;
+use super::locked;
+use super::manifest::{self, JournalCursor, ManifestEntry, ManifestHead};
+use super::messages::{self, TextMessage};
+use super::clipboard::{self, ClipboardUpdate};
+use super::paths;
+use super::priority::{self, TransferPriority};
+use super::peers;
+use super::pool::{self, PooledStream};
+use super::history;
+use super::quota;
+use super::receipts::{self, TransferReceipt};
+use super::metrics;
+use super::registry::{self, TransferDirection, TransferHandle};
+use super::revocation::{self, Revocation, RevocationOutcome};
+use super::scanhook;
+use super::schedule::{self, ScheduleWindow};
+use super::dedup::{self, ChunkStore, DedupPlan};
+use super::speedtest;
+use super::xattrs::{self, ExtendedAttribute};
+use super::service::{self, ServiceKind};
+use super::thumbnails;
+use super::tuning;
+use super::verifier::{ChunkMismatch, ChunkVerifier};
+use super::util::unix_now;
+
+/// 청크 크기 (1MB, 기본값)
 pub const CHUNK_SIZE: usize = 1024 * 1024;
 
-/// 전송 포트
+/// 전송 포트 (기본값)
 pub const TRANSFER_PORT: u16 = 37846;
 
-/// 최대 전송 속도 (bytes/sec) - 기본값: 무제한 (0)
-pub const MAX_TRANSFER_RATE: u64 = 0;
+/// 프레임 길이 헤더 크기 (u32, big-endian)
+pub const FRAME_HEADER_SIZE: usize = 4;
+
+/// 허용하는 최대 메시지 크기
+///
+/// ChunkData의 바이트 배열은 JSON 숫자 배열로 직렬화되어 바이트당 최대 4바이트를
+/// 차지하므로, 최대 청크 크기의 4배에 메타데이터 여유분을 더합니다.
+pub const MAX_MESSAGE_SIZE: usize = MAX_CHUNK_SIZE as usize * 4 + 64 * 1024;
+
+/// 요청에 내용을 담아 한 번에 보내는 파일의 최대 크기 (청크 프레이밍과 ACK 왕복 생략)
+pub const INLINE_FILE_MAX: u64 = 4 * 1024;
+
+/// 수신측이 다음 요청을 기다리는 최대 시간 (이후 유휴 연결을 닫음)
+///
+/// 송신측 연결 풀의 유휴 시간(`pool::POOL_IDLE_TIMEOUT`)보다 길어야 합니다.
+pub const CONNECTION_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// 전송 전 중복 검사 응답 대기 시간
+pub const HASH_QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 완료 메시지를 보낸 뒤 수신측의 최종 결과를 기다리는 최대 시간 (검사 명령의 최대 제한 시간 + 여유)
+const VERDICT_TIMEOUT: Duration = Duration::from_secs(scanhook::MAX_TIMEOUT_SECS + 60);
+
+/// 묶음 ACK 한 번에 확인하는 청크 수 (수신측 DB 기록도 이 주기로 수행)
+pub const ACK_INTERVAL: u64 = 8;
+
+/// 해시가 맞지 않은 청크 하나를 다시 요청하는 최대 횟수
+pub const MAX_CHUNK_RETRIES: u32 = 3;
+
+/// 전송 하나에서 청크를 다시 요청하는 최대 횟수 (송신측은 이보다 많은 요청을 거부)
+pub const MAX_TRANSFER_RETRIES: u32 = 16;
+
+/// 우선순위에 밀려 멈춘 송신이 취소 요청을 확인하는 간격
+const PREEMPTION_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 여러 주소로 연결할 때 다음 주소를 시도하기 전 기다리는 시간 (RFC 8305 권장값)
+pub const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+fn default_chunk_size() -> u64 {
+    CHUNK_SIZE as u64
+}
+
+fn default_ack_interval() -> u64 {
+    1
+}
+
+/// 묶음 ACK로 확인하는 청크 인덱스 범위 (`start` 포함, `end` 제외)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AckRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+/// 전송 거부 사유 코드
+///
+/// 송신측이 거부 이유를 문자열 파싱 없이 구분할 수 있도록 `TransferReject`에 함께 보냅니다.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RejectCode {
+    /// 송신 기기의 저장 한도 초과
+    QuotaExceeded,
+    /// 수락 정책에 의해 거부됨 (실행 파일 형식 등)
+    PolicyDenied,
+    /// 사용자가 승인하지 않음 (거절 또는 응답 없음)
+    Declined,
+    /// 대소문자만 다른 이름의 파일이 있어 받지 않음 (`CaseCollisionPolicy::Skip`)
+    CaseCollision,
+    /// 이 버전이 알지 못하는 코드
+    #[serde(other)]
+    Unknown,
+}
+
+/// 거부 메시지를 코드에 맞는 에러로 변환합니다.
+pub(crate) fn reject_error(reason: String, code: Option<RejectCode>) -> PebbleError {
+    match code {
+        Some(RejectCode::QuotaExceeded) => PebbleError::quota_exceeded(reason),
+        _ => PebbleError::rejected(reason),
+    }
+}
+
+/// 송신 사용량을 집계할 상대 기기 ID
+///
+/// `peer`가 주소면 그 주소에서 마지막으로 본 기기 ID, 알 수 없으면 IP를 사용합니다
+/// (수신측의 구버전 기기 집계 방식과 같음).
+pub(crate) fn sent_peer_id(peer: &str) -> String {
+    let Ok(addr) = peer.parse::<SocketAddr>() else {
+        return peer.to_string();
+    };
+    let ip = addr.ip().to_string();
+    match peers::device_id_at(&ip) {
+        Ok(Some(device_id)) => device_id,
+        Ok(None) => ip,
+        Err(e) => {
+            tracing::warn!("Failed to look up device at {}: {:#}", ip, e);
+            ip
+        }
+    }
+}
+
+/// 여러 주소 중 가장 먼저 연결되는 주소로 TCP 연결합니다 (Happy Eyeballs).
+///
+/// 앞 주소부터 `CONNECTION_ATTEMPT_DELAY` 간격으로 연결을 시작하고, 시도 하나가
+/// 실패하면 다음 주소를 바로 시작합니다. 먼저 성공한 연결을 쓰고 나머지 시도는 취소합니다.
+///
+/// # Arguments
+/// * `addrs` - 시도할 주소 (우선순위 순)
+///
+/// # Returns
+/// * `Result<(SocketAddr, TcpStream)>` - 연결된 주소와 스트림 (모두 실패하면 마지막 에러)
+pub async fn connect_first(addrs: &[SocketAddr]) -> Result<(SocketAddr, TcpStream)> {
+    let Some(&first) = addrs.first() else {
+        return Err(PebbleError::invalid_argument("No address to connect to").into());
+    };
+
+    let attempt = |addr: SocketAddr| async move {
+        let result = tuning::current().connect(addr).await
+            .with_context(|| format!("Failed to connect to {}", addr));
+        (addr, result)
+    };
+
+    let mut attempts = JoinSet::new();
+    attempts.spawn(attempt(first));
+    let mut next = 1;
+    let mut last_error = None;
+
+    loop {
+        tokio::select! {
+            finished = attempts.join_next() => match finished {
+                Some(Ok((addr, Ok(stream)))) => {
+                    if addrs.len() > 1 {
+                        tracing::debug!("Connected to {} out of {} address(es)", addr, addrs.len());
+                    }
+                    return Ok((addr, stream));
+                }
+                Some(Ok((_, Err(e)))) => last_error = Some(e),
+                Some(Err(e)) => last_error = Some(anyhow::Error::new(e).context("Connection attempt panicked")),
+                None => {}
+            },
+            _ = tokio::time::sleep(CONNECTION_ATTEMPT_DELAY), if next < addrs.len() => {
+                attempts.spawn(attempt(addrs[next]));
+                next += 1;
+                continue;
+            }
+        }
+
+        // 진행 중인 시도가 실패했으면 기다리지 않고 다음 주소를 시작
+        if next < addrs.len() {
+            attempts.spawn(attempt(addrs[next]));
+            next += 1;
+        } else if attempts.is_empty() {
+            return Err(last_error.unwrap_or_else(|| anyhow::anyhow!("No address to connect to")));
+        }
+    }
+}
+
+/// 레지스트리가 전송을 끊으면(`TransferRegistry::interrupt`) 진행 중인 작업을 네트워크 에러로 끝냅니다.
+///
+/// 청크 사이의 `checkpoint`와 달리 응답 없는 소켓에서 기다리는 중에도 끊깁니다.
+async fn interruptible<T>(interrupt: CancellationToken, work: impl std::future::Future<Output = Result<T>>) -> Result<T> {
+    tokio::select! {
+        result = work => result,
+        _ = interrupt.cancelled() => Err(PebbleError::network("Transfer interrupted: connection stopped responding").into()),
+    }
+}
+
+/// 같은 기기가 같은 파일을 같은 기기로 다시 보내면 같은 값이 되는 전송 ID를 만듭니다.
+///
+/// 수신측은 전송 ID로 이어받기 상태(`transfer_state`)를 찾으므로, 끊긴 전송을 다시 보내면
+/// 받은 청크 뒤부터 이어받습니다. 청크 크기가 바뀌면 받은 청크의 위치가 달라지므로 다른 ID가 됩니다.
+/// 다른 기기가 같은 내용을 보내면 다른 ID가 됩니다 (수신측도 이어받기 상태를 송신 기기별로 구분).
+///
+/// # Arguments
+/// * `file_hash` - 파일 전체의 blake3 해시
+/// * `sender` - 이 기기 ID (`service::device_id`, 서비스를 시작하지 않았으면 빈 문자열)
+/// * `target` - 수신 기기 (`TransferClient::target_id`)
+/// * `relative_path` - 폴더 전송의 상대 경로 (같은 내용의 파일이 폴더 안 여러 곳에 있어도 구분)
+pub(crate) fn stable_transfer_id(
+    file_hash: &str,
+    file_size: u64,
+    chunk_size: u64,
+    sender: &str,
+    target: &str,
+    relative_path: Option<&str>,
+) -> String {
+    let mut hasher = blake3::Hasher::new();
+    for part in [file_hash, &file_size.to_string(), &chunk_size.to_string(), sender, target, relative_path.unwrap_or("")] {
+        hasher.update(part.as_bytes());
+        hasher.update(&[0]);
+    }
+    Uuid::from_bytes(hasher.finalize().as_bytes()[..16].try_into().expect("blake3 hash is 32 bytes")).to_string()
+}
+
+/// 0으로만 된 청크인지 확인합니다 (구멍으로 보낼 수 있는 청크).
+fn is_zeroes(data: &[u8]) -> bool {
+    data.iter().all(|&byte| byte == 0)
+}
 
 /// 전송 프로토콜 메시지 타입
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,18 +273,73 @@ pub enum TransferMessage {
         file_size: u64,
         file_hash: String,
         total_chunks: u64,
+        /// 송신측 청크 크기 (구버전 호환을 위해 누락 시 기본값)
+        #[serde(default = "default_chunk_size")]
+        chunk_size: u64,
+        /// 송신측이 지원하는 청크 해시 알고리즘 (선호 순서, 구버전은 누락)
+        #[serde(default)]
+        chunk_hash_algos: Vec<HashAlgo>,
+        /// 송신 기기 ID (사용량 집계용, 구버전 또는 start_pebble 이전이면 None)
+        #[serde(default)]
+        sender_device_id: Option<String>,
+        /// 송신측이 묶음 ACK(`ChunkAcks`)를 처리할 수 있는지 여부 (구버전은 누락)
+        #[serde(default)]
+        ack_ranges: bool,
+        /// 송신측이 청크 해시 목록(`ChunkManifest`)을 보낼 수 있는지 여부 (구버전은 누락)
+        #[serde(default)]
+        chunk_manifest: bool,
+        /// `INLINE_FILE_MAX` 이하인 파일의 내용 (있으면 수신측은 수락 대신 바로 저장하고
+        /// `TransferComplete`로 응답, 구버전 수신측은 무시하고 수락하므로 청크로 다시 보냄)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        inline_data: Option<Vec<u8>>,
+        /// 파일의 확장 속성 (송신측 `preserve_xattrs`가 켜져 있을 때만, 구버전은 누락)
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        xattrs: Vec<ExtendedAttribute>,
+        /// 송신측이 완료 메시지 뒤에 수신측의 최종 결과를 기다릴 수 있는지 여부 (구버전은 누락)
+        #[serde(default)]
+        verdict: bool,
+        /// 송신측이 CRC32C 청크 체크섬(`HashAlgo::Crc32c`)을 쓸 수 있는지 여부 (구버전은 누락)
+        #[serde(default)]
+        crc32c: bool,
+        /// 송신측이 해시가 맞지 않은 청크의 재전송 요청(`ChunkNack`)을 처리할 수 있는지 여부 (구버전은 누락)
+        #[serde(default)]
+        retransmit: bool,
+        /// 폴더 전송에서 보낸 폴더 기준의 상대 경로 (`/` 구분, 보낸 폴더 이름 포함,
+        /// 수신측은 받을 폴더 아래에 같은 구조로 저장하며 구버전은 누락되어 파일 이름만 사용)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        relative_path: Option<String>,
     },
 
     /// 전송 수락
     TransferAccept {
         transfer_id: String,
         resume_from_chunk: u64,
+        /// 수신측이 선택한 청크 해시 알고리즘 (구버전은 누락되어 Sha256)
+        #[serde(default)]
+        chunk_hash_algo: HashAlgo,
+        /// 수신측이 ACK를 보내는 청크 간격 (1이면 청크마다 `ChunkAck`, 구버전은 누락되어 1)
+        #[serde(default = "default_ack_interval")]
+        ack_interval: u64,
+        /// 수신측이 중복 제거를 위해 청크 해시 목록을 요청하는지 여부 (구버전은 누락)
+        #[serde(default)]
+        want_manifest: bool,
+        /// 수신측이 0으로만 된 청크를 구멍 표시(`ChunkHole`)로 받을 수 있는지 여부 (구버전은 누락)
+        #[serde(default)]
+        sparse: bool,
+        /// 수신측이 완료 메시지를 받은 뒤 검증과 검사(`scan_hook`)를 마치고 최종 결과
+        /// (`TransferComplete`, `TransferReject` 또는 `Error`)를 보내는지 여부
+        /// (요청에서 `verdict`를 밝힌 송신측에만, 구버전은 누락)
+        #[serde(default)]
+        verdict: bool,
     },
 
     /// 전송 거부
     TransferReject {
         transfer_id: String,
         reason: String,
+        /// 거부 사유 코드 (구버전은 누락)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        code: Option<RejectCode>,
     },
 
     /// 청크 데이터
@@ -57,15 +350,50 @@ pub enum TransferMessage {
         data: Vec<u8>,
     },
 
-    /// 청크 확인
+    /// 0으로만 된 청크 (데이터 없이 수신측 파일에 구멍으로 남김, 수락 응답에서 허용한 경우)
+    ChunkHole {
+        transfer_id: String,
+        chunk_index: u64,
+    },
+
+    /// 청크별 저장소 해시 목록의 한 페이지 (수락 응답에서 요청한 경우, 청크 전송 전)
+    ChunkManifest {
+        transfer_id: String,
+        /// 이 페이지의 첫 청크 인덱스
+        first_chunk: u64,
+        chunk_hashes: Vec<String>,
+    },
+
+    /// 네트워크로 보내야 하는 청크 범위 (나머지는 수신측 저장소에서 채움)
+    ChunkNeeds {
+        transfer_id: String,
+        ranges: Vec<AckRange>,
+    },
+
+    /// 청크 확인 (이 청크까지 모두 받음)
     ChunkAck {
         transfer_id: String,
         chunk_index: u64,
     },
 
+    /// 묶음 청크 확인 (`ack_interval` 청크마다, 마지막 청크에서 한 번 더)
+    ChunkAcks {
+        transfer_id: String,
+        ranges: Vec<AckRange>,
+    },
+
+    /// 해시가 맞지 않은 청크의 재전송 요청 (송신측은 이 청크부터 다시 보냄, 요청에서 `retransmit`을 밝힌 경우)
+    ChunkNack {
+        transfer_id: String,
+        chunk_index: u64,
+    },
+
     /// 전송 완료
     TransferComplete {
         transfer_id: String,
+        /// 수신측이 서명한 수령증 (수신측의 최종 결과에만, 서명할 수 없거나 구버전이면 누락)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        receipt: Option<TransferReceipt>,
     },
 
     /// 에러
@@ -73,6 +401,122 @@ pub enum TransferMessage {
         transfer_id: String,
         message: String,
     },
+
+    /// 텍스트 메시지 (URL, 클립보드 스니펫 등)
+    SendText {
+        message_id: String,
+        /// 송신 기기 ID (start_pebble로 시작되지 않았으면 None)
+        sender_device_id: Option<String>,
+        text: String,
+    },
+
+    /// 텍스트 메시지 수신 확인
+    TextAck {
+        message_id: String,
+    },
+
+    /// 속도 측정 요청 (이후 ChunkData로 생성된 데이터를 전송)
+    SpeedTestRequest {
+        test_id: String,
+        total_bytes: u64,
+        chunk_size: u64,
+    },
+
+    /// 속도 측정 결과 (수신측 측정값)
+    SpeedTestResult {
+        test_id: String,
+        bytes_received: u64,
+        receive_ms: f64,
+    },
+
+    /// 연결 상태 확인 (연결 풀에서 재사용 전에 전송)
+    Ping {
+        nonce: u64,
+    },
+
+    /// 연결 상태 확인 응답
+    Pong {
+        nonce: u64,
+    },
+
+    /// 비공개 비콘을 보낸 기기에 ID와 이름을 요청
+    IdentityRequest {
+        nonce: String,
+        /// 같은 비밀 키를 가졌다는 증명 (`discovery::identity_proof`)
+        proof: String,
+    },
+
+    /// 신원 요청 응답
+    Identity {
+        device_id: String,
+        device_name: String,
+    },
+
+    /// 같은 내용의 파일이 수신측에 이미 있는지 확인 (전송 전 중복 검사)
+    HashQuery {
+        query_id: String,
+        /// 파일 전체의 blake3 해시
+        file_hash: String,
+        file_size: u64,
+    },
+
+    /// 중복 검사 응답
+    HashQueryResult {
+        query_id: String,
+        present: bool,
+    },
+
+    /// 폴더 매니페스트 요청 (동기화 크기 예상 등)
+    ManifestRequest {
+        request_id: String,
+        /// 응답측 기기의 루트 경로
+        root: String,
+        /// 요청 기기 ID (공유 권한 확인용)
+        sender_device_id: Option<String>,
+        /// 이전 동기화 때 받은 기록 위치 (있으면 그 뒤의 변경분만 요청)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        since: Option<JournalCursor>,
+    },
+
+    /// 폴더 매니페스트 응답의 한 페이지 (경로 순)
+    ManifestPage {
+        request_id: String,
+        /// 0부터 시작하는 페이지 번호
+        page: u32,
+        /// 마지막 페이지 여부
+        last: bool,
+        /// zstd로 압축한 항목 목록 (JSON)
+        data: Vec<u8>,
+        /// `since` 뒤의 변경분만 담았는지 여부 (false면 전체 매니페스트)
+        #[serde(default)]
+        incremental: bool,
+        /// 응답측의 기록 위치 (마지막 페이지에만 있음)
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cursor: Option<JournalCursor>,
+        /// 응답측 루트의 변경 감지 방식 (`SizeMtime`이면 항목의 해시는 내용 해시가 아님, 구버전은 누락)
+        #[serde(default)]
+        change_detection: ChangeDetection,
+    },
+
+    /// 분실한 기기의 신뢰 철회 요청 (같은 비밀 키로 서명, 대상 기기가 받으면 스스로 페어링을 지움)
+    RevokeMe {
+        revocation: Revocation,
+    },
+
+    /// 철회 요청을 적용함
+    RevokeAck {
+        revocation_id: String,
+    },
+
+    /// 클립보드 내용 (동기화를 켠 기기끼리만, 처음 복사한 기기만 보냄)
+    ClipboardUpdate {
+        update: ClipboardUpdate,
+    },
+
+    /// 클립보드 내용 수신 확인 (이미 적용한 내용이어도 응답)
+    ClipboardAck {
+        update_id: String,
+    },
 }
 
 impl TransferMessage {
@@ -88,7 +532,7 @@ impl TransferMessage {
         Ok(buf.freeze())
     }
 
-    /// 바이트에서 메시지를 역직렬화합니다.
+    /// 스트림에서 프레임 하나를 읽어 메시지를 역직렬화합니다.
     pub async fn from_stream<S>(stream: &mut S) -> Result<Self>
     where
         S: AsyncReadExt + Unpin,
@@ -97,17 +541,110 @@ impl TransferMessage {
         let msg_len = stream.read_u32().await
             .context("Failed to read message length")? as usize;
 
+        Self::read_body(stream, msg_len).await
+    }
+
+    /// 다음 메시지를 읽습니다. 메시지 경계에서 상대가 연결을 닫았으면 None을 반환합니다.
+    pub async fn next_from_stream<S>(stream: &mut S) -> Result<Option<Self>>
+    where
+        S: AsyncReadExt + Unpin,
+    {
+        let msg_len = match stream.read_u32().await {
+            Ok(len) => len as usize,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(anyhow::Error::new(e).context("Failed to read message length")),
+        };
+
+        Self::read_body(stream, msg_len).await.map(Some)
+    }
+
+    /// 길이 헤더 뒤의 메시지 본문을 읽어 디코딩합니다.
+    async fn read_body<S>(stream: &mut S, msg_len: usize) -> Result<Self>
+    where
+        S: AsyncReadExt + Unpin,
+    {
+        // 길이를 검증한 뒤에만 버퍼를 할당
+        Self::check_frame_len(msg_len)?;
+
         // 메시지 데이터 읽기
         let mut buf = vec![0u8; msg_len];
         stream.read_exact(&mut buf).await
             .context("Failed to read message data")?;
 
-        // 역직렬화
-        let msg: TransferMessage = serde_json::from_slice(&buf)
+        Self::decode_payload(&buf)
+    }
+
+    /// 바이트 슬라이스에서 프레임 하나를 디코딩합니다. (I/O 없음)
+    ///
+    /// # Arguments
+    /// * `bytes` - 길이 헤더로 시작하는 바이트 (뒤에 다른 프레임이 이어져도 됨)
+    ///
+    /// # Returns
+    /// * `Result<(TransferMessage, usize)>` - 메시지와 소비한 바이트 수
+    pub fn decode_frame(bytes: &[u8]) -> Result<(Self, usize)> {
+        let header: [u8; FRAME_HEADER_SIZE] = bytes
+            .get(..FRAME_HEADER_SIZE)
+            .and_then(|h| h.try_into().ok())
+            .ok_or_else(|| PebbleError::protocol("Truncated frame header"))?;
+
+        let msg_len = u32::from_be_bytes(header) as usize;
+        Self::check_frame_len(msg_len)?;
+
+        let end = FRAME_HEADER_SIZE + msg_len;
+        let payload = bytes.get(FRAME_HEADER_SIZE..end).ok_or_else(|| {
+            PebbleError::protocol(format!(
+                "Truncated frame: expected {} bytes, got {}", msg_len, bytes.len() - FRAME_HEADER_SIZE
+            ))
+        })?;
+
+        Ok((Self::decode_payload(payload)?, end))
+    }
+
+    /// 길이 헤더를 제외한 JSON 페이로드를 디코딩하고 검증합니다. (I/O 없음)
+    pub fn decode_payload(payload: &[u8]) -> Result<Self> {
+        let msg: TransferMessage = serde_json::from_slice(payload)
             .context("Failed to deserialize transfer message")?;
 
+        msg.validate()?;
+
         Ok(msg)
     }
+
+    fn check_frame_len(msg_len: usize) -> Result<()> {
+        if msg_len > MAX_MESSAGE_SIZE {
+            return Err(PebbleError::protocol(format!(
+                "Message too large: {} bytes (max {})", msg_len, MAX_MESSAGE_SIZE
+            )).into());
+        }
+        Ok(())
+    }
+
+    /// 요청 메시지에 담긴 송신 기기 ID (구버전이거나 기기 ID를 싣지 않는 메시지면 None)
+    pub fn sender_device_id(&self) -> Option<&str> {
+        match self {
+            TransferMessage::TransferRequest { sender_device_id, .. }
+            | TransferMessage::SendText { sender_device_id, .. }
+            | TransferMessage::ManifestRequest { sender_device_id, .. } => sender_device_id.as_deref(),
+            TransferMessage::ClipboardUpdate { update } => Some(&update.origin_device_id),
+            _ => None,
+        }
+    }
+
+    /// 역직렬화 이후의 필드 제한을 검사합니다.
+    fn validate(&self) -> Result<()> {
+        match self {
+            TransferMessage::ChunkData { data, .. } if data.len() as u64 > MAX_CHUNK_SIZE => {
+                Err(PebbleError::protocol(format!("Chunk too large: {} bytes", data.len())).into())
+            }
+            TransferMessage::SendText { text, .. } if text.len() > messages::MAX_TEXT_LENGTH => {
+                Err(PebbleError::protocol(format!("Text too large: {} bytes", text.len())).into())
+            }
+            TransferMessage::ClipboardUpdate { update } if update.data.len() > clipboard::MAX_CLIPBOARD_IMAGE_BYTES => {
+                Err(PebbleError::protocol(format!("Clipboard content too large: {} bytes", update.data.len())).into())
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 /// 전송 진행률 정보
@@ -123,14 +660,49 @@ pub struct TransferProgress {
     pub transfer_rate_mbps: f64,
 }
 
+/// 송신측이 읽은 청크 확인 응답
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AckReply {
+    /// 이 수만큼의 청크가 확인됨
+    Acked(u64),
+    /// 해시가 맞지 않아 이 청크부터 다시 보내야 함
+    Resend(u64),
+}
+
+/// 송수신 양측이 합의한 전송 파라미터
+#[derive(Debug, Clone)]
+struct TransferSpec {
+    transfer_id: String,
+    file_path: String,
+    file_size: u64,
+    total_chunks: u64,
+    chunk_size: u64,
+    chunk_hash_algo: HashAlgo,
+    /// 수신측이 ACK를 보내는 청크 간격 (수신측이 수락하면서 확정)
+    ack_interval: u64,
+    /// 수신측 저장소에서 채우는 청크 (중복 제거를 쓰지 않으면 None)
+    dedup: Option<DedupPlan>,
+    /// 0으로만 된 청크를 `ChunkHole`로 주고받는지 여부 (수신측이 수락하면서 확정)
+    sparse: bool,
+    /// 해시가 맞지 않은 청크를 `ChunkNack`으로 다시 요청할 수 있는지 여부 (송신측이 요청하면서 확정)
+    retransmit: bool,
+    /// 폴더 전송에서 보낸 폴더 기준의 상대 경로 (파일 하나를 보내면 None)
+    relative_path: Option<String>,
+    /// 수신측에서 이어받기 상태를 구분하는 송신 기기 ID (구버전은 IP, 송신측은 빈 문자열)
+    sender_id: String,
+}
+
 /// 전송 상태
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TransferStatus {
     Pending,
     InProgress,
+    Paused,
     Completed,
     Failed,
     Cancelled,
+    /// 다 받았지만 저장 위치가 다른 앱에 잠겨 있어 옮기기를 기다리는 중
+    WaitingForUnlock,
 }
 
 impl TransferStatus {
@@ -138,9 +710,11 @@ impl TransferStatus {
         match self {
             Self::Pending => "Pending",
             Self::InProgress => "InProgress",
+            Self::Paused => "Paused",
             Self::Completed => "Completed",
             Self::Failed => "Failed",
             Self::Cancelled => "Cancelled",
+            Self::WaitingForUnlock => "WaitingForUnlock",
         }
     }
 }
@@ -151,6 +725,10 @@ impl TransferStatus {
 pub struct TransferServer {
     cert: TlsCertificate,
     progress_tx: Option<mpsc::UnboundedSender<TransferProgress>>,
+    #[cfg(any(test, feature = "fault-injection"))]
+    fault: FaultPlan,
+    /// 이 서버의 저장 폴더와 수락 정책 (기본 서버는 모두 전역 설정)
+    listener: Arc<ListenerConfig>,
 }
 
 impl TransferServer {
@@ -159,6 +737,9 @@ impl TransferServer {
         Self {
             cert,
             progress_tx: None,
+            #[cfg(any(test, feature = "fault-injection"))]
+            fault: FaultPlan::default(),
+            listener: Arc::default(),
         }
     }
 
@@ -167,465 +748,2644 @@ impl TransferServer {
         self.progress_tx = Some(tx);
     }
 
+    /// 장애 주입 계획을 설정합니다 (테스트 전용).
+    #[cfg(any(test, feature = "fault-injection"))]
+    pub fn set_fault_plan(&mut self, plan: FaultPlan) {
+        self.fault = plan;
+    }
+
+    /// 이 서버로 받는 전송에 쓸 저장 폴더와 수락 정책을 설정합니다 (추가 전송 서버).
+    pub fn set_listener(&mut self, listener: Arc<ListenerConfig>) {
+        self.listener = listener;
+    }
+
+    /// 이 서버로 받는 파일을 저장할 폴더를 정합니다.
+    ///
+    /// 설정의 `download_dir`보다 우선하며, 송신측 경로의 폴더 부분은 쓰지 않고
+    /// 정리한 파일 이름(폴더 전송이면 정리한 상대 경로)으로 이 폴더 아래에만 저장합니다.
+    pub fn with_download_dir(mut self, download_dir: impl Into<String>) -> Self {
+        self.listener = Arc::new(ListenerConfig {
+            download_dir: Some(download_dir.into()),
+            ..(*self.listener).clone()
+        });
+        self
+    }
+
     /// 서버를 시작합니다.
     pub async fn start(&self, bind_addr: SocketAddr) -> Result<()> {
-        let server_config = self.cert.build_server_config()?;
-        let acceptor = TlsAcceptor::from(server_config);
+        let listener = self.listen(bind_addr).await?;
+        self.serve(listener, CancellationToken::new()).await
+    }
 
-        let listener = TcpListener::bind(bind_addr).await
+    /// 주소에 바인딩만 수행합니다.
+    ///
+    /// 바인딩 실패(포트 사용 중 등)를 백그라운드 태스크로 넘기기 전에 호출자에게
+    /// 바로 알리기 위해 `serve`와 분리되어 있습니다.
+    pub async fn listen(&self, bind_addr: SocketAddr) -> Result<TcpListener> {
+        let listener = tuning::current().bind(bind_addr)
             .with_context(|| format!("Failed to bind to {}", bind_addr))?;
 
-        log::info!("Transfer server listening on {}", bind_addr);
+        tracing::info!("Transfer server listening on {}", listener.local_addr()?);
+
+        Ok(listener)
+    }
+
+    /// 바인딩된 리스너에서 연결을 수락합니다.
+    ///
+    /// `shutdown`이 취소되면 새 연결을 받지 않고, 처리 중인 연결을 중단한 뒤 반환합니다.
+    /// 중단된 수신은 DB에 남은 상태로 나중에 이어받을 수 있습니다.
+    pub async fn serve(&self, listener: TcpListener, shutdown: CancellationToken) -> Result<()> {
+        let server_config = self.cert.build_server_config()?;
+        let acceptor = TlsAcceptor::from(server_config);
+        let mut connections = JoinSet::new();
 
         loop {
-            match listener.accept().await {
+            let accepted = tokio::select! {
+                accepted = listener.accept() => accepted,
+                // 끝난 연결 태스크 정리
+                Some(_) = connections.join_next(), if !connections.is_empty() => continue,
+                _ = shutdown.cancelled() => break,
+            };
+
+            match accepted {
                 Ok((stream, peer_addr)) => {
-                    log::info!("Accepting connection from {}", peer_addr);
+                    tracing::info!("Accepting connection from {}", peer_addr);
+                    if let Err(e) = tuning::current().configure(&stream) {
+                        tracing::warn!("Failed to tune connection from {}: {}", peer_addr, e);
+                    }
 
                     let acceptor = acceptor.clone();
                     let progress_tx = self.progress_tx.clone();
-
-                    tokio::spawn(async move {
-                        if let Err(e) = Self::handle_client(stream, acceptor, progress_tx).await {
-                            log::error!("Error handling client {}: {}", peer_addr, e);
+                    #[cfg(any(test, feature = "fault-injection"))]
+                    let fault = self.fault.clone();
+                    let listener = Arc::clone(&self.listener);
+
+                    connections.spawn(async move {
+                        let _connection = metrics::track_connection();
+                        let handled = Self::handle_client(stream, peer_addr, acceptor, progress_tx, listener);
+                        #[cfg(any(test, feature = "fault-injection"))]
+                        let handled = fault::scope(fault, handled);
+                        if let Err(e) = handled.await {
+                            tracing::error!("Error handling client {}: {}", peer_addr, e);
+                            service::record_error(
+                                ServiceKind::TransferServer,
+                                format!("Error handling client {}: {:#}", peer_addr, e),
+                            );
                         }
                     });
                 }
                 Err(e) => {
-                    log::error!("Error accepting connection: {}", e);
+                    tracing::error!("Error accepting connection: {}", e);
+                    service::record_error(ServiceKind::TransferServer, format!("Error accepting connection: {}", e));
                 }
             }
         }
+
+        if !connections.is_empty() {
+            tracing::info!("Closing {} active connection(s)", connections.len());
+        }
+        connections.shutdown().await;
+
+        Ok(())
     }
 
     /// 클라이언트 연결을 처리합니다.
     async fn handle_client(
         stream: TcpStream,
+        peer_addr: SocketAddr,
         acceptor: TlsAcceptor,
         progress_tx: Option<mpsc::UnboundedSender<TransferProgress>>,
+        listener: Arc<ListenerConfig>,
     ) -> Result<()> {
+        let audit = ConnectionAudit::new(peer_addr, &listener);
+
         // TLS 핸드셰이크
-        let mut tls_stream = acceptor.accept(stream).await
-            .context("TLS handshake failed")?;
+        let tls_stream = match acceptor.accept(audit.wrap(stream)).await.context("TLS handshake failed") {
+            Ok(tls_stream) => tls_stream,
+            Err(e) => {
+                audit.finish(ConnectionOutcome::HandshakeFailed, Some(&e));
+                return Err(e);
+            }
+        };
+
+        tracing::info!("TLS handshake successful");
+
+        let peer_fingerprint = TlsCertificate::peer_fingerprint(tls_stream.get_ref().1.peer_certificates());
+        let result = Self::serve_requests(
+            tls_stream,
+            peer_addr,
+            peer_fingerprint.as_deref(),
+            progress_tx,
+            &listener,
+            Some(&audit),
+        )
+        .await;
+        match &result {
+            Ok(()) => audit.finish(ConnectionOutcome::Closed, None),
+            Err(e) => audit.finish(ConnectionOutcome::Failed, Some(e)),
+        }
+        result
+    }
+
+    /// 핸드셰이크가 끝난 스트림에서 요청을 처리합니다.
+    ///
+    /// 연결을 재사용하는 송신측(연결 풀)을 위해 상대가 연결을 닫거나 `CONNECTION_IDLE_TIMEOUT`
+    /// 동안 요청이 없을 때까지 요청을 계속 받습니다. 요청 하나라도 실패하면 연결을 닫습니다.
+    ///
+    /// TLS 여부와 무관하게 동작하므로 테스트에서 메모리 스트림(tokio duplex)으로
+    /// 프로토콜을 검증할 때 사용합니다. 수신측 장애 주입은 `fault::scope` 안에서 호출합니다.
+    pub async fn handle_stream<S>(
+        tls_stream: S,
+        peer_addr: SocketAddr,
+        progress_tx: Option<mpsc::UnboundedSender<TransferProgress>>,
+    ) -> Result<()>
+    where
+        S: AsyncReadExt + AsyncWriteExt + Unpin,
+    {
+        Self::handle_stream_for(tls_stream, peer_addr, progress_tx, &ListenerConfig::default()).await
+    }
+
+    /// 추가 전송 서버의 저장 폴더와 수락 정책으로 스트림의 요청을 처리합니다.
+    pub async fn handle_stream_for<S>(
+        tls_stream: S,
+        peer_addr: SocketAddr,
+        progress_tx: Option<mpsc::UnboundedSender<TransferProgress>>,
+        listener: &ListenerConfig,
+    ) -> Result<()>
+    where
+        S: AsyncReadExt + AsyncWriteExt + Unpin,
+    {
+        Self::serve_requests(tls_stream, peer_addr, None, progress_tx, listener, None).await
+    }
+
+    /// 연결이 끝날 때까지 요청을 처리하고, `audit`이 있으면 요청마다 연결 기록에 집계합니다.
+    ///
+    /// `peer_fingerprint`는 상대가 보낸 TLS 클라이언트 인증서의 핑거프린트입니다 (없으면 None).
+    async fn serve_requests<S>(
+        mut tls_stream: S,
+        peer_addr: SocketAddr,
+        peer_fingerprint: Option<&str>,
+        progress_tx: Option<mpsc::UnboundedSender<TransferProgress>>,
+        listener: &ListenerConfig,
+        audit: Option<&ConnectionAudit>,
+    ) -> Result<()>
+    where
+        S: AsyncReadExt + AsyncWriteExt + Unpin,
+    {
+        loop {
+            let next = tokio::time::timeout(CONNECTION_IDLE_TIMEOUT, TransferMessage::next_from_stream(&mut tls_stream));
+            let msg = match next.await {
+                Ok(Ok(Some(msg))) => msg,
+                Ok(Ok(None)) => return Ok(()),
+                Ok(Err(e)) => return Err(e),
+                Err(_) => {
+                    tracing::debug!("Closing idle connection from {}", peer_addr);
+                    return Ok(());
+                }
+            };
+
+            // 연결 풀의 상태 확인
+            if let TransferMessage::Ping { nonce } = msg {
+                tls_stream.write_all(&TransferMessage::Pong { nonce }.to_bytes()?).await?;
+                continue;
+            }
+            if let Some(audit) = audit {
+                audit.observe(&msg);
+            }
 
-        log::info!("TLS handshake successful");
+            Self::handle_message(&mut tls_stream, peer_addr, peer_fingerprint, msg, progress_tx.clone(), listener)
+                .await?;
+        }
+    }
 
-        // 전송 요청 수신
-        let msg = TransferMessage::from_stream(&mut tls_stream).await?;
+    /// 요청 메시지 하나(전송, 텍스트, 속도 측정)를 끝까지 처리합니다.
+    #[tracing::instrument(
+        name = "transfer",
+        skip_all,
+        fields(
+            direction = "receive",
+            peer = %peer_addr,
+            transfer_id = tracing::field::Empty,
+            chunk_hash = tracing::field::Empty
+        )
+    )]
+    async fn handle_message<S>(
+        tls_stream: &mut S,
+        peer_addr: SocketAddr,
+        peer_fingerprint: Option<&str>,
+        msg: TransferMessage,
+        progress_tx: Option<mpsc::UnboundedSender<TransferProgress>>,
+        listener: &ListenerConfig,
+    ) -> Result<()>
+    where
+        S: AsyncReadExt + AsyncWriteExt + Unpin,
+    {
+        // 신뢰를 철회한 기기와, 다른 기기의 ID를 사칭하는 연결의 요청은 받지 않음
+        if let Err(e) = revocation::check_peer(msg.sender_device_id(), peer_fingerprint) {
+            tracing::warn!("Refused request from {}: {:#}", peer_addr, e);
+            let reject_msg = TransferMessage::TransferReject {
+                transfer_id: String::new(),
+                reason: "This device is no longer trusted".to_string(),
+                code: Some(RejectCode::PolicyDenied),
+            };
+            tls_stream.write_all(&reject_msg.to_bytes()?).await?;
+            return Err(e);
+        }
 
-        let (transfer_id, file_path, file_size, total_chunks) = match msg {
+        let (transfer_id, file_path, file_size, file_hash, total_chunks, chunk_size, chunk_hash_algos, sender_device_id, ack_interval, store, xattrs, inline_data, verdict, crc32c, retransmit, relative_path) = match msg {
             TransferMessage::TransferRequest {
                 transfer_id,
                 file_path,
                 file_size,
-                file_hash: _,
+                file_hash,
                 total_chunks,
+                chunk_size,
+                chunk_hash_algos,
+                sender_device_id,
+                ack_ranges,
+                chunk_manifest,
+                inline_data,
+                xattrs,
+                verdict,
+                crc32c,
+                retransmit,
+                relative_path,
             } => {
-                log::info!("Received transfer request: {} ({} bytes, {} chunks)",
+                tracing::Span::current().record("transfer_id", transfer_id.as_str());
+                tracing::info!("Received transfer request: {} ({} bytes, {} chunks)",
                     file_path, file_size, total_chunks);
 
-                (transfer_id, file_path, file_size, total_chunks)
+                let ack_interval = if ack_ranges { ACK_INTERVAL } else { 1 };
+                // 저장소에서 채운 청크는 묶음 ACK로만 알릴 수 있음
+                let store = if chunk_manifest && ack_ranges { ChunkStore::configured() } else { None };
+                (transfer_id, file_path, file_size, file_hash, total_chunks, chunk_size, chunk_hash_algos, sender_device_id, ack_interval, store, xattrs, inline_data, verdict, crc32c, retransmit, relative_path)
+            }
+            TransferMessage::SendText { message_id, sender_device_id, text } => {
+                return Self::receive_text(tls_stream, peer_addr, message_id, sender_device_id, text).await;
+            }
+            TransferMessage::SpeedTestRequest { test_id, total_bytes, chunk_size } => {
+                tracing::info!("Speed test requested by {} ({} bytes)", peer_addr, total_bytes);
+                return speedtest::serve(tls_stream, test_id, total_bytes, chunk_size).await;
+            }
+            TransferMessage::IdentityRequest { nonce, proof } => {
+                let reply = match discovery::answer_identity(&nonce, &proof) {
+                    Some((device_id, device_name)) => TransferMessage::Identity { device_id, device_name },
+                    None => {
+                        tracing::warn!("Refused identity request from {}", peer_addr);
+                        TransferMessage::TransferReject {
+                            transfer_id: nonce,
+                            reason: "Identity is only shared with devices using the same secret key".to_string(),
+                            code: None,
+                        }
+                    }
+                };
+                tls_stream.write_all(&reply.to_bytes()?).await?;
+                return Ok(());
+            }
+            TransferMessage::HashQuery { query_id, file_hash, file_size } => {
+                let present = Self::has_file(&file_hash, file_size);
+                tracing::info!("Hash query from {}: {} ({} bytes) present = {}", peer_addr, file_hash, file_size, present);
+
+                let reply = TransferMessage::HashQueryResult { query_id, present };
+                tls_stream.write_all(&reply.to_bytes()?).await?;
+                return Ok(());
+            }
+            TransferMessage::ManifestRequest { request_id, root, sender_device_id, since } => {
+                return manifest::serve(tls_stream, peer_addr, request_id, root, sender_device_id, since).await;
+            }
+            TransferMessage::RevokeMe { revocation } => {
+                return Self::receive_revocation(tls_stream, peer_addr, revocation).await;
+            }
+            TransferMessage::ClipboardUpdate { update } => {
+                return Self::receive_clipboard(tls_stream, peer_addr, update).await;
             }
             _ => {
-                anyhow::bail!("Expected TransferRequest, got {:?}", msg);
+                return Err(PebbleError::protocol(format!("Expected TransferRequest, got {:?}", msg)).into());
             }
         };
 
-        // 이어받기 지원: 기존 전송 상태 확인
-        let resume_from_chunk = Self::get_resume_chunk(&transfer_id)?;
+        if !(MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE).contains(&chunk_size) {
+            let reject_msg = TransferMessage::TransferReject {
+                transfer_id: transfer_id.clone(),
+                reason: format!("Unsupported chunk size: {}", chunk_size),
+                code: None,
+            };
+            tls_stream.write_all(&reject_msg.to_bytes()?).await?;
+            return Err(PebbleError::protocol(format!("Unsupported chunk size: {}", chunk_size)).into());
+        }
 
-        // 전송 수락
-        let accept_msg = TransferMessage::TransferAccept {
-            transfer_id: transfer_id.clone(),
-            resume_from_chunk,
-        };
+        // 청크 수가 선언한 크기와 맞지 않으면 받을 양을 믿을 수 없음 (저장 한도, 대역폭 집계의 기준)
+        if total_chunks != file_size.div_ceil(chunk_size) {
+            let reason = format!(
+                "{} chunks of {} bytes do not match the file size {}",
+                total_chunks, chunk_size, file_size
+            );
+            let reject_msg = TransferMessage::TransferReject {
+                transfer_id: transfer_id.clone(),
+                reason: reason.clone(),
+                code: None,
+            };
+            tls_stream.write_all(&reject_msg.to_bytes()?).await?;
+            return Err(PebbleError::protocol(reason).into());
+        }
 
-        tls_stream.write_all(&accept_msg.to_bytes()?).await?;
+        // 같은 전송을 이미 받는 중이면 같은 받을 위치에 함께 쓰게 되므로 거부
+        if registry::global().is_active(&transfer_id, TransferDirection::Receive) {
+            let reason = format!("Transfer {} is already being received", transfer_id);
+            let reject_msg = TransferMessage::TransferReject {
+                transfer_id: transfer_id.clone(),
+                reason: reason.clone(),
+                code: None,
+            };
+            tls_stream.write_all(&reject_msg.to_bytes()?).await?;
+            return Err(PebbleError::rejected(reason).into());
+        }
 
-        log::info!("Transfer accepted. Resuming from chunk {}", resume_from_chunk);
+        // 이어받기와 저장 한도는 송신 기기별로 구분 (ID를 보내지 않는 구버전은 IP)
+        let peer_id = sender_device_id.clone().unwrap_or_else(|| peer_addr.ip().to_string());
 
-        // 파일 수신
-        Self::receive_file(
-            &mut tls_stream,
-            &transfer_id,
-            &file_path,
+        // 이어받기 지원: 기존 전송 상태 확인
+        let resume_from_chunk = Self::get_resume_chunk(&transfer_id, &peer_id)?;
+        let resume_offset = (resume_from_chunk * chunk_size).min(file_size);
+
+        // 송신 기기별 저장 한도 확인
+        if let Err(e) = quota::check(&peer_id, file_size - resume_offset) {
+            let reject_msg = TransferMessage::TransferReject {
+                transfer_id: transfer_id.clone(),
+                reason: format!("{:#}", e),
+                code: Some(RejectCode::QuotaExceeded),
+            };
+            tls_stream.write_all(&reject_msg.to_bytes()?).await?;
+            return Err(e);
+        }
+
+        // 수락 정책 평가 (승인이 필요하면 사용자 응답을 기다림)
+        if let Err((reason, code)) = Self::apply_accept_policy(listener, &transfer_id, sender_device_id.as_deref(), peer_addr, &file_path, file_size).await {
+            let reject_msg = TransferMessage::TransferReject {
+                transfer_id: transfer_id.clone(),
+                reason: reason.clone(),
+                code: Some(code),
+            };
+            tls_stream.write_all(&reject_msg.to_bytes()?).await?;
+            return Err(PebbleError::rejected(reason).into());
+        }
+
+        // 저장 위치 결정 및 열기 (호스트 저장소 모드에서는 호스트가 만든 문서)
+        let resuming = resume_from_chunk > 0;
+        // 항상 저장 폴더 아래에 저장 (추가 전송 서버의 폴더 > 설정의 download_dir > 기본 폴더)
+        let download_dir = listener.download_dir.clone().unwrap_or_else(|| config::current().receive_dir());
+        let request = DestinationRequest {
+            transfer_id: &transfer_id,
+            file_path: &file_path,
             file_size,
-            total_chunks,
-            resume_from_chunk,
-            progress_tx,
-        )
-        .await?;
+            resuming,
+            download_dir: Some(Self::folder_download_dir(download_dir, relative_path.as_deref())),
+            sender_device_id: sender_device_id.as_deref(),
+        };
+        let Destination { mut sink, path: dest_path, staged, claim } = match backend::open_destination(request).await {
+            Ok(Some(opened)) => opened,
+            Ok(None) => {
+                let reject_msg = TransferMessage::TransferReject {
+                    transfer_id: transfer_id.clone(),
+                    reason: format!("Skipped {}: a file differing only in letter case already exists", file_path),
+                    code: Some(RejectCode::CaseCollision),
+                };
+                tls_stream.write_all(&reject_msg.to_bytes()?).await?;
+                return Ok(());
+            }
+            Err(e) => {
+                // 읽기 전용 공유 폴더로의 수신은 정책 거부로 알림
+                let denied = matches!(e.downcast_ref::<PebbleError>(), Some(PebbleError::Rejected { .. }));
+                let reject_msg = TransferMessage::TransferReject {
+                    transfer_id: transfer_id.clone(),
+                    reason: format!("Cannot store file: {:#}", e),
+                    code: denied.then_some(RejectCode::PolicyDenied),
+                };
+                tls_stream.write_all(&reject_msg.to_bytes()?).await?;
+                return Err(e);
+            }
+        };
 
-        Ok(())
-    }
+        // 이어받을 앞부분이 없는 저장 위치(호스트가 기존 문서 대신 새 문서를 준 경우 등)는 처음부터 받음
+        let (resume_from_chunk, resume_offset, resuming) = match sink.size() {
+            Ok(size) if resuming && size < resume_offset => {
+                tracing::warn!("{} holds {} of {} bytes already received, restarting from the beginning",
+                    dest_path, size, resume_offset);
+                (0, 0, false)
+            }
+            _ => (resume_from_chunk, resume_offset, resuming),
+        };
 
-    /// 이어받기 청크 인덱스를 가져옵니다.
-    fn get_resume_chunk(transfer_id: &str) -> Result<u64> {
-        let conn = Connection::open("pebble.db")?;
+        // 작은 파일은 요청에 담긴 내용을 바로 저장 (크기가 맞지 않으면 청크로 받음)
+        let inline_data = inline_data.filter(|data| {
+            !resuming && file_size <= INLINE_FILE_MAX && data.len() as u64 == file_size
+        });
+
+        let (chunk_hash_algo, dedup) = if inline_data.is_some() {
+            (HashAlgo::default(), None)
+        } else {
+            // 청크 해시 알고리즘 합의
+            // CRC32C는 파일 전체 해시로 끝에서 다시 검증할 수 있을 때만 사용
+            let current = config::current();
+            let chunk_hash_algo = if crc32c && current.crc_chunk_check && !file_hash.is_empty() {
+                HashAlgo::Crc32c
+            } else {
+                let supported = HashAlgo::chunk_preferences(current.fast_chunk_hash);
+                HashAlgo::negotiate(&chunk_hash_algos, &supported)
+            };
+            tracing::Span::current().record("chunk_hash", chunk_hash_algo.name());
+
+            // 전송 수락
+            let store = store.filter(|_| resume_from_chunk < total_chunks);
+            let accept_msg = TransferMessage::TransferAccept {
+                transfer_id: transfer_id.clone(),
+                resume_from_chunk,
+                chunk_hash_algo,
+                ack_interval,
+                want_manifest: store.is_some(),
+                sparse: true,
+                verdict,
+            };
 
-        let mut stmt = conn.prepare(
-            "SELECT received_chunks FROM transfer_state WHERE transfer_id = ?1"
-        )?;
+            tls_stream.write_all(&accept_msg.to_bytes()?).await?;
 
-        let result: Result<i64, _> = stmt.query_row(params![transfer_id], |row| row.get(0));
+            tracing::info!("Transfer accepted. Resuming from chunk {} (chunk hash: {})",
+                resume_from_chunk, chunk_hash_algo.name());
 
-        Ok(result.unwrap_or(0) as u64)
+            // 중복 제거: 해시 목록을 받아 저장소에 없는 청크만 요청
+            let dedup = match store {
+                Some(store) => Some(
+                    Self::receive_manifest(tls_stream, &transfer_id, store, resume_from_chunk, total_chunks, file_size, chunk_size)
+                        .await?,
+                ),
+                None => None,
+            };
+            (chunk_hash_algo, dedup)
+        };
+
+        // 파일 수신
+        let spec = TransferSpec {
+            transfer_id,
+            file_path: dest_path,
+            file_size,
+            total_chunks,
+            chunk_size,
+            chunk_hash_algo,
+            ack_interval,
+            dedup,
+            sparse: true,
+            retransmit,
+            relative_path,
+            sender_id: peer_id.clone(),
+        };
+        let mut handle = registry::global().register(
+            &spec.transfer_id,
+            &peer_addr.to_string(),
+            &spec.file_path,
+            TransferDirection::Receive,
+            file_size,
+        )?;
+        handle.set_chunk_hash_algo(chunk_hash_algo);
+        let inline = inline_data.is_some();
+        let result = match inline_data {
+            Some(data) => Self::receive_inline(sink.as_mut(), &data, &mut handle)
+                .and_then(|received_hash| Self::verify_received(&spec, &file_hash, received_hash)),
+            None => {
+                let interrupt = handle.interrupted();
+                interruptible(
+                    interrupt,
+                    Self::receive_file(tls_stream, sink.as_mut(), &spec, resume_from_chunk, &mut handle, progress_tx),
+                )
+                .await
+                .and_then(|received_hash| Self::verify_received(&spec, &file_hash, received_hash))
+            }
+        };
+        // 검증까지 끝난 뒤에만 저장소에 완료를 알림
+        let result = result.and_then(|received_hash| {
+            sink.finish(Some(&received_hash))?;
+            Ok(Some(received_hash))
+        });
+        drop(sink);
+
+        // 외부 검사 명령 (저장 위치로 옮기기 전의 임시 파일을 검사)
+        let result = match result {
+            Ok(received_hash) => {
+                let received = staged.clone().unwrap_or_else(|| PathBuf::from(&spec.file_path));
+                let scanned = scanhook::check(&received, &spec.transfer_id, sender_device_id.as_deref()).await;
+                if scanned.is_err() {
+                    // 격리한 파일은 이어받지 않고 다음 시도에 처음부터 다시 받음
+                    let _ = Self::update_transfer_state(&spec, 0);
+                }
+                scanned.map(|()| received_hash)
+            }
+            Err(e) => Err(e),
+        };
+
+        // 검사를 통과한 뒤에만 임시 파일을 저장 위치로 옮김 (잠겨 있으면 풀린 뒤 옮김)
+        let mut waiting_for_unlock = false;
+        let result = result.and_then(|received_hash| {
+            if let Some(staged) = &staged {
+                match std::fs::rename(paths::long_path(staged), paths::long_path(Path::new(&spec.file_path))) {
+                    Ok(()) => {}
+                    Err(e) if locked::is_locked(&e) => {
+                        tracing::warn!("{} is locked by another app, keeping {}", spec.file_path, staged.display());
+                        waiting_for_unlock = true;
+                    }
+                    Err(e) => {
+                        return Err(anyhow::Error::from(e).context(format!("Failed to move received file to {}", spec.file_path)))
+                    }
+                }
+            }
+            Ok(received_hash)
+        });
+
+        // 작은 파일을 바로 저장했거나 송신측이 최종 결과를 기다리면 알림
+        if inline || verdict {
+            let reply = match &result {
+                Ok(received_hash) => TransferMessage::TransferComplete {
+                    transfer_id: spec.transfer_id.clone(),
+                    receipt: Self::issue_receipt(&spec, received_hash.as_deref()),
+                },
+                Err(e) if matches!(e.downcast_ref::<PebbleError>(), Some(PebbleError::Rejected { .. })) => {
+                    TransferMessage::TransferReject {
+                        transfer_id: spec.transfer_id.clone(),
+                        reason: format!("{:#}", e),
+                        code: Some(RejectCode::PolicyDenied),
+                    }
+                }
+                Err(e) => TransferMessage::Error { transfer_id: spec.transfer_id.clone(), message: format!("{:#}", e) },
+            };
+            if let Err(e) = tls_stream.write_all(&reply.to_bytes()?).await {
+                tracing::warn!("Failed to send transfer result for {}: {}", spec.transfer_id, e);
+            }
+        }
+
+        let (result, received_hash) = match result {
+            Ok(received_hash) => (Ok(()), received_hash),
+            Err(e) => (Err(e), None),
+        };
+
+        if let Err(e) = &result {
+            handle.set_error(e);
+        }
+
+        // 중단된 전송도 받은 만큼은 사용량에 포함
+        let received_bytes = handle.bytes_transferred().saturating_sub(resume_offset);
+        if let Err(e) = quota::record_received(&peer_id, received_bytes, result.is_ok()) {
+            tracing::warn!("Failed to record usage for {}: {:#}", peer_id, e);
+        }
+        if let Err(e) = bandwidth::record(&peer_id, 0, received_bytes) {
+            tracing::warn!("Failed to record bandwidth for {}: {:#}", peer_id, e);
+        }
+        Self::record_history(&handle, &result);
+
+        result?;
+
+        // 확장 속성은 임시 파일에 기록해도 옮길 때 함께 옮겨짐
+        let staged = staged.filter(|_| waiting_for_unlock);
+        if !xattrs.is_empty() && config::current().preserve_xattrs {
+            let written = staged.clone().unwrap_or_else(|| PathBuf::from(&spec.file_path));
+            if paths::long_path(&written).is_file() {
+                let restored = xattrs::restore(&paths::long_path(&written), &xattrs);
+                tracing::debug!("Restored {} of {} extended attributes on {}", restored, xattrs.len(), spec.file_path);
+            }
+        }
+
+        // 저장 위치가 잠겨 있어 임시 파일로 받았으면 잠금이 풀린 뒤 완료
+        if let Some(staged) = staged {
+            Self::finish_when_unlocked(handle, staged, spec.file_path, claim, received_hash);
+            return Ok(());
+        }
+        handle.set_status(TransferStatus::Completed);
+        Self::complete_transfer_state(&spec.transfer_id);
+
+        // 같은 파일을 다시 보내면 전송 없이 완료되도록 내용 해시를 기록
+        if let Err(e) = Self::record_received_file(&spec.file_path, received_hash).await {
+            tracing::warn!("Failed to index received file {}: {:#}", spec.file_path, e);
+        }
+        thumbnails::spawn_for(&spec.transfer_id, &spec.file_path);
+
+        Ok(())
     }
 
-    /// 파일을 수신합니다.
-    async fn receive_file<S>(
-        stream: &mut S,
+    /// 수신 중 계산한 파일 해시를 송신측이 보낸 해시와 비교합니다.
+    ///
+    /// 해시를 보내지 않는 구버전 기기와는 청크 해시 검증만 합니다. 일치하지 않으면
+    /// 받은 내용을 믿을 수 없으므로 다음 시도는 처음부터 다시 받습니다.
+    ///
+    /// # Returns
+    /// * 검증한 해시
+    fn verify_received(spec: &TransferSpec, expected_hash: &str, actual: String) -> Result<String> {
+        if expected_hash.is_empty() || actual == expected_hash {
+            return Ok(actual);
+        }
+
+        Self::update_transfer_state(spec, 0)?;
+        anyhow::bail!("File hash mismatch for {} (expected {}, got {})", spec.file_path, expected_hash, actual)
+    }
+
+    /// 잠긴 저장 위치 대신 임시 파일로 받은 전송을 잠금이 풀린 뒤 마무리합니다 (백그라운드).
+    ///
+    /// 기다리는 동안 전송은 WaitingForUnlock 상태로 진행 중 목록에 남고,
+    /// 옮기면 완료 이벤트, 끝내 옮기지 못하면 실패 이벤트가 발행됩니다.
+    fn finish_when_unlocked(
+        mut handle: TransferHandle,
+        staged: PathBuf,
+        dest_path: String,
+        claim: Option<DestinationClaim>,
+        received_hash: Option<String>,
+    ) {
+        handle.set_status(TransferStatus::WaitingForUnlock);
+        if let Some(info) = handle.info() {
+            events::emit(PebbleEvent::DestinationLocked {
+                transfer_id: info.transfer_id,
+                path: dest_path.clone(),
+                staged_path: staged.to_string_lossy().to_string(),
+            });
+        }
+
+        tokio::spawn(async move {
+            // 옮길 때까지 다른 수신이 같은 경로를 쓰지 않도록 점유 유지
+            let _claim = claim;
+            match locked::replace_when_unlocked(&staged, Path::new(&dest_path)).await {
+                Ok(()) => {
+                    handle.set_status(TransferStatus::Completed);
+                    if let Some(info) = handle.info() {
+                        Self::complete_transfer_state(&info.transfer_id);
+                        thumbnails::spawn_for(&info.transfer_id, &dest_path);
+                    }
+                    if let Err(e) = Self::record_received_file(&dest_path, received_hash).await {
+                        tracing::warn!("Failed to index received file {}: {:#}", dest_path, e);
+                    }
+                }
+                Err(e) => {
+                    tracing::error!("Giving up on locked destination {}: {:#}", dest_path, e);
+                    // 내용은 모두 받았으므로 임시 파일은 미완성 파일 정리에서 제외
+                    if let Some(info) = handle.info() {
+                        Self::complete_transfer_state(&info.transfer_id);
+                    }
+                    handle.set_error(&e);
+                    Self::record_history(&handle, &Err(e));
+                }
+            }
+        });
+    }
+
+    /// 전체 파일 해시를 확인한 수신의 수령증을 기기 인증서로 서명합니다.
+    ///
+    /// 전송 서버가 실행 중이 아니어서 인증서가 없거나 해시를 계산하지 않았으면 None입니다.
+    fn issue_receipt(spec: &TransferSpec, received_hash: Option<&str>) -> Option<TransferReceipt> {
+        let received_hash = received_hash?;
+        let cert = service::certificate()?;
+        match receipts::sign(&cert, &spec.transfer_id, received_hash, spec.file_size, service::device_id()) {
+            Ok(receipt) => Some(receipt),
+            Err(e) => {
+                tracing::warn!("Failed to sign receipt for {}: {:#}", spec.transfer_id, e);
+                None
+            }
+        }
+    }
+
+    /// 끝난 전송을 전송 기록에 남깁니다. 실패해도 전송 결과에는 영향을 주지 않습니다.
+    fn record_history(handle: &TransferHandle, result: &Result<()>) {
+        let Some(info) = handle.info() else {
+            return;
+        };
+        if let Err(e) = history::record(&info, result) {
+            tracing::warn!("Failed to record transfer history for {}: {:#}", info.transfer_id, e);
+        }
+    }
+
+    /// 같은 해시와 크기의 파일이 DB에 있고 디스크에도 남아 있는지 확인합니다.
+    ///
+    /// # Security
+    /// - 응답은 존재 여부만 담고 경로는 알리지 않습니다.
+    fn has_file(file_hash: &str, file_size: u64) -> bool {
+        let candidates = match db::find_files_by_hash(file_hash, file_size as i64) {
+            Ok(candidates) => candidates,
+            Err(e) => {
+                tracing::warn!("Failed to look up file hash {}: {}", file_hash, e);
+                return false;
+            }
+        };
+
+        candidates.iter().any(|file| {
+            std::fs::metadata(paths::long_path(&file.path)).is_ok_and(|metadata| metadata.len() == file_size)
+        })
+    }
+
+    /// 수신을 마친 파일의 해시를 files 테이블에 기록합니다.
+    ///
+    /// 수신 중 계산한 해시(`received_hash`)가 있으면 파일을 다시 읽지 않습니다.
+    /// 호스트 저장소 모드의 문서 URI처럼 로컬 경로가 아니면 기록하지 않습니다.
+    async fn record_received_file(dest_path: &str, received_hash: Option<String>) -> Result<()> {
+        let path = dest_path.to_string();
+        if !paths::long_path(&path).is_file() {
+            return Ok(());
+        }
+
+        tokio::task::spawn_blocking(move || -> Result<()> {
+            let metadata = std::fs::metadata(paths::long_path(&path))?;
+            let last_modified = metadata
+                .modified()?
+                .duration_since(UNIX_EPOCH)?
+                .as_secs() as i64;
+
+            let file_hash = match received_hash {
+                Some(file_hash) => file_hash,
+                None => integrity::calculate_file_hash(&path)?,
+            };
+            db::upsert_file(db::FileMetadata::new(
+                path,
+                last_modified,
+                file_hash,
+                db::SyncStatus::Synced,
+                metadata.len() as i64,
+            ))?;
+            Ok(())
+        })
+        .await?
+    }
+
+    /// 설정된 수락 정책을 적용합니다 (추가 전송 서버에 정책이 있으면 그 정책).
+    ///
+    /// # Returns
+    /// * 거부되면 `Err((사유, 거부 코드))`
+    async fn apply_accept_policy(
+        listener: &ListenerConfig,
         transfer_id: &str,
+        sender_device_id: Option<&str>,
+        peer_addr: SocketAddr,
         file_path: &str,
         file_size: u64,
-        total_chunks: u64,
+    ) -> std::result::Result<(), (String, RejectCode)> {
+        let policy = listener.accept_policy.clone().unwrap_or_else(|| config::current().accept_policy);
+
+        match policy.evaluate(sender_device_id, file_path, file_size) {
+            AcceptDecision::Accept => Ok(()),
+            AcceptDecision::Reject(reason) => {
+                tracing::info!("Transfer {} denied by accept policy: {}", transfer_id, reason);
+                Err((reason, RejectCode::PolicyDenied))
+            }
+            AcceptDecision::AskUser => {
+                let request = ApprovalRequest::new(
+                    transfer_id,
+                    sender_device_id.map(str::to_string),
+                    peer_addr.to_string(),
+                    file_path,
+                    file_size,
+                );
+                match accept::global().request_approval(request).await {
+                    Ok(true) => Ok(()),
+                    Ok(false) => Err(("Transfer was declined by the user".to_string(), RejectCode::Declined)),
+                    Err(e) => Err((format!("Approval failed: {:#}", e), RejectCode::Declined)),
+                }
+            }
+        }
+    }
+
+    /// 텍스트 메시지를 수신하여 기록에 저장하고 구독자에게 알립니다.
+    async fn receive_text<S>(
+        stream: &mut S,
+        peer_addr: SocketAddr,
+        message_id: String,
+        sender_device_id: Option<String>,
+        text: String,
+    ) -> Result<()>
+    where
+        S: AsyncWriteExt + Unpin,
+    {
+        if let Err(e) = messages::validate_text(&text) {
+            let reject_msg = TransferMessage::TransferReject {
+                transfer_id: message_id,
+                reason: e.to_string(),
+                code: None,
+            };
+            stream.write_all(&reject_msg.to_bytes()?).await?;
+            return Err(PebbleError::rejected(e.to_string()).into());
+        }
+
+        tracing::info!("Received text message {} ({} bytes) from {}", message_id, text.len(), peer_addr);
+
+        let message = TextMessage::new(
+            message_id.clone(),
+            TransferDirection::Receive,
+            sender_device_id,
+            peer_addr.to_string(),
+            text,
+        );
+        messages::deliver_incoming(message)?;
+
+        let ack_msg = TransferMessage::TextAck { message_id };
+        stream.write_all(&ack_msg.to_bytes()?).await?;
+
+        Ok(())
+    }
+
+    /// 클립보드 내용을 받아 새 내용이면 구독자에게 알립니다.
+    async fn receive_clipboard<S>(stream: &mut S, peer_addr: SocketAddr, update: ClipboardUpdate) -> Result<()>
+    where
+        S: AsyncWriteExt + Unpin,
+    {
+        let update_id = update.update_id.clone();
+        let origin = update.origin_device_id.clone();
+        match clipboard::receive(update) {
+            Ok(applied) => {
+                tracing::debug!("Clipboard update {} from {} ({}), applied: {}", update_id, origin, peer_addr, applied);
+            }
+            Err(e) => {
+                tracing::warn!("Refused clipboard update from {} ({}): {:#}", origin, peer_addr, e);
+                let reject_msg = TransferMessage::TransferReject {
+                    transfer_id: update_id,
+                    reason: format!("{:#}", e),
+                    code: Some(RejectCode::PolicyDenied),
+                };
+                stream.write_all(&reject_msg.to_bytes()?).await?;
+                return Err(e);
+            }
+        }
+
+        let ack_msg = TransferMessage::ClipboardAck { update_id };
+        stream.write_all(&ack_msg.to_bytes()?).await?;
+        Ok(())
+    }
+
+    /// 기기 신뢰 철회 요청을 확인하고 적용합니다.
+    ///
+    /// 이 기기가 철회 대상이면 응답한 뒤 사용자에게 페어링을 지울지 확인을 요청합니다.
+    async fn receive_revocation<S>(stream: &mut S, peer_addr: SocketAddr, revocation: Revocation) -> Result<()>
+    where
+        S: AsyncWriteExt + Unpin,
+    {
+        tracing::info!("Revocation of {} from {} ({})", revocation.device_id, revocation.issued_by, peer_addr);
+
+        let outcome = match revocation::receive(&revocation) {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                tracing::warn!("Refused revocation from {}: {:#}", peer_addr, e);
+                let reject_msg = TransferMessage::TransferReject {
+                    transfer_id: revocation.revocation_id,
+                    reason: format!("{:#}", e),
+                    code: Some(RejectCode::PolicyDenied),
+                };
+                stream.write_all(&reject_msg.to_bytes()?).await?;
+                return Err(e);
+            }
+        };
+
+        let ack_msg = TransferMessage::RevokeAck { revocation_id: revocation.revocation_id.clone() };
+        stream.write_all(&ack_msg.to_bytes()?).await?;
+        stream.flush().await?;
+
+        if outcome == RevocationOutcome::ThisDevice {
+            revocation::request_wipe(&revocation)?;
+        }
+        Ok(())
+    }
+
+    /// 폴더 전송의 상대 경로에 있는 하위 폴더를 받을 폴더에 붙입니다.
+    ///
+    /// # Security
+    /// - 상대 경로는 `filename::sanitize_relative_path`로 정리하므로 받을 폴더 밖을 가리키지 않음
+    fn folder_download_dir(download_dir: String, relative_path: Option<&str>) -> String {
+        let Some(relative) = relative_path.and_then(filename::sanitize_relative_path) else {
+            return download_dir;
+        };
+        match relative.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => {
+                Path::new(&download_dir).join(parent).to_string_lossy().to_string()
+            }
+            _ => download_dir,
+        }
+    }
+
+    /// 이어받기 청크 인덱스를 가져옵니다.
+    ///
+    /// 같은 파일을 다시 보내면 전송 ID가 같으므로, 끝난 수신의 상태는 이어받지 않고 처음부터 받습니다.
+    /// 다른 기기가 받다 만 파일은 이어받지 않습니다.
+    fn get_resume_chunk(transfer_id: &str, sender_id: &str) -> Result<u64> {
+        let conn = db::open_connection()?;
+
+        let mut stmt = conn.prepare(
+            "SELECT received_chunks FROM transfer_state
+             WHERE transfer_id = ?1 AND transfer_status != ?2 AND peer_device_id = ?3"
+        )?;
+
+        let result: Result<i64, _> = stmt.query_row(
+            params![transfer_id, TransferStatus::Completed.to_string(), sender_id],
+            |row| row.get(0),
+        );
+
+        Ok(result.unwrap_or(0) as u64)
+    }
+
+    /// 파일을 수신합니다.
+    ///
+    /// # Returns
+    /// * 받은 파일 전체의 blake3 해시
+    async fn receive_file<S>(
+        stream: &mut S,
+        mut file: &mut dyn ReceiveSink,
+        spec: &TransferSpec,
         resume_from: u64,
+        handle: &mut TransferHandle,
         progress_tx: Option<mpsc::UnboundedSender<TransferProgress>>,
-    ) -> Result<()>
+    ) -> Result<String>
     where
         S: AsyncReadExt + AsyncWriteExt + Unpin,
     {
-        // 파일 열기 (이어받기 지원)
-        let mut file = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .open(file_path)
-            .with_context(|| format!("Failed to open file: {}", file_path))?;
+        let TransferSpec { ref transfer_id, ref file_path, file_size, total_chunks, chunk_size, chunk_hash_algo, ack_interval, ref dedup, retransmit, .. } = *spec;
+        #[cfg(any(test, feature = "fault-injection"))]
+        let fault = fault::receiver_plan();
+
+        // 받는 대로 파일 해시를 계산해 완료 시 다시 읽지 않고 검증
+        let mut running_hash = Some(integrity::RunningHash::new());
+
+        // 구멍은 쓰지 않고 건너뛰므로 받을 위치 뒤에 남은 이전 내용을 지움
+        file.set_len(resume_from * chunk_size)?;
+
+        // 이어받기 위치로 이동
+        if resume_from > 0 {
+            let offset = resume_from * chunk_size;
+            // 이미 받은 앞부분을 해시 (호스트 저장소의 쓰기 전용 파일은 읽을 수 없음)
+            running_hash = file
+                .seek(SeekFrom::Start(0))
+                .map_err(anyhow::Error::from)
+                .and_then(|_| integrity::RunningHash::with_prefix(&mut file, offset))
+                .inspect_err(|e| tracing::warn!("Cannot hash already received data of {}: {:#}", file_path, e))
+                .ok();
+            file.seek(SeekFrom::Start(offset))?;
+            tracing::info!("Resuming from offset {}", offset);
+        }
+
+        let mut received_chunks = resume_from;
+        // 실제로 파일에 쓴 바이트 수 (구멍 포함, 선언한 크기를 넘으면 중단)
+        let mut written = (resume_from * chunk_size).min(file_size);
+        // 아직 ACK를 보내지 않은 첫 청크
+        let mut unacked_from = resume_from;
+        let mut completed = false;
+        let start_time = SystemTime::now();
+
+        // 재전송: 해시가 맞지 않은 청크와 청크별 요청 횟수
+        let mut mismatched = None;
+        let mut retries = HashMap::new();
+        // 다시 요청한 청크가 올 때까지 요청 전에 보낸 청크는 버림
+        let mut resending = false;
+        // ACK하지 않은 청크마다 쓰기 전의 파일 해시 (다시 받을 청크부터 해시를 이어 계산)
+        let mut checkpoints: VecDeque<(u64, Option<integrity::RunningHash>)> = VecDeque::new();
+
+        // 청크 해시는 작업자가 검증하고, 검증을 마친 청크만 중복 제거 저장소에 기록
+        let mut verifier = ChunkVerifier::new(chunk_hash_algo);
+        let remember = |chunk_index: u64, data: &[u8]| {
+            if let Some(plan) = dedup {
+                plan.remember(chunk_index, data);
+            }
+        };
+
+        // 청크 수신 루프
+        while received_chunks < total_chunks {
+            // 일시정지/취소 요청 반영 (일시정지 중에는 읽지 않으므로 송신측도 멈춤)
+            handle.checkpoint().await?;
+
+            // 저장소에 있는 청크는 송신측이 보내지 않으므로 저장소에서 채움
+            let stored = match dedup {
+                Some(plan) => plan.read_present(received_chunks, file_size, chunk_size)?,
+                None => None,
+            };
+
+            // 구멍(`ChunkHole`)이면 데이터가 None
+            let (chunk_index, data) = match stored {
+                Some(data) => (received_chunks, Some(Bytes::from(data))),
+                None => match TransferMessage::from_stream(stream).await? {
+                    TransferMessage::ChunkData {
+                        chunk_index,
+                        chunk_hash,
+                        data,
+                        ..
+                    } => {
+                        // 순서대로 쓰므로 다음 청크가 아니면 프로토콜 에러
+                        if chunk_index != received_chunks {
+                            if resending && chunk_index > received_chunks {
+                                continue;
+                            }
+                            return Err(PebbleError::protocol(format!(
+                                "Expected chunk {}, got {}", received_chunks, chunk_index
+                            )).into());
+                        }
+                        resending = false;
+
+                        // 청크 크기보다 크거나 마지막이 아닌데 짧은 청크는 선언한 크기를 벗어남
+                        let length = data.len() as u64;
+                        if length > chunk_size || (chunk_index + 1 < total_chunks && length < chunk_size) {
+                            return Err(PebbleError::protocol(format!(
+                                "Chunk {} has {} bytes, expected {} per chunk", chunk_index, length, chunk_size
+                            )).into());
+                        }
+
+                        // 청크 해시 검증 (쓰기와 다음 청크 읽기와 겹치도록 작업자에게 맡김)
+                        let data = Bytes::from(data);
+                        let verified = verifier.submit(chunk_index, data.clone(), chunk_hash, remember).await;
+                        mismatched = Self::retry_mismatch(verified, retransmit, &mut retries)?;
+                        (chunk_index, Some(data))
+                    }
+                    TransferMessage::ChunkHole { chunk_index, .. } => {
+                        if chunk_index != received_chunks {
+                            if resending && chunk_index > received_chunks {
+                                continue;
+                            }
+                            return Err(PebbleError::protocol(format!(
+                                "Expected chunk {}, got hole {}", received_chunks, chunk_index
+                            )).into());
+                        }
+                        resending = false;
+                        (chunk_index, None)
+                    }
+                    TransferMessage::TransferComplete { .. } => {
+                        tracing::info!("Transfer completed");
+                        completed = true;
+                        break;
+                    }
+                    msg => {
+                        tracing::warn!("Unexpected message: {:?}", msg);
+                        continue;
+                    }
+                },
+            };
+
+            if retransmit {
+                checkpoints.push_back((chunk_index, running_hash.clone()));
+            }
+
+            // 파일에 쓰기 (구멍은 건너뛰어 희소 파일로 남김)
+            let length = match &data {
+                Some(data) => data.len() as u64,
+                None => chunk_size.min(file_size.saturating_sub(chunk_index * chunk_size)),
+            };
+            written += length;
+            if written > file_size {
+                return Err(PebbleError::protocol(format!(
+                    "{} exceeds the declared size of {} bytes", file_path, file_size
+                )).into());
+            }
+            // 구멍과 저장소에서 채운 청크도 받은 파일 바이트로 기록
+            metrics::add_bytes_received(length);
+            match data {
+                Some(data) => {
+                    file.write_all(&data)?;
+                    if let Some(running_hash) = running_hash.as_mut() {
+                        running_hash.update(&data);
+                    }
+                }
+                None => {
+                    file.seek(SeekFrom::Current(length as i64))?;
+                    if let Some(running_hash) = running_hash.as_mut() {
+                        running_hash.update_zeroes(length);
+                    }
+                }
+            }
+
+            received_chunks += 1;
+
+            // 청크 확인 전송 (묶음 ACK면 간격마다, 마지막 청크에서 남은 분량까지)
+            let ack_due = received_chunks - unacked_from >= ack_interval || received_chunks == total_chunks;
+            if ack_due && mismatched.is_none() {
+                // ACK한 청크는 이어받기에서 다시 받지 않으므로 검증이 끝난 뒤에 ACK
+                let verified = verifier.settle(received_chunks, remember).await;
+                mismatched = Self::retry_mismatch(verified, retransmit, &mut retries)?;
+            }
+            if ack_due && mismatched.is_none() {
+                #[cfg(any(test, feature = "fault-injection"))]
+                fault.delay_ack().await;
+                let ack_msg = if ack_interval > 1 {
+                    TransferMessage::ChunkAcks {
+                        transfer_id: transfer_id.to_string(),
+                        ranges: vec![AckRange { start: unacked_from, end: received_chunks }],
+                    }
+                } else {
+                    TransferMessage::ChunkAck {
+                        transfer_id: transfer_id.to_string(),
+                        chunk_index,
+                    }
+                };
+                stream.write_all(&ack_msg.to_bytes()?).await?;
+
+                // DB 업데이트 (이어받기는 ACK한 청크부터 다시 받음)
+                Self::update_transfer_state(spec, received_chunks)?;
+                unacked_from = received_chunks;
+                checkpoints.clear();
+            }
+
+            // 해시가 맞지 않은 청크부터 지우고 다시 요청 (ACK하지 않은 청크이므로 항상 `unacked_from` 이후)
+            if let Some(bad) = mismatched.take() {
+                tracing::warn!("Chunk {} of {} failed verification, requesting it again", bad, file_path);
+                verifier.discard();
+                let offset = bad * chunk_size;
+                file.set_len(offset)?;
+                file.seek(SeekFrom::Start(offset))?;
+                running_hash = checkpoints
+                    .iter()
+                    .find(|(chunk_index, _)| *chunk_index == bad)
+                    .and_then(|(_, hash)| hash.clone());
+                checkpoints.clear();
+
+                let nack_msg = TransferMessage::ChunkNack {
+                    transfer_id: transfer_id.to_string(),
+                    chunk_index: bad,
+                };
+                stream.write_all(&nack_msg.to_bytes()?).await?;
+                received_chunks = bad;
+                written = offset;
+                resending = true;
+                continue;
+            }
+
+            #[cfg(any(test, feature = "fault-injection"))]
+            fault.check_drop(received_chunks - resume_from)?;
+
+            handle.set_progress(written);
+
+            // 진행률 전송
+            if let Some(ref tx) = progress_tx {
+                let elapsed = start_time.elapsed().unwrap_or(Duration::from_secs(1));
+                let transfer_rate = (written as f64 / elapsed.as_secs_f64()) / 1_000_000.0;
+
+                let progress = TransferProgress {
+                    transfer_id: transfer_id.to_string(),
+                    file_path: file_path.to_string(),
+                    total_chunks,
+                    completed_chunks: received_chunks,
+                    progress_percent: (written as f64 / file_size as f64) * 100.0,
+                    bytes_transferred: written,
+                    total_bytes: file_size,
+                    transfer_rate_mbps: transfer_rate,
+                };
+
+                let _ = tx.send(progress);
+            }
+
+            tracing::debug!("Received chunk {}/{} ({:.1}%)",
+                received_chunks, total_chunks,
+                (received_chunks as f64 / total_chunks as f64) * 100.0);
+        }
+
+        // 마지막 청크가 짧으면 선언한 크기보다 작게 받음
+        if received_chunks == total_chunks && written != file_size {
+            return Err(PebbleError::protocol(format!(
+                "Received {} of {} bytes of {}", written, file_size, file_path
+            )).into());
+        }
+
+        // 마지막 청크 이후의 완료 메시지까지 읽은 뒤 연결을 닫음
+        // (먼저 닫으면 송신측의 완료 메시지 쓰기가 실패할 수 있음)
+        if !completed {
+            match TransferMessage::from_stream(stream).await? {
+                TransferMessage::TransferComplete { .. } => tracing::info!("Transfer completed"),
+                other => tracing::warn!("Expected TransferComplete, got {:?}", other),
+            }
+        }
+        verifier.settle(received_chunks, remember).await?;
+
+        // 끝부분이 구멍이면 건너뛴 만큼 파일 크기를 맞춤
+        let end = file.stream_position()?;
+        if received_chunks == total_chunks && end > file.size()? {
+            file.set_len(end)?;
+        }
+        file.flush()?;
+
+        tracing::info!("File received successfully: {}", file_path);
+
+        Self::finalize_hash(file, running_hash, file_size).inspect_err(|_| {
+            // 다음 시도는 처음부터 받아 받는 동안 해시를 계산
+            let _ = Self::update_transfer_state(spec, 0);
+        })
+    }
+
+    /// 받은 파일의 해시를 마무리합니다.
+    ///
+    /// 이어받은 앞부분을 읽지 못해 받는 동안 해시를 계산하지 못했으면 받은 파일 전체를 다시 읽어 해시합니다.
+    /// 그것도 읽을 수 없으면 파일 해시를 검증할 수 없으므로 청크 해시만 믿고 받아들이지 않습니다.
+    fn finalize_hash(
+        mut file: &mut dyn ReceiveSink,
+        running_hash: Option<integrity::RunningHash>,
+        file_size: u64,
+    ) -> Result<String> {
+        if let Some(running_hash) = running_hash {
+            return Ok(running_hash.finalize());
+        }
+        let running_hash = file
+            .seek(SeekFrom::Start(0))
+            .map_err(anyhow::Error::from)
+            .and_then(|_| integrity::RunningHash::with_prefix(&mut file, file_size))
+            .map_err(|e| PebbleError::io(format!("Cannot read the received file to verify its hash: {:#}", e)))?;
+        Ok(running_hash.finalize())
+    }
+
+    /// 청크 검증 결과에서 다시 요청할 청크를 찾습니다.
+    ///
+    /// # Arguments
+    /// * `retries` - 청크별 재전송 요청 횟수 (다시 요청하면 늘어남)
+    ///
+    /// # Returns
+    /// * 해시가 맞지 않아 다시 요청할 청크 (재전송을 쓰지 않거나 횟수를 넘었으면 원래 에러)
+    fn retry_mismatch(verified: Result<()>, retransmit: bool, retries: &mut HashMap<u64, u32>) -> Result<Option<u64>> {
+        let Err(e) = verified else {
+            return Ok(None);
+        };
+        let Some(&ChunkMismatch { chunk_index }) = e.downcast_ref::<ChunkMismatch>() else {
+            return Err(e);
+        };
+        if !retransmit
+            || retries.get(&chunk_index).is_some_and(|count| *count >= MAX_CHUNK_RETRIES)
+            || retries.values().sum::<u32>() >= MAX_TRANSFER_RETRIES
+        {
+            return Err(e);
+        }
+
+        *retries.entry(chunk_index).or_insert(0) += 1;
+        Ok(Some(chunk_index))
+    }
+
+    /// 요청에 담겨 온 작은 파일의 내용을 저장합니다.
+    ///
+    /// # Returns
+    /// * 받은 내용의 파일 해시
+    fn receive_inline(file: &mut dyn ReceiveSink, data: &[u8], handle: &mut TransferHandle) -> Result<String> {
+        file.set_len(0)?;
+        file.write_all(data)?;
+        file.flush()?;
+
+        metrics::add_bytes_received(data.len() as u64);
+        handle.set_progress(data.len() as u64);
+
+        let mut running_hash = integrity::RunningHash::new();
+        running_hash.update(data);
+        Ok(running_hash.finalize())
+    }
+
+    /// 송신측의 청크 해시 목록을 받아 저장소와 대조하고, 보내야 할 청크 범위를 응답합니다.
+    ///
+    /// # Arguments
+    /// * `first` - 이어받기 위치 (목록은 이 청크부터)
+    /// * `total_chunks` - 전체 청크 수
+    async fn receive_manifest<S>(
+        stream: &mut S,
+        transfer_id: &str,
+        store: ChunkStore,
+        first: u64,
+        total_chunks: u64,
+        file_size: u64,
+        chunk_size: u64,
+    ) -> Result<DedupPlan>
+    where
+        S: AsyncReadExt + AsyncWriteExt + Unpin,
+    {
+        let expected = total_chunks - first;
+        let mut hashes = Vec::with_capacity(expected.min(dedup::MANIFEST_PAGE_CHUNKS as u64) as usize);
+
+        while (hashes.len() as u64) < expected {
+            match TransferMessage::from_stream(stream).await? {
+                TransferMessage::ChunkManifest { first_chunk, chunk_hashes, .. }
+                    if first_chunk == first + hashes.len() as u64
+                        && chunk_hashes.len() as u64 <= expected - hashes.len() as u64 =>
+                {
+                    hashes.extend(chunk_hashes);
+                }
+                other => {
+                    return Err(PebbleError::protocol(format!(
+                        "Expected ChunkManifest from chunk {}, got {:?}", first + hashes.len() as u64, other
+                    )).into());
+                }
+            }
+        }
+
+        let plan = DedupPlan::new(store, first, hashes, file_size, chunk_size);
+        let needs = TransferMessage::ChunkNeeds {
+            transfer_id: transfer_id.to_string(),
+            ranges: plan.needed_ranges(),
+        };
+        stream.write_all(&needs.to_bytes()?).await?;
+
+        tracing::info!("Dedup store has {} of {} chunks", plan.present_count(), expected);
+
+        Ok(plan)
+    }
+
+    /// 수신을 마친 전송의 상태를 완료로 표시합니다 (미완성 파일 정리에서 제외).
+    fn complete_transfer_state(transfer_id: &str) {
+        let result = db::open_connection().and_then(|conn| {
+            conn.execute(
+                "UPDATE transfer_state SET transfer_status = ?2 WHERE transfer_id = ?1",
+                params![transfer_id, TransferStatus::Completed.to_string()],
+            )
+        });
+        if let Err(e) = result {
+            tracing::warn!("Failed to mark transfer state {} completed: {}", transfer_id, e);
+        }
+    }
+
+    /// 전송 상태를 DB에 업데이트합니다.
+    fn update_transfer_state(spec: &TransferSpec, received_chunks: u64) -> Result<()> {
+        let conn = db::open_connection()?;
+
+        let now = unix_now();
+
+        conn.execute(
+            "INSERT OR REPLACE INTO transfer_state
+             (transfer_id, file_path, file_size, total_chunks, received_chunks, transfer_status, peer_device_id, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+             ON CONFLICT(transfer_id) DO UPDATE SET
+                file_path = excluded.file_path,
+                received_chunks = excluded.received_chunks,
+                transfer_status = excluded.transfer_status,
+                peer_device_id = excluded.peer_device_id,
+                updated_at = excluded.updated_at",
+            params![
+                spec.transfer_id,
+                spec.file_path,
+                0i64,
+                0i64,
+                received_chunks as i64,
+                TransferStatus::InProgress.to_string(),
+                spec.sender_id,
+                now,
+                now
+            ],
+        )?;
+
+        Ok(())
+    }
+}
+
+/// 폴더 전송에서 보내지 못한 파일
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FailedFile {
+    /// 보낸 폴더 기준의 상대 경로
+    pub relative_path: String,
+    pub error: String,
+}
+
+/// 폴더 전송 결과
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DirectoryTransferReport {
+    /// 보낸 파일의 상대 경로 (보낸 순서)
+    pub sent: Vec<String>,
+
+    /// 보낸 파일의 전체 크기 (bytes)
+    pub sent_bytes: u64,
+
+    /// 보내지 못한 파일 (거부, 읽기 실패 등)
+    pub failed: Vec<FailedFile>,
+}
+
+/// 폴더 전송에서 파일 하나를 보내지 못한 이유
+enum FolderSendError {
+    /// 수신 기기에 연결하지 못함 (남은 파일도 보낼 수 없음)
+    Connect(anyhow::Error),
+    /// 이 파일만 실패함
+    File(anyhow::Error),
+}
+
+/// 폴더 전송에서 보낼 파일의 상대 경로 (`/` 구분, 보낸 폴더 이름 포함)
+fn folder_relative_path(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    root.file_name()
+        .map(Path::new)
+        .into_iter()
+        .chain(std::iter::once(relative))
+        .flat_map(|part| part.components())
+        .map(|component| component.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// 파일 전송 클라이언트
+///
+/// TLS로 암호화된 TCP 연결을 통해 파일을 송신합니다.
+pub struct TransferClient {
+    server_fingerprint: Option<String>,
+    progress_tx: Option<mpsc::UnboundedSender<TransferProgress>>,
+    #[cfg(any(test, feature = "fault-injection"))]
+    fault: FaultPlan,
+    /// 동기화 쌍의 전송 일정 (None이면 전역 설정의 일정)
+    schedule: Option<Vec<ScheduleWindow>>,
+    /// 송신 우선순위 (더 높은 우선순위의 송신이 있으면 청크 전송을 멈춤)
+    priority: TransferPriority,
+    /// 데이터 사용 한도에 도달해도 보낼지 여부 (사용자가 직접 허용한 전송)
+    cap_override: bool,
+    /// 수신 기기 ID (알면 주소나 인증서 지문 대신 전송 ID 계산에 사용)
+    target_device_id: Option<String>,
+}
+
+impl TransferClient {
+    /// 새로운 전송 클라이언트를 생성합니다.
+    pub fn new(server_fingerprint: Option<String>) -> Self {
+        Self {
+            server_fingerprint,
+            progress_tx: None,
+            #[cfg(any(test, feature = "fault-injection"))]
+            fault: FaultPlan::default(),
+            schedule: None,
+            priority: TransferPriority::default(),
+            cap_override: false,
+            target_device_id: None,
+        }
+    }
+
+    /// 진행률 채널을 설정합니다.
+    pub fn set_progress_channel(&mut self, tx: mpsc::UnboundedSender<TransferProgress>) {
+        self.progress_tx = Some(tx);
+    }
+
+    /// 전역 설정 대신 사용할 전송 일정을 설정합니다.
+    pub fn set_schedule(&mut self, schedule: Option<Vec<ScheduleWindow>>) {
+        self.schedule = schedule;
+    }
+
+    /// 송신 우선순위를 설정합니다.
+    pub fn set_priority(&mut self, priority: TransferPriority) {
+        self.priority = priority;
+    }
+
+    /// 데이터 사용 한도(`config::data_caps`)를 무시하고 보낼지 설정합니다.
+    pub fn set_cap_override(&mut self, cap_override: bool) {
+        self.cap_override = cap_override;
+    }
+
+    /// 장애 주입 계획을 설정합니다 (테스트 전용).
+    #[cfg(any(test, feature = "fault-injection"))]
+    pub fn set_fault_plan(&mut self, plan: FaultPlan) {
+        self.fault = plan;
+    }
+
+    /// 수신 기기 ID를 설정합니다.
+    ///
+    /// 기기의 주소가 바뀌거나 앱을 다시 시작한 뒤 다시 보내도 같은 전송 ID가 되어 이어받습니다.
+    pub fn set_target_device(&mut self, device_id: impl Into<String>) {
+        self.target_device_id = Some(device_id.into());
+    }
+
+    /// 파일을 전송합니다.
+    ///
+    /// 수신측에 같은 내용의 파일이 이미 있으면 청크를 보내지 않고 바로 완료합니다.
+    pub async fn send_file(
+        &self,
+        server_addr: SocketAddr,
+        file_path: &str,
+    ) -> Result<()> {
+        self.send_file_any(&[server_addr], file_path).await.map(|_| ())
+    }
+
+    /// 여러 주소 중 연결되는 주소로 파일을 전송합니다.
+    ///
+    /// 풀에 살아 있는 연결이 있는 주소를 먼저 쓰고, 없으면 `connect_first`로
+    /// 주소들에 동시에 연결을 시도합니다.
+    ///
+    /// # Arguments
+    /// * `addrs` - 수신 기기 주소 (우선순위 순)
+    /// * `file_path` - 전송할 파일 경로
+    ///
+    /// # Returns
+    /// * `Result<SocketAddr>` - 전송에 사용한 주소
+    pub async fn send_file_any(&self, addrs: &[SocketAddr], file_path: &str) -> Result<SocketAddr> {
+        let (spec, file_hash) = Self::prepare(file_path, &self.target_id_any(addrs), None)?;
+        let (server_addr, mut tls_stream) = self.checkout_any(addrs).await?;
+        let _connection = metrics::track_connection();
+
+        // 작은 파일은 중복 검사 왕복보다 요청에 담아 보내는 편이 빠름
+        let query = if spec.file_size <= INLINE_FILE_MAX {
+            Ok(false)
+        } else {
+            Self::query_present(&mut tls_stream, &file_hash, spec.file_size).await
+        };
+        match query {
+            Ok(true) => {
+                self.complete_already_present(&server_addr.to_string(), &spec);
+                self.checkin(server_addr, tls_stream);
+                return Ok(server_addr);
+            }
+            Ok(false) => {}
+            Err(e) => {
+                // 중복 검사를 모르는 구버전은 연결을 닫으므로 다시 연결
+                tracing::debug!("Hash query to {} failed, sending without it: {:#}", server_addr, e);
+                tls_stream = self.connect(server_addr).await?;
+            }
+        }
+
+        self.send_prepared(&mut tls_stream, &server_addr.to_string(), &spec, &file_hash).await?;
+        self.checkin(server_addr, tls_stream);
+
+        Ok(server_addr)
+    }
+
+    /// 폴더 아래의 모든 파일을 하위 폴더 구조를 유지한 채 전송합니다.
+    ///
+    /// 파일마다 보낸 폴더 이름을 포함한 상대 경로(`Photos/2024/a.jpg`)를 요청에 담아, 수신측이
+    /// 받을 폴더 아래에 같은 구조로 저장하게 합니다. 파일은 이름 순으로 하나의 연결에서 차례로
+    /// 보내며, 수신측이 거부한 파일은 기록하고 새로 연결해 다음 파일을 보냅니다.
+    /// 빈 폴더와 심볼릭 링크는 보내지 않습니다.
+    ///
+    /// 다른 경로에 같은 내용이 있어도 이 경로에 만들어야 하므로 중복 검사(`HashQuery`)는 하지 않습니다
+    /// (바뀌지 않은 청크는 청크 해시 목록으로 생략됨).
+    ///
+    /// # Arguments
+    /// * `addrs` - 수신 기기 주소 (우선순위 순)
+    /// * `dir_path` - 전송할 폴더 경로
+    ///
+    /// # Returns
+    /// * 보낸 파일과 보내지 못한 파일 (연결하지 못하면 오류)
+    pub async fn send_directory(&self, addrs: &[SocketAddr], dir_path: &str) -> Result<DirectoryTransferReport> {
+        let root = Path::new(dir_path);
+        if !paths::long_path(root).is_dir() {
+            return Err(PebbleError::invalid_argument(format!("Not a directory: {}", dir_path)).into());
+        }
+
+        let mut report = DirectoryTransferReport::default();
+        let mut connection = None;
+        let _connection = metrics::track_connection();
+
+        for entry in WalkDir::new(root).follow_links(false).sort_by_file_name() {
+            let entry = match entry {
+                Ok(entry) if entry.file_type().is_file() => entry,
+                Ok(_) => continue,
+                Err(e) => {
+                    let path = e.path().map(|path| folder_relative_path(root, path)).unwrap_or_default();
+                    report.failed.push(FailedFile { relative_path: path, error: e.to_string() });
+                    continue;
+                }
+            };
+            let relative_path = folder_relative_path(root, entry.path());
+
+            match self.send_in_folder(addrs, &mut connection, &entry.path().to_string_lossy(), &relative_path).await {
+                Ok(file_size) => {
+                    report.sent.push(relative_path);
+                    report.sent_bytes += file_size;
+                }
+                // 연결하지 못하면 남은 파일도 보낼 수 없음
+                Err(FolderSendError::Connect(e)) => return Err(e),
+                Err(FolderSendError::File(e)) => {
+                    tracing::warn!("Failed to send {} in folder {}: {:#}", relative_path, dir_path, e);
+                    report.failed.push(FailedFile { relative_path, error: format!("{:#}", e) });
+                }
+            }
+        }
+
+        if let Some((server_addr, stream)) = connection {
+            self.checkin(server_addr, stream);
+        }
+        tracing::info!("Sent folder {}: {} files ({} bytes), {} failed",
+            dir_path, report.sent.len(), report.sent_bytes, report.failed.len());
+        Ok(report)
+    }
+
+    /// 폴더 전송의 파일 하나를 열려 있는 연결(없으면 새 연결)로 보냅니다.
+    ///
+    /// 실패한 요청 뒤에는 수신측이 연결을 닫으므로 성공했을 때만 연결을 남깁니다.
+    async fn send_in_folder(
+        &self,
+        addrs: &[SocketAddr],
+        connection: &mut Option<(SocketAddr, PooledStream)>,
+        file_path: &str,
+        relative_path: &str,
+    ) -> std::result::Result<u64, FolderSendError> {
+        let (spec, file_hash) = Self::prepare(file_path, &self.target_id_any(addrs), Some(relative_path))
+            .map_err(FolderSendError::File)?;
+
+        let (server_addr, mut stream) = match connection.take() {
+            Some(open) => open,
+            None => self.checkout_any(addrs).await.map_err(FolderSendError::Connect)?,
+        };
+        self.send_prepared(&mut stream, &server_addr.to_string(), &spec, &file_hash)
+            .await
+            .map_err(FolderSendError::File)?;
+
+        *connection = Some((server_addr, stream));
+        Ok(spec.file_size)
+    }
+
+    /// 이미 연결된 스트림으로 파일을 전송합니다.
+    ///
+    /// TLS 여부와 무관하게 동작하므로 테스트에서 메모리 스트림(tokio duplex)으로
+    /// 프로토콜을 검증할 때 사용합니다.
+    ///
+    /// # Arguments
+    /// * `stream` - 연결된 스트림
+    /// * `peer` - 상대 기기 표시용 이름 (레지스트리, 로그용)
+    /// * `file_path` - 전송할 파일 경로
+    pub async fn send_file_over<S>(&self, mut stream: S, peer: &str, file_path: &str) -> Result<()>
+    where
+        S: AsyncReadExt + AsyncWriteExt + Unpin,
+    {
+        let (spec, file_hash) = Self::prepare(file_path, &self.target_id(peer), None)?;
+        self.send_prepared(&mut stream, peer, &spec, &file_hash).await
+    }
+
+    /// 전송 ID에 쓰는 수신 기기 식별자 (설정한 기기 ID, 고정한 인증서 지문, 둘 다 없으면 `sent_peer_id`)
+    fn target_id(&self, peer: &str) -> String {
+        self.target_device_id
+            .clone()
+            .or_else(|| self.server_fingerprint.clone())
+            .unwrap_or_else(|| sent_peer_id(peer))
+    }
+
+    /// 여러 주소로 보낼 때의 수신 기기 식별자 (첫 주소 기준)
+    fn target_id_any(&self, addrs: &[SocketAddr]) -> String {
+        self.target_id(&addrs.first().map(SocketAddr::to_string).unwrap_or_default())
+    }
+
+    /// 보낼 파일의 확장 속성을 읽습니다 (`preserve_xattrs`가 꺼져 있거나 읽지 못하면 빈 목록).
+    fn read_xattrs(file_path: &str) -> Vec<ExtendedAttribute> {
+        if !config::current().preserve_xattrs {
+            return Vec::new();
+        }
+        xattrs::read(&paths::long_path(file_path)).unwrap_or_else(|e| {
+            tracing::warn!("Failed to read extended attributes of {}: {:#}", file_path, e);
+            Vec::new()
+        })
+    }
+
+    /// 전송 파라미터와 파일 해시를 준비합니다.
+    ///
+    /// # Arguments
+    /// * `target` - 수신 기기 식별자 (전송 ID 계산용)
+    /// * `relative_path` - 폴더 전송의 상대 경로
+    fn prepare(file_path: &str, target: &str, relative_path: Option<&str>) -> Result<(TransferSpec, String)> {
+        // 파일 정보 가져오기
+        let file_metadata = std::fs::metadata(paths::long_path(file_path))
+            .with_context(|| format!("Failed to get file metadata: {}", file_path))?;
+
+        let file_size = file_metadata.len();
+        let chunk_size = config::current().chunk_size;
+        let total_chunks = file_size.div_ceil(chunk_size);
+
+        // 파일 해시 계산
+        let file_hash = integrity::calculate_file_hash(file_path)?;
+
+        let spec = TransferSpec {
+            // 다시 보내면 수신측이 이어받도록 파일과 송수신 기기로 정함
+            transfer_id: stable_transfer_id(
+                &file_hash,
+                file_size,
+                chunk_size,
+                &service::device_id().unwrap_or_default(),
+                target,
+                relative_path,
+            ),
+            file_path: file_path.to_string(),
+            file_size,
+            total_chunks,
+            chunk_size,
+            // 수신측이 수락하면서 확정
+            chunk_hash_algo: HashAlgo::default(),
+            ack_interval: 1,
+            dedup: None,
+            sparse: false,
+            retransmit: true,
+            relative_path: relative_path.map(str::to_string),
+            sender_id: String::new(),
+        };
+
+        Ok((spec, file_hash))
+    }
+
+    /// 전송 요청부터 완료 메시지까지 송신 프로토콜을 수행합니다.
+    #[tracing::instrument(
+        name = "transfer",
+        skip_all,
+        fields(
+            direction = "send",
+            peer = %peer,
+            transfer_id = %spec.transfer_id,
+            chunk_hash = tracing::field::Empty
+        )
+    )]
+    async fn send_prepared<S>(&self, stream: &mut S, peer: &str, spec: &TransferSpec, file_hash: &str) -> Result<()>
+    where
+        S: AsyncReadExt + AsyncWriteExt + Unpin,
+    {
+        // 기기별 송신량 집계 (주소를 아는 기기는 기기 ID로, 모르면 IP로)
+        let peer_id = sent_peer_id(peer);
+        if !self.cap_override {
+            bandwidth::check_caps(&peer_id)?;
+        }
+
+        let mut handle = registry::global().register(
+            &spec.transfer_id,
+            peer,
+            &spec.file_path,
+            TransferDirection::Send,
+            spec.file_size,
+        )?;
+        handle.set_priority(self.priority);
+        let _slot = priority::begin(self.priority);
+
+        tracing::info!("Starting file transfer: {} ({} bytes, {} chunks)",
+            spec.file_path, spec.file_size, spec.total_chunks);
+
+        let (result, receipt) = match self.send_registered(stream, spec, file_hash, &mut handle).await {
+            Ok(receipt) => (Ok(()), receipt),
+            Err(e) => (Err(e), None),
+        };
+        if let Err(e) = &result {
+            handle.set_error(e);
+        }
+
+        if let Err(e) = quota::record_sent(&peer_id, handle.bytes_transferred(), result.is_ok()) {
+            tracing::warn!("Failed to record usage for {}: {:#}", peer_id, e);
+        }
+        if let Err(e) = bandwidth::record(&peer_id, handle.bytes_transferred(), 0) {
+            tracing::warn!("Failed to record bandwidth for {}: {:#}", peer_id, e);
+        }
+        TransferServer::record_history(&handle, &result);
+        if let Some(receipt) = receipt {
+            self.store_receipt(&receipt, spec, file_hash);
+        }
+
+        result
+    }
+
+    /// 수신측의 수령증을 확인하고 전송 기록에 남깁니다 (확인되지 않으면 버림).
+    fn store_receipt(&self, receipt: &TransferReceipt, spec: &TransferSpec, file_hash: &str) {
+        let checked = receipt.check(&spec.transfer_id, file_hash, spec.file_size, self.server_fingerprint.as_deref());
+        if let Err(e) = checked.and_then(|()| history::set_receipt(receipt)) {
+            tracing::warn!("Discarding receipt for {}: {:#}", spec.transfer_id, e);
+        }
+    }
+
+    /// 레지스트리에 등록된 전송의 요청/청크/완료 메시지를 주고받습니다.
+    ///
+    /// # Returns
+    /// * 수신측이 서명한 수령증 (아직 확인하지 않음)
+    async fn send_registered<S>(
+        &self,
+        stream: &mut S,
+        spec: &TransferSpec,
+        file_hash: &str,
+        handle: &mut TransferHandle,
+    ) -> Result<Option<TransferReceipt>>
+    where
+        S: AsyncReadExt + AsyncWriteExt + Unpin,
+    {
+
+        // 작은 파일은 내용을 요청에 담음 (읽지 못하면 청크로 보냄)
+        let inline_data = if spec.file_size <= INLINE_FILE_MAX {
+            std::fs::read(paths::long_path(&spec.file_path))
+                .inspect_err(|e| tracing::debug!("Cannot read {} for inline transfer: {}", spec.file_path, e))
+                .ok()
+                .filter(|data| data.len() as u64 == spec.file_size)
+        } else {
+            None
+        };
+
+        // 전송 요청 전송
+        let request_msg = TransferMessage::TransferRequest {
+            transfer_id: spec.transfer_id.clone(),
+            file_path: spec.file_path.clone(),
+            file_size: spec.file_size,
+            file_hash: file_hash.to_string(),
+            total_chunks: spec.total_chunks,
+            chunk_size: spec.chunk_size,
+            chunk_hash_algos: HashAlgo::chunk_preferences(config::current().fast_chunk_hash),
+            sender_device_id: service::device_id(),
+            ack_ranges: true,
+            chunk_manifest: true,
+            inline_data,
+            xattrs: Self::read_xattrs(&spec.file_path),
+            verdict: true,
+            crc32c: true,
+            retransmit: true,
+            relative_path: spec.relative_path.clone(),
+        };
+
+        stream.write_all(&request_msg.to_bytes()?).await?;
+
+        // 전송 수락 대기
+        let response = TransferMessage::from_stream(stream).await?;
+
+        let (resume_from_chunk, chunk_hash_algo, ack_interval, want_manifest, sparse, verdict) = match response {
+            TransferMessage::TransferAccept { resume_from_chunk, chunk_hash_algo, ack_interval, want_manifest, sparse, verdict, .. } => {
+                tracing::info!("Transfer accepted. Resuming from chunk {} (chunk hash: {}, ACK every {} chunks)",
+                    resume_from_chunk, chunk_hash_algo.name(), ack_interval);
+                (resume_from_chunk, chunk_hash_algo, ack_interval.max(1), want_manifest, sparse, verdict)
+            }
+            TransferMessage::TransferReject { reason, code, .. } => {
+                return Err(reject_error(reason, code).into());
+            }
+            // 요청에 담은 내용을 수신측이 바로 저장함
+            TransferMessage::TransferComplete { receipt, .. } => {
+                metrics::add_bytes_sent(spec.file_size);
+                handle.set_progress(spec.file_size);
+                handle.set_status(TransferStatus::Completed);
+                tracing::info!("File transfer completed inline");
+                return Ok(receipt);
+            }
+            TransferMessage::Error { message, .. } => {
+                return Err(PebbleError::io(format!("Receiver failed to store {}: {}", spec.file_path, message)).into());
+            }
+            _ => {
+                return Err(PebbleError::protocol("Expected TransferAccept or TransferReject").into());
+            }
+        };
 
-        // 이어받기 위치로 이동
-        if resume_from > 0 {
-            let offset = resume_from * CHUNK_SIZE as u64;
-            file.seek(SeekFrom::Start(offset))?;
-            log::info!("Resuming from offset {}", offset);
+        if resume_from_chunk > spec.total_chunks {
+            return Err(PebbleError::protocol(format!(
+                "Resume chunk {} exceeds total chunks {}", resume_from_chunk, spec.total_chunks
+            )).into());
         }
 
-        let mut received_chunks = resume_from;
-        let start_time = SystemTime::now();
+        tracing::Span::current().record("chunk_hash", chunk_hash_algo.name());
+        handle.set_chunk_hash_algo(chunk_hash_algo);
+        // 수신측이 중복 제거를 쓰면 해시 목록을 보내고 필요한 청크만 전송
+        let dedup = if want_manifest && resume_from_chunk < spec.total_chunks {
+            Some(Self::send_manifest(stream, spec, resume_from_chunk).await?)
+        } else {
+            None
+        };
 
-        // 청크 수신 루프
-        while received_chunks < total_chunks {
-            let msg = TransferMessage::from_stream(stream).await?;
+        let spec = &TransferSpec { chunk_hash_algo, ack_interval, dedup, sparse, ..spec.clone() };
 
-            match msg {
-                TransferMessage::ChunkData {
-                    chunk_index,
-                    chunk_hash,
-                    data,
-                    ..
-                } => {
-                    // 청크 해시 검증
-                    let computed_hash = {
-                        use sha2::{Digest, Sha256};
-                        let mut hasher = Sha256::new();
-                        hasher.update(&data);
-                        hex::encode(hasher.finalize())
-                    };
-
-                    if computed_hash != chunk_hash {
-                        anyhow::bail!("Chunk hash mismatch at index {}", chunk_index);
-                    }
+        // 파일 전송
+        interruptible(handle.interrupted(), self.send_file_chunks(stream, spec, resume_from_chunk, handle)).await?;
 
-                    // 파일에 쓰기
-                    file.write_all(&data)?;
+        // 전송 완료 메시지
+        let complete_msg = TransferMessage::TransferComplete {
+            transfer_id: spec.transfer_id.clone(),
+            receipt: None,
+        };
 
-                    received_chunks += 1;
+        stream.write_all(&complete_msg.to_bytes()?).await?;
+        stream.flush().await?;
 
-                    // 청크 확인 전송
-                    let ack_msg = TransferMessage::ChunkAck {
-                        transfer_id: transfer_id.to_string(),
-                        chunk_index,
-                    };
-                    stream.write_all(&ack_msg.to_bytes()?).await?;
-
-                    // DB 업데이트
-                    Self::update_transfer_state(transfer_id, received_chunks)?;
-
-                    // 진행률 전송
-                    if let Some(ref tx) = progress_tx {
-                        let elapsed = start_time.elapsed().unwrap_or(Duration::from_secs(1));
-                        let bytes_transferred = received_chunks * CHUNK_SIZE as u64;
-                        let transfer_rate = (bytes_transferred as f64 / elapsed.as_secs_f64()) / 1_000_000.0;
-
-                        let progress = TransferProgress {
-                            transfer_id: transfer_id.to_string(),
-                            file_path: file_path.to_string(),
-                            total_chunks,
-                            completed_chunks: received_chunks,
-                            progress_percent: (received_chunks as f64 / total_chunks as f64) * 100.0,
-                            bytes_transferred,
-                            total_bytes: file_size,
-                            transfer_rate_mbps: transfer_rate,
-                        };
-
-                        let _ = tx.send(progress);
-                    }
+        // 수신측이 검증과 검사를 마치고 보내는 최종 결과
+        let receipt = if verdict { Self::wait_verdict(stream, spec).await? } else { None };
 
-                    log::debug!("Received chunk {}/{} ({:.1}%)",
-                        received_chunks, total_chunks,
-                        (received_chunks as f64 / total_chunks as f64) * 100.0);
-                }
-                TransferMessage::TransferComplete { .. } => {
-                    log::info!("Transfer completed");
-                    break;
-                }
-                _ => {
-                    log::warn!("Unexpected message: {:?}", msg);
-                }
+        handle.set_status(TransferStatus::Completed);
+        tracing::info!("File transfer completed successfully");
+
+        Ok(receipt)
+    }
+
+    /// 완료 메시지를 보낸 뒤 수신측의 최종 결과를 기다립니다.
+    ///
+    /// # Returns
+    /// * 수신측이 서명한 수령증 (보내지 않았으면 None)
+    /// * 수신측이 검사 명령으로 거부하면 `PebbleError::Rejected`
+    async fn wait_verdict<S>(stream: &mut S, spec: &TransferSpec) -> Result<Option<TransferReceipt>>
+    where
+        S: AsyncReadExt + AsyncWriteExt + Unpin,
+    {
+        let reply = tokio::time::timeout(VERDICT_TIMEOUT, TransferMessage::from_stream(stream))
+            .await
+            .map_err(|_| PebbleError::network(format!("Timed out waiting for the receiver to finish {}", spec.file_path)))??;
+        match reply {
+            TransferMessage::TransferComplete { receipt, .. } => Ok(receipt),
+            TransferMessage::TransferReject { reason, code, .. } => Err(reject_error(reason, code).into()),
+            TransferMessage::Error { message, .. } => {
+                Err(PebbleError::io(format!("Receiver failed to store {}: {}", spec.file_path, message)).into())
             }
+            other => Err(PebbleError::protocol(format!("Expected transfer result, got {:?}", other)).into()),
         }
+    }
 
-        file.flush()?;
+    /// 청크 해시 목록을 페이지 단위로 보내고 수신측이 요청한 청크 범위를 받습니다.
+    async fn send_manifest<S>(stream: &mut S, spec: &TransferSpec, first: u64) -> Result<DedupPlan>
+    where
+        S: AsyncReadExt + AsyncWriteExt + Unpin,
+    {
+        let hashes = dedup::chunk_manifest(&spec.file_path, spec.chunk_size, first)?;
+        if hashes.len() as u64 != spec.total_chunks - first {
+            return Err(PebbleError::io(format!(
+                "{} changed while sending ({} chunks, expected {})",
+                spec.file_path, first + hashes.len() as u64, spec.total_chunks
+            )).into());
+        }
 
-        log::info!("File received successfully: {}", file_path);
+        for (page, chunk_hashes) in hashes.chunks(dedup::MANIFEST_PAGE_CHUNKS).enumerate() {
+            let manifest_msg = TransferMessage::ChunkManifest {
+                transfer_id: spec.transfer_id.clone(),
+                first_chunk: first + (page * dedup::MANIFEST_PAGE_CHUNKS) as u64,
+                chunk_hashes: chunk_hashes.to_vec(),
+            };
+            stream.write_all(&manifest_msg.to_bytes()?).await?;
+        }
 
-        Ok(())
+        match TransferMessage::from_stream(stream).await? {
+            TransferMessage::ChunkNeeds { ranges, .. } => {
+                let plan = DedupPlan::from_needs(first, spec.total_chunks, &ranges)?;
+                tracing::info!("Receiver already has {} of {} chunks", plan.present_count(), hashes.len());
+                Ok(plan)
+            }
+            other => Err(PebbleError::protocol(format!("Expected ChunkNeeds, got {:?}", other)).into()),
+        }
     }
 
-    /// 전송 상태를 DB에 업데이트합니다.
-    fn update_transfer_state(transfer_id: &str, received_chunks: u64) -> Result<()> {
-        let conn = Connection::open("pebble.db")?;
+    /// 수신측에 같은 해시와 크기의 파일이 이미 있는지 확인합니다.
+    ///
+    /// # Returns
+    /// * `Result<bool>` - 이미 있으면 true. 구버전 수신측은 연결을 닫으므로 에러
+    pub async fn query_present<S>(stream: &mut S, file_hash: &str, file_size: u64) -> Result<bool>
+    where
+        S: AsyncReadExt + AsyncWriteExt + Unpin,
+    {
+        let query_id = Uuid::new_v4().to_string();
+        let query = TransferMessage::HashQuery {
+            query_id: query_id.clone(),
+            file_hash: file_hash.to_string(),
+            file_size,
+        };
 
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)?
-            .as_secs() as i64;
+        let exchange = async {
+            stream.write_all(&query.to_bytes()?).await?;
+            stream.flush().await?;
+            TransferMessage::from_stream(stream).await
+        };
 
-        conn.execute(
-            "INSERT OR REPLACE INTO transfer_state
-             (transfer_id, file_path, file_size, total_chunks, received_chunks, transfer_status, peer_device_id, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
-             ON CONFLICT(transfer_id) DO UPDATE SET
-                received_chunks = excluded.received_chunks,
-                updated_at = excluded.updated_at",
-            params![
-                transfer_id,
-                "",
-                0i64,
-                0i64,
-                received_chunks as i64,
-                TransferStatus::InProgress.to_string(),
-                "",
-                now,
-                now
-            ],
-        )?;
+        match tokio::time::timeout(HASH_QUERY_TIMEOUT, exchange).await {
+            Ok(Ok(TransferMessage::HashQueryResult { query_id: reply, present })) if reply == query_id => Ok(present),
+            Ok(Ok(other)) => Err(PebbleError::protocol(format!("Expected HashQueryResult, got {:?}", other)).into()),
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err(PebbleError::network("Hash query timed out").into()),
+        }
+    }
+
+    /// 분실한 기기의 신뢰 철회 요청을 보냅니다.
+    ///
+    /// # Arguments
+    /// * `addrs` - 받을 기기 주소 (가장 먼저 연결되는 주소 사용)
+    /// * `revocation` - 서명된 철회 요청
+    ///
+    /// # Returns
+    /// * 받은 기기가 서명을 확인하고 적용하면 Ok, 거부하면 `PebbleError::Rejected`
+    pub async fn send_revocation(&self, addrs: &[SocketAddr], revocation: &Revocation) -> Result<()> {
+        let request = TransferMessage::RevokeMe { revocation: revocation.clone() };
+
+        let (server_addr, mut tls_stream) = self.checkout_any(addrs).await?;
+        let exchange = async {
+            tls_stream.write_all(&request.to_bytes()?).await?;
+            tls_stream.flush().await?;
+            TransferMessage::from_stream(&mut tls_stream).await
+        };
+        let reply = match tokio::time::timeout(HASH_QUERY_TIMEOUT, exchange).await {
+            Ok(reply) => reply?,
+            Err(_) => return Err(PebbleError::network("Revocation request timed out").into()),
+        };
 
+        match reply {
+            TransferMessage::RevokeAck { revocation_id } if revocation_id == revocation.revocation_id => {}
+            TransferMessage::TransferReject { reason, code, .. } => return Err(reject_error(reason, code).into()),
+            other => return Err(PebbleError::protocol(format!("Expected RevokeAck, got {:?}", other)).into()),
+        }
+        self.checkin(server_addr, tls_stream);
         Ok(())
     }
-}
-
-/// 파일 전송 클라이언트
-///
-/// TLS로 암호화된 TCP 연결을 통해 파일을 송신합니다.
-pub struct TransferClient {
-    server_fingerprint: Option<String>,
-    progress_tx: Option<mpsc::UnboundedSender<TransferProgress>>,
-}
 
-impl TransferClient {
-    /// 새로운 전송 클라이언트를 생성합니다.
-    pub fn new(server_fingerprint: Option<String>) -> Self {
-        Self {
-            server_fingerprint,
-            progress_tx: None,
+    /// 클립보드 내용을 보냅니다.
+    ///
+    /// # Arguments
+    /// * `addrs` - 받을 기기 주소 (가장 먼저 연결되는 주소 사용)
+    /// * `update` - 클립보드 내용
+    ///
+    /// # Returns
+    /// * 받은 기기가 확인하면 Ok, 동기화가 꺼져 있으면 `PebbleError::Rejected`
+    pub async fn send_clipboard(&self, addrs: &[SocketAddr], update: &ClipboardUpdate) -> Result<()> {
+        let request = TransferMessage::ClipboardUpdate { update: update.clone() };
+
+        let (server_addr, mut tls_stream) = self.checkout_any(addrs).await?;
+        tls_stream.write_all(&request.to_bytes()?).await?;
+        tls_stream.flush().await?;
+
+        match TransferMessage::from_stream(&mut tls_stream).await? {
+            TransferMessage::ClipboardAck { update_id } if update_id == update.update_id => {}
+            TransferMessage::TransferReject { reason, code, .. } => return Err(reject_error(reason, code).into()),
+            other => return Err(PebbleError::protocol(format!("Expected ClipboardAck, got {:?}", other)).into()),
         }
+        self.checkin(server_addr, tls_stream);
+        Ok(())
     }
 
-    /// 진행률 채널을 설정합니다.
-    pub fn set_progress_channel(&mut self, tx: mpsc::UnboundedSender<TransferProgress>) {
-        self.progress_tx = Some(tx);
+    /// 비공개 비콘을 보낸 기기의 ID와 이름을 물어봅니다.
+    ///
+    /// # Returns
+    /// * `Result<(String, String)>` - (기기 ID, 기기 이름). 비밀 키가 다르면 `PebbleError::Rejected`
+    pub async fn fetch_identity(&self, server_addr: SocketAddr, secret_key: &str) -> Result<(String, String)> {
+        let nonce = Uuid::new_v4().to_string();
+        let request = TransferMessage::IdentityRequest {
+            proof: discovery::identity_proof(&nonce, secret_key)?,
+            nonce,
+        };
+
+        let mut tls_stream = self.checkout(server_addr).await?;
+        let exchange = async {
+            tls_stream.write_all(&request.to_bytes()?).await?;
+            tls_stream.flush().await?;
+            TransferMessage::from_stream(&mut tls_stream).await
+        };
+        let reply = match tokio::time::timeout(HASH_QUERY_TIMEOUT, exchange).await {
+            Ok(reply) => reply?,
+            Err(_) => return Err(PebbleError::network("Identity request timed out").into()),
+        };
+
+        let identity = match reply {
+            TransferMessage::Identity { device_id, device_name } => (device_id, device_name),
+            TransferMessage::TransferReject { reason, code, .. } => return Err(reject_error(reason, code).into()),
+            other => return Err(PebbleError::protocol(format!("Expected Identity, got {:?}", other)).into()),
+        };
+        self.checkin(server_addr, tls_stream);
+        Ok(identity)
     }
 
-    /// 파일을 전송합니다.
-    pub async fn send_file(
+    /// 상대 기기의 폴더 매니페스트를 페이지 단위로 가져옵니다.
+    ///
+    /// # Arguments
+    /// * `addrs` - 상대 기기 주소 (가장 먼저 연결되는 주소 사용)
+    /// * `root` - 상대 기기의 루트 경로
+    /// * `since` - 이전 동기화 때 받은 기록 위치 (있으면 변경분만 요청)
+    /// * `on_page` - 페이지가 도착할 때마다 호출 (변경분 여부, 경로 순 항목)
+    pub async fn fetch_manifest_any<F>(
         &self,
-        server_addr: SocketAddr,
-        file_path: &str,
-    ) -> Result<()> {
-        // 파일 정보 가져오기
-        let file_metadata = std::fs::metadata(file_path)
-            .with_context(|| format!("Failed to get file metadata: {}", file_path))?;
+        addrs: &[SocketAddr],
+        root: &str,
+        since: Option<&JournalCursor>,
+        on_page: F,
+    ) -> Result<ManifestHead>
+    where
+        F: FnMut(bool, Vec<ManifestEntry>) -> Result<()>,
+    {
+        let (server_addr, mut stream) = self.checkout_any(addrs).await?;
+        let head = manifest::request(&mut stream, root, since, on_page).await?;
+        self.checkin(server_addr, stream);
+        Ok(head)
+    }
 
-        let file_size = file_metadata.len();
-        let total_chunks = (file_size + CHUNK_SIZE as u64 - 1) / CHUNK_SIZE as u64;
+    /// 수신측에 이미 있는 파일의 전송을 바로 완료 처리합니다.
+    fn complete_already_present(&self, peer: &str, spec: &TransferSpec) {
+        let handle = match registry::global().register(
+            &spec.transfer_id,
+            peer,
+            &spec.file_path,
+            TransferDirection::Send,
+            spec.file_size,
+        ) {
+            Ok(handle) => handle,
+            Err(e) => {
+                tracing::debug!("Not recording {} as completed: {:#}", spec.file_path, e);
+                return;
+            }
+        };
+        handle.set_progress(spec.file_size);
+        handle.set_status(TransferStatus::Completed);
+
+        if let Some(ref tx) = self.progress_tx {
+            let _ = tx.send(TransferProgress {
+                transfer_id: spec.transfer_id.clone(),
+                file_path: spec.file_path.clone(),
+                total_chunks: spec.total_chunks,
+                completed_chunks: spec.total_chunks,
+                progress_percent: 100.0,
+                bytes_transferred: spec.file_size,
+                total_bytes: spec.file_size,
+                transfer_rate_mbps: 0.0,
+            });
+        }
 
-        // 파일 해시 계산
-        let file_hash = integrity::calculate_file_hash(file_path)?;
+        tracing::info!("{} is already present on {}, skipped transfer", spec.file_path, peer);
+    }
+
+    /// 연결 풀에서 살아 있는 연결을 꺼내고, 없으면 새로 연결합니다.
+    async fn checkout(&self, server_addr: SocketAddr) -> Result<PooledStream> {
+        Ok(self.checkout_any(&[server_addr]).await?.1)
+    }
+
+    /// 주소들 중 풀에 살아 있는 연결을 꺼내고, 없으면 가장 먼저 연결되는 주소로 연결합니다.
+    async fn checkout_any(&self, addrs: &[SocketAddr]) -> Result<(SocketAddr, PooledStream)> {
+        for &server_addr in addrs {
+            while let Some(mut stream) = pool::global().take(server_addr, &self.server_fingerprint) {
+                match pool::check_health(&mut stream).await {
+                    Ok(()) => {
+                        tracing::debug!("Reusing pooled connection to {}", server_addr);
+                        return Ok((server_addr, stream));
+                    }
+                    Err(e) => tracing::debug!("Discarding stale connection to {}: {:#}", server_addr, e),
+                }
+            }
+        }
 
-        let transfer_id = Uuid::new_v4().to_string();
+        let (server_addr, tcp_stream) = connect_first(addrs).await?;
+        Ok((server_addr, self.handshake(tcp_stream).await?))
+    }
 
-        log::info!("Starting file transfer: {} ({} bytes, {} chunks)",
-            file_path, file_size, total_chunks);
+    /// 요청을 끝까지 마친 연결을 다음 전송을 위해 풀에 반환합니다.
+    fn checkin(&self, server_addr: SocketAddr, stream: PooledStream) {
+        pool::global().put(server_addr, &self.server_fingerprint, stream);
+    }
 
+    /// 서버에 TCP로 연결하고 TLS 핸드셰이크를 수행합니다.
+    async fn connect(&self, server_addr: SocketAddr) -> Result<tokio_rustls::client::TlsStream<TcpStream>> {
         // TCP 연결
-        let tcp_stream = TcpStream::connect(server_addr).await
+        let tcp_stream = tuning::current().connect(server_addr).await
             .with_context(|| format!("Failed to connect to {}", server_addr))?;
 
-        // TLS 핸드셰이크
-        let client_config = TlsCertificate::build_client_config(self.server_fingerprint.clone())?;
+        self.handshake(tcp_stream).await
+    }
+
+    /// 연결된 TCP 스트림에서 TLS 핸드셰이크를 수행합니다.
+    async fn handshake(&self, tcp_stream: TcpStream) -> Result<tokio_rustls::client::TlsStream<TcpStream>> {
+        let client_config =
+            TlsCertificate::build_client_config_as(self.server_fingerprint.clone(), service::certificate().as_ref())?;
         let connector = TlsConnector::from(client_config);
 
         let domain = rustls::pki_types::ServerName::try_from("pebble.local")
             .map_err(|_| anyhow::anyhow!("Invalid DNS name"))?;
 
-        let mut tls_stream = connector.connect(domain, tcp_stream).await
+        let tls_stream = connector.connect(domain, tcp_stream).await
             .context("TLS handshake failed")?;
 
-        log::info!("TLS handshake successful");
+        tracing::info!("TLS handshake successful");
 
-        // 전송 요청 전송
-        let request_msg = TransferMessage::TransferRequest {
-            transfer_id: transfer_id.clone(),
-            file_path: file_path.to_string(),
-            file_size,
-            file_hash: file_hash.clone(),
-            total_chunks,
-        };
-
-        tls_stream.write_all(&request_msg.to_bytes()?).await?;
+        Ok(tls_stream)
+    }
 
-        // 전송 수락 대기
-        let response = TransferMessage::from_stream(&mut tls_stream).await?;
+    /// 텍스트 메시지를 전송하고 기록에 저장합니다.
+    ///
+    /// # Arguments
+    /// * `server_addr` - 수신 기기 주소
+    /// * `peer_device_id` - 수신 기기 ID (기록용)
+    /// * `text` - 보낼 텍스트
+    pub async fn send_text(
+        &self,
+        server_addr: SocketAddr,
+        peer_device_id: Option<String>,
+        text: &str,
+    ) -> Result<TextMessage> {
+        messages::validate_text(text)?;
+
+        let message_id = Uuid::new_v4().to_string();
+        let mut tls_stream = self.checkout(server_addr).await?;
+
+        let text_msg = TransferMessage::SendText {
+            message_id: message_id.clone(),
+            sender_device_id: service::device_id(),
+            text: text.to_string(),
+        };
+        tls_stream.write_all(&text_msg.to_bytes()?).await?;
 
-        let resume_from_chunk = match response {
-            TransferMessage::TransferAccept { resume_from_chunk, .. } => {
-                log::info!("Transfer accepted. Resuming from chunk {}", resume_from_chunk);
-                resume_from_chunk
+        match TransferMessage::from_stream(&mut tls_stream).await? {
+            TransferMessage::TextAck { message_id: ack_id } if ack_id == message_id => {}
+            TransferMessage::TransferReject { reason, code, .. } => {
+                return Err(reject_error(reason, code).into());
             }
-            TransferMessage::TransferReject { reason, .. } => {
-                anyhow::bail!("Transfer rejected: {}", reason);
+            other => {
+                return Err(PebbleError::protocol(format!("Expected TextAck, got {:?}", other)).into());
             }
-            _ => {
-                anyhow::bail!("Expected TransferAccept or TransferReject");
-            }
-        };
-
-        // 파일 전송
-        self.send_file_chunks(
-            &mut tls_stream,
-            &transfer_id,
-            file_path,
-            file_size,
-            total_chunks,
-            resume_from_chunk,
-        )
-        .await?;
-
-        // 전송 완료 메시지
-        let complete_msg = TransferMessage::TransferComplete {
-            transfer_id: transfer_id.clone(),
-        };
+        }
+        self.checkin(server_addr, tls_stream);
 
-        tls_stream.write_all(&complete_msg.to_bytes()?).await?;
+        let message = TextMessage::new(
+            message_id,
+            TransferDirection::Send,
+            peer_device_id,
+            server_addr.to_string(),
+            text.to_string(),
+        );
+        messages::save(&message)?;
 
-        log::info!("File transfer completed successfully");
+        tracing::info!("Text message {} sent to {}", message.message_id, server_addr);
 
-        Ok(())
+        Ok(message)
     }
 
     /// 파일 청크를 전송합니다.
+    ///
+    /// 수신측이 묶음 ACK를 쓰면 ACK 없이 창(`AdaptiveWindow`) 크기만큼 청크를 이어 보내며,
+    /// 창은 ACK RTT에 맞춰 조정됩니다. 간격이 1이면(구버전 수신측) 청크마다 ACK를 기다립니다.
+    /// 수신측 저장소에서 채우는 청크는 보내지 않고 건너뜁니다.
     async fn send_file_chunks<S>(
         &self,
         stream: &mut S,
-        transfer_id: &str,
-        file_path: &str,
-        file_size: u64,
-        total_chunks: u64,
+        spec: &TransferSpec,
         resume_from: u64,
+        handle: &mut TransferHandle,
     ) -> Result<()>
     where
         S: AsyncReadExt + AsyncWriteExt + Unpin,
     {
-        let mut file = File::open(file_path)
+        let TransferSpec { ref transfer_id, ref file_path, file_size, total_chunks, chunk_size, chunk_hash_algo, ack_interval, ref dedup, sparse, .. } = *spec;
+
+        let mut file = File::open(paths::long_path(file_path))
             .with_context(|| format!("Failed to open file: {}", file_path))?;
 
         // 이어보내기 위치로 이동
         if resume_from > 0 {
-            let offset = resume_from * CHUNK_SIZE as u64;
+            let offset = resume_from * chunk_size;
             file.seek(SeekFrom::Start(offset))?;
-            log::info!("Resuming from chunk {}", resume_from);
+            tracing::info!("Resuming from chunk {}", resume_from);
         }
 
         let start_time = SystemTime::now();
-        let mut buffer = vec![0u8; CHUNK_SIZE];
-
-        for chunk_index in resume_from..total_chunks {
-            // 청크 읽기
-            let bytes_read = file.read(&mut buffer)?;
+        let mut buffer = vec![0u8; chunk_size as usize];
+        let mut window = AdaptiveWindow::new(ack_interval, chunk_size);
+        let mut acked = resume_from;
+
+        // 속도 제한 기준점 (제한 값이 바뀌면 그 시점부터 다시 계산)
+        let mut rate_limit = 0;
+        let mut throttle_start = Instant::now();
+        let mut throttle_base = 0;
+        // 실제로 보낸 바이트 (저장소에서 채우는 청크 제외)
+        let mut bytes_sent = 0;
+        // 직전 청크를 건너뛰어 파일 위치를 맞춰야 하는지 여부
+        let mut skipped = false;
+
+        // 다음에 보낼 청크와 재전송 요청 횟수
+        let mut next = resume_from;
+        let mut resends = 0;
 
-            if bytes_read == 0 {
+        loop {
+            // 창이 가득 찼거나 모두 보냈으면 ACK 대기 (재전송 요청을 받으면 그 청크부터 다시 보냄)
+            while acked < next && (next - acked >= window.size() || next == total_chunks) {
+                match Self::read_ack(stream, acked, next).await? {
+                    AckReply::Acked(count) => {
+                        acked = count;
+                        window.on_ack(acked, Instant::now());
+                        self.report_progress(spec, handle, acked, start_time);
+                    }
+                    AckReply::Resend(chunk_index) => {
+                        resends += 1;
+                        if resends > MAX_TRANSFER_RETRIES {
+                            return Err(PebbleError::protocol(format!(
+                                "Receiver requested more than {} chunk resends", MAX_TRANSFER_RETRIES
+                            )).into());
+                        }
+                        tracing::warn!("Receiver requested chunk {} again", chunk_index);
+                        window.on_loss(chunk_index, Instant::now());
+                        file.seek(SeekFrom::Start(chunk_index * chunk_size))?;
+                        skipped = false;
+                        next = chunk_index;
+                    }
+                }
+            }
+            if next == total_chunks {
                 break;
             }
 
-            let chunk_data = &buffer[..bytes_read];
+            let chunk_index = next;
+            next += 1;
 
-            // 청크 해시 계산
-            let chunk_hash = {
-                use sha2::{Digest, Sha256};
-                let mut hasher = Sha256::new();
-                hasher.update(chunk_data);
-                hex::encode(hasher.finalize())
-            };
+            // 일시정지/취소 요청 반영
+            handle.checkpoint().await?;
+            self.yield_to_higher_priority(handle).await?;
 
-            // 청크 전송
-            let chunk_msg = TransferMessage::ChunkData {
-                transfer_id: transfer_id.to_string(),
-                chunk_index,
-                chunk_hash,
-                data: chunk_data.to_vec(),
-            };
+            #[cfg(any(test, feature = "fault-injection"))]
+            self.fault.check_drop(chunk_index - resume_from)?;
 
-            stream.write_all(&chunk_msg.to_bytes()?).await?;
+            // 수신측이 저장소에서 채우는 청크 (ACK는 다른 청크와 같이 받음)
+            if dedup.as_ref().is_some_and(|plan| plan.is_present(chunk_index)) {
+                skipped = true;
+                window.on_send(chunk_index, Instant::now());
+                metrics::add_bytes_sent(chunk_size.min(file_size.saturating_sub(chunk_index * chunk_size)));
+                continue;
+            }
+            if skipped {
+                file.seek(SeekFrom::Start(chunk_index * chunk_size))?;
+                skipped = false;
+            }
 
-            // ACK 대기
-            let ack = TransferMessage::from_stream(stream).await?;
+            // 청크 읽기 (묶음 ACK는 마지막 청크에서 끝나므로 중간에 끝나면 에러)
+            let bytes_read = file.read(&mut buffer)?;
 
-            match ack {
-                TransferMessage::ChunkAck { chunk_index: ack_idx, .. } => {
-                    if ack_idx != chunk_index {
-                        anyhow::bail!("Chunk ACK mismatch: expected {}, got {}", chunk_index, ack_idx);
-                    }
-                }
-                _ => {
-                    anyhow::bail!("Expected ChunkAck");
-                }
+            if bytes_read == 0 {
+                return Err(PebbleError::io(format!(
+                    "{} ended at chunk {} of {} while sending", file_path, chunk_index, total_chunks
+                )).into());
             }
 
-            // 진행률 전송
-            if let Some(ref tx) = self.progress_tx {
-                let elapsed = start_time.elapsed().unwrap_or(Duration::from_secs(1));
-                let bytes_transferred = (chunk_index + 1) * CHUNK_SIZE as u64;
-                let transfer_rate = (bytes_transferred as f64 / elapsed.as_secs_f64()) / 1_000_000.0;
+            let chunk_data = &buffer[..bytes_read];
 
-                let progress = TransferProgress {
+            // 0으로만 된 청크(디스크 이미지의 빈 영역 등)는 데이터 없이 구멍으로 알림
+            if sparse && is_zeroes(chunk_data) {
+                let hole_msg = TransferMessage::ChunkHole {
                     transfer_id: transfer_id.to_string(),
-                    file_path: file_path.to_string(),
-                    total_chunks,
-                    completed_chunks: chunk_index + 1,
-                    progress_percent: ((chunk_index + 1) as f64 / total_chunks as f64) * 100.0,
-                    bytes_transferred,
-                    total_bytes: file_size,
-                    transfer_rate_mbps: transfer_rate,
+                    chunk_index,
+                };
+                stream.write_all(&hole_msg.to_bytes()?).await?;
+                window.on_send(chunk_index, Instant::now());
+                metrics::add_bytes_sent(bytes_read as u64);
+            } else {
+                // 청크 해시 계산
+                let chunk_hash = chunk_hash_algo.digest(chunk_data);
+
+                let data = chunk_data.to_vec();
+                #[cfg(any(test, feature = "fault-injection"))]
+                let data = self.fault.corrupted(chunk_index, data);
+
+                // 청크 전송
+                let chunk_msg = TransferMessage::ChunkData {
+                    transfer_id: transfer_id.to_string(),
+                    chunk_index,
+                    chunk_hash,
+                    data,
                 };
 
-                let _ = tx.send(progress);
+                stream.write_all(&chunk_msg.to_bytes()?).await?;
+                window.on_send(chunk_index, Instant::now());
+                metrics::add_bytes_sent(bytes_read as u64);
+                bytes_sent += bytes_read as u64;
             }
 
-            // Flow Control: 전송 속도 제한
-            if MAX_TRANSFER_RATE > 0 {
-                let elapsed = start_time.elapsed().unwrap_or(Duration::from_secs(1));
-                let bytes_transferred = (chunk_index + 1) * CHUNK_SIZE as u64;
-                let expected_duration = Duration::from_secs_f64(bytes_transferred as f64 / MAX_TRANSFER_RATE as f64);
+            // Flow Control: 전송 속도 제한 (설정 변경과 일정 시간대 전환이 전송 중에도 반영됨)
+            let current_limit = schedule::current(self.schedule.as_deref()).rate_limit;
+            if current_limit != rate_limit {
+                rate_limit = current_limit;
+                throttle_start = Instant::now();
+                throttle_base = bytes_sent;
+            }
+            if rate_limit > 0 {
+                let elapsed = throttle_start.elapsed();
+                let expected_duration =
+                    Duration::from_secs_f64((bytes_sent - throttle_base) as f64 / rate_limit as f64);
 
                 if elapsed < expected_duration {
                     tokio::time::sleep(expected_duration - elapsed).await;
                 }
             }
 
-            log::debug!("Sent chunk {}/{} ({:.1}%)",
-                chunk_index + 1, total_chunks,
-                ((chunk_index + 1) as f64 / total_chunks as f64) * 100.0);
+            tracing::debug!("Sent chunk {}/{} ({:.1}%)",
+                next, total_chunks,
+                (next as f64 / total_chunks as f64) * 100.0);
         }
 
+        tracing::debug!("Send window ended at {} chunks (srtt {:?})", window.size(), window.smoothed_rtt());
+
         Ok(())
     }
+
+    /// ACK 메시지 하나를 읽습니다.
+    ///
+    /// `ChunkAck`은 해당 청크까지, `ChunkAcks`는 범위들을 확인합니다. 수신측은 순서대로
+    /// 쓰므로 범위는 아직 확인되지 않은 첫 청크부터 빈틈없이 이어져야 합니다.
+    /// `ChunkNack`은 확인되지 않은 청크 중 하나를 다시 요청합니다.
+    ///
+    /// # Arguments
+    /// * `acked` - 지금까지 확인된 청크 수
+    /// * `sent` - 지금까지 보낸 청크 수
+    async fn read_ack<S>(stream: &mut S, acked: u64, sent: u64) -> Result<AckReply>
+    where
+        S: AsyncReadExt + Unpin,
+    {
+        match TransferMessage::from_stream(stream).await? {
+            TransferMessage::ChunkAck { chunk_index, .. } if (acked..sent).contains(&chunk_index) => Ok(AckReply::Acked(chunk_index + 1)),
+            TransferMessage::ChunkAck { chunk_index, .. } => Err(PebbleError::protocol(format!(
+                "Chunk ACK mismatch: expected {}, got {}", acked, chunk_index
+            )).into()),
+            TransferMessage::ChunkAcks { ranges, .. } => {
+                let mut acked = acked;
+                for range in ranges {
+                    if range.start != acked || range.end <= range.start || range.end > sent {
+                        return Err(PebbleError::protocol(format!(
+                            "Chunk ACK range {}..{} does not continue from {} (sent {})",
+                            range.start, range.end, acked, sent
+                        )).into());
+                    }
+                    acked = range.end;
+                }
+                Ok(AckReply::Acked(acked))
+            }
+            TransferMessage::ChunkNack { chunk_index, .. } if (acked..sent).contains(&chunk_index) => Ok(AckReply::Resend(chunk_index)),
+            TransferMessage::ChunkNack { chunk_index, .. } => Err(PebbleError::protocol(format!(
+                "Chunk NACK for {} outside unacknowledged chunks {}..{}", chunk_index, acked, sent
+            )).into()),
+            other => Err(PebbleError::protocol(format!("Expected ChunkAck, got {:?}", other)).into()),
+        }
+    }
+
+    /// 더 높은 우선순위의 송신이 진행 중이면 모두 끝날 때까지 청크 전송을 멈춥니다.
+    ///
+    /// 멈춘 동안 상태는 Paused로 표시되며, 취소 요청은 `PREEMPTION_POLL_INTERVAL`마다 반영합니다.
+    async fn yield_to_higher_priority(&self, handle: &mut TransferHandle) -> Result<()> {
+        if !priority::is_preempted(self.priority) {
+            return Ok(());
+        }
+
+        tracing::info!("Preempted by higher-priority send, waiting");
+        loop {
+            handle.set_status(TransferStatus::Paused);
+            if tokio::time::timeout(PREEMPTION_POLL_INTERVAL, priority::wait_turn(self.priority)).await.is_ok() {
+                break;
+            }
+            handle.checkpoint().await?;
+        }
+        tracing::info!("Resuming after higher-priority send finished");
+
+        handle.checkpoint().await
+    }
+
+    /// 수신측이 확인한 청크까지 진행률을 갱신합니다.
+    fn report_progress(&self, spec: &TransferSpec, handle: &TransferHandle, acked: u64, start_time: SystemTime) {
+        let bytes_transferred = (acked * spec.chunk_size).min(spec.file_size);
+        handle.set_progress(bytes_transferred);
+
+        if let Some(ref tx) = self.progress_tx {
+            let elapsed = start_time.elapsed().unwrap_or(Duration::from_secs(1));
+            let transfer_rate = (bytes_transferred as f64 / elapsed.as_secs_f64()) / 1_000_000.0;
+
+            let progress = TransferProgress {
+                transfer_id: spec.transfer_id.clone(),
+                file_path: spec.file_path.clone(),
+                total_chunks: spec.total_chunks,
+                completed_chunks: acked,
+                progress_percent: (acked as f64 / spec.total_chunks as f64) * 100.0,
+                bytes_transferred,
+                total_bytes: spec.file_size,
+                transfer_rate_mbps: transfer_rate,
+            };
+
+            let _ = tx.send(progress);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_frame_roundtrip() {
+        let msg = TransferMessage::ChunkAck {
+            transfer_id: "t1".to_string(),
+            chunk_index: 7,
+        };
+        let mut bytes = msg.to_bytes().unwrap().to_vec();
+        let frame_len = bytes.len();
+        bytes.extend_from_slice(&[0xde, 0xad]);
+
+        let (decoded, consumed) = TransferMessage::decode_frame(&bytes).unwrap();
+        assert_eq!(consumed, frame_len);
+        assert!(matches!(decoded, TransferMessage::ChunkAck { chunk_index: 7, .. }));
+    }
+
+    #[test]
+    fn test_decode_frame_rejects_malformed_input() {
+        // 헤더 부족
+        assert!(TransferMessage::decode_frame(&[0, 0]).is_err());
+
+        // 헤더가 선언한 길이보다 짧은 페이로드
+        assert!(TransferMessage::decode_frame(&[0, 0, 0, 10, b'{']).is_err());
+
+        // 최대 크기를 넘는 길이 헤더는 할당 전에 거부
+        let err = TransferMessage::decode_frame(&u32::MAX.to_be_bytes()).unwrap_err();
+        assert!(err.to_string().contains("too large"));
+    }
+
+    #[test]
+    fn test_legacy_accept_defaults_to_sha256() {
+        let json = br#"{"type":"TransferAccept","transfer_id":"t1","resume_from_chunk":0}"#;
+        let msg = TransferMessage::decode_payload(json).unwrap();
+        assert!(matches!(
+            msg,
+            TransferMessage::TransferAccept { chunk_hash_algo: HashAlgo::Sha256, ack_interval: 1, .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_read_ack_accepts_ranges_and_rejects_gaps() {
+        let frames = |messages: &[TransferMessage]| -> Vec<u8> {
+            messages.iter().flat_map(|m| m.to_bytes().unwrap().to_vec()).collect()
+        };
+        let acks = |ranges: Vec<AckRange>| TransferMessage::ChunkAcks { transfer_id: "t1".to_string(), ranges };
+
+        let bytes = frames(&[
+            acks(vec![AckRange { start: 0, end: 8 }, AckRange { start: 8, end: 10 }]),
+            TransferMessage::ChunkAck { transfer_id: "t1".to_string(), chunk_index: 12 },
+        ]);
+        let mut input: &[u8] = &bytes;
+        assert_eq!(TransferClient::read_ack(&mut input, 0, 16).await.unwrap(), AckReply::Acked(10));
+        assert_eq!(TransferClient::read_ack(&mut input, 10, 16).await.unwrap(), AckReply::Acked(13));
+
+        // 재전송 요청은 확인되지 않은 청크만 가능
+        let nack = |chunk_index| TransferMessage::ChunkNack { transfer_id: "t1".to_string(), chunk_index };
+        let bytes = frames(&[nack(14), nack(3)]);
+        let mut input: &[u8] = &bytes;
+        assert_eq!(TransferClient::read_ack(&mut input, 13, 16).await.unwrap(), AckReply::Resend(14));
+        assert!(TransferClient::read_ack(&mut input, 13, 16).await.is_err());
+
+        // 확인되지 않은 청크를 건너뛰거나 보내지 않은 청크를 확인하면 거부
+        for invalid in [vec![AckRange { start: 2, end: 8 }], vec![AckRange { start: 0, end: 17 }]] {
+            let bytes = frames(&[acks(invalid)]);
+            let mut input: &[u8] = &bytes;
+            let err = TransferClient::read_ack(&mut input, 0, 16).await.unwrap_err();
+            assert!(matches!(PebbleError::from(err), PebbleError::Protocol { .. }));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connect_first_skips_unreachable_address() {
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let open = listener.local_addr().unwrap();
+
+        let (addr, _stream) = connect_first(&[closed, open]).await.unwrap();
+        assert_eq!(addr, open);
+
+        // 모든 주소가 실패하면 마지막 에러를 반환
+        let err = connect_first(&[closed]).await.unwrap_err();
+        assert!(err.chain().any(|cause| cause.downcast_ref::<std::io::Error>().is_some()));
+        assert!(connect_first(&[]).await.is_err());
+    }
+
+    #[test]
+    fn test_stable_transfer_id() {
+        let id = |hash: &str, size, chunk_size, target: &str, relative: Option<&str>| {
+            stable_transfer_id(hash, size, chunk_size, "sender", target, relative)
+        };
+        let base = id("abc", 100, 64, "device-a", None);
+        assert_eq!(base, id("abc", 100, 64, "device-a", None));
+        assert!(Uuid::parse_str(&base).is_ok());
+
+        // 내용, 크기, 청크 크기, 수신 기기, 폴더 안 경로가 다르면 다른 전송
+        for other in [
+            id("abd", 100, 64, "device-a", None),
+            id("abc", 101, 64, "device-a", None),
+            id("abc", 100, 128, "device-a", None),
+            id("abc", 100, 64, "device-b", None),
+            id("abc", 100, 64, "device-a", Some("Photos/a.jpg")),
+            stable_transfer_id("abc", 100, 64, "other-sender", "device-a", None),
+        ] {
+            assert_ne!(base, other);
+        }
+    }
+
+    #[test]
+    fn test_resume_state_is_kept_per_sender() {
+        crate::loopback::use_temp_environment();
+        let spec = |sender_id: &str| TransferSpec {
+            transfer_id: "resume-per-sender".to_string(),
+            file_path: "/tmp/resume-per-sender.bin".to_string(),
+            file_size: 0,
+            total_chunks: 0,
+            chunk_size: 0,
+            chunk_hash_algo: HashAlgo::default(),
+            ack_interval: 1,
+            dedup: None,
+            sparse: true,
+            retransmit: true,
+            relative_path: None,
+            sender_id: sender_id.to_string(),
+        };
+
+        TransferServer::update_transfer_state(&spec("device-a"), 5).unwrap();
+        assert_eq!(TransferServer::get_resume_chunk("resume-per-sender", "device-a").unwrap(), 5);
+        // 같은 내용을 보낸 다른 기기는 처음부터 받고, 받기 시작하면 상태를 넘겨받음
+        assert_eq!(TransferServer::get_resume_chunk("resume-per-sender", "device-b").unwrap(), 0);
+        TransferServer::update_transfer_state(&spec("device-b"), 2).unwrap();
+        assert_eq!(TransferServer::get_resume_chunk("resume-per-sender", "device-a").unwrap(), 0);
+        assert_eq!(TransferServer::get_resume_chunk("resume-per-sender", "device-b").unwrap(), 2);
+    }
+
+    #[test]
+    fn test_target_id_prefers_device_id() {
+        let moved = |port| SocketAddr::from(([192, 0, 2, 1], port));
+        let mut client = TransferClient::new(Some("fingerprint".to_string()));
+        assert_eq!(client.target_id_any(&[moved(1)]), "fingerprint");
+
+        // 대기열에서 다시 보낼 때 주소가 바뀌어도 같은 기기면 같은 전송 ID
+        client.set_target_device("queued-device");
+        assert_eq!(client.target_id_any(&[moved(1)]), "queued-device");
+        assert_eq!(client.target_id_any(&[moved(2)]), "queued-device");
+    }
+
+    #[test]
+    fn test_folder_paths_stay_under_download_dir() {
+        let root = Path::new("/home/me/Photos");
+        assert_eq!(folder_relative_path(root, &root.join("2024").join("a.jpg")), "Photos/2024/a.jpg");
+
+        let dir = |relative: Option<&str>| TransferServer::folder_download_dir("/dl".to_string(), relative);
+        assert_eq!(dir(None), "/dl");
+        assert_eq!(dir(Some("a.jpg")), "/dl");
+        assert_eq!(PathBuf::from(dir(Some("Photos/2024/a.jpg"))), Path::new("/dl").join("Photos").join("2024"));
+        assert_eq!(PathBuf::from(dir(Some("../../etc/passwd"))), Path::new("/dl").join("etc"));
+        assert_eq!(PathBuf::from(dir(Some("/C:/x/../y.txt"))), Path::new("/dl").join("x"));
+    }
+
+    #[tokio::test]
+    async fn test_from_stream_rejects_oversized_length() {
+        let mut input: &[u8] = &u32::MAX.to_be_bytes();
+        let err = TransferMessage::from_stream(&mut input).await.unwrap_err();
+        assert!(matches!(PebbleError::from(err), PebbleError::Protocol { .. }));
+    }
+
+    #[test]
+    fn test_unhashed_resume_is_verified_by_rereading_the_file() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("resumed.bin");
+        let content = b"resumed after a restart".to_vec();
+        std::fs::write(&path, &content).unwrap();
+
+        // 받는 동안 해시를 계산하지 못했으면 받은 파일 전체를 다시 읽음
+        let mut file = File::options().read(true).write(true).open(&path).unwrap();
+        let file_hash = TransferServer::finalize_hash(&mut file, None, content.len() as u64).unwrap();
+        assert_eq!(file_hash, integrity::calculate_file_hash(&path).unwrap());
+
+        // 다시 읽을 수도 없으면 청크 해시만으로 받아들이지 않음
+        let mut write_only = File::options().write(true).open(&path).unwrap();
+        let e = TransferServer::finalize_hash(&mut write_only, None, content.len() as u64).unwrap_err();
+        assert!(matches!(e.downcast_ref::<PebbleError>(), Some(PebbleError::Io { .. })));
+    }
 }
@@ -0,0 +1,146 @@
+//! 소켓 튜닝 (Socket Tuning)
+//!
+//! 2.5/10GbE처럼 빠른 LAN에서는 OS 기본 소켓 버퍼와 TLS 레코드 크기가 처리량을 제한하므로,
+//! 전송 연결의 TCP_NODELAY, 송수신 버퍼 크기, TLS 레코드 크기를 설정(`socket_tuning`)으로 조정합니다.
+//!
+//! # Process Flow
+//! 1. 송신측은 `connect`로 버퍼 크기를 지정한 소켓을 만든 뒤 연결하고 TCP_NODELAY 적용
+//! 2. 수신측은 `bind`로 리스너 소켓에 버퍼 크기를 지정 (수락한 연결이 물려받음)하고,
+//!    수락한 연결마다 `configure`로 TCP_NODELAY 적용
+//! 3. TLS 설정을 만들 때 `max_fragment_size`로 레코드 크기 적용 (서버와 클라이언트 모두)
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::net::SocketAddr;
+use tokio::net::{TcpListener, TcpSocket, TcpStream};
+
+use super::config;
+
+/// TLS 레코드 헤더 크기 (rustls의 `max_fragment_size`는 헤더를 포함)
+const TLS_RECORD_HEADER: usize = 5;
+
+/// 허용되는 TLS 레코드 크기 범위 (평문 기준, 최대는 TLS 최대값 16KB)
+pub const MIN_TLS_RECORD_SIZE: u32 = 512;
+pub const MAX_TLS_RECORD_SIZE: u32 = 16 * 1024;
+
+/// 리스너의 연결 대기열 길이
+const LISTEN_BACKLOG: u32 = 1024;
+
+/// 전송 연결의 소켓 설정
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SocketTuning {
+    /// Nagle 알고리즘 끄기 (ACK와 작은 제어 메시지의 지연 방지)
+    pub nodelay: bool,
+
+    /// 송신 버퍼 크기 (bytes, 0이면 OS 기본값)
+    pub send_buffer_size: u32,
+
+    /// 수신 버퍼 크기 (bytes, 0이면 OS 기본값)
+    pub recv_buffer_size: u32,
+
+    /// TLS 레코드 하나에 담을 최대 평문 크기 (bytes, 0이면 TLS 최대값 16KB)
+    ///
+    /// 작게 하면 지연이 줄고, 크게 하면 레코드당 오버헤드가 줄어듭니다.
+    pub tls_record_size: u32,
+}
+
+impl Default for SocketTuning {
+    fn default() -> Self {
+        Self {
+            nodelay: true,
+            send_buffer_size: 0,
+            recv_buffer_size: 0,
+            tls_record_size: 0,
+        }
+    }
+}
+
+impl SocketTuning {
+    /// 설정 값의 유효성을 검사합니다.
+    pub fn validate(&self) -> Result<()> {
+        if self.tls_record_size != 0 && !(MIN_TLS_RECORD_SIZE..=MAX_TLS_RECORD_SIZE).contains(&self.tls_record_size) {
+            anyhow::bail!(
+                "socket_tuning.tls_record_size must be 0 or between {} and {} bytes",
+                MIN_TLS_RECORD_SIZE,
+                MAX_TLS_RECORD_SIZE
+            );
+        }
+        Ok(())
+    }
+
+    /// 주소 종류에 맞는 소켓을 만들고 버퍼 크기를 지정합니다.
+    ///
+    /// 버퍼 크기는 연결 전에 지정해야 TCP 윈도우 스케일링에 반영됩니다.
+    fn socket_for(&self, addr: SocketAddr) -> io::Result<TcpSocket> {
+        let socket = if addr.is_ipv4() { TcpSocket::new_v4()? } else { TcpSocket::new_v6()? };
+        if self.send_buffer_size > 0 {
+            socket.set_send_buffer_size(self.send_buffer_size)?;
+        }
+        if self.recv_buffer_size > 0 {
+            socket.set_recv_buffer_size(self.recv_buffer_size)?;
+        }
+        Ok(socket)
+    }
+
+    /// 설정을 적용한 소켓으로 연결합니다.
+    pub async fn connect(&self, addr: SocketAddr) -> io::Result<TcpStream> {
+        let stream = self.socket_for(addr)?.connect(addr).await?;
+        self.configure(&stream)?;
+        Ok(stream)
+    }
+
+    /// 설정을 적용한 소켓으로 바인딩합니다 (`TcpListener::bind`와 같이 주소 재사용 허용).
+    pub fn bind(&self, addr: SocketAddr) -> io::Result<TcpListener> {
+        let socket = self.socket_for(addr)?;
+        #[cfg(unix)]
+        socket.set_reuseaddr(true)?;
+        socket.bind(addr)?;
+        socket.listen(LISTEN_BACKLOG)
+    }
+
+    /// 연결된 스트림에 TCP_NODELAY를 적용합니다 (버퍼 크기는 리스너에서 물려받음).
+    pub fn configure(&self, stream: &TcpStream) -> io::Result<()> {
+        stream.set_nodelay(self.nodelay)
+    }
+
+    /// rustls의 `max_fragment_size` 값 (None이면 TLS 최대값)
+    pub fn max_fragment_size(&self) -> Option<usize> {
+        (self.tls_record_size > 0).then(|| self.tls_record_size as usize + TLS_RECORD_HEADER)
+    }
+}
+
+/// 현재 설정의 소켓 튜닝 값
+pub fn current() -> SocketTuning {
+    config::current().socket_tuning
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tuned_connection() {
+        let tuning = SocketTuning {
+            nodelay: true,
+            send_buffer_size: 256 * 1024,
+            recv_buffer_size: 256 * 1024,
+            tls_record_size: 4096,
+        };
+        tuning.validate().unwrap();
+        assert!(SocketTuning { tls_record_size: 100, ..tuning.clone() }.validate().is_err());
+        assert_eq!(tuning.max_fragment_size(), Some(4096 + TLS_RECORD_HEADER));
+        assert_eq!(SocketTuning::default().max_fragment_size(), None);
+
+        let listener = tuning.bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (client, accepted) = tokio::join!(tuning.connect(addr), listener.accept());
+        let client = client.unwrap();
+        let (server, _) = accepted.unwrap();
+        tuning.configure(&server).unwrap();
+
+        assert!(client.nodelay().unwrap());
+        assert!(server.nodelay().unwrap());
+    }
+}
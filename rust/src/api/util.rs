@@ -0,0 +1,92 @@
+//! 여러 모듈이 함께 쓰는 작은 도구 (현재 시각, 콜백 구독자 목록)
+
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 현재 UNIX 시각 (초, 시계가 1970년 이전이면 0)
+pub fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// 구독자 콜백 (false를 반환하면 구독 해제)
+type Subscriber<T> = Box<dyn Fn(&T) -> bool + Send>;
+
+/// 콜백 구독자 목록 (Dart 스트림 등으로 알림을 전달)
+pub struct Subscribers<T: ?Sized> {
+    subscribers: Mutex<Vec<Subscriber<T>>>,
+}
+
+impl<T: ?Sized> Subscribers<T> {
+    pub const fn new() -> Self {
+        Self { subscribers: Mutex::new(Vec::new()) }
+    }
+
+    /// 구독자를 등록합니다.
+    pub fn subscribe<F>(&self, subscriber: F)
+    where
+        F: Fn(&T) -> bool + Send + 'static,
+    {
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push(Box::new(subscriber));
+        }
+    }
+
+    /// 모든 구독자에게 알리고 false를 반환한 구독자는 해제합니다.
+    ///
+    /// # Returns
+    /// * 알림을 받은 구독자가 남아 있으면 true
+    pub fn notify(&self, value: &T) -> bool {
+        match self.subscribers.lock() {
+            Ok(mut subscribers) => {
+                subscribers.retain(|subscriber| subscriber(value));
+                !subscribers.is_empty()
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// 모든 구독자를 해제합니다.
+    pub fn clear(&self) {
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.clear();
+        }
+    }
+}
+
+impl<T: ?Sized> Default for Subscribers<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_subscriber_returning_false_is_removed() {
+        let subscribers = Subscribers::<str>::new();
+        assert!(!subscribers.notify("nobody"));
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        subscribers.subscribe(move |line| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            line != "stop"
+        });
+
+        assert!(subscribers.notify("first"));
+        assert!(!subscribers.notify("stop"));
+        assert!(!subscribers.notify("after"));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        subscribers.subscribe(|_| true);
+        subscribers.clear();
+        assert!(!subscribers.notify("cleared"));
+    }
+}
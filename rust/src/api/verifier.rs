@@ -0,0 +1,224 @@
+//! 수신 청크 검증 작업자 (Chunk Verification Workers)
+//!
+//! 청크 해시 계산은 CPU가 느린 기기에서 수신 처리량을 제한하므로, 수신 루프에서 직접 계산하지 않고
+//! 작업자(`PebbleConfig::chunk_verify_workers`)에게 맡겨 네트워크 읽기, 디스크 쓰기, 해시 계산이
+//! 겹치도록 합니다. 작업자 수가 0이면 수신 루프에서 바로 검증합니다.
+//!
+//! # Process Flow
+//! 1. 수신 루프가 청크를 파일에 쓰면서 `submit`으로 검증을 맡김
+//! 2. 검증 대기 중인 청크가 `QUEUE_PER_WORKER` × 작업자 수에 이르면 가장 오래된 검증을 기다림 (메모리 제한)
+//! 3. ACK를 보내기 전에 `settle`로 그 청크까지의 검증을 모두 기다림
+//!    → ACK한 청크(이어받기 위치)는 항상 검증된 청크
+//! 4. 해시가 맞지 않으면 `ChunkMismatch` 에러 (송신측이 재전송을 지원하면 `discard`로 남은 검증을 버리고
+//!    그 청크부터 다시 요청, 아니면 전송 실패 후 이어받을 때 ACK한 청크 뒤의 내용은 지우고 다시 받음)
+
+use anyhow::Result;
+use bytes::Bytes;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
+
+use super::config;
+use super::integrity::HashAlgo;
+
+/// 기본 작업자 수
+pub const DEFAULT_WORKERS: u32 = 2;
+
+/// 허용되는 최대 작업자 수
+pub const MAX_WORKERS: u32 = 32;
+
+/// 전송 하나에서 작업자당 검증을 기다릴 수 있는 청크 수
+const QUEUE_PER_WORKER: usize = 4;
+
+/// 작업자 수와 동시에 검증할 수 있는 슬롯
+type WorkerSlots = (u32, Arc<Semaphore>);
+
+/// 모든 전송이 공유하는 작업자 슬롯
+static WORKERS: once_cell::sync::Lazy<Mutex<Option<WorkerSlots>>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(None));
+
+/// 현재 설정의 작업자 슬롯 (0이면 None, 작업자 수가 바뀌면 새로 만듦)
+fn workers() -> Option<WorkerSlots> {
+    let count = config::current().chunk_verify_workers;
+    if count == 0 {
+        return None;
+    }
+
+    let mut workers = WORKERS.lock().unwrap();
+    match workers.as_ref() {
+        Some((current, slots)) if *current == count => Some((count, Arc::clone(slots))),
+        _ => {
+            let slots = Arc::new(Semaphore::new(count as usize));
+            *workers = Some((count, Arc::clone(&slots)));
+            Some((count, slots))
+        }
+    }
+}
+
+/// 청크 해시가 맞지 않음 (`anyhow::Error`에서 `downcast_ref`로 확인)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkMismatch {
+    pub chunk_index: u64,
+}
+
+impl fmt::Display for ChunkMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Chunk hash mismatch at index {}", self.chunk_index)
+    }
+}
+
+impl std::error::Error for ChunkMismatch {}
+
+/// 검증을 기다리는 청크
+struct PendingChunk {
+    chunk_index: u64,
+    data: Bytes,
+    matched: JoinHandle<bool>,
+}
+
+/// 전송 하나의 청크 검증 대기열
+pub struct ChunkVerifier {
+    algo: HashAlgo,
+    workers: Option<Arc<Semaphore>>,
+    capacity: usize,
+    pending: VecDeque<PendingChunk>,
+}
+
+impl ChunkVerifier {
+    /// 설정의 작업자 수로 검증 대기열을 만듭니다.
+    pub fn new(algo: HashAlgo) -> Self {
+        let workers = workers();
+        let capacity = workers.as_ref().map(|(count, _)| *count as usize * QUEUE_PER_WORKER).unwrap_or(0);
+        Self {
+            algo,
+            workers: workers.map(|(_, slots)| slots),
+            capacity,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// 청크 검증을 맡깁니다. 대기열이 가득 차면 가장 오래된 검증이 끝날 때까지 기다립니다.
+    ///
+    /// # Arguments
+    /// * `on_verified` - 검증을 마친 청크마다 순서대로 호출 (중복 제거 저장소 기록 등)
+    ///
+    /// # Returns
+    /// * 이미 끝난 검증 중 해시가 맞지 않은 청크가 있으면 에러
+    pub async fn submit<F: FnMut(u64, &[u8])>(
+        &mut self,
+        chunk_index: u64,
+        data: Bytes,
+        expected_hash: String,
+        mut on_verified: F,
+    ) -> Result<()> {
+        let Some(workers) = self.workers.clone() else {
+            Self::check(chunk_index, self.algo.digest(&data) == expected_hash)?;
+            on_verified(chunk_index, &data);
+            return Ok(());
+        };
+
+        while self.pending.len() >= self.capacity {
+            self.settle_one(&mut on_verified).await?;
+        }
+
+        let algo = self.algo;
+        let job_data = data.clone();
+        let matched = tokio::spawn(async move {
+            let Ok(_slot) = workers.acquire_owned().await else {
+                return false;
+            };
+            tokio::task::spawn_blocking(move || algo.digest(&job_data) == expected_hash)
+                .await
+                .unwrap_or(false)
+        });
+        self.pending.push_back(PendingChunk { chunk_index, data, matched });
+
+        Ok(())
+    }
+
+    /// `until` 이전 청크의 검증이 모두 끝날 때까지 기다립니다 (ACK 전).
+    pub async fn settle<F: FnMut(u64, &[u8])>(&mut self, until: u64, mut on_verified: F) -> Result<()> {
+        while self.pending.front().is_some_and(|pending| pending.chunk_index < until) {
+            self.settle_one(&mut on_verified).await?;
+        }
+        Ok(())
+    }
+
+    /// 검증을 기다리는 청크 수
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// 남은 검증을 결과를 기다리지 않고 버립니다 (해시가 맞지 않은 청크부터 다시 받을 때).
+    pub fn discard(&mut self) {
+        for pending in self.pending.drain(..) {
+            pending.matched.abort();
+        }
+    }
+
+    async fn settle_one<F: FnMut(u64, &[u8])>(&mut self, on_verified: &mut F) -> Result<()> {
+        let Some(pending) = self.pending.pop_front() else {
+            return Ok(());
+        };
+        Self::check(pending.chunk_index, pending.matched.await.unwrap_or(false))?;
+        on_verified(pending.chunk_index, &pending.data);
+        Ok(())
+    }
+
+    fn check(chunk_index: u64, matched: bool) -> Result<()> {
+        if !matched {
+            return Err(ChunkMismatch { chunk_index }.into());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ChunkVerifier {
+    fn drop(&mut self) {
+        // 실패한 전송의 남은 검증은 결과를 기다리지 않음
+        for pending in &self.pending {
+            pending.matched.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_verifier_orders_and_detects_mismatch() {
+        let algo = HashAlgo::Blake3;
+        let slots = Arc::new(Semaphore::new(2));
+        let mut verifier = ChunkVerifier { algo, workers: Some(slots), capacity: 3, pending: VecDeque::new() };
+
+        let mut verified = Vec::new();
+        for index in 0..5u64 {
+            let data = Bytes::from(vec![index as u8; 1024]);
+            let hash = algo.digest(&data);
+            verifier.submit(index, data, hash, |i, _| verified.push(i)).await.unwrap();
+            assert!(verifier.pending() <= 3);
+        }
+        verifier.settle(5, |i, _| verified.push(i)).await.unwrap();
+        assert_eq!(verified, vec![0, 1, 2, 3, 4]);
+        assert_eq!(verifier.pending(), 0);
+
+        // 맞지 않는 청크는 ACK 전에 전송을 실패시킴
+        verifier.submit(5, Bytes::from_static(b"data"), "bad".to_string(), |_, _| {}).await.unwrap();
+        verifier.submit(6, Bytes::from_static(b"next"), algo.digest(b"next"), |_, _| {}).await.unwrap();
+        let err = verifier.settle(7, |_, _| {}).await.unwrap_err();
+        assert!(err.to_string().contains("index 5"));
+        assert_eq!(err.downcast_ref::<ChunkMismatch>(), Some(&ChunkMismatch { chunk_index: 5 }));
+
+        // 다시 받을 때는 남은 검증을 버림
+        assert_eq!(verifier.pending(), 1);
+        verifier.discard();
+        assert_eq!(verifier.pending(), 0);
+
+        // 작업자가 없으면 바로 검증
+        let mut inline = ChunkVerifier { algo, workers: None, capacity: 0, pending: VecDeque::new() };
+        assert!(inline.submit(0, Bytes::from_static(b"x"), "bad".to_string(), |_, _| {}).await.is_err());
+    }
+}
@@ -0,0 +1,156 @@
+//! 동기화 루트 식별 (Volume Identity)
+//!
+//! 외장 드라이브가 다른 경로나 드라이브 문자로 다시 연결되면 경로만으로는 같은 폴더인지
+//! 알 수 없어, 모든 파일이 삭제된 뒤 새로 추가된 것처럼 보입니다. 루트에 식별자 파일
+//! (`MARKER_FILE_NAME`)을 두고 식별자별 마지막 경로를 기록해, 옮겨진 루트를 알아보면
+//! 기존 기록(파일 상태, 공유, 동기화 쌍)을 새 경로로 옮깁니다.
+//!
+//! # Process Flow
+//! 1. 감시를 시작하기 전에 `attach_root`로 루트의 식별자 파일을 읽거나 새로 만듦
+//! 2. 식별자의 마지막 경로가 지금 경로와 다르고 이전 경로가 사라졌으면 기록을 새 경로로 옮김
+//! 3. 이전 경로도 같은 식별자로 남아 있으면(폴더 복사) 새 루트에 새 식별자를 부여
+//! 4. 식별자 파일은 스캔, 재조정, 파일 감시에서 제외
+//!
+//! 읽기 전용 매체처럼 식별자 파일을 만들 수 없는 루트는 경로로만 추적합니다.
+
+use anyhow::{Context, Result};
+use rusqlite::{params, OptionalExtension};
+use std::path::Path;
+use uuid::Uuid;
+
+use super::db;
+use super::paths;
+use super::util::unix_now;
+
+/// 루트 식별자 파일 이름
+pub const MARKER_FILE_NAME: &str = ".pebble-volume";
+
+/// 경로가 루트 식별자 파일인지 확인합니다.
+pub fn is_marker(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name == MARKER_FILE_NAME)
+}
+
+/// 루트의 식별자 파일을 읽습니다. 없거나 읽을 수 없으면 None.
+pub fn read_marker(root: &str) -> Option<String> {
+    let content = std::fs::read_to_string(paths::long_path(Path::new(root).join(MARKER_FILE_NAME))).ok()?;
+    let id = content.trim();
+    Uuid::parse_str(id).is_ok().then(|| id.to_string())
+}
+
+/// 새 식별자를 루트의 식별자 파일에 기록합니다.
+fn write_marker(root: &str) -> Result<String> {
+    let id = Uuid::new_v4().to_string();
+    let marker = Path::new(root).join(MARKER_FILE_NAME);
+    std::fs::write(paths::long_path(&marker), &id)
+        .with_context(|| format!("Failed to write volume marker: {}", marker.display()))?;
+    Ok(id)
+}
+
+/// 식별자로 기록된 마지막 루트 경로
+fn last_path(volume_id: &str) -> Result<Option<String>> {
+    let conn = db::open_connection()?;
+    Ok(conn
+        .query_row("SELECT root_path FROM volume_roots WHERE volume_id = ?1", params![volume_id], |row| row.get(0))
+        .optional()?)
+}
+
+/// 이전 루트의 기록을 새 루트로 옮기고 식별자의 경로를 갱신합니다.
+fn record(volume_id: &str, root: &str, moved_from: Option<&str>) -> Result<()> {
+    let now = unix_now();
+
+    let mut conn = db::open_connection()?;
+    let tx = conn.transaction()?;
+    if let Some(old_root) = moved_from {
+        let files = db::rebase_paths(&tx, "files", "path", old_root, root)?;
+        db::rebase_paths(&tx, "shares", "root_path", old_root, root)?;
+        db::rebase_paths(&tx, "sync_pairs", "local_root", old_root, root)?;
+        tracing::info!("Re-associated {} file record(s) from {} to {}", files, old_root, root);
+    }
+    tx.execute(
+        "INSERT INTO volume_roots (volume_id, root_path, updated_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(volume_id) DO UPDATE SET root_path = excluded.root_path, updated_at = excluded.updated_at",
+        params![volume_id, root, now],
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// 동기화 루트를 식별자에 연결하고, 옮겨진 루트면 기존 기록을 새 경로로 옮깁니다.
+///
+/// # Arguments
+/// * `root` - 동기화 루트 (감시할 디렉토리)
+///
+/// # Returns
+/// * `Result<Option<String>>` - 기록을 옮겼으면 이전 루트 경로
+pub fn attach_root(root: &str) -> Result<Option<String>> {
+    if paths::is_uri(root) {
+        return Ok(None);
+    }
+    let root = paths::normalize(root);
+
+    let volume_id = match read_marker(&root) {
+        Some(id) => id,
+        None => match write_marker(&root) {
+            Ok(id) => id,
+            Err(e) => {
+                tracing::warn!("Tracking {} by path only: {:#}", root, e);
+                return Ok(None);
+            }
+        },
+    };
+
+    let moved_from = match last_path(&volume_id)? {
+        Some(previous) if previous != root => {
+            if read_marker(&previous).as_deref() == Some(volume_id.as_str()) {
+                // 식별자 파일째 복사된 폴더: 원본은 그대로 두고 복사본에 새 식별자 부여
+                tracing::info!("{} is a copy of {}, assigning a new volume identity", root, previous);
+                let copy_id = write_marker(&root)?;
+                record(&copy_id, &root, None)?;
+                return Ok(None);
+            }
+            Some(previous)
+        }
+        _ => None,
+    };
+
+    record(&volume_id, &root, moved_from.as_deref())?;
+    Ok(moved_from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::db::{FileMetadata, SyncStatus};
+
+    #[test]
+    fn test_remounted_root_keeps_file_records() {
+        crate::loopback::use_temp_environment();
+        let mounts = tempfile::TempDir::new().unwrap();
+        let first = mounts.path().join("usb0");
+        let second = mounts.path().join("usb1");
+        std::fs::create_dir(&first).unwrap();
+        let first_root = paths::normalize(&first);
+        let second_root = paths::normalize(&second);
+
+        assert_eq!(attach_root(&first_root).unwrap(), None);
+        let file = paths::normalize(first.join("photo.jpg"));
+        db::upsert_file(FileMetadata::new(file, 1, "volume-hash".to_string(), SyncStatus::Synced, 5)).unwrap();
+
+        // 같은 드라이브가 다른 경로로 다시 연결됨
+        std::fs::rename(&first, &second).unwrap();
+        assert_eq!(attach_root(&second_root).unwrap(), Some(first_root.clone()));
+
+        let moved = db::get_file_metadata(&paths::normalize(second.join("photo.jpg"))).unwrap().unwrap();
+        assert_eq!(moved.sync_status, SyncStatus::Synced);
+        assert_eq!(moved.file_hash, "volume-hash");
+        assert!(db::list_files_under(&first_root).unwrap().is_empty());
+
+        // 폴더째 복사하면 복사본은 새 식별자를 받고 원본 기록은 그대로
+        let copy = mounts.path().join("copy");
+        std::fs::create_dir(&copy).unwrap();
+        std::fs::copy(second.join(MARKER_FILE_NAME), copy.join(MARKER_FILE_NAME)).unwrap();
+        assert_eq!(attach_root(&paths::normalize(&copy)).unwrap(), None);
+        assert_ne!(read_marker(&paths::normalize(&copy)), read_marker(&second_root));
+        assert_eq!(db::list_files_under(&second_root).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,166 @@
+//! 절전 복귀 감지 (Sleep/Wake Resilience)
+//!
+//! 노트북이 잠자기에 들어가면 비콘이 멈추고 타이머가 밀리며, 진행 중이던 전송은
+//! 상대 연결이 이미 끊겼는데도 타임아웃까지 멈춰 있습니다. 주기적으로 시계를 확인해
+//! 큰 공백을 찾고, 복귀하면 주변 기기에 바로 다시 알리고 진행 중인 전송이 살아
+//! 있는지 확인합니다.
+//!
+//! # Process Flow
+//! 1. `CLOCK_CHECK_INTERVAL`마다 단조 시계와 벽시계의 경과 시간을 잼
+//! 2. 어느 쪽이든 예상보다 `SLEEP_GAP_THRESHOLD` 이상 늦으면 절전 복귀로 판단
+//!    (잠자는 동안 멈추는 단조 시계는 벽시계와의 차이로 드러남)
+//! 3. 유휴 연결 풀을 비우고 즉시 비콘을 보낸 뒤 `SystemWoke` 이벤트 발생
+//! 4. `REVALIDATE_GRACE` 동안 진행이 없는 전송을 네트워크 에러로 끊음
+//!    → 이어받기 상태가 남으므로 송신측이 다시 연결하면 이어서 전송
+
+use anyhow::Result;
+use std::time::{Duration, Instant, SystemTime};
+use tokio_util::sync::CancellationToken;
+
+use super::discovery;
+use super::events::{self, PebbleEvent};
+use super::pool;
+use super::registry::{self, TransferRegistry};
+use super::transfer::TransferStatus;
+
+/// 시계 확인 주기
+pub const CLOCK_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 절전으로 판단하는 최소 공백 (확인 주기를 넘어선 시간)
+pub const SLEEP_GAP_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// 복귀 후 전송이 다시 진행되기를 기다리는 시간
+pub const REVALIDATE_GRACE: Duration = Duration::from_secs(10);
+
+/// 확인 주기 사이의 경과 시간에서 절전 공백을 계산합니다.
+///
+/// # Arguments
+/// * `monotonic` - 단조 시계 경과 시간 (OS에 따라 절전 시간을 포함하지 않음)
+/// * `wall` - 벽시계 경과 시간 (시계가 뒤로 갔으면 None)
+/// * `expected` - 예상 경과 시간 (확인 주기)
+///
+/// # Returns
+/// * 공백이 `SLEEP_GAP_THRESHOLD` 이상이면 예상보다 늦은 시간
+pub fn sleep_gap(monotonic: Duration, wall: Option<Duration>, expected: Duration) -> Option<Duration> {
+    let elapsed = monotonic.max(wall.unwrap_or_default());
+    let gap = elapsed.saturating_sub(expected);
+    (gap >= SLEEP_GAP_THRESHOLD).then_some(gap)
+}
+
+/// 취소될 때까지 절전 복귀를 감시합니다.
+pub async fn run_watchdog(token: CancellationToken) -> Result<()> {
+    let mut last_instant = Instant::now();
+    let mut last_wall = SystemTime::now();
+
+    loop {
+        tokio::select! {
+            _ = token.cancelled() => return Ok(()),
+            _ = tokio::time::sleep(CLOCK_CHECK_INTERVAL) => {}
+        }
+
+        let (now_instant, now_wall) = (Instant::now(), SystemTime::now());
+        let gap = sleep_gap(
+            now_instant.duration_since(last_instant),
+            now_wall.duration_since(last_wall).ok(),
+            CLOCK_CHECK_INTERVAL,
+        );
+        last_instant = now_instant;
+        last_wall = now_wall;
+
+        if let Some(gap) = gap {
+            tokio::select! {
+                _ = token.cancelled() => return Ok(()),
+                _ = handle_wake(gap) => {}
+            }
+            // 재확인 동안 흐른 시간은 공백으로 보지 않음
+            last_instant = Instant::now();
+            last_wall = SystemTime::now();
+        }
+    }
+}
+
+/// 절전 복귀 후 연결 상태를 다시 맞춥니다.
+async fn handle_wake(slept: Duration) {
+    tracing::info!("System woke after ~{}s, revalidating connections", slept.as_secs());
+
+    // 잠든 동안 상대가 닫았을 연결은 재사용하지 않음
+    pool::global().clear();
+    discovery::announce_now();
+    events::emit(PebbleEvent::SystemWoke { slept_secs: slept.as_secs() });
+
+    let interrupted = revalidate_transfers(registry::global(), REVALIDATE_GRACE).await;
+    if !interrupted.is_empty() {
+        tracing::warn!("Interrupted {} transfer(s) stalled after wake: {:?}", interrupted.len(), interrupted);
+    }
+}
+
+/// 진행 중인 전송 중 `grace` 동안 진행이 없는 전송을 끊습니다.
+///
+/// 일시정지되었거나 수락을 기다리는 전송은 진행이 없어도 정상이므로 제외합니다.
+///
+/// # Returns
+/// * 끊은 전송 ID 목록
+pub async fn revalidate_transfers(registry: &TransferRegistry, grace: Duration) -> Vec<String> {
+    let before: Vec<(String, u64)> = registry
+        .list()
+        .into_iter()
+        .filter(|transfer| transfer.status == TransferStatus::InProgress)
+        .map(|transfer| (transfer.transfer_id, transfer.bytes_transferred))
+        .collect();
+    if before.is_empty() {
+        return Vec::new();
+    }
+
+    tokio::time::sleep(grace).await;
+
+    let mut interrupted = Vec::new();
+    for transfer in registry.list() {
+        let stalled = transfer.status == TransferStatus::InProgress
+            && before
+                .iter()
+                .any(|(id, bytes)| *id == transfer.transfer_id && *bytes == transfer.bytes_transferred);
+        if stalled && registry.interrupt(&transfer.transfer_id).is_ok() {
+            interrupted.push(transfer.transfer_id);
+        }
+    }
+    interrupted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::registry::TransferDirection;
+
+    #[test]
+    fn test_sleep_gap() {
+        let interval = CLOCK_CHECK_INTERVAL;
+
+        assert_eq!(sleep_gap(interval, Some(interval), interval), None);
+        // 단조 시계가 절전 동안 멈춰도 벽시계로 감지
+        assert_eq!(sleep_gap(interval, Some(interval * 20), interval), Some(interval * 19));
+        // 절전 시간을 포함하는 단조 시계
+        assert_eq!(sleep_gap(interval * 20, None, interval), Some(interval * 19));
+    }
+
+    #[tokio::test]
+    async fn test_revalidate_interrupts_only_stalled_transfers() {
+        let registry = TransferRegistry::new();
+        let stalled = registry.register("stalled", "peer", "a.bin", TransferDirection::Receive, 100).unwrap();
+        let moving = registry.register("moving", "peer", "b.bin", TransferDirection::Send, 100).unwrap();
+        let paused = registry.register("paused", "peer", "c.bin", TransferDirection::Send, 100).unwrap();
+        stalled.set_status(TransferStatus::InProgress);
+        moving.set_status(TransferStatus::InProgress);
+        paused.set_status(TransferStatus::Paused);
+
+        let progress = async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            moving.set_progress(50);
+        };
+        let (interrupted, ()) = tokio::join!(revalidate_transfers(&registry, Duration::from_millis(100)), progress);
+
+        assert_eq!(interrupted, vec!["stalled".to_string()]);
+        assert!(stalled.interrupted().is_cancelled());
+        assert!(!moving.interrupted().is_cancelled());
+        assert!(!paused.interrupted().is_cancelled());
+    }
+}
@@ -3,14 +3,24 @@ use notify::{
     event::{CreateKind, ModifyKind, RemoveKind},
     Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher,
 };
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{channel, Receiver};
 use std::sync::{Arc, Mutex};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::task;
+use tracing::Instrument;
 
-use super::db::{self, FileMetadata};
+use super::config;
+use super::db::{self, FileMetadata, SyncStatus};
+use super::events::{self, PebbleEvent};
 use super::integrity;
+use super::paths;
+use super::service::{self, ServiceKind};
+use super::storage;
+use super::supervisor::TaskSupervisor;
+use super::locked;
+use super::volume;
 
 /// 파일 시스템 이벤트 타입
 #[derive(Debug, Clone)]
@@ -20,13 +30,25 @@ pub enum FileEvent {
     Removed(PathBuf),
 }
 
+/// 감시 루트가 사라졌는지 확인하는 주기
+pub const ROOT_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
 /// 파일 감시 핸들러
 ///
 /// 백그라운드에서 실행되며 파일 시스템 변경 사항을 감지하고 DB를 업데이트합니다.
+/// 감시 루트가 사라지면(외장 드라이브 분리 등) 감시를 멈췄다가, 다시 나타나면
+/// 재조정 스캔 후 이어서 감시합니다.
 pub struct FileWatcher {
-    _watcher: RecommendedWatcher,
-    #[allow(dead_code)]
+    watcher: Arc<Mutex<RecommendedWatcher>>,
     watch_path: PathBuf,
+    /// 감시 루트를 잃어 감시가 멈춘 상태
+    root_lost: Arc<AtomicBool>,
+    tasks: TaskSupervisor,
+}
+
+/// 감시 루트가 읽을 수 있는 디렉토리로 남아 있는지 확인합니다.
+fn root_available(root: &Path) -> bool {
+    std::fs::read_dir(paths::long_path(root)).is_ok()
 }
 
 impl FileWatcher {
@@ -64,95 +86,203 @@ impl FileWatcher {
             .watch(&watch_path, RecursiveMode::Recursive)
             .with_context(|| format!("Failed to watch directory: {}", path))?;
 
-        log::info!("Started watching directory: {}", path);
+        tracing::info!("Started watching directory: {}", path);
 
-        // 이벤트 처리를 위한 백그라운드 태스크 생성
-        Self::spawn_event_handler(rx);
+        // 이벤트 처리를 위한 백그라운드 태스크 생성 (감시 세션 단위 span)
+        let span = tracing::info_span!("sync", root = %path, phase = "watch");
+        let tasks = TaskSupervisor::new(ServiceKind::Watcher);
+        let watcher = Arc::new(Mutex::new(watcher));
+        let root_lost = Arc::new(AtomicBool::new(false));
+        Self::spawn_event_handler(&tasks, rx, watch_path.clone(), Arc::clone(&root_lost), span.clone());
+        Self::spawn_root_monitor(&tasks, Arc::clone(&watcher), watch_path.clone(), Arc::clone(&root_lost), span);
 
         Ok(Self {
-            _watcher: watcher,
+            watcher,
             watch_path,
+            root_lost,
+            tasks,
         })
     }
 
+    /// 감시를 멈추고 이벤트 처리 태스크가 끝날 때까지 기다립니다.
+    pub async fn shutdown(self) -> Result<()> {
+        // 루트 확인 태스크가 감시자를 함께 들고 있으므로 태스크를 먼저 끝낸 뒤,
+        // 마지막 참조가 사라지면 이벤트 채널이 닫히고 블로킹 수신이 끝남
+        drop(self.watcher);
+        self.tasks.shutdown().await
+    }
+
+    /// 감시 루트가 사라지거나 다시 나타나는지 주기적으로 확인하는 태스크를 생성합니다.
+    fn spawn_root_monitor(
+        tasks: &TaskSupervisor,
+        watcher: Arc<Mutex<RecommendedWatcher>>,
+        root: PathBuf,
+        root_lost: Arc<AtomicBool>,
+        span: tracing::Span,
+    ) {
+        tasks.spawn("root_monitor", |token| async move {
+            loop {
+                tokio::select! {
+                    _ = token.cancelled() => return Ok(()),
+                    _ = tokio::time::sleep(ROOT_CHECK_INTERVAL) => {}
+                }
+
+                if let Err(e) = Self::check_root(&watcher, &root, &root_lost).await {
+                    tracing::warn!("Failed to resume watching {}: {:#}", root.display(), e);
+                }
+            }
+        }.instrument(span));
+    }
+
+    /// 감시 루트 상태가 바뀌었으면 감시를 멈추거나 재개합니다.
+    ///
+    /// # Process Flow
+    /// 1. 루트를 읽을 수 없게 되면 감시를 해제하고 `WatchRootLost` 이벤트 발생
+    ///    (사라진 파일을 Deleted로 표시하지 않음)
+    /// 2. 루트가 다시 나타나면 감시를 다시 등록하고 재조정 스캔 후 `WatchRootRestored` 이벤트 발생
+    ///
+    /// # Returns
+    /// * `Result<Option<PebbleEvent>>` - 상태가 바뀌었으면 발생시킨 이벤트
+    async fn check_root(
+        watcher: &Mutex<RecommendedWatcher>,
+        root: &Path,
+        root_lost: &AtomicBool,
+    ) -> Result<Option<PebbleEvent>> {
+        let available = {
+            let root = root.to_path_buf();
+            task::spawn_blocking(move || root_available(&root)).await?
+        };
+        let root_str = paths::normalize(root);
+
+        let event = match (available, root_lost.load(Ordering::SeqCst)) {
+            (false, false) => {
+                root_lost.store(true, Ordering::SeqCst);
+                if let Ok(mut watcher) = watcher.lock() {
+                    // 이미 사라진 경로는 해제에 실패할 수 있음
+                    let _ = watcher.unwatch(root);
+                }
+
+                tracing::warn!("Watch root {} is no longer available, pausing watch", root_str);
+                service::record_error(ServiceKind::Watcher, format!("Watch root lost: {}", root_str));
+                PebbleEvent::WatchRootLost { root: root_str }
+            }
+            (true, true) => {
+                watcher
+                    .lock()
+                    .map_err(|e| anyhow::anyhow!("Failed to acquire watcher lock: {}", e))?
+                    .watch(root, RecursiveMode::Recursive)
+                    .with_context(|| format!("Failed to watch directory: {}", root_str))?;
+                // 스캔 도중의 변경도 놓치지 않도록 감시를 먼저 재개
+                root_lost.store(false, Ordering::SeqCst);
+
+                let scan_root = root_str.clone();
+                let report = task::spawn_blocking(move || db::reconcile_directory(&scan_root)).await??;
+
+                tracing::info!("Watch root {} is available again, resumed watching", root_str);
+                PebbleEvent::WatchRootRestored {
+                    root: root_str,
+                    added: report.added,
+                    modified: report.modified,
+                    deleted: report.deleted,
+                }
+            }
+            _ => return Ok(None),
+        };
+
+        events::emit(event.clone());
+        Ok(Some(event))
+    }
+
     /// 파일 시스템 이벤트를 처리하는 백그라운드 태스크를 생성합니다.
     ///
     /// # Arguments
+    /// * `tasks` - 태스크를 등록할 감독자
     /// * `rx` - 이벤트 수신 채널
     ///
     /// # Architecture
     /// - tokio 런타임에서 비동기로 실행
     /// - 블로킹 작업(파일 I/O, DB 작업)은 별도 스레드에서 처리
     /// - UI 스레드를 방해하지 않도록 설계
-    fn spawn_event_handler(rx: Receiver<notify::Result<Event>>) {
-        tokio::spawn(async move {
+    fn spawn_event_handler(
+        tasks: &TaskSupervisor,
+        rx: Receiver<notify::Result<Event>>,
+        root: PathBuf,
+        root_lost: Arc<AtomicBool>,
+        span: tracing::Span,
+    ) {
+        tasks.spawn("event_handler", |token| async move {
             // Arc<Mutex>로 Receiver를 감싸서 여러 태스크에서 안전하게 사용
             let rx = Arc::new(Mutex::new(rx));
 
             loop {
                 // 이벤트 수신 (블로킹 작업이므로 spawn_blocking 사용)
                 let rx_clone = Arc::clone(&rx);
-                let event_result = task::spawn_blocking(move || {
+                let receive = task::spawn_blocking(move || {
                     let rx = rx_clone.lock().unwrap();
                     rx.recv()
-                })
-                .await;
+                });
+
+                let event_result = tokio::select! {
+                    result = receive => result,
+                    _ = token.cancelled() => break,
+                };
 
                 match event_result {
                     Ok(Ok(Ok(event))) => {
+                        // 감시 루트를 잃은 동안(분리 중 쏟아지는 삭제 이벤트 포함)은 무시
+                        if root_lost.load(Ordering::SeqCst) {
+                            continue;
+                        }
+
                         // 이벤트 처리
-                        if let Err(e) = Self::handle_event(event).await {
-                            log::error!("Error handling file event: {}", e);
+                        if let Err(e) = Self::handle_event(event, &root).await {
+                            tracing::error!("Error handling file event: {}", e);
+                            service::record_error(ServiceKind::Watcher, format!("{:#}", e));
                         }
                     }
                     Ok(Ok(Err(e))) => {
-                        log::error!("File watcher error: {}", e);
+                        tracing::error!("File watcher error: {}", e);
+                        service::record_error(ServiceKind::Watcher, format!("File watcher error: {}", e));
                     }
                     Ok(Err(_)) => {
                         // 채널이 닫힘 (감시 종료)
-                        log::info!("File watcher channel closed");
+                        tracing::info!("File watcher channel closed");
                         break;
                     }
                     Err(e) => {
-                        log::error!("Task join error: {}", e);
-                        break;
+                        return Err(anyhow::anyhow!("Event receiver task failed: {}", e));
                     }
                 }
             }
-        });
+
+            Ok(())
+        }.instrument(span));
     }
 
     /// 파일 시스템 이벤트를 처리합니다.
     ///
     /// # Arguments
     /// * `event` - notify 이벤트
+    /// * `root` - 감시 루트
     ///
     /// # Process Flow
     /// 1. 이벤트 타입 분류 (Create/Modify/Remove)
     /// 2. 파일 경로 추출
     /// 3. 해당 작업 수행 (해시 계산 및 DB 업데이트)
-    async fn handle_event(event: Event) -> Result<()> {
+    async fn handle_event(event: Event, root: &Path) -> Result<()> {
         let file_event = match event.kind {
             EventKind::Create(CreateKind::File) => {
-                if let Some(path) = event.paths.first() {
-                    Some(FileEvent::Created(path.clone()))
-                } else {
-                    None
-                }
+                event.paths.first().map(|path| FileEvent::Created(path.clone()))
             }
             EventKind::Modify(ModifyKind::Data(_)) => {
-                if let Some(path) = event.paths.first() {
-                    Some(FileEvent::Modified(path.clone()))
-                } else {
-                    None
-                }
+                event.paths.first().map(|path| FileEvent::Modified(path.clone()))
             }
             EventKind::Remove(RemoveKind::File) => {
-                if let Some(path) = event.paths.first() {
-                    Some(FileEvent::Removed(path.clone()))
-                } else {
-                    None
+                // 루트째 사라지는 중이면(루트 확인 주기 전) 파일 삭제로 보지 않음
+                if !root_available(root) {
+                    return Ok(());
                 }
+                event.paths.first().map(|path| FileEvent::Removed(path.clone()))
             }
             _ => None, // 다른 이벤트는 무시
         };
@@ -178,16 +308,13 @@ impl FileWatcher {
             FileEvent::Created(path) | FileEvent::Modified(path) => {
                 // 블로킹 작업이므로 spawn_blocking 사용
                 task::spawn_blocking(move || -> Result<()> {
-                    // 파일이 실제로 존재하고 디렉토리가 아닌지 확인
-                    if !path.exists() || !path.is_file() {
+                    // 파일이 실제로 존재하고 디렉토리가 아닌지 확인 (루트 식별자 파일, 수신 임시 파일 제외)
+                    if !path.exists() || !path.is_file() || volume::is_marker(&path) || locked::is_staging(&path) {
                         return Ok(());
                     }
 
-                    let path_str = path.to_string_lossy().to_string();
-
-                    // 파일 해시 계산
-                    let file_hash = integrity::calculate_file_hash(&path)
-                        .with_context(|| format!("Failed to calculate hash for: {}", path_str))?;
+                    let path_str = paths::normalize(&path);
+                    let path = paths::long_path(&path);
 
                     // 파일 수정 시간 가져오기
                     let metadata = std::fs::metadata(&path)
@@ -200,16 +327,22 @@ impl FileWatcher {
                         .unwrap_or_default()
                         .as_secs() as i64;
 
+                    // 파일 해시 계산 (크기와 수정 시각만 쓰는 루트는 해시하지 않음)
+                    let mode = config::current().change_detection_for(&path_str);
+                    let file_hash = integrity::change_hash(&path, mode, metadata.len(), last_modified)
+                        .with_context(|| format!("Failed to calculate hash for: {}", path_str))?;
+
                     // DB에 파일 정보 업데이트 (Upsert)
-                    db::upsert_file(FileMetadata {
-                        path: path_str.clone(),
+                    db::upsert_file(FileMetadata::new(
+                        path_str.clone(),
                         last_modified,
                         file_hash,
-                        sync_status: "Pending".to_string(),
-                    })
+                        SyncStatus::Pending,
+                        metadata.len() as i64,
+                    ))
                     .with_context(|| format!("Failed to update DB for: {}", path_str))?;
 
-                    log::info!("File change recorded: {} (status: Pending)", path_str);
+                    tracing::info!("File change recorded: {} (status: Pending)", path_str);
 
                     Ok(())
                 })
@@ -217,17 +350,17 @@ impl FileWatcher {
                 .context("Task execution failed")??;
             }
             FileEvent::Removed(path) => {
-                let path_str = path.to_string_lossy().to_string();
+                let path_str = paths::normalize(&path);
 
                 // 삭제된 파일은 DB에서 sync_status를 "Deleted"로 업데이트
                 // (완전히 삭제하지 않고 상태만 변경하여 동기화 추적 가능)
                 task::spawn_blocking(move || -> Result<()> {
                     // 파일이 DB에 존재하는지 확인
                     if let Ok(Some(_)) = db::get_file_metadata(&path_str) {
-                        db::update_sync_status(&path_str, "Deleted")
+                        db::update_sync_status(&path_str, &SyncStatus::Deleted)
                             .with_context(|| format!("Failed to mark file as deleted: {}", path_str))?;
 
-                        log::info!("File marked as deleted: {}", path_str);
+                        tracing::info!("File marked as deleted: {}", path_str);
                     }
 
                     Ok(())
@@ -268,24 +401,115 @@ pub fn start_watching(path: &str) -> Result<()> {
 
     *instance = Some(watcher);
 
-    log::info!("File watcher started successfully for: {}", path);
+    tracing::info!("File watcher started successfully for: {}", path);
 
     Ok(())
 }
 
-/// 파일 감시를 중지합니다.
+/// 파일 감시를 중지하고 이벤트 처리 태스크가 끝날 때까지 기다립니다.
 ///
-/// # Notes
-/// - 감시자 인스턴스를 제거하면 자동으로 감시가 중지됩니다
-pub fn stop_watching() -> Result<()> {
-    let mut instance = WATCHER_INSTANCE
+/// # Returns
+/// * `Result<()>` - 감시 중 에러로 끝난 태스크가 있으면 에러
+pub async fn stop_watching() -> Result<()> {
+    let watcher = WATCHER_INSTANCE
         .lock()
-        .map_err(|e| anyhow::anyhow!("Failed to acquire watcher lock: {}", e))?;
+        .map_err(|e| anyhow::anyhow!("Failed to acquire watcher lock: {}", e))?
+        .take();
 
-    if instance.is_some() {
-        *instance = None;
-        log::info!("File watcher stopped");
+    if let Some(watcher) = watcher {
+        watcher.shutdown().await?;
+        tracing::info!("File watcher stopped");
     }
 
     Ok(())
 }
+
+/// 호스트(Android SAF)가 감지한 문서 변경을 기록합니다.
+///
+/// SAF 문서 트리는 파일 시스템 감시를 사용할 수 없으므로, 호스트가 변경을 감지하면
+/// 문서 URI와 읽기용 파일 디스크립터를 넘겨주고 여기서 해시를 계산해 DB에 기록합니다.
+///
+/// # Arguments
+/// * `uri` - 문서 URI (DB 경로 키로 그대로 사용)
+/// * `fd` - 읽기 모드로 연 파일 디스크립터 (소유권이 넘어오며 함수가 끝나면 닫힘)
+/// * `last_modified` - 문서 수정 시간 (Unix timestamp)
+pub fn record_host_file_change(uri: &str, fd: i32, last_modified: i64) -> Result<()> {
+    let file = storage::file_from_fd(fd)?;
+    let file_size = file.metadata().map(|m| m.len() as i64).unwrap_or(0);
+
+    let file_hash = integrity::hash_reader(file)
+        .with_context(|| format!("Failed to calculate hash for: {}", uri))?;
+
+    db::upsert_file(FileMetadata::new(uri.to_string(), last_modified, file_hash, SyncStatus::Pending, file_size))
+    .with_context(|| format!("Failed to update DB for: {}", uri))?;
+
+    tracing::info!("Host file change recorded: {} (status: Pending)", uri);
+
+    Ok(())
+}
+
+/// 호스트(Android SAF)가 감지한 문서 삭제를 기록합니다.
+pub fn record_host_file_removed(uri: &str) -> Result<()> {
+    if db::get_file_metadata(uri)?.is_some() {
+        db::update_sync_status(uri, &SyncStatus::Deleted)
+            .with_context(|| format!("Failed to mark file as deleted: {}", uri))?;
+
+        tracing::info!("Host file marked as deleted: {}", uri);
+    }
+
+    Ok(())
+}
+
+/// 감시 루트를 잃어 감시가 멈춘 상태인지 확인합니다.
+pub fn is_watch_root_lost() -> bool {
+    WATCHER_INSTANCE
+        .lock()
+        .ok()
+        .and_then(|instance| instance.as_ref().map(|watcher| watcher.root_lost.load(Ordering::SeqCst)))
+        .unwrap_or(false)
+}
+
+/// 현재 감시 중인 경로를 반환합니다.
+pub fn current_watch_path() -> Option<String> {
+    let instance = WATCHER_INSTANCE.lock().ok()?;
+    instance
+        .as_ref()
+        .map(|watcher| watcher.watch_path.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_lost_root_pauses_and_resumes_with_reconcile() {
+        crate::loopback::use_temp_environment();
+        let parent = tempfile::TempDir::new().unwrap();
+        let root = parent.path().join("drive");
+        std::fs::create_dir(&root).unwrap();
+        std::fs::write(root.join("photo.jpg"), b"photo").unwrap();
+        let photo = paths::normalize(root.join("photo.jpg"));
+        db::reconcile_directory(&paths::normalize(&root)).unwrap();
+
+        let watcher = Mutex::new(notify::recommended_watcher(|_: notify::Result<Event>| {}).unwrap());
+        watcher.lock().unwrap().watch(&root, RecursiveMode::Recursive).unwrap();
+        let root_lost = AtomicBool::new(false);
+        assert_eq!(FileWatcher::check_root(&watcher, &root, &root_lost).await.unwrap(), None);
+
+        // 드라이브 분리: 감시는 멈추고 파일은 Deleted로 표시되지 않음
+        let unplugged = parent.path().join("unplugged");
+        std::fs::rename(&root, &unplugged).unwrap();
+        let event = FileWatcher::check_root(&watcher, &root, &root_lost).await.unwrap();
+        assert!(matches!(event, Some(PebbleEvent::WatchRootLost { .. })));
+        assert!(root_lost.load(Ordering::SeqCst));
+        assert!(db::reconcile_directory(&paths::normalize(&root)).is_err());
+        assert_ne!(db::get_file_metadata(&photo).unwrap().unwrap().sync_status, SyncStatus::Deleted);
+
+        // 다시 연결: 분리된 동안 추가된 파일을 재조정 스캔으로 반영
+        std::fs::write(unplugged.join("new.jpg"), b"new").unwrap();
+        std::fs::rename(&unplugged, &root).unwrap();
+        let event = FileWatcher::check_root(&watcher, &root, &root_lost).await.unwrap();
+        assert!(matches!(event, Some(PebbleEvent::WatchRootRestored { added: 1, deleted: 0, .. })));
+        assert!(!root_lost.load(Ordering::SeqCst));
+    }
+}
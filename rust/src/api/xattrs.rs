@@ -0,0 +1,125 @@
+//! 확장 속성 보존 (Extended Attributes)
+//!
+//! Mac끼리 주고받을 때 Finder 태그와 색상(`com.apple.FinderInfo`, `com.apple.metadata:_kMDItemUserTags`),
+//! 리소스 포크(`com.apple.ResourceFork`)처럼 파일 내용 밖에 있는 메타데이터가 사라지지 않도록
+//! 전송 요청에 확장 속성을 실어 보내고 받은 파일에 복원합니다.
+//! 양쪽 모두 설정의 `preserve_xattrs`가 켜져 있어야 하며, 확장 속성이 없는 플랫폼(Windows)에서는 아무것도 하지 않습니다.
+//!
+//! # Process Flow
+//! 1. 송신측: `read`로 파일의 확장 속성을 읽어 `TransferRequest.xattrs`에 담음 (설정이 꺼져 있으면 빈 목록)
+//! 2. 수신측: 파일을 다 받고 검증한 뒤 `restore`로 확장 속성을 기록 (설정이 꺼져 있으면 무시)
+//! 3. 시스템/보안 네임스페이스의 속성이나 이 파일 시스템에 기록할 수 없는 속성은 경고만 남기고 건너뜀
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// 전송 요청 하나에 싣는 확장 속성의 최대 합계 크기 (이름 + 값, 1MB)
+///
+/// 리소스 포크가 이보다 크면 확장 속성을 보내지 않습니다 (파일 내용은 그대로 전송).
+pub const MAX_XATTR_BYTES: usize = 1024 * 1024;
+
+/// 보내지도 복원하지도 않는 네임스페이스 (커널/보안 모듈이 관리)
+const SKIPPED_PREFIXES: &[&str] = &["system.", "security.", "trusted."];
+
+/// 확장 속성 하나
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtendedAttribute {
+    pub name: String,
+    pub value: Vec<u8>,
+}
+
+/// 주고받을 수 있는 속성 이름인지 확인합니다.
+fn is_portable(name: &str) -> bool {
+    !SKIPPED_PREFIXES.iter().any(|prefix| name.starts_with(prefix))
+}
+
+/// 파일의 확장 속성을 읽습니다.
+///
+/// 합계가 `MAX_XATTR_BYTES`를 넘으면 일부만 보내지 않고 모두 생략합니다.
+///
+/// # Returns
+/// * 확장 속성이 없거나 지원하지 않는 플랫폼이면 빈 목록
+#[cfg(unix)]
+pub fn read(path: &Path) -> Result<Vec<ExtendedAttribute>> {
+    let mut attributes = Vec::new();
+    let mut total = 0;
+
+    for name in xattr::list(path)? {
+        let Some(name) = name.to_str().filter(|name| is_portable(name)).map(str::to_string) else {
+            continue;
+        };
+        // 목록을 읽은 뒤 지워진 속성은 건너뜀
+        let Some(value) = xattr::get(path, &name)? else {
+            continue;
+        };
+
+        total += name.len() + value.len();
+        if total > MAX_XATTR_BYTES {
+            tracing::warn!("Extended attributes of {} exceed {} bytes, not sending them", path.display(), MAX_XATTR_BYTES);
+            return Ok(Vec::new());
+        }
+        attributes.push(ExtendedAttribute { name, value });
+    }
+
+    Ok(attributes)
+}
+
+#[cfg(not(unix))]
+pub fn read(_path: &Path) -> Result<Vec<ExtendedAttribute>> {
+    Ok(Vec::new())
+}
+
+/// 받은 파일에 확장 속성을 기록합니다.
+///
+/// 기록하지 못한 속성(다른 플랫폼 전용 이름, 파일 시스템 미지원 등)은 경고만 남깁니다.
+///
+/// # Returns
+/// * 기록한 속성 수
+#[cfg(unix)]
+pub fn restore(path: &Path, attributes: &[ExtendedAttribute]) -> usize {
+    attributes
+        .iter()
+        .filter(|attribute| is_portable(&attribute.name))
+        .filter(|attribute| match xattr::set(path, &attribute.name, &attribute.value) {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!("Failed to restore extended attribute {} on {}: {}", attribute.name, path.display(), e);
+                false
+            }
+        })
+        .count()
+}
+
+#[cfg(not(unix))]
+pub fn restore(_path: &Path, _attributes: &[ExtendedAttribute]) -> usize {
+    0
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_user_attributes() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let source = dir.path().join("tagged.txt");
+        let target = dir.path().join("received.txt");
+        std::fs::write(&source, b"a").unwrap();
+        std::fs::write(&target, b"a").unwrap();
+
+        // tmpfs 등 확장 속성을 지원하지 않는 파일 시스템에서는 확인할 수 없음
+        if xattr::set(&source, "user.pebble.tag", b"red").is_err() {
+            return;
+        }
+
+        let attributes = read(&source).unwrap();
+        assert!(attributes.contains(&ExtendedAttribute { name: "user.pebble.tag".to_string(), value: b"red".to_vec() }));
+
+        // 보안 네임스페이스는 복원하지 않음
+        let skipped = ExtendedAttribute { name: "security.selinux".to_string(), value: b"x".to_vec() };
+        let restored = restore(&target, &[attributes.clone(), vec![skipped]].concat());
+        assert_eq!(restored, attributes.len());
+        assert_eq!(xattr::get(&target, "user.pebble.tag").unwrap(), Some(b"red".to_vec()));
+    }
+}
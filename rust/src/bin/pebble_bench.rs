@@ -0,0 +1,117 @@
+//! 전송 처리량 벤치마크
+//!
+//! 실제 TLS 전송 경로로 생성된 데이터를 보내 처리량, 단계별 지연 시간, CPU 사용량을 출력합니다.
+//!
+//! # 사용법
+//! ```bash
+//! # 터미널 1 - 수신측
+//! cargo run --release --bin pebble_bench -- server
+//!
+//! # 터미널 2 - 송신측 (256MB, 3회 반복)
+//! cargo run --release --bin pebble_bench -- client 127.0.0.1 --size-mb 256 --runs 3
+//! ```
+
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use native::api::certificate::CertificateManager;
+use native::api::logging;
+use native::api::speedtest::{self, SpeedTestReport};
+use native::api::transfer::{TransferServer, TRANSFER_PORT};
+use std::net::SocketAddr;
+
+const CERT_DIR: &str = "/tmp/pebble_bench_certs";
+
+#[derive(Parser)]
+#[command(name = "pebble_bench", about = "Pebble transfer throughput benchmark")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// 속도 측정 요청을 받는 전송 서버를 실행합니다
+    Server {
+        #[arg(long, default_value_t = TRANSFER_PORT)]
+        port: u16,
+    },
+
+    /// 서버로 속도 측정을 실행합니다
+    Client {
+        /// 서버 IP 주소
+        ip: String,
+
+        #[arg(long, default_value_t = TRANSFER_PORT)]
+        port: u16,
+
+        /// 보낼 데이터 크기 (MB)
+        #[arg(long, default_value_t = 64)]
+        size_mb: u64,
+
+        /// 반복 횟수
+        #[arg(long, default_value_t = 1)]
+        runs: u32,
+
+        /// 서버 인증서 핑거프린트 (Certificate Pinning)
+        #[arg(long)]
+        fingerprint: Option<String>,
+    },
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    logging::init();
+
+    match Cli::parse().command {
+        Command::Server { port } => run_server(port).await,
+        Command::Client { ip, port, size_mb, runs, fingerprint } => {
+            run_client(&ip, port, size_mb, runs, fingerprint).await
+        }
+    }
+}
+
+async fn run_server(port: u16) -> Result<()> {
+    let cert = CertificateManager::new(CERT_DIR.to_string())
+        .get_or_create_certificate("pebble-bench", "Pebble Bench")?;
+
+    println!("Fingerprint: {}", cert.fingerprint);
+
+    let server = TransferServer::new(cert);
+    let bind_addr: SocketAddr = format!("0.0.0.0:{}", port).parse()?;
+    server.start(bind_addr).await
+}
+
+async fn run_client(ip: &str, port: u16, size_mb: u64, runs: u32, fingerprint: Option<String>) -> Result<()> {
+    let server_addr: SocketAddr = format!("{}:{}", ip, port).parse()
+        .with_context(|| format!("Invalid server address: {}:{}", ip, port))?;
+
+    let mut reports = Vec::new();
+    for run in 1..=runs {
+        let report = speedtest::run(server_addr, fingerprint.clone(), size_mb * 1024 * 1024).await?;
+        println!("\n--- Run {}/{} ---", run, runs);
+        print_report(&report);
+        reports.push(report);
+    }
+
+    if reports.len() > 1 {
+        let avg = reports.iter().map(|r| r.throughput_mbps).sum::<f64>() / reports.len() as f64;
+        let best = reports.iter().map(|r| r.throughput_mbps).fold(0.0, f64::max);
+        println!("\nAverage: {:.2} MB/s, best: {:.2} MB/s", avg, best);
+    }
+
+    Ok(())
+}
+
+fn print_report(report: &SpeedTestReport) {
+    println!("Bytes sent:       {} ({} byte chunks)", report.bytes_sent, report.chunk_size);
+    println!("TCP connect:      {:.2} ms", report.connect_ms);
+    println!("TLS handshake:    {:.2} ms", report.tls_handshake_ms);
+    println!("Request RTT:      {:.2} ms", report.request_rtt_ms);
+    println!("Transfer:         {:.0} ms (server {:.0} ms)", report.transfer_ms, report.server_receive_ms);
+    println!("Avg chunk RTT:    {:.2} ms", report.avg_chunk_rtt_ms);
+    println!("Throughput:       {:.2} MB/s", report.throughput_mbps);
+    match (report.cpu_time_ms, report.cpu_usage_percent) {
+        (Some(cpu), Some(percent)) => println!("CPU:              {:.0} ms ({:.0}%)", cpu, percent),
+        _ => println!("CPU:              n/a"),
+    }
+}
@@ -0,0 +1,567 @@
+//! Pebble 헤드리스 데몬
+//!
+//! Flutter 없이 NAS나 서버에서 Pebble을 실행하기 위한 CLI입니다.
+//!
+//! # 사용법
+//! ```bash
+//! # 발견 + 전송 서버 + 폴더 동기화 실행 (Ctrl+C로 종료)
+//! pebbled --data-dir /var/lib/pebble serve --name nas --secret "$PEBBLE_SECRET" --watch /srv/share
+//!
+//! # 파일 전송
+//! pebbled send 192.168.1.100 /tmp/report.pdf
+//!
+//! # 네트워크의 기기 목록
+//! pebbled devices --secret "$PEBBLE_SECRET" --wait 5
+//!
+//! # 폴더 변경 감시만 실행
+//! pebbled watch /srv/share
+//!
+//! # 드롭 폴더: 새로 생기거나 바뀐 파일을 다른 기기로 자동 전송
+//! pebbled forward /srv/outbox 192.168.1.100 --fingerprint a8f5f167...
+//!
+//! # 저장된 해시와 비교하여 손상된 파일 검사
+//! pebbled verify /srv/share
+//!
+//! # 로컬 상태 및 자가 진단
+//! pebbled status
+//!
+//! # 스크립트/CI용 JSON 출력 (명령마다 JSON 한 줄, serve는 이벤트마다 한 줄)
+//! pebbled --json devices --secret "$PEBBLE_SECRET" | jq '.[].device_name'
+//!
+//! # 전송/동기화/발견 span을 JSON 줄로 기록 (여러 기기의 기록을 transfer_id로 대조)
+//! pebbled --trace-dir /var/log/pebble serve --name nas --secret "$PEBBLE_SECRET"
+//!
+//! # 스크립트, Home Assistant용 로컬 제어 API (토큰: <data_dir>/control.token)
+//! pebbled serve --name nas --secret "$PEBBLE_SECRET" --control-addr 127.0.0.1:37850
+//! curl -H "Authorization: Bearer $(cat pebble-data/control.token)" http://127.0.0.1:37850/v1/devices
+//! websocat "ws://127.0.0.1:37850/v1/events?token=$(cat pebble-data/control.token)"
+//!
+//! # Prometheus 메트릭 노출 (`--features metrics`로 빌드)
+//! pebbled serve --name nas --secret "$PEBBLE_SECRET" --metrics-addr 0.0.0.0:9464
+//!
+//! # OTLP 수집기(Jaeger, Tempo 등)로 span 전송 (`--features otlp`로 빌드)
+//! pebbled --otlp-endpoint http://localhost:4318/v1/traces serve --name nas --secret "$PEBBLE_SECRET"
+//! ```
+
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use serde::Serialize;
+use native::api::config::{self, PebbleConfig};
+use native::api::logging::{self, LogLevel};
+use native::api::service::{self, PebbleStartOptions};
+use native::api::transfer::TransferClient;
+use native::api::forward::{self, ForwardOptions, Forwarder};
+use native::api::{control, db, diagnostics, discovery, events, volume, watcher};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+#[derive(Parser)]
+#[command(name = "pebbled", version, about = "Headless Pebble daemon")]
+struct Cli {
+    /// 설정, DB, 기기 ID, 인증서를 저장할 데이터 디렉토리
+    #[arg(long, global = true, env = "PEBBLE_DATA_DIR", default_value = "pebble-data")]
+    data_dir: String,
+
+    /// 디버그 로그 출력
+    #[arg(short, long, global = true)]
+    verbose: bool,
+
+    /// span/이벤트를 JSON 줄로 내보낼 디렉토리
+    #[arg(long, global = true, env = "PEBBLE_TRACE_DIR")]
+    trace_dir: Option<String>,
+
+    /// 결과를 사람이 읽는 표 대신 JSON으로 출력
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// span을 내보낼 OTLP 수집기 주소 (예: http://localhost:4318/v1/traces)
+    #[cfg(feature = "otlp")]
+    #[arg(long, global = true, env = "PEBBLE_OTLP_ENDPOINT")]
+    otlp_endpoint: Option<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// 발견, 전송 서버, 폴더 동기화를 실행합니다
+    Serve {
+        /// 다른 기기에 표시될 이름
+        #[arg(long, default_value = "pebbled")]
+        name: String,
+
+        /// 비콘 HMAC 인증용 비밀 키
+        #[arg(long, env = "PEBBLE_SECRET")]
+        secret: String,
+
+        /// 감시할 동기화 폴더
+        #[arg(long)]
+        watch: Option<String>,
+
+        /// 로컬 제어 API 주소 (루프백만 허용, 예: 127.0.0.1:37850)
+        #[arg(long, env = "PEBBLE_CONTROL_ADDR")]
+        control_addr: Option<SocketAddr>,
+
+        /// Prometheus 메트릭 리스너 주소 (예: 127.0.0.1:9464)
+        #[cfg(feature = "metrics")]
+        #[arg(long, env = "PEBBLE_METRICS_ADDR")]
+        metrics_addr: Option<SocketAddr>,
+    },
+
+    /// 파일을 다른 기기로 전송합니다
+    Send {
+        /// 수신 기기 IP 주소
+        ip: String,
+
+        /// 전송할 파일 경로
+        file: String,
+
+        /// 수신 기기 포트 (기본값: 설정의 transfer_port)
+        #[arg(long)]
+        port: Option<u16>,
+
+        /// 수신 기기 인증서 핑거프린트 (Certificate Pinning)
+        #[arg(long)]
+        fingerprint: Option<String>,
+    },
+
+    /// 네트워크에서 발견된 기기를 출력합니다
+    Devices {
+        /// 비콘 HMAC 인증용 비밀 키
+        #[arg(long, env = "PEBBLE_SECRET")]
+        secret: String,
+
+        /// 이 기기의 이름
+        #[arg(long, default_value = "pebbled")]
+        name: String,
+
+        /// 비콘을 기다릴 시간 (초)
+        #[arg(long, default_value_t = 5)]
+        wait: u64,
+    },
+
+    /// 폴더 변경을 감시하여 DB에 기록합니다
+    Watch {
+        /// 감시할 폴더
+        path: String,
+    },
+
+    /// 폴더를 감시하여 새로 생기거나 바뀐 파일을 다른 기기로 자동 전송합니다
+    Forward {
+        /// 감시할 폴더
+        path: String,
+
+        /// 수신 기기 IP 주소
+        ip: String,
+
+        /// 수신 기기 포트 (기본값: 설정의 transfer_port)
+        #[arg(long)]
+        port: Option<u16>,
+
+        /// 수신 기기 인증서 핑거프린트 (Certificate Pinning)
+        #[arg(long)]
+        fingerprint: Option<String>,
+
+        /// 파일당 최대 전송 시도 횟수
+        #[arg(long, default_value_t = 5)]
+        max_attempts: u32,
+
+        /// 전달 기록 파일 (기본값: <data_dir>/forward.jsonl)
+        #[arg(long)]
+        log: Option<String>,
+    },
+
+    /// 폴더의 파일을 다시 해시하여 저장된 해시와 비교합니다
+    Verify {
+        /// 검사할 동기화 폴더
+        path: String,
+    },
+
+    /// 로컬 상태와 자가 진단 결과를 출력합니다
+    Status,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    #[cfg(feature = "otlp")]
+    if let Some(endpoint) = &cli.otlp_endpoint {
+        logging::init_with_otlp(endpoint)?;
+    }
+    logging::init();
+    if cli.verbose {
+        logging::set_level(LogLevel::Debug);
+    }
+    if let Some(dir) = &cli.trace_dir {
+        logging::enable_trace_export(dir)?;
+    }
+
+    load_config(&cli.data_dir)?;
+
+    let json = cli.json;
+    let result = match cli.command {
+        Command::Serve {
+            name,
+            secret,
+            watch,
+            control_addr,
+            #[cfg(feature = "metrics")]
+            metrics_addr,
+        } => {
+            #[cfg(feature = "metrics")]
+            if let Some(addr) = metrics_addr {
+                let bound = native::api::metrics::start_listener(addr).await?;
+                if !json {
+                    println!("Metrics:      http://{}/metrics", bound);
+                }
+            }
+            serve(&cli.data_dir, name, secret, watch, control_addr, json).await
+        }
+        Command::Send { ip, file, port, fingerprint } => send(&ip, &file, port, fingerprint, json).await,
+        Command::Devices { secret, name, wait } => devices(&cli.data_dir, name, secret, wait, json).await,
+        Command::Watch { path } => watch(&path, json).await,
+        Command::Forward { path, ip, port, fingerprint, max_attempts, log } => {
+            let log_path = log
+                .map(std::path::PathBuf::from)
+                .unwrap_or_else(|| Path::new(&cli.data_dir).join(forward::FORWARD_LOG_FILE_NAME));
+            forward_folder(&path, &ip, port, fingerprint, max_attempts, log_path, json).await
+        }
+        Command::Verify { path } => verify(&path, json),
+        Command::Status => status(&cli.data_dir, json),
+    };
+
+    logging::shutdown();
+
+    result
+}
+
+/// 값을 JSON 한 줄로 출력합니다 (`--json`).
+fn print_json(value: &impl Serialize) -> Result<()> {
+    println!("{}", serde_json::to_string(value)?);
+    Ok(())
+}
+
+/// 데이터 디렉토리의 설정을 로드합니다.
+///
+/// 상대 경로 DB는 `config::load`가 데이터 디렉토리 기준으로 바꿉니다.
+fn load_config(data_dir: &str) -> Result<PebbleConfig> {
+    std::fs::create_dir_all(data_dir).with_context(|| format!("Failed to create data directory: {}", data_dir))?;
+
+    let current = config::load(data_dir)?;
+
+    db::init_db().context("Failed to initialize database")?;
+
+    Ok(current)
+}
+
+async fn serve(
+    data_dir: &str,
+    name: String,
+    secret: String,
+    watch: Option<String>,
+    control_addr: Option<SocketAddr>,
+    json: bool,
+) -> Result<()> {
+    let info = service::start(PebbleStartOptions {
+        app_data_dir: data_dir.to_string(),
+        device_name: name,
+        secret_key: secret,
+        watch_path: watch,
+        config: None,
+    })
+    .await?;
+
+    if json {
+        print_json(&info)?;
+    } else {
+        println!("Device ID:    {}", info.device_id);
+        println!("Fingerprint:  {}", info.fingerprint);
+        println!("Listening on: 0.0.0.0:{}", info.transfer_port);
+    }
+
+    let control_listener = match control_addr {
+        Some(addr) => {
+            let token = control::load_or_create_token(data_dir)?;
+            let listener = control::start_listener(addr, token).await?;
+            if !json {
+                println!("Control API:  http://{}/v1 (token in {})", listener.addr,
+                    Path::new(data_dir).join(control::TOKEN_FILE_NAME).display());
+            }
+            Some(listener)
+        }
+        None => None,
+    };
+    if !json {
+        println!("Press Ctrl+C to stop.");
+    }
+
+    events::subscribe(move |event| {
+        if json {
+            let _ = print_json(event);
+        } else {
+            tracing::info!(target: "pebble::events", "{}", event.summary());
+        }
+        true
+    });
+
+    tokio::signal::ctrl_c().await?;
+
+    if let Some(listener) = &control_listener {
+        listener.stop();
+    }
+    service::stop().await?;
+    if !json {
+        println!("Stopped.");
+    }
+
+    Ok(())
+}
+
+/// `send --json` 결과
+#[derive(Serialize)]
+struct SendResult<'a> {
+    file: &'a str,
+    addr: SocketAddr,
+    bytes: u64,
+    elapsed_ms: u128,
+}
+
+async fn send(ip: &str, file: &str, port: Option<u16>, fingerprint: Option<String>, json: bool) -> Result<()> {
+    let port = port.unwrap_or(config::current().transfer_port);
+    let server_addr: SocketAddr = format!("{}:{}", ip, port).parse()
+        .with_context(|| format!("Invalid server address: {}:{}", ip, port))?;
+
+    let started = Instant::now();
+    let client = TransferClient::new(fingerprint);
+    client.send_file(server_addr, file).await?;
+
+    if json {
+        let bytes = std::fs::metadata(file).map(|metadata| metadata.len()).unwrap_or(0);
+        print_json(&SendResult { file, addr: server_addr, bytes, elapsed_ms: started.elapsed().as_millis() })?;
+    } else {
+        println!("Sent {} to {}", file, server_addr);
+    }
+
+    Ok(())
+}
+
+async fn devices(data_dir: &str, name: String, secret: String, wait: u64, json: bool) -> Result<()> {
+    let device_id = service::load_or_create_device_id(data_dir)?;
+    discovery::start_discovery_with_id(device_id, name, secret, None).await?;
+
+    tokio::time::sleep(Duration::from_secs(wait)).await;
+
+    let devices = discovery::get_discovered_devices()?;
+    discovery::stop_discovery().await?;
+
+    if json {
+        return print_json(&devices);
+    }
+    if devices.is_empty() {
+        println!("No devices found.");
+        return Ok(());
+    }
+
+    println!("{:<38} {:<20} {:<16} {:<8} ONLINE", "DEVICE ID", "NAME", "IP", "PROTO");
+    for device in devices {
+        println!(
+            "{:<38} {:<20} {:<16} {:<8} {}",
+            device.device_id, device.device_name, device.ip_address, device.protocol_version, device.is_online
+        );
+    }
+
+    Ok(())
+}
+
+/// 다른 경로로 다시 연결된 드라이브면 기존 기록을 새 경로로 옮깁니다.
+fn attach_volume(path: &str, json: bool) {
+    match volume::attach_root(path) {
+        Ok(Some(previous)) if !json => println!("Re-associated {} (previously at {})", path, previous),
+        Ok(_) => {}
+        Err(e) => eprintln!("Warning: failed to attach volume identity: {:#}", e),
+    }
+}
+
+/// 초기 스캔 결과를 출력합니다.
+fn print_initial_scan(report: &db::ReconcileReport, json: bool) -> Result<()> {
+    if json {
+        return print_json(report);
+    }
+    println!(
+        "Initial scan: {} added, {} modified, {} deleted",
+        report.added, report.modified, report.deleted
+    );
+    Ok(())
+}
+
+async fn watch(path: &str, json: bool) -> Result<()> {
+    attach_volume(path, json);
+    print_initial_scan(&db::reconcile_directory(path)?, json)?;
+
+    watcher::start_watching(path)?;
+    if !json {
+        println!("Watching {} (Ctrl+C to stop)", path);
+    }
+
+    tokio::signal::ctrl_c().await?;
+
+    watcher::stop_watching().await?;
+
+    Ok(())
+}
+
+async fn forward_folder(
+    path: &str,
+    ip: &str,
+    port: Option<u16>,
+    fingerprint: Option<String>,
+    max_attempts: u32,
+    log_path: std::path::PathBuf,
+    json: bool,
+) -> Result<()> {
+    let port = port.unwrap_or(config::current().transfer_port);
+    let peer: SocketAddr = format!("{}:{}", ip, port).parse()
+        .with_context(|| format!("Invalid peer address: {}:{}", ip, port))?;
+
+    // 감시가 중단된 동안 바뀐 파일도 Pending으로 기록되어 전달됨
+    attach_volume(path, json);
+    print_initial_scan(&db::reconcile_directory(path)?, json)?;
+
+    watcher::start_watching(path)?;
+    if !json {
+        println!("Forwarding {} to {} (Ctrl+C to stop)", path, peer);
+        println!("Forward log:  {}", log_path.display());
+    }
+
+    let forwarder = Forwarder::new(ForwardOptions {
+        root: path.to_string(),
+        peer,
+        fingerprint,
+        max_attempts: max_attempts.max(1),
+        log_path: Some(log_path),
+        schedule: None,
+    });
+    let result = forwarder.run_until(tokio::signal::ctrl_c()).await;
+
+    watcher::stop_watching().await?;
+
+    result
+}
+
+fn verify(path: &str, json: bool) -> Result<()> {
+    let report = db::verify_tree(path)?;
+
+    if json {
+        print_json(&report)?;
+        if !report.is_clean() {
+            anyhow::bail!("Integrity check failed");
+        }
+        return Ok(());
+    }
+
+    for file in &report.mismatched {
+        println!("MISMATCH  {}", file);
+    }
+    for file in &report.missing {
+        println!("MISSING   {}", file);
+    }
+    for file in &report.modified {
+        println!("MODIFIED  {}", file);
+    }
+    println!(
+        "Checked {} files: {} mismatched, {} missing, {} modified since last scan",
+        report.checked, report.mismatched.len(), report.missing.len(), report.modified.len()
+    );
+    if report.metadata_only > 0 {
+        println!("Skipped {} files recorded by size and modification time only", report.metadata_only);
+    }
+    if report.unhashed > 0 {
+        println!("Skipped {} files not hashed since the initial scan", report.unhashed);
+    }
+
+    if !report.is_clean() {
+        anyhow::bail!("Integrity check failed");
+    }
+
+    Ok(())
+}
+
+/// `status --json` 결과
+#[derive(Serialize)]
+struct StatusReport<'a> {
+    data_dir: &'a str,
+    db_path: String,
+    transfer_port: u16,
+    discovery_port: u16,
+    download_dir: String,
+    pending_files: usize,
+    diagnostics: diagnostics::DiagnosticsReport,
+}
+
+fn status(data_dir: &str, json: bool) -> Result<()> {
+    let current = config::current();
+
+    if json {
+        let report = StatusReport {
+            data_dir,
+            pending_files: db::get_pending_files()?.len(),
+            diagnostics: diagnostics::run(Some(data_dir)),
+            download_dir: current.receive_dir(),
+            db_path: current.db_path,
+            transfer_port: current.transfer_port,
+            discovery_port: current.discovery_port,
+        };
+        print_json(&report)?;
+        if !report.diagnostics.healthy {
+            anyhow::bail!("Diagnostics reported failures");
+        }
+        return Ok(());
+    }
+
+    println!("Data directory:  {}", data_dir);
+    println!("Database:        {}", current.db_path);
+    println!("Transfer port:   {}", current.transfer_port);
+    println!("Discovery port:  {}", current.discovery_port);
+    println!("Download dir:    {}", current.receive_dir());
+    println!("Pending files:   {}", db::get_pending_files()?.len());
+    println!();
+
+    let report = diagnostics::run(Some(data_dir));
+    for check in &report.checks {
+        println!("[{:?}] {:<16} {}", check.status, check.name, check.message);
+    }
+
+    if !report.healthy {
+        anyhow::bail!("Diagnostics reported failures");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_flag_is_accepted_before_or_after_the_subcommand() {
+        let cli = Cli::try_parse_from(["pebbled", "status", "--json"]).unwrap();
+        assert!(cli.json);
+        assert!(matches!(cli.command, Command::Status));
+
+        let cli = Cli::try_parse_from(["pebbled", "--json", "send", "10.0.0.2", "a.txt"]).unwrap();
+        assert!(cli.json);
+        assert!(matches!(cli.command, Command::Send { ref ip, .. } if ip == "10.0.0.2"));
+
+        assert!(!Cli::try_parse_from(["pebbled", "verify", "/data"]).unwrap().json);
+    }
+
+    #[test]
+    fn test_send_result_is_one_json_object() {
+        let result = SendResult { file: "a.txt", addr: "10.0.0.2:37849".parse().unwrap(), bytes: 5, elapsed_ms: 12 };
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json, serde_json::json!({ "file": "a.txt", "addr": "10.0.0.2:37849", "bytes": 5, "elapsed_ms": 12 }));
+    }
+}
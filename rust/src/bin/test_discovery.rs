@@ -1,30 +1,63 @@
-/// Phase 2 테스트: 기기 탐색 (Discovery)
-///
-/// # 사용법
-/// ```bash
-/// # 터미널 1 (Device A)
-/// cargo run --bin test_discovery device-a
-///
-/// # 터미널 2 (Device B)
-/// cargo run --bin test_discovery device-b
-/// ```
-
-use native::api::discovery;
+//! Phase 2 테스트: 기기 탐색 (Discovery)
+//!
+//! # 사용법
+//! ```bash
+//! # 터미널 1 (Device A)
+//! cargo run --bin test_discovery device-a
+//!
+//! # 터미널 2 (Device B)
+//! cargo run --bin test_discovery device-b
+//!
+//! # 스크립트/CI: 검색이 끝나면 결과를 JSON 한 줄로 출력
+//! cargo run --bin test_discovery -- device-a --json
+//! ```
+//!
+//! 발견된 기기 표가 실시간으로 갱신됩니다. 표가 깨지지 않도록 로그는 Warn 이상만
+//! 출력하며, `RUST_LOG=info`로 전체 로그를 볼 수 있습니다.
+
+use indicatif::{ProgressBar, ProgressStyle};
+use native::api::discovery::{self, DiscoveredDevice};
+use native::api::logging::{self, LogLevel};
+use serde::Serialize;
+use std::collections::BTreeMap;
 use std::env;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use tokio::time::{sleep, Duration};
 
 const SECRET_KEY: &str = "pebble-test-key-2024";
 
+/// 검색 시간 (초)
+const SCAN_SECS: u64 = 30;
+
+/// `--json` 결과
+#[derive(Serialize)]
+struct ScanResult {
+    device_id: String,
+    device_name: String,
+    scan_secs: u64,
+    /// 기기마다 처음 발견되기까지 걸린 시간 (ms)
+    first_seen_ms: BTreeMap<String, u128>,
+    devices: Vec<DiscoveredDevice>,
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    logging::init();
+    if env::var_os("RUST_LOG").is_none() {
+        logging::set_level(LogLevel::Warn);
+    }
 
-    let args: Vec<String> = env::args().collect();
-    let device_name = if args.len() > 1 {
-        args[1].clone()
-    } else {
-        "Test Device".to_string()
-    };
+    let args: Vec<String> = env::args().skip(1).collect();
+    let json = args.iter().any(|arg| arg == "--json");
+    let device_name = args
+        .iter()
+        .find(|arg| !arg.starts_with("--"))
+        .cloned()
+        .unwrap_or_else(|| "Test Device".to_string());
+
+    if json {
+        return scan_json(device_name).await;
+    }
 
     println!("\n{}", "=".repeat(60));
     println!("  Pebble Discovery Test");
@@ -36,23 +69,129 @@ async fn main() -> anyhow::Result<()> {
     let device_id = discovery::start_discovery(device_name, SECRET_KEY.to_string()).await?;
     println!("✅ Device ID: {}\n", device_id);
 
-    println!("🔎 Scanning for 30 seconds...\n");
+    let table = ProgressBar::new(SCAN_SECS);
+    table.set_style(
+        ProgressStyle::with_template("{spinner:.green} Scanning [{elapsed_precise}] {wide_bar:.cyan/blue} {pos}/{len}s\n{msg}")
+            .expect("valid progress template"),
+    );
+    table.enable_steady_tick(Duration::from_millis(120));
 
-    for i in 1..=30 {
-        sleep(Duration::from_secs(1)).await;
+    // 0.5초마다 표 갱신
+    for tick in 1..=SCAN_SECS * 2 {
+        sleep(Duration::from_millis(500)).await;
 
-        let devices = discovery::get_discovered_devices()?;
+        let mut devices = discovery::get_discovered_devices()?;
+        devices.sort_by(|a, b| a.device_name.cmp(&b.device_name));
 
-        if !devices.is_empty() && i % 2 == 0 {
-            println!("\n[{}s] Found {} device(s):", i, devices.len());
-            for d in &devices {
-                println!("  📱 {} - {} ({})", d.device_name, d.ip_address, if d.is_online { "Online" } else { "Offline" });
-            }
-        }
+        table.set_position(tick / 2);
+        table.set_message(render_table(&devices));
     }
 
-    discovery::stop_discovery()?;
+    table.finish();
+    discovery::stop_discovery().await?;
     println!("\n✅ Done");
 
     Ok(())
 }
+
+/// 표 없이 검색한 뒤 결과를 JSON 한 줄로 출력합니다.
+async fn scan_json(device_name: String) -> anyhow::Result<()> {
+    let started = Instant::now();
+    let device_id = discovery::start_discovery(device_name.clone(), SECRET_KEY.to_string()).await?;
+
+    let mut first_seen_ms = BTreeMap::new();
+    while started.elapsed() < Duration::from_secs(SCAN_SECS) {
+        sleep(Duration::from_millis(500)).await;
+        for device in discovery::get_discovered_devices()? {
+            first_seen_ms.entry(device.device_id).or_insert_with(|| started.elapsed().as_millis());
+        }
+    }
+
+    let mut devices = discovery::get_discovered_devices()?;
+    devices.sort_by(|a, b| a.device_name.cmp(&b.device_name));
+    discovery::stop_discovery().await?;
+
+    let result = ScanResult { device_id, device_name, scan_secs: SCAN_SECS, first_seen_ms, devices };
+    println!("{}", serde_json::to_string(&result)?);
+
+    Ok(())
+}
+
+/// 발견된 기기 목록을 표 문자열로 만듭니다.
+fn render_table(devices: &[DiscoveredDevice]) -> String {
+    if devices.is_empty() {
+        return "  (no devices yet)".to_string();
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let mut lines = vec![format!(
+        "  {:<20} {:<16} {:<8} {:<8} {:<9} PINNABLE",
+        "NAME", "IP", "PROTO", "STATUS", "LAST SEEN"
+    )];
+    for d in devices {
+        lines.push(format!(
+            "  {:<20} {:<16} {:<8} {:<8} {:<9} {}",
+            d.device_name,
+            d.ip_address,
+            d.protocol_version,
+            if d.is_online { "Online" } else { "Offline" },
+            format!("{}s ago", now.saturating_sub(d.last_seen)),
+            if d.cert_fingerprint.is_some() { "yes" } else { "no" },
+        ));
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(name: &str, is_online: bool, cert_fingerprint: Option<&str>) -> DiscoveredDevice {
+        serde_json::from_value(serde_json::json!({
+            "device_id": format!("{}-id", name),
+            "device_name": name,
+            "ip_address": "192.168.0.10",
+            "protocol_version": "1.0",
+            "last_seen": 0,
+            "is_online": is_online,
+            "cert_fingerprint": cert_fingerprint,
+            "transfer_port": null,
+            "capabilities": null,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_scan_result_json() {
+        let result = ScanResult {
+            device_id: "me".to_string(),
+            device_name: "device-a".to_string(),
+            scan_secs: SCAN_SECS,
+            first_seen_ms: BTreeMap::from([("laptop-id".to_string(), 1500)]),
+            devices: vec![device("laptop", true, None)],
+        };
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["scan_secs"], SCAN_SECS);
+        assert_eq!(json["first_seen_ms"]["laptop-id"], 1500);
+        assert_eq!(json["devices"][0]["device_name"], "laptop");
+    }
+
+    #[test]
+    fn test_render_table() {
+        assert_eq!(render_table(&[]), "  (no devices yet)");
+
+        let table = render_table(&[device("laptop", true, Some("ab:cd")), device("phone", false, None)]);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].trim_start().starts_with("NAME"));
+        assert!(lines[1].contains("laptop") && lines[1].contains("Online") && lines[1].ends_with("yes"));
+        assert!(lines[2].contains("phone") && lines[2].contains("Offline") && lines[2].ends_with("no"));
+        // 열 너비가 맞춰져 상태 열이 같은 위치에서 시작
+        assert_eq!(lines[1].find("Online"), lines[2].find("Offline"));
+    }
+}
@@ -0,0 +1,270 @@
+//! 통합 테스트: 기기 탐색 + 인증서 고정 + 파일 전송
+//!
+//! 새 네트워크에서 전체 스택(UDP 비콘, HMAC 검증, TLS 인증서 고정, 청크 전송)이
+//! 동작하는지 한 번에 확인합니다.
+//!
+//! # 사용법
+//! ```bash
+//! # 한 프로세스 안에서 두 노드를 실행 (발견 → 핑거프린트 고정 → 전송 → 해시 비교)
+//! cargo run --release --bin test_e2e -- local --size-mb 32
+//!
+//! # 다른 기기에서 실행 중인 노드로 전송 (상대: pebbled serve --name nas --secret ...)
+//! cargo run --release --bin test_e2e -- peer --secret "$PEBBLE_SECRET" --device nas
+//! ```
+
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use native::api::certificate::CertificateManager;
+use native::api::config::{self, PebbleConfig};
+use native::api::discovery::{DiscoveredDevice, DiscoveryService};
+use native::api::transfer::{TransferClient, TRANSFER_PORT};
+use native::api::{db, integrity, logging, service};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+const SECRET_KEY: &str = "pebble-test-key-2024";
+
+#[derive(Parser)]
+#[command(name = "test_e2e", about = "Pebble end-to-end discovery + transfer test")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// 한 프로세스에서 송신/수신 노드를 모두 실행합니다
+    Local {
+        /// 생성할 테스트 파일 크기 (MB)
+        #[arg(long, default_value_t = 16)]
+        size_mb: u64,
+
+        /// 비콘 HMAC 인증용 비밀 키
+        #[arg(long, default_value = SECRET_KEY)]
+        secret: String,
+
+        /// 상대 노드를 기다릴 시간 (초)
+        #[arg(long, default_value_t = 15)]
+        wait: u64,
+    },
+
+    /// 다른 기기에서 실행 중인 노드를 발견하여 전송합니다
+    Peer {
+        /// 비콘 HMAC 인증용 비밀 키
+        #[arg(long, env = "PEBBLE_SECRET")]
+        secret: String,
+
+        /// 대상 기기 이름 (생략하면 처음 발견된 기기)
+        #[arg(long)]
+        device: Option<String>,
+
+        /// 대상 기기 전송 포트
+        #[arg(long, default_value_t = TRANSFER_PORT)]
+        port: u16,
+
+        /// 생성할 테스트 파일 크기 (MB)
+        #[arg(long, default_value_t = 16)]
+        size_mb: u64,
+
+        /// 상대 노드를 기다릴 시간 (초)
+        #[arg(long, default_value_t = 15)]
+        wait: u64,
+    },
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    logging::init();
+
+    let work_dir = tempfile::TempDir::new().context("Failed to create work directory")?;
+    use_work_dir(work_dir.path())?;
+
+    let result = match Cli::parse().command {
+        Command::Local { size_mb, secret, wait } => run_local(work_dir.path(), size_mb, &secret, wait).await,
+        Command::Peer { secret, device, port, size_mb, wait } => {
+            run_peer(work_dir.path(), &secret, device, port, size_mb, wait).await
+        }
+    };
+
+    match &result {
+        Ok(()) => println!("\n✅ End-to-end test passed"),
+        Err(e) => println!("\n❌ End-to-end test failed: {:#}", e),
+    }
+
+    result
+}
+
+/// 설정과 DB, 다운로드 디렉토리를 작업 디렉토리 아래로 지정합니다.
+fn use_work_dir(work_dir: &Path) -> Result<()> {
+    config::update(PebbleConfig {
+        db_path: work_dir.join("pebble.db").to_string_lossy().to_string(),
+        download_dir: Some(work_dir.join("downloads").to_string_lossy().to_string()),
+        ..config::current()
+    })?;
+    db::init_db().context("Failed to initialize database")?;
+    Ok(())
+}
+
+async fn run_local(work_dir: &Path, size_mb: u64, secret: &str, wait: u64) -> Result<()> {
+    step("Starting receiver node (B)");
+    let cert = CertificateManager::new(work_dir.join("certs-b").to_string_lossy().to_string())
+        .get_or_create_certificate("e2e-node-b", "E2E Node B")?;
+    let expected_fingerprint = cert.fingerprint.clone();
+    let port = service::start_transfer_server(cert, 0).await?;
+    let node_b = DiscoveryService::with_device_id("e2e-node-b".to_string(), "E2E Node B".to_string(), secret.to_string())
+        .with_cert_fingerprint(expected_fingerprint.clone());
+    node_b.start().await?;
+    println!("   Transfer server on port {}", port);
+
+    step("Starting sender node (A) and waiting for B's beacon");
+    let node_a = DiscoveryService::with_device_id("e2e-node-a".to_string(), "E2E Node A".to_string(), secret.to_string());
+    node_a.start().await?;
+
+    let result = async {
+        let peer = wait_for_device(&node_a, Some("E2E Node B"), wait).await?;
+        let fingerprint = pinned_fingerprint(&peer)?;
+        if fingerprint != expected_fingerprint {
+            anyhow::bail!("Beacon fingerprint {} does not match node B certificate {}", fingerprint, expected_fingerprint);
+        }
+
+        let server_addr: SocketAddr = format!("{}:{}", peer.ip_address, port).parse()?;
+
+        step("Checking that a wrong pin is refused");
+        let wrong_pin = TransferClient::new(Some("00".repeat(32)));
+        let probe = generate_file(&work_dir.join("probe.bin"), 1)?;
+        if wrong_pin.send_file(server_addr, &probe).await.is_ok() {
+            anyhow::bail!("Transfer succeeded with a wrong certificate pin");
+        }
+        println!("   Refused as expected");
+
+        let source = generate_file(&work_dir.join("e2e_payload.bin"), size_mb)?;
+        let received = work_dir.join("downloads").join("e2e_payload.bin");
+        send_and_report(server_addr, Some(fingerprint), &source).await?;
+
+        step("Verifying received file");
+        let sent_hash = integrity::calculate_file_hash(&source)?;
+        let received_hash = integrity::calculate_file_hash(&received)
+            .with_context(|| format!("Received file missing: {}", received.display()))?;
+        if sent_hash != received_hash {
+            anyhow::bail!("Hash mismatch: sent {}, received {}", sent_hash, received_hash);
+        }
+        println!("   blake3 {}", received_hash);
+
+        Ok(())
+    }
+    .await;
+
+    node_a.stop().await?;
+    node_b.stop().await?;
+    service::stop_transfer_server().await?;
+
+    result
+}
+
+async fn run_peer(
+    work_dir: &Path,
+    secret: &str,
+    device: Option<String>,
+    port: u16,
+    size_mb: u64,
+    wait: u64,
+) -> Result<()> {
+    step("Starting discovery");
+    let node = DiscoveryService::with_device_id(
+        format!("e2e-{}", uuid::Uuid::new_v4()),
+        "E2E Sender".to_string(),
+        secret.to_string(),
+    );
+    node.start().await?;
+
+    let result = async {
+        let peer = wait_for_device(&node, device.as_deref(), wait).await?;
+        let fingerprint = pinned_fingerprint(&peer)?;
+        let server_addr: SocketAddr = format!("{}:{}", peer.ip_address, port).parse()?;
+
+        let source = generate_file(&work_dir.join("e2e_payload.bin"), size_mb)?;
+        send_and_report(server_addr, Some(fingerprint), &source).await?;
+
+        // 수신측은 청크마다 해시를 검증하므로 완료되면 내용이 일치함
+        println!("   Compare on the peer: blake3 {}", integrity::calculate_file_hash(&source)?);
+
+        Ok(())
+    }
+    .await;
+
+    node.stop().await?;
+
+    result
+}
+
+fn step(message: &str) {
+    println!("\n▶ {}", message);
+}
+
+/// 조건에 맞는 기기가 발견될 때까지 기다립니다.
+async fn wait_for_device(node: &DiscoveryService, name: Option<&str>, wait: u64) -> Result<DiscoveredDevice> {
+    let deadline = Instant::now() + Duration::from_secs(wait);
+
+    while Instant::now() < deadline {
+        let found = node
+            .get_discovered_devices()
+            .into_iter()
+            .find(|d| name.is_none_or(|name| d.device_name == name));
+
+        if let Some(device) = found {
+            println!("   Found {} ({}) at {}", device.device_name, device.device_id, device.ip_address);
+            return Ok(device);
+        }
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+    }
+
+    anyhow::bail!("No matching device discovered within {}s (is UDP broadcast allowed on this network?)", wait)
+}
+
+/// 비콘에서 서명 검증된 핑거프린트를 가져옵니다.
+fn pinned_fingerprint(device: &DiscoveredDevice) -> Result<String> {
+    let fingerprint = device
+        .cert_fingerprint
+        .clone()
+        .with_context(|| format!("{} does not advertise a certificate fingerprint", device.device_name))?;
+    println!("   Pinned fingerprint {}", fingerprint);
+    Ok(fingerprint)
+}
+
+async fn send_and_report(server_addr: SocketAddr, fingerprint: Option<String>, source: &str) -> Result<()> {
+    let size = std::fs::metadata(source)?.len();
+    step(&format!("Sending {:.1} MB to {}", size as f64 / 1_048_576.0, server_addr));
+
+    let started = Instant::now();
+    TransferClient::new(fingerprint).send_file(server_addr, source).await?;
+    let elapsed = started.elapsed().as_secs_f64();
+
+    println!("   Done in {:.2}s ({:.2} MB/s)", elapsed, size as f64 / 1_048_576.0 / elapsed.max(0.001));
+
+    Ok(())
+}
+
+/// 압축되지 않는 의사 난수 데이터로 테스트 파일을 만듭니다.
+fn generate_file(path: &Path, size_mb: u64) -> Result<String> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    let mut block = vec![0u8; 1024 * 1024];
+
+    for _ in 0..size_mb {
+        for word in block.chunks_exact_mut(8) {
+            // xorshift64
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            word.copy_from_slice(&state.to_le_bytes());
+        }
+        writer.write_all(&block)?;
+    }
+    writer.flush()?;
+
+    Ok(path.to_string_lossy().to_string())
+}
@@ -1,28 +1,84 @@
-/// Phase 3 테스트: 암호화된 파일 전송 (Secure File Transfer)
-///
-/// # 사용법
-/// ```bash
-/// # 터미널 1 - 수신자
-/// cargo run --release --bin test_transfer -- receiver
-///
-/// # 터미널 2 - 송신자
-/// cargo run --release --bin test_transfer -- sender 127.0.0.1 /tmp/test_file.bin
-///
-/// # 테스트 파일 생성
-/// dd if=/dev/urandom of=/tmp/test_file.bin bs=1048576 count=10  # 10MB
-/// ```
+//! Phase 3 테스트: 암호화된 파일 전송 (Secure File Transfer)
+//!
+//! # 사용법
+//! ```bash
+//! # 터미널 1 - 수신자
+//! cargo run --release --bin test_transfer -- receiver
+//!
+//! # 터미널 2 - 송신자
+//! cargo run --release --bin test_transfer -- sender 127.0.0.1 /tmp/test_file.bin
+//!
+//! # 테스트 파일 생성
+//! dd if=/dev/urandom of=/tmp/test_file.bin bs=1048576 count=10  # 10MB
+//!
+//! # 장애 주입 (양쪽 모두 사용 가능, fault-injection 기능 필요)
+//! cargo run --release --features fault-injection --bin test_transfer -- sender 127.0.0.1 /tmp/test_file.bin --drop-after 3
+//! cargo run --release --features fault-injection --bin test_transfer -- sender 127.0.0.1 /tmp/test_file.bin --corrupt-chunk 2
+//! cargo run --release --features fault-injection --bin test_transfer -- receiver --ack-delay-ms 200
+//!
+//! # 스크립트/CI: 진행률 표시줄과 입력 대기 없이 결과를 JSON 줄로 출력
+//! cargo run --release --bin test_transfer -- receiver --json
+//! cargo run --release --bin test_transfer -- sender 127.0.0.1 /tmp/test_file.bin --json --fingerprint a8f5f167...
+//! ```
+//!
+//! 전송마다 진행률 표시줄(속도, 남은 시간)이 표시됩니다. 표시줄이 깨지지 않도록
+//! 로그는 Warn 이상만 출력하며, `RUST_LOG=info`로 전체 로그를 볼 수 있습니다.
+//! `--json`이면 수신자는 수신이 끝날 때마다, 송신자는 전송이 끝나면 JSON 한 줄을 출력합니다.
 
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use native::api::certificate::CertificateManager;
-use native::api::transfer::{TransferClient, TransferServer, TRANSFER_PORT};
+#[cfg(feature = "fault-injection")]
+use native::api::fault::FaultPlan;
+use native::api::logging::{self, LogLevel};
+use native::api::transfer::{TransferClient, TransferProgress, TransferServer, TRANSFER_PORT};
+use serde::Serialize;
+use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
 
 const CERT_DIR: &str = "/tmp/pebble_certs";
 
+const BAR_TEMPLATE: &str =
+    "{spinner:.green} {prefix:20!} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} {binary_bytes_per_sec} ETA {eta}";
+
+/// 명령행 옵션
+#[derive(Default)]
+struct Options {
+    #[cfg(feature = "fault-injection")]
+    fault: FaultPlan,
+
+    /// 결과를 JSON 줄로 출력
+    json: bool,
+
+    /// 수신자 인증서 핑거프린트 (지정하면 입력을 기다리지 않음)
+    fingerprint: Option<String>,
+}
+
+/// `--json` 출력 한 줄
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum JsonEvent<'a> {
+    Listening { addr: SocketAddr, fingerprint: &'a str },
+    Received { transfer_id: &'a str, file_path: &'a str, bytes: u64, elapsed_ms: u128 },
+    Sent { file_path: &'a str, addr: SocketAddr, bytes: u64, elapsed_ms: u128 },
+    Failed { file_path: &'a str, addr: SocketAddr, error: String, elapsed_ms: u128 },
+}
+
+fn print_json(event: &JsonEvent) {
+    if let Ok(line) = serde_json::to_string(event) {
+        println!("{}", line);
+    }
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    logging::init();
+    if env::var_os("RUST_LOG").is_none() {
+        logging::set_level(LogLevel::Warn);
+    }
 
     let args: Vec<String> = env::args().collect();
 
@@ -32,9 +88,17 @@ async fn main() -> anyhow::Result<()> {
     }
 
     let mode = &args[1];
+    let options = match parse_options(&args[2..]) {
+        Ok(options) => options,
+        Err(e) => {
+            println!("❌ Error: {}", e);
+            print_usage();
+            return Ok(());
+        }
+    };
 
     match mode.as_str() {
-        "receiver" => run_receiver().await?,
+        "receiver" => run_receiver(options).await?,
         "sender" => {
             if args.len() < 4 {
                 println!("❌ Error: Missing arguments");
@@ -43,7 +107,7 @@ async fn main() -> anyhow::Result<()> {
             }
             let server_ip = &args[2];
             let file_path = &args[3];
-            run_sender(server_ip, file_path).await?;
+            run_sender(server_ip, file_path, options).await?;
         }
         _ => {
             println!("❌ Unknown mode: {}", mode);
@@ -66,44 +130,124 @@ fn print_usage() {
     println!("  cargo run --release --bin test_transfer -- receiver");
     println!("\n  # Terminal 2");
     println!("  cargo run --release --bin test_transfer -- sender 127.0.0.1 /tmp/test_file.bin");
+    #[cfg(feature = "fault-injection")]
+    {
+        println!("\nFault injection options:");
+        println!("  --drop-after <n>      Drop the connection after n chunks");
+        println!("  --corrupt-chunk <i>   Corrupt chunk i after hashing (sender)");
+        println!("  --ack-delay-ms <ms>   Delay every ACK (receiver)");
+    }
+    println!("\nOutput options:");
+    println!("  --json                Print one JSON line per result instead of progress bars");
+    println!("  --fingerprint <fp>    Pin the receiver certificate without prompting (sender)");
     println!("\nCreate test file:");
     println!("  dd if=/dev/urandom of=/tmp/test_file.bin bs=1048576 count=10");
     println!("{}\n", "=".repeat(70));
 }
 
-async fn run_receiver() -> anyhow::Result<()> {
-    println!("\n{}", "=".repeat(70));
-    println!("  📥 RECEIVER MODE");
-    println!("{}\n", "=".repeat(70));
+/// 옵션 값을 숫자로 읽습니다.
+#[cfg(feature = "fault-injection")]
+fn parse_number(arg: &str, value: &str) -> anyhow::Result<u64> {
+    value.parse().map_err(|_| anyhow::anyhow!("Invalid value for {}", arg))
+}
+
+/// 명령행의 출력, 장애 주입 옵션을 읽습니다. 위치 인자는 건너뜁니다.
+fn parse_options(args: &[String]) -> anyhow::Result<Options> {
+    let mut options = Options::default();
+    let mut iter = args.iter();
+
+    while let Some(arg) = iter.next() {
+        if !arg.starts_with("--") {
+            continue;
+        }
+        if arg == "--json" {
+            options.json = true;
+            continue;
+        }
+
+        let value = iter.next().ok_or_else(|| anyhow::anyhow!("Missing value for {}", arg))?;
+        if arg == "--fingerprint" {
+            options.fingerprint = Some(value.clone());
+            continue;
+        }
+
+        match arg.as_str() {
+            #[cfg(feature = "fault-injection")]
+            "--drop-after" => options.fault.drop_after_chunks = Some(parse_number(arg, value)?),
+            #[cfg(feature = "fault-injection")]
+            "--corrupt-chunk" => options.fault.corrupt_chunk = Some(parse_number(arg, value)?),
+            #[cfg(feature = "fault-injection")]
+            "--ack-delay-ms" => options.fault.ack_delay = Some(Duration::from_millis(parse_number(arg, value)?)),
+            _ => anyhow::bail!("Unknown option: {}", arg),
+        }
+    }
+
+    Ok(options)
+}
+
+async fn run_receiver(options: Options) -> anyhow::Result<()> {
+    let json = options.json;
+    if !json {
+        println!("\n{}", "=".repeat(70));
+        println!("  📥 RECEIVER MODE");
+        println!("{}\n", "=".repeat(70));
+    }
 
     fs::create_dir_all(CERT_DIR)?;
 
-    println!("🔐 Loading TLS certificate...");
+    if !json {
+        println!("🔐 Loading TLS certificate...");
+    }
     let manager = CertificateManager::new(CERT_DIR.to_string());
     let cert = manager.get_or_create_certificate("receiver-id", "Test Receiver")?;
 
-    println!("✅ Certificate loaded");
-    println!("📋 Fingerprint: {}", cert.fingerprint);
-    println!("   (Copy this for Certificate Pinning)\n");
-
     let bind_addr: SocketAddr = format!("0.0.0.0:{}", TRANSFER_PORT).parse()?;
-    let server = TransferServer::new(cert);
+    if json {
+        print_json(&JsonEvent::Listening { addr: bind_addr, fingerprint: &cert.fingerprint });
+    } else {
+        println!("✅ Certificate loaded");
+        println!("📋 Fingerprint: {}", cert.fingerprint);
+        println!("   (Copy this for Certificate Pinning)\n");
+    }
 
-    println!("📡 Transfer server listening on {}", bind_addr);
-    println!("🔄 Waiting for files...");
-    println!("   Press Ctrl+C to stop\n");
+    let mut server = TransferServer::new(cert);
+    #[cfg(feature = "fault-injection")]
+    {
+        if !options.fault.is_empty() && !json {
+            println!("⚠️  Fault injection enabled: {:?}\n", options.fault);
+        }
+        server.set_fault_plan(options.fault);
+    }
+
+    let (progress_tx, progress_rx) = mpsc::unbounded_channel();
+    server.set_progress_channel(progress_tx);
+    if json {
+        spawn_json_progress(progress_rx);
+    } else {
+        spawn_progress_display(progress_rx);
+
+        println!("📡 Transfer server listening on {}", bind_addr);
+        println!("🔄 Waiting for files...");
+        println!("   Press Ctrl+C to stop\n");
+    }
 
     server.start(bind_addr).await?;
 
     Ok(())
 }
 
-async fn run_sender(server_ip: &str, file_path: &str) -> anyhow::Result<()> {
-    println!("\n{}", "=".repeat(70));
-    println!("  📤 SENDER MODE");
-    println!("{}\n", "=".repeat(70));
+async fn run_sender(server_ip: &str, file_path: &str, options: Options) -> anyhow::Result<()> {
+    let json = options.json;
+    if !json {
+        println!("\n{}", "=".repeat(70));
+        println!("  📤 SENDER MODE");
+        println!("{}\n", "=".repeat(70));
+    }
 
     if !std::path::Path::new(file_path).exists() {
+        if json {
+            anyhow::bail!("File not found: {}", file_path);
+        }
         println!("❌ Error: File not found: {}", file_path);
         println!("\n💡 Create test file:");
         println!("   dd if=/dev/urandom of={} bs=1048576 count=10", file_path);
@@ -111,13 +255,77 @@ async fn run_sender(server_ip: &str, file_path: &str) -> anyhow::Result<()> {
     }
 
     let file_size = fs::metadata(file_path)?.len();
-    println!("📁 File: {}", file_path);
-    println!("📊 Size: {:.2} MB", file_size as f64 / 1_048_576.0);
-    println!();
-
     let server_addr: SocketAddr = format!("{}:{}", server_ip, TRANSFER_PORT).parse()?;
-    println!("🎯 Target: {}", server_addr);
 
+    if !json {
+        println!("📁 File: {}", file_path);
+        println!("📊 Size: {:.2} MB", file_size as f64 / 1_048_576.0);
+        println!();
+        println!("🎯 Target: {}", server_addr);
+    }
+
+    let server_fingerprint = match options.fingerprint {
+        Some(fingerprint) => Some(fingerprint),
+        None if json => None,
+        None => prompt_fingerprint()?,
+    };
+
+    if !json {
+        println!("\n🚀 Starting transfer...\n");
+    }
+
+    let mut client = TransferClient::new(server_fingerprint);
+    #[cfg(feature = "fault-injection")]
+    {
+        if !options.fault.is_empty() && !json {
+            println!("⚠️  Fault injection enabled: {:?}\n", options.fault);
+        }
+        client.set_fault_plan(options.fault);
+    }
+
+    let display = (!json).then(|| {
+        let (progress_tx, progress_rx) = mpsc::unbounded_channel();
+        client.set_progress_channel(progress_tx);
+        spawn_progress_display(progress_rx)
+    });
+
+    let started = Instant::now();
+    let result = client.send_file(server_addr, file_path).await;
+    let elapsed_ms = started.elapsed().as_millis();
+
+    // 클라이언트가 채널을 닫아야 진행률 표시가 끝남
+    drop(client);
+    if let Some(display) = display {
+        let _ = display.await;
+    }
+
+    match result {
+        Ok(_) if json => {
+            print_json(&JsonEvent::Sent { file_path, addr: server_addr, bytes: file_size, elapsed_ms });
+        }
+        Ok(_) => {
+            println!("\n{}", "=".repeat(70));
+            println!("  ✅ FILE TRANSFER COMPLETED");
+            println!("{}\n", "=".repeat(70));
+        }
+        Err(e) if json => {
+            print_json(&JsonEvent::Failed { file_path, addr: server_addr, error: format!("{:#}", e), elapsed_ms });
+            // 스크립트가 종료 코드로 실패를 알 수 있도록
+            anyhow::bail!("File transfer failed");
+        }
+        Err(e) => {
+            println!("\n{}", "=".repeat(70));
+            println!("  ❌ FILE TRANSFER FAILED");
+            println!("{}", "=".repeat(70));
+            println!("Error: {}\n", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// 수신자 핑거프린트를 입력받습니다 (빈 줄이면 검증하지 않음).
+fn prompt_fingerprint() -> anyhow::Result<Option<String>> {
     println!("\n🔐 Certificate Pinning (optional):");
     println!("   Enter fingerprint or press Enter to skip:");
     print!("   > ");
@@ -129,31 +337,139 @@ async fn run_sender(server_ip: &str, file_path: &str) -> anyhow::Result<()> {
     io::stdin().read_line(&mut input)?;
     let fingerprint = input.trim();
 
-    let server_fingerprint = if fingerprint.is_empty() {
+    if fingerprint.is_empty() {
         println!("   ⚠️  Skipping certificate verification");
-        None
+        Ok(None)
     } else {
         println!("   ✅ Using Certificate Pinning");
-        Some(fingerprint.to_string())
-    };
+        Ok(Some(fingerprint.to_string()))
+    }
+}
 
-    println!("\n🚀 Starting transfer...\n");
+/// 진행률 채널을 읽어 수신이 끝날 때마다 JSON 한 줄을 출력합니다 (`--json`).
+fn spawn_json_progress(mut rx: mpsc::UnboundedReceiver<TransferProgress>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut started: HashMap<String, Instant> = HashMap::new();
 
-    let client = TransferClient::new(server_fingerprint);
+        while let Some(progress) = rx.recv().await {
+            let start = *started.entry(progress.transfer_id.clone()).or_insert_with(Instant::now);
 
-    match client.send_file(server_addr, file_path).await {
-        Ok(_) => {
-            println!("\n{}", "=".repeat(70));
-            println!("  ✅ FILE TRANSFER COMPLETED");
-            println!("{}\n", "=".repeat(70));
+            if progress.completed_chunks >= progress.total_chunks {
+                print_json(&JsonEvent::Received {
+                    transfer_id: &progress.transfer_id,
+                    file_path: &progress.file_path,
+                    bytes: progress.total_bytes,
+                    elapsed_ms: start.elapsed().as_millis(),
+                });
+                started.remove(&progress.transfer_id);
+            }
         }
-        Err(e) => {
-            println!("\n{}", "=".repeat(70));
-            println!("  ❌ FILE TRANSFER FAILED");
-            println!("{}", "=".repeat(70));
-            println!("Error: {}\n", e);
+    })
+}
+
+/// 진행률 채널을 읽어 전송마다 진행률 표시줄을 갱신합니다.
+///
+/// 채널이 닫히면 완료되지 않은 표시줄은 현재 상태로 남겨 둡니다.
+fn spawn_progress_display(mut rx: mpsc::UnboundedReceiver<TransferProgress>) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let multi = MultiProgress::new();
+        let mut bars: HashMap<String, ProgressBar> = HashMap::new();
+
+        while let Some(progress) = rx.recv().await {
+            let bar = bars
+                .entry(progress.transfer_id.clone())
+                .or_insert_with(|| multi.add(transfer_bar(&progress)));
+
+            bar.set_position(progress.bytes_transferred);
+
+            if progress.completed_chunks >= progress.total_chunks {
+                bar.finish();
+                bars.remove(&progress.transfer_id);
+            }
+        }
+
+        for bar in bars.values() {
+            bar.abandon();
+        }
+    })
+}
+
+fn transfer_bar(progress: &TransferProgress) -> ProgressBar {
+    let name = std::path::Path::new(&progress.file_path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| progress.file_path.clone());
+
+    let bar = ProgressBar::new(progress.total_bytes);
+    bar.set_style(
+        ProgressStyle::with_template(BAR_TEMPLATE)
+            .expect("valid progress template")
+            .progress_chars("=> "),
+    );
+    bar.set_prefix(name);
+    bar.enable_steady_tick(Duration::from_millis(120));
+    bar
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(file_path: &str, total_bytes: u64) -> TransferProgress {
+        TransferProgress {
+            transfer_id: "t1".to_string(),
+            file_path: file_path.to_string(),
+            total_chunks: 4,
+            completed_chunks: 1,
+            progress_percent: 25.0,
+            bytes_transferred: total_bytes / 4,
+            total_bytes,
+            transfer_rate_mbps: 0.0,
         }
     }
 
-    Ok(())
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let options = parse_options(&args(&["send", "10.0.0.2", "a.txt", "--json", "--fingerprint", "ab:cd"])).unwrap();
+        assert!(options.json);
+        assert_eq!(options.fingerprint.as_deref(), Some("ab:cd"));
+
+        assert!(!parse_options(&args(&["receive"])).unwrap().json);
+        assert!(parse_options(&args(&["--fingerprint"])).is_err());
+        assert!(parse_options(&args(&["--unknown", "1"])).is_err());
+    }
+
+    #[test]
+    fn test_json_events_are_tagged() {
+        let addr: SocketAddr = "10.0.0.2:37849".parse().unwrap();
+        let sent = serde_json::to_value(JsonEvent::Sent { file_path: "a.txt", addr, bytes: 5, elapsed_ms: 7 }).unwrap();
+        assert_eq!(sent["event"], "sent");
+        assert_eq!(sent["addr"], "10.0.0.2:37849");
+
+        let failed = serde_json::to_value(JsonEvent::Failed {
+            file_path: "a.txt",
+            addr,
+            error: "Connection refused".to_string(),
+            elapsed_ms: 1,
+        })
+        .unwrap();
+        assert_eq!(failed["event"], "failed");
+        assert_eq!(failed["error"], "Connection refused");
+    }
+
+    #[test]
+    fn test_transfer_bar_shows_file_name_and_size() {
+        let bar = transfer_bar(&progress("/home/user/videos/clip.mp4", 4096));
+        assert_eq!(bar.prefix(), "clip.mp4");
+        assert_eq!(bar.length(), Some(4096));
+        bar.finish_and_clear();
+
+        let bar = transfer_bar(&progress("", 0));
+        assert_eq!(bar.prefix(), "");
+        bar.finish_and_clear();
+    }
 }
@@ -1,5 +1,8 @@
 pub mod api;
 mod frb_generated;
 
+#[cfg(test)]
+mod loopback;
+
 // Re-export for examples
 pub use api::*;
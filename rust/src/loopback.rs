@@ -0,0 +1,1417 @@
+//! 전송 프로토콜 루프백 테스트 하네스
+//!
+//! 실제 소켓 없이 `tokio::io::duplex` 메모리 스트림 위에서 TransferServer와
+//! TransferClient를 실행하여 프로토콜 상태 머신, 이어받기, 에러 경로를
+//! 빠르고 결정적으로 검증합니다. TLS는 선택적으로 같은 스트림 위에 올릴 수 있습니다.
+//! 테스트 빌드에만 포함되며 `api` 밖에 두어 Dart 바인딩으로 내보내지 않습니다.
+
+use anyhow::{Context, Result};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use tokio::io::{duplex, AsyncWriteExt, DuplexStream};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+use crate::api::certificate::TlsCertificate;
+use crate::api::config::{self, PebbleConfig};
+use crate::api::db;
+use crate::api::fault::{self, FaultPlan};
+use crate::api::transfer::{TransferClient, TransferMessage, TransferServer};
+
+/// 메모리 스트림 버퍼 크기
+pub const DUPLEX_BUFFER_SIZE: usize = 256 * 1024;
+
+/// 루프백 전송 방식
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transport {
+    /// 암호화 없이 메모리 스트림 그대로 사용
+    Plain,
+    /// 메모리 스트림 위에 TLS 핸드셰이크 수행
+    Tls,
+}
+
+/// 양측 실행 결과
+#[derive(Debug)]
+pub struct LoopbackOutcome {
+    pub client: Result<()>,
+    pub server: Result<()>,
+}
+
+/// 로그와 레지스트리에 표시되는 가상의 상대 주소
+pub fn loopback_peer() -> SocketAddr {
+    SocketAddr::from(([127, 0, 0, 1], 0))
+}
+
+/// 임시 DB와 다운로드 디렉토리를 전역 설정으로 적용합니다.
+///
+/// 설정은 프로세스 전역이므로 같은 프로세스의 테스트들이 이 환경을 공유합니다.
+/// 수신 파일은 파일 이름 기준으로 저장되므로 테스트마다 다른 파일 이름을 사용하세요.
+///
+/// # Returns
+/// * 다운로드 디렉토리 경로
+pub fn use_temp_environment() -> &'static Path {
+    static ENV: once_cell::sync::Lazy<tempfile::TempDir> = once_cell::sync::Lazy::new(|| {
+        let dir = tempfile::TempDir::new().expect("Failed to create temp directory");
+        let config = PebbleConfig {
+            db_path: dir.path().join("pebble.db").to_string_lossy().to_string(),
+            download_dir: Some(dir.path().join("downloads").to_string_lossy().to_string()),
+            chunk_size: config::MIN_CHUNK_SIZE,
+            dedup_store_dir: Some(dir.path().join("chunks").to_string_lossy().to_string()),
+            preserve_xattrs: true,
+            ..config::current()
+        };
+        config::update(config).expect("Failed to apply test configuration");
+        db::init_db().expect("Failed to initialize test database");
+        dir
+    });
+
+    static DOWNLOADS: once_cell::sync::Lazy<PathBuf> =
+        once_cell::sync::Lazy::new(|| ENV.path().join("downloads"));
+
+    &DOWNLOADS
+}
+
+/// 한 쌍의 메모리 스트림을 만듭니다. (클라이언트 측, 서버 측)
+pub fn stream_pair() -> (DuplexStream, DuplexStream) {
+    duplex(DUPLEX_BUFFER_SIZE)
+}
+
+/// 스크립트 측에서 메시지 하나를 보냅니다.
+pub async fn write_message(stream: &mut DuplexStream, msg: &TransferMessage) -> Result<()> {
+    stream.write_all(&msg.to_bytes()?).await?;
+    Ok(())
+}
+
+/// 스크립트 측에서 메시지 하나를 읽습니다.
+pub async fn read_message(stream: &mut DuplexStream) -> Result<TransferMessage> {
+    TransferMessage::from_stream(stream).await
+}
+
+/// 실제 TransferClient와 TransferServer로 파일 하나를 전송합니다.
+///
+/// # Arguments
+/// * `client` - 송신 클라이언트 (송신측 장애는 `set_fault_plan`으로 설정)
+/// * `server_fault` - 수신측에 적용할 장애 주입 계획
+/// * `file_path` - 전송할 파일 경로
+/// * `transport` - 평문 또는 TLS
+pub async fn run_transfer(
+    client: &TransferClient,
+    server_fault: FaultPlan,
+    file_path: &str,
+    transport: Transport,
+) -> LoopbackOutcome {
+    let (client_io, server_io) = stream_pair();
+
+    match transport {
+        Transport::Plain => {
+            let (client, server) = tokio::join!(
+                client.send_file_over(client_io, "loopback", file_path),
+                fault::scope(server_fault, TransferServer::handle_stream(server_io, loopback_peer(), None)),
+            );
+            LoopbackOutcome { client, server }
+        }
+        Transport::Tls => {
+            let cert = match TlsCertificate::generate_self_signed("loopback", "Loopback") {
+                Ok(cert) => cert,
+                Err(e) => {
+                    return LoopbackOutcome {
+                        client: Err(anyhow::anyhow!("Failed to generate certificate: {:#}", e)),
+                        server: Err(e),
+                    };
+                }
+            };
+            let fingerprint = cert.fingerprint.clone();
+
+            let server = async {
+                let acceptor = TlsAcceptor::from(cert.build_server_config()?);
+                let tls_stream = acceptor.accept(server_io).await.context("TLS handshake failed")?;
+                fault::scope(server_fault, TransferServer::handle_stream(tls_stream, loopback_peer(), None)).await
+            };
+            let client = async {
+                let connector = TlsConnector::from(TlsCertificate::build_client_config(Some(fingerprint))?);
+                let domain = rustls::pki_types::ServerName::try_from("pebble.local")
+                    .map_err(|_| anyhow::anyhow!("Invalid DNS name"))?;
+                let tls_stream = connector.connect(domain, client_io).await.context("TLS handshake failed")?;
+                client.send_file_over(tls_stream, "loopback", file_path).await
+            };
+
+            let (client, server) = tokio::join!(client, server);
+            LoopbackOutcome { client, server }
+        }
+    }
+}
+
+/// 실제 TransferClient를 스크립트로 작성한 가짜 서버와 대결시킵니다.
+pub async fn run_client_against<F, Fut, T>(client: &TransferClient, file_path: &str, script: F) -> (Result<()>, T)
+where
+    F: FnOnce(DuplexStream) -> Fut,
+    Fut: Future<Output = T>,
+{
+    let (client_io, server_io) = stream_pair();
+    tokio::join!(client.send_file_over(client_io, "loopback", file_path), script(server_io))
+}
+
+/// 실제 TransferServer를 스크립트로 작성한 가짜 클라이언트와 대결시킵니다.
+pub async fn run_server_against<F, Fut, T>(script: F) -> (Result<()>, T)
+where
+    F: FnOnce(DuplexStream) -> Fut,
+    Fut: Future<Output = T>,
+{
+    let (client_io, server_io) = stream_pair();
+    tokio::join!(
+        TransferServer::handle_stream(server_io, loopback_peer(), None),
+        script(client_io)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::dedup;
+    use crate::api::error::PebbleError;
+    use crate::api::integrity::{self, HashAlgo};
+    use crate::api::metrics;
+    use crate::api::priority::{self, TransferPriority};
+    use crate::api::quota;
+    use crate::api::registry;
+    use crate::api::transfer::{AckRange, RejectCode, TransferStatus, ACK_INTERVAL};
+    use sha2::{Digest, Sha256};
+    use std::fs;
+    use std::time::Duration;
+
+    fn pattern(len: usize, seed: u8) -> Vec<u8> {
+        (0..len).map(|i| (i as u8).wrapping_mul(31).wrapping_add(seed)).collect()
+    }
+
+    fn write_source(name: &str, data: &[u8]) -> (tempfile::TempDir, String) {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(name);
+        fs::write(&path, data).unwrap();
+        (dir, path.to_string_lossy().to_string())
+    }
+
+    fn chunk_msg(transfer_id: &str, chunk_index: u64, data: &[u8]) -> TransferMessage {
+        TransferMessage::ChunkData {
+            transfer_id: transfer_id.to_string(),
+            chunk_index,
+            chunk_hash: hex::encode(Sha256::digest(data)),
+            data: data.to_vec(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_plain_transfer_roundtrip() {
+        let downloads = use_temp_environment();
+        let data = pattern(200 * 1024 + 17, 1);
+        let (_src, path) = write_source("plain_roundtrip.bin", &data);
+
+        let outcome = run_transfer(&TransferClient::new(None), FaultPlan::default(), &path, Transport::Plain).await;
+        outcome.client.unwrap();
+        outcome.server.unwrap();
+
+        assert_eq!(fs::read(downloads.join("plain_roundtrip.bin")).unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_tls_transfer_roundtrip() {
+        let downloads = use_temp_environment();
+        let data = pattern(70 * 1024, 2);
+        let (_src, path) = write_source("tls_roundtrip.bin", &data);
+
+        let outcome = run_transfer(&TransferClient::new(None), FaultPlan::default(), &path, Transport::Tls).await;
+        outcome.client.unwrap();
+        outcome.server.unwrap();
+
+        assert_eq!(fs::read(downloads.join("tls_roundtrip.bin")).unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_repeated_transfer_does_not_overwrite() {
+        let downloads = use_temp_environment();
+        let first = pattern(3000, 8);
+        let second = pattern(1000, 9);
+        let (_src, path) = write_source("same_name.bin", &first);
+
+        let client = TransferClient::new(None);
+        run_transfer(&client, FaultPlan::default(), &path, Transport::Plain).await.client.unwrap();
+        fs::write(&path, &second).unwrap();
+        run_transfer(&client, FaultPlan::default(), &path, Transport::Plain).await.client.unwrap();
+
+        assert_eq!(fs::read(downloads.join("same_name.bin")).unwrap(), first);
+        assert_eq!(fs::read(downloads.join("same_name (2).bin")).unwrap(), second);
+    }
+
+    #[tokio::test]
+    async fn test_connection_reused_for_consecutive_transfers() {
+        let downloads = use_temp_environment();
+        let first = pattern(5000, 10);
+        let second = pattern(7000, 11);
+        let (_src1, path1) = write_source("reuse_first.bin", &first);
+        let (_src2, path2) = write_source("reuse_second.bin", &second);
+
+        let (mut client_io, server_io) = stream_pair();
+        let client = TransferClient::new(None);
+        let server = tokio::spawn(TransferServer::handle_stream(server_io, loopback_peer(), None));
+
+        client.send_file_over(&mut client_io, "loopback", &path1).await.unwrap();
+        crate::api::pool::check_health(&mut client_io).await.unwrap();
+        client.send_file_over(&mut client_io, "loopback", &path2).await.unwrap();
+        drop(client_io);
+        server.await.unwrap().unwrap();
+
+        assert_eq!(fs::read(downloads.join("reuse_first.bin")).unwrap(), first);
+        assert_eq!(fs::read(downloads.join("reuse_second.bin")).unwrap(), second);
+    }
+
+    #[tokio::test]
+    async fn test_listener_uses_own_download_dir_and_policy() {
+        use crate::api::accept::AcceptPolicy;
+        use crate::api::listeners::ListenerConfig;
+
+        use_temp_environment();
+        let vpn_dir = tempfile::TempDir::new().unwrap();
+        let listener = ListenerConfig {
+            name: "vpn".to_string(),
+            bind_addr: "0.0.0.0:0".to_string(),
+            download_dir: Some(vpn_dir.path().to_string_lossy().to_string()),
+            accept_policy: Some(AcceptPolicy { reject_executables: true, ..AcceptPolicy::default() }),
+        };
+        let serve = |server_io| TransferServer::handle_stream_for(server_io, loopback_peer(), None, &listener);
+        let client = TransferClient::new(None);
+
+        let data = pattern(4000, 12);
+        let (_src, path) = write_source("listener_dir.bin", &data);
+        let (client_io, server_io) = stream_pair();
+        let (sent, server) = tokio::join!(client.send_file_over(client_io, "loopback", &path), serve(server_io));
+        sent.unwrap();
+        server.unwrap();
+        assert_eq!(fs::read(vpn_dir.path().join("listener_dir.bin")).unwrap(), data);
+
+        // 전역 정책은 실행 파일을 받지만 이 서버의 정책은 거부
+        let (_src, path) = write_source("listener_setup.exe", &pattern(100, 13));
+        let (client_io, server_io) = stream_pair();
+        let (sent, _) = tokio::join!(client.send_file_over(client_io, "loopback", &path), serve(server_io));
+        assert!(matches!(PebbleError::from(sent.unwrap_err()), PebbleError::Rejected { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_hash_query_finds_received_file() {
+        use_temp_environment();
+        let data = pattern(6000, 12);
+        let (_src, path) = write_source("dedup.bin", &data);
+        let file_hash = crate::api::integrity::calculate_file_hash(&path).unwrap();
+
+        let (mut client_io, server_io) = stream_pair();
+        let client = TransferClient::new(None);
+        let server = tokio::spawn(TransferServer::handle_stream(server_io, loopback_peer(), None));
+
+        assert!(!TransferClient::query_present(&mut client_io, &file_hash, data.len() as u64).await.unwrap());
+        client.send_file_over(&mut client_io, "loopback", &path).await.unwrap();
+        assert!(TransferClient::query_present(&mut client_io, &file_hash, data.len() as u64).await.unwrap());
+        // 크기가 다르면 같은 파일로 보지 않음
+        assert!(!TransferClient::query_present(&mut client_io, &file_hash, 1).await.unwrap());
+
+        drop(client_io);
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_low_priority_send_yields_to_high_priority() {
+        let downloads = use_temp_environment();
+        let data = pattern(config::MIN_CHUNK_SIZE as usize * 3, 14);
+        let (_src, path) = write_source("low_priority.bin", &data);
+
+        let mut client = TransferClient::new(None);
+        client.set_priority(TransferPriority::Low);
+        let high = priority::begin(TransferPriority::High);
+
+        let watch = async {
+            // 취소되지 않고 Paused로 멈춰 있다가 높은 우선순위 송신이 끝나면 이어서 전송
+            // (루프백에서는 송수신이 같은 전송 ID를 쓰므로 레지스트리에 방향별로 두 항목이 있음)
+            loop {
+                let paused = registry::global().list().into_iter().any(|transfer| {
+                    transfer.file_path.ends_with("low_priority.bin") && transfer.status == TransferStatus::Paused
+                });
+                if paused {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            drop(high);
+        };
+        let (outcome, ()) = tokio::join!(run_transfer(&client, FaultPlan::default(), &path, Transport::Plain), watch);
+
+        outcome.client.unwrap();
+        assert_eq!(fs::read(downloads.join("low_priority.bin")).unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_injected_client_drop() {
+        use_temp_environment();
+        let (_src, path) = write_source("fault_drop.bin", &pattern(config::MIN_CHUNK_SIZE as usize * 4, 5));
+
+        let mut client = TransferClient::new(None);
+        client.set_fault_plan(FaultPlan {
+            drop_after_chunks: Some(2),
+            ..Default::default()
+        });
+
+        let outcome = run_transfer(&client, FaultPlan::default(), &path, Transport::Plain).await;
+        assert!(matches!(PebbleError::from(outcome.client.unwrap_err()), PebbleError::Network { .. }));
+        assert!(outcome.server.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_injected_corruption_detected_by_server() {
+        use_temp_environment();
+        let (_src, path) = write_source("fault_corrupt.bin", &pattern(config::MIN_CHUNK_SIZE as usize * 3, 6));
+
+        let mut client = TransferClient::new(None);
+        client.set_fault_plan(FaultPlan {
+            corrupt_chunk: Some(1),
+            ..Default::default()
+        });
+
+        let outcome = run_transfer(&client, FaultPlan::default(), &path, Transport::Plain).await;
+        assert!(outcome.server.unwrap_err().to_string().contains("hash mismatch"));
+        assert!(outcome.client.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_injected_ack_delay() {
+        let downloads = use_temp_environment();
+        let data = pattern(config::MIN_CHUNK_SIZE as usize * 3, 7);
+        let (_src, path) = write_source("fault_delay.bin", &data);
+
+        let server_fault = FaultPlan {
+            ack_delay: Some(std::time::Duration::from_millis(20)),
+            ..Default::default()
+        };
+
+        let started = std::time::Instant::now();
+        let outcome = run_transfer(&TransferClient::new(None), server_fault, &path, Transport::Plain).await;
+        outcome.client.unwrap();
+        outcome.server.unwrap();
+
+        assert!(started.elapsed() >= std::time::Duration::from_millis(60));
+        assert_eq!(fs::read(downloads.join("fault_delay.bin")).unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_server_rejects_bad_chunk_size() {
+        use_temp_environment();
+
+        let (server, reply) = run_server_against(|mut io| async move {
+            let request = TransferMessage::TransferRequest {
+                transfer_id: "bad-chunk".to_string(),
+                file_path: "bad_chunk.bin".to_string(),
+                file_size: 100,
+                file_hash: String::new(),
+                total_chunks: 10,
+                chunk_size: 10,
+                chunk_hash_algos: Vec::new(),
+                sender_device_id: None,
+                ack_ranges: false,
+                chunk_manifest: false,
+                inline_data: None,
+                xattrs: Vec::new(),
+                verdict: false,
+                crc32c: false,
+                retransmit: false,
+                relative_path: None,
+            };
+            write_message(&mut io, &request).await.unwrap();
+            read_message(&mut io).await.unwrap()
+        })
+        .await;
+
+        assert!(matches!(reply, TransferMessage::TransferReject { .. }));
+        assert!(matches!(PebbleError::from(server.unwrap_err()), PebbleError::Protocol { .. }));
+    }
+
+    /// 선언한 크기와 함께 청크를 그대로 보내고 서버의 결과를 돌려줍니다.
+    async fn stream_declared(name: &str, file_size: u64, total_chunks: u64, chunks: Vec<Vec<u8>>) -> (Result<()>, TransferMessage) {
+        let name = name.to_string();
+        run_server_against(|mut io| async move {
+            let request = TransferMessage::TransferRequest {
+                transfer_id: format!("declared-{}", name),
+                file_path: format!("{}.bin", name),
+                file_size,
+                file_hash: String::new(),
+                total_chunks,
+                chunk_size: config::MIN_CHUNK_SIZE,
+                chunk_hash_algos: Vec::new(),
+                sender_device_id: None,
+                ack_ranges: false,
+                chunk_manifest: false,
+                inline_data: None,
+                xattrs: Vec::new(),
+                verdict: false,
+                crc32c: false,
+                retransmit: false,
+                relative_path: None,
+            };
+            write_message(&mut io, &request).await.unwrap();
+            let reply = read_message(&mut io).await.unwrap();
+            for (chunk_index, data) in chunks.into_iter().enumerate() {
+                let chunk = TransferMessage::ChunkData {
+                    transfer_id: format!("declared-{}", name),
+                    chunk_index: chunk_index as u64,
+                    chunk_hash: HashAlgo::Sha256.digest(&data),
+                    data,
+                };
+                // 서버가 먼저 연결을 끊으면 남은 청크는 보내지 못함
+                if write_message(&mut io, &chunk).await.is_err() {
+                    break;
+                }
+            }
+            // 서버가 연결을 닫을 때까지 ACK를 읽음
+            while read_message(&mut io).await.is_ok() {}
+            reply
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_server_rejects_stream_beyond_declared_size() {
+        let downloads = use_temp_environment();
+        let chunk = config::MIN_CHUNK_SIZE as usize;
+
+        // 청크 수가 크기와 맞지 않는 요청
+        let (server, reply) = stream_declared("declared-count", 10, 3, Vec::new()).await;
+        assert!(matches!(reply, TransferMessage::TransferReject { .. }));
+        assert!(matches!(PebbleError::from(server.unwrap_err()), PebbleError::Protocol { .. }));
+
+        // 10바이트라고 알리고 한 청크를 가득 채워 보냄
+        let (server, reply) = stream_declared("declared-oversize", 10, 1, vec![pattern(chunk, 61)]).await;
+        assert!(matches!(reply, TransferMessage::TransferAccept { .. }));
+        assert!(matches!(PebbleError::from(server.unwrap_err()), PebbleError::Protocol { .. }));
+        assert!(fs::metadata(downloads.join("declared-oversize.bin")).map_or(true, |m| m.len() <= 10));
+
+        // 청크 크기보다 큰 청크
+        let (server, _) = stream_declared("declared-large", 2 * chunk as u64, 2, vec![pattern(chunk + 1, 62)]).await;
+        assert!(matches!(PebbleError::from(server.unwrap_err()), PebbleError::Protocol { .. }));
+
+        // 마지막이 아닌데 짧은 청크
+        let (server, _) = stream_declared("declared-short", 2 * chunk as u64, 2, vec![pattern(100, 63), pattern(chunk, 64)]).await;
+        assert!(matches!(PebbleError::from(server.unwrap_err()), PebbleError::Protocol { .. }));
+
+        // 마지막 청크가 짧으면 파일이 선언한 크기보다 작음
+        let (server, _) = stream_declared("declared-truncated", chunk as u64 + 50, 2, vec![pattern(chunk, 65), pattern(20, 66)]).await;
+        assert!(matches!(PebbleError::from(server.unwrap_err()), PebbleError::Protocol { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_server_rejects_over_quota() {
+        use_temp_environment();
+        quota::set_quota("loopback-quota-peer", Some(50)).unwrap();
+
+        let (server, reply) = run_server_against(|mut io| async move {
+            let request = TransferMessage::TransferRequest {
+                transfer_id: "over-quota".to_string(),
+                file_path: "over_quota.bin".to_string(),
+                file_size: 100,
+                file_hash: String::new(),
+                total_chunks: 1,
+                chunk_size: config::MIN_CHUNK_SIZE,
+                chunk_hash_algos: Vec::new(),
+                sender_device_id: Some("loopback-quota-peer".to_string()),
+                ack_ranges: false,
+                chunk_manifest: false,
+                inline_data: None,
+                xattrs: Vec::new(),
+                verdict: false,
+                crc32c: false,
+                retransmit: false,
+                relative_path: None,
+            };
+            write_message(&mut io, &request).await.unwrap();
+            read_message(&mut io).await.unwrap()
+        })
+        .await;
+
+        assert!(matches!(reply, TransferMessage::TransferReject { code: Some(RejectCode::QuotaExceeded), .. }));
+        assert!(matches!(PebbleError::from(server.unwrap_err()), PebbleError::QuotaExceeded { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_server_refuses_revoked_device_and_unsigned_revocation() {
+        use_temp_environment();
+        crate::api::db::open_connection().unwrap().execute(
+            "INSERT OR IGNORE INTO revoked_devices
+                (revocation_id, device_id, issued_by, issued_at, signature, revoked_at)
+             VALUES ('loopback-revocation', 'loopback-lost', 'loopback-laptop', 0, '', 0)",
+            [],
+        ).unwrap();
+
+        let (server, reply) = run_server_against(|mut io| async move {
+            let text = TransferMessage::SendText {
+                message_id: "from-lost".to_string(),
+                sender_device_id: Some("loopback-lost".to_string()),
+                text: "hello".to_string(),
+            };
+            write_message(&mut io, &text).await.unwrap();
+            read_message(&mut io).await.unwrap()
+        })
+        .await;
+        assert!(matches!(reply, TransferMessage::TransferReject { code: Some(RejectCode::PolicyDenied), .. }));
+        assert!(matches!(PebbleError::from(server.unwrap_err()), PebbleError::Rejected { .. }));
+
+        let (server, reply) = run_server_against(|mut io| async move {
+            let revoke = TransferMessage::RevokeMe {
+                revocation: crate::api::revocation::Revocation {
+                    revocation_id: "forged".to_string(),
+                    device_id: "loopback-desktop".to_string(),
+                    fingerprint: None,
+                    issued_by: "loopback-lost".to_string(),
+                    issued_at: 0,
+                    certificate: String::new(),
+                    signature_scheme: String::new(),
+                    signature: "00".repeat(32),
+                },
+            };
+            write_message(&mut io, &revoke).await.unwrap();
+            read_message(&mut io).await.unwrap()
+        })
+        .await;
+        assert!(matches!(reply, TransferMessage::TransferReject { code: Some(RejectCode::PolicyDenied), .. }));
+        assert!(server.is_err());
+        assert!(!crate::api::revocation::is_revoked("loopback-desktop", None).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_tls_server_sees_client_certificate_fingerprint() {
+        let server_cert = TlsCertificate::generate_self_signed("loopback", "Loopback").unwrap();
+        let client_cert = TlsCertificate::generate_self_signed("loopback-client", "Client").unwrap();
+        let acceptor = TlsAcceptor::from(server_cert.build_server_config().unwrap());
+
+        for identity in [Some(&client_cert), None] {
+            let (client_io, server_io) = stream_pair();
+            let config = TlsCertificate::build_client_config_as(Some(server_cert.fingerprint.clone()), identity).unwrap();
+            let domain = rustls::pki_types::ServerName::try_from("pebble.local").unwrap();
+            let (client, server) = tokio::join!(
+                TlsConnector::from(config).connect(domain, client_io),
+                acceptor.accept(server_io),
+            );
+            client.unwrap();
+            let server = server.unwrap();
+            let seen = TlsCertificate::peer_fingerprint(server.get_ref().1.peer_certificates());
+            assert_eq!(seen, identity.map(|cert| cert.fingerprint.clone()));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_server_acks_clipboard_only_from_enabled_device() {
+        use crate::api::clipboard::{self, ClipboardKind, ClipboardUpdate};
+
+        use_temp_environment();
+        let update = |origin: &str| {
+            ClipboardUpdate::new(origin.to_string(), ClipboardKind::Text, "text/plain".to_string(), b"loopback clip".to_vec())
+        };
+        let exchange = |update: ClipboardUpdate| {
+            run_server_against(|mut io| async move {
+                write_message(&mut io, &TransferMessage::ClipboardUpdate { update }).await.unwrap();
+                read_message(&mut io).await.unwrap()
+            })
+        };
+
+        let (server, reply) = exchange(update("loopback-clip-off")).await;
+        assert!(matches!(reply, TransferMessage::TransferReject { code: Some(RejectCode::PolicyDenied), .. }));
+        assert!(matches!(PebbleError::from(server.unwrap_err()), PebbleError::Rejected { .. }));
+
+        clipboard::set_enabled("loopback-clip-on", true).unwrap();
+        let sent = update("loopback-clip-on");
+        let (server, reply) = exchange(sent.clone()).await;
+        server.unwrap();
+        assert!(matches!(reply, TransferMessage::ClipboardAck { update_id } if update_id == sent.update_id));
+    }
+
+    #[tokio::test]
+    async fn test_client_detects_ack_mismatch() {
+        use_temp_environment();
+        let (_src, path) = write_source("ack_mismatch.bin", &pattern(1000, 3));
+
+        let (client, _) = run_client_against(&TransferClient::new(None), &path, |mut io| async move {
+            let request = read_message(&mut io).await.unwrap();
+            let TransferMessage::TransferRequest { transfer_id, .. } = request else {
+                panic!("expected TransferRequest");
+            };
+            let accept = TransferMessage::TransferAccept {
+                transfer_id: transfer_id.clone(),
+                resume_from_chunk: 0,
+                chunk_hash_algo: HashAlgo::Sha256,
+                ack_interval: 1,
+                want_manifest: false,
+                sparse: false,
+                verdict: false,
+            };
+            write_message(&mut io, &accept).await.unwrap();
+            read_message(&mut io).await.unwrap();
+            let ack = TransferMessage::ChunkAck { transfer_id, chunk_index: 7 };
+            write_message(&mut io, &ack).await.unwrap();
+        })
+        .await;
+
+        assert!(matches!(PebbleError::from(client.unwrap_err()), PebbleError::Protocol { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_client_waits_for_receiver_verdict() {
+        use_temp_environment();
+        let (_src, path) = write_source("verdict.bin", &pattern(1000, 5));
+
+        let (client, _) = run_client_against(&TransferClient::new(None), &path, |mut io| async move {
+            let TransferMessage::TransferRequest { transfer_id, verdict: true, .. } = read_message(&mut io).await.unwrap() else {
+                panic!("expected TransferRequest waiting for a verdict");
+            };
+            let accept = TransferMessage::TransferAccept {
+                transfer_id: transfer_id.clone(),
+                resume_from_chunk: 0,
+                chunk_hash_algo: HashAlgo::Sha256,
+                ack_interval: 1,
+                want_manifest: false,
+                sparse: false,
+                verdict: true,
+            };
+            write_message(&mut io, &accept).await.unwrap();
+            loop {
+                match read_message(&mut io).await.unwrap() {
+                    TransferMessage::ChunkData { chunk_index, .. } => {
+                        let ack = TransferMessage::ChunkAck { transfer_id: transfer_id.clone(), chunk_index };
+                        write_message(&mut io, &ack).await.unwrap();
+                    }
+                    TransferMessage::TransferComplete { .. } => break,
+                    other => panic!("unexpected {:?}", other),
+                }
+            }
+            // 검사 명령이 파일을 거부한 것처럼 응답
+            let reject = TransferMessage::TransferReject {
+                transfer_id,
+                reason: "verdict.bin was rejected by the receiver's file scan".to_string(),
+                code: Some(RejectCode::PolicyDenied),
+            };
+            write_message(&mut io, &reject).await.unwrap();
+        })
+        .await;
+
+        assert!(matches!(PebbleError::from(client.unwrap_err()), PebbleError::Rejected { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_server_resumes_interrupted_transfer() {
+        let downloads = use_temp_environment();
+        let chunk = config::MIN_CHUNK_SIZE as usize;
+        let data = pattern(chunk * 3 + 5, 4);
+        let request = TransferMessage::TransferRequest {
+            transfer_id: "resume-test".to_string(),
+            file_path: "resume_test.bin".to_string(),
+            file_size: data.len() as u64,
+            // 이어받은 앞부분까지 포함한 파일 전체 해시로 검증
+            file_hash: integrity::hash_reader(&data[..]).unwrap(),
+            total_chunks: 4,
+            chunk_size: chunk as u64,
+            chunk_hash_algos: Vec::new(),
+            sender_device_id: None,
+            ack_ranges: false,
+            chunk_manifest: false,
+            inline_data: None,
+            xattrs: Vec::new(),
+            verdict: false,
+            crc32c: false,
+            retransmit: false,
+            relative_path: None,
+        };
+
+        // 첫 연결: 청크 2개만 보내고 끊김
+        let first = data.clone();
+        let first_request = request.clone();
+        let (server, _) = run_server_against(|mut io| async move {
+            write_message(&mut io, &first_request).await.unwrap();
+            read_message(&mut io).await.unwrap();
+            for index in 0..2u64 {
+                let start = index as usize * chunk;
+                write_message(&mut io, &chunk_msg("resume-test", index, &first[start..start + chunk])).await.unwrap();
+                read_message(&mut io).await.unwrap();
+            }
+        })
+        .await;
+        assert!(server.is_err());
+
+        // 두 번째 연결: 서버가 청크 2부터 이어받기
+        let second = data.clone();
+        let (server, resume_from) = run_server_against(|mut io| async move {
+            write_message(&mut io, &request).await.unwrap();
+            let TransferMessage::TransferAccept { resume_from_chunk, .. } = read_message(&mut io).await.unwrap() else {
+                panic!("expected TransferAccept");
+            };
+            for index in resume_from_chunk..4 {
+                let start = index as usize * chunk;
+                let end = (start + chunk).min(second.len());
+                write_message(&mut io, &chunk_msg("resume-test", index, &second[start..end])).await.unwrap();
+                read_message(&mut io).await.unwrap();
+            }
+            let complete = TransferMessage::TransferComplete { transfer_id: "resume-test".to_string(), receipt: None };
+            write_message(&mut io, &complete).await.unwrap();
+            resume_from_chunk
+        })
+        .await;
+
+        server.unwrap();
+        assert_eq!(resume_from, 2);
+        assert_eq!(fs::read(downloads.join("resume_test.bin")).unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_resume_restarts_when_partial_content_is_gone() {
+        let downloads = use_temp_environment();
+        let chunk = config::MIN_CHUNK_SIZE as usize;
+        let data = pattern(chunk * 3 + 5, 6);
+        let request = TransferMessage::TransferRequest {
+            transfer_id: "restart-test".to_string(),
+            file_path: "restart_test.bin".to_string(),
+            file_size: data.len() as u64,
+            file_hash: integrity::hash_reader(&data[..]).unwrap(),
+            total_chunks: 4,
+            chunk_size: chunk as u64,
+            chunk_hash_algos: Vec::new(),
+            sender_device_id: None,
+            ack_ranges: false,
+            chunk_manifest: false,
+            inline_data: None,
+            xattrs: Vec::new(),
+            verdict: false,
+            crc32c: false,
+            retransmit: false,
+            relative_path: None,
+        };
+
+        let first = data.clone();
+        let first_request = request.clone();
+        let (server, _) = run_server_against(|mut io| async move {
+            write_message(&mut io, &first_request).await.unwrap();
+            read_message(&mut io).await.unwrap();
+            for index in 0..2u64 {
+                let start = index as usize * chunk;
+                write_message(&mut io, &chunk_msg("restart-test", index, &first[start..start + chunk])).await.unwrap();
+                read_message(&mut io).await.unwrap();
+            }
+        })
+        .await;
+        assert!(server.is_err());
+
+        // 받던 내용이 사라진 저장 위치(새 SAF 문서 등)에 이어 쓰지 않고 처음부터 받음
+        fs::remove_file(crate::api::locked::staging_path(&downloads.join("restart_test.bin"))).unwrap();
+
+        let second = data.clone();
+        let (server, resume_from) = run_server_against(|mut io| async move {
+            write_message(&mut io, &request).await.unwrap();
+            let TransferMessage::TransferAccept { resume_from_chunk, .. } = read_message(&mut io).await.unwrap() else {
+                panic!("expected TransferAccept");
+            };
+            for index in resume_from_chunk..4 {
+                let start = index as usize * chunk;
+                let end = (start + chunk).min(second.len());
+                write_message(&mut io, &chunk_msg("restart-test", index, &second[start..end])).await.unwrap();
+                read_message(&mut io).await.unwrap();
+            }
+            let complete = TransferMessage::TransferComplete { transfer_id: "restart-test".to_string(), receipt: None };
+            write_message(&mut io, &complete).await.unwrap();
+            resume_from_chunk
+        })
+        .await;
+
+        server.unwrap();
+        assert_eq!(resume_from, 0);
+        assert_eq!(fs::read(downloads.join("restart_test.bin")).unwrap(), data);
+    }
+
+    /// 중계하면서 본 전송 진행
+    #[derive(Debug, Default)]
+    struct Relayed {
+        /// 송신측이 보낸 청크 데이터의 인덱스
+        chunks: Vec<u64>,
+        /// 수신측이 수락하면서 알린 이어받기 위치
+        resume_from: Option<u64>,
+    }
+
+    /// 양쪽 메시지를 기록하며 실제 클라이언트와 서버 사이를 중계합니다.
+    async fn run_transfer_relayed(client: &TransferClient, file_path: &str) -> (LoopbackOutcome, Relayed) {
+        use tokio::io::AsyncWriteExt;
+
+        async fn relay<R, W>(mut from: R, mut to: W, mut observe: impl FnMut(&TransferMessage))
+        where
+            R: tokio::io::AsyncRead + Unpin,
+            W: tokio::io::AsyncWrite + Unpin,
+        {
+            while let Ok(Some(msg)) = TransferMessage::next_from_stream(&mut from).await {
+                observe(&msg);
+                if to.write_all(&msg.to_bytes().unwrap()).await.is_err() {
+                    break;
+                }
+            }
+            let _ = to.shutdown().await;
+        }
+
+        let (client_io, proxy_client) = stream_pair();
+        let (proxy_server, server_io) = stream_pair();
+        let (from_client, to_client) = tokio::io::split(proxy_client);
+        let (from_server, to_server) = tokio::io::split(proxy_server);
+
+        let mut chunks = Vec::new();
+        let mut resume_from = None;
+        let upstream = relay(from_client, to_server, |msg| {
+            if let TransferMessage::ChunkData { chunk_index, .. } = msg {
+                chunks.push(*chunk_index);
+            }
+        });
+        let downstream = relay(from_server, to_client, |msg| {
+            if let TransferMessage::TransferAccept { resume_from_chunk, .. } = msg {
+                resume_from = Some(*resume_from_chunk);
+            }
+        });
+
+        let (client, server, (), ()) = tokio::join!(
+            client.send_file_over(client_io, "loopback", file_path),
+            TransferServer::handle_stream(server_io, loopback_peer(), None),
+            upstream,
+            downstream,
+        );
+        (LoopbackOutcome { client, server }, Relayed { chunks, resume_from })
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_send_resumes_with_missing_chunks_only() {
+        let downloads = use_temp_environment();
+        let total_chunks = 24;
+        // 청크마다 내용이 달라야 중복 제거 저장소에서 채워지지 않음
+        let data: Vec<u8> = (0..total_chunks).flat_map(|index| pattern(config::MIN_CHUNK_SIZE as usize, 41 + index as u8)).collect();
+        let (_src, path) = write_source("resume_real.bin", &data);
+
+        // 첫 시도: 묶음 ACK(8청크)를 받은 뒤 20청크째에서 끊김
+        let mut client = TransferClient::new(None);
+        client.set_fault_plan(FaultPlan { drop_after_chunks: Some(20), ..Default::default() });
+        let outcome = run_transfer(&client, FaultPlan::default(), &path, Transport::Plain).await;
+        assert!(outcome.client.is_err() && outcome.server.is_err());
+
+        // 다시 보내면 같은 전송 ID로 확인된 청크 뒤부터 이어받음
+        let (outcome, relayed) = run_transfer_relayed(&TransferClient::new(None), &path).await;
+        let sent = relayed.chunks;
+        outcome.client.unwrap();
+        outcome.server.unwrap();
+        assert!(relayed.resume_from.is_some_and(|from| from >= ACK_INTERVAL));
+        // 수신측은 묶음 ACK를 보낼 때마다 이어받기 상태를 기록하므로 적어도 첫 묶음은 다시 보내지 않음
+        assert!(sent.iter().all(|&index| index >= ACK_INTERVAL), "resent chunks {:?}", sent);
+        assert!(!sent.is_empty() && sent.len() < total_chunks, "resent chunks {:?}", sent);
+        assert_eq!(fs::read(downloads.join("resume_real.bin")).unwrap(), data);
+        assert!(!downloads.join("resume_real (2).bin").exists());
+    }
+
+    #[tokio::test]
+    async fn test_retry_after_maintenance_resumes_and_replaces_history() {
+        use crate::api::{history, integrity, transfer};
+
+        let downloads = use_temp_environment();
+        let total_chunks = 20;
+        let data: Vec<u8> = (0..total_chunks).flat_map(|index| pattern(config::MIN_CHUNK_SIZE as usize, 97 + index as u8)).collect();
+        let (_src, path) = write_source("resume_history.bin", &data);
+        let transfer_id = transfer::stable_transfer_id(
+            &integrity::calculate_file_hash(&path).unwrap(),
+            data.len() as u64,
+            config::MIN_CHUNK_SIZE,
+            &crate::api::service::device_id().unwrap_or_default(),
+            "loopback",
+            None,
+        );
+
+        let mut client = TransferClient::new(None);
+        client.set_fault_plan(FaultPlan { drop_after_chunks: Some(18), ..Default::default() });
+        let outcome = run_transfer(&client, FaultPlan::default(), &path, Transport::Plain).await;
+        assert!(outcome.client.is_err());
+        let failed = history::list(u32::MAX, 0).unwrap().into_iter().find(|r| r.transfer_id == transfer_id).unwrap();
+        assert_eq!(failed.status, TransferStatus::Failed);
+
+        // 유지보수의 보존 정책은 끊긴 수신의 이어받기 상태를 남김
+        history::prune(&history::RetentionPolicy::default()).unwrap();
+
+        let (outcome, relayed) = run_transfer_relayed(&TransferClient::new(None), &path).await;
+        let sent = relayed.chunks;
+        outcome.client.unwrap();
+        outcome.server.unwrap();
+        assert!(relayed.resume_from.is_some_and(|from| from >= ACK_INTERVAL));
+        assert!(sent.iter().all(|&index| index >= ACK_INTERVAL), "resent chunks {:?}", sent);
+        assert_eq!(fs::read(downloads.join("resume_history.bin")).unwrap(), data);
+
+        // 다시 보낸 전송은 같은 기록을 완료로 바꿈
+        let records: Vec<_> = history::list(u32::MAX, 0).unwrap().into_iter().filter(|r| r.transfer_id == transfer_id).collect();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].status, TransferStatus::Completed);
+        assert_eq!(records[0].bytes_transferred, data.len() as u64);
+    }
+
+    #[tokio::test]
+    async fn test_stale_partial_cleanup_restarts_retry_and_keeps_completed_file() {
+        use crate::api::{integrity, locked, partials, transfer};
+        use std::path::Path;
+        use rusqlite::params;
+        use std::time::SystemTime;
+
+        let downloads = use_temp_environment();
+        let total_chunks = 20;
+        let data: Vec<u8> = (0..total_chunks).flat_map(|index| pattern(config::MIN_CHUNK_SIZE as usize, 151 + index as u8)).collect();
+        let (_src, path) = write_source("partial_cleanup.bin", &data);
+        let received = downloads.join("partial_cleanup.bin");
+        let transfer_id = transfer::stable_transfer_id(
+            &integrity::calculate_file_hash(&path).unwrap(),
+            data.len() as u64,
+            config::MIN_CHUNK_SIZE,
+            &crate::api::service::device_id().unwrap_or_default(),
+            "loopback",
+            None,
+        );
+
+        // 다른 테스트의 미완성 수신보다 오래된 것으로 만들어 이 수신만 정리 대상으로 함
+        let age = Duration::from_secs(30 * 24 * 60 * 60);
+        let make_stale = |file: &Path| {
+            let old = SystemTime::now() - age;
+            let old_secs = old.duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
+            db::open_connection()
+                .unwrap()
+                .execute("UPDATE transfer_state SET updated_at = ?2 WHERE transfer_id = ?1", params![transfer_id, old_secs])
+                .unwrap();
+            fs::File::options().write(true).open(file).unwrap().set_modified(old).unwrap();
+        };
+        let partial = locked::staging_path(&received);
+
+        let mut client = TransferClient::new(None);
+        client.set_fault_plan(FaultPlan { drop_after_chunks: Some(18), ..Default::default() });
+        assert!(run_transfer(&client, FaultPlan::default(), &path, Transport::Plain).await.client.is_err());
+        assert!(partial.exists() && !received.exists());
+
+        // 오래 이어받지 않은 미완성 파일과 상태를 지우면 다시 보낼 때 처음부터 받음
+        make_stale(&partial);
+        partials::clean(age - Duration::from_secs(24 * 60 * 60)).unwrap();
+        assert!(!partial.exists());
+
+        let (outcome, relayed) = run_transfer_relayed(&TransferClient::new(None), &path).await;
+        outcome.client.unwrap();
+        outcome.server.unwrap();
+        assert_eq!(relayed.resume_from, Some(0));
+        assert_eq!(fs::read(&received).unwrap(), data);
+
+        // 같은 전송 ID로 끝난 수신의 파일은 정리하지 않음
+        make_stale(&received);
+        partials::clean(age - Duration::from_secs(24 * 60 * 60)).unwrap();
+        assert_eq!(fs::read(&received).unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_server_rejects_corrupted_chunk() {
+        use_temp_environment();
+
+        let (server, _) = run_server_against(|mut io| async move {
+            let request = TransferMessage::TransferRequest {
+                transfer_id: "corrupt-test".to_string(),
+                file_path: "corrupt_test.bin".to_string(),
+                file_size: 4,
+                file_hash: String::new(),
+                total_chunks: 1,
+                chunk_size: config::MIN_CHUNK_SIZE,
+                chunk_hash_algos: Vec::new(),
+                sender_device_id: None,
+                ack_ranges: false,
+                chunk_manifest: false,
+                inline_data: None,
+                xattrs: Vec::new(),
+                verdict: false,
+                crc32c: false,
+                retransmit: false,
+                relative_path: None,
+            };
+            write_message(&mut io, &request).await.unwrap();
+            read_message(&mut io).await.unwrap();
+
+            let mut corrupted = chunk_msg("corrupt-test", 0, b"data");
+            if let TransferMessage::ChunkData { ref mut data, .. } = corrupted {
+                data[0] ^= 0xff;
+            }
+            write_message(&mut io, &corrupted).await.unwrap();
+        })
+        .await;
+
+        assert!(server.unwrap_err().to_string().contains("hash mismatch"));
+    }
+
+    #[tokio::test]
+    async fn test_server_requests_corrupted_chunk_again() {
+        let downloads = use_temp_environment();
+        let chunk = config::MIN_CHUNK_SIZE as usize;
+        let data = [pattern(chunk, 50), pattern(100, 51)].concat();
+
+        let sent = data.clone();
+        let (server, nack) = run_server_against(|mut io| async move {
+            let request = TransferMessage::TransferRequest {
+                transfer_id: "nack-test".to_string(),
+                file_path: "nack_test.bin".to_string(),
+                file_size: sent.len() as u64,
+                file_hash: integrity::hash_reader(&sent[..]).unwrap(),
+                total_chunks: 2,
+                chunk_size: config::MIN_CHUNK_SIZE,
+                chunk_hash_algos: Vec::new(),
+                sender_device_id: None,
+                ack_ranges: false,
+                chunk_manifest: false,
+                inline_data: None,
+                xattrs: Vec::new(),
+                verdict: false,
+                crc32c: false,
+                retransmit: true,
+                relative_path: None,
+            };
+            write_message(&mut io, &request).await.unwrap();
+            read_message(&mut io).await.unwrap();
+
+            // 변조된 첫 청크 뒤에 이미 보낸 청크는 수신측이 버림
+            let mut corrupted = chunk_msg("nack-test", 0, &sent[..chunk]);
+            if let TransferMessage::ChunkData { ref mut data, .. } = corrupted {
+                data[0] ^= 0xff;
+            }
+            write_message(&mut io, &corrupted).await.unwrap();
+            write_message(&mut io, &chunk_msg("nack-test", 1, &sent[chunk..])).await.unwrap();
+            let nack = read_message(&mut io).await.unwrap();
+
+            write_message(&mut io, &chunk_msg("nack-test", 0, &sent[..chunk])).await.unwrap();
+            read_message(&mut io).await.unwrap();
+            write_message(&mut io, &chunk_msg("nack-test", 1, &sent[chunk..])).await.unwrap();
+            read_message(&mut io).await.unwrap();
+            let complete = TransferMessage::TransferComplete { transfer_id: "nack-test".to_string(), receipt: None };
+            write_message(&mut io, &complete).await.unwrap();
+            nack
+        })
+        .await;
+
+        server.unwrap();
+        assert!(matches!(nack, TransferMessage::ChunkNack { chunk_index: 0, .. }));
+        assert_eq!(fs::read(downloads.join("nack_test.bin")).unwrap(), data);
+    }
+
+    #[tokio::test]
+    async fn test_client_resends_requested_chunk() {
+        use_temp_environment();
+        let chunk = config::current().chunk_size as usize;
+        let (_src, path) = write_source("resend.bin", &[pattern(chunk, 52), pattern(10, 53)].concat());
+
+        let (client, (retransmit, sent)) = run_client_against(&TransferClient::new(None), &path, |mut io| async move {
+            let TransferMessage::TransferRequest { transfer_id, retransmit, .. } = read_message(&mut io).await.unwrap() else {
+                panic!("expected TransferRequest");
+            };
+            let accept = TransferMessage::TransferAccept {
+                transfer_id: transfer_id.clone(),
+                resume_from_chunk: 0,
+                chunk_hash_algo: HashAlgo::Sha256,
+                ack_interval: 1,
+                want_manifest: false,
+                sparse: false,
+                verdict: false,
+            };
+            write_message(&mut io, &accept).await.unwrap();
+
+            let mut sent = Vec::new();
+            let mut next_chunk = |msg: TransferMessage| match msg {
+                TransferMessage::ChunkData { chunk_index, .. } => sent.push(chunk_index),
+                other => panic!("expected ChunkData, got {:?}", other),
+            };
+            next_chunk(read_message(&mut io).await.unwrap());
+            let nack = TransferMessage::ChunkNack { transfer_id: transfer_id.clone(), chunk_index: 0 };
+            write_message(&mut io, &nack).await.unwrap();
+            for chunk_index in 0..2 {
+                next_chunk(read_message(&mut io).await.unwrap());
+                let ack = TransferMessage::ChunkAck { transfer_id: transfer_id.clone(), chunk_index };
+                write_message(&mut io, &ack).await.unwrap();
+            }
+            read_message(&mut io).await.unwrap();
+            (retransmit, sent)
+        })
+        .await;
+
+        client.unwrap();
+        assert!(retransmit);
+        assert_eq!(sent, vec![0, 0, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_server_verifies_whole_file_hash() {
+        use_temp_environment();
+
+        // 청크 해시는 맞지만 파일 해시가 다른 내용
+        let (server, _) = run_server_against(|mut io| async move {
+            let request = TransferMessage::TransferRequest {
+                transfer_id: "file-hash-test".to_string(),
+                file_path: "file_hash_test.bin".to_string(),
+                file_size: 4,
+                file_hash: integrity::hash_reader(&b"data"[..]).unwrap(),
+                total_chunks: 1,
+                chunk_size: config::MIN_CHUNK_SIZE,
+                chunk_hash_algos: Vec::new(),
+                sender_device_id: None,
+                ack_ranges: false,
+                chunk_manifest: false,
+                inline_data: None,
+                xattrs: Vec::new(),
+                verdict: false,
+                crc32c: false,
+                retransmit: false,
+                relative_path: None,
+            };
+            write_message(&mut io, &request).await.unwrap();
+            read_message(&mut io).await.unwrap();
+            write_message(&mut io, &chunk_msg("file-hash-test", 0, b"dat!")).await.unwrap();
+            read_message(&mut io).await.unwrap();
+            let complete = TransferMessage::TransferComplete { transfer_id: "file-hash-test".to_string(), receipt: None };
+            write_message(&mut io, &complete).await.unwrap();
+        })
+        .await;
+
+        assert!(server.unwrap_err().to_string().contains("File hash mismatch"));
+    }
+
+    #[tokio::test]
+    async fn test_server_fills_stored_chunks_from_dedup_store() {
+        let downloads = use_temp_environment();
+        let chunk = config::MIN_CHUNK_SIZE as usize;
+        let data = [pattern(chunk, 20), pattern(chunk, 21), pattern(100, 22)].concat();
+        let hashes: Vec<String> = data.chunks(chunk).map(|c| dedup::STORE_HASH.digest(c)).collect();
+
+        // 첫 청크는 이전 수신에서 저장된 상태
+        let store = dedup::ChunkStore::configured().unwrap();
+        store.insert(&hashes[0], &data[..chunk]).unwrap();
+
+        let sent = data.clone();
+        let (server, (accept, needs)) = run_server_against(|mut io| async move {
+            let request = TransferMessage::TransferRequest {
+                transfer_id: "dedup-test".to_string(),
+                file_path: "dedup_store.bin".to_string(),
+                file_size: sent.len() as u64,
+                file_hash: String::new(),
+                total_chunks: 3,
+                chunk_size: chunk as u64,
+                chunk_hash_algos: Vec::new(),
+                sender_device_id: None,
+                ack_ranges: true,
+                chunk_manifest: true,
+                inline_data: None,
+                xattrs: Vec::new(),
+                verdict: false,
+                crc32c: false,
+                retransmit: false,
+                relative_path: None,
+            };
+            write_message(&mut io, &request).await.unwrap();
+            let accept = read_message(&mut io).await.unwrap();
+
+            let manifest = TransferMessage::ChunkManifest {
+                transfer_id: "dedup-test".to_string(),
+                first_chunk: 0,
+                chunk_hashes: hashes,
+            };
+            write_message(&mut io, &manifest).await.unwrap();
+            let needs = read_message(&mut io).await.unwrap();
+
+            // 저장소에 없는 청크만 전송
+            write_message(&mut io, &chunk_msg("dedup-test", 1, &sent[chunk..chunk * 2])).await.unwrap();
+            write_message(&mut io, &chunk_msg("dedup-test", 2, &sent[chunk * 2..])).await.unwrap();
+            read_message(&mut io).await.unwrap();
+            let complete = TransferMessage::TransferComplete { transfer_id: "dedup-test".to_string(), receipt: None };
+            write_message(&mut io, &complete).await.unwrap();
+            (accept, needs)
+        })
+        .await;
+
+        server.unwrap();
+        assert!(matches!(accept, TransferMessage::TransferAccept { want_manifest: true, .. }));
+        let TransferMessage::ChunkNeeds { ranges, .. } = needs else {
+            panic!("expected ChunkNeeds");
+        };
+        assert_eq!(ranges, vec![AckRange { start: 1, end: 3 }]);
+        assert_eq!(fs::read(downloads.join("dedup_store.bin")).unwrap(), data);
+
+        // 네트워크로 받은 청크도 저장소에 추가됨
+        assert!(store.contains(&dedup::STORE_HASH.digest(&data[chunk..chunk * 2]), chunk as u64));
+    }
+
+    #[tokio::test]
+    async fn test_client_skips_chunks_receiver_already_has() {
+        let downloads = use_temp_environment();
+        let chunk = config::MIN_CHUNK_SIZE as usize;
+        let original = [pattern(chunk, 30), pattern(chunk, 31), pattern(chunk, 32), pattern(500, 33)].concat();
+        let mut edited = original.clone();
+        edited[chunk + 10] ^= 0xff;
+        let (_src1, path1) = write_source("dedup_original.bin", &original);
+        let (_src2, path2) = write_source("dedup_edited.bin", &edited);
+
+        let client = TransferClient::new(None);
+        run_transfer(&client, FaultPlan::default(), &path1, Transport::Plain).await.client.unwrap();
+        let outcome = run_transfer(&client, FaultPlan::default(), &path2, Transport::Plain).await;
+        outcome.client.unwrap();
+        outcome.server.unwrap();
+
+        assert_eq!(fs::read(downloads.join("dedup_edited.bin")).unwrap(), edited);
+    }
+
+    #[tokio::test]
+    async fn test_zero_chunks_sent_as_holes() {
+        use_temp_environment();
+        let chunk = config::current().chunk_size as usize;
+        let data = [pattern(chunk, 40), vec![0; chunk], pattern(10, 41)].concat();
+        let (_src, path) = write_source("holes_sent.bin", &data);
+
+        let (client, holes) = run_client_against(&TransferClient::new(None), &path, |mut io| async move {
+            let TransferMessage::TransferRequest { transfer_id, total_chunks, .. } = read_message(&mut io).await.unwrap() else {
+                panic!("expected TransferRequest");
+            };
+            let accept = TransferMessage::TransferAccept {
+                transfer_id: transfer_id.clone(),
+                resume_from_chunk: 0,
+                chunk_hash_algo: HashAlgo::Sha256,
+                ack_interval: 1,
+                want_manifest: false,
+                sparse: true,
+                verdict: false,
+            };
+            write_message(&mut io, &accept).await.unwrap();
+
+            let mut holes = Vec::new();
+            for chunk_index in 0..total_chunks {
+                holes.push(matches!(read_message(&mut io).await.unwrap(), TransferMessage::ChunkHole { .. }));
+                let ack = TransferMessage::ChunkAck { transfer_id: transfer_id.clone(), chunk_index };
+                write_message(&mut io, &ack).await.unwrap();
+            }
+            read_message(&mut io).await.unwrap();
+            holes
+        })
+        .await;
+
+        client.unwrap();
+        assert_eq!(holes, vec![false, true, false]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_extended_attributes_roundtrip() {
+        let downloads = use_temp_environment();
+        let (_src, path) = write_source("xattr_tagged.txt", b"tagged");
+        // 확장 속성을 지원하지 않는 파일 시스템에서는 확인할 수 없음
+        if xattr::set(&path, "user.pebble.color", b"blue").is_err() {
+            return;
+        }
+
+        let outcome = run_transfer(&TransferClient::new(None), FaultPlan::default(), &path, Transport::Plain).await;
+        outcome.client.unwrap();
+        outcome.server.unwrap();
+
+        let received = downloads.join("xattr_tagged.txt");
+        assert_eq!(xattr::get(&received, "user.pebble.color").unwrap(), Some(b"blue".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_sparse_file_roundtrip() {
+        let downloads = use_temp_environment();
+        let chunk = config::current().chunk_size as usize;
+        // 가운데와 끝부분이 구멍인 디스크 이미지
+        let data = [pattern(chunk, 42), vec![0; chunk * 2], pattern(chunk, 43), vec![0; chunk + 7]].concat();
+        let (_src, path) = write_source("sparse_image.bin", &data);
+        let (sent_before, received_before) = metrics::transfer_bytes();
+
+        let outcome = run_transfer(&TransferClient::new(None), FaultPlan::default(), &path, Transport::Plain).await;
+        outcome.client.unwrap();
+        outcome.server.unwrap();
+
+        assert_eq!(fs::read(downloads.join("sparse_image.bin")).unwrap(), data);
+        // 구멍으로 보낸 청크도 전송 바이트에 포함 (다른 테스트도 함께 세므로 하한만 확인)
+        let (sent_after, received_after) = metrics::transfer_bytes();
+        assert!(sent_after - sent_before >= data.len() as u64);
+        assert!(received_after - received_before >= data.len() as u64);
+    }
+
+    #[tokio::test]
+    async fn test_tiny_files_sent_inline() {
+        let downloads = use_temp_environment();
+        for (name, data) in [("inline_empty.txt", Vec::new()), ("inline_small.txt", pattern(300, 44))] {
+            let (_src, path) = write_source(name, &data);
+            let outcome = run_transfer(&TransferClient::new(None), FaultPlan::default(), &path, Transport::Plain).await;
+            outcome.client.unwrap();
+            outcome.server.unwrap();
+            assert_eq!(fs::read(downloads.join(name)).unwrap(), data);
+        }
+
+        // 요청 하나에 내용이 담기고, 완료 응답만으로 끝남
+        let (_src, path) = write_source("inline_script.txt", b"tiny");
+        let (client, inline_data) = run_client_against(&TransferClient::new(None), &path, |mut io| async move {
+            let TransferMessage::TransferRequest { transfer_id, inline_data, .. } = read_message(&mut io).await.unwrap() else {
+                panic!("expected TransferRequest");
+            };
+            write_message(&mut io, &TransferMessage::TransferComplete { transfer_id, receipt: None }).await.unwrap();
+            inline_data
+        })
+        .await;
+        client.unwrap();
+        assert_eq!(inline_data.as_deref(), Some(&b"tiny"[..]));
+    }
+
+    #[tokio::test]
+    async fn test_send_directory_preserves_layout_over_one_connection() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let downloads = use_temp_environment();
+        let src = tempfile::TempDir::new().unwrap();
+        let root = src.path().join("folder-send-album");
+        let files = [
+            ("cover.jpg", pattern(200, 51)),
+            ("2024/trip/clip.bin", pattern(3 * config::MIN_CHUNK_SIZE as usize + 17, 52)),
+            ("2024/notes.txt", pattern(40, 53)),
+            ("empty.txt", Vec::new()),
+        ];
+        for (relative, data) in &files {
+            let path = root.join(relative);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, data).unwrap();
+        }
+        fs::create_dir_all(root.join("nothing-here")).unwrap();
+
+        let cert = TlsCertificate::generate_self_signed("loopback", "Loopback").unwrap();
+        let acceptor = TlsAcceptor::from(cert.build_server_config().unwrap());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let accepted = connections.clone();
+        let server = tokio::spawn(async move {
+            loop {
+                let (tcp, peer) = listener.accept().await.unwrap();
+                accepted.fetch_add(1, Ordering::SeqCst);
+                let tls = acceptor.accept(tcp).await.unwrap();
+                tokio::spawn(TransferServer::handle_stream(tls, peer, None));
+            }
+        });
+
+        let client = TransferClient::new(Some(cert.fingerprint.clone()));
+        let report = client.send_directory(&[addr], &root.to_string_lossy()).await.unwrap();
+        server.abort();
+
+        assert!(report.failed.is_empty(), "{:?}", report.failed);
+        assert_eq!(
+            report.sent,
+            [
+                "folder-send-album/2024/notes.txt",
+                "folder-send-album/2024/trip/clip.bin",
+                "folder-send-album/cover.jpg",
+                "folder-send-album/empty.txt",
+            ]
+        );
+        assert_eq!(report.sent_bytes, files.iter().map(|(_, data)| data.len() as u64).sum::<u64>());
+        for (relative, data) in &files {
+            assert_eq!(&fs::read(downloads.join("folder-send-album").join(relative)).unwrap(), data, "{}", relative);
+        }
+        assert!(!downloads.join("folder-send-album/nothing-here").exists());
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+}